@@ -0,0 +1,28 @@
+//! Print the build information embedded in firmware ELFs
+//!
+//! Usage: `image-info <elf>...`
+
+use std::env;
+use std::process::ExitCode;
+
+use firmware_elf::ImageHeader;
+
+fn main() -> ExitCode {
+    let paths: Vec<String> = env::args().skip(1).collect();
+    if paths.is_empty() {
+        eprintln!("usage: image-info <elf>...");
+        return ExitCode::from(2);
+    }
+
+    let mut status = ExitCode::SUCCESS;
+    for path in &paths {
+        match ImageHeader::from_elf(path) {
+            Ok(header) => println!("{}: {}", path, header.build_line()),
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                status = ExitCode::FAILURE;
+            }
+        }
+    }
+    status
+}
@@ -0,0 +1,259 @@
+//! Compare firmware sizes with the committed baseline
+//!
+//! Usage: `size-report [CRATE]... [--update] [--threshold 2]`
+//!
+//! Measures `target/thumbv7em-none-eabihf/release/stm32f3-<x>` for every
+//! firmware crate (or the given ones) and compares flash (`text + data`)
+//! and RAM (`data + bss`) with `size-baseline.txt`. A crate whose flash or
+//! RAM grew by more than `--threshold` percent and `--min-bytes` fails the
+//! check, and the symbols that grew are listed. `--update` rewrites the
+//! baseline entries of the measured crates instead.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use firmware_elf::size::{self, SizeInfo};
+
+/// Firmware target triple
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+#[derive(Parser)]
+#[command(about = "Compare firmware section and symbol sizes with the committed baseline")]
+struct Args {
+    /// Peripheral crates (e.g. `dma i2c`); all when omitted
+    crates: Vec<String>,
+
+    /// Firmware workspace root
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))]
+    root: PathBuf,
+
+    /// Baseline file (default: `<root>/size-baseline.txt`)
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+
+    /// Rewrite the baseline for the measured crates
+    #[arg(long)]
+    update: bool,
+
+    /// Allowed growth of flash or RAM, in percent
+    #[arg(long, default_value_t = 2.0)]
+    threshold: f64,
+
+    /// Growth below this many bytes is always allowed
+    #[arg(long, default_value_t = 64)]
+    min_bytes: u64,
+
+    /// Number of symbols listed per crate
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let baseline_path = args
+        .baseline
+        .clone()
+        .unwrap_or_else(|| args.root.join("size-baseline.txt"));
+
+    let measured = match measure(&args.root, &args.crates) {
+        Ok(measured) => measured,
+        Err(err) => {
+            eprintln!("size-report: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    let baseline = match fs::read_to_string(&baseline_path) {
+        Ok(text) => match size::parse_baseline(&text) {
+            Ok(baseline) => baseline,
+            Err(err) => {
+                eprintln!("{}: {}", baseline_path.display(), err);
+                return ExitCode::from(2);
+            }
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound && args.update => Vec::new(),
+        Err(err) => {
+            eprintln!("{}: {}", baseline_path.display(), err);
+            return ExitCode::from(2);
+        }
+    };
+
+    if args.update {
+        return update(&baseline_path, baseline, measured);
+    }
+
+    println!(
+        "{:<16} {:>7} {:>6} {:>6} {:>7} {:>6} {:>8} {:>8}",
+        "crate", "text", "data", "bss", "flash", "ram", "Δflash", "Δram"
+    );
+    let mut regressions = Vec::new();
+    for info in &measured {
+        let s = info.sections;
+        let old = baseline.iter().find(|b| b.name == info.name);
+        let (flash, ram) = match old {
+            Some(old) => (
+                delta(old.sections.flash(), s.flash()),
+                delta(old.sections.ram(), s.ram()),
+            ),
+            None => ("new".to_string(), "new".to_string()),
+        };
+        println!(
+            "{:<16} {:>7} {:>6} {:>6} {:>7} {:>6} {:>8} {:>8}",
+            info.name,
+            s.text,
+            s.data,
+            s.bss,
+            s.flash(),
+            s.ram(),
+            flash,
+            ram
+        );
+
+        if let Some(old) = old {
+            let grown: Vec<&str> = [
+                ("flash", old.sections.flash(), s.flash()),
+                ("RAM", old.sections.ram(), s.ram()),
+            ]
+            .into_iter()
+            .filter(|&(_, before, after)| exceeds(&args, before, after))
+            .map(|(what, _, _)| what)
+            .collect();
+            if !grown.is_empty() {
+                regressions.push((old, info, grown.join(" and ")));
+            }
+        }
+    }
+
+    for (old, info, what) in &regressions {
+        println!();
+        println!(
+            "{}: {} grew by more than {}% (baseline flash {} ram {})",
+            info.name,
+            what,
+            args.threshold,
+            old.sections.flash(),
+            old.sections.ram()
+        );
+        let changes = size::grown_symbols(old, info);
+        if changes.is_empty() {
+            println!("  no listed symbol grew; padding or sections outside the symbol table");
+        }
+        for change in changes.iter().take(args.top) {
+            match change.before {
+                Some(before) => println!(
+                    "  {:>+7} {} ({} -> {})",
+                    change.growth(),
+                    change.path,
+                    before,
+                    change.after
+                ),
+                None => println!(
+                    "  {:>+7} {} (new in the top {})",
+                    change.growth(),
+                    change.path,
+                    size::BASELINE_SYMBOLS
+                ),
+            }
+        }
+    }
+
+    if regressions.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        println!();
+        println!(
+            "{} crate(s) over the size threshold; run with --update to accept",
+            regressions.len()
+        );
+        ExitCode::FAILURE
+    }
+}
+
+/// Measure the release ELF of each selected firmware crate
+fn measure(root: &Path, wanted: &[String]) -> Result<Vec<SizeInfo>, String> {
+    let release = root.join("target").join(TARGET).join("release");
+    firmware_crates(root, wanted)?
+        .into_iter()
+        .map(|name| {
+            let elf = release.join(&name);
+            if !elf.is_file() {
+                return Err(format!(
+                    "{} not built; run `cargo build --release` first",
+                    elf.display()
+                ));
+            }
+            SizeInfo::from_elf(&name, &elf).map_err(|err| format!("{}: {}", elf.display(), err))
+        })
+        .collect()
+}
+
+/// `stm32f3-*` binary crates, restricted to `wanted` if non-empty
+fn firmware_crates(root: &Path, wanted: &[String]) -> Result<Vec<String>, String> {
+    let entries = fs::read_dir(root).map_err(|err| format!("{}: {}", root.display(), err))?;
+    let mut all: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.join("src/main.rs").is_file())
+        .filter_map(|path| path.file_name()?.to_str().map(str::to_string))
+        .filter(|name| name.starts_with("stm32f3-"))
+        .collect();
+    all.sort();
+
+    if wanted.is_empty() {
+        return Ok(all);
+    }
+    wanted
+        .iter()
+        .map(|name| {
+            let name = if name.starts_with("stm32f3-") {
+                name.clone()
+            } else {
+                format!("stm32f3-{}", name)
+            };
+            if all.contains(&name) {
+                Ok(name)
+            } else {
+                Err(format!("no firmware crate {}", name))
+            }
+        })
+        .collect()
+}
+
+/// Replace the measured crates' entries and write the baseline
+fn update(path: &Path, mut baseline: Vec<SizeInfo>, measured: Vec<SizeInfo>) -> ExitCode {
+    for info in measured {
+        println!(
+            "{}: text {} data {} bss {}",
+            info.name, info.sections.text, info.sections.data, info.sections.bss
+        );
+        baseline.retain(|b| b.name != info.name);
+        baseline.push(info);
+    }
+    baseline.sort_by(|a, b| a.name.cmp(&b.name));
+
+    match fs::write(path, size::write_baseline(&baseline)) {
+        Ok(()) => {
+            println!("Updated {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}: {}", path.display(), err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Whether growing from `before` to `after` exceeds both limits
+fn exceeds(args: &Args, before: u64, after: u64) -> bool {
+    let growth = after.saturating_sub(before);
+    growth > args.min_bytes && growth as f64 > before as f64 * args.threshold / 100.0
+}
+
+/// `+12`, `-4` or `0`
+fn delta(before: u64, after: u64) -> String {
+    match after as i64 - before as i64 {
+        0 => "0".into(),
+        change => format!("{:+}", change),
+    }
+}
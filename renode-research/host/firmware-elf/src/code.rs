@@ -0,0 +1,106 @@
+//! Executable sections and Thumb-2 instruction boundaries
+//!
+//! The decoders in the host tools only need to walk code instruction by
+//! instruction: a halfword whose top five bits are `0b11101`, `0b11110` or
+//! `0b11111` starts a 32-bit instruction, everything else is 16 bits.
+
+use object::{Object, ObjectSection, SectionKind};
+
+use crate::Error;
+
+/// Whether `hw1` starts a 32-bit Thumb-2 instruction
+pub fn is_wide(hw1: u16) -> bool {
+    matches!(hw1 >> 11, 0b11101..=0b11111)
+}
+
+/// Little-endian halfword at `pos`
+pub fn halfword(code: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes([*code.get(pos)?, *code.get(pos + 1)?]))
+}
+
+/// Contents of the executable sections of one ELF
+#[derive(Clone, Debug, Default)]
+pub struct Code {
+    /// `(address, bytes)` of each section
+    sections: Vec<(u64, Vec<u8>)>,
+}
+
+impl Code {
+    pub fn from_elf_data(data: &[u8]) -> Result<Code, Error> {
+        Self::from_file(&object::File::parse(data)?)
+    }
+
+    /// Load the code of an already parsed ELF
+    pub fn from_file(file: &object::File) -> Result<Code, Error> {
+        let sections = file
+            .sections()
+            .filter(|s| s.kind() == SectionKind::Text)
+            .map(|s| Ok((s.address(), s.data()?.to_vec())))
+            .collect::<Result<Vec<_>, object::Error>>()?;
+        Ok(Code { sections })
+    }
+
+    /// Whether `address` is inside an executable section
+    pub fn contains(&self, address: u64) -> bool {
+        self.bytes_from(address).is_some()
+    }
+
+    /// Bytes from `address` to the end of its section
+    pub fn bytes_from(&self, address: u64) -> Option<&[u8]> {
+        self.sections.iter().find_map(|(base, bytes)| {
+            let offset = usize::try_from(address.checked_sub(*base)?).ok()?;
+            bytes.get(offset..).filter(|rest| !rest.is_empty())
+        })
+    }
+
+    /// Halfword at `address`
+    pub fn halfword(&self, address: u64) -> Option<u16> {
+        halfword(self.bytes_from(address)?, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{thumb, ElfBuilder};
+
+    /// `.text` at address 0 and a `.rodata` that is not code
+    fn code() -> Code {
+        let mut elf = ElfBuilder::new();
+        elf.section(
+            ".text",
+            SectionKind::Text,
+            &thumb(&[0xB580, 0xF000, 0xF804]),
+        );
+        elf.section(".rodata", SectionKind::ReadOnlyData, &[0xFF; 8]);
+        Code::from_elf_data(&elf.build()).unwrap()
+    }
+
+    #[test]
+    fn wide_prefixes() {
+        assert!(is_wide(0xF000));
+        assert!(is_wide(0xE92D));
+        assert!(is_wide(0xE800));
+        assert!(!is_wide(0xE7FE));
+        assert!(!is_wide(0xB580));
+    }
+
+    #[test]
+    fn halfwords() {
+        assert_eq!(halfword(&[0x80, 0xB5, 0x00], 0), Some(0xB580));
+        assert_eq!(halfword(&[0x80, 0xB5, 0x00], 2), None);
+    }
+
+    #[test]
+    fn executable_sections_only() {
+        let code = code();
+        assert_eq!(code.halfword(0x00), Some(0xB580));
+        assert_eq!(code.halfword(0x04), Some(0xF804));
+        assert_eq!(code.halfword(0x05), None);
+        assert_eq!(code.bytes_from(0x02), Some(&[0x00, 0xF0, 0x04, 0xF8][..]));
+        assert!(code.contains(0x05));
+        assert!(!code.contains(0x06));
+        assert!(Code::default().bytes_from(0).is_none());
+        assert!(Code::from_elf_data(b"not an elf").is_err());
+    }
+}
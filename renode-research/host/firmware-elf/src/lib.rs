@@ -0,0 +1,241 @@
+//! Read firmware metadata from STM32F3 release ELFs
+//!
+//! Host-side counterpart of `stm32f3_common::image`: locates the
+//! `.image_header` section in a firmware ELF and decodes the build
+//! information the firmware prints at boot. [`symbols`] resolves statics
+//! and functions for debugger-based checks, [`code`] reads the executable
+//! sections for the Thumb decoders, and [`size`] measures images against
+//! the committed size baseline.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use object::{Object, ObjectSection};
+
+pub mod code;
+pub mod size;
+pub mod symbols;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+
+pub use code::Code;
+pub use size::{Sections, SizeInfo};
+pub use symbols::{Symbol, SymbolTable};
+
+/// Flash address of the image header (matches `stm32f3_common::image`)
+pub const IMAGE_HEADER_ADDR: u64 = 0x0800_0200;
+
+/// Magic value identifying a valid header ("IMGH")
+pub const IMAGE_HEADER_MAGIC: u32 = u32::from_le_bytes(*b"IMGH");
+
+/// Section the firmware places the header in
+pub const IMAGE_HEADER_SECTION: &str = ".image_header";
+
+/// Size of the version 1 header layout
+const HEADER_SIZE_V1: usize = 0x80;
+
+/// Errors reading firmware metadata
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be read
+    Io(std::io::Error),
+    /// The file is not a parseable ELF
+    Elf(object::Error),
+    /// The ELF has no `.image_header` section
+    MissingHeader,
+    /// The header bytes are too short or have the wrong magic
+    InvalidHeader(&'static str),
+    /// A malformed line in the size baseline file
+    Baseline { line: usize, msg: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "read failed: {}", err),
+            Error::Elf(err) => write!(f, "invalid ELF: {}", err),
+            Error::MissingHeader => write!(f, "no {} section", IMAGE_HEADER_SECTION),
+            Error::InvalidHeader(reason) => write!(f, "invalid image header: {}", reason),
+            Error::Baseline { line, msg } => write!(f, "size baseline line {}: {}", line, msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<object::Error> for Error {
+    fn from(err: object::Error) -> Self {
+        Error::Elf(err)
+    }
+}
+
+/// Decoded firmware image header
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageHeader {
+    pub header_version: u16,
+    pub build_timestamp: u64,
+    pub git_dirty: bool,
+    pub crate_name: String,
+    pub crate_version: String,
+    pub git_commit: String,
+    pub profile: String,
+}
+
+impl ImageHeader {
+    /// Decode a header from its raw bytes (see `stm32f3_common::image`)
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < HEADER_SIZE_V1 {
+            return Err(Error::InvalidHeader("too short"));
+        }
+        if u32_at(bytes, 0x00) != IMAGE_HEADER_MAGIC {
+            return Err(Error::InvalidHeader("bad magic"));
+        }
+
+        Ok(ImageHeader {
+            header_version: u16::from_le_bytes([bytes[0x04], bytes[0x05]]),
+            build_timestamp: u64::from_le_bytes(bytes[0x08..0x10].try_into().unwrap()),
+            git_dirty: u32_at(bytes, 0x10) & 1 != 0,
+            crate_name: field_string(&bytes[0x18..0x38]),
+            crate_version: field_string(&bytes[0x38..0x48]),
+            git_commit: field_string(&bytes[0x48..0x70]),
+            profile: field_string(&bytes[0x70..0x80]),
+        })
+    }
+
+    /// Read the header from the `.image_header` section of ELF data
+    pub fn from_elf_data(data: &[u8]) -> Result<Self, Error> {
+        let file = object::File::parse(data)?;
+        let section = file
+            .section_by_name(IMAGE_HEADER_SECTION)
+            .ok_or(Error::MissingHeader)?;
+        Self::parse(section.data()?)
+    }
+
+    /// Read the header from an ELF file on disk
+    pub fn from_elf(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_elf_data(&fs::read(path)?)
+    }
+
+    /// The same line the firmware prints at boot
+    pub fn build_line(&self) -> String {
+        format!(
+            "Build: {} {} git:{}{} profile:{} time:{}",
+            self.crate_name,
+            self.crate_version,
+            self.git_commit,
+            if self.git_dirty { "-dirty" } else { "" },
+            self.profile,
+            self.build_timestamp,
+        )
+    }
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn field_string(field: &[u8]) -> String {
+    let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..len]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ElfBuilder;
+    use object::SectionKind;
+
+    /// A header laid out as `stm32f3_common::image` writes it
+    fn raw_header(dirty: bool) -> Vec<u8> {
+        let mut bytes = vec![0; HEADER_SIZE_V1];
+        bytes[0x00..0x04].copy_from_slice(b"IMGH");
+        bytes[0x04..0x06].copy_from_slice(&1u16.to_le_bytes());
+        bytes[0x06..0x08].copy_from_slice(&(HEADER_SIZE_V1 as u16).to_le_bytes());
+        bytes[0x08..0x10].copy_from_slice(&1_700_000_000u64.to_le_bytes());
+        bytes[0x10..0x14].copy_from_slice(&u32::from(dirty).to_le_bytes());
+        bytes[0x18..0x18 + 11].copy_from_slice(b"stm32f3-dma");
+        bytes[0x38..0x38 + 5].copy_from_slice(b"0.1.0");
+        bytes[0x48..0x70].copy_from_slice(&[b'a'; 40]);
+        bytes[0x70..0x70 + 7].copy_from_slice(b"release");
+        bytes
+    }
+
+    fn elf(section: &str, data: &[u8]) -> Vec<u8> {
+        let mut elf = ElfBuilder::new();
+        elf.section(section, SectionKind::ReadOnlyData, data);
+        elf.build()
+    }
+
+    #[test]
+    fn parses_the_firmware_layout() {
+        let header = ImageHeader::parse(&raw_header(true)).unwrap();
+        assert_eq!(
+            header,
+            ImageHeader {
+                header_version: 1,
+                build_timestamp: 1_700_000_000,
+                git_dirty: true,
+                crate_name: "stm32f3-dma".into(),
+                crate_version: "0.1.0".into(),
+                git_commit: "a".repeat(40),
+                profile: "release".into(),
+            }
+        );
+        assert_eq!(
+            header.build_line(),
+            format!(
+                "Build: stm32f3-dma 0.1.0 git:{}-dirty profile:release time:1700000000",
+                "a".repeat(40)
+            )
+        );
+        let clean = ImageHeader::parse(&raw_header(false)).unwrap();
+        assert!(!clean.git_dirty);
+        assert!(!clean.build_line().contains("-dirty"));
+    }
+
+    #[test]
+    fn ignores_bytes_past_the_header() {
+        let mut bytes = raw_header(false);
+        bytes.extend_from_slice(&[0xFF; 16]);
+        assert_eq!(ImageHeader::parse(&bytes).unwrap().profile, "release");
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let bytes = raw_header(false);
+        assert!(matches!(
+            ImageHeader::parse(&bytes[..HEADER_SIZE_V1 - 1]),
+            Err(Error::InvalidHeader("too short"))
+        ));
+        let mut erased = bytes.clone();
+        erased[..4].copy_from_slice(&[0xFF; 4]);
+        assert!(matches!(
+            ImageHeader::parse(&erased),
+            Err(Error::InvalidHeader("bad magic"))
+        ));
+    }
+
+    #[test]
+    fn reads_the_header_section() {
+        let data = elf(IMAGE_HEADER_SECTION, &raw_header(false));
+        let header = ImageHeader::from_elf_data(&data).unwrap();
+        assert_eq!(header.crate_name, "stm32f3-dma");
+
+        let data = elf(".rodata", &raw_header(false));
+        assert!(matches!(
+            ImageHeader::from_elf_data(&data),
+            Err(Error::MissingHeader)
+        ));
+        assert!(matches!(
+            ImageHeader::from_elf_data(b"not an ELF"),
+            Err(Error::Elf(_))
+        ));
+    }
+}
@@ -0,0 +1,413 @@
+//! Section and symbol sizes of firmware ELFs, compared with a baseline
+//!
+//! Sizes follow the Berkeley `size` convention: `text` is every allocated
+//! read-only section (vector table, image header, code, rodata), `data` the
+//! initialised RAM that is also stored in flash and `bss` the zeroed RAM.
+//!
+//! The baseline is a plain text file with one block per crate, its largest
+//! symbols indented underneath:
+//!
+//! ```text
+//! crate stm32f3-dma text 6156 data 16 bss 20
+//!   1316 stm32f3_dma::__cortex_m_rt_main
+//!   412 core::fmt::write
+//! ```
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use object::elf::{SHF_ALLOC, SHF_WRITE};
+use object::{Object, ObjectSection, SectionFlags, SectionKind};
+
+use crate::{Error, SymbolTable};
+
+/// Number of symbols kept per crate in the baseline
+pub const BASELINE_SYMBOLS: usize = 25;
+
+/// Section totals of one firmware image, in bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Sections {
+    pub text: u64,
+    pub data: u64,
+    pub bss: u64,
+}
+
+impl Sections {
+    /// Bytes stored in flash
+    pub fn flash(&self) -> u64 {
+        self.text + self.data
+    }
+
+    /// Bytes of RAM used before the stack
+    pub fn ram(&self) -> u64 {
+        self.data + self.bss
+    }
+}
+
+/// Sizes of one crate's firmware image
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SizeInfo {
+    pub name: String,
+    pub sections: Sections,
+    /// `(size, demangled path)`, largest first; monomorphised copies of the
+    /// same function are added together
+    pub symbols: Vec<(u64, String)>,
+}
+
+impl SizeInfo {
+    /// Measure an ELF file on disk
+    pub fn from_elf(name: &str, path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_elf_data(name, &fs::read(path)?)
+    }
+
+    /// Measure ELF data
+    pub fn from_elf_data(name: &str, data: &[u8]) -> Result<Self, Error> {
+        let file = object::File::parse(data)?;
+        let mut sections = Sections::default();
+        for section in file.sections() {
+            let SectionFlags::Elf { sh_flags } = section.flags() else {
+                continue;
+            };
+            if sh_flags & u64::from(SHF_ALLOC) == 0 {
+                continue;
+            }
+            if section.kind() == SectionKind::UninitializedData {
+                sections.bss += section.size();
+            } else if sh_flags & u64::from(SHF_WRITE) != 0 {
+                sections.data += section.size();
+            } else {
+                sections.text += section.size();
+            }
+        }
+
+        let mut symbols: Vec<(u64, String)> = Vec::new();
+        for symbol in SymbolTable::from_elf_data(data)?.iter() {
+            // `.Lanon.<hash>.N` constants are renamed on every build
+            if symbol.size == 0 || symbol.name.starts_with(".L") {
+                continue;
+            }
+            match symbols.iter_mut().find(|(_, path)| *path == symbol.path) {
+                Some((size, _)) => *size += symbol.size,
+                None => symbols.push((symbol.size, symbol.path.clone())),
+            }
+        }
+        symbols.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+        Ok(SizeInfo {
+            name: name.to_string(),
+            sections,
+            symbols,
+        })
+    }
+
+    /// Size of the symbol at `path`, if it is listed
+    pub fn symbol(&self, path: &str) -> Option<u64> {
+        self.symbols
+            .iter()
+            .find(|(_, p)| p == path)
+            .map(|(size, _)| *size)
+    }
+}
+
+/// Parse a baseline file (see the module docs)
+pub fn parse_baseline(text: &str) -> Result<Vec<SizeInfo>, Error> {
+    let mut crates: Vec<SizeInfo> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let error = |msg: &str| Error::Baseline {
+            line: index + 1,
+            msg: msg.to_string(),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            let current = crates
+                .last_mut()
+                .ok_or_else(|| error("symbol before the first `crate` line"))?;
+            let (size, path) = line
+                .trim()
+                .split_once(' ')
+                .ok_or_else(|| error("expected `<size> <symbol>`"))?;
+            let size = size.parse().map_err(|_| error("invalid symbol size"))?;
+            current.symbols.push((size, path.to_string()));
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let ["crate", name, "text", text, "data", data, "bss", bss] = fields[..] else {
+            return Err(error("expected `crate <name> text <n> data <n> bss <n>`"));
+        };
+        let number = |value: &str| value.parse().map_err(|_| error("invalid size"));
+        crates.push(SizeInfo {
+            name: name.to_string(),
+            sections: Sections {
+                text: number(text)?,
+                data: number(data)?,
+                bss: number(bss)?,
+            },
+            symbols: Vec::new(),
+        });
+    }
+    Ok(crates)
+}
+
+/// Render a baseline file, keeping the top [`BASELINE_SYMBOLS`] per crate
+pub fn write_baseline(crates: &[SizeInfo]) -> String {
+    let mut out = String::from(
+        "# Firmware size baseline (release builds), written by `size-report --update`\n",
+    );
+    for info in crates {
+        let s = info.sections;
+        let _ = writeln!(
+            out,
+            "crate {} text {} data {} bss {}",
+            info.name, s.text, s.data, s.bss
+        );
+        for (size, path) in info.symbols.iter().take(BASELINE_SYMBOLS) {
+            let _ = writeln!(out, "  {} {}", size, path);
+        }
+    }
+    out
+}
+
+/// Change of one symbol between the baseline and the current build
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolChange {
+    pub path: String,
+    /// `None` if the symbol was not among the baseline's largest
+    pub before: Option<u64>,
+    pub after: u64,
+}
+
+impl SymbolChange {
+    pub fn growth(&self) -> i64 {
+        self.after as i64 - self.before.unwrap_or(0) as i64
+    }
+}
+
+/// Symbols of `current` that grew or are new compared with `baseline`,
+/// largest growth first
+///
+/// Only symbols at least as large as the smallest one recorded in the
+/// baseline can be compared; smaller ones were never written down.
+pub fn grown_symbols(baseline: &SizeInfo, current: &SizeInfo) -> Vec<SymbolChange> {
+    let floor = baseline
+        .symbols
+        .iter()
+        .map(|(size, _)| *size)
+        .min()
+        .unwrap_or(0);
+    let mut changes: Vec<SymbolChange> = current
+        .symbols
+        .iter()
+        .filter_map(|(after, path)| {
+            let before = baseline.symbol(path);
+            let change = SymbolChange {
+                path: path.clone(),
+                before,
+                after: *after,
+            };
+            match before {
+                Some(before) if *after > before => Some(change),
+                None if *after > floor => Some(change),
+                _ => None,
+            }
+        })
+        .collect();
+    changes.sort_by(|a, b| {
+        b.growth()
+            .cmp(&a.growth())
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ElfBuilder;
+    use object::SymbolKind;
+
+    /// `.text` with two copies of one generic function, a `.Lanon` constant
+    /// and a marker symbol, initialised `.data`, `.bss` and a `.comment`
+    /// that is not loaded
+    fn elf() -> Vec<u8> {
+        let mut elf = ElfBuilder::new();
+        let text = elf.section(".text", SectionKind::Text, &[0; 0x100]);
+        let rodata = elf.section(".rodata", SectionKind::ReadOnlyData, &[0; 0x20]);
+        elf.section(".data", SectionKind::Data, &[1; 0x10]);
+        let bss = elf.bss(".bss", 0x30);
+        elf.section(".comment", SectionKind::OtherString, b"rustc\0");
+
+        let functions = [
+            ("_ZN4core3fmt5write17h0000000000000001E", 0x01, 0x40),
+            ("_ZN4core3fmt5write17h0000000000000002E", 0x41, 0x20),
+            ("_ZN3app4main17h0000000000000003E", 0x61, 0x50),
+            ("_ZN3app5START17h0000000000000004E", 0xB1, 0),
+        ];
+        for (name, value, size) in functions {
+            elf.symbol(name, value, size, SymbolKind::Text, text);
+        }
+        let statics = [
+            (".Lanon.abcdef.0", 0, 0x20, rodata),
+            ("_ZN3app5STATE17h0000000000000005E", 0, 0x30, bss),
+        ];
+        for (name, value, size, section) in statics {
+            elf.symbol(name, value, size, SymbolKind::Data, section);
+        }
+        elf.build()
+    }
+
+    fn info(name: &str, text: u64, symbols: &[(u64, &str)]) -> SizeInfo {
+        SizeInfo {
+            name: name.into(),
+            sections: Sections {
+                text,
+                data: 16,
+                bss: 20,
+            },
+            symbols: symbols
+                .iter()
+                .map(|(size, path)| (*size, path.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn measures_sections_berkeley_style() {
+        let info = SizeInfo::from_elf_data("app", &elf()).unwrap();
+        assert_eq!(
+            info.sections,
+            Sections {
+                text: 0x120,
+                data: 0x10,
+                bss: 0x30,
+            }
+        );
+        assert_eq!(info.sections.flash(), 0x130);
+        assert_eq!(info.sections.ram(), 0x40);
+    }
+
+    #[test]
+    fn adds_up_monomorphised_copies() {
+        let info = SizeInfo::from_elf_data("app", &elf()).unwrap();
+        assert_eq!(
+            info.symbols,
+            [
+                (0x60, "core::fmt::write".to_string()),
+                (0x50, "app::main".to_string()),
+                (0x30, "app::STATE".to_string()),
+            ]
+        );
+        assert_eq!(info.symbol("app::main"), Some(0x50));
+        assert_eq!(info.symbol("app::START"), None);
+        assert!(SizeInfo::from_elf_data("app", b"not an elf").is_err());
+    }
+
+    #[test]
+    fn parses_the_checked_in_baseline() {
+        let crates = parse_baseline(include_str!("../../../size-baseline.txt")).unwrap();
+        assert!(crates.iter().any(|info| info.name == "stm32f3-uart"));
+        for info in &crates {
+            assert!(info.sections.text > 0, "{}", info.name);
+            assert!(!info.symbols.is_empty(), "{}", info.name);
+            assert!(info.symbols.len() <= BASELINE_SYMBOLS, "{}", info.name);
+        }
+    }
+
+    #[test]
+    fn baseline_round_trip() {
+        let crates = vec![
+            info(
+                "stm32f3-dma",
+                6156,
+                &[
+                    (1316, "stm32f3_dma::__cortex_m_rt_main"),
+                    (412, "core::fmt::write"),
+                    (8, "<T as core::any::Any>::type_id"),
+                ],
+            ),
+            info("stm32f3-crc", 4000, &[]),
+        ];
+        let text = write_baseline(&crates);
+        assert!(text.starts_with('#'));
+        assert!(text.contains(
+            "crate stm32f3-dma text 6156 data 16 bss 20\n  1316 stm32f3_dma::__cortex_m_rt_main\n"
+        ));
+        assert_eq!(parse_baseline(&text).unwrap(), crates);
+    }
+
+    #[test]
+    fn baseline_keeps_the_largest_symbols() {
+        let symbols: Vec<(u64, String)> = (0..40).rev().map(|n| (n, format!("f{}", n))).collect();
+        let mut big = info("big", 1, &[]);
+        big.symbols = symbols;
+        let parsed = parse_baseline(&write_baseline(&[big])).unwrap();
+        assert_eq!(parsed[0].symbols.len(), BASELINE_SYMBOLS);
+        assert_eq!(parsed[0].symbols[0], (39, "f39".to_string()));
+    }
+
+    #[test]
+    fn baseline_errors_name_the_line() {
+        let error = |text: &str| match parse_baseline(text) {
+            Err(Error::Baseline { line, msg }) => (line, msg),
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(
+            error("  12 main\n"),
+            (1, "symbol before the first `crate` line".into())
+        );
+        assert_eq!(error("# sizes\n\ncrate a text 1 data 2\n").0, 3);
+        assert_eq!(
+            error("crate a text 1 data 2 bss x\n"),
+            (1, "invalid size".into())
+        );
+        assert_eq!(
+            error("crate a text 1 data 2 bss 3\n  main\n"),
+            (2, "expected `<size> <symbol>`".into())
+        );
+        assert_eq!(
+            error("crate a text 1 data 2 bss 3\n  big main\n"),
+            (2, "invalid symbol size".into())
+        );
+    }
+
+    #[test]
+    fn grown_symbols_largest_first() {
+        let baseline = info("a", 1, &[(100, "main"), (50, "write"), (20, "init")]);
+        let current = info(
+            "a",
+            1,
+            &[
+                (100, "main"),
+                (90, "write"),
+                (60, "new_big"),
+                (18, "init"),
+                (10, "new_small"),
+            ],
+        );
+        let changes = grown_symbols(&baseline, &current);
+        assert_eq!(
+            changes,
+            [
+                SymbolChange {
+                    path: "new_big".into(),
+                    before: None,
+                    after: 60,
+                },
+                SymbolChange {
+                    path: "write".into(),
+                    before: Some(50),
+                    after: 90,
+                },
+            ]
+        );
+        assert_eq!(changes[0].growth(), 60);
+        assert_eq!(changes[1].growth(), 40);
+        // Without recorded symbols, anything new counts
+        let empty = info("a", 1, &[]);
+        assert_eq!(grown_symbols(&empty, &current).len(), 5);
+    }
+}
@@ -0,0 +1,154 @@
+//! Symbol lookup for firmware ELFs
+//!
+//! Rust statics keep their mangled names in the ELF
+//! (`_ZN11stm32f3_dma10DST_BUFFER17h9290e2b110a909a0E`), so lookups accept
+//! the plain item name (`DST_BUFFER`), the demangled path without the hash
+//! (`stm32f3_dma::DST_BUFFER`) or the raw symbol name.
+
+use std::fs;
+use std::path::Path;
+
+use object::{Object, ObjectSymbol, SymbolKind};
+
+use crate::Error;
+
+/// A data or function symbol
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    /// Raw (possibly mangled) name
+    pub name: String,
+    /// Demangled path without the hash, e.g. `stm32f3_dma::DST_BUFFER`
+    pub path: String,
+    pub address: u64,
+    pub size: u64,
+    pub kind: SymbolKind,
+}
+
+impl Symbol {
+    /// Whether `name` refers to this symbol (see the module docs)
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name
+            || self.path == name
+            || self
+                .path
+                .strip_suffix(name)
+                .is_some_and(|prefix| prefix.ends_with("::"))
+    }
+}
+
+/// Data and text symbols from an ELF
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    /// Read the symbol table from ELF data
+    pub fn from_elf_data(data: &[u8]) -> Result<Self, Error> {
+        let file = object::File::parse(data)?;
+        let symbols = file
+            .symbols()
+            .filter(|s| matches!(s.kind(), SymbolKind::Data | SymbolKind::Text))
+            .filter_map(|s| {
+                let name = s.name().ok()?.to_string();
+                Some(Symbol {
+                    path: format!("{:#}", rustc_demangle::demangle(&name)),
+                    name,
+                    address: s.address(),
+                    size: s.size(),
+                    kind: s.kind(),
+                })
+            })
+            .collect();
+        Ok(SymbolTable { symbols })
+    }
+
+    /// Read the symbol table from an ELF file on disk
+    pub fn from_elf(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_elf_data(&fs::read(path)?)
+    }
+
+    /// Find a symbol by name; the first match wins
+    pub fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|s| s.matches(name))
+    }
+
+    /// The symbol whose range contains `address` (e.g. to name a PC value)
+    pub fn containing(&self, address: u64) -> Option<&Symbol> {
+        // Thumb function symbols have bit 0 set
+        self.symbols.iter().find(|s| {
+            let start = s.address & !1;
+            address >= start && address < start + s.size.max(1)
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::ElfBuilder;
+    use object::SectionKind;
+
+    const DST_BUFFER: &str = "_ZN11stm32f3_dma10DST_BUFFER17h9290e2b110a909a0E";
+    const MAIN: &str = "_ZN11stm32f3_dma18__cortex_m_rt_main17h0123456789abcdefE";
+
+    /// An ELF with `DST_BUFFER` in `.bss`, a Thumb `main` and a section symbol
+    fn elf() -> Vec<u8> {
+        let mut elf = ElfBuilder::new();
+        let text = elf.section(".text", SectionKind::Text, &[0; 0x40]);
+        let bss = elf.bss(".bss", 0x100);
+        elf.symbol(MAIN, 0x11, 0x20, SymbolKind::Text, text)
+            .symbol(DST_BUFFER, 0x40, 0x80, SymbolKind::Data, bss)
+            .section_symbol(text);
+        elf.build()
+    }
+
+    #[test]
+    fn reads_data_and_text_symbols() {
+        let table = SymbolTable::from_elf_data(&elf()).unwrap();
+        let names: Vec<&str> = table.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(
+            names,
+            ["stm32f3_dma::__cortex_m_rt_main", "stm32f3_dma::DST_BUFFER"]
+        );
+        let buffer = table.lookup("DST_BUFFER").unwrap();
+        assert_eq!(buffer.name, DST_BUFFER);
+        assert_eq!((buffer.address, buffer.size), (0x40, 0x80));
+        assert_eq!(buffer.kind, SymbolKind::Data);
+    }
+
+    #[test]
+    fn lookup_by_any_form_of_the_name() {
+        let table = SymbolTable::from_elf_data(&elf()).unwrap();
+        for name in ["DST_BUFFER", "stm32f3_dma::DST_BUFFER", DST_BUFFER] {
+            assert_eq!(table.lookup(name).unwrap().name, DST_BUFFER, "{}", name);
+        }
+        // Only whole path segments match
+        assert!(table.lookup("BUFFER").is_none());
+        assert!(table.lookup("dma::DST_BUFFER").is_none());
+        assert!(table.lookup("stm32f3_dma").is_none());
+    }
+
+    #[test]
+    fn containing_ignores_the_thumb_bit() {
+        let table = SymbolTable::from_elf_data(&elf()).unwrap();
+        let name = |address| table.containing(address).map(|s| s.path.as_str());
+        assert_eq!(name(0x10), Some("stm32f3_dma::__cortex_m_rt_main"));
+        assert_eq!(name(0x2F), Some("stm32f3_dma::__cortex_m_rt_main"));
+        assert_eq!(name(0x30), None);
+        assert_eq!(name(0xBF), Some("stm32f3_dma::DST_BUFFER"));
+        assert_eq!(name(0xC0), None);
+    }
+
+    #[test]
+    fn not_an_elf() {
+        assert!(matches!(
+            SymbolTable::from_elf_data(b"\x7fELF"),
+            Err(Error::Elf(_))
+        ));
+    }
+}
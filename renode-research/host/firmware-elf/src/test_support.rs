@@ -0,0 +1,100 @@
+//! Small ARM ELF images for the host crates' tests
+//!
+//! Enabled by the `test-support` feature; other crates pull it in as a
+//! dev-dependency. Sections all start at address 0 and symbols carry the
+//! values the test gives them, Thumb bit included.
+
+use object::write::{self, SymbolSection};
+use object::{
+    Architecture, BinaryFormat, Endianness, SectionKind, SymbolFlags, SymbolKind, SymbolScope,
+};
+
+pub use object::write::SectionId;
+
+/// Builds a little-endian ARM ELF section by section
+pub struct ElfBuilder {
+    obj: write::Object<'static>,
+}
+
+impl ElfBuilder {
+    pub fn new() -> Self {
+        ElfBuilder {
+            obj: write::Object::new(BinaryFormat::Elf, Architecture::Arm, Endianness::Little),
+        }
+    }
+
+    /// Add a section holding `data`
+    pub fn section(&mut self, name: &str, kind: SectionKind, data: &[u8]) -> SectionId {
+        let id = self.obj.add_section(Vec::new(), name.into(), kind);
+        self.obj.append_section_data(id, data, 4);
+        id
+    }
+
+    /// Add a `.bss`-style section of `size` zero bytes
+    pub fn bss(&mut self, name: &str, size: u64) -> SectionId {
+        let id = self
+            .obj
+            .add_section(Vec::new(), name.into(), SectionKind::UninitializedData);
+        self.obj.append_section_bss(id, size, 4);
+        id
+    }
+
+    /// Add a global symbol defined in `section`
+    pub fn symbol(
+        &mut self,
+        name: &str,
+        value: u64,
+        size: u64,
+        kind: SymbolKind,
+        section: SectionId,
+    ) -> &mut Self {
+        self.add(name, value, size, kind, SymbolSection::Section(section))
+    }
+
+    /// Add an absolute data symbol, as the linker script defines them
+    pub fn absolute(&mut self, name: &str, value: u64) -> &mut Self {
+        self.add(name, value, 0, SymbolKind::Data, SymbolSection::Absolute)
+    }
+
+    /// Add the section symbol of `section`
+    pub fn section_symbol(&mut self, section: SectionId) -> &mut Self {
+        self.obj.section_symbol(section);
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        self.obj.write().unwrap()
+    }
+
+    fn add(
+        &mut self,
+        name: &str,
+        value: u64,
+        size: u64,
+        kind: SymbolKind,
+        section: SymbolSection,
+    ) -> &mut Self {
+        self.obj.add_symbol(write::Symbol {
+            name: name.into(),
+            value,
+            size,
+            kind,
+            scope: SymbolScope::Linkage,
+            weak: false,
+            section,
+            flags: SymbolFlags::None,
+        });
+        self
+    }
+}
+
+impl Default for ElfBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Little-endian bytes of Thumb code given as halfwords
+pub fn thumb(halfwords: &[u16]) -> Vec<u8> {
+    halfwords.iter().flat_map(|hw| hw.to_le_bytes()).collect()
+}
@@ -0,0 +1,215 @@
+//! COBS and CRC-16 for the framed results channel
+//!
+//! `stm32f3-common`'s `framed` module encodes frames with these on the
+//! firmware and `frame-decode` checks them on the host, so both ends share
+//! one implementation. `no_std` and allocation-free: callers bring the
+//! output buffer.
+//!
+//! ```
+//! use frame_codec::{cobs_decode, cobs_encode, crc16, max_encoded_len};
+//!
+//! let data = [0x11, 0x00, 0x22];
+//! let mut encoded = [0u8; max_encoded_len(3)];
+//! let len = cobs_encode(&data, &mut encoded).unwrap();
+//! assert_eq!(&encoded[..len], &[0x02, 0x11, 0x02, 0x22]);
+//!
+//! let mut decoded = [0u8; 3];
+//! assert_eq!(cobs_decode(&encoded[..len], &mut decoded), Some(3));
+//! assert_eq!(decoded, data);
+//! assert_eq!(crc16(b"123456789"), 0x29B1);
+//! ```
+
+#![no_std]
+
+/// CRC-16/CCITT-FALSE (polynomial 0x1021, initial value 0xFFFF)
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Longest COBS encoding of `len` bytes: one code byte per 254 data bytes,
+/// and at least one
+pub const fn max_encoded_len(len: usize) -> usize {
+    len + len / 254 + 1
+}
+
+/// COBS-encode `data` into `out`, returning the encoded length
+///
+/// The result contains no zero bytes and is at most
+/// [`max_encoded_len`]`(data.len())` long; `None` if `out` is shorter.
+pub fn cobs_encode(data: &[u8], out: &mut [u8]) -> Option<usize> {
+    let mut code_at = 0;
+    let mut len = 1;
+    let mut code = 1u8;
+    for &byte in data {
+        if byte != 0 {
+            *out.get_mut(len)? = byte;
+            len += 1;
+            code += 1;
+        }
+        if byte == 0 || code == 0xFF {
+            *out.get_mut(code_at)? = code;
+            code_at = len;
+            len += 1;
+            code = 1;
+        }
+    }
+    *out.get_mut(code_at)? = code;
+    Some(len)
+}
+
+/// Undo [`cobs_encode`], returning the decoded length
+///
+/// `None` if `data` is not valid COBS (a zero byte, or a code pointing
+/// past the end) or `out` is too short. The decoded data is never longer
+/// than `data`.
+pub fn cobs_decode(data: &[u8], out: &mut [u8]) -> Option<usize> {
+    let mut len = 0;
+    let mut at = 0;
+    while at < data.len() {
+        let code = data[at] as usize;
+        let end = at + code;
+        if code == 0 || end > data.len() {
+            return None;
+        }
+        let block = &data[at + 1..end];
+        if block.contains(&0) {
+            return None;
+        }
+        out.get_mut(len..len + block.len())?.copy_from_slice(block);
+        len += block.len();
+        at = end;
+        if code < 0xFF && at < data.len() {
+            *out.get_mut(len)? = 0;
+            len += 1;
+        }
+    }
+    Some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::vec;
+    use std::vec::Vec;
+
+    fn encode(data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0xAA; max_encoded_len(data.len())];
+        let len = cobs_encode(data, &mut out).unwrap();
+        out.truncate(len);
+        out
+    }
+
+    fn decode(data: &[u8]) -> Option<Vec<u8>> {
+        let mut out = vec![0xAA; data.len()];
+        let len = cobs_decode(data, &mut out)?;
+        out.truncate(len);
+        Some(out)
+    }
+
+    /// Encode, check the encoding is zero-free and within bounds, decode
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let encoded = encode(data);
+        assert!(!encoded.contains(&0), "zero in {:02X?}", encoded);
+        assert!(encoded.len() <= max_encoded_len(data.len()));
+        assert_eq!(decode(&encoded).as_deref(), Some(data));
+        encoded
+    }
+
+    #[test]
+    fn empty_payload() {
+        assert_eq!(round_trip(&[]), [0x01]);
+    }
+
+    #[test]
+    fn zero_runs() {
+        assert_eq!(round_trip(&[0x00]), [0x01, 0x01]);
+        assert_eq!(round_trip(&[0x00, 0x00]), [0x01, 0x01, 0x01]);
+        assert_eq!(
+            round_trip(&[0x00, 0x11, 0x00, 0x00, 0x22, 0x00]),
+            [0x01, 0x02, 0x11, 0x01, 0x02, 0x22, 0x01]
+        );
+        assert_eq!(
+            round_trip(&[0x11, 0x22, 0x00, 0x33]),
+            [0x03, 0x11, 0x22, 0x02, 0x33]
+        );
+        round_trip(&[0; 600]);
+    }
+
+    #[test]
+    fn full_blocks() {
+        // 254 non-zero bytes fill a block exactly
+        let data: Vec<u8> = (1..=254).collect();
+        let encoded = round_trip(&data);
+        assert_eq!(encoded.len(), 256);
+        assert_eq!((encoded[0], encoded[255]), (0xFF, 0x01));
+        assert_eq!(encoded.len(), max_encoded_len(data.len()));
+
+        // One more starts a second block
+        let data: Vec<u8> = (0..255).map(|i| (i % 255 + 1) as u8).collect();
+        let encoded = round_trip(&data);
+        assert_eq!((encoded[0], encoded[255], encoded[256]), (0xFF, 0x02, 255));
+
+        // A zero right after a full block
+        let mut data: Vec<u8> = (1..=254).collect();
+        data.extend([0x00, 0x01]);
+        let encoded = round_trip(&data);
+        assert_eq!(&encoded[255..], [0x01, 0x02, 0x01]);
+
+        let data: Vec<u8> = (0..1000).map(|i| (i % 7) as u8).collect();
+        round_trip(&data);
+        let data: Vec<u8> = (0..1000).map(|i| (i % 251 + 1) as u8).collect();
+        round_trip(&data);
+    }
+
+    #[test]
+    fn short_output_buffers() {
+        let data: Vec<u8> = (1..=10).collect();
+        for len in 0..11 {
+            assert_eq!(cobs_encode(&data, &mut vec![0; len]), None);
+        }
+        assert_eq!(cobs_encode(&data, &mut [0; 11]), Some(11));
+
+        let encoded = encode(&[0x11, 0x00, 0x22]);
+        assert_eq!(cobs_decode(&encoded, &mut [0; 2]), None);
+        assert_eq!(cobs_decode(&encoded, &mut [0; 3]), Some(3));
+    }
+
+    #[test]
+    fn invalid_encodings() {
+        // A code pointing past the end
+        assert_eq!(decode(&[0x03, 0x11]), None);
+        assert_eq!(decode(&[0x02, 0x11, 0x05]), None);
+        // Zero bytes only ever delimit frames
+        assert_eq!(decode(&[0x00]), None);
+        assert_eq!(decode(&[0x03, 0x11, 0x00]), None);
+        // Nothing at all decodes to nothing
+        assert_eq!(decode(&[]), Some(vec![]));
+    }
+
+    #[test]
+    fn crc_check_values() {
+        assert_eq!(crc16(b""), 0xFFFF);
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc16(&[0x00]), 0xE1F0);
+        // Any single flipped bit changes the CRC
+        let data = *b"SELFTEST";
+        for bit in 0..data.len() * 8 {
+            let mut flipped = data;
+            flipped[bit / 8] ^= 1 << (bit % 8);
+            assert_ne!(crc16(&flipped), crc16(&data));
+        }
+    }
+}
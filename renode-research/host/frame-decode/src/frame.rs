@@ -0,0 +1,232 @@
+//! One frame: `COBS(postcard((seq, record)) | crc16)`, without delimiters
+//!
+//! COBS and the CRC come from `frame-codec`, which the firmware encodes
+//! with as well.
+
+use std::fmt;
+
+use crate::record::Record;
+
+/// Largest serialized record the firmware sends (`framed::MAX_PAYLOAD`)
+pub const MAX_PAYLOAD: usize = 128;
+
+/// A decoded frame
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// Counts frames from 0 since the firmware started, wrapping
+    pub seq: u16,
+    pub record: Record,
+}
+
+/// Why a frame was rejected
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Corrupt {
+    /// Not valid COBS: a code byte points past the end of the frame
+    Cobs,
+    /// Too short to hold a CRC and a record
+    Short,
+    /// Longer than any frame the firmware sends
+    Long,
+    /// The CRC trailer does not match the payload
+    Crc { expected: u16, actual: u16 },
+    /// The CRC matched but the payload is not a record
+    Record(String),
+}
+
+impl fmt::Display for Corrupt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Corrupt::Cobs => write!(f, "bad COBS encoding"),
+            Corrupt::Short => write!(f, "too short"),
+            Corrupt::Long => write!(f, "too long"),
+            Corrupt::Crc { expected, actual } => {
+                write!(f, "CRC 0x{:04X}, payload has 0x{:04X}", expected, actual)
+            }
+            Corrupt::Record(msg) => write!(f, "not a record: {}", msg),
+        }
+    }
+}
+
+pub use frame_codec::crc16;
+
+/// COBS-encode `data`; the result contains no zero bytes
+pub fn cobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0; frame_codec::max_encoded_len(data.len())];
+    let len = frame_codec::cobs_encode(data, &mut out).expect("sized for the worst case");
+    out.truncate(len);
+    out
+}
+
+/// Undo [`cobs_encode`]
+pub fn cobs_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = vec![0; data.len()];
+    let len = frame_codec::cobs_decode(data, &mut out)?;
+    out.truncate(len);
+    Some(out)
+}
+
+impl Frame {
+    /// Encode as the firmware does, without the `0x00` delimiters
+    pub fn encode(&self) -> Vec<u8> {
+        let mut payload =
+            postcard::to_allocvec(&(self.seq, &self.record)).expect("records always serialize");
+        let crc = crc16(&payload);
+        payload.extend_from_slice(&crc.to_le_bytes());
+        cobs_encode(&payload)
+    }
+
+    /// Decode the bytes between two delimiters
+    pub fn decode(data: &[u8]) -> Result<Frame, Corrupt> {
+        let payload = cobs_decode(data).ok_or(Corrupt::Cobs)?;
+        if payload.len() < 3 {
+            return Err(Corrupt::Short);
+        }
+        let (body, trailer) = payload.split_at(payload.len() - 2);
+        let actual = u16::from_le_bytes([trailer[0], trailer[1]]);
+        let expected = crc16(body);
+        if expected != actual {
+            return Err(Corrupt::Crc { expected, actual });
+        }
+        match postcard::take_from_bytes::<(u16, Record)>(body) {
+            Ok(((seq, record), [])) => Ok(Frame { seq, record }),
+            Ok((_, rest)) => Err(Corrupt::Record(format!("{} trailing bytes", rest.len()))),
+            Err(err) => Err(Corrupt::Record(err.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Level;
+
+    fn records() -> Vec<Record> {
+        vec![
+            Record::Start {
+                name: "stm32f3-selftest".into(),
+            },
+            Record::Result {
+                test: "crc".into(),
+                passed: true,
+                value: 0,
+            },
+            Record::Result {
+                test: String::new(),
+                passed: false,
+                value: u32::MAX,
+            },
+            Record::Log {
+                level: Level::Trace,
+                module: "stm32f3_dma".into(),
+                message: "x".repeat(96),
+            },
+            Record::Summary {
+                passed: 0,
+                failed: 0,
+            },
+        ]
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        for seq in [0, 1, 0x7F, 0x80, 0xFF00, u16::MAX] {
+            for record in records() {
+                let frame = Frame { seq, record };
+                let encoded = frame.encode();
+                assert!(!encoded.contains(&0));
+                assert_eq!(Frame::decode(&encoded), Ok(frame));
+            }
+        }
+    }
+
+    #[test]
+    fn wire_format_matches_the_firmware() {
+        // postcard: varint seq, varint variant index, then the fields in
+        // order, strings as a varint length and the bytes
+        let frame = Frame {
+            seq: 0x80,
+            record: Record::Result {
+                test: "crc".into(),
+                passed: true,
+                value: 300,
+            },
+        };
+        let payload = [0x80, 0x01, 0x01, 0x03, b'c', b'r', b'c', 0x01, 0xAC, 0x02];
+        let mut expected = payload.to_vec();
+        expected.extend(crc16(&payload).to_le_bytes());
+        assert_eq!(cobs_decode(&frame.encode()), Some(expected));
+
+        let frame = Frame {
+            seq: 2,
+            record: Record::Summary {
+                passed: 3,
+                failed: 0,
+            },
+        };
+        let payload = [0x02, 0x03, 0x03, 0x00];
+        assert_eq!(&cobs_decode(&frame.encode()).unwrap()[..4], &payload);
+    }
+
+    /// Re-encode `payload` (without its CRC) with a trailer of `crc`
+    fn with_crc(payload: &[u8], crc: u16) -> Vec<u8> {
+        let mut bytes = payload.to_vec();
+        bytes.extend(crc.to_le_bytes());
+        cobs_encode(&bytes)
+    }
+
+    #[test]
+    fn bad_crc() {
+        let payload = [0x00, 0x03, 0x01, 0x00];
+        let good = crc16(&payload);
+        assert!(Frame::decode(&with_crc(&payload, good)).is_ok());
+        assert_eq!(
+            Frame::decode(&with_crc(&payload, good ^ 0x0100)),
+            Err(Corrupt::Crc {
+                expected: good,
+                actual: good ^ 0x0100
+            })
+        );
+
+        // A flipped payload bit is caught by the CRC, not the record parser
+        let mut bytes = payload.to_vec();
+        bytes[1] ^= 0x04;
+        bytes.extend(good.to_le_bytes());
+        assert!(matches!(
+            Frame::decode(&cobs_encode(&bytes)),
+            Err(Corrupt::Crc { .. })
+        ));
+    }
+
+    #[test]
+    fn malformed_frames() {
+        assert_eq!(Frame::decode(&[0x05, 0x01]), Err(Corrupt::Cobs));
+        assert_eq!(Frame::decode(&cobs_encode(&[])), Err(Corrupt::Short));
+        assert_eq!(
+            Frame::decode(&cobs_encode(&[0x01, 0x02])),
+            Err(Corrupt::Short)
+        );
+
+        // Variant 9 does not exist
+        let payload = [0x00, 0x09];
+        assert!(matches!(
+            Frame::decode(&with_crc(&payload, crc16(&payload))),
+            Err(Corrupt::Record(_))
+        ));
+        // A whole record with bytes left over
+        let payload = [0x00, 0x03, 0x01, 0x00, 0xEE];
+        assert_eq!(
+            Frame::decode(&with_crc(&payload, crc16(&payload))),
+            Err(Corrupt::Record("1 trailing bytes".into()))
+        );
+    }
+
+    #[test]
+    fn corrupt_reasons_read_well() {
+        let crc = Corrupt::Crc {
+            expected: 0x8A5A,
+            actual: 0x569A,
+        };
+        assert_eq!(crc.to_string(), "CRC 0x8A5A, payload has 0x569A");
+        assert_eq!(Corrupt::Cobs.to_string(), "bad COBS encoding");
+    }
+}
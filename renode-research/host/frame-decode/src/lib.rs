@@ -0,0 +1,60 @@
+//! Decoder for the firmware's framed results channel
+//!
+//! With the `framed` feature of `stm32f3-common` the firmware sends
+//! postcard-serialized records over USART1, each in a COBS frame with a
+//! CRC-16 trailer and `0x00` delimiters, mixed with ordinary text output.
+//! [`Decoder`] reassembles the records from the byte stream, passes text
+//! lines through and reports frames that fail to decode, as well as gaps
+//! in the sequence numbers:
+//!
+//! ```
+//! use frame_decode::{Decoder, Frame, Item, Record};
+//!
+//! let frame = Frame {
+//!     seq: 0,
+//!     record: Record::Start { name: "stm32f3-crc".into() },
+//! };
+//! let mut stream = b"CRC Peripheral Test\r\n\0".to_vec();
+//! stream.extend(frame.encode());
+//! stream.push(0);
+//!
+//! let items = Decoder::new().push(&stream);
+//! assert_eq!(items[0], Item::Text("CRC Peripheral Test".into()));
+//! assert_eq!(items[1], Item::Frame(frame));
+//! ```
+
+use std::fmt;
+
+pub mod frame;
+pub mod record;
+pub mod stream;
+
+pub use frame::{cobs_decode, cobs_encode, crc16, Corrupt, Frame};
+pub use record::{Level, Record};
+pub use stream::{Decoder, Item};
+
+/// Errors reading the stream
+#[derive(Debug)]
+pub enum Error {
+    /// A file, pipe or socket could not be read
+    Io(std::io::Error),
+    /// Could not connect to the Renode terminal
+    Connect { target: String, msg: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Connect { target, msg } => write!(f, "cannot connect to {}: {}", target, msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,176 @@
+//! Decode the framed results channel from firmware UART output
+//!
+//! Usage: `frame-decode [--connect HOST:PORT] [--summary] [--quiet] [FILE]`
+//!
+//! ```text
+//! # renode: emulation CreateServerSocketTerminal 4567 "frames" false
+//! #         connector Connect sysbus.usart1 frames
+//! frame-decode --connect 127.0.0.1:4567
+//! frame-decode capture.bin                 # raw bytes saved from the UART
+//! ```
+//!
+//! Prints records, text lines and corrupt frames as they arrive, then a
+//! tally. Exits with 1 when a test failed or a frame was corrupt or lost,
+//! or with `--summary` when the stream ends without a summary record.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+
+use frame_decode::{Decoder, Error, Item, Record};
+
+/// Corrupt bytes shown per frame
+const SHOW_BYTES: usize = 16;
+
+#[derive(Parser)]
+#[command(about = "Decode the framed binary results channel from firmware UART output")]
+struct Args {
+    /// Raw UART capture (default: stdin)
+    #[arg(conflicts_with = "connect")]
+    file: Option<PathBuf>,
+
+    /// Renode server socket terminal (`CreateServerSocketTerminal`)
+    #[arg(long, value_name = "HOST:PORT")]
+    connect: Option<String>,
+
+    /// How long to keep retrying while the terminal does not exist yet
+    #[arg(long, default_value_t = 10.0, value_name = "SECONDS")]
+    wait: f64,
+
+    /// Stop at the first summary record, and fail if none arrives
+    #[arg(long)]
+    summary: bool,
+
+    /// Do not print text lines between frames
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Default)]
+struct Tally {
+    frames: usize,
+    passed: usize,
+    failed: usize,
+    corrupt: usize,
+    lost: usize,
+    summary: bool,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(tally) => {
+            let complete = tally.summary || !args.summary;
+            if !complete {
+                println!("!      no summary record");
+            }
+            println!(
+                "=== {} frame(s): {} passed, {} failed, {} corrupt, {} lost ===",
+                tally.frames, tally.passed, tally.failed, tally.corrupt, tally.lost
+            );
+            if tally.failed + tally.corrupt + tally.lost == 0 && complete {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            }
+        }
+        Err(err) => {
+            eprintln!("frame-decode: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<Tally, Error> {
+    let mut input: Box<dyn Read> = match (&args.connect, &args.file) {
+        (Some(address), _) => Box::new(connect(address, Duration::from_secs_f64(args.wait))?),
+        (None, Some(path)) if path.as_os_str() != "-" => Box::new(File::open(path)?),
+        _ => Box::new(io::stdin().lock()),
+    };
+
+    let mut decoder = Decoder::new();
+    let mut tally = Tally::default();
+    let mut buf = [0u8; 4096];
+    while !(args.summary && tally.summary) {
+        let n = match input.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        for item in decoder.push(&buf[..n]) {
+            show(&item, args.quiet, &mut tally);
+        }
+    }
+    if args.summary && tally.summary {
+        return Ok(tally);
+    }
+    for item in decoder.finish() {
+        show(&item, args.quiet, &mut tally);
+    }
+    Ok(tally)
+}
+
+fn show(item: &Item, quiet: bool, tally: &mut Tally) {
+    match item {
+        Item::Frame(frame) => {
+            tally.frames += 1;
+            match frame.record {
+                Record::Result { passed: true, .. } => tally.passed += 1,
+                Record::Result { passed: false, .. } => tally.failed += 1,
+                Record::Summary { .. } => tally.summary = true,
+                _ => {}
+            }
+            println!("#{:<5} {}", frame.seq, frame.record);
+        }
+        Item::Text(line) => {
+            if !quiet {
+                println!("       | {}", line);
+            }
+        }
+        Item::Corrupt { reason, bytes } => {
+            tally.corrupt += 1;
+            let shown: Vec<String> = bytes
+                .iter()
+                .take(SHOW_BYTES)
+                .map(|b| format!("{:02X}", b))
+                .collect();
+            let more = if bytes.len() > SHOW_BYTES { " .." } else { "" };
+            println!(
+                "!      corrupt frame ({}), {} bytes: {}{}",
+                reason,
+                bytes.len(),
+                shown.join(" "),
+                more
+            );
+        }
+        Item::Lost { count, seq } => {
+            tally.lost += *count as usize;
+            println!("!      {} frame(s) lost before #{}", count, seq);
+        }
+    }
+}
+
+/// Connect to a Renode server socket terminal, retrying until `timeout`
+/// since Renode may not have created it yet
+fn connect(address: &str, timeout: Duration) -> Result<TcpStream, Error> {
+    let start = Instant::now();
+    loop {
+        match TcpStream::connect(address) {
+            Ok(stream) => return Ok(stream),
+            Err(err) if start.elapsed() > timeout => {
+                return Err(Error::Connect {
+                    target: address.to_string(),
+                    msg: err.to_string(),
+                })
+            }
+            Err(_) => thread::sleep(Duration::from_millis(100)),
+        }
+    }
+}
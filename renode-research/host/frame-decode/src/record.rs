@@ -0,0 +1,128 @@
+//! Records carried by the channel
+//!
+//! Mirrors `stm32f3_common::framed::Record` and `stm32f3_common::log::Level`
+//! with owned strings. postcard encodes enum variants by index and struct
+//! fields in order, so the order here is the wire format and must match the
+//! firmware.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Log level (matches `stm32f3_common::log::Level`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        })
+    }
+}
+
+/// One message on the channel (matches `stm32f3_common::framed::Record`)
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Record {
+    /// A run started; sent before its results
+    Start { name: String },
+    /// Outcome of one test, with the value it was judged on
+    Result {
+        test: String,
+        passed: bool,
+        value: u32,
+    },
+    Log {
+        level: Level,
+        module: String,
+        message: String,
+    },
+    /// The run finished
+    Summary { passed: u16, failed: u16 },
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Record::Start { name } => write!(f, "START   {}", name),
+            Record::Result {
+                test,
+                passed,
+                value,
+            } => write!(
+                f,
+                "{:<7} {}  (0x{:08X})",
+                if *passed { "PASS" } else { "FAIL" },
+                test,
+                value
+            ),
+            Record::Log {
+                level,
+                module,
+                message,
+            } => write!(f, "{:<7} [{}] {}", level, module, message),
+            Record::Summary { passed, failed } => {
+                write!(f, "SUMMARY {} passed, {} failed", passed, failed)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_display_as_columns() {
+        let shown = [
+            Record::Start {
+                name: "stm32f3-crc".into(),
+            },
+            Record::Result {
+                test: "Single word CRC".into(),
+                passed: true,
+                value: 0xCBF4_3926,
+            },
+            Record::Result {
+                test: "DAC".into(),
+                passed: false,
+                value: 7,
+            },
+            Record::Log {
+                level: Level::Warn,
+                module: "stm32f3_dma".into(),
+                message: "slow".into(),
+            },
+            Record::Summary {
+                passed: 1,
+                failed: 1,
+            },
+        ]
+        .map(|record| record.to_string());
+        assert_eq!(
+            shown,
+            [
+                "START   stm32f3-crc",
+                "PASS    Single word CRC  (0xCBF43926)",
+                "FAIL    DAC  (0x00000007)",
+                "WARN    [stm32f3_dma] slow",
+                "SUMMARY 1 passed, 1 failed",
+            ]
+        );
+    }
+
+    #[test]
+    fn levels_are_ordered_by_severity() {
+        assert!(Level::Error < Level::Warn && Level::Debug < Level::Trace);
+    }
+}
@@ -0,0 +1,308 @@
+//! Reassembling frames and text from a UART byte stream
+//!
+//! The stream is split on `0x00`. Each chunk between delimiters is a frame
+//! if it decodes as one, text if it is printable, and corrupt otherwise.
+//!
+//! The firmware opens and closes every frame with a delimiter, so after a
+//! frame has closed the stream carries text until the next `0x00`. That
+//! text is released line by line as it arrives, so plain firmware output
+//! shows up without waiting for the next frame; after an opening delimiter
+//! nothing is released before the chunk ends, since frame bytes may well
+//! contain `\n`.
+
+use crate::frame::{Corrupt, Frame, MAX_PAYLOAD};
+
+/// Longest chunk that can still be a frame: payload, CRC and COBS overhead
+const MAX_ENCODED: usize = frame_codec::max_encoded_len(MAX_PAYLOAD + 2);
+
+/// Something found in the stream
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Frame(Frame),
+    /// A line of ordinary output, without its terminator
+    Text(String),
+    /// Bytes between delimiters that are neither a frame nor text
+    Corrupt {
+        reason: Corrupt,
+        bytes: Vec<u8>,
+    },
+    /// Frames that never arrived (not even as corrupt ones) before `seq`
+    Lost {
+        count: u16,
+        seq: u16,
+    },
+}
+
+/// Incremental decoder; feed it bytes as they are read
+#[derive(Debug, Default)]
+pub struct Decoder {
+    chunk: Vec<u8>,
+    /// Sequence number the next frame should have
+    next_seq: Option<u16>,
+    /// Corrupt chunks since the last good frame, each presumably one frame
+    corrupt: u16,
+    /// The last delimiter opened a frame rather than closing one
+    opened: bool,
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder::default()
+    }
+
+    /// Decode `bytes`, returning everything completed by them
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Item> {
+        let mut items = Vec::new();
+        for &byte in bytes {
+            if byte == 0 {
+                self.end_chunk(&mut items);
+            } else {
+                self.chunk.push(byte);
+                if byte == b'\n' && !self.opened && is_text(&self.chunk) {
+                    items.extend(text_lines(&self.chunk));
+                    self.chunk.clear();
+                }
+            }
+        }
+        items
+    }
+
+    /// Decode whatever is left at the end of the stream
+    pub fn finish(&mut self) -> Vec<Item> {
+        let mut items = Vec::new();
+        self.end_chunk(&mut items);
+        items
+    }
+
+    fn end_chunk(&mut self, items: &mut Vec<Item>) {
+        let chunk = std::mem::take(&mut self.chunk);
+        let opened = std::mem::replace(&mut self.opened, true);
+        if chunk.is_empty() {
+            return;
+        }
+        let reason = if chunk.len() <= MAX_ENCODED {
+            match Frame::decode(&chunk) {
+                Ok(frame) => {
+                    self.check_seq(&frame, items);
+                    items.push(Item::Frame(frame));
+                    self.opened = false;
+                    return;
+                }
+                Err(reason) => reason,
+            }
+        } else {
+            Corrupt::Long
+        };
+        if is_text(&chunk) {
+            items.extend(text_lines(&chunk));
+        } else {
+            // A damaged frame was just closed; damaged text ran into an opening
+            self.opened = !opened;
+            self.corrupt = self.corrupt.saturating_add(1);
+            items.push(Item::Corrupt {
+                reason,
+                bytes: chunk,
+            });
+        }
+    }
+
+    fn check_seq(&mut self, frame: &Frame, items: &mut Vec<Item>) {
+        // Sequence numbers start over when the firmware (re)boots
+        if let (Some(next), false) = (self.next_seq, frame.seq == 0) {
+            let missing = frame.seq.wrapping_sub(next).saturating_sub(self.corrupt);
+            if missing > 0 {
+                items.push(Item::Lost {
+                    count: missing,
+                    seq: frame.seq,
+                });
+            }
+        }
+        self.next_seq = Some(frame.seq.wrapping_add(1));
+        self.corrupt = 0;
+    }
+}
+
+/// Printable ASCII and line breaks only
+fn is_text(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .all(|&b| matches!(b, b' '..=b'~' | b'\r' | b'\n' | b'\t'))
+}
+
+fn text_lines(bytes: &[u8]) -> impl Iterator<Item = Item> + '_ {
+    bytes
+        .split(|&b| b == b'\n')
+        .map(|line| {
+            String::from_utf8_lossy(line)
+                .trim_end_matches('\r')
+                .to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .map(Item::Text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Record;
+
+    fn frame(seq: u16) -> Frame {
+        Frame {
+            seq,
+            record: Record::Result {
+                test: format!("test {}", seq),
+                passed: true,
+                value: seq as u32,
+            },
+        }
+    }
+
+    /// `frame` as the firmware sends it, delimiters included
+    fn sent(frame: &Frame) -> Vec<u8> {
+        let mut bytes = vec![0];
+        bytes.extend(frame.encode());
+        bytes.push(0);
+        bytes
+    }
+
+    fn text(line: &str) -> Item {
+        Item::Text(line.into())
+    }
+
+    #[test]
+    fn text_and_frames_interleave() {
+        let mut stream = b"Self-Test\r\n".to_vec();
+        stream.extend(sent(&frame(0)));
+        stream.extend(b"Suite crc: PASS\r\n\r\n");
+        stream.extend(sent(&frame(1)));
+        stream.extend(b"> ");
+
+        let mut decoder = Decoder::new();
+        let mut items = decoder.push(&stream);
+        items.extend(decoder.finish());
+        assert_eq!(
+            items,
+            [
+                text("Self-Test"),
+                Item::Frame(frame(0)),
+                text("Suite crc: PASS"),
+                Item::Frame(frame(1)),
+                text("> "),
+            ]
+        );
+    }
+
+    #[test]
+    fn text_is_released_line_by_line() {
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.push(b"CRC Peripheral"), []);
+        assert_eq!(
+            decoder.push(b" Test\r\nCRC: "),
+            [text("CRC Peripheral Test")]
+        );
+        assert_eq!(decoder.push(b"0x1234"), []);
+        assert_eq!(decoder.finish(), [text("CRC: 0x1234")]);
+    }
+
+    #[test]
+    fn frames_are_decoded_across_reads() {
+        let stream = sent(&frame(0));
+        let mut decoder = Decoder::new();
+        let mut items = Vec::new();
+        for byte in &stream {
+            items.extend(decoder.push(std::slice::from_ref(byte)));
+        }
+        assert_eq!(items, [Item::Frame(frame(0))]);
+    }
+
+    #[test]
+    fn newlines_inside_frames_do_not_split_them() {
+        // A frame whose COBS bytes contain `\n`, opened after text
+        let frame = Frame {
+            seq: 0,
+            record: Record::Start {
+                name: "\n\n".into(),
+            },
+        };
+        assert!(frame.encode().contains(&b'\n'));
+        let mut stream = b"boot\n".to_vec();
+        stream.extend(sent(&frame));
+        let items = Decoder::new().push(&stream);
+        assert_eq!(items, [text("boot"), Item::Frame(frame)]);
+    }
+
+    #[test]
+    fn corrupt_frames_are_reported_not_lost() {
+        let mut damaged = sent(&frame(1));
+        let at = damaged.len() / 2;
+        damaged[at] ^= 0x80;
+
+        let mut stream = sent(&frame(0));
+        stream.extend(&damaged);
+        stream.extend(sent(&frame(2)));
+        let items = Decoder::new().push(&stream);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0], Item::Frame(frame(0)));
+        assert!(matches!(items[1], Item::Corrupt { .. }));
+        assert_eq!(items[2], Item::Frame(frame(2)));
+    }
+
+    #[test]
+    fn missing_frames_are_lost() {
+        let mut stream = sent(&frame(3));
+        stream.extend(sent(&frame(4)));
+        stream.extend(sent(&frame(7)));
+        let items = Decoder::new().push(&stream);
+        assert_eq!(
+            items,
+            [
+                Item::Frame(frame(3)),
+                Item::Frame(frame(4)),
+                Item::Lost { count: 2, seq: 7 },
+                Item::Frame(frame(7)),
+            ]
+        );
+    }
+
+    #[test]
+    fn sequence_numbers_wrap_and_restart() {
+        let mut stream = sent(&frame(u16::MAX));
+        stream.extend(sent(&frame(0)));
+        stream.extend(sent(&frame(1)));
+        // A reboot starts over at 0 without anything being lost
+        stream.extend(sent(&frame(0)));
+        let items = Decoder::new().push(&stream);
+        assert!(items.iter().all(|item| matches!(item, Item::Frame(_))));
+        assert_eq!(items.len(), 4);
+    }
+
+    #[test]
+    fn binary_garbage_is_corrupt() {
+        let mut stream = vec![0, 0x01, 0xFF, 0x02, 0];
+        stream.extend(vec![0x41; MAX_ENCODED + 1]);
+        stream.push(0x80);
+        stream.push(0);
+        let items = Decoder::new().push(&stream);
+        assert_eq!(items.len(), 2);
+        assert!(matches!(
+            &items[0],
+            Item::Corrupt { reason: Corrupt::Cobs, bytes } if bytes == &[0x01, 0xFF, 0x02]
+        ));
+        assert!(matches!(
+            &items[1],
+            Item::Corrupt {
+                reason: Corrupt::Long,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn long_text_between_delimiters_is_still_text() {
+        let line = "x".repeat(MAX_ENCODED * 2);
+        let mut stream = vec![0];
+        stream.extend(line.as_bytes());
+        stream.push(0);
+        assert_eq!(Decoder::new().push(&stream), [text(&line)]);
+    }
+}
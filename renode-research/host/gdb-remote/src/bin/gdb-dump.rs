@@ -0,0 +1,85 @@
+//! Halt a running target and dump registers and statics over GDB
+//!
+//! Usage: `gdb-dump <elf> [SYMBOL]... [--address 127.0.0.1:3333]`
+//!
+//! The target is resumed afterwards unless `--stay-halted` is given.
+
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::Parser;
+
+use firmware_elf::SymbolTable;
+use gdb_remote::GdbClient;
+
+#[derive(Parser)]
+#[command(about = "Halt a running target and dump registers and statics over GDB")]
+struct Args {
+    /// Firmware ELF used to resolve symbols
+    elf: String,
+
+    /// Statics to dump, e.g. `DST_BUFFER`
+    symbols: Vec<String>,
+
+    /// GDB server address (`machine StartGdbServer $gdb_port`)
+    #[arg(long, default_value = "127.0.0.1:3333")]
+    address: String,
+
+    /// Leave the target halted
+    #[arg(long)]
+    stay_halted: bool,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("gdb-dump: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let symbols = SymbolTable::from_elf(&args.elf)?;
+    let mut gdb = GdbClient::connect(&args.address, Duration::from_secs(5))?;
+    gdb.halt()?;
+
+    let registers = gdb.read_registers()?;
+    for n in 0..16 {
+        if let Some(value) = registers.r(n) {
+            println!("r{:<2} = 0x{:08X}", n, value);
+        }
+    }
+    if let Some(function) = registers
+        .pc()
+        .and_then(|pc| symbols.containing(u64::from(pc)))
+    {
+        println!("pc in {}", function.path);
+    }
+
+    for name in &args.symbols {
+        let symbol = symbols
+            .lookup(name)
+            .ok_or_else(|| gdb_remote::Error::Symbol(name.clone()))?;
+        let bytes = gdb.read_memory(symbol.address, symbol.size as usize)?;
+        println!(
+            "\n{} @ 0x{:08X} ({} bytes)",
+            symbol.path, symbol.address, symbol.size
+        );
+        for (row, chunk) in bytes.chunks(16).enumerate() {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+            println!(
+                "  {:08X}: {}",
+                symbol.address + row as u64 * 16,
+                hex.join(" ")
+            );
+        }
+    }
+
+    if !args.stay_halted {
+        gdb.resume()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,517 @@
+//! GDB Remote Serial Protocol client over TCP
+
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use firmware_elf::SymbolTable;
+
+use crate::packet;
+use crate::Error;
+
+/// Largest `m`/`M` transfer per packet
+const MEMORY_CHUNK: usize = 256;
+
+/// Ctrl-C, sent outside a packet to interrupt the target
+const INTERRUPT: u8 = 0x03;
+
+/// How long the first [`GdbClient::halt`] after connecting waits for a stop
+/// reply before asking the stub why the target is stopped instead
+const ATTACH_HALT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Why the target stopped
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// `S`/`T` reply; 5 (SIGTRAP) for breakpoints, steps and interrupts
+    Signal(u8),
+    /// `W` reply: the target exited with a status
+    Exited(u8),
+    /// `X` reply: the target was terminated by a signal
+    Terminated(u8),
+}
+
+/// ARM core registers from a `g` reply
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Registers {
+    /// Every 32-bit register in the reply, in target order (r0 first)
+    pub values: Vec<u32>,
+}
+
+impl Registers {
+    /// General purpose register `r0`..`r15`
+    pub fn r(&self, n: usize) -> Option<u32> {
+        (n < 16).then(|| self.values.get(n).copied()).flatten()
+    }
+
+    pub fn sp(&self) -> Option<u32> {
+        self.r(13)
+    }
+
+    pub fn lr(&self) -> Option<u32> {
+        self.r(14)
+    }
+
+    pub fn pc(&self) -> Option<u32> {
+        self.r(15)
+    }
+}
+
+/// Connection to a GDB stub such as `machine StartGdbServer $gdb_port`
+pub struct GdbClient {
+    stream: TcpStream,
+    timeout: Duration,
+    /// Whether the target runs, as far as this client has seen; `None`
+    /// until it first stops or is resumed
+    running: Option<bool>,
+    /// Bytes read from the socket but not consumed yet
+    pending: VecDeque<u8>,
+}
+
+impl GdbClient {
+    /// Connect to `host:port`, retrying until `timeout` while the server starts
+    pub fn connect(address: &str, timeout: Duration) -> Result<GdbClient, Error> {
+        let start = Instant::now();
+        let stream = loop {
+            match TcpStream::connect(address) {
+                Ok(stream) => break stream,
+                Err(err) if start.elapsed() > timeout => {
+                    return Err(Error::Connect(format!(
+                        "could not connect to {}: {}",
+                        address, err
+                    )))
+                }
+                Err(_) => thread::sleep(Duration::from_millis(200)),
+            }
+        };
+        stream.set_nodelay(true)?;
+
+        let mut client = GdbClient {
+            stream,
+            timeout: Duration::from_secs(10),
+            running: None,
+            pending: VecDeque::new(),
+        };
+        client.request(b"qSupported:swbreak+;hwbreak+")?;
+        Ok(client)
+    }
+
+    /// How long to wait for a reply
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Stop the target and wait for the stop reply
+    ///
+    /// A target this client saw stop is not interrupted again (stubs ignore
+    /// Ctrl-C then); its stop reason is returned right away. Right after
+    /// connecting the client cannot tell, so when no stop reply arrives
+    /// within [`ATTACH_HALT_TIMEOUT`] it asks the stub instead.
+    pub fn halt(&mut self) -> Result<StopReason, Error> {
+        if self.running == Some(false) {
+            return self.stop_reason();
+        }
+        self.stream.write_all(&[INTERRUPT])?;
+        match self.running {
+            Some(_) => self.wait_for_stop(self.timeout),
+            None => match self.wait_for_stop(ATTACH_HALT_TIMEOUT.min(self.timeout)) {
+                Err(Error::Timeout) => self.stop_reason(),
+                result => result,
+            },
+        }
+    }
+
+    /// Why the target is currently stopped (`?`)
+    pub fn stop_reason(&mut self) -> Result<StopReason, Error> {
+        let reply = self.request(b"?")?;
+        let reason = parse_stop(&reply)?;
+        self.running = Some(false);
+        Ok(reason)
+    }
+
+    /// Resume the target without waiting for it to stop
+    pub fn resume(&mut self) -> Result<(), Error> {
+        self.send(b"c")?;
+        self.running = Some(true);
+        Ok(())
+    }
+
+    /// Execute one instruction
+    pub fn step(&mut self) -> Result<StopReason, Error> {
+        self.send(b"s")?;
+        self.wait_for_stop(self.timeout)
+    }
+
+    /// Wait for a stop reply after [`resume`](Self::resume)
+    ///
+    /// Console output (`O`) packets are skipped.
+    pub fn wait_for_stop(&mut self, timeout: Duration) -> Result<StopReason, Error> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let reply = self.read_packet(deadline)?;
+            if reply.first() == Some(&b'O') && reply != b"OK" {
+                continue;
+            }
+            let reason = parse_stop(&reply)?;
+            self.running = Some(false);
+            return Ok(reason);
+        }
+    }
+
+    /// Read all core registers (`g`)
+    pub fn read_registers(&mut self) -> Result<Registers, Error> {
+        let reply = self.request(b"g")?;
+        let bytes = packet::from_hex(&reply)?;
+        let values = bytes
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        Ok(Registers { values })
+    }
+
+    /// Read one register by GDB register number (`p`)
+    pub fn read_register(&mut self, number: u32) -> Result<u32, Error> {
+        let reply = self.request(format!("p{:x}", number).as_bytes())?;
+        let bytes = packet::from_hex(&reply)?;
+        let mut word = [0u8; 4];
+        let len = bytes.len().min(4);
+        word[..len].copy_from_slice(&bytes[..len]);
+        Ok(u32::from_le_bytes(word))
+    }
+
+    /// Write one register by GDB register number (`P`)
+    pub fn write_register(&mut self, number: u32, value: u32) -> Result<(), Error> {
+        let command = format!("P{:x}={}", number, packet::to_hex(&value.to_le_bytes()));
+        self.expect_ok(command.as_bytes())
+    }
+
+    /// Read `len` bytes of target memory
+    pub fn read_memory(&mut self, address: u64, len: usize) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            let offset = data.len();
+            let chunk = (len - offset).min(MEMORY_CHUNK);
+            let command = format!("m{:x},{:x}", address + offset as u64, chunk);
+            let bytes = packet::from_hex(&self.request(command.as_bytes())?)?;
+            if bytes.is_empty() {
+                return Err(Error::Protocol(format!(
+                    "empty read at 0x{:08x}",
+                    address + offset as u64
+                )));
+            }
+            data.extend_from_slice(&bytes[..bytes.len().min(chunk)]);
+        }
+        Ok(data)
+    }
+
+    /// Write target memory
+    pub fn write_memory(&mut self, address: u64, bytes: &[u8]) -> Result<(), Error> {
+        for (index, chunk) in bytes.chunks(MEMORY_CHUNK).enumerate() {
+            let command = format!(
+                "M{:x},{:x}:{}",
+                address + (index * MEMORY_CHUNK) as u64,
+                chunk.len(),
+                packet::to_hex(chunk)
+            );
+            self.expect_ok(command.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_u32(&mut self, address: u64) -> Result<u32, Error> {
+        let bytes = self.read_memory(address, 4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read the whole contents of a static, e.g. `DST_BUFFER`
+    pub fn read_symbol(&mut self, symbols: &SymbolTable, name: &str) -> Result<Vec<u8>, Error> {
+        let symbol = symbols
+            .lookup(name)
+            .ok_or_else(|| Error::Symbol(name.to_string()))?;
+        self.read_memory(symbol.address, symbol.size as usize)
+    }
+
+    /// Set a software breakpoint (`Z0`) on a Thumb instruction
+    pub fn set_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        self.expect_ok(format!("Z0,{:x},2", address & !1).as_bytes())
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        self.expect_ok(format!("z0,{:x},2", address & !1).as_bytes())
+    }
+
+    /// Set a breakpoint on a function by symbol name
+    pub fn break_at(&mut self, symbols: &SymbolTable, name: &str) -> Result<u64, Error> {
+        let symbol = symbols
+            .lookup(name)
+            .ok_or_else(|| Error::Symbol(name.to_string()))?;
+        self.set_breakpoint(symbol.address)?;
+        Ok(symbol.address & !1)
+    }
+
+    /// Detach, leaving the target running
+    pub fn detach(mut self) -> Result<(), Error> {
+        self.expect_ok(b"D")
+    }
+
+    /// Send a packet and return its reply
+    pub fn request(&mut self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        self.send(data)?;
+        let reply = self.read_packet(Instant::now() + self.timeout)?;
+        if reply.len() == 3 && reply[0] == b'E' {
+            let code = packet::from_hex(&reply[1..]).map(|b| b[0]).unwrap_or(0);
+            return Err(Error::Target(code));
+        }
+        Ok(reply)
+    }
+
+    fn expect_ok(&mut self, data: &[u8]) -> Result<(), Error> {
+        let reply = self.request(data)?;
+        match reply.as_slice() {
+            b"OK" => Ok(()),
+            b"" => Err(Error::Unsupported(
+                String::from_utf8_lossy(data).into_owned(),
+            )),
+            other => Err(Error::Protocol(format!(
+                "expected OK, got `{}`",
+                String::from_utf8_lossy(other)
+            ))),
+        }
+    }
+
+    /// Send a packet, retransmitting until the stub acknowledges it
+    fn send(&mut self, data: &[u8]) -> Result<(), Error> {
+        let framed = packet::encode(data);
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            self.stream.write_all(&framed)?;
+            match self.read_byte(deadline)? {
+                b'+' => return Ok(()),
+                b'-' => continue,
+                // No-ack mode or an unsolicited packet: leave it for the reader
+                other => {
+                    self.pending.push_front(other);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Read one packet, acknowledge it and return its decoded data
+    fn read_packet(&mut self, deadline: Instant) -> Result<Vec<u8>, Error> {
+        loop {
+            // Skip acks and noise up to the start of a packet
+            while self.read_byte(deadline)? != b'$' {}
+
+            let mut raw = Vec::new();
+            loop {
+                match self.read_byte(deadline)? {
+                    b'#' => break,
+                    byte => raw.push(byte),
+                }
+            }
+            let sum = [self.read_byte(deadline)?, self.read_byte(deadline)?];
+            let expected = packet::from_hex(&sum)?[0];
+
+            if packet::checksum(&raw) == expected {
+                self.stream.write_all(b"+")?;
+                return packet::decode(&raw);
+            }
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    fn read_byte(&mut self, deadline: Instant) -> Result<u8, Error> {
+        if let Some(byte) = self.pending.pop_front() {
+            return Ok(byte);
+        }
+        let mut buf = [0u8; 1024];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::Timeout);
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(Error::Connect("connection closed".into())),
+                Ok(n) => {
+                    self.pending.extend(&buf[1..n]);
+                    return Ok(buf[0]);
+                }
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+fn parse_stop(reply: &[u8]) -> Result<StopReason, Error> {
+    let code = || {
+        reply
+            .get(1..3)
+            .and_then(|hex| packet::from_hex(hex).ok())
+            .map(|b| b[0])
+            .ok_or_else(|| Error::Protocol("short stop reply".into()))
+    };
+    match reply.first() {
+        Some(b'S' | b'T') => Ok(StopReason::Signal(code()?)),
+        Some(b'W') => Ok(StopReason::Exited(code()?)),
+        Some(b'X') => Ok(StopReason::Terminated(code()?)),
+        _ => Err(Error::Protocol(format!(
+            "unexpected stop reply `{}`",
+            String::from_utf8_lossy(reply)
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    const RAM: u64 = 0x2000_0000;
+    const MEMORY: [u8; 16] = [
+        0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xAA, 0xBB, 0xCC, 0xDD, 0xEE,
+        0xFF,
+    ];
+
+    /// A stub serving one client on a local port, with [`MEMORY`] at [`RAM`]
+    ///
+    /// Like Renode's, it answers Ctrl-C only while the target runs. Returns
+    /// the address and a handle yielding the commands it received, with
+    /// Ctrl-C as `^C`.
+    fn stub(mut running: bool) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let handle = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut log = Vec::new();
+            let mut byte = [0u8];
+            let mut next = |stream: &mut TcpStream| stream.read_exact(&mut byte).map(|()| byte[0]);
+            while let Ok(first) = next(&mut stream) {
+                let reply = match first {
+                    INTERRUPT => {
+                        log.push("^C".to_string());
+                        if !running {
+                            continue;
+                        }
+                        running = false;
+                        "S05".to_string()
+                    }
+                    b'$' => {
+                        let mut data = Vec::new();
+                        loop {
+                            match next(&mut stream).unwrap() {
+                                b'#' => break,
+                                other => data.push(other),
+                            }
+                        }
+                        next(&mut stream).unwrap();
+                        next(&mut stream).unwrap();
+                        stream.write_all(b"+").unwrap();
+                        let command = String::from_utf8(data).unwrap();
+                        log.push(command.clone());
+                        match command.as_bytes()[0] {
+                            b'?' => "S05".to_string(),
+                            b'c' => {
+                                running = true;
+                                continue;
+                            }
+                            b'm' => {
+                                let (address, len) = command[1..].split_once(',').unwrap();
+                                let start =
+                                    (u64::from_str_radix(address, 16).unwrap() - RAM) as usize;
+                                let len = usize::from_str_radix(len, 16).unwrap();
+                                packet::to_hex(&MEMORY[start..start + len])
+                            }
+                            b'D' => {
+                                stream.write_all(&packet::encode(b"OK")).unwrap();
+                                break;
+                            }
+                            _ => String::new(),
+                        }
+                    }
+                    // Acks
+                    _ => continue,
+                };
+                stream.write_all(&packet::encode(reply.as_bytes())).unwrap();
+            }
+            log
+        });
+        (address, handle)
+    }
+
+    fn connect(address: &str) -> GdbClient {
+        let mut gdb = GdbClient::connect(address, Duration::from_secs(5)).unwrap();
+        gdb.set_timeout(Duration::from_secs(5));
+        gdb
+    }
+
+    #[test]
+    fn halts_a_running_target_and_reads_memory() {
+        let (address, stub) = stub(true);
+        let mut gdb = connect(&address);
+        assert_eq!(gdb.halt().unwrap(), StopReason::Signal(5));
+
+        // Already stopped: answered at once, without another Ctrl-C
+        let start = Instant::now();
+        assert_eq!(gdb.halt().unwrap(), StopReason::Signal(5));
+        assert!(start.elapsed() < ATTACH_HALT_TIMEOUT);
+
+        assert_eq!(gdb.read_memory(RAM + 4, 8).unwrap(), MEMORY[4..12]);
+        gdb.resume().unwrap();
+        assert_eq!(gdb.halt().unwrap(), StopReason::Signal(5));
+        gdb.detach().unwrap();
+
+        assert_eq!(
+            stub.join().unwrap(),
+            [
+                "qSupported:swbreak+;hwbreak+",
+                "^C",
+                "?",
+                "m20000004,8",
+                "c",
+                "^C",
+                "D"
+            ]
+        );
+    }
+
+    #[test]
+    fn first_halt_of_a_stopped_target_asks_the_stub() {
+        let (address, stub) = stub(false);
+        let mut gdb = connect(&address);
+        let start = Instant::now();
+        assert_eq!(gdb.halt().unwrap(), StopReason::Signal(5));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(gdb.read_memory(RAM, 16).unwrap(), MEMORY);
+        gdb.detach().unwrap();
+
+        assert_eq!(
+            stub.join().unwrap(),
+            [
+                "qSupported:swbreak+;hwbreak+",
+                "^C",
+                "?",
+                "m20000000,10",
+                "D"
+            ]
+        );
+    }
+
+    #[test]
+    fn stop_replies() {
+        assert!(matches!(parse_stop(b"S05"), Ok(StopReason::Signal(5))));
+        assert!(matches!(
+            parse_stop(b"T0b0f:00080008;"),
+            Ok(StopReason::Signal(0x0b))
+        ));
+        assert!(matches!(parse_stop(b"W00"), Ok(StopReason::Exited(0))));
+        assert!(matches!(parse_stop(b"X09"), Ok(StopReason::Terminated(9))));
+        assert!(matches!(parse_stop(b"S0"), Err(Error::Protocol(_))));
+        assert!(matches!(parse_stop(b"OK"), Err(Error::Protocol(_))));
+        assert!(matches!(parse_stop(b""), Err(Error::Protocol(_))));
+    }
+}
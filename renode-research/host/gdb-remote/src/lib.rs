@@ -0,0 +1,70 @@
+//! GDB Remote Serial Protocol client for inspecting emulated firmware
+//!
+//! Every `renode-config.resc` and generated suite starts `machine
+//! StartGdbServer $gdb_port`, 3333 unless the script or the runner's
+//! `${GDB_PORT}` sets another port. This crate talks to that server
+//! directly so host tests can halt the target and assert on registers and
+//! memory, resolving statics from the ELF:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use firmware_elf::SymbolTable;
+//! use gdb_remote::GdbClient;
+//!
+//! let symbols = SymbolTable::from_elf("target/thumbv7em-none-eabihf/release/stm32f3-dma")?;
+//! let mut gdb = GdbClient::connect("127.0.0.1:3333", Duration::from_secs(5))?;
+//! gdb.halt()?;
+//! let dst = gdb.read_symbol(&symbols, "DST_BUFFER")?;
+//! assert_eq!(dst[1], 0x11);
+//! let pc = gdb.read_registers()?.pc();
+//! gdb.resume()?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fmt;
+
+pub mod client;
+pub mod packet;
+
+pub use client::{GdbClient, Registers, StopReason};
+
+/// Errors talking to a GDB stub
+#[derive(Debug)]
+pub enum Error {
+    /// Socket I/O failed
+    Io(std::io::Error),
+    /// The server could not be reached or closed the connection
+    Connect(String),
+    /// No reply arrived in time
+    Timeout,
+    /// A malformed packet or unexpected reply
+    Protocol(String),
+    /// The stub answered with an `Exx` error code
+    Target(u8),
+    /// The stub does not implement a request (empty reply)
+    Unsupported(String),
+    /// The symbol is not in the ELF
+    Symbol(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Connect(msg) => write!(f, "{}", msg),
+            Error::Timeout => write!(f, "timed out waiting for the GDB server"),
+            Error::Protocol(msg) => write!(f, "protocol error: {}", msg),
+            Error::Target(code) => write!(f, "target error E{:02x}", code),
+            Error::Unsupported(request) => write!(f, "`{}` not supported by the server", request),
+            Error::Symbol(name) => write!(f, "no symbol `{}` in the ELF", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,157 @@
+//! GDB Remote Serial Protocol packet framing
+//!
+//! Packets are `$<data>#<checksum>` where the checksum is the modulo-256
+//! sum of the data bytes as two hex digits. Within data, `#`, `$`, `}` and
+//! `*` are escaped as `}` followed by the byte XOR 0x20, and replies may use
+//! run-length encoding (`<byte>*<count + 29>`).
+
+use crate::Error;
+
+/// Frame `data` as a packet
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(data.len());
+    for &byte in data {
+        if matches!(byte, b'#' | b'$' | b'}' | b'*') {
+            body.push(b'}');
+            body.push(byte ^ 0x20);
+        } else {
+            body.push(byte);
+        }
+    }
+
+    let mut packet = Vec::with_capacity(body.len() + 4);
+    packet.push(b'$');
+    packet.extend_from_slice(&body);
+    packet.push(b'#');
+    packet.extend_from_slice(format!("{:02x}", checksum(&body)).as_bytes());
+    packet
+}
+
+/// Checksum of raw (still escaped) packet data
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+/// Undo escaping and run-length encoding in received packet data
+pub fn decode(data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            b'}' => {
+                let byte = *data
+                    .get(i + 1)
+                    .ok_or_else(|| Error::Protocol("truncated escape".into()))?;
+                out.push(byte ^ 0x20);
+                i += 2;
+            }
+            b'*' => {
+                let count = *data
+                    .get(i + 1)
+                    .ok_or_else(|| Error::Protocol("truncated run-length".into()))?;
+                let previous = *out
+                    .last()
+                    .ok_or_else(|| Error::Protocol("run-length without a byte".into()))?;
+                let repeat = usize::from(count)
+                    .checked_sub(29)
+                    .ok_or_else(|| Error::Protocol("bad run-length count".into()))?;
+                out.extend(std::iter::repeat_n(previous, repeat));
+                i += 2;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Lower-case hex encoding
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decode a hex string (as in `m` and `g` replies)
+pub fn from_hex(text: &[u8]) -> Result<Vec<u8>, Error> {
+    if !text.len().is_multiple_of(2) {
+        return Err(Error::Protocol("odd-length hex data".into()));
+    }
+    text.chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .ok_or_else(|| {
+                    Error::Protocol(format!("bad hex `{}`", String::from_utf8_lossy(pair)))
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn protocol_error(result: Result<Vec<u8>, Error>) -> String {
+        match result {
+            Err(Error::Protocol(msg)) => msg,
+            other => panic!("expected a protocol error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn checksum_is_the_byte_sum() {
+        assert_eq!(checksum(b""), 0);
+        // `$OK#9a` from the GDB manual
+        assert_eq!(checksum(b"OK"), 0x9a);
+        assert_eq!(checksum(&[0xff, 0x02]), 0x01);
+    }
+
+    #[test]
+    fn encode_frames_and_escapes() {
+        assert_eq!(encode(b"g"), b"$g#67");
+        assert_eq!(encode(b""), b"$#00");
+        // The checksum covers the escaped bytes
+        assert_eq!(encode(b"a#b"), b"$a}\x03b#43");
+        assert_eq!(encode(b"$}*"), b"$}\x04}]}\x0a#e2");
+    }
+
+    #[test]
+    fn decode_undoes_encode() {
+        let data: Vec<u8> = (0..=255).collect();
+        let packet = encode(&data);
+        let body = &packet[1..packet.len() - 3];
+        assert_eq!(decode(body).unwrap(), data);
+        assert_eq!(
+            format!("{:02x}", checksum(body)).as_bytes(),
+            &packet[packet.len() - 2..]
+        );
+    }
+
+    #[test]
+    fn decode_run_length() {
+        // `0* ` is `0` and 3 more: ' ' is 32 = 3 + 29
+        assert_eq!(decode(b"0* ").unwrap(), b"0000");
+        assert_eq!(decode(b"ab*\x1d").unwrap(), b"ab");
+        assert_eq!(decode(b"x*~y").unwrap().len(), 1 + 97 + 1);
+        // The repeated byte is the decoded one, not the escape
+        assert_eq!(decode(b"}\x03*!").unwrap(), b"#####");
+    }
+
+    #[test]
+    fn decode_rejects_truncated_and_bad_sequences() {
+        assert_eq!(protocol_error(decode(b"ab}")), "truncated escape");
+        assert_eq!(protocol_error(decode(b"a*")), "truncated run-length");
+        assert_eq!(protocol_error(decode(b"*!")), "run-length without a byte");
+        assert_eq!(protocol_error(decode(b"a*\x1c")), "bad run-length count");
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(to_hex(&[0x00, 0xab, 0x7f]), "00ab7f");
+        assert_eq!(from_hex(b"00AB7f").unwrap(), [0x00, 0xab, 0x7f]);
+        assert_eq!(protocol_error(from_hex(b"abc")), "odd-length hex data");
+        assert_eq!(protocol_error(from_hex(b"0g")), "bad hex `0g`");
+    }
+}
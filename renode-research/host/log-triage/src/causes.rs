@@ -0,0 +1,213 @@
+//! Likely causes behind a log's warnings
+//!
+//! Known gaps in the Renode models this platform uses come first, with the
+//! consequence spelled out; anything else gets a generic reading of what an
+//! unimplemented access does to the firmware.
+
+use crate::log::Access;
+use crate::triage::{Group, Kind, Triage};
+
+/// A suggested explanation for a failure
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cause {
+    /// Group the cause was derived from (`DMA1`); empty for the whole log
+    pub peripheral: String,
+    pub text: String,
+    /// Expected in every run, so unlikely to explain a failure
+    pub benign: bool,
+}
+
+/// Causes for every group in `triage`, likely ones first
+pub fn causes(triage: &Triage) -> Vec<Cause> {
+    let mut causes: Vec<Cause> = triage.groups.iter().flat_map(group_causes).collect();
+    if causes.iter().all(|cause| cause.benign) {
+        causes.push(Cause {
+            peripheral: String::new(),
+            text: "No peripheral model complained: compare the UART output with the \
+                   expected line, and check whether the firmware is just slower than \
+                   the test's timeout under emulation"
+                .into(),
+            benign: false,
+        });
+    }
+    causes.sort_by_key(|cause| cause.benign);
+    causes
+}
+
+fn group_causes(group: &Group) -> Vec<Cause> {
+    let cause = |text: String, benign: bool| Cause {
+        peripheral: group.name.clone(),
+        text,
+        benign,
+    };
+    let source = group.source.to_ascii_lowercase();
+    let unhandled = group.has(|k| *k == Kind::Unhandled);
+    let width = group.findings.iter().find_map(|f| match &f.kind {
+        Kind::Width(width) => Some(width.clone()),
+        _ => None,
+    });
+    let configuration = group
+        .messages
+        .iter()
+        .any(|(m, _)| m.contains("configuration register"));
+
+    if source.starts_with("dma") && (unhandled || configuration) {
+        return vec![cause(
+            "DMA model does not copy data: Renode's STM32DMA decodes the F4 stream \
+             layout, so the F3 channel registers (CCRx, CNDTRx, CPARx, CMARx) and IFCR \
+             are ignored and the transfer never starts or completes"
+                .into(),
+            false,
+        )];
+    }
+    if source.starts_with("adc") && unhandled {
+        return vec![cause(
+            "ADC model is Renode's STM32F0_ADC: the F3-only registers (CFGR, SQRx, \
+             SMPRx, calibration in CR) are missing, so calibration or sequence setup \
+             can wait forever or convert the wrong channel"
+                .into(),
+            false,
+        )];
+    }
+    if source == "iwdg" && width.is_some() {
+        return vec![cause(
+            "IWDG model only accepts 16-bit accesses: the firmware's 32-bit KR/PR/RLR \
+             writes are dropped, so the watchdog is never unlocked, configured or fed"
+                .into(),
+            false,
+        )];
+    }
+
+    let mut causes = Vec::new();
+    if let Some(width) = width {
+        causes.push(cause(
+            format!(
+                "{} model rejects {} accesses: those register writes never take effect",
+                group.name, width
+            ),
+            false,
+        ));
+    }
+    if group.has(|k| *k == Kind::NotModelled) {
+        let (text, benign) = match source.as_str() {
+            "flash_interface" | "flash" => (
+                "Flash interface is not modelled: the wait-state (ACR) setup is ignored, \
+                 which is harmless under emulation"
+                    .to_string(),
+                true,
+            ),
+            "pwr" => (
+                "PWR is not modelled: setting DBP has no effect, so backup-domain \
+                 (RTC, BDCR) writes may never take effect"
+                    .to_string(),
+                false,
+            ),
+            _ => (
+                format!(
+                    "{} is not modelled: reads return 0, so polling a ready flag there \
+                     never finishes",
+                    group.name
+                ),
+                false,
+            ),
+        };
+        causes.push(cause(text, benign));
+    }
+    if unhandled {
+        let reads = group.unhandled(Access::Read);
+        if !reads.is_empty() {
+            causes.push(cause(
+                format!(
+                    "{} model does not implement {}: reads return 0, so code waiting \
+                     on a flag there can hang",
+                    group.name,
+                    reads.join(", ")
+                ),
+                false,
+            ));
+        }
+        let writes = group.unhandled(Access::Write);
+        if !writes.is_empty() {
+            causes.push(cause(
+                format!(
+                    "{} model ignores writes to {}: that configuration has no effect",
+                    group.name,
+                    writes.join(", ")
+                ),
+                false,
+            ));
+        }
+    }
+    causes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::parse;
+
+    fn causes_of(log: &str) -> Vec<Cause> {
+        causes(&Triage::new(log.lines().filter_map(parse), None))
+    }
+
+    #[test]
+    fn known_model_gaps() {
+        let causes = causes_of("[WARNING] dma1: Unhandled read from offset 0x8.");
+        assert_eq!(causes.len(), 1);
+        assert!(causes[0].text.starts_with("DMA model does not copy data"));
+        assert_eq!(causes[0].peripheral, "dma1");
+
+        let causes = causes_of("[WARNING] adc1: Unhandled read from offset 0xC.");
+        assert!(causes[0]
+            .text
+            .starts_with("ADC model is Renode's STM32F0_ADC"));
+
+        let causes = causes_of(
+            "[WARNING] iwdg: Attempted Word write isn't supported by the peripheral. Offset 0x0, value 0xAAAA.",
+        );
+        assert!(causes[0].text.starts_with("IWDG model only accepts 16-bit"));
+    }
+
+    #[test]
+    fn generic_causes_name_the_registers() {
+        let causes = causes_of(
+            "[WARNING] rtc: Unhandled read from offset 0xC.
+[WARNING] rtc: Unhandled write to offset 0x24, value 0x1.
+[WARNING] spi1: Attempted Byte write isn't supported by the peripheral. Offset 0xC, value 0x1.",
+        );
+        let texts: Vec<&str> = causes.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "rtc model does not implement +0xC: reads return 0, so code waiting on a flag there can hang",
+                "rtc model ignores writes to +0x24: that configuration has no effect",
+                "spi1 model rejects Byte accesses: those register writes never take effect",
+            ]
+        );
+    }
+
+    #[test]
+    fn benign_causes_go_last() {
+        let causes = causes_of(
+            "[WARNING] sysbus: (tag: 'FLASH_INTERFACE') WriteDoubleWord to non existing peripheral at 0x40022000, value 0x12.
+[WARNING] sysbus: (tag: 'PWR') WriteDoubleWord to non existing peripheral at 0x40007000, value 0x100.",
+        );
+        let benign: Vec<(&str, bool)> = causes
+            .iter()
+            .map(|c| (c.peripheral.as_str(), c.benign))
+            .collect();
+        assert_eq!(benign, [("PWR", false), ("FLASH_INTERFACE", true)]);
+    }
+
+    #[test]
+    fn quiet_logs_point_at_the_output() {
+        let causes = causes_of(
+            "[WARNING] sysbus: (tag: 'FLASH_INTERFACE') WriteDoubleWord to non existing peripheral at 0x40022000, value 0x12.",
+        );
+        assert_eq!(causes.len(), 2);
+        assert!(causes[0].text.starts_with("No peripheral model complained"));
+        assert!(causes[0].peripheral.is_empty());
+        assert!(causes[1].benign);
+        assert_eq!(causes_of("").len(), 1);
+    }
+}
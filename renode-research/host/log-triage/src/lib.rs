@@ -0,0 +1,65 @@
+//! Triage of Renode failure logs
+//!
+//! Robot's `Create Log On Failure` (and `renode-runner --log-dir`) keep
+//! Renode's log, where the peripheral models report every access they do
+//! not implement among the UART output. This crate parses those lines
+//! ([`log`]), groups them by peripheral and register, naming registers
+//! from the STM32F303 SVD when one is given ([`svd`], [`triage`]), and
+//! turns them into likely causes ([`causes`]):
+//!
+//! ```
+//! use log_triage::{causes, log, Triage};
+//!
+//! let text = "\
+//! 20:43:37.5728 [WARNING] dma1: Unhandled write to offset 0x4, value 0xF.
+//! 20:43:37.5728 [WARNING] dma1: Unhandled read from offset 0xC.";
+//! let triage = Triage::new(text.lines().filter_map(log::parse), None);
+//! assert_eq!(triage.groups[0].findings.len(), 2);
+//! assert!(causes::causes(&triage)[0].text.starts_with("DMA model does not copy data"));
+//! ```
+
+use std::fmt;
+
+pub mod causes;
+pub mod log;
+pub mod svd;
+pub mod triage;
+
+pub use causes::Cause;
+pub use svd::Device;
+pub use triage::{Finding, Group, Kind, Triage};
+
+/// Errors reading logs or the SVD
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read
+    Io(std::io::Error),
+    /// The SVD is not well-formed XML
+    Xml(roxmltree::Error),
+    /// The SVD is missing required elements
+    Svd(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "read failed: {}", err),
+            Error::Xml(err) => write!(f, "invalid XML: {}", err),
+            Error::Svd(msg) => write!(f, "invalid SVD: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(err: roxmltree::Error) -> Self {
+        Error::Xml(err)
+    }
+}
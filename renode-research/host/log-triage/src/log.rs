@@ -0,0 +1,308 @@
+//! Renode log lines
+//!
+//! Lines look like `20:43:37.5728 [WARNING] dma1: Unhandled read from offset
+//! 0xC.`: a timestamp, a level, the emitting peripheral and a message,
+//! optionally prefixed with the PC (`[cpu: 0x8000506]`) and suffixed with a
+//! repeat count (`(2)`). The messages the triage cares about are the ones
+//! a peripheral model logs for register accesses it does not implement.
+
+/// Direction of a bus access
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// What a log line says
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// `Unhandled read from offset 0xC.` or
+    /// `Unhandled write to offset 0x4, value 0xF.`
+    Unhandled {
+        access: Access,
+        offset: u64,
+        value: Option<u64>,
+    },
+    /// `Attempted Word write isn't supported by the peripheral. Offset 0x0,
+    /// value 0xAAAA.`
+    Unsupported {
+        access: Access,
+        width: String,
+        offset: u64,
+        value: Option<u64>,
+    },
+    /// From `sysbus`: `(tag: 'PWR') ReadDoubleWord from non existing
+    /// peripheral at 0x40007000, returning 0x00000000.`
+    NotModelled {
+        tag: Option<String>,
+        access: Access,
+        address: u64,
+        value: Option<u64>,
+    },
+    /// Any other warning or error
+    Message(String),
+    /// Output of a UART analyzer
+    Uart(String),
+}
+
+/// One parsed line
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Emitting peripheral or subsystem (`dma1`, `sysbus`, `cpu`)
+    pub source: String,
+    pub pc: Option<u64>,
+    pub event: Event,
+    /// Times Renode logged the line (it folds repeats into `(n)`)
+    pub repeat: u32,
+}
+
+/// Parse one log line; `None` for lines of no interest (info chatter,
+/// blank lines, anything that is not a Renode log line)
+///
+/// ```
+/// use log_triage::log::{parse, Access, Event};
+///
+/// let entry = parse("20:43:37.5728 [WARNING] dma1: Unhandled write to offset 0x4, value 0xF.").unwrap();
+/// assert_eq!(entry.source, "dma1");
+/// assert_eq!(
+///     entry.event,
+///     Event::Unhandled { access: Access::Write, offset: 4, value: Some(0xF) }
+/// );
+///
+/// let entry = parse("[WARNING] iwdg: [cpu: 0x8000468] Attempted Word write isn't supported by the peripheral. Offset 0x0, value 0xAAAA. (2)").unwrap();
+/// assert_eq!((entry.pc, entry.repeat), (Some(0x800_0468), 2));
+/// ```
+pub fn parse(line: &str) -> Option<Entry> {
+    let line = line.trim();
+    let start = line.find('[')?;
+    let (level, rest) = line[start + 1..].split_once(']')?;
+    let (source, message) = rest.trim_start().split_once(": ")?;
+    if source.contains(' ') {
+        return None;
+    }
+
+    let mut message = message.trim();
+    let mut pc = None;
+    if let Some(rest) = message.strip_prefix("[cpu: ") {
+        let (address, rest) = rest.split_once(']')?;
+        pc = hex(address);
+        message = rest.trim_start();
+    }
+    let mut repeat = 1;
+    if let Some((text, count)) = message.strip_suffix(')').and_then(|m| m.rsplit_once(" (")) {
+        if let Ok(count) = count.parse() {
+            repeat = count;
+            message = text;
+        }
+    }
+
+    let event = match level {
+        "WARNING" | "ERROR" => warning(source, message),
+        "INFO" | "NOISY" if is_uart(source) => Event::Uart(message.to_string()),
+        _ => return None,
+    };
+    Some(Entry {
+        source: source.to_string(),
+        pc,
+        event,
+        repeat,
+    })
+}
+
+fn warning(source: &str, message: &str) -> Event {
+    if let Some(rest) = message.strip_prefix("Unhandled ") {
+        if let Some(offset) = after(rest, "offset ").and_then(hex) {
+            return Event::Unhandled {
+                access: access_word(rest),
+                offset,
+                value: after(rest, "value ").and_then(hex),
+            };
+        }
+    }
+    if let Some(rest) = message.strip_prefix("Attempted ") {
+        if let Some((width, rest)) = rest.split_once(' ') {
+            if let Some(offset) = after(rest, "Offset ").and_then(hex) {
+                return Event::Unsupported {
+                    access: access_word(rest),
+                    width: width.to_string(),
+                    offset,
+                    value: after(rest, "value ").and_then(hex),
+                };
+            }
+        }
+    }
+    if source == "sysbus" {
+        if let Some(address) = after(message, "non existing peripheral at ").and_then(hex) {
+            let tag = after(message, "(tag: '")
+                .and_then(|rest| rest.split_once('\''))
+                .map(|(tag, _)| tag.to_string());
+            let access = if message.contains("Read") {
+                Access::Read
+            } else {
+                Access::Write
+            };
+            let value = match access {
+                Access::Read => None,
+                Access::Write => after(message, "value ").and_then(hex),
+            };
+            return Event::NotModelled {
+                tag,
+                access,
+                address,
+                value,
+            };
+        }
+    }
+    Event::Message(message.to_string())
+}
+
+/// `read …` or `write …`
+fn access_word(text: &str) -> Access {
+    if text.starts_with("read") {
+        Access::Read
+    } else {
+        Access::Write
+    }
+}
+
+fn is_uart(source: &str) -> bool {
+    source.starts_with("usart") || source.starts_with("uart")
+}
+
+/// The text after the first `marker`
+fn after<'a>(text: &'a str, marker: &str) -> Option<&'a str> {
+    text.find(marker).map(|at| &text[at + marker.len()..])
+}
+
+/// A leading `0x…` number, ignoring what follows (`0xC.`, `0x4,`)
+fn hex(text: &str) -> Option<u64> {
+    let digits = text.trim().strip_prefix("0x")?;
+    let end = digits
+        .find(|c: char| !c.is_ascii_hexdigit())
+        .unwrap_or(digits.len());
+    u64::from_str_radix(&digits[..end], 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unhandled_reads_and_writes() {
+        let entry = parse("20:43:37.5728 [WARNING] dma1: Unhandled read from offset 0xC.").unwrap();
+        assert_eq!(entry.source, "dma1");
+        assert_eq!((entry.pc, entry.repeat), (None, 1));
+        assert_eq!(
+            entry.event,
+            Event::Unhandled {
+                access: Access::Read,
+                offset: 0xC,
+                value: None
+            }
+        );
+
+        let entry =
+            parse("[ERROR] adc1: Unhandled write to offset 0x10, value 0x80000000.").unwrap();
+        assert_eq!(
+            entry.event,
+            Event::Unhandled {
+                access: Access::Write,
+                offset: 0x10,
+                value: Some(0x8000_0000)
+            }
+        );
+    }
+
+    #[test]
+    fn unsupported_widths() {
+        let entry = parse(
+            "20:43:37.5728 [WARNING] iwdg: Attempted Byte read isn't supported by the peripheral. Offset 0xC.",
+        )
+        .unwrap();
+        assert_eq!(
+            entry.event,
+            Event::Unsupported {
+                access: Access::Read,
+                width: "Byte".into(),
+                offset: 0xC,
+                value: None
+            }
+        );
+    }
+
+    #[test]
+    fn accesses_to_missing_peripherals() {
+        let entry = parse(
+            "[WARNING] sysbus: [cpu: 0x8000240] (tag: 'PWR') ReadDoubleWord from non existing peripheral at 0x40007000, returning 0x00000000.",
+        )
+        .unwrap();
+        assert_eq!(entry.pc, Some(0x800_0240));
+        assert_eq!(
+            entry.event,
+            Event::NotModelled {
+                tag: Some("PWR".into()),
+                access: Access::Read,
+                address: 0x4000_7000,
+                value: None
+            }
+        );
+
+        let entry = parse(
+            "[WARNING] sysbus: WriteDoubleWord to non existing peripheral at 0x40022000, value 0x12.",
+        )
+        .unwrap();
+        assert_eq!(
+            entry.event,
+            Event::NotModelled {
+                tag: None,
+                access: Access::Write,
+                address: 0x4002_2000,
+                value: Some(0x12)
+            }
+        );
+    }
+
+    #[test]
+    fn other_warnings_keep_their_message() {
+        let entry = parse("[WARNING] cpu: Guest is in a loop (3)").unwrap();
+        assert_eq!(entry.event, Event::Message("Guest is in a loop".into()));
+        assert_eq!(entry.repeat, 3);
+        // A parenthesised word is part of the message, not a count
+        let entry = parse("[WARNING] rtc: Clock stopped (LSE)").unwrap();
+        assert_eq!(entry.event, Event::Message("Clock stopped (LSE)".into()));
+        assert_eq!(entry.repeat, 1);
+        // Unhandled without an offset is just a message
+        let entry = parse("[WARNING] dma1: Unhandled interrupt").unwrap();
+        assert_eq!(entry.event, Event::Message("Unhandled interrupt".into()));
+    }
+
+    #[test]
+    fn uart_output_only_from_uarts() {
+        let entry =
+            parse("20:43:37.6000 [INFO] usart1: [host: 0.2s (tag: 1)] DMA TEST PASSED").unwrap();
+        assert_eq!(
+            entry.event,
+            Event::Uart("[host: 0.2s (tag: 1)] DMA TEST PASSED".into())
+        );
+        assert!(parse("[INFO] uart4: hello").is_some());
+        assert!(parse("[INFO] sysbus: Loaded SVD").is_none());
+        assert!(parse("[DEBUG] dma1: Unhandled read from offset 0xC.").is_none());
+    }
+
+    #[test]
+    fn lines_that_are_not_log_lines() {
+        assert!(parse("").is_none());
+        assert!(parse("Renode, version 1.14.0").is_none());
+        assert!(parse("[WARNING] no source here").is_none());
+        assert!(parse("[WARNING] two words: message").is_none());
+        assert!(parse("[WARNING] cpu: [cpu: 0x800").is_none());
+    }
+
+    #[test]
+    fn hex_stops_at_the_first_non_digit() {
+        assert_eq!(hex("0xC."), Some(0xC));
+        assert_eq!(hex(" 0x4, value"), Some(4));
+        assert_eq!(hex("12"), None);
+        assert_eq!(hex("0x"), None);
+    }
+}
@@ -0,0 +1,235 @@
+//! Triage Renode failure logs
+//!
+//! Usage: `log-triage [OPTIONS] [PATH]...`
+//!
+//! `PATH` is a log file or a directory of `*.log` files; by default every
+//! `stm32f3-*/logs/` directory is read. For Robot's
+//! `<suite>.<Test_Name>.fail0.log` the test's failure message is taken from
+//! the crate's `robot_output.xml` when it has one.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use log_triage::{causes, log, svd, Device, Error, Kind, Triage};
+use test_report::{robot, Status, Suite};
+
+#[derive(Parser)]
+#[command(about = "Group Renode failure-log warnings by peripheral and suggest causes")]
+struct Args {
+    /// Log files or directories (default: stm32f3-*/logs)
+    paths: Vec<PathBuf>,
+
+    /// STM32F303 SVD for register names (default: the one `cargo xtask
+    /// svd` downloads to target/svd/STM32F303.svd, if it is there)
+    #[arg(long, env = "STM32F3_SVD")]
+    svd: Option<PathBuf>,
+
+    /// Firmware workspace root
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))]
+    root: PathBuf,
+
+    /// UART lines shown per log
+    #[arg(long, default_value_t = 5)]
+    uart: usize,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("log-triage: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Error> {
+    let root = args.root.canonicalize().unwrap_or(args.root.clone());
+    let svd_path = args.svd.clone().or_else(|| {
+        let path = root.join(svd::DEFAULT_PATH);
+        path.is_file().then_some(path)
+    });
+    let device = match &svd_path {
+        Some(path) => Some(Device::from_file(path).map_err(|err| match err {
+            Error::Io(err) => Error::Io(std::io::Error::new(
+                err.kind(),
+                format!("{}: {}", path.display(), err),
+            )),
+            other => other,
+        })?),
+        None => None,
+    };
+
+    let files = if args.paths.is_empty() {
+        default_logs(&root)?
+    } else {
+        let mut files = Vec::new();
+        for path in &args.paths {
+            if path.is_dir() {
+                files.extend(logs_in(path)?);
+            } else {
+                files.push(path.clone());
+            }
+        }
+        files
+    };
+
+    let mut tally: Vec<(String, Vec<String>)> = Vec::new();
+    for file in &files {
+        let text = fs::read_to_string(file)?;
+        let triage = Triage::new(text.lines().filter_map(log::parse), device.as_ref());
+        let causes = causes::causes(&triage);
+        let (krate, test) = describe(file);
+
+        println!("=== {}: {} ===", krate, test);
+        if let Some(message) = failure_message(file, &test) {
+            println!("  failure: {}", message);
+        }
+        for group in &triage.groups {
+            if group.name == group.source {
+                println!("  {}", group.name);
+            } else {
+                println!("  {} ({})", group.name, group.source);
+            }
+            for finding in &group.findings {
+                let access = match finding.access {
+                    log::Access::Read => "read",
+                    log::Access::Write => "write",
+                };
+                let kind = match &finding.kind {
+                    Kind::Unhandled => "unhandled".to_string(),
+                    Kind::Width(width) => format!("{} access refused", width),
+                    Kind::NotModelled => "not modelled".to_string(),
+                };
+                let address = finding
+                    .address
+                    .map(|a| format!("0x{:08X}", a))
+                    .unwrap_or_default();
+                let mut line = format!(
+                    "    {:<5} {:<12} {:<10} x{:<3} {}",
+                    access,
+                    finding.label(),
+                    address,
+                    finding.count,
+                    kind
+                );
+                if !finding.values.is_empty() {
+                    let values: Vec<String> = finding
+                        .values
+                        .iter()
+                        .map(|v| format!("0x{:X}", v))
+                        .collect();
+                    line.push_str(&format!("  values {}", values.join(", ")));
+                }
+                if let Some(pc) = finding.pc {
+                    line.push_str(&format!("  pc 0x{:08X}", pc));
+                }
+                println!("{}", line);
+            }
+            for (message, count) in &group.messages {
+                println!("    {} (x{})", message, count);
+            }
+        }
+        println!("  likely causes:");
+        for cause in &causes {
+            let note = if cause.benign { " (harmless)" } else { "" };
+            println!("    - {}{}", cause.text, note);
+        }
+        if args.uart > 0 && !triage.uart.is_empty() {
+            println!("  uart (last {}):", args.uart.min(triage.uart.len()));
+            let skip = triage.uart.len().saturating_sub(args.uart);
+            for line in &triage.uart[skip..] {
+                println!("    {}", line);
+            }
+        }
+        println!();
+
+        for cause in causes.into_iter().filter(|c| !c.benign) {
+            let label = format!("{}: {}", krate, test);
+            match tally.iter_mut().find(|(text, _)| *text == cause.text) {
+                Some((_, tests)) => tests.push(label),
+                None => tally.push((cause.text, vec![label])),
+            }
+        }
+    }
+
+    println!("=== {} log(s) ===", files.len());
+    tally.sort_by_key(|(_, tests)| std::cmp::Reverse(tests.len()));
+    for (text, tests) in &tally {
+        println!("{:>3} x {}", tests.len(), text);
+    }
+    if device.is_none() {
+        println!("(registers shown as offsets; run `cargo xtask svd` or give --svd for names)");
+    }
+    Ok(())
+}
+
+/// `*.log` in every `stm32f3-*/logs/` directory under `root`
+fn default_logs(root: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    let mut crates: Vec<PathBuf> = fs::read_dir(root)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("stm32f3-"))
+        })
+        .collect();
+    crates.sort();
+    for krate in crates {
+        let logs = krate.join("logs");
+        if logs.is_dir() {
+            files.extend(logs_in(&logs)?);
+        }
+    }
+    Ok(files)
+}
+
+fn logs_in(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Crate and test name for a log: `stm32f3-dma/logs/test-dma.Should_Complete_Second_Transfer.fail0.log`
+/// is test "Should Complete Second Transfer" of `stm32f3-dma`
+fn describe(file: &Path) -> (String, String) {
+    let krate = file
+        .canonicalize()
+        .ok()
+        .and_then(|path| {
+            path.ancestors()
+                .filter_map(|p| p.file_name()?.to_str().map(str::to_string))
+                .find(|name| name.starts_with("stm32f3-"))
+        })
+        .unwrap_or_else(|| "?".to_string());
+    let name = file
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let test = match name.split('.').collect::<Vec<_>>()[..] {
+        [_, test, _, "log"] => test.replace('_', " "),
+        _ => name,
+    };
+    (krate, test)
+}
+
+/// Failure message of `test` from `robot_output.xml` next to the log dir
+fn failure_message(file: &Path, test: &str) -> Option<String> {
+    let output = file.parent()?.parent()?.join("robot_output.xml");
+    let suites: Vec<Suite> = robot::load(&output).ok()?;
+    suites
+        .iter()
+        .flat_map(|suite| &suite.tests)
+        .find_map(|case| match &case.status {
+            Status::Failed(message) if case.name == test => Some(message.clone()),
+            _ => None,
+        })
+}
@@ -0,0 +1,370 @@
+//! Register names from a CMSIS-SVD file
+//!
+//! Reads the STM32F303 SVD that the `stm32f3` PAC is generated from, so
+//! addresses in the log get the same names the firmware uses
+//! (`DMA1.CH1.CR`, `IWDG.KR`). Handles `derivedFrom` peripherals and
+//! `dim` arrays of registers and clusters.
+
+use std::fs;
+use std::path::Path;
+
+use roxmltree::{Document, Node};
+
+use crate::Error;
+
+/// Where `cargo xtask svd` saves the SVD, relative to the firmware root;
+/// used when no other is given
+pub const DEFAULT_PATH: &str = "target/svd/STM32F303.svd";
+
+/// Address range assumed for a peripheral without an `addressBlock`
+const DEFAULT_BLOCK_SIZE: u64 = 0x400;
+
+/// A register, with the cluster path in its name (`CH1.CR`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Register {
+    pub name: String,
+    /// Offset from the peripheral base
+    pub offset: u64,
+    /// Width in bytes
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Peripheral {
+    pub name: String,
+    pub base: u64,
+    /// Bytes of address space the peripheral decodes
+    pub size: u64,
+    /// Sorted by offset
+    pub registers: Vec<Register>,
+}
+
+impl Peripheral {
+    /// The register covering `offset`
+    pub fn register(&self, offset: u64) -> Option<&Register> {
+        self.registers
+            .iter()
+            .find(|r| (r.offset..r.offset + r.size.max(1)).contains(&offset))
+    }
+}
+
+/// The peripherals of one device
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Device {
+    pub peripherals: Vec<Peripheral>,
+}
+
+impl Device {
+    pub fn from_file(path: &Path) -> Result<Device, Error> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Device, Error> {
+        let doc = Document::parse(text)?;
+        let root = doc.root_element();
+        let default_size = child_number(root, "size").unwrap_or(32) / 8;
+
+        let nodes: Vec<Node> = root
+            .descendants()
+            .filter(|n| n.has_tag_name("peripheral"))
+            .collect();
+        let mut peripherals = Vec::new();
+        for node in &nodes {
+            let name = child_text(*node, "name")
+                .ok_or_else(|| Error::Svd("peripheral without a name".into()))?;
+            let base = child_number(*node, "baseAddress")
+                .ok_or_else(|| Error::Svd(format!("{}: no baseAddress", name)))?;
+            // A derived peripheral inherits everything it does not redefine
+            let template = node.attribute("derivedFrom").and_then(|from| {
+                nodes
+                    .iter()
+                    .find(|n| child_text(**n, "name").as_deref() == Some(from))
+            });
+            let source = match template {
+                Some(template) if child(*node, "registers").is_none() => *template,
+                _ => *node,
+            };
+
+            let mut registers = Vec::new();
+            if let Some(list) = child(source, "registers") {
+                collect(list, "", 0, default_size, &mut registers);
+            }
+            registers.sort_by_key(|r| r.offset);
+            let size = child(source, "addressBlock")
+                .and_then(|block| child_number(block, "size"))
+                .unwrap_or(DEFAULT_BLOCK_SIZE);
+            peripherals.push(Peripheral {
+                name,
+                base,
+                size,
+                registers,
+            });
+        }
+        peripherals.sort_by_key(|p| p.base);
+        Ok(Device { peripherals })
+    }
+
+    /// A peripheral by name, ignoring case
+    pub fn peripheral(&self, name: &str) -> Option<&Peripheral> {
+        self.peripherals
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The peripheral decoding `address`
+    pub fn at(&self, address: u64) -> Option<&Peripheral> {
+        self.peripherals
+            .iter()
+            .find(|p| (p.base..p.base + p.size).contains(&address))
+    }
+}
+
+/// Registers under `node` (a `<registers>` or `<cluster>`), expanding arrays
+fn collect(node: Node, prefix: &str, base: u64, size: u64, out: &mut Vec<Register>) {
+    for item in node.children().filter(Node::is_element) {
+        let is_cluster = item.has_tag_name("cluster");
+        if !is_cluster && !item.has_tag_name("register") {
+            continue;
+        }
+        let Some(name) = child_text(item, "name") else {
+            continue;
+        };
+        let offset = base + child_number(item, "addressOffset").unwrap_or(0);
+        let size = child_number(item, "size").map_or(size, |bits| bits / 8);
+
+        for (index, element) in dim(item, &name).into_iter().enumerate() {
+            let step = child_number(item, "dimIncrement").unwrap_or(0);
+            let offset = offset + step * index as u64;
+            let name = format!("{}{}", prefix, element);
+            if is_cluster {
+                collect(item, &format!("{}.", name), offset, size, out);
+            } else {
+                out.push(Register { name, offset, size });
+            }
+        }
+    }
+}
+
+/// Names of the elements of a `dim` array, or just `name`
+fn dim(node: Node, name: &str) -> Vec<String> {
+    let Some(count) = child_number(node, "dim") else {
+        return vec![name.to_string()];
+    };
+    let indices: Vec<String> = match child_text(node, "dimIndex") {
+        Some(list) if list.contains('-') => {
+            let (first, last) = list.split_once('-').unwrap_or_default();
+            match (first.trim().parse::<u64>(), last.trim().parse::<u64>()) {
+                (Ok(first), Ok(last)) => (first..=last).map(|i| i.to_string()).collect(),
+                _ => (0..count).map(|i| i.to_string()).collect(),
+            }
+        }
+        Some(list) => list.split(',').map(|s| s.trim().to_string()).collect(),
+        None => (0..count).map(|i| i.to_string()).collect(),
+    };
+    indices
+        .iter()
+        .take(count as usize)
+        .map(|index| name.replace("[%s]", index).replace("%s", index))
+        .collect()
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, tag: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(tag))
+}
+
+fn child_text(node: Node, tag: &str) -> Option<String> {
+    child(node, tag)
+        .and_then(|n| n.text())
+        .map(|text| text.trim().to_string())
+}
+
+/// An SVD scaled non-negative integer: `0x40`, `64` or `#1000000`
+fn child_number(node: Node, tag: &str) -> Option<u64> {
+    let text = child_text(node, tag)?;
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = text.strip_prefix('#') {
+        u64::from_str_radix(binary, 2).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVD: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<device>
+  <name>STM32F303</name>
+  <size>0x20</size>
+  <peripherals>
+    <peripheral>
+      <name>IWDG</name>
+      <baseAddress>0x40003000</baseAddress>
+      <addressBlock><offset>0x0</offset><size>0x400</size></addressBlock>
+      <registers>
+        <register><name>PR</name><addressOffset>0x4</addressOffset></register>
+        <register><name>KR</name><addressOffset>0x0</addressOffset></register>
+        <register><name>SR</name><addressOffset>12</addressOffset><size>16</size></register>
+      </registers>
+    </peripheral>
+    <peripheral>
+      <name>DMA1</name>
+      <baseAddress>0x40020000</baseAddress>
+      <addressBlock><offset>0x0</offset><size>#10000000</size></addressBlock>
+      <registers>
+        <register><name>ISR</name><addressOffset>0x0</addressOffset></register>
+        <cluster>
+          <dim>2</dim>
+          <dimIncrement>0x14</dimIncrement>
+          <dimIndex>1-2</dimIndex>
+          <name>CH%s</name>
+          <addressOffset>0x8</addressOffset>
+          <register><name>CR</name><addressOffset>0x0</addressOffset></register>
+          <register><name>NDTR</name><addressOffset>0x4</addressOffset></register>
+        </cluster>
+      </registers>
+    </peripheral>
+    <peripheral derivedFrom="DMA1">
+      <name>DMA2</name>
+      <baseAddress>0x40020400</baseAddress>
+    </peripheral>
+    <peripheral>
+      <name>RTC</name>
+      <baseAddress>0x40002800</baseAddress>
+      <registers>
+        <register>
+          <dim>3</dim>
+          <dimIncrement>4</dimIncrement>
+          <dimIndex>A,B,C</dimIndex>
+          <name>BKP[%s]R</name>
+          <addressOffset>0x50</addressOffset>
+        </register>
+      </registers>
+    </peripheral>
+  </peripherals>
+</device>"#;
+
+    fn names(peripheral: &Peripheral) -> Vec<(&str, u64, u64)> {
+        peripheral
+            .registers
+            .iter()
+            .map(|r| (r.name.as_str(), r.offset, r.size))
+            .collect()
+    }
+
+    #[test]
+    fn peripherals_are_sorted_by_base() {
+        let device = Device::parse(SVD).unwrap();
+        let order: Vec<&str> = device.peripherals.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(order, ["RTC", "IWDG", "DMA1", "DMA2"]);
+    }
+
+    #[test]
+    fn registers_are_sorted_and_sized() {
+        let device = Device::parse(SVD).unwrap();
+        let iwdg = device.peripheral("iwdg").unwrap();
+        assert_eq!(names(iwdg), [("KR", 0, 4), ("PR", 4, 4), ("SR", 12, 2)]);
+    }
+
+    #[test]
+    fn clusters_expand_with_a_dim_index_range() {
+        let device = Device::parse(SVD).unwrap();
+        let dma = device.peripheral("DMA1").unwrap();
+        assert_eq!(
+            names(dma),
+            [
+                ("ISR", 0x0, 4),
+                ("CH1.CR", 0x8, 4),
+                ("CH1.NDTR", 0xC, 4),
+                ("CH2.CR", 0x1C, 4),
+                ("CH2.NDTR", 0x20, 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn register_arrays_expand_with_a_dim_index_list() {
+        let device = Device::parse(SVD).unwrap();
+        let rtc = device.peripheral("RTC").unwrap();
+        assert_eq!(
+            names(rtc),
+            [("BKPAR", 0x50, 4), ("BKPBR", 0x54, 4), ("BKPCR", 0x58, 4)]
+        );
+    }
+
+    #[test]
+    fn dim_without_an_index_counts_from_zero() {
+        let doc = Document::parse("<r><dim>2</dim><name>X%s</name></r>").unwrap();
+        assert_eq!(dim(doc.root_element(), "X%s"), ["X0", "X1"]);
+        let doc = Document::parse("<r><name>X</name></r>").unwrap();
+        assert_eq!(dim(doc.root_element(), "X"), ["X"]);
+    }
+
+    #[test]
+    fn derived_peripherals_take_the_template_registers() {
+        let device = Device::parse(SVD).unwrap();
+        let dma1 = device.peripheral("DMA1").unwrap();
+        let dma2 = device.peripheral("DMA2").unwrap();
+        assert_eq!(dma2.base, 0x4002_0400);
+        assert_eq!(dma2.registers, dma1.registers);
+        assert_eq!(dma2.size, dma1.size);
+    }
+
+    #[test]
+    fn numbers_are_hex_binary_or_decimal() {
+        let doc =
+            Document::parse("<n><a>0x1F</a><b>0X10</b><c>#101</c><d> 42 </d><e>x</e></n>").unwrap();
+        let node = doc.root_element();
+        assert_eq!(child_number(node, "a"), Some(0x1F));
+        assert_eq!(child_number(node, "b"), Some(0x10));
+        assert_eq!(child_number(node, "c"), Some(5));
+        assert_eq!(child_number(node, "d"), Some(42));
+        assert_eq!(child_number(node, "e"), None);
+        assert_eq!(child_number(node, "f"), None);
+    }
+
+    #[test]
+    fn addresses_resolve_to_peripherals_and_registers() {
+        let device = Device::parse(SVD).unwrap();
+        // DMA1's block is #10000000 = 128 bytes
+        assert_eq!(device.at(0x4002_007F).unwrap().name, "DMA1");
+        assert!(device.at(0x4002_0080).is_none());
+        assert_eq!(device.at(0x4002_0408).unwrap().name, "DMA2");
+        // RTC has no addressBlock
+        assert_eq!(device.at(0x4000_2800 + 0x3FF).unwrap().name, "RTC");
+        assert!(device.at(0x4000_2C00).is_none());
+
+        let iwdg = device.peripheral("IWDG").unwrap();
+        assert_eq!(iwdg.register(0x6).unwrap().name, "PR");
+        assert_eq!(iwdg.register(0xD).unwrap().name, "SR");
+        assert!(iwdg.register(0xE).is_none());
+        assert!(iwdg.register(0x8).is_none());
+    }
+
+    #[test]
+    fn register_size_defaults_to_the_device_size() {
+        let svd = "<device><size>16</size><peripherals><peripheral><name>P</name>\
+                   <baseAddress>0</baseAddress><registers><register><name>R</name>\
+                   </register></registers></peripheral></peripherals></device>";
+        let device = Device::parse(svd).unwrap();
+        assert_eq!(names(&device.peripherals[0]), [("R", 0, 2)]);
+    }
+
+    #[test]
+    fn missing_names_and_bases_are_errors() {
+        let err =
+            Device::parse("<device><peripheral><baseAddress>0</baseAddress></peripheral></device>")
+                .unwrap_err();
+        assert!(matches!(err, Error::Svd(msg) if msg == "peripheral without a name"));
+        let err =
+            Device::parse("<device><peripheral><name>P</name></peripheral></device>").unwrap_err();
+        assert!(matches!(err, Error::Svd(msg) if msg == "P: no baseAddress"));
+    }
+
+    #[test]
+    fn bad_xml_is_an_error() {
+        assert!(Device::parse("<device><peripheral>").is_err());
+    }
+}
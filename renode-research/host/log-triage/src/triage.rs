@@ -0,0 +1,396 @@
+//! Grouping a log's warnings by peripheral and register
+
+use std::collections::BTreeMap;
+
+use crate::log::{Access, Entry, Event};
+use crate::svd::Device;
+
+/// Written values kept per finding
+const MAX_VALUES: usize = 4;
+
+/// Why the model logged the access
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    /// The model has no such register
+    Unhandled,
+    /// The model refuses accesses of this width (`Word`, `Byte`)
+    Width(String),
+    /// Nothing is mapped at the address (a `Tag` at most)
+    NotModelled,
+}
+
+/// Accesses of one kind to one register
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub kind: Kind,
+    pub access: Access,
+    /// Offset from the peripheral base
+    pub offset: u64,
+    /// Absolute address, when the peripheral base is known
+    pub address: Option<u64>,
+    /// SVD register name (`CH1.CR`)
+    pub register: Option<String>,
+    pub count: u32,
+    /// First distinct values written
+    pub values: Vec<u64>,
+    /// PC of the first access, when logged
+    pub pc: Option<u64>,
+}
+
+/// Everything logged about one peripheral
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Group {
+    /// SVD name when known (`DMA1`), else the Renode name or tag
+    pub name: String,
+    /// Renode peripheral or `sysbus` tag (`dma1`, `FLASH_INTERFACE`)
+    pub source: String,
+    pub findings: Vec<Finding>,
+    /// Other warnings, with repeat counts
+    pub messages: Vec<(String, u32)>,
+}
+
+impl Group {
+    pub fn has(&self, kind: impl Fn(&Kind) -> bool) -> bool {
+        self.findings.iter().any(|f| kind(&f.kind))
+    }
+
+    /// Register names (or offsets) the model does not implement, by access
+    pub fn unhandled(&self, access: Access) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .findings
+            .iter()
+            .filter(|f| f.kind == Kind::Unhandled && f.access == access)
+            .map(Finding::label)
+            .collect();
+        names.dedup();
+        names
+    }
+}
+
+impl Finding {
+    /// Register name, or the offset when there is no SVD entry
+    pub fn label(&self) -> String {
+        self.register
+            .clone()
+            .unwrap_or_else(|| format!("+0x{:X}", self.offset))
+    }
+}
+
+/// The grouped contents of one log
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Triage {
+    /// In order of first appearance
+    pub groups: Vec<Group>,
+    /// UART analyzer output
+    pub uart: Vec<String>,
+}
+
+impl Triage {
+    /// Group `entries`, naming registers from `device` when given
+    pub fn new(entries: impl IntoIterator<Item = Entry>, device: Option<&Device>) -> Triage {
+        let mut triage = Triage::default();
+        let mut index: BTreeMap<String, usize> = BTreeMap::new();
+
+        for entry in entries {
+            let (source, kind, access, offset, address, value) = match entry.event {
+                Event::Uart(line) => {
+                    triage.uart.push(line);
+                    continue;
+                }
+                Event::Message(message) => {
+                    let group = triage.group(&mut index, &entry.source, device);
+                    match group.messages.iter_mut().find(|(m, _)| *m == message) {
+                        Some((_, count)) => *count += entry.repeat,
+                        None => group.messages.push((message, entry.repeat)),
+                    }
+                    continue;
+                }
+                Event::Unhandled {
+                    access,
+                    offset,
+                    value,
+                } => {
+                    let base = svd_peripheral(device, &entry.source).map(|p| p.base);
+                    let address = base.map(|base| base + offset);
+                    (
+                        entry.source,
+                        Kind::Unhandled,
+                        access,
+                        offset,
+                        address,
+                        value,
+                    )
+                }
+                Event::Unsupported {
+                    access,
+                    width,
+                    offset,
+                    value,
+                } => {
+                    let base = svd_peripheral(device, &entry.source).map(|p| p.base);
+                    let address = base.map(|base| base + offset);
+                    (
+                        entry.source,
+                        Kind::Width(width),
+                        access,
+                        offset,
+                        address,
+                        value,
+                    )
+                }
+                Event::NotModelled {
+                    tag,
+                    access,
+                    address,
+                    value,
+                } => {
+                    let owner = device.and_then(|d| d.at(address));
+                    let source = tag
+                        .or_else(|| owner.map(|p| p.name.clone()))
+                        .unwrap_or_else(|| format!("0x{:08X}", address & !0x3FF));
+                    let offset = owner.map_or(address & 0x3FF, |p| address - p.base);
+                    (
+                        source,
+                        Kind::NotModelled,
+                        access,
+                        offset,
+                        Some(address),
+                        value,
+                    )
+                }
+            };
+
+            let register = address.and_then(|address| {
+                let peripheral = device?.at(address)?;
+                Some(peripheral.register(address - peripheral.base)?.name.clone())
+            });
+            let group = triage.group(&mut index, &source, device);
+            if kind == Kind::NotModelled && group.name == source {
+                if let Some(peripheral) = address.and_then(|a| device?.at(a)) {
+                    group.name = peripheral.name.clone();
+                }
+            }
+            let finding = match group
+                .findings
+                .iter_mut()
+                .find(|f| f.kind == kind && f.access == access && f.offset == offset)
+            {
+                Some(finding) => finding,
+                None => {
+                    group.findings.push(Finding {
+                        kind,
+                        access,
+                        offset,
+                        address,
+                        register,
+                        count: 0,
+                        values: Vec::new(),
+                        pc: entry.pc,
+                    });
+                    group.findings.last_mut().expect("just pushed")
+                }
+            };
+            finding.count += entry.repeat;
+            if let Some(value) = value {
+                if !finding.values.contains(&value) && finding.values.len() < MAX_VALUES {
+                    finding.values.push(value);
+                }
+            }
+        }
+        triage
+    }
+
+    fn group(
+        &mut self,
+        index: &mut BTreeMap<String, usize>,
+        source: &str,
+        device: Option<&Device>,
+    ) -> &mut Group {
+        let at = *index.entry(source.to_string()).or_insert_with(|| {
+            let name = svd_peripheral(device, source)
+                .map_or_else(|| source.to_string(), |p| p.name.clone());
+            self.groups.push(Group {
+                name,
+                source: source.to_string(),
+                ..Group::default()
+            });
+            self.groups.len() - 1
+        });
+        &mut self.groups[at]
+    }
+}
+
+/// The SVD peripheral for a Renode peripheral name
+///
+/// The `.repl` names mostly match the SVD ones in lower case; timers and
+/// GPIO ports are named differently (`timer2` is `TIM2`, `gpioPortA` is
+/// `GPIOA`), and a few SVDs number single instances (`DAC1`).
+pub fn svd_peripheral<'a>(
+    device: Option<&'a Device>,
+    source: &str,
+) -> Option<&'a crate::svd::Peripheral> {
+    let device = device?;
+    let name = if let Some(n) = source.strip_prefix("timer") {
+        format!("TIM{}", n)
+    } else if let Some(port) = source.strip_prefix("gpioPort") {
+        format!("GPIO{}", port)
+    } else {
+        source.to_string()
+    };
+    device
+        .peripheral(&name)
+        .or_else(|| device.peripheral(&format!("{}1", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::parse;
+    use crate::svd::{Peripheral, Register};
+
+    fn peripheral(name: &str, base: u64, registers: &[(&str, u64)]) -> Peripheral {
+        Peripheral {
+            name: name.into(),
+            base,
+            size: 0x400,
+            registers: registers
+                .iter()
+                .map(|&(name, offset)| Register {
+                    name: name.into(),
+                    offset,
+                    size: 4,
+                })
+                .collect(),
+        }
+    }
+
+    fn device() -> Device {
+        Device {
+            peripherals: vec![
+                peripheral("TIM2", 0x4000_0000, &[("CR1", 0x0)]),
+                peripheral("PWR", 0x4000_7000, &[("CR", 0x0), ("CSR", 0x4)]),
+                peripheral("DAC1", 0x4000_7400, &[("CR", 0x0)]),
+                peripheral("DMA1", 0x4002_0000, &[("ISR", 0x0), ("CH1.CR", 0x8)]),
+                peripheral("GPIOA", 0x4800_0000, &[("MODER", 0x0)]),
+            ],
+        }
+    }
+
+    fn triage(log: &str, device: Option<&Device>) -> Triage {
+        Triage::new(log.lines().filter_map(parse), device)
+    }
+
+    #[test]
+    fn findings_are_grouped_and_counted() {
+        let t = triage(
+            "[WARNING] dma1: Unhandled write to offset 0x8, value 0x1.
+[WARNING] dma1: Unhandled read from offset 0x0.
+[WARNING] dma1: Unhandled write to offset 0x8, value 0x1. (3)
+[WARNING] dma1: Unhandled write to offset 0x8, value 0x2.
+[WARNING] iwdg: Unhandled write to offset 0x0, value 0xCCCC.",
+            None,
+        );
+        assert_eq!(t.groups.len(), 2);
+        let dma = &t.groups[0];
+        assert_eq!((dma.name.as_str(), dma.source.as_str()), ("dma1", "dma1"));
+        assert_eq!(dma.findings.len(), 2);
+        let write = &dma.findings[0];
+        assert_eq!(
+            (write.access, write.offset, write.count),
+            (Access::Write, 8, 5)
+        );
+        assert_eq!(write.values, [1, 2]);
+        assert_eq!((write.address, write.label()), (None, "+0x8".to_string()));
+        assert_eq!(dma.unhandled(Access::Read), ["+0x0"]);
+        assert_eq!(t.groups[1].name, "iwdg");
+    }
+
+    #[test]
+    fn written_values_are_capped() {
+        let log: Vec<String> = (0..10)
+            .map(|v| {
+                format!(
+                    "[WARNING] dma1: Unhandled write to offset 0x8, value 0x{:X}.",
+                    v
+                )
+            })
+            .collect();
+        let t = triage(&log.join("\n"), None);
+        assert_eq!(t.groups[0].findings[0].values, [0, 1, 2, 3]);
+        assert_eq!(t.groups[0].findings[0].count, 10);
+    }
+
+    #[test]
+    fn registers_are_named_from_the_svd() {
+        let device = device();
+        let t = triage(
+            "[WARNING] dma1: Unhandled write to offset 0x8, value 0x1.
+[WARNING] timer2: Attempted Byte write isn't supported by the peripheral. Offset 0x0, value 0x1.
+[WARNING] gpioPortA: Unhandled read from offset 0x0.
+[WARNING] dac: Unhandled read from offset 0x0.",
+            Some(&device),
+        );
+        let names: Vec<(&str, String)> = t
+            .groups
+            .iter()
+            .map(|g| (g.name.as_str(), g.findings[0].label()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("DMA1", "CH1.CR".to_string()),
+                ("TIM2", "CR1".to_string()),
+                ("GPIOA", "MODER".to_string()),
+                ("DAC1", "CR".to_string()),
+            ]
+        );
+        assert_eq!(t.groups[0].findings[0].address, Some(0x4002_0008));
+        assert_eq!(t.groups[1].findings[0].kind, Kind::Width("Byte".into()));
+    }
+
+    #[test]
+    fn missing_peripherals_are_grouped_by_tag_or_address() {
+        let log = "[WARNING] sysbus: (tag: 'PWR') ReadDoubleWord from non existing peripheral at 0x40007004, returning 0x00000000.
+[WARNING] sysbus: WriteDoubleWord to non existing peripheral at 0x40007000, value 0x100.";
+        let t = triage(log, None);
+        let sources: Vec<&str> = t.groups.iter().map(|g| g.source.as_str()).collect();
+        assert_eq!(sources, ["PWR", "0x40007000"]);
+        assert_eq!(t.groups[1].findings[0].offset, 0);
+        assert_eq!(t.groups[0].findings[0].kind, Kind::NotModelled);
+
+        // With the SVD the untagged access lands in the same peripheral
+        let device = device();
+        let t = triage(log, Some(&device));
+        assert_eq!(t.groups.len(), 1);
+        let labels: Vec<String> = t.groups[0].findings.iter().map(Finding::label).collect();
+        assert_eq!(labels, ["CSR", "CR"]);
+    }
+
+    #[test]
+    fn messages_and_uart_output_are_kept() {
+        let t = triage(
+            "[WARNING] cpu: Guest is in a loop (2)
+[INFO] usart1: DMA TEST FAILED
+[WARNING] cpu: Guest is in a loop",
+            None,
+        );
+        assert_eq!(
+            t.groups[0].messages,
+            [("Guest is in a loop".to_string(), 3)]
+        );
+        assert!(t.groups[0].findings.is_empty());
+        assert_eq!(t.uart, ["DMA TEST FAILED"]);
+    }
+
+    #[test]
+    fn svd_names_for_renode_names() {
+        let device = device();
+        let name = |source| svd_peripheral(Some(&device), source).map(|p| p.name.as_str());
+        assert_eq!(name("timer2"), Some("TIM2"));
+        assert_eq!(name("gpioPortA"), Some("GPIOA"));
+        assert_eq!(name("dma1"), Some("DMA1"));
+        assert_eq!(name("dac"), Some("DAC1"));
+        assert_eq!(name("usart1"), None);
+        assert!(svd_peripheral(None, "dma1").is_none());
+    }
+}
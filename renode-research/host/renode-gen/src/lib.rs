@@ -0,0 +1,549 @@
+//! Generate Renode scripts and Robot suites from per-crate metadata
+//!
+//! Each firmware crate declares its emulation setup and UART expectations
+//! once, in `[package.metadata.renode]` of its `Cargo.toml`:
+//!
+//! ```toml
+//! [package.metadata.renode]
+//! title = "DMA"
+//! description = "This script runs a DMA peripheral test on STM32F3"
+//!
+//! [[package.metadata.renode.tests]]
+//! name = "Should Initialize DMA And Report"
+//! doc = "Verify DMA test initializes and reports on UART"
+//! steps = [
+//!     { wait = "DMA Peripheral Test", timeout = 5 },
+//! ]
+//! ```
+//!
+//! A crate whose firmware talks to a host program on a second UART adds a
+//! `[package.metadata.renode.peer]` table; test cases then name the
+//! `uart-peer` role to start for them:
+//!
+//! ```toml
+//! [package.metadata.renode.peer]
+//! uart = "sysbus.usart2"
+//!
+//! [[package.metadata.renode.tests]]
+//! name = "Should Talk To Modem"
+//! peer = ["modem"]
+//! steps = [{ wait = "AT: OK PASS", timeout = 10 }]
+//! ```
+//!
+//! `renode-runner --golden` reads the same table. Firmware that needs input
+//! to reach the end of its run, or ends with something other than a
+//! `... TEST PASSED` / `... TEST FAILED` line, says so in a
+//! `[package.metadata.renode.golden]` table:
+//!
+//! ```toml
+//! [package.metadata.renode.golden]
+//! end = ["SELFTEST PASSED", "SELFTEST FAILED"]
+//! peer = ["modem"]
+//! steps = [{ wait = "Waiting for button press" }, { execute = "gpioPortA.UserButton Press" }]
+//! ```
+//!
+//! From that, [`Crate::files`] renders `renode-config.resc`,
+//! `test-quick.resc` and `tests/test-<x>.robot` ([`resc`], [`robot`]),
+//! plus the crate's `.repl` files from [`renode_platform::variants`].
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+pub mod resc;
+pub mod robot;
+
+/// Firmware target triple the scripts load ELFs from
+pub const TARGET: &str = "thumbv7em-none-eabihf";
+
+/// First line of every generated file, after any Renode `:name:` headers
+pub const GENERATED_NOTICE: &str =
+    "Generated by renode-gen from [package.metadata.renode] in Cargo.toml; do not edit.";
+
+/// First line of the generated `.repl` files
+pub const PLATFORM_NOTICE: &str =
+    "Generated by renode-gen from renode-platform's variants; do not edit.";
+
+/// Errors reading crate metadata
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    /// `Cargo.toml` or its metadata table is malformed
+    Metadata {
+        path: PathBuf,
+        msg: String,
+    },
+    /// A platform variant failed its checks
+    Platform {
+        name: String,
+        err: renode_platform::Error,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Metadata { path, msg } => write!(f, "{}: {}", path.display(), msg),
+            Error::Platform { name, err } => write!(f, "{}: {}", name, err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// `[package.metadata.renode]`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Metadata {
+    /// Used in the script names, e.g. `DMA` for "STM32F3 DMA Test"
+    pub title: String,
+    /// `:description:` line of `renode-config.resc`
+    pub description: Option<String>,
+    /// Renode machine name; `STM32F3_<TITLE>` by default
+    pub machine: Option<String>,
+    /// Platform description; `stm32f3_<x>.repl` by default
+    pub platform: Option<String>,
+    /// UART the tests read from
+    #[serde(default = "default_uart")]
+    pub uart: String,
+    /// Host program on a second UART
+    pub peer: Option<Peer>,
+    /// Robot test cases; no suite is generated without any
+    #[serde(default)]
+    pub tests: Vec<Test>,
+    /// How `renode-runner --golden` records the transcript
+    #[serde(default)]
+    pub golden: Golden,
+}
+
+/// `[package.metadata.renode.golden]`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Golden {
+    /// Endings of the line that ends the run
+    #[serde(default = "default_golden_end")]
+    pub end: Vec<String>,
+    /// `uart-peer` role and options, as for a test; needs `[...renode.peer]`
+    #[serde(default)]
+    pub peer: Vec<String>,
+    /// Input the firmware needs before it gets to the end line, run after
+    /// the emulation has started
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+impl Default for Golden {
+    fn default() -> Self {
+        Golden {
+            end: default_golden_end(),
+            peer: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+}
+
+/// `[package.metadata.renode.peer]`: a UART connected to a Renode server
+/// socket terminal that `host/uart-peer` plays a device on
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Peer {
+    /// UART the peer is connected to, e.g. `sysbus.usart2`
+    pub uart: String,
+    /// TCP port of the socket terminal
+    #[serde(default = "default_peer_port")]
+    pub port: u16,
+}
+
+/// One Robot test case
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Test {
+    pub name: String,
+    /// `[Documentation]`; each line becomes a row
+    #[serde(default)]
+    pub doc: String,
+    /// `uart-peer` role and options started before the emulation, e.g.
+    /// `["modem", "--drop-every", "3"]`; needs `[...renode.peer]`
+    #[serde(default)]
+    pub peer: Vec<String>,
+    /// Steps after the firmware has been loaded and started
+    pub steps: Vec<Step>,
+}
+
+/// A test step, written as an inline table with one of these keys
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum Step {
+    /// `{ wait = "line", timeout = 5 }`: `Wait For Line On Uart`
+    Wait { wait: String, timeout: Option<f64> },
+    /// `{ execute = "gpioPortA.UserButton Press" }`: `Execute Command`
+    Execute { execute: String },
+    /// `{ send = "led 3 on" }`: `Write Line To Uart`
+    Send { send: String },
+    /// `{ sleep = 0.1 }`: `Sleep`
+    Sleep { sleep: f64 },
+    /// `{ comment = "..." }`: a `#` comment row
+    Comment { comment: String },
+    /// `{ run = ["frame-decode", "--summary", "frames.bin"] }`: `Run Process`
+    /// a host program to completion, failing unless it exits with 0
+    Run { run: Vec<String> },
+}
+
+fn default_uart() -> String {
+    "sysbus.usart1".into()
+}
+
+fn default_peer_port() -> u16 {
+    4567
+}
+
+fn default_golden_end() -> Vec<String> {
+    vec!["TEST PASSED".into(), "TEST FAILED".into()]
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    /// Absent for virtual workspace manifests
+    package: Option<Package>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    #[serde(default)]
+    metadata: Option<PackageMetadata>,
+}
+
+#[derive(Deserialize)]
+struct PackageMetadata {
+    renode: Option<Metadata>,
+}
+
+/// A firmware crate with Renode metadata
+#[derive(Clone, Debug)]
+pub struct Crate {
+    /// Package name, e.g. `stm32f3-dma`
+    pub name: String,
+    pub dir: PathBuf,
+    pub metadata: Metadata,
+}
+
+impl Crate {
+    /// Read `dir/Cargo.toml`; `None` if it has no `[package.metadata.renode]`
+    pub fn load(dir: &Path) -> Result<Option<Crate>, Error> {
+        let path = dir.join("Cargo.toml");
+        let text = fs::read_to_string(&path)?;
+        let manifest: Manifest = toml::from_str(&text).map_err(|err| Error::Metadata {
+            path: path.clone(),
+            msg: err.to_string(),
+        })?;
+        let Some(krate) = manifest.package.and_then(|package| {
+            let metadata = package.metadata?.renode?;
+            Some(Crate {
+                name: package.name,
+                dir: dir.to_path_buf(),
+                metadata,
+            })
+        }) else {
+            return Ok(None);
+        };
+
+        let meta = &krate.metadata;
+        if meta.peer.is_none() {
+            if let Some(test) = meta.tests.iter().find(|t| !t.peer.is_empty()) {
+                return Err(Error::Metadata {
+                    path,
+                    msg: format!(
+                        "test `{}` starts a peer but there is no [package.metadata.renode.peer]",
+                        test.name
+                    ),
+                });
+            }
+            if !meta.golden.peer.is_empty() {
+                return Err(Error::Metadata {
+                    path,
+                    msg: "the golden run starts a peer but there is no \
+                          [package.metadata.renode.peer]"
+                        .into(),
+                });
+            }
+        }
+        let steps = meta.tests.iter().flat_map(|t| &t.steps);
+        if steps.chain(&meta.golden.steps).any(|step| match step {
+            Step::Run { run } => run.is_empty(),
+            _ => false,
+        }) {
+            return Err(Error::Metadata {
+                path,
+                msg: "a `run` step needs a program".into(),
+            });
+        }
+        if meta.golden.end.is_empty() {
+            return Err(Error::Metadata {
+                path,
+                msg: "[package.metadata.renode.golden] needs at least one `end` line".into(),
+            });
+        }
+        Ok(Some(krate))
+    }
+
+    /// Peripheral part of the name, e.g. `dma`
+    pub fn peripheral(&self) -> &str {
+        self.name.strip_prefix("stm32f3-").unwrap_or(&self.name)
+    }
+
+    pub fn machine(&self) -> String {
+        self.metadata
+            .machine
+            .clone()
+            .unwrap_or_else(|| format!("STM32F3_{}", self.metadata.title.replace(' ', "_")))
+    }
+
+    pub fn platform(&self) -> String {
+        self.metadata
+            .platform
+            .clone()
+            .unwrap_or_else(|| format!("{}.repl", self.name.replace('-', "_")))
+    }
+
+    /// Release ELF, relative to the firmware workspace root
+    pub fn elf(&self) -> String {
+        format!("target/{}/release/{}", TARGET, self.name)
+    }
+
+    /// Every generated file with its contents
+    pub fn files(&self) -> Result<Vec<(PathBuf, String)>, Error> {
+        let mut files = vec![
+            (self.dir.join("renode-config.resc"), resc::config(self)),
+            (self.dir.join("test-quick.resc"), resc::quick(self)),
+        ];
+        if !self.metadata.tests.is_empty() {
+            let suite = format!("tests/test-{}.robot", self.peripheral());
+            files.push((self.dir.join(suite), robot::suite(self)));
+        }
+        for (name, platform) in renode_platform::variants::for_crate(&self.name) {
+            let repl = platform.render().map_err(|err| Error::Platform {
+                name: name.clone(),
+                err,
+            })?;
+            files.push((
+                self.dir.join(name),
+                format!("// {}\n{}", PLATFORM_NOTICE, repl),
+            ));
+        }
+        Ok(files)
+    }
+}
+
+/// Every crate under `root` with Renode metadata, sorted by name
+pub fn discover(root: &Path) -> Result<Vec<Crate>, Error> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.join("Cargo.toml").is_file())
+        .collect();
+    dirs.sort();
+
+    let mut crates = Vec::new();
+    for dir in dirs {
+        if let Some(krate) = Crate::load(&dir)? {
+            crates.push(krate);
+        }
+    }
+    Ok(crates)
+}
+
+/// Format seconds the way the hand-written suites do (`5`, `0.1`)
+pub(crate) fn seconds(value: f64) -> String {
+    format!("{}", value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Write `manifest` into a crate directory of its own and load it
+    fn load(manifest: &str) -> Result<Option<Crate>, Error> {
+        static DIRS: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "renode-gen-{}-{}",
+            std::process::id(),
+            DIRS.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Cargo.toml"), manifest).unwrap();
+        let krate = Crate::load(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        krate
+    }
+
+    fn error(manifest: &str) -> String {
+        match load(manifest) {
+            Err(Error::Metadata { msg, .. }) => msg,
+            other => panic!("expected a metadata error, got {:?}", other),
+        }
+    }
+
+    const PACKAGE: &str = "[package]\nname = \"stm32f3-dma\"\nversion = \"0.1.0\"\n";
+
+    #[test]
+    fn defaults() {
+        let manifest = format!(
+            "{}[package.metadata.renode]\ntitle = \"DMA Test\"\n",
+            PACKAGE
+        );
+        let krate = load(&manifest).unwrap().unwrap();
+        assert_eq!(krate.name, "stm32f3-dma");
+        assert_eq!(krate.peripheral(), "dma");
+        assert_eq!(krate.machine(), "STM32F3_DMA_Test");
+        assert_eq!(krate.platform(), "stm32f3_dma.repl");
+        assert_eq!(
+            krate.elf(),
+            "target/thumbv7em-none-eabihf/release/stm32f3-dma"
+        );
+        let meta = &krate.metadata;
+        assert_eq!(meta.uart, "sysbus.usart1");
+        assert!(meta.peer.is_none() && meta.tests.is_empty());
+        assert_eq!(meta.golden.end, ["TEST PASSED", "TEST FAILED"]);
+    }
+
+    #[test]
+    fn steps_and_peer() {
+        let manifest = format!(
+            r#"{}
+[package.metadata.renode]
+title = "USART2"
+machine = "M"
+platform = "board.repl"
+peer = {{ uart = "sysbus.usart2" }}
+
+[[package.metadata.renode.tests]]
+name = "Should Talk"
+peer = ["modem", "--drop-every", "3"]
+steps = [
+    {{ comment = "Boot" }},
+    {{ wait = "ready", timeout = 2.5 }},
+    {{ wait = "again" }},
+    {{ send = "led 3 on" }},
+    {{ execute = "gpioPortA.UserButton Press" }},
+    {{ sleep = 0.1 }},
+    {{ run = ["frame-decode", "--summary", "f.bin"] }},
+]
+"#,
+            PACKAGE
+        );
+        let krate = load(&manifest).unwrap().unwrap();
+        assert_eq!(krate.machine(), "M");
+        assert_eq!(krate.platform(), "board.repl");
+        let peer = krate.metadata.peer.as_ref().unwrap();
+        assert_eq!((peer.uart.as_str(), peer.port), ("sysbus.usart2", 4567));
+        let test = &krate.metadata.tests[0];
+        assert_eq!(test.peer, ["modem", "--drop-every", "3"]);
+        assert_eq!(
+            test.steps,
+            [
+                Step::Comment {
+                    comment: "Boot".into()
+                },
+                Step::Wait {
+                    wait: "ready".into(),
+                    timeout: Some(2.5)
+                },
+                Step::Wait {
+                    wait: "again".into(),
+                    timeout: None
+                },
+                Step::Send {
+                    send: "led 3 on".into()
+                },
+                Step::Execute {
+                    execute: "gpioPortA.UserButton Press".into()
+                },
+                Step::Sleep { sleep: 0.1 },
+                Step::Run {
+                    run: vec!["frame-decode".into(), "--summary".into(), "f.bin".into()]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn crates_without_metadata() {
+        assert!(load(PACKAGE).unwrap().is_none());
+        assert!(load("[workspace]\nmembers = [\"a\"]\n").unwrap().is_none());
+        let other = format!("{}[package.metadata.docs]\nall-features = true\n", PACKAGE);
+        assert!(load(&other).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_bad_metadata() {
+        let renode = format!("{}[package.metadata.renode]\ntitle = \"DMA\"\n", PACKAGE);
+        assert!(error(&format!("{}colour = \"red\"\n", renode)).contains("colour"));
+        assert!(error(&format!(
+            "{}[[package.metadata.renode.tests]]\nname = \"T\"\nsteps = [{{ wait = 5 }}]\n",
+            renode
+        ))
+        .contains("did not match any variant"));
+        assert_eq!(
+            error(&format!(
+                "{}[[package.metadata.renode.tests]]\nname = \"T\"\npeer = [\"modem\"]\nsteps = []\n",
+                renode
+            )),
+            "test `T` starts a peer but there is no [package.metadata.renode.peer]"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}[package.metadata.renode.golden]\npeer = [\"modem\"]\n",
+                renode
+            )),
+            "the golden run starts a peer but there is no [package.metadata.renode.peer]"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}[package.metadata.renode.golden]\nsteps = [{{ run = [] }}]\n",
+                renode
+            )),
+            "a `run` step needs a program"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}[package.metadata.renode.golden]\nend = []\n",
+                renode
+            )),
+            "[package.metadata.renode.golden] needs at least one `end` line"
+        );
+    }
+
+    #[test]
+    fn every_firmware_crate_loads_and_renders() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        let crates = discover(&root).unwrap();
+        assert!(crates.len() > 10, "{} crates", crates.len());
+        for krate in &crates {
+            let files = krate.files().unwrap();
+            assert!(
+                files
+                    .iter()
+                    .any(|(path, _)| path.ends_with("renode-config.resc")),
+                "{}",
+                krate.name
+            );
+        }
+    }
+
+    #[test]
+    fn seconds_as_written_by_hand() {
+        assert_eq!(seconds(5.0), "5");
+        assert_eq!(seconds(0.1), "0.1");
+        assert_eq!(seconds(2.5), "2.5");
+    }
+}
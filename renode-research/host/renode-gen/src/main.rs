@@ -0,0 +1,113 @@
+//! Command line front end for the script generator
+//!
+//! Usage: `renode-gen [OPTIONS] [CRATE]...`
+//!
+//! Regenerates `renode-config.resc`, `test-quick.resc`,
+//! `tests/test-<x>.robot` and the `.repl` platform files for every crate
+//! with `[package.metadata.renode]` (or only the given ones). `--check`
+//! writes nothing and exits non-zero if any generated file is missing or
+//! out of date.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use renode_gen::Crate;
+
+#[derive(Parser)]
+#[command(about = "Generate Renode scripts and Robot suites from Cargo.toml metadata")]
+struct Args {
+    /// Peripheral crates (e.g. `dma i2c`); all when omitted
+    crates: Vec<String>,
+
+    /// Firmware workspace root
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))]
+    root: PathBuf,
+
+    /// Report stale files instead of writing them
+    #[arg(long)]
+    check: bool,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let root = args.root.canonicalize().unwrap_or(args.root);
+
+    let crates = match select(renode_gen::discover(&root), &args.crates) {
+        Ok(crates) => crates,
+        Err(err) => {
+            eprintln!("renode-gen: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut stale = 0;
+    for krate in &crates {
+        let files = match krate.files() {
+            Ok(files) => files,
+            Err(err) => {
+                eprintln!("renode-gen: {}", err);
+                return ExitCode::from(2);
+            }
+        };
+        for (path, contents) in files {
+            let current = fs::read_to_string(&path).ok();
+            if current.as_deref() == Some(contents.as_str()) {
+                continue;
+            }
+            let shown = path.strip_prefix(&root).unwrap_or(&path).display();
+            if args.check {
+                println!("stale: {}", shown);
+                stale += 1;
+                continue;
+            }
+            let written = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|()| fs::write(&path, contents));
+            if let Err(err) = written {
+                eprintln!("renode-gen: cannot write {}: {}", shown, err);
+                return ExitCode::from(2);
+            }
+            println!("wrote {}", shown);
+        }
+    }
+
+    if stale > 0 {
+        println!(
+            "{} generated file(s) out of date; run `cargo xtask gen`",
+            stale
+        );
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Keep only the requested crates, in the order given
+fn select(
+    crates: Result<Vec<Crate>, renode_gen::Error>,
+    wanted: &[String],
+) -> Result<Vec<Crate>, String> {
+    let crates = crates.map_err(|err| err.to_string())?;
+    if wanted.is_empty() {
+        return Ok(crates);
+    }
+    wanted
+        .iter()
+        .map(|name| {
+            let name = if name.starts_with("stm32f3-") {
+                name.clone()
+            } else {
+                format!("stm32f3-{}", name)
+            };
+            crates
+                .iter()
+                .find(|c| c.name == name)
+                .cloned()
+                .ok_or_else(|| format!("no crate {} with [package.metadata.renode]", name))
+        })
+        .collect()
+}
@@ -0,0 +1,205 @@
+//! Renode scripts
+//!
+//! `renode-config.resc` is the interactive setup (analyzer window, PTY
+//! terminal, GDB server and a `reset` macro); `test-quick.resc` only loads
+//! the firmware and the PTY for scripted runs. Both resolve the ELF relative
+//! to the firmware workspace root, where `renode` is normally started.
+//!
+//! The PTY path, GDB port and peer port are `?=` variables, so a second
+//! instance can set `$uart`, `$gdb_port` and `$peer_port` before including
+//! the script instead of clashing with the first.
+
+use std::fmt::Write;
+
+use crate::{Crate, Peer, GENERATED_NOTICE};
+
+/// PTY the scripts connect the UART to
+const UART_PTY: &str = "/tmp/uart";
+
+/// GDB server port
+pub(crate) const GDB_PORT: u16 = 3333;
+
+/// `renode-config.resc`
+pub fn config(krate: &Crate) -> String {
+    let meta = &krate.metadata;
+    let uart = &meta.uart;
+
+    let mut out = String::new();
+    writeln!(out, ":name: STM32F3 {} Test", meta.title).unwrap();
+    if let Some(description) = &meta.description {
+        writeln!(out, ":description: {}", description).unwrap();
+    }
+    writeln!(out, "# {}", GENERATED_NOTICE).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "$name?=\"{}\"", krate.machine()).unwrap();
+    writeln!(out, "$bin?=@{}", krate.elf()).unwrap();
+    write_variables(&mut out, krate);
+    writeln!(out, "$gdb_port?={}", GDB_PORT).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "# Create Machine & Load config").unwrap();
+    writeln!(out, "mach create $name").unwrap();
+    writeln!(out, "machine LoadPlatformDescription @{}", krate.platform()).unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "# Create a terminal window showing the output of {}",
+        short(uart)
+    )
+    .unwrap();
+    writeln!(out, "showAnalyzer {}", uart).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "# Set up UART PTY terminal for programmatic access").unwrap();
+    write_pty(&mut out, uart);
+    writeln!(out).unwrap();
+    if let Some(peer) = &meta.peer {
+        write_peer(&mut out, peer);
+        writeln!(out).unwrap();
+    }
+    writeln!(out, "# Enable GDB").unwrap();
+    writeln!(out, "machine StartGdbServer $gdb_port").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "macro reset").unwrap();
+    writeln!(out, "\"\"\"").unwrap();
+    writeln!(out, "    sysbus LoadELF $bin").unwrap();
+    writeln!(out, "\"\"\"").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "runMacro $reset").unwrap();
+    out
+}
+
+/// `test-quick.resc`
+pub fn quick(krate: &Crate) -> String {
+    let meta = &krate.metadata;
+
+    let mut out = String::new();
+    writeln!(out, ":name: STM32F3 {} Quick Test", meta.title).unwrap();
+    writeln!(out, "# {}", GENERATED_NOTICE).unwrap();
+    writeln!(out, "$bin=@{}", krate.elf()).unwrap();
+    write_variables(&mut out, krate);
+    writeln!(out).unwrap();
+    writeln!(out, "mach create \"{}\"", krate.machine()).unwrap();
+    writeln!(out, "machine LoadPlatformDescription @{}", krate.platform()).unwrap();
+    writeln!(out, "sysbus LoadELF $bin").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "# Set up UART logging").unwrap();
+    write_pty(&mut out, &meta.uart);
+    if let Some(peer) = &meta.peer {
+        writeln!(out).unwrap();
+        write_peer(&mut out, peer);
+    }
+    out
+}
+
+/// `$uart` and, with a peer, `$peer_port`
+fn write_variables(out: &mut String, krate: &Crate) {
+    writeln!(out, "$uart?=\"{}\"", UART_PTY).unwrap();
+    if let Some(peer) = &krate.metadata.peer {
+        writeln!(out, "$peer_port?={}", peer.port).unwrap();
+    }
+}
+
+/// Socket terminal for `host/uart-peer`
+fn write_peer(out: &mut String, peer: &Peer) {
+    writeln!(
+        out,
+        "# {} is connected to a socket terminal; start the device with e.g.",
+        short(&peer.uart)
+    )
+    .unwrap();
+    writeln!(out, "#   uart-peer --connect 127.0.0.1:{} modem", peer.port).unwrap();
+    writeln!(
+        out,
+        "emulation CreateServerSocketTerminal $peer_port \"peer\" false"
+    )
+    .unwrap();
+    writeln!(out, "connector Connect {} peer", peer.uart).unwrap();
+}
+
+fn write_pty(out: &mut String, uart: &str) {
+    writeln!(out, "logLevel -1 {}", uart).unwrap();
+    writeln!(out, "emulation CreateUartPtyTerminal \"term\" $uart true").unwrap();
+    writeln!(out, "connector Connect {} term", uart).unwrap();
+}
+
+/// `sysbus.usart1` -> `USART1`
+fn short(peripheral: &str) -> String {
+    peripheral
+        .rsplit('.')
+        .next()
+        .unwrap_or(peripheral)
+        .to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Metadata;
+    use std::path::PathBuf;
+
+    fn krate(metadata: &str) -> Crate {
+        Crate {
+            name: "stm32f3-usart2".into(),
+            dir: PathBuf::from("stm32f3-usart2"),
+            metadata: toml::from_str::<Metadata>(metadata).unwrap(),
+        }
+    }
+
+    #[test]
+    fn quick_script() {
+        let krate = krate("title = \"USART2\"\n");
+        let expected = format!(
+            ":name: STM32F3 USART2 Quick Test
+# {}
+$bin=@target/thumbv7em-none-eabihf/release/stm32f3-usart2
+$uart?=\"/tmp/uart\"
+
+mach create \"STM32F3_USART2\"
+machine LoadPlatformDescription @stm32f3_usart2.repl
+sysbus LoadELF $bin
+
+# Set up UART logging
+logLevel -1 sysbus.usart1
+emulation CreateUartPtyTerminal \"term\" $uart true
+connector Connect sysbus.usart1 term
+",
+            GENERATED_NOTICE
+        );
+        assert_eq!(quick(&krate), expected);
+    }
+
+    #[test]
+    fn config_script_with_a_peer() {
+        let krate = krate(
+            "title = \"USART2\"\ndescription = \"Modem and GNSS\"\npeer = { uart = \"sysbus.usart2\" }\n",
+        );
+        let text = config(&krate);
+        assert!(text.starts_with(":name: STM32F3 USART2 Test\n:description: Modem and GNSS\n# "));
+        for line in [
+            "$name?=\"STM32F3_USART2\"",
+            "$peer_port?=4567",
+            "$gdb_port?=3333",
+            "# Create a terminal window showing the output of USART1",
+            "showAnalyzer sysbus.usart1",
+            "# USART2 is connected to a socket terminal; start the device with e.g.",
+            "#   uart-peer --connect 127.0.0.1:4567 modem",
+            "emulation CreateServerSocketTerminal $peer_port \"peer\" false",
+            "connector Connect sysbus.usart2 peer",
+            "machine StartGdbServer $gdb_port",
+            "runMacro $reset",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "no {:?} in\n{}",
+                line,
+                text
+            );
+        }
+        assert!(quick(&krate).contains("connector Connect sysbus.usart2 peer\n"));
+    }
+
+    #[test]
+    fn short_names() {
+        assert_eq!(short("sysbus.usart1"), "USART1");
+        assert_eq!(short("uart4"), "UART4");
+    }
+}
@@ -0,0 +1,365 @@
+//! Robot Framework suites
+//!
+//! Every test case boots the firmware the same way, so the
+//! `mach create` / `LoadPlatformDescription` / `LoadELF` sequence lives in a
+//! `Start Firmware` keyword and the test cases only list their steps.
+//! Arguments start at column 30, as in the hand-written suites.
+//!
+//! The keyword also starts a GDB server on `${GDB_PORT}`, 3333 as in
+//! `renode-config.resc` unless the runner gives the slot another port, so
+//! a `run` step can inspect the target with `gdb-dump`.
+//!
+//! With a [`Peer`](crate::Peer), `Load Firmware` also connects the peer's
+//! UART to a server socket terminal, and test cases that name a role start
+//! `uart-peer` between loading and starting the emulation, so the device is
+//! there before the firmware's first byte. The runner finds `uart-peer`
+//! next to its own executable; Robot needs it on `PATH`.
+//!
+//! A `run` step runs a host program (such as `frame-decode`) to completion
+//! and checks its exit status, with the program's output as the failure
+//! message.
+
+use std::fmt::Write;
+
+use crate::resc::GDB_PORT;
+use crate::{seconds, Crate, Step, GENERATED_NOTICE};
+
+/// Column the first argument starts at
+const ARGUMENT_COLUMN: usize = 30;
+
+/// Name of the generated setup keyword
+const START_KEYWORD: &str = "Start Firmware";
+
+/// Name of the keyword that loads without starting, for crates with a peer
+const LOAD_KEYWORD: &str = "Load Firmware";
+
+/// Socket terminal the peer's UART is connected to
+const PEER_TERMINAL: &str = "peer";
+
+/// Variable a `run` step keeps the process result in
+const RUN_RESULT: &str = "result";
+
+/// `tests/test-<x>.robot`
+pub fn suite(krate: &Crate) -> String {
+    let meta = &krate.metadata;
+
+    let mut out = String::new();
+    writeln!(out, "# {}", GENERATED_NOTICE).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "*** Settings ***").unwrap();
+    row(&mut out, "", "Suite Setup", &["Setup"]);
+    row(&mut out, "", "Suite Teardown", &["Teardown"]);
+    row(&mut out, "", "Test Setup", &["Reset Emulation"]);
+    if meta.peer.is_some() {
+        row(
+            &mut out,
+            "",
+            "Test Teardown",
+            &["Terminate All Processes", "kill=True"],
+        );
+    }
+    row(&mut out, "", "Resource", &["${RENODEKEYWORDS}"]);
+    let runs = meta
+        .tests
+        .iter()
+        .flat_map(|test| &test.steps)
+        .any(|step| matches!(step, Step::Run { .. }));
+    if meta.peer.is_some() || runs {
+        row(&mut out, "", "Library", &["Process"]);
+    }
+    writeln!(out).unwrap();
+
+    writeln!(out, "*** Variables ***").unwrap();
+    let platform = format!("${{CURDIR}}/../{}", krate.platform());
+    let elf = format!("${{CURDIR}}/../../{}", krate.elf());
+    row(&mut out, "", "${PLATFORM}", &[&platform]);
+    row(&mut out, "", "${ELF}", &[&elf]);
+    row(&mut out, "", "${GDB_PORT}", &[&GDB_PORT.to_string()]);
+    if let Some(peer) = &meta.peer {
+        row(&mut out, "", "${UART_PEER}", &["uart-peer"]);
+        row(&mut out, "", "${PEER_PORT}", &[&peer.port.to_string()]);
+    }
+    writeln!(out).unwrap();
+
+    writeln!(out, "*** Test Cases ***").unwrap();
+    for (index, test) in meta.tests.iter().enumerate() {
+        if index > 0 {
+            writeln!(out).unwrap();
+        }
+        writeln!(out, "{}", test.name).unwrap();
+        let mut doc = test.doc.lines();
+        if let Some(first) = doc.next() {
+            row(&mut out, "    ", "[Documentation]", &[first]);
+        }
+        for line in doc {
+            row(&mut out, "    ", "...", &[line]);
+        }
+        if test.peer.is_empty() {
+            writeln!(out, "    {}", START_KEYWORD).unwrap();
+        } else {
+            writeln!(out, "    {}", LOAD_KEYWORD).unwrap();
+            let mut args = vec!["${UART_PEER}", "--connect", "127.0.0.1:${PEER_PORT}"];
+            args.extend(test.peer.iter().map(String::as_str));
+            row(&mut out, "    ", "Start Process", &args);
+            row(&mut out, "    ", "Start Emulation", &[]);
+        }
+        writeln!(out).unwrap();
+
+        for (index, step) in test.steps.iter().enumerate() {
+            match step {
+                Step::Wait { wait, timeout } => {
+                    let timeout = timeout.map(|t| format!("timeout={}", seconds(t)));
+                    let mut args = vec![wait.as_str()];
+                    args.extend(timeout.as_deref());
+                    row(&mut out, "    ", "Wait For Line On Uart", &args);
+                }
+                Step::Execute { execute } => {
+                    row(&mut out, "    ", "Execute Command", &[execute]);
+                }
+                Step::Send { send } => {
+                    row(&mut out, "    ", "Write Line To Uart", &[send]);
+                }
+                Step::Sleep { sleep } => {
+                    row(&mut out, "    ", "Sleep", &[&seconds(*sleep)]);
+                }
+                Step::Run { run } => {
+                    let mut args = vec!["Run Process"];
+                    args.extend(run.iter().map(String::as_str));
+                    row(&mut out, "    ", &format!("${{{}}}=", RUN_RESULT), &args);
+                    let rc = format!("${{{}.rc}}", RUN_RESULT);
+                    let stdout = format!("${{{}.stdout}}", RUN_RESULT);
+                    row(
+                        &mut out,
+                        "    ",
+                        "Should Be Equal As Integers",
+                        &[&rc, "0", &stdout],
+                    );
+                }
+                Step::Comment { comment } => {
+                    // Comments open a new group of steps
+                    if index > 0 {
+                        writeln!(out).unwrap();
+                    }
+                    writeln!(out, "    # {}", comment).unwrap();
+                }
+            }
+        }
+    }
+    writeln!(out).unwrap();
+
+    writeln!(out, "*** Keywords ***").unwrap();
+    match &meta.peer {
+        None => {
+            writeln!(out, "{}", START_KEYWORD).unwrap();
+            row(
+                &mut out,
+                "    ",
+                "[Documentation]",
+                &["Load the platform and firmware, then start it"],
+            );
+            load_rows(&mut out, krate);
+            row(&mut out, "    ", "Start Emulation", &[]);
+        }
+        Some(peer) => {
+            writeln!(out, "{}", LOAD_KEYWORD).unwrap();
+            row(
+                &mut out,
+                "    ",
+                "[Documentation]",
+                &["Load the platform and firmware and connect both UARTs"],
+            );
+            load_rows(&mut out, krate);
+            let terminal = format!(
+                "emulation CreateServerSocketTerminal ${{PEER_PORT}} \"{}\" false",
+                PEER_TERMINAL
+            );
+            let connect = format!("connector Connect {} {}", peer.uart, PEER_TERMINAL);
+            row(&mut out, "    ", "Execute Command", &[&terminal]);
+            row(&mut out, "    ", "Execute Command", &[&connect]);
+
+            if meta.tests.iter().any(|t| t.peer.is_empty()) {
+                writeln!(out).unwrap();
+                writeln!(out, "{}", START_KEYWORD).unwrap();
+                row(
+                    &mut out,
+                    "    ",
+                    "[Documentation]",
+                    &["Load the platform and firmware, then start it without a peer"],
+                );
+                writeln!(out, "    {}", LOAD_KEYWORD).unwrap();
+                row(&mut out, "    ", "Start Emulation", &[]);
+            }
+        }
+    }
+    out
+}
+
+/// `mach create` through `Create Terminal Tester`
+fn load_rows(out: &mut String, krate: &Crate) {
+    row(out, "    ", "Execute Command", &["mach create"]);
+    row(
+        out,
+        "    ",
+        "Execute Command",
+        &["machine LoadPlatformDescription @${PLATFORM}"],
+    );
+    row(out, "    ", "Execute Command", &["$gdb_port=${GDB_PORT}"]);
+    row(
+        out,
+        "    ",
+        "Execute Command",
+        &["machine StartGdbServer $gdb_port"],
+    );
+    row(out, "    ", "Execute Command", &["sysbus LoadELF @${ELF}"]);
+    row(
+        out,
+        "    ",
+        "Create Terminal Tester",
+        &[&krate.metadata.uart],
+    );
+}
+
+/// Write `indent keyword args...` with the arguments aligned
+fn row(out: &mut String, indent: &str, keyword: &str, args: &[&str]) {
+    let mut line = format!("{}{}", indent, keyword);
+    if !args.is_empty() {
+        let width = ARGUMENT_COLUMN.max(line.len() + 2);
+        line = format!("{:<width$}{}", line, args.join("    "), width = width);
+    }
+    writeln!(out, "{}", line).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Metadata;
+    use std::path::PathBuf;
+
+    fn krate(metadata: &str) -> Crate {
+        Crate {
+            name: "stm32f3-dma".into(),
+            dir: PathBuf::from("stm32f3-dma"),
+            metadata: toml::from_str::<Metadata>(metadata).unwrap(),
+        }
+    }
+
+    #[test]
+    fn renders_a_suite() {
+        let krate = krate(
+            r#"
+title = "DMA"
+[[tests]]
+name = "Should Transfer"
+doc = "Verify the transfer\nand the summary"
+steps = [
+    { wait = "DMA Peripheral Test", timeout = 5 },
+    { comment = "Shell" },
+    { send = "led 3 on" },
+    { execute = "gpioPortA.UserButton Press" },
+    { sleep = 0.1 },
+]
+
+[[tests]]
+name = "Should Decode Frames"
+steps = [{ run = ["frame-decode", "--summary", "${TEMPDIR}/f.bin"] }]
+"#,
+        );
+        let expected = format!(
+            "# {}
+
+*** Settings ***
+Suite Setup                   Setup
+Suite Teardown                Teardown
+Test Setup                    Reset Emulation
+Resource                      ${{RENODEKEYWORDS}}
+Library                       Process
+
+*** Variables ***
+${{PLATFORM}}                   ${{CURDIR}}/../stm32f3_dma.repl
+${{ELF}}                        ${{CURDIR}}/../../target/thumbv7em-none-eabihf/release/stm32f3-dma
+${{GDB_PORT}}                   3333
+
+*** Test Cases ***
+Should Transfer
+    [Documentation]           Verify the transfer
+    ...                       and the summary
+    Start Firmware
+
+    Wait For Line On Uart     DMA Peripheral Test    timeout=5
+
+    # Shell
+    Write Line To Uart        led 3 on
+    Execute Command           gpioPortA.UserButton Press
+    Sleep                     0.1
+
+Should Decode Frames
+    Start Firmware
+
+    ${{result}}=                Run Process    frame-decode    --summary    ${{TEMPDIR}}/f.bin
+    Should Be Equal As Integers  ${{result.rc}}    0    ${{result.stdout}}
+
+*** Keywords ***
+Start Firmware
+    [Documentation]           Load the platform and firmware, then start it
+    Execute Command           mach create
+    Execute Command           machine LoadPlatformDescription @${{PLATFORM}}
+    Execute Command           $gdb_port=${{GDB_PORT}}
+    Execute Command           machine StartGdbServer $gdb_port
+    Execute Command           sysbus LoadELF @${{ELF}}
+    Create Terminal Tester    sysbus.usart1
+    Start Emulation
+",
+            GENERATED_NOTICE
+        );
+        assert_eq!(suite(&krate), expected);
+    }
+
+    #[test]
+    fn peer_tests_start_uart_peer_before_the_emulation() {
+        let krate = krate(
+            r#"
+title = "USART2"
+peer = { uart = "sysbus.usart2", port = 4600 }
+[[tests]]
+name = "Should Talk To Modem"
+peer = ["modem"]
+steps = [{ wait = "AT: OK PASS", timeout = 10 }]
+[[tests]]
+name = "Should Boot"
+steps = []
+"#,
+        );
+        let text = suite(&krate);
+        for line in [
+            "Test Teardown                 Terminate All Processes    kill=True",
+            "Library                       Process",
+            "${PEER_PORT}                  4600",
+            "    Load Firmware\n    Start Process             ${UART_PEER}    --connect    127.0.0.1:${PEER_PORT}    modem\n    Start Emulation\n",
+            "Should Boot\n    Start Firmware\n",
+            "    Execute Command           emulation CreateServerSocketTerminal ${PEER_PORT} \"peer\" false\n    Execute Command           connector Connect sysbus.usart2 peer\n",
+            "Start Firmware\n    [Documentation]           Load the platform and firmware, then start it without a peer\n    Load Firmware\n    Start Emulation\n",
+        ] {
+            assert!(text.contains(line), "no {:?} in\n{}", line, text);
+        }
+    }
+
+    #[test]
+    fn no_process_library_without_peer_or_run() {
+        let krate = krate("title = \"DMA\"\n[[tests]]\nname = \"T\"\nsteps = []\n");
+        let text = suite(&krate);
+        assert!(!text.contains("Library"));
+        assert!(!text.contains("Test Teardown"));
+    }
+
+    #[test]
+    fn rows_align_arguments() {
+        let mut out = String::new();
+        row(&mut out, "    ", "Sleep", &["1"]);
+        row(&mut out, "    ", "Should Be Equal As Integers", &["1", "1"]);
+        row(&mut out, "", "Start Emulation", &[]);
+        assert_eq!(
+            out,
+            "    Sleep                     1\n    Should Be Equal As Integers  1    1\nStart Emulation\n"
+        );
+    }
+}
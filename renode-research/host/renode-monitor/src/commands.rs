@@ -0,0 +1,362 @@
+//! Typed wrappers for monitor commands used by the scripts and suites
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{Error, Monitor};
+
+/// Extra wall-clock allowance per unit of virtual time for `RunFor`
+///
+/// Emulation can run well below real time with tracing or logging enabled.
+const RUN_FOR_SLOWDOWN: u32 = 10;
+
+impl Monitor {
+    /// `mach create "<name>"` and select the new machine
+    pub fn create_machine(&mut self, name: &str) -> Result<(), Error> {
+        self.execute(&format!("mach create \"{}\"", name))?;
+        self.machine = Some(name.to_string());
+        Ok(())
+    }
+
+    /// `mach set "<name>"`
+    pub fn select_machine(&mut self, name: &str) -> Result<(), Error> {
+        self.execute(&format!("mach set \"{}\"", name))?;
+        self.machine = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Remove all machines and terminals (`Clear`, Robot's `Reset Emulation`)
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.execute("Clear")?;
+        self.machine = None;
+        Ok(())
+    }
+
+    /// `machine LoadPlatformDescription @"<path>"`
+    pub fn load_platform(&mut self, path: &Path) -> Result<(), Error> {
+        self.execute(&format!(
+            "machine LoadPlatformDescription {}",
+            path_arg(path)
+        ))
+        .map(drop)
+    }
+
+    /// `sysbus LoadELF @"<path>"`
+    pub fn load_elf(&mut self, path: &Path) -> Result<(), Error> {
+        self.execute(&format!("sysbus LoadELF {}", path_arg(path)))
+            .map(drop)
+    }
+
+    /// Start (or resume) the emulation
+    pub fn start(&mut self) -> Result<(), Error> {
+        self.execute("start").map(drop)
+    }
+
+    /// Pause the emulation
+    pub fn pause(&mut self) -> Result<(), Error> {
+        self.execute("pause").map(drop)
+    }
+
+    /// Reset the selected machine (`machine Reset`)
+    pub fn reset_machine(&mut self) -> Result<(), Error> {
+        self.execute("machine Reset").map(drop)
+    }
+
+    /// Run for `duration` of virtual time, then pause
+    ///
+    /// Blocks until Renode returns to the prompt, allowing the monitor timeout
+    /// plus ten times `duration` of wall-clock time.
+    pub fn run_for(&mut self, duration: Duration) -> Result<(), Error> {
+        let timeout = self.timeout() + duration * RUN_FOR_SLOWDOWN;
+        let command = format!("emulation RunFor \"{}\"", time_interval(duration));
+        self.execute_within(&command, timeout).map(drop)
+    }
+
+    /// Virtual time elapsed on the selected machine (`machine ElapsedVirtualTime`)
+    pub fn elapsed_virtual_time(&mut self) -> Result<Duration, Error> {
+        let command = "machine ElapsedVirtualTime";
+        let output = self.execute(command)?;
+        parse_time_interval(output.trim()).ok_or_else(|| Error::UnexpectedOutput {
+            command: command.into(),
+            output,
+        })
+    }
+
+    /// `emulation CreateUartPtyTerminal "<name>" "<path>" true`
+    pub fn create_uart_pty(&mut self, name: &str, path: &Path) -> Result<(), Error> {
+        self.execute(&format!(
+            "emulation CreateUartPtyTerminal \"{}\" \"{}\" true",
+            name,
+            path.display()
+        ))
+        .map(drop)
+    }
+
+    /// `connector Connect <peripheral> <external>`, e.g. `sysbus.usart1 term`
+    pub fn connect_external(&mut self, peripheral: &str, external: &str) -> Result<(), Error> {
+        self.execute(&format!("connector Connect {} {}", peripheral, external))
+            .map(drop)
+    }
+
+    /// `machine StartGdbServer <port>`
+    pub fn start_gdb_server(&mut self, port: u16) -> Result<(), Error> {
+        self.execute(&format!("machine StartGdbServer {}", port))
+            .map(drop)
+    }
+
+    /// Press a button peripheral, e.g. `gpioPortA.UserButton`
+    pub fn press_button(&mut self, button: &str) -> Result<(), Error> {
+        self.execute(&format!("{} Press", button)).map(drop)
+    }
+
+    /// Release a button peripheral
+    pub fn release_button(&mut self, button: &str) -> Result<(), Error> {
+        self.execute(&format!("{} Release", button)).map(drop)
+    }
+
+    /// Press and immediately release a button peripheral
+    pub fn press_and_release_button(&mut self, button: &str) -> Result<(), Error> {
+        self.execute(&format!("{} PressAndRelease", button))
+            .map(drop)
+    }
+
+    /// Queue `repeat` ADC samples of `millivolts` on `channel`
+    ///
+    /// `adc` is the peripheral path, e.g. `sysbus.adc1`.
+    pub fn feed_adc_sample(
+        &mut self,
+        adc: &str,
+        channel: u32,
+        millivolts: f64,
+        repeat: u32,
+    ) -> Result<(), Error> {
+        self.execute(&format!(
+            "{} FeedVoltageSampleToChannel {} {} {}",
+            adc, channel, millivolts, repeat
+        ))
+        .map(drop)
+    }
+
+    /// Value an ADC channel returns once its queued samples run out
+    pub fn set_adc_default(
+        &mut self,
+        adc: &str,
+        channel: u32,
+        millivolts: f64,
+    ) -> Result<(), Error> {
+        self.execute(&format!(
+            "{} SetDefaultVoltageValue {} {}",
+            adc, millivolts, channel
+        ))
+        .map(drop)
+    }
+
+    /// Core register `index` of `sysbus.cpu` (`GetRegisterUnsafe`)
+    ///
+    /// On the Cortex-M, 0–15 are R0–R15 and 25 is xPSR.
+    pub fn cpu_register(&mut self, index: u32) -> Result<u64, Error> {
+        let command = format!("sysbus.cpu GetRegisterUnsafe {}", index);
+        let output = self.execute(&command)?;
+        let values: Vec<Option<u64>> = numbers(&output).collect();
+        match values[..] {
+            [Some(value)] => Ok(value),
+            _ => Err(Error::UnexpectedOutput { command, output }),
+        }
+    }
+
+    pub fn read_u8(&mut self, address: u64) -> Result<u8, Error> {
+        self.read("ReadByte", address).map(|v| v as u8)
+    }
+
+    pub fn read_u16(&mut self, address: u64) -> Result<u16, Error> {
+        self.read("ReadWord", address).map(|v| v as u16)
+    }
+
+    pub fn read_u32(&mut self, address: u64) -> Result<u32, Error> {
+        self.read("ReadDoubleWord", address).map(|v| v as u32)
+    }
+
+    /// Read `len` bytes starting at `address`
+    pub fn read_bytes(&mut self, address: u64, len: usize) -> Result<Vec<u8>, Error> {
+        let command = format!("sysbus ReadBytes 0x{:08X} {}", address, len);
+        let output = self.execute(&command)?;
+        let bytes: Option<Vec<u8>> = numbers(&output)
+            .map(|v| v.and_then(|v| u8::try_from(v).ok()))
+            .collect();
+        match bytes {
+            Some(bytes) if bytes.len() == len => Ok(bytes),
+            _ => Err(Error::UnexpectedOutput { command, output }),
+        }
+    }
+
+    pub fn write_u8(&mut self, address: u64, value: u8) -> Result<(), Error> {
+        self.write("WriteByte", address, value.into())
+    }
+
+    pub fn write_u16(&mut self, address: u64, value: u16) -> Result<(), Error> {
+        self.write("WriteWord", address, value.into())
+    }
+
+    pub fn write_u32(&mut self, address: u64, value: u32) -> Result<(), Error> {
+        self.write("WriteDoubleWord", address, value.into())
+    }
+
+    /// Write `bytes` starting at `address`
+    pub fn write_bytes(&mut self, address: u64, bytes: &[u8]) -> Result<(), Error> {
+        for (offset, byte) in bytes.iter().enumerate() {
+            self.write_u8(address + offset as u64, *byte)?;
+        }
+        Ok(())
+    }
+
+    /// Save the whole emulation to a snapshot file (`Save @"<path>"`)
+    pub fn save_snapshot(&mut self, path: &Path) -> Result<(), Error> {
+        self.execute(&format!("Save {}", path_arg(path))).map(drop)
+    }
+
+    /// Replace the emulation with a snapshot (`Load @"<path>"`)
+    ///
+    /// The previously selected machine is selected again, since `Load`
+    /// leaves the monitor without a current machine.
+    pub fn load_snapshot(&mut self, path: &Path) -> Result<(), Error> {
+        self.execute(&format!("Load {}", path_arg(path)))?;
+        match self.machine.clone() {
+            Some(name) => self.select_machine(&name),
+            None => Ok(()),
+        }
+    }
+
+    fn read(&mut self, method: &str, address: u64) -> Result<u64, Error> {
+        let command = format!("sysbus {} 0x{:08X}", method, address);
+        let output = self.execute(&command)?;
+        let values: Vec<Option<u64>> = numbers(&output).collect();
+        match values[..] {
+            [Some(value)] => Ok(value),
+            _ => Err(Error::UnexpectedOutput { command, output }),
+        }
+    }
+
+    fn write(&mut self, method: &str, address: u64, value: u64) -> Result<(), Error> {
+        self.execute(&format!(
+            "sysbus {} 0x{:08X} 0x{:X}",
+            method, address, value
+        ))
+        .map(drop)
+    }
+}
+
+/// Renode `TimeInterval` literal (`hh:mm:ss.ffffff`)
+fn time_interval(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{:06}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        duration.subsec_micros()
+    )
+}
+
+/// A path argument, `@"<path>"`, quoted so paths with spaces stay one token
+pub fn path_arg(path: &Path) -> String {
+    format!("@\"{}\"", path.display().to_string().replace('"', "\\\""))
+}
+
+/// Parse a `TimeInterval` as printed by the monitor (`hh:mm:ss.ffffff`)
+fn parse_time_interval(text: &str) -> Option<Duration> {
+    let mut secs = 0.0;
+    for field in text.split(':') {
+        secs = secs * 60.0 + field.trim().parse::<f64>().ok()?;
+    }
+    (secs >= 0.0).then(|| Duration::from_secs_f64(secs))
+}
+
+/// Numbers in command output (`0x1F` or decimal), in order
+///
+/// Yields `None` for a token that looks numeric but does not parse.
+fn numbers(output: &str) -> impl Iterator<Item = Option<u64>> + '_ {
+    output
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | '[' | ']'))
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            match token
+                .strip_prefix("0x")
+                .or_else(|| token.strip_prefix("0X"))
+            {
+                Some(hex) => u64::from_str_radix(hex, 16).ok(),
+                None => token.parse().ok(),
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_intervals() {
+        assert_eq!(time_interval(Duration::ZERO), "00:00:00.000000");
+        assert_eq!(
+            time_interval(Duration::from_millis(1500)),
+            "00:00:01.500000"
+        );
+        assert_eq!(
+            time_interval(Duration::from_micros(3_723_000_007)),
+            "01:02:03.000007"
+        );
+        assert_eq!(
+            time_interval(Duration::from_secs(100 * 3600)),
+            "100:00:00.000000"
+        );
+    }
+
+    #[test]
+    fn parses_printed_time_intervals() {
+        assert_eq!(
+            parse_time_interval("00:00:01.500000"),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            parse_time_interval("01:02:03.25"),
+            Some(Duration::from_secs_f64(3723.25))
+        );
+        assert_eq!(parse_time_interval("42"), Some(Duration::from_secs(42)));
+        for duration in [Duration::from_millis(12_345), Duration::from_secs(7200)] {
+            assert_eq!(
+                parse_time_interval(&time_interval(duration)),
+                Some(duration)
+            );
+        }
+        assert_eq!(parse_time_interval(""), None);
+        assert_eq!(parse_time_interval("00:xx:01"), None);
+        assert_eq!(parse_time_interval("-1"), None);
+    }
+
+    #[test]
+    fn quoted_paths() {
+        assert_eq!(
+            path_arg(Path::new("/tmp/stm32f3.elf")),
+            "@\"/tmp/stm32f3.elf\""
+        );
+        assert_eq!(
+            path_arg(Path::new("/home/me/My Firmware/a.repl")),
+            "@\"/home/me/My Firmware/a.repl\""
+        );
+        assert_eq!(path_arg(Path::new("a\"b")), "@\"a\\\"b\"");
+    }
+
+    #[test]
+    fn numbers_in_output() {
+        let values: Vec<Option<u64>> = numbers("0x2000_0000").collect();
+        assert_eq!(values, [None]);
+        let values: Vec<Option<u64>> = numbers("0x1F").collect();
+        assert_eq!(values, [Some(0x1F)]);
+        // `sysbus ReadBytes` prints a bracketed, comma-separated list
+        let values: Vec<Option<u64>> = numbers("[\n  0x01, 0xFF,\n  0X0a, 17\n]\n").collect();
+        assert_eq!(values, [Some(1), Some(255), Some(10), Some(17)]);
+        let values: Vec<Option<u64>> = numbers("  ").collect();
+        assert!(values.is_empty());
+        let values: Vec<Option<u64>> = numbers("There is no peripheral").collect();
+        assert_eq!(values, [None, None, None, None]);
+    }
+}
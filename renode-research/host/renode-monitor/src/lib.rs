@@ -0,0 +1,80 @@
+//! Typed client for the Renode monitor socket
+//!
+//! `renode --port <n>` exposes the same monitor the `.resc` scripts and
+//! Robot keywords use. This crate starts Renode headless ([`Renode`]),
+//! talks to its monitor ([`Monitor`]) and reads UART output from a PTY
+//! terminal ([`UartReader`]), so host tests can script scenarios in Rust:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use renode_monitor::{Renode, UartReader};
+//!
+//! let mut renode = Renode::launch("renode".as_ref(), 1234, None)?;
+//! let m = &mut renode.monitor;
+//! m.create_machine("stm32f3")?;
+//! m.load_platform("stm32f3-gpio/stm32f3_gpio.repl".as_ref())?;
+//! m.load_elf("target/thumbv7em-none-eabihf/release/stm32f3-gpio".as_ref())?;
+//! m.create_uart_pty("term", "/tmp/uart".as_ref())?;
+//! m.connect_external("sysbus.usart1", "term")?;
+//! let mut uart = UartReader::open("/tmp/uart".as_ref(), Duration::from_secs(5))?;
+//!
+//! m.start()?;
+//! uart.wait_for_line("Waiting for button", Duration::from_secs(5));
+//! m.press_button("gpioPortA.UserButton")?;
+//! uart.wait_for_line("Button press detected: PASS", Duration::from_secs(5));
+//! # Ok::<(), renode_monitor::Error>(())
+//! ```
+//!
+//! Anything without a typed wrapper can be sent with [`Monitor::execute`].
+
+use std::fmt;
+
+mod commands;
+pub mod monitor;
+pub mod renode;
+pub mod uart;
+
+pub use commands::path_arg;
+pub use monitor::Monitor;
+pub use renode::Renode;
+pub use uart::UartReader;
+
+/// Errors talking to Renode
+#[derive(Debug)]
+pub enum Error {
+    /// Socket or file I/O failed
+    Io(std::io::Error),
+    /// Renode could not be started or its monitor could not be reached
+    Connect(String),
+    /// Renode reported an error for a command
+    Command { command: String, output: String },
+    /// No monitor prompt arrived in time
+    Timeout(String),
+    /// A command's output could not be interpreted
+    UnexpectedOutput { command: String, output: String },
+    /// The UART PTY could not be opened
+    Uart(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Connect(msg) => write!(f, "{}", msg),
+            Error::Command { command, output } => write!(f, "`{}` failed: {}", command, output),
+            Error::Timeout(msg) => write!(f, "timed out: {}", msg),
+            Error::UnexpectedOutput { command, output } => {
+                write!(f, "unexpected output from `{}`: {}", command, output)
+            }
+            Error::Uart(msg) => write!(f, "uart: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
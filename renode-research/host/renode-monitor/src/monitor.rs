@@ -0,0 +1,237 @@
+//! Connection to the Renode monitor socket (`renode --port`)
+//!
+//! The monitor speaks a telnet-flavoured line protocol: each command is
+//! echoed back, followed by its output and a prompt such as `(monitor) `
+//! or `(machine-0) `. Telnet negotiation and ANSI colour codes are stripped.
+//! Typed wrappers for common commands are in `commands.rs`.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::Error;
+
+/// Telnet "interpret as command" byte
+const IAC: u8 = 0xFF;
+
+/// Telnet commands: option negotiation (`WILL`, `WONT`, `DO`, `DONT`) is
+/// followed by an option byte; a subnegotiation runs from `SB` to `IAC SE`
+const SE: u8 = 0xF0;
+const SB: u8 = 0xFA;
+const WILL: u8 = 0xFB;
+const DONT: u8 = 0xFE;
+
+/// Marker Renode prints when a command fails
+const ERROR_MARKER: &str = "There was an error executing command";
+
+/// Default time to wait for a command to finish
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct Monitor {
+    stream: TcpStream,
+    timeout: Duration,
+    /// Machine selected with `mach create`/`mach set`, restored after `Load`
+    pub(crate) machine: Option<String>,
+}
+
+impl Monitor {
+    /// Connect to a monitor socket, retrying while Renode starts up
+    pub fn connect(port: u16, startup_timeout: Duration) -> Result<Monitor, Error> {
+        let start = Instant::now();
+        let stream = loop {
+            match TcpStream::connect(("127.0.0.1", port)) {
+                Ok(stream) => break stream,
+                Err(err) if start.elapsed() > startup_timeout => {
+                    return Err(Error::Connect(format!(
+                        "could not connect to port {}: {}",
+                        port, err
+                    )))
+                }
+                Err(_) => thread::sleep(Duration::from_millis(200)),
+            }
+        };
+
+        let mut monitor = Monitor {
+            stream,
+            timeout: DEFAULT_TIMEOUT,
+            machine: None,
+        };
+        monitor.read_until_prompt(startup_timeout)?;
+        Ok(monitor)
+    }
+
+    /// How long [`execute`](Self::execute) waits for a command to finish
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Run a monitor command and return its output (without echo or prompt)
+    pub fn execute(&mut self, command: &str) -> Result<String, Error> {
+        self.execute_within(command, self.timeout)
+    }
+
+    /// [`execute`](Self::execute) with a one-off timeout
+    pub fn execute_within(&mut self, command: &str, timeout: Duration) -> Result<String, Error> {
+        self.stream.write_all(command.as_bytes())?;
+        self.stream.write_all(b"\n")?;
+
+        let output = self.read_until_prompt(timeout)?;
+
+        // Drop the echoed command line
+        let body = match output.split_once('\n') {
+            Some((first, rest)) if first.trim_end().ends_with(command.trim()) => rest,
+            _ => output.as_str(),
+        };
+        let body = body.trim().to_string();
+
+        if body.contains(ERROR_MARKER) {
+            return Err(Error::Command {
+                command: command.to_string(),
+                output: body,
+            });
+        }
+        Ok(body)
+    }
+
+    /// Read until the last line looks like a `(name) ` prompt
+    fn read_until_prompt(&mut self, timeout: Duration) -> Result<String, Error> {
+        let deadline = Instant::now() + timeout;
+        let mut raw = Vec::new();
+        let mut buf = [0u8; 1024];
+
+        self.stream
+            .set_read_timeout(Some(Duration::from_millis(200)))?;
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return Err(Error::Connect("monitor connection closed".into())),
+                Ok(n) => raw.extend_from_slice(&buf[..n]),
+                Err(err)
+                    if err.kind() == std::io::ErrorKind::WouldBlock
+                        || err.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(err) => return Err(err.into()),
+            }
+
+            let text = clean(&raw);
+            if let Some(body) = strip_prompt(&text) {
+                return Ok(body.to_string());
+            }
+            if Instant::now() > deadline {
+                return Err(Error::Timeout(format!("no prompt after: {}", text.trim())));
+            }
+        }
+    }
+}
+
+/// If `text` ends in a monitor prompt, return everything before it
+fn strip_prompt(text: &str) -> Option<&str> {
+    let last_line_start = text.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let last = &text[last_line_start..];
+    let is_prompt = last.starts_with('(')
+        && last.ends_with(") ")
+        && !last[1..last.len() - 2].contains(['(', ')', ' ']);
+    is_prompt.then(|| &text[..last_line_start])
+}
+
+/// Remove telnet negotiation, ANSI escape sequences and carriage returns
+///
+/// An escaped 0xFF data byte (`IAC IAC`) is dropped as well: the monitor
+/// writes UTF-8, where 0xFF never occurs, so it could only decode to
+/// U+FFFD.
+fn clean(raw: &[u8]) -> String {
+    let mut bytes = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        match raw[i] {
+            IAC => match raw.get(i + 1) {
+                // Escaped 0xFF data byte, never part of UTF-8 text
+                Some(&IAC) => i += 2,
+                Some(WILL..=DONT) => i += 3,
+                Some(&SB) => {
+                    i += 2;
+                    while i < raw.len() && !(raw[i] == IAC && raw.get(i + 1) == Some(&SE)) {
+                        i += 1;
+                    }
+                    i += 2;
+                }
+                // NOP, GA and the other two-byte commands
+                _ => i += 2,
+            },
+            // ESC [ ... final byte
+            0x1B => {
+                i += 1;
+                if raw.get(i) == Some(&b'[') {
+                    i += 1;
+                    while i < raw.len() && !(0x40..=0x7E).contains(&raw[i]) {
+                        i += 1;
+                    }
+                }
+                i += 1;
+            }
+            b'\r' => i += 1,
+            b => {
+                bytes.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_strips_negotiation() {
+        // DO ECHO, WILL SUPPRESS-GO-AHEAD, then text
+        assert_eq!(clean(b"\xff\xfd\x01\xff\xfb\x03(monitor) "), "(monitor) ");
+        // Subnegotiation up to IAC SE, with an IAC IAC inside it
+        assert_eq!(clean(b"a\xff\xfa\x18\x01\xff\xff\x02\xff\xf0b"), "ab");
+        // Two-byte commands: NOP and GA
+        assert_eq!(clean(b"a\xff\xf1b\xff\xf9c"), "abc");
+    }
+
+    #[test]
+    fn clean_drops_escaped_iac() {
+        assert_eq!(clean(b"x\xff\xffy"), "xy");
+        assert_eq!(clean(b"\xff\xff\xff\xfb\x01"), "");
+        // Other bytes that are not UTF-8 still show up
+        assert_eq!(clean(b"x\xfey"), "x\u{fffd}y");
+    }
+
+    #[test]
+    fn clean_tolerates_truncated_sequences() {
+        assert_eq!(clean(b"ok\xff"), "ok");
+        assert_eq!(clean(b"ok\xff\xfb"), "ok");
+        assert_eq!(clean(b"ok\xff\xfa\x18\x01"), "ok");
+        assert_eq!(clean(b"ok\x1b["), "ok");
+    }
+
+    #[test]
+    fn clean_strips_ansi_and_carriage_returns() {
+        assert_eq!(
+            clean(b"\x1b[32mrunning\x1b[0m\r\n\x1b[1;31m(machine-0) \x1b[0m"),
+            "running\n(machine-0) "
+        );
+    }
+
+    #[test]
+    fn strip_prompt_returns_the_body() {
+        assert_eq!(strip_prompt("echo\nout\n(monitor) "), Some("echo\nout\n"));
+        assert_eq!(strip_prompt("(STM32F3_DMA) "), Some(""));
+    }
+
+    #[test]
+    fn strip_prompt_needs_a_whole_prompt() {
+        assert_eq!(strip_prompt("out\n(monitor)"), None);
+        assert_eq!(strip_prompt("out\n(two words) "), None);
+        assert_eq!(strip_prompt("out\n(a(b)) "), None);
+        assert_eq!(strip_prompt("out\nmonitor) "), None);
+        assert_eq!(strip_prompt("(monitor) \nmore"), None);
+    }
+}
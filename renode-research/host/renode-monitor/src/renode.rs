@@ -0,0 +1,67 @@
+//! Headless Renode process management
+
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use crate::commands::path_arg;
+use crate::monitor::Monitor;
+use crate::Error;
+
+/// How long to wait for Renode to open its monitor socket
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A running Renode instance with a connected monitor
+pub struct Renode {
+    child: Child,
+    pub monitor: Monitor,
+}
+
+impl Renode {
+    /// Start `renode` without a GUI, with its monitor on `port`
+    ///
+    /// Renode's own log is written to `log_file` if given, otherwise hidden.
+    pub fn launch(binary: &Path, port: u16, log_file: Option<&Path>) -> Result<Renode, Error> {
+        let mut command = Command::new(binary);
+        command
+            .arg("--disable-xwt")
+            .arg("--port")
+            .arg(port.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if log_file.is_none() {
+            command.arg("--hide-log");
+        }
+
+        let mut child = command
+            .spawn()
+            .map_err(|err| Error::Connect(format!("cannot start {}: {}", binary.display(), err)))?;
+
+        let monitor = match Monitor::connect(port, STARTUP_TIMEOUT) {
+            Ok(monitor) => monitor,
+            Err(err) => {
+                // Don't leave a half-started Renode behind
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err);
+            }
+        };
+        let mut renode = Renode { child, monitor };
+
+        if let Some(path) = log_file {
+            renode
+                .monitor
+                .execute(&format!("logFile {}", path_arg(path)))?;
+        }
+        Ok(renode)
+    }
+}
+
+impl Drop for Renode {
+    fn drop(&mut self) {
+        let _ = self.monitor.execute("quit");
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
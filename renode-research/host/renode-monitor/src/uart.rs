@@ -0,0 +1,204 @@
+//! Line reader for the Renode UART PTY terminal
+//!
+//! `emulation CreateUartPtyTerminal "term" "/tmp/uart" true` makes Renode
+//! create a pseudo-terminal and symlink it at `/tmp/uart`. A background
+//! thread reads it, splits the stream into lines and forwards them over a
+//! channel so the runner can wait for expected output with a timeout.
+//! Lines written go the other way, into the emulated UART's receiver.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::sys::termios::{self, SetArg};
+
+use crate::Error;
+
+pub struct UartReader {
+    lines: Receiver<String>,
+    /// The same PTY, for typing into the UART
+    writer: File,
+    /// Every line received so far, in order
+    transcript: Vec<String>,
+}
+
+impl UartReader {
+    /// Open the PTY at `path`, waiting up to `timeout` for Renode to create it
+    pub fn open(path: &Path, timeout: Duration) -> Result<UartReader, Error> {
+        let start = Instant::now();
+        let file = loop {
+            match OpenOptions::new().read(true).write(true).open(path) {
+                Ok(file) => break file,
+                Err(err) if start.elapsed() > timeout => {
+                    return Err(Error::Uart(format!(
+                        "cannot open {}: {}",
+                        path.display(),
+                        err
+                    )))
+                }
+                Err(_) => thread::sleep(Duration::from_millis(100)),
+            }
+        };
+
+        // Raw mode: no echo back into the emulated UART, no CR/LF rewriting
+        if let Ok(mut attrs) = termios::tcgetattr(&file) {
+            termios::cfmakeraw(&mut attrs);
+            let _ = termios::tcsetattr(&file, SetArg::TCSANOW, &attrs);
+        }
+
+        let writer = file.try_clone()?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || read_lines(file, tx));
+
+        Ok(UartReader {
+            lines: rx,
+            writer,
+            transcript: Vec::new(),
+        })
+    }
+
+    /// Send `text` followed by CR, as Robot's `Write Line To Uart` does
+    pub fn write_line(&mut self, text: &str) -> Result<(), Error> {
+        self.writer.write_all(text.as_bytes())?;
+        self.writer.write_all(b"\r")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Wait for a line containing `pattern`, consuming lines before it
+    ///
+    /// Returns the matching line, or `None` on timeout.
+    pub fn wait_for_line(&mut self, pattern: &str, timeout: Duration) -> Option<String> {
+        self.wait_for(timeout, |line| line.contains(pattern))
+    }
+
+    /// Wait for a line `matches` accepts, consuming lines before it
+    ///
+    /// Returns the matching line, or `None` on timeout.
+    pub fn wait_for(
+        &mut self,
+        timeout: Duration,
+        mut matches: impl FnMut(&str) -> bool,
+    ) -> Option<String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(remaining) {
+                Ok(line) => {
+                    self.transcript.push(line.clone());
+                    if matches(&line) {
+                        return Some(line);
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => {
+                    return None
+                }
+            }
+        }
+    }
+
+    /// Collect any lines that arrive within `quiet` of each other
+    pub fn drain(&mut self, quiet: Duration) {
+        while let Ok(line) = self.lines.recv_timeout(quiet) {
+            self.transcript.push(line);
+        }
+    }
+
+    /// Lines received so far
+    pub fn transcript(&self) -> &[String] {
+        &self.transcript
+    }
+
+    /// Take ownership of the transcript
+    pub fn into_transcript(self) -> Vec<String> {
+        self.transcript
+    }
+}
+
+/// Reader thread: split the byte stream on `\n`, dropping `\r`
+fn read_lines(mut input: impl Read, tx: mpsc::Sender<String>) {
+    let mut buf = [0u8; 256];
+    let mut line = Vec::new();
+    loop {
+        let n = match input.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        for &byte in &buf[..n] {
+            match byte {
+                b'\n' => {
+                    let text = String::from_utf8_lossy(&line).into_owned();
+                    line.clear();
+                    if tx.send(text).is_err() {
+                        return;
+                    }
+                }
+                b'\r' => {}
+                b => line.push(b),
+            }
+        }
+    }
+    if !line.is_empty() {
+        let _ = tx.send(String::from_utf8_lossy(&line).into_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hands out its chunks one `read` at a time, as a PTY does
+    struct Chunks(Vec<&'static [u8]>);
+
+    impl Read for Chunks {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.0.is_empty() {
+                return Ok(0);
+            }
+            let chunk = self.0.remove(0);
+            buf[..chunk.len()].copy_from_slice(chunk);
+            Ok(chunk.len())
+        }
+    }
+
+    fn lines(chunks: Vec<&'static [u8]>) -> Vec<String> {
+        let (tx, rx) = mpsc::channel();
+        read_lines(Chunks(chunks), tx);
+        rx.iter().collect()
+    }
+
+    #[test]
+    fn splits_lines_across_reads() {
+        assert_eq!(
+            lines(vec![b"hello wo", b"rld!\r\nBuild: stm32f3", b"-uart\r\n> "]),
+            ["hello world!", "Build: stm32f3-uart", "> "]
+        );
+    }
+
+    #[test]
+    fn keeps_empty_lines_and_drops_carriage_returns() {
+        assert_eq!(lines(vec![b"\r\n\n\ra\rb\n"]), ["", "", "ab"]);
+        assert!(lines(vec![b"\r"]).is_empty());
+    }
+
+    #[test]
+    fn replaces_invalid_utf8() {
+        assert_eq!(lines(vec![b"\xffok\n"]), ["\u{fffd}ok"]);
+    }
+
+    #[test]
+    fn stops_on_a_read_error() {
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::Other.into())
+            }
+        }
+        let (tx, rx) = mpsc::channel();
+        read_lines(Broken, tx);
+        assert!(rx.iter().next().is_none());
+    }
+}
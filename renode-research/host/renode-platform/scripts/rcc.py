@@ -0,0 +1,25 @@
+if request.isInit:
+    lastVal = 0
+    ahbenr = 0
+    apb1enr = 0
+    apb2enr = 0
+elif request.isRead:
+    if request.offset == 0x00:  # CR
+        request.value = 0x00000083  # HSI ready, HSI on
+    elif request.offset == 0x04:  # CFGR
+        request.value = 0x00000000
+    elif request.offset == 0x14:  # AHBENR
+        request.value = ahbenr
+    elif request.offset == 0x18:  # APB2ENR
+        request.value = apb2enr
+    elif request.offset == 0x1C:  # APB1ENR
+        request.value = apb1enr
+    else:
+        request.value = 0
+elif request.isWrite:
+    if request.offset == 0x14:  # AHBENR
+        ahbenr = request.value
+    elif request.offset == 0x18:  # APB2ENR
+        apb2enr = request.value
+    elif request.offset == 0x1C:  # APB1ENR
+        apb1enr = request.value
@@ -0,0 +1,145 @@
+//! Extra devices the peripheral tests attach to the SoC
+
+use crate::stm32f303::{on, sysbus};
+use crate::{Lines, Peripheral, Registration, Value};
+
+/// BME280 temperature/humidity/pressure sensor on an I2C bus
+///
+/// The address is 0x76, or 0x77 with SDO high.
+pub fn bme280(name: &str, bus: &str, address: u64) -> Peripheral {
+    Peripheral::new(
+        name,
+        "I2C.BME280",
+        Registration::At {
+            parent: bus.into(),
+            address: Some(address),
+        },
+    )
+    .comment(&format!(
+        "{} sensor: BME280 at 0x{:X}",
+        bus.to_uppercase(),
+        address
+    ))
+}
+
+/// Echoes MOSI back on MISO
+pub fn spi_loopback(name: &str, bus: &str) -> Peripheral {
+    Peripheral::new(name, "SPI.SPILoopback", on(bus)).comment(&format!(
+        "{} loopback: echoes MOSI data back to MISO",
+        bus.to_uppercase()
+    ))
+}
+
+/// ADC1, using Renode's STM32F0 ADC model which is compatible with the F3
+pub fn adc1() -> Peripheral {
+    Peripheral::new("adc1", "Analog.STM32F0_ADC", sysbus(0x5000_0000))
+        .comment(
+            "ADC1 - Analog to Digital Converter\nUsing STM32F0_ADC which is compatible with F3",
+        )
+        .property("referenceVoltage", Value::Float(3.3))
+        .property("externalEventFrequency", Value::Int(1000))
+        .irq(18)
+}
+
+/// Independent watchdog clocked from the ~40 kHz LSI
+pub fn iwdg() -> Peripheral {
+    Peripheral::new(
+        "iwdg",
+        "Timers.STM32_IndependentWatchdog",
+        sysbus(0x4000_3000),
+    )
+    .comment(
+        "Independent Watchdog Timer (IWDG)\nLSI clock is typically 40kHz, prescaler divides this",
+    )
+    .property("frequency", Value::Int(40_000))
+}
+
+/// RTC, using the STM32F4 model; the alarm goes to EXTI line 17
+pub fn rtc() -> Peripheral {
+    Peripheral::new("rtc", "Timers.STM32F4_RTC", sysbus(0x4000_2800))
+        .comment("Real-Time Clock (RTC)\nUsing STM32F4_RTC which is compatible with F3")
+        .connect(
+            Some(Lines::Named("AlarmIRQ".into())),
+            "exti",
+            Lines::One(17),
+        )
+}
+
+/// CRC unit as a Python peripheral (CRC-32, polynomial 0x04C11DB7)
+pub fn crc() -> Peripheral {
+    python(
+        "crc",
+        0x4002_3000,
+        include_str!("../scripts/crc.py"),
+        "CRC Calculation Unit - Python peripheral implementation\n\
+         STM32F3 CRC uses polynomial 0x04C11DB7 (CRC-32/MPEG-2)",
+    )
+}
+
+/// Two-channel 12-bit DAC as a Python peripheral (PA4, PA5)
+pub fn dac() -> Peripheral {
+    python(
+        "dac",
+        0x4000_7400,
+        include_str!("../scripts/dac.py"),
+        "DAC - Digital to Analog Converter\n\
+         2 channels, 12-bit resolution\n\
+         PA4 = DAC1 output, PA5 = DAC2 output",
+    )
+}
+
+/// A 1 KiB Python peripheral running `script`
+fn python(name: &str, address: u64, script: &str, comment: &str) -> Peripheral {
+    Peripheral::new(name, "Python.PythonPeripheral", sysbus(address))
+        .comment(comment)
+        .property("size", Value::Hex(0x400))
+        .property("initable", Value::Bool(true))
+        .property("script", Value::Script(script.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bme280_on_its_bus() {
+        assert_eq!(
+            bme280("env", "i2c2", 0x77).to_string(),
+            "// I2C2 sensor: BME280 at 0x77\nenv: I2C.BME280 @ i2c2 0x77\n"
+        );
+    }
+
+    #[test]
+    fn python_peripherals_carry_their_scripts() {
+        for (peripheral, marker) in [(crc(), "0x04C11DB7"), (dac(), "DAC")] {
+            let script = peripheral
+                .properties
+                .iter()
+                .find_map(|p| match &p.value {
+                    Value::Script(script) if p.name == "script" => Some(script),
+                    _ => None,
+                })
+                .unwrap();
+            assert!(!script.is_empty(), "{}", peripheral.name);
+            assert!(
+                peripheral.to_string().contains(marker),
+                "{}",
+                peripheral.name
+            );
+        }
+    }
+
+    #[test]
+    fn devices_fit_the_soc() {
+        let platform = crate::Platform::new("All")
+            .using("soc.repl", crate::stm32f303::platform())
+            .with(adc1())
+            .with(crc())
+            .with(dac())
+            .with(iwdg())
+            .with(rtc())
+            .with(bme280("i2cSensor", "i2c1", 0x76))
+            .with(spi_loopback("spi1Loopback", "spi1"));
+        assert_eq!(platform.check(), Ok(()));
+    }
+}
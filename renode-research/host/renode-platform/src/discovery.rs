@@ -0,0 +1,74 @@
+//! STM32F3 Discovery board attachments
+//!
+//! The user button is on PA0 (active high). The eight user LEDs are on
+//! PE8-PE15; LD3 on PE9 is the firmware's status LED.
+//!
+//! | LED | Colour | Pin |
+//! |-----|--------|-----|
+//! | LD3 | Red | PE9 |
+//! | LD4 | Blue | PE8 |
+//! | LD5 | Orange | PE10 |
+//! | LD6 | Green | PE15 |
+//! | LD7 | Green | PE11 |
+//! | LD8 | Orange | PE14 |
+//! | LD9 | Blue | PE12 |
+//! | LD10 | Red | PE13 |
+
+use crate::stm32f303::on;
+use crate::{Lines, Peripheral};
+
+/// Header line shared by the Discovery variants
+pub const BOARD: &str = "STM32F303VCT6: 256KB Flash, 40KB SRAM, 8KB CCM";
+
+/// User button on PA0, active high
+pub fn user_button() -> Peripheral {
+    Peripheral::new("UserButton", "Miscellaneous.Button", on("gpioPortA"))
+        .comment("User Button on PA0")
+        .connect(None, "gpioPortA", Lines::One(0))
+}
+
+/// LD3 on PE9 as `UserLED`
+pub fn user_led() -> [Peripheral; 2] {
+    led("UserLED", 9)
+}
+
+/// An LED named `name` driven by PE`pin`
+///
+/// Returns the LED and the `gpioPortE` update wiring the pin to it.
+pub fn led(name: &str, pin: u32) -> [Peripheral; 2] {
+    [
+        Peripheral::new(name, "Miscellaneous.LED", on("gpioPortE"))
+            .comment(&format!("LED on PE{}", pin)),
+        Peripheral::update("gpioPortE").connect(Some(Lines::One(pin)), name, Lines::One(0)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn led_wires_its_pin() {
+        let [led, wiring] = led("LD4", 8);
+        assert_eq!(
+            led.to_string(),
+            "// LED on PE8\nLD4: Miscellaneous.LED @ gpioPortE\n"
+        );
+        assert_eq!(wiring.to_string(), "gpioPortE:\n    8 -> LD4@0\n");
+    }
+
+    #[test]
+    fn user_led_is_ld3() {
+        let [led, wiring] = user_led();
+        assert_eq!(led.name, "UserLED");
+        assert_eq!(wiring.connections[0].source, Some(Lines::One(9)));
+    }
+
+    #[test]
+    fn button_on_pa0() {
+        assert_eq!(
+            user_button().to_string(),
+            "// User Button on PA0\nUserButton: Miscellaneous.Button @ gpioPortA\n    -> gpioPortA@0\n"
+        );
+    }
+}
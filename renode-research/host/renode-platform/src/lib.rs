@@ -0,0 +1,68 @@
+//! Typed builder for the STM32F303 Renode platform descriptions
+//!
+//! Every firmware crate's `stm32f3_<x>.repl` is the same SoC
+//! ([`stm32f303`]), the same Discovery board wiring ([`discovery`]) and at
+//! most one extra device ([`devices`]), except `stm32f3-selftest`, which
+//! has all of them. The per-crate compositions live in
+//! [`variants`] and are checked for unknown references, duplicate names and
+//! overlapping bus addresses before they are rendered:
+//!
+//! ```
+//! use renode_platform::{devices, discovery, stm32f303, Platform};
+//!
+//! let repl = Platform::new("STM32F3 Discovery Board Platform with I2C Sensor")
+//!     .using(stm32f303::REPL_PATH, stm32f303::platform())
+//!     .with(discovery::user_button())
+//!     .with_all(discovery::user_led())
+//!     .with(devices::bme280("i2cSensor", "i2c1", 0x76))
+//!     .render()?;
+//! assert!(repl.contains("i2cSensor: I2C.BME280 @ i2c1 0x76"));
+//! # Ok::<(), renode_platform::Error>(())
+//! ```
+//!
+//! `renode-gen` writes the rendered variants next to each crate.
+
+use std::fmt;
+
+pub mod devices;
+pub mod discovery;
+pub mod platform;
+pub mod stm32f303;
+pub mod variants;
+
+pub use platform::{Connection, Lines, Peripheral, Platform, Property, Registration, Value};
+
+/// A platform description that would not load in Renode
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// Two peripherals with the same name
+    Duplicate(String),
+    /// A registration, connection, property or update names a peripheral
+    /// that is not defined
+    UnknownPeripheral {
+        peripheral: String,
+        reference: String,
+    },
+    /// Two peripherals claim the same bus address range
+    Overlap { first: String, second: String },
+    /// A connection's source and target line counts differ
+    Connection { peripheral: String, msg: String },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Duplicate(name) => write!(f, "peripheral `{}` defined twice", name),
+            Error::UnknownPeripheral {
+                peripheral,
+                reference,
+            } => write!(f, "`{}` refers to unknown `{}`", peripheral, reference),
+            Error::Overlap { first, second } => {
+                write!(f, "`{}` and `{}` overlap on the bus", first, second)
+            }
+            Error::Connection { peripheral, msg } => write!(f, "`{}`: {}", peripheral, msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
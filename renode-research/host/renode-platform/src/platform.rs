@@ -0,0 +1,687 @@
+//! Platform description model, rendering and checks
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use crate::Error;
+
+/// Where a peripheral is registered
+#[derive(Clone, Debug, PartialEq)]
+pub enum Registration {
+    /// `@ parent` or `@ parent address` (e.g. `@ sysbus 0x40013800`,
+    /// `@ i2c1 0x76`, `@ gpioPortA`)
+    At {
+        parent: String,
+        address: Option<u64>,
+    },
+    /// `@ parent <base, +size>`
+    Range {
+        parent: String,
+        base: u64,
+        size: u64,
+    },
+    /// `@ none`: not registered anywhere (e.g. combiners)
+    None,
+}
+
+/// A property value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// Decimal integer
+    Int(u64),
+    /// Hexadecimal integer (`0x40000`)
+    Hex(u64),
+    Float(f64),
+    Bool(bool),
+    /// Quoted string
+    Str(String),
+    /// Reference to another peripheral by name
+    Ref(String),
+    /// Multi-line `'''...'''` script (Python peripherals)
+    Script(String),
+}
+
+/// GPIO/IRQ line numbers on either side of a connection
+#[derive(Clone, Debug, PartialEq)]
+pub enum Lines {
+    /// `9`
+    One(u32),
+    /// `[0-4]`
+    Range(u32, u32),
+    /// A named output such as `IRQ` or `AlarmIRQ`
+    Named(String),
+}
+
+impl Lines {
+    fn count(&self) -> u32 {
+        match self {
+            Lines::Range(first, last) => last.saturating_sub(*first) + 1,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for Lines {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lines::One(n) => write!(f, "{}", n),
+            Lines::Range(first, last) => write!(f, "[{}-{}]", first, last),
+            Lines::Named(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// `source -> target@lines`; no source means the default output
+#[derive(Clone, Debug, PartialEq)]
+pub struct Connection {
+    pub source: Option<Lines>,
+    pub target: String,
+    pub lines: Lines,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Property {
+    pub name: String,
+    pub value: Value,
+    /// Trailing `// comment`
+    pub comment: Option<String>,
+}
+
+/// One entry of a platform description
+///
+/// An entry without a model updates a peripheral defined earlier (or in an
+/// included file), e.g. to wire a GPIO pin to a board LED.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Peripheral {
+    pub name: String,
+    /// Renode type, e.g. `UART.STM32F7_USART`
+    pub model: Option<String>,
+    pub registration: Option<Registration>,
+    pub properties: Vec<Property>,
+    pub connections: Vec<Connection>,
+    /// Monitor commands for an `init:` block
+    pub init: Vec<String>,
+    /// `//` comment lines above the entry
+    pub comments: Vec<String>,
+}
+
+impl Peripheral {
+    /// A new peripheral of type `model` registered on the system bus
+    pub fn new(name: &str, model: &str, registration: Registration) -> Peripheral {
+        Peripheral {
+            name: name.to_string(),
+            model: Some(model.to_string()),
+            registration: Some(registration),
+            properties: Vec::new(),
+            connections: Vec::new(),
+            init: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+
+    /// An update to an existing peripheral
+    pub fn update(name: &str) -> Peripheral {
+        Peripheral {
+            name: name.to_string(),
+            model: None,
+            registration: None,
+            properties: Vec::new(),
+            connections: Vec::new(),
+            init: Vec::new(),
+            comments: Vec::new(),
+        }
+    }
+
+    pub fn comment(mut self, text: &str) -> Peripheral {
+        self.comments.extend(text.lines().map(str::to_string));
+        self
+    }
+
+    pub fn property(mut self, name: &str, value: Value) -> Peripheral {
+        self.properties.push(Property {
+            name: name.to_string(),
+            value,
+            comment: None,
+        });
+        self
+    }
+
+    /// A property with a trailing comment, e.g. `size: 0x40000  // 256KB`
+    pub fn property_noted(mut self, name: &str, value: Value, comment: &str) -> Peripheral {
+        self.properties.push(Property {
+            name: name.to_string(),
+            value,
+            comment: Some(comment.to_string()),
+        });
+        self
+    }
+
+    /// Connect an output to `target@lines`
+    pub fn connect(mut self, source: Option<Lines>, target: &str, lines: Lines) -> Peripheral {
+        self.connections.push(Connection {
+            source,
+            target: target.to_string(),
+            lines,
+        });
+        self
+    }
+
+    /// Connect the `IRQ` output to an NVIC line
+    pub fn irq(self, nvic_line: u32) -> Peripheral {
+        self.connect(
+            Some(Lines::Named("IRQ".into())),
+            "nvic",
+            Lines::One(nvic_line),
+        )
+    }
+
+    pub fn init(mut self, command: &str) -> Peripheral {
+        self.init.push(command.to_string());
+        self
+    }
+
+    /// `size` property, used for overlap checks of single-address registrations
+    fn size(&self) -> Option<u64> {
+        self.properties
+            .iter()
+            .find(|p| p.name == "size")
+            .and_then(|p| match p.value {
+                Value::Int(n) | Value::Hex(n) => Some(n),
+                _ => None,
+            })
+    }
+
+    /// Address range on the system bus, if known
+    fn sysbus_range(&self) -> Option<(u64, u64)> {
+        match &self.registration {
+            Some(Registration::Range { parent, base, size }) if parent == "sysbus" => {
+                Some((*base, *size))
+            }
+            Some(Registration::At {
+                parent,
+                address: Some(address),
+            }) if parent == "sysbus" => self.size().map(|size| (*address, size)),
+            _ => None,
+        }
+    }
+}
+
+/// A platform description (`.repl`)
+#[derive(Clone, Debug, Default)]
+pub struct Platform {
+    /// `//` comment lines at the top of the file
+    pub header: Vec<String>,
+    /// Included descriptions: the `using` path and the model behind it
+    pub includes: Vec<(String, Platform)>,
+    pub peripherals: Vec<Peripheral>,
+}
+
+impl Platform {
+    pub fn new(header: &str) -> Platform {
+        Platform {
+            header: header.lines().map(str::to_string).collect(),
+            ..Platform::default()
+        }
+    }
+
+    /// `using "path"`, with `base` describing what that file defines
+    pub fn using(mut self, path: &str, base: Platform) -> Platform {
+        self.includes.push((path.to_string(), base));
+        self
+    }
+
+    pub fn with(mut self, peripheral: Peripheral) -> Platform {
+        self.peripherals.push(peripheral);
+        self
+    }
+
+    pub fn with_all(mut self, peripherals: impl IntoIterator<Item = Peripheral>) -> Platform {
+        self.peripherals.extend(peripherals);
+        self
+    }
+
+    /// Every peripheral defined here or in an included description
+    pub fn defined(&self) -> Vec<&Peripheral> {
+        let mut all: Vec<&Peripheral> = self
+            .includes
+            .iter()
+            .flat_map(|(_, base)| base.defined())
+            .collect();
+        all.extend(self.peripherals.iter().filter(|p| p.model.is_some()));
+        all
+    }
+
+    /// Check names, references, bus addresses and connection widths
+    pub fn check(&self) -> Result<(), Error> {
+        for (_, base) in &self.includes {
+            base.check()?;
+        }
+
+        let defined = self.defined();
+        let mut names: HashSet<&str> = HashSet::new();
+        for peripheral in &defined {
+            if !names.insert(&peripheral.name) {
+                return Err(Error::Duplicate(peripheral.name.clone()));
+            }
+        }
+        // Built-in names every machine has
+        names.insert("sysbus");
+        let exists = |name: &str| names.contains(name);
+
+        let mut bus_addresses: HashMap<(&str, u64), &str> = HashMap::new();
+        for peripheral in &defined {
+            if let Some(Registration::At {
+                parent,
+                address: Some(address),
+            }) = &peripheral.registration
+            {
+                if let Some(other) = bus_addresses.insert((parent, *address), &peripheral.name) {
+                    return Err(Error::Overlap {
+                        first: other.to_string(),
+                        second: peripheral.name.clone(),
+                    });
+                }
+            }
+        }
+
+        for peripheral in &self.peripherals {
+            let unknown = |reference: &str| Error::UnknownPeripheral {
+                peripheral: peripheral.name.clone(),
+                reference: reference.to_string(),
+            };
+
+            if peripheral.model.is_none() && !exists(&peripheral.name) {
+                return Err(unknown(&peripheral.name));
+            }
+            match &peripheral.registration {
+                Some(Registration::At { parent, .. } | Registration::Range { parent, .. })
+                    if !exists(parent) =>
+                {
+                    return Err(unknown(parent));
+                }
+                _ => {}
+            }
+            for connection in &peripheral.connections {
+                if !exists(&connection.target) {
+                    return Err(unknown(&connection.target));
+                }
+                if let Some(source) = &connection.source {
+                    if source.count() != connection.lines.count() {
+                        return Err(Error::Connection {
+                            peripheral: peripheral.name.clone(),
+                            msg: format!(
+                                "{} lines connected to {}@{}",
+                                source.count(),
+                                connection.target,
+                                connection.lines
+                            ),
+                        });
+                    }
+                }
+            }
+            for property in &peripheral.properties {
+                if let Value::Ref(reference) = &property.value {
+                    if !exists(reference) {
+                        return Err(unknown(reference));
+                    }
+                }
+            }
+        }
+
+        let mut ranges: Vec<(u64, u64, &str)> = defined
+            .iter()
+            .filter_map(|p| {
+                p.sysbus_range()
+                    .map(|(base, size)| (base, size, p.name.as_str()))
+            })
+            .collect();
+        ranges.sort();
+        for pair in ranges.windows(2) {
+            let (base, size, first) = pair[0];
+            let (next, _, second) = pair[1];
+            if base + size > next {
+                return Err(Error::Overlap {
+                    first: first.to_string(),
+                    second: second.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the platform and render it as `.repl` text
+    pub fn render(&self) -> Result<String, Error> {
+        self.check()?;
+        Ok(self.to_string())
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sections = Vec::new();
+        if !self.header.is_empty() {
+            sections.push(comment_lines(&self.header));
+        }
+        for (path, _) in &self.includes {
+            sections.push(format!("using \"{}\"\n", path));
+        }
+        for peripheral in &self.peripherals {
+            sections.push(peripheral.to_string());
+        }
+        write!(f, "{}", sections.join("\n"))
+    }
+}
+
+impl fmt::Display for Peripheral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = comment_lines(&self.comments);
+        write!(out, "{}:", self.name)?;
+        if let Some(model) = &self.model {
+            write!(out, " {}", model)?;
+        }
+        match &self.registration {
+            Some(Registration::At { parent, address }) => {
+                write!(out, " @ {}", parent)?;
+                if let Some(address) = address {
+                    write!(out, " {}", hex(*address))?;
+                }
+            }
+            Some(Registration::Range { parent, base, size }) => {
+                write!(out, " @ {} <{}, +0x{:X}>", parent, hex(*base), size)?;
+            }
+            Some(Registration::None) => write!(out, " @ none")?,
+            None => {}
+        }
+        out.push('\n');
+
+        for property in &self.properties {
+            let value = match &property.value {
+                Value::Int(n) => n.to_string(),
+                Value::Hex(n) => format!("0x{:X}", n),
+                Value::Float(x) => format!("{:?}", x),
+                Value::Bool(b) => b.to_string(),
+                Value::Str(s) => format!("\"{}\"", s),
+                Value::Ref(name) => name.clone(),
+                Value::Script(script) => format!("'''\n{}'''", script),
+            };
+            write!(out, "    {}: {}", property.name, value)?;
+            if let Some(comment) = &property.comment {
+                write!(out, "  // {}", comment)?;
+            }
+            out.push('\n');
+        }
+        for connection in &self.connections {
+            out.push_str("    ");
+            if let Some(source) = &connection.source {
+                write!(out, "{} ", source)?;
+            }
+            writeln!(out, "-> {}@{}", connection.target, connection.lines)?;
+        }
+        if !self.init.is_empty() {
+            out.push_str("    init:\n");
+            for command in &self.init {
+                writeln!(out, "        {}", command)?;
+            }
+        }
+        write!(f, "{}", out)
+    }
+}
+
+fn comment_lines(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("// {}\n", line)).collect()
+}
+
+/// Bus addresses: 32-bit addresses zero-padded, device addresses as-is
+fn hex(address: u64) -> String {
+    if address > 0xFFFF {
+        format!("0x{:08X}", address)
+    } else {
+        format!("0x{:X}", address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stm32f303::{on, sysbus};
+
+    fn soc() -> Platform {
+        Platform::new("SoC")
+            .with(
+                Peripheral::new("nvic", "IRQControllers.NVIC", sysbus(0xE000_E000)).connect(
+                    Some(Lines::Named("IRQ".into())),
+                    "cpu",
+                    Lines::One(0),
+                ),
+            )
+            .with(
+                Peripheral::new("cpu", "CPU.CortexM", on("sysbus"))
+                    .property("nvic", Value::Ref("nvic".into())),
+            )
+            .with(
+                Peripheral::new("sram", "Memory.MappedMemory", sysbus(0x2000_0000))
+                    .property("size", Value::Hex(0xA000)),
+            )
+    }
+
+    fn board(peripheral: Peripheral) -> Platform {
+        Platform::new("Board")
+            .using("soc.repl", soc())
+            .with(peripheral)
+    }
+
+    #[test]
+    fn renders_entries() {
+        let uart = Peripheral::new("usart1", "UART.STM32F7_USART", sysbus(0x4001_3800))
+            .comment("USART\nsecond line")
+            .property("frequency", Value::Int(72_000_000))
+            .property_noted("size", Value::Hex(0x400), "1KB")
+            .property("scale", Value::Float(3.3))
+            .property("initable", Value::Bool(true))
+            .property("cpuType", Value::Str("cortex-m4f".into()))
+            .property("nvic", Value::Ref("nvic".into()))
+            .irq(37)
+            .connect(Some(Lines::Range(0, 4)), "nvic", Lines::Range(6, 10))
+            .connect(None, "nvic", Lines::One(3))
+            .init("Tag <0x0, 0x3> \"X\"");
+        assert_eq!(
+            uart.to_string(),
+            "// USART\n\
+             // second line\n\
+             usart1: UART.STM32F7_USART @ sysbus 0x40013800\n    \
+             frequency: 72000000\n    \
+             size: 0x400  // 1KB\n    \
+             scale: 3.3\n    \
+             initable: true\n    \
+             cpuType: \"cortex-m4f\"\n    \
+             nvic: nvic\n    \
+             IRQ -> nvic@37\n    \
+             [0-4] -> nvic@[6-10]\n    \
+             -> nvic@3\n    \
+             init:\n        \
+             Tag <0x0, 0x3> \"X\"\n"
+        );
+    }
+
+    #[test]
+    fn renders_registrations() {
+        let at = |registration| Peripheral::new("p", "M", registration).to_string();
+        assert_eq!(at(on("gpioPortA")), "p: M @ gpioPortA\n");
+        assert_eq!(
+            at(Registration::At {
+                parent: "i2c1".into(),
+                address: Some(0x76),
+            }),
+            "p: M @ i2c1 0x76\n"
+        );
+        assert_eq!(
+            at(Registration::Range {
+                parent: "sysbus".into(),
+                base: 0x4800_0000,
+                size: 0x400,
+            }),
+            "p: M @ sysbus <0x48000000, +0x400>\n"
+        );
+        assert_eq!(at(Registration::None), "p: M @ none\n");
+        assert_eq!(
+            Peripheral::update("gpioPortE")
+                .connect(Some(Lines::One(9)), "UserLED", Lines::One(0))
+                .to_string(),
+            "gpioPortE:\n    9 -> UserLED@0\n"
+        );
+        assert_eq!(
+            Peripheral::new("rcc", "Python.PythonPeripheral", sysbus(0x4002_1000))
+                .property("script", Value::Script("pass\n".into()))
+                .to_string(),
+            "rcc: Python.PythonPeripheral @ sysbus 0x40021000\n    script: '''\npass\n'''\n"
+        );
+    }
+
+    #[test]
+    fn renders_sections() {
+        let platform = board(Peripheral::new("led", "Miscellaneous.LED", on("cpu")));
+        assert_eq!(
+            platform.render().unwrap(),
+            "// Board\n\nusing \"soc.repl\"\n\nled: Miscellaneous.LED @ cpu\n"
+        );
+        assert_eq!(Platform::default().render().unwrap(), "");
+    }
+
+    #[test]
+    fn defined_includes_the_base() {
+        let platform = board(Peripheral::update("nvic").property("x", Value::Int(1)));
+        let names: Vec<&str> = platform.defined().iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["nvic", "cpu", "sram"]);
+        assert_eq!(platform.check(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_duplicates() {
+        let platform = board(Peripheral::new("cpu", "CPU.CortexM", on("sysbus")));
+        assert_eq!(platform.check(), Err(Error::Duplicate("cpu".into())));
+    }
+
+    #[test]
+    fn rejects_unknown_references() {
+        let unknown = |peripheral: &str, reference: &str| {
+            Err(Error::UnknownPeripheral {
+                peripheral: peripheral.into(),
+                reference: reference.into(),
+            })
+        };
+        assert_eq!(
+            board(Peripheral::update("gpioPortE")).check(),
+            unknown("gpioPortE", "gpioPortE")
+        );
+        assert_eq!(
+            board(Peripheral::new("led", "Miscellaneous.LED", on("gpioPortE"))).check(),
+            unknown("led", "gpioPortE")
+        );
+        assert_eq!(
+            board(
+                Peripheral::new("rtc", "Timers.STM32F4_RTC", sysbus(0x4000_2800)).connect(
+                    Some(Lines::Named("AlarmIRQ".into())),
+                    "exti",
+                    Lines::One(17)
+                )
+            )
+            .check(),
+            unknown("rtc", "exti")
+        );
+        assert_eq!(
+            board(
+                Peripheral::new("cpu2", "CPU.CortexM", on("sysbus"))
+                    .property("nvic", Value::Ref("nvic2".into()))
+            )
+            .check(),
+            unknown("cpu2", "nvic2")
+        );
+        assert_eq!(
+            board(Peripheral::new(
+                "flash",
+                "Memory.MappedMemory",
+                Registration::Range {
+                    parent: "ahb".into(),
+                    base: 0,
+                    size: 1,
+                }
+            ))
+            .check(),
+            unknown("flash", "ahb")
+        );
+    }
+
+    #[test]
+    fn rejects_shared_addresses() {
+        let platform = board(Peripheral::new("uart", "UART.X", sysbus(0x2000_0000)));
+        assert_eq!(
+            platform.check(),
+            Err(Error::Overlap {
+                first: "sram".into(),
+                second: "uart".into(),
+            })
+        );
+        // The same address on different buses is fine
+        let platform = board(Peripheral::new(
+            "sensor",
+            "I2C.BME280",
+            Registration::At {
+                parent: "cpu".into(),
+                address: Some(0x2000_0000),
+            },
+        ));
+        assert_eq!(platform.check(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_overlapping_ranges() {
+        let inside = Peripheral::new(
+            "bitband",
+            "Miscellaneous.BitBanding",
+            Registration::Range {
+                parent: "sysbus".into(),
+                base: 0x2000_9000,
+                size: 0x2000,
+            },
+        );
+        assert_eq!(
+            board(inside).check(),
+            Err(Error::Overlap {
+                first: "sram".into(),
+                second: "bitband".into(),
+            })
+        );
+        // Right after the end of SRAM
+        let after = Peripheral::new("ccm", "Memory.MappedMemory", sysbus(0x2000_A000))
+            .property("size", Value::Hex(0x2000));
+        assert_eq!(board(after).check(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_mismatched_connections() {
+        let dma = Peripheral::new("dma1", "DMA.STM32DMA", sysbus(0x4002_0000)).connect(
+            Some(Lines::Range(0, 6)),
+            "nvic",
+            Lines::Range(11, 16),
+        );
+        let error = board(dma).check().unwrap_err();
+        assert_eq!(
+            error,
+            Error::Connection {
+                peripheral: "dma1".into(),
+                msg: "7 lines connected to nvic@[11-16]".into(),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "`dma1`: 7 lines connected to nvic@[11-16]"
+        );
+    }
+
+    #[test]
+    fn checks_includes() {
+        let broken = soc().with(Peripheral::new("cpu", "CPU.CortexM", on("sysbus")));
+        let platform = Platform::new("Board").using("soc.repl", broken);
+        assert_eq!(platform.render(), Err(Error::Duplicate("cpu".into())));
+    }
+}
@@ -0,0 +1,305 @@
+//! STM32F303xC SoC model (`stm32f3-uart/stm32f3.repl`)
+
+use crate::{Lines, Peripheral, Platform, Registration, Value};
+
+/// Where the SoC description lives, relative to the firmware crates
+pub const REPL_PATH: &str = "../stm32f3-uart/stm32f3.repl";
+
+/// Core clock the timers, USARTs and SysTick run from
+pub const CORE_CLOCK: u64 = 72_000_000;
+
+/// The STM32F303xC: memories, core, EXTI, GPIO, USART, RCC, timers, SPI,
+/// I2C, DMA and bit-banding
+pub fn platform() -> Platform {
+    let mut platform = Platform::new(
+        "STM32F303 Platform Description for Renode\n\
+         Based on STM32F303xC (256KB Flash, 40KB SRAM, 8KB CCM)",
+    );
+
+    platform = platform
+        .with(
+            memory("flash", 0x0800_0000)
+                .comment("Memory regions")
+                .property_noted("size", Value::Hex(0x40000), "256KB"),
+        )
+        .with(memory("sram", 0x2000_0000).property_noted("size", Value::Hex(0xA000), "40KB"))
+        .with(memory("ccm", 0x1000_0000).property_noted("size", Value::Hex(0x2000), "8KB CCM SRAM"))
+        .with(
+            memory("rom", 0x1FFF_D800)
+                .comment("System ROM (bootloader area)")
+                .property("size", Value::Hex(0x2000)),
+        );
+
+    platform = platform
+        .with(
+            Peripheral::new("nvic", "IRQControllers.NVIC", sysbus(0xE000_E000))
+                .comment("CPU and NVIC")
+                .property("priorityMask", Value::Hex(0xF0))
+                .property("systickFrequency", Value::Int(CORE_CLOCK))
+                .connect(Some(named("IRQ")), "cpu", Lines::One(0)),
+        )
+        .with(
+            Peripheral::new("cpu", "CPU.CortexM", on("sysbus"))
+                .property("cpuType", Value::Str("cortex-m4f".into()))
+                .property("nvic", Value::Ref("nvic".into())),
+        )
+        .with(
+            Peripheral::new("exti", "IRQControllers.STM32F4_EXTI", sysbus(0x4001_0400))
+                .comment("EXTI - External Interrupt Controller")
+                .property("numberOfOutputLines", Value::Int(36))
+                .connect(Some(Lines::Range(0, 4)), "nvic", Lines::Range(6, 10))
+                .connect(Some(Lines::Range(5, 9)), "nvicInput23", Lines::Range(0, 4))
+                .connect(
+                    Some(Lines::Range(10, 15)),
+                    "nvicInput40",
+                    Lines::Range(0, 5),
+                ),
+        )
+        .with(combined_input("nvicInput23", 5, 23))
+        .with(combined_input("nvicInput40", 6, 40));
+
+    // GPIO ports A-F on AHB2, every pin routed to its EXTI line
+    let ports = [
+        (
+            'A',
+            0x4800_0000,
+            vec![
+                ("modeResetValue", 0xA800_0000),
+                ("pullUpPullDownResetValue", 0x6400_0000),
+            ],
+        ),
+        (
+            'B',
+            0x4800_0400,
+            vec![
+                ("modeResetValue", 0x0000_0280),
+                ("outputSpeedResetValue", 0x0000_00C0),
+                ("pullUpPullDownResetValue", 0x0000_0100),
+            ],
+        ),
+        ('C', 0x4800_0800, vec![]),
+        ('D', 0x4800_0C00, vec![]),
+        ('E', 0x4800_1000, vec![]),
+        ('F', 0x4800_1400, vec![]),
+    ];
+    for (index, (letter, base, resets)) in ports.into_iter().enumerate() {
+        let mut port = Peripheral::new(
+            &format!("gpioPort{}", letter),
+            "GPIOPort.STM32_GPIOPort",
+            Registration::Range {
+                parent: "sysbus".into(),
+                base,
+                size: 0x400,
+            },
+        );
+        if index == 0 {
+            port = port.comment("GPIO Ports (AHB2 bus at 0x48000000)");
+        }
+        for (name, value) in resets {
+            port = port.property(name, Value::Hex(value));
+        }
+        platform = platform.with(port.property("numberOfAFs", Value::Int(16)).connect(
+            Some(Lines::Range(0, 15)),
+            "exti",
+            Lines::Range(0, 15),
+        ));
+    }
+
+    let usarts = [
+        ("usart1", 0x4001_3800, 37),
+        ("usart2", 0x4000_4400, 38),
+        ("usart3", 0x4000_4800, 39),
+    ];
+    for (index, (name, address, irq)) in usarts.into_iter().enumerate() {
+        let mut usart = Peripheral::new(name, "UART.STM32F7_USART", sysbus(address))
+            .property("frequency", Value::Int(CORE_CLOCK))
+            .irq(irq);
+        if index == 0 {
+            usart = usart.comment("USARTs - Using STM32F7_USART which is compatible with F3 USART");
+        }
+        platform = platform.with(usart);
+    }
+
+    platform = platform.with(
+        Peripheral::new("rcc", "Python.PythonPeripheral", sysbus(0x4002_1000))
+            .comment("RCC - Reset and Clock Control (simplified Python peripheral)")
+            .property("size", Value::Hex(0x400))
+            .property("initable", Value::Bool(true))
+            .property(
+                "script",
+                Value::Script(include_str!("../scripts/rcc.py").into()),
+            ),
+    );
+
+    let timers = [
+        ("timer1", 0x4001_2C00, 27, 0xFFFF),
+        ("timer2", 0x4000_0000, 28, 0xFFFF_FFFF),
+        ("timer3", 0x4000_0400, 29, 0xFFFF),
+        ("timer4", 0x4000_0800, 30, 0xFFFF),
+        ("timer7", 0x4000_1400, 55, 0xFFFF),
+    ];
+    for (index, (name, address, irq, limit)) in timers.into_iter().enumerate() {
+        let mut timer = Peripheral::new(name, "Timers.STM32_Timer", sysbus(address))
+            .property("frequency", Value::Int(CORE_CLOCK))
+            .property("initialLimit", Value::Hex(limit))
+            .connect(None, "nvic", Lines::One(irq));
+        if index == 0 {
+            timer = timer.comment("Timers");
+        }
+        if name == "timer7" {
+            timer = timer.comment("TIM7 basic timer - tick source for the software timer service");
+        }
+        platform = platform.with(timer);
+    }
+
+    let spis = [
+        ("spi1", 0x4001_3000, 35),
+        ("spi2", 0x4000_3800, 36),
+        ("spi3", 0x4000_3C00, 51),
+    ];
+    for (index, (name, address, irq)) in spis.into_iter().enumerate() {
+        let mut spi = Peripheral::new(name, "SPI.STM32SPI", sysbus(address)).irq(irq);
+        if index == 0 {
+            spi = spi.comment("SPI");
+        }
+        platform = platform.with(spi);
+    }
+
+    platform
+        .with(Peripheral::new("i2c1", "I2C.STM32F7_I2C", sysbus(0x4000_5400)).comment("I2C"))
+        .with(Peripheral::new(
+            "i2c2",
+            "I2C.STM32F7_I2C",
+            sysbus(0x4000_5800),
+        ))
+        .with(
+            Peripheral::new("dma1", "DMA.STM32DMA", sysbus(0x4002_0000))
+                .comment("DMA")
+                .connect(Some(Lines::Range(0, 6)), "nvic", Lines::Range(11, 17)),
+        )
+        .with(
+            Peripheral::new("dma2", "DMA.STM32DMA", sysbus(0x4002_0400)).connect(
+                Some(Lines::Range(0, 4)),
+                "nvic",
+                Lines::Range(56, 60),
+            ),
+        )
+        .with(
+            bit_banding("bitbandPeripherals", 0x4200_0000, 0x0200_0000, 0x4000_0000)
+                .comment("Bit-banding regions"),
+        )
+        .with(bit_banding(
+            "bitbandSram",
+            0x2200_0000,
+            0x0020_0000,
+            0x2000_0000,
+        ))
+        .with(
+            Peripheral::update("sysbus")
+                .comment("System configuration")
+                .init("Tag <0x40010000, 0x400103FF> \"SYSCFG\"")
+                .init("Tag <0x40007000, 0x400073FF> \"PWR\"")
+                .init("Tag <0x40022000, 0x400223FF> \"FLASH_INTERFACE\"")
+                .init("Tag <0x40023000, 0x400233FF> \"CRC\""),
+        )
+}
+
+/// `@ sysbus <address>`
+pub fn sysbus(address: u64) -> Registration {
+    Registration::At {
+        parent: "sysbus".into(),
+        address: Some(address),
+    }
+}
+
+/// `@ parent` without an address
+pub fn on(parent: &str) -> Registration {
+    Registration::At {
+        parent: parent.into(),
+        address: None,
+    }
+}
+
+fn named(line: &str) -> Lines {
+    Lines::Named(line.into())
+}
+
+fn memory(name: &str, address: u64) -> Peripheral {
+    Peripheral::new(name, "Memory.MappedMemory", sysbus(address))
+}
+
+/// Combines several EXTI lines into one shared NVIC interrupt
+fn combined_input(name: &str, inputs: u64, nvic_line: u32) -> Peripheral {
+    Peripheral::new(name, "Miscellaneous.CombinedInput", Registration::None)
+        .property("numberOfInputs", Value::Int(inputs))
+        .connect(None, "nvic", Lines::One(nvic_line))
+}
+
+fn bit_banding(name: &str, base: u64, size: u64, target: u64) -> Peripheral {
+    Peripheral::new(
+        name,
+        "Miscellaneous.BitBanding",
+        Registration::Range {
+            parent: "sysbus".into(),
+            base,
+            size,
+        },
+    )
+    .property("peripheralBase", Value::Hex(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soc_passes_its_checks() {
+        let soc = platform();
+        assert_eq!(soc.check(), Ok(()));
+        assert!(soc.includes.is_empty());
+    }
+
+    #[test]
+    fn peripherals_at_their_reference_manual_addresses() {
+        let soc = platform();
+        let address = |name: &str| match &soc
+            .defined()
+            .iter()
+            .find(|p| p.name == name)
+            .unwrap()
+            .registration
+        {
+            Some(Registration::At {
+                address: Some(address),
+                ..
+            }) => *address,
+            Some(Registration::Range { base, .. }) => *base,
+            other => panic!("{}: {:?}", name, other),
+        };
+        assert_eq!(address("flash"), 0x0800_0000);
+        assert_eq!(address("usart1"), 0x4001_3800);
+        assert_eq!(address("usart2"), 0x4000_4400);
+        assert_eq!(address("gpioPortE"), 0x4800_1000);
+        assert_eq!(address("timer7"), 0x4000_1400);
+        assert_eq!(address("i2c1"), 0x4000_5400);
+        assert_eq!(address("dma2"), 0x4002_0400);
+    }
+
+    #[test]
+    fn registration_helpers() {
+        assert_eq!(
+            sysbus(0x4000_0000),
+            Registration::At {
+                parent: "sysbus".into(),
+                address: Some(0x4000_0000),
+            }
+        );
+        assert_eq!(
+            on("gpioPortA"),
+            Registration::At {
+                parent: "gpioPortA".into(),
+                address: None,
+            }
+        );
+    }
+}
@@ -0,0 +1,157 @@
+//! Platform descriptions of the firmware crates
+//!
+//! Each variant is the SoC plus the Discovery button/LED wiring and the
+//! device its test needs; `stm32f3-selftest` gets every device at once.
+
+use crate::{devices, discovery, stm32f303, Platform};
+
+/// The `.repl` files of a firmware crate, as `(file name, platform)`
+///
+/// `stm32f3-uart` also owns the SoC description the others include.
+pub fn for_crate(name: &str) -> Vec<(String, Platform)> {
+    let peripheral = name.strip_prefix("stm32f3-").unwrap_or(name);
+    let file = format!("stm32f3_{}.repl", peripheral);
+
+    // (header suffix, has the user button, extra device)
+    let (title, button, device) = match peripheral {
+        "uart" => {
+            let discovery = board("", "stm32f3.repl")
+                .with(discovery::user_button())
+                .with_all(discovery::user_led());
+            return vec![
+                ("stm32f3.repl".into(), stm32f303::platform()),
+                ("stm32f3_discovery.repl".into(), discovery),
+            ];
+        }
+        "gpio" => {
+            let platform = board(" with GPIO Test Configuration", stm32f303::REPL_PATH)
+                .with(discovery::user_button())
+                .with_all(discovery::led("LED_PE8", 8))
+                .with_all(discovery::led("LED_PE9", 9));
+            return vec![(file, platform)];
+        }
+        "selftest" => {
+            let platform = board(" with Every Test Device", stm32f303::REPL_PATH)
+                .with(discovery::user_button())
+                .with_all(discovery::led("LED_PE8", 8))
+                .with_all(discovery::user_led())
+                .with(devices::adc1())
+                .with(devices::crc())
+                .with(devices::dac())
+                .with(devices::bme280("i2cSensor", "i2c1", 0x76))
+                .with(devices::iwdg())
+                .with(devices::rtc())
+                .with(devices::spi_loopback("spi1Loopback", "spi1"));
+            return vec![(file, platform)];
+        }
+        "adc" => (" with ADC", true, Some(devices::adc1())),
+        "crc" => (" with CRC Peripheral", false, Some(devices::crc())),
+        "dac" => (" with DAC Peripheral", false, Some(devices::dac())),
+        "dma" => (" with DMA Test", true, None),
+        "exti" => (" with EXTI Test Configuration", true, None),
+        "i2c" => (
+            " with I2C Sensor",
+            true,
+            Some(devices::bme280("i2cSensor", "i2c1", 0x76)),
+        ),
+        "iwdg" => (" with IWDG", true, Some(devices::iwdg())),
+        "rtc" => (" with RTC", true, Some(devices::rtc())),
+        "spi" => (
+            " with SPI Loopback",
+            true,
+            Some(devices::spi_loopback("spi1Loopback", "spi1")),
+        ),
+        "timer" => (" with Timer Test", true, None),
+        "usart2" => (" with USART2 Peer", true, None),
+        _ => return Vec::new(),
+    };
+
+    let mut platform = board(title, stm32f303::REPL_PATH);
+    if button {
+        platform = platform.with(discovery::user_button());
+    }
+    platform = platform.with_all(discovery::user_led()).with_all(device);
+    vec![(file, platform)]
+}
+
+/// A Discovery board description including the SoC from `soc`
+fn board(title: &str, soc: &str) -> Platform {
+    Platform::new(&format!(
+        "STM32F3 Discovery Board Platform{}\n{}",
+        title,
+        discovery::BOARD
+    ))
+    .using(soc, stm32f303::platform())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    const CRATES: &[&str] = &[
+        "stm32f3-adc",
+        "stm32f3-crc",
+        "stm32f3-dac",
+        "stm32f3-dma",
+        "stm32f3-exti",
+        "stm32f3-gpio",
+        "stm32f3-i2c",
+        "stm32f3-iwdg",
+        "stm32f3-rtc",
+        "stm32f3-selftest",
+        "stm32f3-spi",
+        "stm32f3-timer",
+        "stm32f3-uart",
+        "stm32f3-usart2",
+    ];
+
+    #[test]
+    fn match_the_checked_in_descriptions() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        for name in CRATES {
+            let variants = for_crate(name);
+            assert!(!variants.is_empty(), "{}", name);
+            for (file, platform) in variants {
+                let path = root.join(name).join(&file);
+                let on_disk = fs::read_to_string(&path).unwrap();
+                // Past the line renode-gen adds
+                let (_, body) = on_disk.split_once('\n').unwrap();
+                assert_eq!(platform.render().unwrap(), body, "{}", path.display());
+            }
+        }
+    }
+
+    #[test]
+    fn uart_owns_the_soc() {
+        let files: Vec<String> = for_crate("stm32f3-uart")
+            .into_iter()
+            .map(|(file, _)| file)
+            .collect();
+        assert_eq!(files, ["stm32f3.repl", "stm32f3_discovery.repl"]);
+        let (file, platform) = for_crate("stm32f3-timer").remove(0);
+        assert_eq!(file, "stm32f3_timer.repl");
+        assert_eq!(platform.includes[0].0, stm32f303::REPL_PATH);
+    }
+
+    #[test]
+    fn button_only_where_tests_press_it() {
+        let has_button = |name: &str| {
+            for_crate(name)[0]
+                .1
+                .peripherals
+                .iter()
+                .any(|p| p.name == "UserButton")
+        };
+        assert!(has_button("stm32f3-adc"));
+        assert!(!has_button("stm32f3-crc"));
+        assert!(!has_button("stm32f3-dac"));
+    }
+
+    #[test]
+    fn unknown_crates_have_none() {
+        assert!(for_crate("stm32f3-can").is_empty());
+        assert!(for_crate("rust-uart").is_empty());
+    }
+}
@@ -0,0 +1,487 @@
+//! Flaky-test detection over repeated runs
+//!
+//! With `--repeat N` every suite is run N times in a fresh Renode. The
+//! results are grouped per test case: a case is flaky when it neither
+//! always passes nor always fails (a run in which its suite could not run
+//! at all counts as not passing), or when the virtual time to one of its
+//! expected UART lines spreads by more than the configured fraction of its
+//! mean.
+//!
+//! Virtual time should be the same on every run of a deterministic
+//! firmware, so any spread there points at host-dependent behaviour such as
+//! polling loops racing the emulator. It is sampled with the emulation
+//! paused (see `run.rs`). Host time depends on the machine's load and is
+//! only reported, never used to flag a case.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::run::{SuiteResult, TestResult, WaitTiming};
+
+/// One suite in one repetition: its results, or the path of a suite that
+/// ended in an error before reporting any
+pub type SuiteRun = Result<SuiteResult, PathBuf>;
+
+/// Virtual-time spread below this is ignored
+pub const VIRTUAL_JITTER: Duration = Duration::from_millis(1);
+
+/// Min/mean/max/standard deviation of a set of durations
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub min: Duration,
+    pub mean: Duration,
+    pub max: Duration,
+    pub stddev: Duration,
+}
+
+impl Stats {
+    /// `None` for an empty set
+    pub fn of(samples: &[Duration]) -> Option<Stats> {
+        if samples.is_empty() {
+            return None;
+        }
+        let n = samples.len() as f64;
+        let secs: Vec<f64> = samples.iter().map(Duration::as_secs_f64).collect();
+        let mean = secs.iter().sum::<f64>() / n;
+        let variance = secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+        Some(Stats {
+            min: *samples.iter().min()?,
+            mean: Duration::from_secs_f64(mean),
+            max: *samples.iter().max()?,
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+        })
+    }
+
+    pub fn spread(&self) -> Duration {
+        self.max - self.min
+    }
+
+    /// Whether the spread exceeds both `jitter` and `fraction` of the mean
+    fn varies(&self, fraction: f64, jitter: Duration) -> bool {
+        self.spread() > jitter && self.spread().as_secs_f64() > fraction * self.mean.as_secs_f64()
+    }
+}
+
+/// Timing of one expected UART line across the runs that reached it
+#[derive(Clone, Debug)]
+pub struct WaitStats {
+    pub pattern: String,
+    /// Number of runs that saw the line
+    pub seen: usize,
+    /// Wall-clock time, reported but never used to flag the case
+    pub host: Stats,
+    /// `None` if Renode never reported virtual time
+    pub virtual_time: Option<Stats>,
+}
+
+/// Outcome of one test case over all runs
+#[derive(Clone, Debug)]
+pub struct CaseStats {
+    pub suite: String,
+    pub path: PathBuf,
+    pub name: String,
+    pub runs: usize,
+    pub passed: usize,
+    /// Runs in which the suite ended in an error, included in `runs`
+    pub errored: usize,
+    /// Over the runs that reported the case
+    pub duration: Stats,
+    pub waits: Vec<WaitStats>,
+    /// Why the case is flaky; empty if it is stable
+    pub reasons: Vec<String>,
+}
+
+impl CaseStats {
+    pub fn is_flaky(&self) -> bool {
+        !self.reasons.is_empty()
+    }
+
+    /// Passes on every run
+    pub fn is_stable_pass(&self) -> bool {
+        self.passed == self.runs && !self.is_flaky()
+    }
+
+    pub fn pass_rate(&self) -> f64 {
+        self.passed as f64 / self.runs as f64
+    }
+}
+
+/// Group the results of repeated runs per test case and flag flaky ones
+///
+/// `runs` holds one `Vec<SuiteRun>` per repetition. A spread in the
+/// virtual time to an expected line larger than `spread` times its mean
+/// (and larger than [`VIRTUAL_JITTER`]) marks the case as flaky.
+pub fn analyze(runs: &[Vec<SuiteRun>], spread: f64) -> Vec<CaseStats> {
+    // (suite, test case) in first-seen order, with its result from each run
+    let mut groups: Vec<(&SuiteResult, &str, Vec<&TestResult>)> = Vec::new();
+    for suite in runs.iter().flatten().filter_map(|run| run.as_ref().ok()) {
+        for test in &suite.tests {
+            match groups
+                .iter_mut()
+                .find(|(s, name, _)| s.path == suite.path && *name == test.name)
+            {
+                Some((_, _, results)) => results.push(test),
+                None => groups.push((suite, &test.name, vec![test])),
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(suite, name, results)| {
+            let errored = runs
+                .iter()
+                .flatten()
+                .filter(|run| matches!(run, Err(path) if *path == suite.path))
+                .count();
+            case_stats(suite, name, &results, errored, spread)
+        })
+        .collect()
+}
+
+fn case_stats(
+    suite: &SuiteResult,
+    name: &str,
+    results: &[&TestResult],
+    errored: usize,
+    spread: f64,
+) -> CaseStats {
+    let runs = results.len() + errored;
+    let passed = results.iter().filter(|t| t.passed()).count();
+    let durations: Vec<Duration> = results.iter().map(|t| t.duration).collect();
+
+    let mut reasons = Vec::new();
+    if passed != 0 && passed != runs {
+        let mut reason = format!("passed {} of {} runs", passed, runs);
+        if errored > 0 {
+            reason.push_str(&format!(" ({} errored)", errored));
+        }
+        reasons.push(reason);
+    }
+
+    // Waits are matched by position: the n-th expected line of each run
+    let longest = results.iter().map(|t| t.waits.len()).max().unwrap_or(0);
+    let mut waits = Vec::new();
+    for step in 0..longest {
+        let reached: Vec<&WaitTiming> = results.iter().filter_map(|t| t.waits.get(step)).collect();
+        let host: Vec<Duration> = reached.iter().map(|w| w.host).collect();
+        let virtual_time: Vec<Duration> = reached.iter().filter_map(|w| w.virtual_time).collect();
+        let wait = WaitStats {
+            pattern: reached[0].pattern.clone(),
+            seen: reached.len(),
+            host: Stats::of(&host).expect("at least one run reached the line"),
+            virtual_time: Stats::of(&virtual_time),
+        };
+
+        if let Some(stats) = wait.virtual_time {
+            if stats.varies(spread, VIRTUAL_JITTER) {
+                reasons.push(format!(
+                    "virtual time to \"{}\" ranges {} - {}",
+                    wait.pattern,
+                    seconds(stats.min),
+                    seconds(stats.max)
+                ));
+            }
+        }
+        waits.push(wait);
+    }
+
+    CaseStats {
+        suite: suite.name.clone(),
+        path: suite.path.clone(),
+        name: name.to_string(),
+        runs,
+        passed,
+        errored,
+        duration: Stats::of(&durations).expect("at least one run"),
+        waits,
+        reasons,
+    }
+}
+
+/// Tab-separated statistics, one row per expected line of each case
+///
+/// Columns: suite, test, runs, passed, pattern, seen, then min/mean/max/
+/// stddev of host and virtual time in seconds (empty without virtual time),
+/// and `flaky` or `stable`.
+pub fn tsv(cases: &[CaseStats]) -> String {
+    let mut out = String::from(
+        "suite\ttest\truns\tpassed\tpattern\tseen\t\
+         host_min\thost_mean\thost_max\thost_stddev\t\
+         virtual_min\tvirtual_mean\tvirtual_max\tvirtual_stddev\tstatus\n",
+    );
+    for case in cases {
+        let status = if case.is_flaky() { "flaky" } else { "stable" };
+        let rows: Vec<(&str, usize, Stats, Option<Stats>)> = if case.waits.is_empty() {
+            vec![("", case.runs, case.duration, None)]
+        } else {
+            case.waits
+                .iter()
+                .map(|w| (w.pattern.as_str(), w.seen, w.host, w.virtual_time))
+                .collect()
+        };
+        for (pattern, seen, host, virtual_time) in rows {
+            let _ = write!(
+                out,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t",
+                case.suite,
+                case.name,
+                case.runs,
+                case.passed,
+                pattern,
+                seen,
+                columns(Some(host))
+            );
+            let _ = writeln!(out, "{}\t{}", columns(virtual_time), status);
+        }
+    }
+    out
+}
+
+fn columns(stats: Option<Stats>) -> String {
+    match stats {
+        Some(s) => [s.min, s.mean, s.max, s.stddev]
+            .iter()
+            .map(|d| format!("{:.6}", d.as_secs_f64()))
+            .collect::<Vec<_>>()
+            .join("\t"),
+        None => "\t\t\t".into(),
+    }
+}
+
+/// `1.234s`
+pub fn seconds(duration: Duration) -> String {
+    format!("{:.3}s", duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::Outcome;
+
+    const DMA: &str = "/fw/stm32f3-dma/tests/test-dma.robot";
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    /// One repetition of suite `dma` with a single test case whose waits
+    /// matched at the given (host, virtual) milliseconds
+    fn run(passed: bool, waits: &[(u64, Option<u64>)]) -> Vec<SuiteRun> {
+        let waits: Vec<WaitTiming> = waits
+            .iter()
+            .enumerate()
+            .map(|(index, &(host, virtual_time))| WaitTiming {
+                pattern: format!("line {}", index),
+                host: ms(host),
+                virtual_time: virtual_time.map(ms),
+            })
+            .collect();
+        vec![Ok(SuiteResult {
+            name: "dma".into(),
+            path: PathBuf::from(DMA),
+            tests: vec![TestResult {
+                name: "Should Transfer".into(),
+                outcome: if passed {
+                    Outcome::Passed
+                } else {
+                    Outcome::Failed("timed out".into())
+                },
+                duration: ms(waits.last().map_or(100, |w| w.host.as_millis() as u64)),
+                transcript: Vec::new(),
+                waits,
+            }],
+            duration: ms(100),
+        })]
+    }
+
+    /// A repetition in which the `dma` suite ended in an error
+    fn error() -> Vec<SuiteRun> {
+        vec![Err(PathBuf::from(DMA))]
+    }
+
+    fn only_case(runs: &[Vec<SuiteRun>]) -> CaseStats {
+        let mut cases = analyze(runs, 0.2);
+        assert_eq!(cases.len(), 1);
+        cases.remove(0)
+    }
+
+    #[test]
+    fn stats_of_samples() {
+        assert_eq!(Stats::of(&[]), None);
+        let stats = Stats::of(&[ms(100), ms(200), ms(300)]).unwrap();
+        assert_eq!(stats.min, ms(100));
+        assert_eq!(stats.max, ms(300));
+        assert_eq!(stats.spread(), ms(200));
+        assert!((stats.mean.as_secs_f64() - 0.2).abs() < 1e-9);
+        // Population standard deviation: sqrt(2/3) * 0.1 s
+        assert!((stats.stddev.as_secs_f64() - 0.081_649_658).abs() < 1e-6);
+
+        let single = Stats::of(&[ms(5)]).unwrap();
+        assert_eq!(single.spread(), Duration::ZERO);
+        assert_eq!(single.stddev, Duration::ZERO);
+    }
+
+    #[test]
+    fn varies_needs_both_fraction_and_jitter() {
+        let stats = Stats::of(&[ms(1000), ms(1100)]).unwrap();
+        // Spread 100 ms, mean 1050 ms
+        assert!(stats.varies(0.05, ms(50)));
+        assert!(!stats.varies(0.2, ms(50)));
+        assert!(!stats.varies(0.05, ms(100)));
+    }
+
+    #[test]
+    fn identical_runs_are_stable() {
+        let runs: Vec<_> = (0..5)
+            .map(|_| run(true, &[(100, Some(10)), (300, Some(40))]))
+            .collect();
+        let case = only_case(&runs);
+        assert_eq!((case.runs, case.passed), (5, 5));
+        assert!(case.is_stable_pass());
+        assert!(case.reasons.is_empty());
+        assert_eq!(case.waits.len(), 2);
+        assert_eq!(case.waits[1].virtual_time.unwrap().mean, ms(40));
+    }
+
+    #[test]
+    fn pass_fail_disagreement_is_flaky() {
+        let runs = [run(true, &[]), run(false, &[]), run(true, &[])];
+        let case = only_case(&runs);
+        assert!(case.is_flaky());
+        assert_eq!(case.reasons, ["passed 2 of 3 runs"]);
+        assert!((case.pass_rate() - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn errors_count_as_not_passing() {
+        let runs = [run(true, &[]), error(), run(true, &[])];
+        let case = only_case(&runs);
+        assert!(case.is_flaky());
+        assert_eq!((case.runs, case.passed, case.errored), (3, 2, 1));
+        assert_eq!(case.reasons, ["passed 2 of 3 runs (1 errored)"]);
+
+        // Failing or erroring on every run is consistent
+        let case = only_case(&[run(false, &[]), error()]);
+        assert!(!case.is_flaky());
+        assert_eq!(case.runs, 2);
+
+        // Another suite's error does not count
+        let other = vec![Err(PathBuf::from("/fw/stm32f3-adc/tests/test-adc.robot"))];
+        let case = only_case(&[run(true, &[]), other]);
+        assert!(case.is_stable_pass());
+    }
+
+    #[test]
+    fn failing_every_run_is_not_flaky() {
+        let runs = [run(false, &[]), run(false, &[])];
+        let case = only_case(&runs);
+        assert!(!case.is_flaky());
+        assert!(!case.is_stable_pass());
+    }
+
+    #[test]
+    fn virtual_time_spread_is_flaky() {
+        let runs = [run(true, &[(100, Some(10))]), run(true, &[(100, Some(20))])];
+        let case = only_case(&runs);
+        assert!(case.is_flaky());
+        assert_eq!(case.reasons.len(), 1);
+        assert!(
+            case.reasons[0].starts_with("virtual time to \"line 0\""),
+            "{:?}",
+            case.reasons
+        );
+    }
+
+    #[test]
+    fn virtual_time_below_jitter_is_stable() {
+        // 0.5 ms apart: a third of the mean, but under the floor
+        let mut runs = [run(true, &[(100, Some(1))]), run(true, &[(100, Some(1))])];
+        runs[1][0].as_mut().unwrap().tests[0].waits[0].virtual_time =
+            Some(Duration::from_micros(1500));
+        let case = only_case(&runs);
+        assert_eq!(
+            case.waits[0].virtual_time.unwrap().spread(),
+            Duration::from_micros(500)
+        );
+        assert!(!case.is_flaky());
+    }
+
+    #[test]
+    fn host_time_spread_is_only_reported() {
+        let runs = [
+            run(true, &[(100, Some(10))]),
+            run(true, &[(5000, Some(10))]),
+        ];
+        let case = only_case(&runs);
+        assert!(!case.is_flaky(), "{:?}", case.reasons);
+        assert_eq!(case.waits[0].host.spread(), ms(4900));
+    }
+
+    #[test]
+    fn waits_are_matched_by_position() {
+        // The second run stopped after its first line
+        let runs = [
+            run(true, &[(100, Some(10)), (200, Some(20))]),
+            run(false, &[(100, Some(10))]),
+        ];
+        let case = only_case(&runs);
+        assert_eq!(case.waits.len(), 2);
+        assert_eq!(case.waits[0].seen, 2);
+        assert_eq!(case.waits[1].seen, 1);
+        assert_eq!(case.reasons, ["passed 1 of 2 runs"]);
+    }
+
+    #[test]
+    fn missing_virtual_time_gives_no_stats() {
+        let case = only_case(&[run(true, &[(100, None)]), run(true, &[(120, None)])]);
+        assert!(case.waits[0].virtual_time.is_none());
+        assert!(!case.is_flaky());
+    }
+
+    #[test]
+    fn cases_are_grouped_per_suite_and_name() {
+        let mut first = run(true, &[]);
+        let suite = first[0].as_mut().unwrap();
+        let report = TestResult {
+            name: "Should Report".into(),
+            ..suite.tests[0].clone()
+        };
+        suite.tests.push(report);
+        let second = run(false, &[]);
+        let cases = analyze(&[first, second], 0.2);
+        let names: Vec<(&str, usize)> = cases.iter().map(|c| (c.name.as_str(), c.runs)).collect();
+        assert_eq!(names, [("Should Transfer", 2), ("Should Report", 1)]);
+    }
+
+    #[test]
+    fn tsv_has_a_row_per_wait() {
+        let runs = [
+            run(true, &[(100, Some(10)), (200, None)]),
+            run(false, &[(100, Some(10)), (200, None)]),
+        ];
+        let text = tsv(&analyze(&runs, 0.2));
+        let rows: Vec<Vec<&str>> = text.lines().map(|l| l.split('\t').collect()).collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|row| row.len() == 15), "{}", text);
+        assert_eq!(rows[0][0], "suite");
+        assert_eq!(
+            &rows[1][..6],
+            ["dma", "Should Transfer", "2", "1", "line 0", "2"]
+        );
+        assert_eq!(rows[1][6], "0.100000");
+        assert_eq!(rows[1][10], "0.010000");
+        assert_eq!(rows[1][14], "flaky");
+        // No virtual time: empty columns
+        assert_eq!(&rows[2][10..14], ["", "", "", ""]);
+    }
+
+    #[test]
+    fn tsv_row_for_a_case_without_waits() {
+        let text = tsv(&analyze(&[run(true, &[])], 0.2));
+        let row: Vec<&str> = text.lines().nth(1).unwrap().split('\t').collect();
+        assert_eq!(&row[4..7], ["", "1", "0.100000"]);
+        assert_eq!(row[14], "stable");
+    }
+}
@@ -0,0 +1,632 @@
+//! Golden UART transcripts
+//!
+//! Instead of the `Wait For Line On Uart` expectations in the `.robot`
+//! files, golden mode boots each firmware from reset, records its complete
+//! USART1 output up to the line that ends its run and compares it line by
+//! line with `stm32f3-<x>/tests/uart.golden`.
+//!
+//! The platform, machine and UART come from the crate's
+//! `[package.metadata.renode]`, like the generated suites. Its optional
+//! `golden` table names the end of the run (a line ending in `TEST PASSED`
+//! or `TEST FAILED` by default), the `uart-peer` role to start and the
+//! input to send, for firmware that waits for a button or a command. A
+//! crate with metadata whose platform description is missing is an error,
+//! not skipped.
+//!
+//! Output that legitimately changes between runs is masked before it is
+//! compared or recorded. The build and stack usage lines are always masked;
+//! crates add their own rules in `tests/uart.masks`, one per line:
+//!
+//! ```text
+//! # regex => replacement
+//! 0x2000[0-9A-F]{4} => 0x2000XXXX
+//! Counter: \d+ => Counter: N
+//! ```
+//!
+//! A mismatch writes the new transcript to `uart.golden.new` next to the
+//! golden file; `--accept` promotes those files once the change has been
+//! reviewed, and `--update` re-records the golden files directly.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use renode_gen::{Crate, Golden};
+use renode_monitor::{Renode, UartReader};
+
+use crate::robot::{Step, TestCase, DEFAULT_WAIT_TIMEOUT};
+use crate::run::{self, Config, Outcome, SuiteResult, TestResult};
+use crate::Error;
+
+/// Golden transcript file, relative to the crate directory
+pub const GOLDEN_FILE: &str = "tests/uart.golden";
+
+/// Mask rules, relative to the crate directory
+pub const MASKS_FILE: &str = "tests/uart.masks";
+
+/// Suffix of a transcript waiting to be accepted
+pub const PENDING_SUFFIX: &str = ".new";
+
+/// Time allowed for a firmware to print its final result line
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Quiet period after the final line before the capture ends
+const TRAILING_QUIET: Duration = Duration::from_millis(500);
+
+/// Name of the test case golden mode reports
+const TEST_NAME: &str = "UART Golden Transcript";
+
+/// Masks applied to every crate
+const DEFAULT_MASKS: &[(&str, &str)] = &[
+    (r"^Build: .*$", "Build: <masked>"),
+    (
+        r"^Stack: used \d+ of \d+ bytes$",
+        "Stack: used N of N bytes",
+    ),
+];
+
+/// Socket terminal the peer's UART is connected to, as in the suites
+const PEER_TERMINAL: &str = "peer";
+
+/// Host program started for a peer
+const UART_PEER: &str = "uart-peer";
+
+/// What to do with the recorded transcript
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Compare with the golden file; write `uart.golden.new` on mismatch
+    Check,
+    /// Overwrite the golden file
+    Update,
+}
+
+/// A `pattern => replacement` rule
+#[derive(Clone, Debug)]
+pub struct Mask {
+    pattern: Regex,
+    replacement: String,
+}
+
+impl Mask {
+    pub fn new(pattern: &str, replacement: &str) -> Result<Mask, regex::Error> {
+        Ok(Mask {
+            pattern: Regex::new(pattern)?,
+            replacement: replacement.to_string(),
+        })
+    }
+}
+
+/// A firmware crate with the files golden mode needs
+#[derive(Clone, Debug)]
+pub struct Target {
+    /// Crate directory, e.g. `<root>/stm32f3-dma`
+    pub dir: PathBuf,
+    /// Renode machine name
+    pub machine: String,
+    /// The platform description the crate's metadata names
+    pub platform: PathBuf,
+    /// Release ELF
+    pub elf: PathBuf,
+    /// UART the transcript is read from
+    pub uart: String,
+    /// UART connected to the peer's socket terminal
+    pub peer_uart: Option<String>,
+    pub golden: Golden,
+}
+
+impl Target {
+    /// Read the `[package.metadata.renode]` of the crate in `dir`
+    ///
+    /// Returns `None` for crates without one, such as `stm32f3-common`, and
+    /// an error when the platform description it names does not exist.
+    pub fn new(root: &Path, dir: &Path) -> Result<Option<Target>, Error> {
+        let Some(krate) = Crate::load(dir)? else {
+            return Ok(None);
+        };
+        let platform = dir.join(krate.platform());
+        if !platform.is_file() {
+            return Err(Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "{}: no platform description {} (cargo xtask gen)",
+                    krate.name,
+                    platform.display()
+                ),
+            )));
+        }
+        Ok(Some(Target {
+            dir: dir.to_path_buf(),
+            machine: krate.machine(),
+            platform,
+            elf: root.join(krate.elf()),
+            uart: krate.metadata.uart.clone(),
+            peer_uart: krate.metadata.peer.as_ref().map(|peer| peer.uart.clone()),
+            golden: krate.metadata.golden,
+        }))
+    }
+
+    pub fn name(&self) -> String {
+        self.dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn golden_path(&self) -> PathBuf {
+        self.dir.join(GOLDEN_FILE)
+    }
+
+    pub fn pending_path(&self) -> PathBuf {
+        pending_path(&self.golden_path())
+    }
+
+    /// Whether `line` ends the run
+    pub fn is_end(&self, line: &str) -> bool {
+        is_end_line(&self.golden.end, line)
+    }
+
+    /// Steps from attaching the terminal to the last input, with the peer
+    /// (if the golden run starts one) on `peer_port`
+    fn steps(&self, peer_port: u16) -> Vec<Step> {
+        let mut steps = vec![Step::CreateTerminalTester(self.uart.clone())];
+        if let (Some(uart), false) = (&self.peer_uart, self.golden.peer.is_empty()) {
+            steps.push(Step::Execute(format!(
+                "emulation CreateServerSocketTerminal {} \"{}\" false",
+                peer_port, PEER_TERMINAL
+            )));
+            steps.push(Step::Execute(format!(
+                "connector Connect {} {}",
+                uart, PEER_TERMINAL
+            )));
+            let mut args = vec!["--connect".to_string(), format!("127.0.0.1:{}", peer_port)];
+            args.extend(self.golden.peer.iter().cloned());
+            steps.push(Step::StartProcess {
+                program: UART_PEER.into(),
+                args,
+            });
+        }
+        steps.push(Step::StartEmulation);
+        steps.extend(self.golden.steps.iter().filter_map(|step| match step {
+            renode_gen::Step::Wait { wait, timeout } => Some(Step::WaitForLine {
+                pattern: wait.clone(),
+                timeout: timeout.map_or(DEFAULT_WAIT_TIMEOUT, Duration::from_secs_f64),
+            }),
+            renode_gen::Step::Execute { execute } => Some(Step::Execute(execute.clone())),
+            renode_gen::Step::Send { send } => Some(Step::WriteLine(send.clone())),
+            renode_gen::Step::Sleep { sleep } => Some(Step::Sleep(Duration::from_secs_f64(*sleep))),
+            renode_gen::Step::Run { run } => Some(Step::RunProcess {
+                program: run[0].clone(),
+                args: run[1..].to_vec(),
+            }),
+            renode_gen::Step::Comment { .. } => None,
+        }));
+        steps
+    }
+}
+
+/// Record `target`'s transcript and check or update its golden file
+///
+/// A peer, if the golden run starts one, listens on `peer_port`.
+pub fn run_target(
+    config: &Config,
+    target: &Target,
+    mode: Mode,
+    peer_port: u16,
+) -> Result<SuiteResult, Error> {
+    let start = Instant::now();
+    let mut masks = default_masks();
+    masks.extend(load_masks(&target.dir.join(MASKS_FILE))?);
+
+    let (lines, finished) = capture(config, target, peer_port)?;
+    let actual: Vec<String> = lines.iter().map(|l| apply_masks(&masks, l)).collect();
+
+    let golden_path = target.golden_path();
+    let pending_path = target.pending_path();
+    let outcome = if let Err(reason) = finished {
+        Outcome::Failed(reason)
+    } else if mode == Mode::Update {
+        write_transcript(&golden_path, &actual)?;
+        remove_if_exists(&pending_path)?;
+        Outcome::Passed
+    } else {
+        match fs::read_to_string(&golden_path) {
+            Ok(text) => {
+                let expected: Vec<String> = text.lines().map(str::to_string).collect();
+                let changes = diff(&expected, &actual);
+                if changes.is_empty() {
+                    remove_if_exists(&pending_path)?;
+                    Outcome::Passed
+                } else {
+                    write_transcript(&pending_path, &actual)?;
+                    Outcome::Failed(format!(
+                        "transcript differs from {} (new output in {}):\n{}",
+                        GOLDEN_FILE,
+                        pending_path.display(),
+                        changes.join("\n")
+                    ))
+                }
+            }
+            Err(_) => {
+                write_transcript(&pending_path, &actual)?;
+                Outcome::Failed(format!(
+                    "no {} yet; review {} and run with --accept",
+                    GOLDEN_FILE,
+                    pending_path.display()
+                ))
+            }
+        }
+    };
+
+    Ok(SuiteResult {
+        name: target.name(),
+        path: target.dir.clone(),
+        tests: vec![TestResult {
+            name: TEST_NAME.into(),
+            outcome,
+            duration: start.elapsed(),
+            transcript: lines,
+            waits: Vec::new(),
+        }],
+        duration: start.elapsed(),
+    })
+}
+
+/// Promote every pending `uart.golden.new` under the given targets
+///
+/// Returns the golden files that were replaced.
+pub fn accept(targets: &[Target]) -> Result<Vec<PathBuf>, Error> {
+    let mut accepted = Vec::new();
+    for target in targets {
+        let pending = target.pending_path();
+        if pending.is_file() {
+            let golden = target.golden_path();
+            fs::rename(&pending, &golden)?;
+            accepted.push(golden);
+        }
+    }
+    Ok(accepted)
+}
+
+/// Boot the firmware, send its input and collect UART lines up to the end
+/// of its run
+///
+/// The result is the reason the run did not get to its end line.
+fn capture(
+    config: &Config,
+    target: &Target,
+    peer_port: u16,
+) -> Result<(Vec<String>, Result<(), String>), Error> {
+    if !target.elf.is_file() {
+        return Err(Error::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("{} not built (cargo build --release)", target.elf.display()),
+        )));
+    }
+
+    let log_file = config
+        .log_dir
+        .as_ref()
+        .map(|dir| dir.join(format!("{}.golden.log", target.name())));
+    let mut renode = Renode::launch(&config.renode, config.port, log_file.as_deref())?;
+    let monitor = &mut renode.monitor;
+    monitor.create_machine(&target.machine)?;
+    monitor.load_platform(&target.platform)?;
+    monitor.load_elf(&target.elf)?;
+
+    let test = TestCase {
+        name: TEST_NAME.into(),
+        documentation: String::new(),
+        steps: target.steps(peer_port),
+    };
+    let mut uart: Option<UartReader> = None;
+    let mut processes = Vec::new();
+    let finished = run::run_steps(
+        config,
+        &mut renode,
+        &test,
+        None,
+        &mut uart,
+        &mut Vec::new(),
+        &mut processes,
+    )
+    .and_then(|()| {
+        let reader = uart.as_mut().ok_or("no terminal attached")?;
+        let timeout = CAPTURE_TIMEOUT.mul_f64(config.timeout_scale);
+        match reader.wait_for(timeout, |line| target.is_end(line)) {
+            Some(_) => {
+                reader.drain(TRAILING_QUIET);
+                Ok(())
+            }
+            None => Err(format!(
+                "no line ending in \"{}\" within {:.0}s",
+                target.golden.end.join("\" or \""),
+                timeout.as_secs_f64()
+            )),
+        }
+    });
+    run::terminate(&mut processes);
+    let lines = uart.map(UartReader::into_transcript).unwrap_or_default();
+    Ok((lines, finished))
+}
+
+/// Whether `line` ends with one of `end`
+fn is_end_line(end: &[String], line: &str) -> bool {
+    let line = line.trim_end();
+    end.iter().any(|end| line.ends_with(end.as_str()))
+}
+
+fn default_masks() -> Vec<Mask> {
+    DEFAULT_MASKS
+        .iter()
+        .map(|(pattern, replacement)| Mask::new(pattern, replacement).expect("valid default mask"))
+        .collect()
+}
+
+/// Read `pattern => replacement` rules; a missing file means no rules
+pub fn load_masks(path: &Path) -> Result<Vec<Mask>, Error> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut masks = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parse_err = |msg: String| Error::Parse {
+            path: path.to_path_buf(),
+            line: index + 1,
+            msg,
+        };
+        let (pattern, replacement) = line
+            .split_once(" => ")
+            .ok_or_else(|| parse_err("expected `pattern => replacement`".into()))?;
+        masks.push(
+            Mask::new(pattern.trim(), replacement.trim())
+                .map_err(|err| parse_err(err.to_string()))?,
+        );
+    }
+    Ok(masks)
+}
+
+/// Apply every mask to `line`, in order
+pub fn apply_masks(masks: &[Mask], line: &str) -> String {
+    masks.iter().fold(line.to_string(), |line, mask| {
+        mask.pattern
+            .replace_all(&line, mask.replacement.as_str())
+            .into_owned()
+    })
+}
+
+/// Line diff of `expected` against `actual`; empty if they match
+///
+/// Lines only in the golden file are marked `-`, lines only in the new
+/// output `+`, each prefixed with its position in the golden file.
+pub fn diff(expected: &[String], actual: &[String]) -> Vec<String> {
+    if expected == actual {
+        return Vec::new();
+    }
+
+    // Longest common subsequence table, filled from the end
+    let (n, m) = (expected.len(), actual.len());
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push(format!("{:>4} - {}", i + 1, expected[i]));
+            i += 1;
+        } else {
+            out.push(format!("{:>4} + {}", i + 1, actual[j]));
+            j += 1;
+        }
+    }
+    out
+}
+
+fn pending_path(golden: &Path) -> PathBuf {
+    let mut name = golden.as_os_str().to_owned();
+    name.push(PENDING_SUFFIX);
+    PathBuf::from(name)
+}
+
+fn write_transcript(path: &Path, lines: &[String]) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    fs::write(path, text)?;
+    Ok(())
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), Error> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    /// Write `text` to a masks file of its own and load it
+    fn masks_from(text: &str) -> Result<Vec<Mask>, Error> {
+        static FILES: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!("golden-masks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.masks", FILES.fetch_add(1, Ordering::Relaxed)));
+        fs::write(&path, text).unwrap();
+        let masks = load_masks(&path);
+        fs::remove_file(&path).unwrap();
+        masks
+    }
+
+    #[test]
+    fn diff_of_equal_transcripts_is_empty() {
+        let golden = lines("A\nB\nC");
+        assert!(diff(&golden, &golden).is_empty());
+        assert!(diff(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn diff_marks_removed_and_added_lines() {
+        let expected = lines("Test 1\nData verified: PASS\nTEST PASSED");
+        let actual = lines("Test 1\nMismatch at 00\nData mismatch: FAIL\nTEST PASSED");
+        assert_eq!(
+            diff(&expected, &actual),
+            [
+                "   2 - Data verified: PASS",
+                "   3 + Mismatch at 00",
+                "   3 + Data mismatch: FAIL",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_reports_truncated_and_extra_output() {
+        assert_eq!(
+            diff(&lines("A\nB\nC"), &lines("A")),
+            ["   2 - B", "   3 - C"]
+        );
+        assert_eq!(diff(&lines("A"), &lines("A\nB")), ["   2 + B"]);
+        assert_eq!(diff(&[], &lines("A")), ["   1 + A"]);
+    }
+
+    #[test]
+    fn default_masks_cover_build_and_stack_lines() {
+        let masks = default_masks();
+        assert_eq!(
+            apply_masks(&masks, "Build: 0.1.0 3f2a9c1-dirty release"),
+            "Build: <masked>"
+        );
+        assert_eq!(
+            apply_masks(&masks, "Stack: used 1184 of 8192 bytes"),
+            "Stack: used N of N bytes"
+        );
+        assert_eq!(apply_masks(&masks, "DMA TEST PASSED"), "DMA TEST PASSED");
+        // Anchored: only whole lines
+        assert_eq!(
+            apply_masks(&masks, "Stack: used 1 of 2 bytes (peak)"),
+            "Stack: used 1 of 2 bytes (peak)"
+        );
+    }
+
+    #[test]
+    fn masks_file_skips_comments_and_applies_in_order() {
+        let masks = masks_from(
+            "# buffer addresses\n\
+             \n\
+             0x2000[0-9A-F]{4} => 0x2000XXXX\n\
+             ^(CNT[12]): 0x[0-9A-F]{8}$ => ${1}: 0xXXXXXXXX\n\
+             XXXX => YYYY\n",
+        )
+        .unwrap();
+        assert_eq!(masks.len(), 3);
+        assert_eq!(
+            apply_masks(&masks, "SRC: 0x20000010 DST: 0x20000020"),
+            "SRC: 0x2000YYYY DST: 0x2000YYYY"
+        );
+        assert_eq!(apply_masks(&masks, "CNT2: 0x0000012C"), "CNT2: 0xYYYYYYYY");
+    }
+
+    #[test]
+    fn masks_file_errors_name_the_line() {
+        match masks_from("# ok\nno arrow here\n") {
+            Err(Error::Parse { line, msg, .. }) => {
+                assert_eq!(line, 2);
+                assert!(msg.contains("pattern => replacement"), "{}", msg);
+            }
+            other => panic!("expected a parse error, got {:?}", other.map(|m| m.len())),
+        }
+        match masks_from("(unclosed => x\n") {
+            Err(Error::Parse { line, .. }) => assert_eq!(line, 1),
+            other => panic!("expected a parse error, got {:?}", other.map(|m| m.len())),
+        }
+    }
+
+    #[test]
+    fn missing_masks_file_means_no_masks() {
+        let masks = load_masks(Path::new("/nonexistent/tests/uart.masks")).unwrap();
+        assert!(masks.is_empty());
+    }
+
+    #[test]
+    fn end_lines_match_line_endings() {
+        let end = Golden::default().end;
+        assert!(is_end_line(&end, "DMA TEST PASSED"));
+        assert!(is_end_line(&end, "DMA TEST FAILED  "));
+        assert!(!is_end_line(&end, "TEST PASSED, more to come"));
+        assert!(!is_end_line(&end, "SELFTEST PASSED extra"));
+
+        let end = vec!["SELFTEST PASSED".to_string(), "SELFTEST FAILED".to_string()];
+        assert!(is_end_line(&end, "SELFTEST PASSED"));
+        assert!(!is_end_line(&end, "CRC TEST PASSED"));
+    }
+
+    /// Every firmware crate has a golden transcript that its own masks
+    /// leave alone and that ends the way its metadata says
+    #[test]
+    fn checked_in_transcripts_are_consistent() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        let mut checked = 0;
+        for entry in fs::read_dir(&root).unwrap() {
+            let dir = entry.unwrap().path();
+            if !dir.join("Cargo.toml").is_file() {
+                continue;
+            }
+            let Some(target) = Target::new(&root, &dir).unwrap() else {
+                continue;
+            };
+            let name = target.name();
+            let mut masks = default_masks();
+            masks.extend(load_masks(&target.dir.join(MASKS_FILE)).unwrap());
+            assert!(
+                target.dir.join(MASKS_FILE).is_file(),
+                "{}: no {}",
+                name,
+                MASKS_FILE
+            );
+            let text = fs::read_to_string(target.golden_path())
+                .unwrap_or_else(|err| panic!("{}: {}: {}", name, GOLDEN_FILE, err));
+            let golden = lines(&text);
+            for line in &golden {
+                assert_eq!(&apply_masks(&masks, line), line, "{}: unmasked line", name);
+            }
+            let last = golden.last().map(String::as_str).unwrap_or("");
+            assert!(target.is_end(last), "{}: ends with `{}`", name, last);
+            assert_eq!(
+                golden.iter().filter(|line| target.is_end(line)).count(),
+                1,
+                "{}: more than one end line",
+                name
+            );
+            checked += 1;
+        }
+        assert!(checked > 0, "no firmware crates under {}", root.display());
+    }
+}
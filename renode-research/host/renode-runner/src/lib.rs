@@ -0,0 +1,69 @@
+//! Run the firmware Robot suites against Renode without Robot Framework
+//!
+//! Robot's Renode keywords need the Mono build of Renode, which the ARM64
+//! dotnet portable package does not provide (see `NOTES.md`). This crate
+//! reads the same `stm32f3-*/tests/*.robot` files, drives a headless Renode
+//! through `renode-monitor` and checks USART1 output on the
+//! `CreateUartPtyTerminal` PTY, so the suites run wherever Renode does.
+
+use std::fmt;
+use std::path::PathBuf;
+
+pub mod flaky;
+pub mod golden;
+pub mod parallel;
+pub mod robot;
+pub mod run;
+
+/// Errors from the runner
+#[derive(Debug)]
+pub enum Error {
+    /// File or socket I/O failed
+    Io(std::io::Error),
+    /// A `.robot` file uses syntax or keywords the runner does not support
+    Parse {
+        path: PathBuf,
+        line: usize,
+        msg: String,
+    },
+    /// Renode could not be started or rejected a command
+    Renode(renode_monitor::Error),
+    /// A crate's `[package.metadata.renode]` could not be read
+    Metadata(renode_gen::Error),
+    /// A suite failed to run on a `--jobs` slot; `slot` names its ports
+    Slot { slot: String, err: Box<Error> },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Parse { path, line, msg } => {
+                write!(f, "{}:{}: {}", path.display(), line, msg)
+            }
+            Error::Renode(err) => write!(f, "renode: {}", err),
+            Error::Metadata(err) => write!(f, "{}", err),
+            Error::Slot { slot, err } => write!(f, "{} (on {})", err, slot),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<renode_monitor::Error> for Error {
+    fn from(err: renode_monitor::Error) -> Self {
+        Error::Renode(err)
+    }
+}
+
+impl From<renode_gen::Error> for Error {
+    fn from(err: renode_gen::Error) -> Self {
+        Error::Metadata(err)
+    }
+}
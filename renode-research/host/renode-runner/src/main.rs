@@ -0,0 +1,527 @@
+//! Command line front end for the Renode test runner
+//!
+//! Usage: `renode-runner [OPTIONS] [CRATE]...`
+//!
+//! `CRATE` is a peripheral name such as `dma` or `stm32f3-dma`; with none
+//! given every `stm32f3-*/tests/*.robot` suite is run. Build the firmware
+//! first (`cargo build --release` in `renode-research/`).
+//!
+//! `--golden` compares each firmware's full UART transcript with its
+//! `tests/uart.golden` instead (see `golden.rs`); `--update` re-records the
+//! golden files and `--accept` promotes reviewed `uart.golden.new` files.
+//!
+//! `--repeat N` runs every suite N times and reports per-case pass rates and
+//! the spread of the host and virtual time to each expected line (see
+//! `flaky.rs`).
+//!
+//! `--jobs N` runs up to N suites at once, each in its own Renode with
+//! its own ports and PTY (see `parallel.rs`); output and reports keep the
+//! suite order of a serial run.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::Parser;
+
+use renode_runner::flaky::{self, CaseStats, SuiteRun};
+use renode_runner::golden;
+use renode_runner::parallel::{self, Slot};
+use renode_runner::robot::Suite;
+use renode_runner::run::{self, Config, Outcome, SuiteResult};
+use test_report::{html, junit, Report};
+
+/// Number of UART lines shown for a failing test
+const TRANSCRIPT_TAIL: usize = 20;
+
+#[derive(Parser)]
+#[command(about = "Run the firmware Robot suites against Renode without Robot Framework")]
+struct Args {
+    /// Peripheral crates to test (e.g. `dma i2c`); all when omitted
+    crates: Vec<String>,
+
+    /// Firmware workspace root
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))]
+    root: PathBuf,
+
+    /// Renode executable
+    #[arg(long, env = "RENODE", default_value = "renode")]
+    renode: PathBuf,
+
+    /// Renode monitor port
+    #[arg(long, default_value_t = 1234)]
+    port: u16,
+
+    /// PTY path for the UART terminal
+    #[arg(long, default_value = "/tmp/uart")]
+    uart: PathBuf,
+
+    /// Run up to this many suites at once, each in its own Renode
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..=64))]
+    jobs: u16,
+
+    /// Multiply every UART wait timeout (for slow hosts)
+    #[arg(long, default_value_t = 1.0)]
+    timeout_scale: f64,
+
+    /// Only run test cases whose name contains this text (repeatable)
+    #[arg(long = "test")]
+    tests: Vec<String>,
+
+    /// Override a suite variable, e.g. `ELF:/path/to/elf` (repeatable)
+    #[arg(long = "variable", value_name = "NAME:VALUE", value_parser = parse_variable)]
+    variables: Vec<(String, String)>,
+
+    /// Save Renode's log for each suite in this directory
+    #[arg(long)]
+    log_dir: Option<PathBuf>,
+
+    /// Record a PC trace of every test case under DIR/<crate>/ for
+    /// `trace-coverage`
+    #[arg(long, value_name = "DIR")]
+    trace_dir: Option<PathBuf>,
+
+    /// Write a JUnit XML report here
+    #[arg(long)]
+    junit: Option<PathBuf>,
+
+    /// Write an HTML summary here
+    #[arg(long)]
+    html: Option<PathBuf>,
+
+    /// Compare full UART transcripts with `tests/uart.golden`
+    #[arg(long)]
+    golden: bool,
+
+    /// Re-record `tests/uart.golden` (implies --golden)
+    #[arg(long, conflicts_with = "accept")]
+    update: bool,
+
+    /// Promote pending `tests/uart.golden.new` files without running Renode
+    #[arg(long)]
+    accept: bool,
+
+    /// Run every suite this many times and flag flaky test cases
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..),
+          conflicts_with_all = ["golden", "update", "accept"])]
+    repeat: u32,
+
+    /// Flag a case whose virtual time to an expected line varies by more
+    /// than this fraction of the mean (with --repeat)
+    #[arg(long, default_value_t = 0.2)]
+    spread: f64,
+
+    /// Write per-case timing statistics as TSV here (with --repeat)
+    #[arg(long)]
+    stats: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let dirs = match crate_dirs(&args.root, &args.crates) {
+        Ok(dirs) => dirs,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::from(2);
+        }
+    };
+
+    if args.accept {
+        return accept(&args.root, &dirs);
+    }
+
+    let variables: HashMap<String, String> = args.variables.iter().cloned().collect();
+    let jobs: Vec<Job> = if args.golden || args.update {
+        let mode = if args.update {
+            golden::Mode::Update
+        } else {
+            golden::Mode::Check
+        };
+        match golden_targets(&args.root, &dirs, !args.crates.is_empty()) {
+            Ok(targets) => targets
+                .into_iter()
+                .map(|target| Job::Golden(target, mode))
+                .collect(),
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::from(2);
+            }
+        }
+    } else {
+        match load_suites(&dirs, &variables) {
+            Ok(suites) => suites.into_iter().map(Job::Suite).collect(),
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::from(2);
+            }
+        }
+    };
+    if jobs.is_empty() {
+        eprintln!("nothing to run under {}", args.root.display());
+        return ExitCode::from(2);
+    }
+
+    if let Some(dir) = &args.log_dir {
+        if let Err(err) = fs::create_dir_all(dir) {
+            eprintln!("{}: {}", dir.display(), err);
+            return ExitCode::from(2);
+        }
+    }
+
+    let mut config = Config {
+        renode: args.renode,
+        port: args.port,
+        uart_path: args.uart,
+        timeout_scale: args.timeout_scale,
+        test_filter: args.tests,
+        log_dir: args.log_dir.clone(),
+        trace_dir: args.trace_dir.clone(),
+    };
+
+    let repeat = args.repeat as usize;
+    let mut runs: Vec<Vec<SuiteRun>> = Vec::new();
+    let mut report = Report::default();
+    let mut errors = 0;
+    for run in 1..=repeat {
+        // Keep each repetition's Renode logs apart
+        if let (Some(dir), true) = (&args.log_dir, repeat > 1) {
+            let dir = dir.join(format!("run{}", run));
+            if let Err(err) = fs::create_dir_all(&dir) {
+                eprintln!("{}: {}", dir.display(), err);
+                return ExitCode::from(2);
+            }
+            config.log_dir = Some(dir);
+        }
+
+        let mut results = Vec::new();
+        let execute = |slot: &Slot, job: &Job| {
+            let result = match job {
+                // Read again with this slot's ports; the file parsed above
+                Job::Suite(suite) => Suite::load_with(&suite.path, &slot.variables(&variables))
+                    .and_then(|suite| run::run_suite(&slot.config(&config), &suite)),
+                Job::Golden(target, mode) => {
+                    golden::run_target(&slot.config(&config), target, *mode, slot.peer_port)
+                }
+            };
+            if args.jobs > 1 {
+                slot.annotate(result)
+            } else {
+                result
+            }
+        };
+        let ran = parallel::run(
+            &config,
+            args.jobs.into(),
+            &jobs,
+            execute,
+            |index, result| {
+                let job = &jobs[index];
+                if repeat > 1 {
+                    println!("=== {} (run {}/{}) ===", job.name(), run, repeat);
+                } else {
+                    println!("=== {} ===", job.name());
+                }
+                let mut suite = match result {
+                    Ok(result) => {
+                        print_suite(&result);
+                        let suite = (&result).into();
+                        results.push(Ok(result));
+                        suite
+                    }
+                    Err(err) => {
+                        println!("  ERROR {}", err);
+                        errors += 1;
+                        results.push(Err(job.path().to_path_buf()));
+                        setup_failure(job.path(), &err)
+                    }
+                };
+                if repeat > 1 {
+                    suite.name = format!("{} (run {})", suite.name, run);
+                }
+                report.push(suite);
+            },
+        );
+        if let Err(err) = ran {
+            eprintln!("{}", err);
+            return ExitCode::from(2);
+        }
+        runs.push(results);
+    }
+
+    let mut flaky_cases = 0;
+    if repeat > 1 {
+        let cases = flaky::analyze(&runs, args.spread);
+        print_flaky(&cases);
+        flaky_cases = cases.iter().filter(|c| c.is_flaky()).count();
+        if let Some(path) = &args.stats {
+            if let Err(err) = fs::write(path, flaky::tsv(&cases)) {
+                eprintln!("{}: {}", path.display(), err);
+                errors += 1;
+            }
+        }
+    }
+
+    for (path, content) in [
+        (&args.junit, junit::write(&report)),
+        (&args.html, html::write(&report)),
+    ] {
+        if let Some(path) = path {
+            if let Err(err) = fs::write(path, content) {
+                eprintln!("{}: {}", path.display(), err);
+                errors += 1;
+            }
+        }
+    }
+
+    let results = runs.iter().flatten().filter_map(|run| run.as_ref().ok());
+    let passed: usize = results.clone().map(SuiteResult::passed).sum();
+    let failed: usize = results.map(SuiteResult::failed).sum();
+    println!();
+    println!("=== Test Summary ===");
+    println!("Passed: {}", passed);
+    println!("Failed: {}", failed);
+    if repeat > 1 {
+        println!("Flaky: {}", flaky_cases);
+    }
+    if errors > 0 {
+        println!("Suite errors: {}", errors);
+    }
+
+    if failed == 0 && errors == 0 && flaky_cases == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// What to run for one crate
+enum Job {
+    Suite(Suite),
+    Golden(golden::Target, golden::Mode),
+}
+
+impl Job {
+    fn name(&self) -> String {
+        match self {
+            Job::Suite(suite) => suite.name.clone(),
+            Job::Golden(target, _) => format!("{} (golden)", target.name()),
+        }
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Job::Suite(suite) => &suite.path,
+            Job::Golden(target, _) => &target.dir,
+        }
+    }
+}
+
+/// `stm32f3-*` crate directories, restricted to `crates` if non-empty
+fn crate_dirs(root: &Path, crates: &[String]) -> Result<Vec<PathBuf>, renode_runner::Error> {
+    let wanted: Vec<String> = crates
+        .iter()
+        .map(|c| {
+            if c.starts_with("stm32f3-") {
+                c.clone()
+            } else {
+                format!("stm32f3-{}", c)
+            }
+        })
+        .collect();
+
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with("stm32f3-"))
+        })
+        .collect();
+    dirs.sort();
+
+    for name in &wanted {
+        if !dirs.iter().any(|d| d.ends_with(name)) {
+            return Err(renode_runner::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no crate {} in {}", name, root.display()),
+            )));
+        }
+    }
+
+    dirs.retain(|dir| wanted.is_empty() || wanted.iter().any(|name| dir.ends_with(name)));
+    Ok(dirs)
+}
+
+/// Parse `<dir>/tests/*.robot` for each crate directory
+fn load_suites(
+    dirs: &[PathBuf],
+    variables: &HashMap<String, String>,
+) -> Result<Vec<Suite>, renode_runner::Error> {
+    let mut suites = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir.join("tests")) else {
+            continue;
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "robot"))
+            .collect();
+        files.sort();
+        for file in files {
+            suites.push(Suite::load_with(file, variables)?);
+        }
+    }
+    Ok(suites)
+}
+
+/// `NAME:VALUE`, as for `robot --variable`
+fn parse_variable(arg: &str) -> Result<(String, String), String> {
+    arg.split_once(':')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME:VALUE, got `{}`", arg))
+}
+
+/// Golden targets for the crate directories; with `named`, every one must
+/// be a firmware crate
+fn golden_targets(
+    root: &Path,
+    dirs: &[PathBuf],
+    named: bool,
+) -> Result<Vec<golden::Target>, renode_runner::Error> {
+    let mut targets = Vec::new();
+    for dir in dirs {
+        match golden::Target::new(root, dir)? {
+            Some(target) => targets.push(target),
+            None if named => {
+                return Err(renode_runner::Error::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!(
+                        "{} has no [package.metadata.renode] to boot it with",
+                        dir.display()
+                    ),
+                )))
+            }
+            None => {}
+        }
+    }
+    Ok(targets)
+}
+
+/// `--accept`: promote pending golden transcripts
+fn accept(root: &Path, dirs: &[PathBuf]) -> ExitCode {
+    let targets = match golden_targets(root, dirs, false) {
+        Ok(targets) => targets,
+        Err(err) => {
+            eprintln!("{}", err);
+            return ExitCode::from(2);
+        }
+    };
+    match golden::accept(&targets) {
+        Ok(accepted) if accepted.is_empty() => {
+            println!("No pending golden transcripts");
+            ExitCode::SUCCESS
+        }
+        Ok(accepted) => {
+            for path in accepted {
+                println!("Accepted {}", path.display());
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Report a suite Renode could not run as a single failed test
+fn setup_failure(path: &Path, err: &renode_runner::Error) -> test_report::Suite {
+    test_report::Suite {
+        name: test_report::peripheral_name(path),
+        source: "renode-runner".into(),
+        duration: Duration::ZERO,
+        tests: vec![test_report::TestCase {
+            name: "Suite Setup".into(),
+            status: test_report::Status::Failed(err.to_string()),
+            duration: Duration::ZERO,
+            transcript: Vec::new(),
+        }],
+    }
+}
+
+fn print_suite(result: &SuiteResult) {
+    for test in &result.tests {
+        match &test.outcome {
+            Outcome::Passed => {
+                println!("  PASS {} ({:.1}s)", test.name, test.duration.as_secs_f64())
+            }
+            Outcome::Failed(reason) => {
+                println!("  FAIL {} ({:.1}s)", test.name, test.duration.as_secs_f64());
+                println!("       {}", reason);
+                let skip = test.transcript.len().saturating_sub(TRANSCRIPT_TAIL);
+                for line in &test.transcript[skip..] {
+                    println!("       | {}", line);
+                }
+            }
+        }
+    }
+    println!(
+        "  {} passed, {} failed in {:.1}s",
+        result.passed(),
+        result.failed(),
+        result.duration.as_secs_f64()
+    );
+}
+
+/// Per-case pass rates and timing over the `--repeat` runs
+fn print_flaky(cases: &[CaseStats]) {
+    println!();
+    println!("=== Repeated Runs ===");
+    for case in cases {
+        let label = if case.is_flaky() {
+            "FLAKY "
+        } else if case.is_stable_pass() {
+            "STABLE"
+        } else {
+            "FAILS "
+        };
+        println!(
+            "  {} {} / {} ({}/{} passed, {:.0}%)",
+            label,
+            case.suite,
+            case.name,
+            case.passed,
+            case.runs,
+            case.pass_rate() * 100.0
+        );
+        for reason in &case.reasons {
+            println!("         {}", reason);
+        }
+        if !case.is_flaky() {
+            continue;
+        }
+        for wait in &case.waits {
+            let virtual_time = match wait.virtual_time {
+                Some(stats) => format!(
+                    "virtual {} +/- {}",
+                    flaky::seconds(stats.mean),
+                    flaky::seconds(stats.stddev)
+                ),
+                None => "virtual n/a".into(),
+            };
+            println!(
+                "         \"{}\": host {} +/- {}, {} ({} runs)",
+                wait.pattern,
+                flaky::seconds(wait.host.mean),
+                flaky::seconds(wait.host.stddev),
+                virtual_time,
+                wait.seen
+            );
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Run suites on several Renode instances at once
+//!
+//! Every Renode instance needs its own monitor port, UART PTY, GDB port and
+//! `uart-peer` socket, so each worker thread owns a [`Slot`] with resources
+//! no other worker uses:
+//!
+//! | Slot | Monitor port | PTY | `${GDB_PORT}` | `${PEER_PORT}` |
+//! |------|--------------|-----|---------------|----------------|
+//! | 0 | `--port` (1234) | `--uart` (`/tmp/uart`) | 3333 | 4567 |
+//! | n | 1234 + n or above | `/tmp/uart-n` | 3333 + n or above | 4567 + n or above |
+//!
+//! Slot 0 matches a serial run, so `--jobs 1` behaves exactly as before.
+//! The ports of the other slots are probed before any Renode starts: a port
+//! something else is listening on is skipped for the next free one. A port
+//! can still be taken between the probe and Renode binding it, so failures
+//! on a slot name its ports ([`Slot::annotate`]).
+//!
+//! Results are handed back in job order whatever order they finish in, so
+//! the console output and reports do not depend on scheduling.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::io;
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::run::{Config, Outcome, SuiteResult};
+use crate::Error;
+
+/// GDB server port of slot 0, as in the generated `renode-config.resc`
+pub const GDB_PORT: u16 = 3333;
+
+/// `uart-peer` socket port of slot 0, as in the generated suites
+pub const PEER_PORT: u16 = 4567;
+
+/// How many ports past its nominal one a slot's port is looked for
+const PORT_SEARCH: u16 = 100;
+
+/// Ports and paths reserved for one Renode instance
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Slot {
+    pub index: usize,
+    pub port: u16,
+    pub uart_path: PathBuf,
+    pub gdb_port: u16,
+    pub peer_port: u16,
+}
+
+impl Slot {
+    /// Slot `index`, offset from the monitor port and PTY in `base`
+    ///
+    /// Slot 0 takes the configured ports as they are. Other slots take the
+    /// first port from their nominal one that can be bound and is not in
+    /// `taken`; the ports chosen are added to `taken`.
+    pub fn new(base: &Config, index: usize, taken: &mut Vec<u16>) -> Result<Slot, Error> {
+        let offset = index as u16;
+        if index == 0 {
+            taken.extend([base.port, GDB_PORT, PEER_PORT]);
+            return Ok(Slot {
+                index,
+                port: base.port,
+                uart_path: base.uart_path.clone(),
+                gdb_port: GDB_PORT,
+                peer_port: PEER_PORT,
+            });
+        }
+        let mut uart_path = base.uart_path.clone().into_os_string();
+        uart_path.push(format!("-{}", index));
+        Ok(Slot {
+            index,
+            port: free_port(base.port.saturating_add(offset), taken)?,
+            uart_path: uart_path.into(),
+            gdb_port: free_port(GDB_PORT + offset, taken)?,
+            peer_port: free_port(PEER_PORT + offset, taken)?,
+        })
+    }
+
+    /// `base` with this slot's monitor port and PTY
+    pub fn config(&self, base: &Config) -> Config {
+        Config {
+            port: self.port,
+            uart_path: self.uart_path.clone(),
+            ..base.clone()
+        }
+    }
+
+    /// Suite variables for this slot; entries in `overrides` (from
+    /// `--variable`) take precedence
+    pub fn variables(&self, overrides: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut variables = HashMap::from([
+            ("GDB_PORT".to_string(), self.gdb_port.to_string()),
+            ("PEER_PORT".to_string(), self.peer_port.to_string()),
+        ]);
+        variables.extend(overrides.iter().map(|(k, v)| (k.clone(), v.clone())));
+        variables
+    }
+
+    /// Name this slot's ports in the error or the failed test cases of
+    /// `result`, for when another program got to a port first
+    pub fn annotate(&self, result: Result<SuiteResult, Error>) -> Result<SuiteResult, Error> {
+        let mut suite = result.map_err(|err| Error::Slot {
+            slot: self.to_string(),
+            err: Box::new(err),
+        })?;
+        for test in &mut suite.tests {
+            if let Outcome::Failed(msg) = &mut test.outcome {
+                msg.push_str(&format!(" (on {})", self));
+            }
+        }
+        Ok(suite)
+    }
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "slot {}: monitor port {}, PTY {}, GDB port {}, peer port {}",
+            self.index,
+            self.port,
+            self.uart_path.display(),
+            self.gdb_port,
+            self.peer_port
+        )
+    }
+}
+
+/// First port from `start` that nothing listens on and no other slot has
+fn free_port(start: u16, taken: &mut Vec<u16>) -> Result<u16, Error> {
+    let end = start.saturating_add(PORT_SEARCH);
+    let port = (start..=end)
+        .find(|port| {
+            !taken.contains(port) && TcpListener::bind((Ipv4Addr::UNSPECIFIED, *port)).is_ok()
+        })
+        .ok_or_else(|| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("no free port in {}-{}", start, end),
+            ))
+        })?;
+    taken.push(port);
+    Ok(port)
+}
+
+/// Run `work` on every item with at most `jobs` running at once
+///
+/// Worker `n` always runs on slot `n`. `done` is called on the calling
+/// thread with each item's index and result, in item order, as soon as that
+/// item and every one before it have finished. Fails before running
+/// anything if there are not enough free ports for the slots.
+pub fn run<T, R, W, D>(
+    base: &Config,
+    jobs: usize,
+    items: &[T],
+    work: W,
+    mut done: D,
+) -> Result<(), Error>
+where
+    T: Sync,
+    R: Send,
+    W: Fn(&Slot, &T) -> R + Sync,
+    D: FnMut(usize, R),
+{
+    let workers = jobs.clamp(1, items.len().max(1));
+    let mut taken = Vec::new();
+    let slots = (0..workers)
+        .map(|index| Slot::new(base, index, &mut taken))
+        .collect::<Result<Vec<_>, _>>()?;
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for slot in slots {
+            let sender = sender.clone();
+            let (next, work) = (&next, &work);
+            scope.spawn(move || loop {
+                let item = next.fetch_add(1, Ordering::Relaxed);
+                let Some(value) = items.get(item) else {
+                    break;
+                };
+                if sender.send((item, work(&slot, value))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        // Hold back results that finish ahead of an earlier item
+        let mut pending = BTreeMap::new();
+        let mut expected = 0;
+        for (item, result) in receiver {
+            pending.insert(item, result);
+            while let Some(result) = pending.remove(&expected) {
+                done(expected, result);
+                expected += 1;
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::run::TestResult;
+
+    fn config(port: u16) -> Config {
+        Config {
+            renode: PathBuf::from("renode"),
+            port,
+            uart_path: PathBuf::from("/tmp/uart"),
+            timeout_scale: 1.0,
+            test_filter: Vec::new(),
+            log_dir: None,
+            trace_dir: None,
+        }
+    }
+
+    /// A port something is listening on, and the listener keeping it busy
+    fn busy_port() -> (u16, TcpListener) {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        (listener.local_addr().unwrap().port(), listener)
+    }
+
+    #[test]
+    fn slot_zero_keeps_the_configured_ports() {
+        let (port, _listener) = busy_port();
+        let slot = Slot::new(&config(port), 0, &mut Vec::new()).unwrap();
+        assert_eq!(slot.port, port);
+        assert_eq!(slot.uart_path, PathBuf::from("/tmp/uart"));
+        assert_eq!((slot.gdb_port, slot.peer_port), (GDB_PORT, PEER_PORT));
+    }
+
+    #[test]
+    fn busy_ports_are_skipped() {
+        let (port, _listener) = busy_port();
+        // Slot 1's nominal monitor port is the busy one
+        let slot = Slot::new(&config(port - 1), 1, &mut Vec::new()).unwrap();
+        assert!(slot.port > port, "{}", slot);
+        assert_eq!(slot.uart_path, PathBuf::from("/tmp/uart-1"));
+    }
+
+    #[test]
+    fn slots_never_share_a_port() {
+        let (port, _listener) = busy_port();
+        let base = config(port);
+        let mut taken = Vec::new();
+        let slots: Vec<Slot> = (0..4)
+            .map(|index| Slot::new(&base, index, &mut taken).unwrap())
+            .collect();
+        let mut ports: Vec<u16> = slots
+            .iter()
+            .flat_map(|slot| [slot.port, slot.gdb_port, slot.peer_port])
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+        assert_eq!(ports.len(), 12);
+    }
+
+    #[test]
+    fn failures_name_the_slot_ports() {
+        let slot = Slot {
+            index: 2,
+            port: 1236,
+            uart_path: PathBuf::from("/tmp/uart-2"),
+            gdb_port: 3335,
+            peer_port: 4569,
+        };
+        let ports = "slot 2: monitor port 1236, PTY /tmp/uart-2, GDB port 3335, peer port 4569";
+        assert_eq!(slot.to_string(), ports);
+
+        let err = slot
+            .annotate(Err(Error::Io(io::Error::other("connection refused"))))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("I/O error: connection refused (on {})", ports)
+        );
+
+        let test = |name: &str, outcome| TestResult {
+            name: name.into(),
+            outcome,
+            duration: Duration::ZERO,
+            transcript: Vec::new(),
+            waits: Vec::new(),
+        };
+        let suite = SuiteResult {
+            name: "usart2".into(),
+            path: PathBuf::from("test-usart2.robot"),
+            tests: vec![
+                test("Passes", Outcome::Passed),
+                test("Fails", Outcome::Failed("timed out".into())),
+            ],
+            duration: Duration::ZERO,
+        };
+        let suite = slot.annotate(Ok(suite)).unwrap();
+        assert_eq!(suite.tests[0].outcome, Outcome::Passed);
+        assert_eq!(
+            suite.tests[1].outcome,
+            Outcome::Failed(format!("timed out (on {})", ports))
+        );
+    }
+
+    #[test]
+    fn results_come_back_in_item_order() {
+        let (port, _listener) = busy_port();
+        let items: Vec<u64> = vec![30, 0, 20, 5, 10];
+        let mut seen = Vec::new();
+        run(
+            &config(port),
+            3,
+            &items,
+            |slot, &ms| {
+                std::thread::sleep(Duration::from_millis(ms));
+                (ms, slot.index)
+            },
+            |index, (ms, slot)| {
+                assert!(slot < 3);
+                seen.push((index, ms));
+            },
+        )
+        .unwrap();
+        assert_eq!(seen, [(0, 30), (1, 0), (2, 20), (3, 5), (4, 10)]);
+    }
+}
@@ -0,0 +1,619 @@
+//! Parser for the subset of Robot Framework used by the firmware suites
+//!
+//! The `tests/*.robot` files only use a handful of Renode keywords, so the
+//! runner reads them directly instead of duplicating the expectations:
+//!
+//! | Keyword | Step |
+//! |---------|------|
+//! | `Execute Command <cmd>` | [`Step::Execute`] |
+//! | `Create Terminal Tester <uart>` | [`Step::CreateTerminalTester`] |
+//! | `Start Emulation` | [`Step::StartEmulation`] |
+//! | `Wait For Line On Uart <text> timeout=<s>` | [`Step::WaitForLine`] |
+//! | `Write Line To Uart <text>` | [`Step::WriteLine`] |
+//! | `Sleep <s>` | [`Step::Sleep`] |
+//! | `Start Process <command> <args>...` | [`Step::StartProcess`] |
+//! | `Terminate All Processes` | [`Step::TerminateProcesses`] |
+//! | `${result}= Run Process <command> <args>...` | [`Step::RunProcess`] |
+//! | `Should Be Equal As Integers ${result.rc} <code>` | [`Step::ExpectExitCode`] |
+//!
+//! Keywords defined in the suite's own `*** Keywords ***` table are expanded
+//! in place. `${VAR}` references are expanded from the `*** Variables ***`
+//! table, `${CURDIR}` and `${TEMPDIR}`; [`Suite::load_with`] overrides table entries like
+//! `robot --variable`. Settings (`Suite Setup`, `Resource`, ...) are
+//! ignored since the runner performs the equivalent setup itself.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::Error;
+
+/// Default `Wait For Line On Uart` timeout used by the Renode keywords
+pub(crate) const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Deepest chain of user keywords calling each other
+const MAX_KEYWORD_DEPTH: usize = 16;
+
+/// One action in a test case
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    /// Send a command to the Renode monitor
+    Execute(String),
+    /// Attach a UART to the PTY terminal the runner reads from
+    CreateTerminalTester(String),
+    /// Start the emulation
+    StartEmulation,
+    /// Wait for a UART line containing `pattern`
+    WaitForLine { pattern: String, timeout: Duration },
+    /// Type a line into the UART, ended with CR
+    WriteLine(String),
+    /// Pause the runner (the emulation keeps going)
+    Sleep(Duration),
+    /// Start a host program (such as `uart-peer`) in the background; it is
+    /// killed when the test case ends
+    StartProcess { program: String, args: Vec<String> },
+    /// Kill every process the test case started
+    TerminateProcesses,
+    /// Run a host program to completion, keeping its exit status and output
+    RunProcess { program: String, args: Vec<String> },
+    /// Fail unless the last [`Step::RunProcess`] exited with this code
+    ExpectExitCode(i32),
+}
+
+/// `Start Process` configuration arguments, which are not passed on to the
+/// program
+const PROCESS_OPTIONS: &[&str] = &["alias=", "cwd=", "shell=", "stdout=", "stderr=", "stdin="];
+
+/// A test case from the `*** Test Cases ***` table
+#[derive(Clone, Debug)]
+pub struct TestCase {
+    pub name: String,
+    pub documentation: String,
+    pub steps: Vec<Step>,
+}
+
+/// A parsed `.robot` file
+#[derive(Clone, Debug)]
+pub struct Suite {
+    /// Suite name, taken from the file stem (e.g. `test-dma`)
+    pub name: String,
+    pub path: PathBuf,
+    pub tests: Vec<TestCase>,
+}
+
+#[derive(PartialEq)]
+enum Table {
+    None,
+    Settings,
+    Variables,
+    TestCases,
+    Keywords,
+    Other,
+}
+
+/// A keyword row, kept unresolved until every user keyword is known
+struct Row {
+    line: usize,
+    keyword: String,
+    args: Vec<String>,
+}
+
+impl Suite {
+    /// Parse a `.robot` file
+    pub fn load(path: impl AsRef<Path>) -> Result<Suite, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        Self::parse(path, &text)
+    }
+
+    /// Parse a `.robot` file with `${NAME}` values that take precedence
+    /// over its `*** Variables ***` table (e.g. a different `ELF`)
+    pub fn load_with(
+        path: impl AsRef<Path>,
+        overrides: &HashMap<String, String>,
+    ) -> Result<Suite, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        Self::parse_with(path, &text, overrides)
+    }
+
+    /// Parse `.robot` source; `path` is used for `${CURDIR}` and messages
+    pub fn parse(path: &Path, text: &str) -> Result<Suite, Error> {
+        Self::parse_with(path, text, &HashMap::new())
+    }
+
+    /// [`parse`](Self::parse) with variable overrides
+    pub fn parse_with(
+        path: &Path,
+        text: &str,
+        overrides: &HashMap<String, String>,
+    ) -> Result<Suite, Error> {
+        let curdir = path
+            .parent()
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|| ".".into());
+        let mut variables = overrides.clone();
+        variables.insert("CURDIR".to_string(), curdir);
+        variables
+            .entry("TEMPDIR".to_string())
+            .or_insert_with(|| env::temp_dir().to_string_lossy().into_owned());
+
+        let mut table = Table::None;
+        let mut tests: Vec<(TestCase, Vec<Row>)> = Vec::new();
+        let mut keywords: HashMap<String, Vec<Row>> = HashMap::new();
+        let mut current_keyword: Option<String> = None;
+
+        for (index, raw) in text.lines().enumerate() {
+            let line_no = index + 1;
+            let parse_err = |msg: String| Error::Parse {
+                path: path.to_path_buf(),
+                line: line_no,
+                msg,
+            };
+
+            if raw.starts_with("***") {
+                let header = raw.trim_matches(|c| c == '*' || c == ' ').to_lowercase();
+                table = match header.as_str() {
+                    "settings" | "setting" => Table::Settings,
+                    "variables" | "variable" => Table::Variables,
+                    "test cases" | "test case" => Table::TestCases,
+                    "keywords" | "keyword" => Table::Keywords,
+                    _ => Table::Other,
+                };
+                continue;
+            }
+
+            let cells = split_cells(raw);
+            if cells.is_empty() || cells[0].starts_with('#') {
+                continue;
+            }
+
+            match table {
+                Table::Variables => {
+                    let name = cells[0]
+                        .strip_prefix("${")
+                        .and_then(|n| n.strip_suffix('}'))
+                        .ok_or_else(|| parse_err(format!("bad variable `{}`", cells[0])))?;
+                    if !overrides.contains_key(name) {
+                        let value = expand(cells.get(1).copied().unwrap_or(""), &variables);
+                        variables.insert(name.to_string(), value);
+                    }
+                }
+                Table::TestCases => {
+                    let indented = raw.starts_with(' ') || raw.starts_with('\t');
+                    if !indented {
+                        let test = TestCase {
+                            name: cells[0].to_string(),
+                            documentation: String::new(),
+                            steps: Vec::new(),
+                        };
+                        tests.push((test, Vec::new()));
+                        continue;
+                    }
+
+                    let (test, rows) = tests
+                        .last_mut()
+                        .ok_or_else(|| parse_err("step outside a test case".into()))?;
+                    let args: Vec<String> =
+                        cells[1..].iter().map(|c| expand(c, &variables)).collect();
+                    match cells[0] {
+                        "[Documentation]" => test.documentation = args.join(" "),
+                        "..." => {
+                            if !test.documentation.is_empty() {
+                                test.documentation.push(' ');
+                            }
+                            test.documentation.push_str(&args.join(" "));
+                        }
+                        keyword => rows.push(Row {
+                            line: line_no,
+                            keyword: keyword.to_string(),
+                            args,
+                        }),
+                    }
+                }
+                Table::Keywords => {
+                    let indented = raw.starts_with(' ') || raw.starts_with('\t');
+                    if !indented {
+                        let name = cells[0].to_lowercase();
+                        keywords.insert(name.clone(), Vec::new());
+                        current_keyword = Some(name);
+                        continue;
+                    }
+
+                    let rows = current_keyword
+                        .as_ref()
+                        .and_then(|name| keywords.get_mut(name))
+                        .ok_or_else(|| parse_err("step outside a keyword".into()))?;
+                    // Keyword settings ([Documentation], [Arguments], ...) and
+                    // documentation continuations don't produce steps
+                    if cells[0].starts_with('[') || cells[0] == "..." {
+                        continue;
+                    }
+                    rows.push(Row {
+                        line: line_no,
+                        keyword: cells[0].to_string(),
+                        args: cells[1..].iter().map(|c| expand(c, &variables)).collect(),
+                    });
+                }
+                Table::None | Table::Settings | Table::Other => {}
+            }
+        }
+
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let tests = tests
+            .into_iter()
+            .map(|(mut test, rows)| {
+                resolve(path, &rows, &keywords, 0, &mut test.steps)?;
+                Ok(test)
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Suite {
+            name,
+            path: path.to_path_buf(),
+            tests,
+        })
+    }
+}
+
+/// Turn rows into steps, expanding calls to user keywords
+fn resolve(
+    path: &Path,
+    rows: &[Row],
+    keywords: &HashMap<String, Vec<Row>>,
+    depth: usize,
+    steps: &mut Vec<Step>,
+) -> Result<(), Error> {
+    for row in rows {
+        let parse_err = |msg: String| Error::Parse {
+            path: path.to_path_buf(),
+            line: row.line,
+            msg,
+        };
+        match keywords.get(&row.keyword.to_lowercase()) {
+            Some(_) if depth >= MAX_KEYWORD_DEPTH => {
+                return Err(parse_err(format!(
+                    "keyword `{}` nested too deeply",
+                    row.keyword
+                )))
+            }
+            Some(body) => resolve(path, body, keywords, depth + 1, steps)?,
+            None => steps.push(parse_step(&row.keyword, &row.args).map_err(parse_err)?),
+        }
+    }
+    Ok(())
+}
+
+/// Translate one keyword row into a step
+fn parse_step(keyword: &str, args: &[String]) -> Result<Step, String> {
+    // `${result}=    Run Process    ...`: the runner keeps one result
+    if keyword.starts_with("${") && keyword.ends_with("}=") {
+        let (keyword, args) = args
+            .split_first()
+            .ok_or_else(|| format!("`{}` needs a keyword", keyword))?;
+        return match keyword.to_lowercase().as_str() {
+            "run process" => parse_step(keyword, args),
+            _ => Err(format!("unsupported assignment from `{}`", keyword)),
+        };
+    }
+    let first = || {
+        args.first()
+            .cloned()
+            .ok_or_else(|| format!("`{}` needs an argument", keyword))
+    };
+
+    match keyword.to_lowercase().as_str() {
+        "execute command" => Ok(Step::Execute(first()?)),
+        "create terminal tester" => Ok(Step::CreateTerminalTester(first()?)),
+        "start emulation" => Ok(Step::StartEmulation),
+        "wait for line on uart" => {
+            let pattern = first()?;
+            let mut timeout = DEFAULT_WAIT_TIMEOUT;
+            for arg in &args[1..] {
+                if let Some(value) = arg.strip_prefix("timeout=") {
+                    timeout = parse_seconds(value)?;
+                }
+            }
+            Ok(Step::WaitForLine { pattern, timeout })
+        }
+        "write line to uart" => Ok(Step::WriteLine(first()?)),
+        "sleep" => Ok(Step::Sleep(parse_seconds(&first()?)?)),
+        "start process" => Ok(Step::StartProcess {
+            program: first()?,
+            args: args[1..]
+                .iter()
+                .filter(|arg| !PROCESS_OPTIONS.iter().any(|o| arg.starts_with(o)))
+                .cloned()
+                .collect(),
+        }),
+        // `kill=True` and the like only change how Robot stops them
+        "terminate all processes" => Ok(Step::TerminateProcesses),
+        "run process" => Ok(Step::RunProcess {
+            program: first()?,
+            args: args[1..]
+                .iter()
+                .filter(|arg| !PROCESS_OPTIONS.iter().any(|o| arg.starts_with(o)))
+                .cloned()
+                .collect(),
+        }),
+        // Only the exit status of a `Run Process` result is compared
+        "should be equal as integers" => match args {
+            [result, code, ..] if result.starts_with("${") && result.ends_with(".rc}") => code
+                .parse()
+                .map(Step::ExpectExitCode)
+                .map_err(|_| format!("bad exit code `{}`", code)),
+            _ => Err(format!(
+                "`{}` is only supported on a `Run Process` exit code",
+                keyword
+            )),
+        },
+        _ => Err(format!("unsupported keyword `{}`", keyword)),
+    }
+}
+
+/// Parse a Robot time value given in (fractional) seconds
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let trimmed = value.trim().trim_end_matches('s');
+    trimmed
+        .parse::<f64>()
+        .ok()
+        .filter(|s| *s >= 0.0)
+        .map(Duration::from_secs_f64)
+        .ok_or_else(|| format!("bad time value `{}`", value))
+}
+
+/// Split a row into cells separated by two or more spaces or a tab
+fn split_cells(line: &str) -> Vec<&str> {
+    let line = line.trim_end();
+    let mut cells = Vec::new();
+    let mut rest = line.trim_start();
+    while !rest.is_empty() {
+        let end = find_separator(rest).unwrap_or(rest.len());
+        cells.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    cells
+}
+
+fn find_separator(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    (0..bytes.len())
+        .find(|&i| bytes[i] == b'\t' || (bytes[i] == b' ' && bytes.get(i + 1) == Some(&b' ')))
+}
+
+/// Expand `${NAME}` references; unknown names are left untouched
+fn expand(value: &str, variables: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        match rest[start..].find('}') {
+            Some(len) => {
+                let name = &rest[start + 2..start + len];
+                match variables.get(name) {
+                    Some(v) => out.push_str(v),
+                    None => out.push_str(&rest[start..start + len + 1]),
+                }
+                rest = &rest[start + len + 1..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = "\
+*** Settings ***
+Suite Setup                   Setup
+Resource                      ${RENODEKEYWORDS}
+
+*** Variables ***
+${PLATFORM}                   ${CURDIR}/../stm32f3_dma.repl
+${ELF}                        ${CURDIR}/../../target/stm32f3-dma
+
+*** Test Cases ***
+Should Report
+    [Documentation]           Verify the firmware
+    ...                       reports on UART
+    Start Firmware
+
+    Wait For Line On Uart     DMA Peripheral Test    timeout=5
+    Write Line To Uart        led 3 on
+    Sleep                     0.5s
+
+Should Use Peer
+    Start Process             uart-peer    --connect    alias=peer    stdout=DEVNULL
+    Wait For Line On Uart     ${UNKNOWN} stays
+    Terminate All Processes   kill=True
+
+*** Keywords ***
+Start Firmware
+    [Documentation]           Load the platform and firmware
+    Execute Command           machine LoadPlatformDescription @${PLATFORM}
+    Execute Command           sysbus LoadELF @${ELF}
+    Create Terminal Tester    sysbus.usart1
+    Start Emulation
+";
+
+    fn parse(text: &str) -> Result<Suite, Error> {
+        Suite::parse(Path::new("/fw/tests/test-dma.robot"), text)
+    }
+
+    fn parse_error(text: &str) -> (usize, String) {
+        match parse(text) {
+            Err(Error::Parse { line, msg, .. }) => (line, msg),
+            other => panic!("expected a parse error, got {:?}", other.map(|s| s.tests)),
+        }
+    }
+
+    #[test]
+    fn parses_tests_keywords_and_variables() {
+        let suite = parse(SUITE).unwrap();
+        assert_eq!(suite.name, "test-dma");
+        assert_eq!(suite.tests.len(), 2);
+
+        let test = &suite.tests[0];
+        assert_eq!(test.name, "Should Report");
+        assert_eq!(test.documentation, "Verify the firmware reports on UART");
+        assert_eq!(
+            test.steps,
+            [
+                Step::Execute(
+                    "machine LoadPlatformDescription @/fw/tests/../stm32f3_dma.repl".into()
+                ),
+                Step::Execute("sysbus LoadELF @/fw/tests/../../target/stm32f3-dma".into()),
+                Step::CreateTerminalTester("sysbus.usart1".into()),
+                Step::StartEmulation,
+                Step::WaitForLine {
+                    pattern: "DMA Peripheral Test".into(),
+                    timeout: Duration::from_secs(5),
+                },
+                Step::WriteLine("led 3 on".into()),
+                Step::Sleep(Duration::from_millis(500)),
+            ]
+        );
+    }
+
+    #[test]
+    fn process_options_and_unknown_variables() {
+        let suite = parse(SUITE).unwrap();
+        assert_eq!(
+            suite.tests[1].steps,
+            [
+                Step::StartProcess {
+                    program: "uart-peer".into(),
+                    args: vec!["--connect".into()],
+                },
+                Step::WaitForLine {
+                    pattern: "${UNKNOWN} stays".into(),
+                    timeout: DEFAULT_WAIT_TIMEOUT,
+                },
+                Step::TerminateProcesses,
+            ]
+        );
+    }
+
+    #[test]
+    fn run_process_and_its_exit_code() {
+        let suite = parse(
+            "*** Test Cases ***\nT\n    \
+             ${result}=    Run Process    frame-decode    --summary    ${TEMPDIR}/f.bin    stdout=DEVNULL\n    \
+             Should Be Equal As Integers    ${result.rc}    0    ${result.stdout}\n",
+        )
+        .unwrap();
+        let temp = env::temp_dir().to_string_lossy().into_owned();
+        assert_eq!(
+            suite.tests[0].steps,
+            [
+                Step::RunProcess {
+                    program: "frame-decode".into(),
+                    args: vec!["--summary".into(), format!("{}/f.bin", temp)],
+                },
+                Step::ExpectExitCode(0),
+            ]
+        );
+
+        let (line, msg) = parse_error("*** Test Cases ***\nT\n    ${x}=    Get Time\n");
+        assert_eq!(
+            (line, msg.as_str()),
+            (3, "unsupported assignment from `Get Time`")
+        );
+        let (_, msg) =
+            parse_error("*** Test Cases ***\nT\n    Should Be Equal As Integers    1    1\n");
+        assert!(msg.contains("only supported on a `Run Process` exit code"));
+        let (_, msg) = parse_error(
+            "*** Test Cases ***\nT\n    Should Be Equal As Integers    ${r.rc}    zero\n",
+        );
+        assert_eq!(msg, "bad exit code `zero`");
+    }
+
+    #[test]
+    fn overrides_win_over_the_variables_table() {
+        let overrides = HashMap::from([("ELF".to_string(), "/other/elf".to_string())]);
+        let suite =
+            Suite::parse_with(Path::new("/fw/tests/test-dma.robot"), SUITE, &overrides).unwrap();
+        assert_eq!(
+            suite.tests[0].steps[1],
+            Step::Execute("sysbus LoadELF @/other/elf".into())
+        );
+    }
+
+    #[test]
+    fn keywords_are_case_insensitive() {
+        let suite = parse(
+            "*** Test Cases ***\nT\n    start firmware\n\n\
+             *** Keywords ***\nStart Firmware\n    START EMULATION\n",
+        )
+        .unwrap();
+        assert_eq!(suite.tests[0].steps, [Step::StartEmulation]);
+    }
+
+    #[test]
+    fn reports_the_line_of_errors() {
+        let (line, msg) = parse_error("*** Test Cases ***\nT\n    Sleep    soon\n");
+        assert_eq!((line, msg.as_str()), (3, "bad time value `soon`"));
+
+        let (line, msg) = parse_error("*** Test Cases ***\nT\n    Click Button    ok\n");
+        assert_eq!(
+            (line, msg.as_str()),
+            (3, "unsupported keyword `Click Button`")
+        );
+
+        let (line, msg) = parse_error("*** Test Cases ***\nT\n    Execute Command\n");
+        assert_eq!(
+            (line, msg.as_str()),
+            (3, "`Execute Command` needs an argument")
+        );
+
+        let (line, _) = parse_error("*** Test Cases ***\n    Start Emulation\n");
+        assert_eq!(line, 2);
+
+        let (line, _) = parse_error("*** Variables ***\nPLATFORM    x\n");
+        assert_eq!(line, 2);
+    }
+
+    #[test]
+    fn recursive_keywords_are_rejected() {
+        let (line, msg) =
+            parse_error("*** Test Cases ***\nT\n    Loop\n\n*** Keywords ***\nLoop\n    Loop\n");
+        assert_eq!(line, 7);
+        assert_eq!(msg, "keyword `Loop` nested too deeply");
+    }
+
+    #[test]
+    fn cells_split_on_two_spaces_or_a_tab() {
+        assert_eq!(
+            split_cells("    Wait For Line On Uart  a b\tc    timeout=5  "),
+            ["Wait For Line On Uart", "a b", "c", "timeout=5"]
+        );
+        assert!(split_cells("   ").is_empty());
+    }
+
+    #[test]
+    fn seconds() {
+        assert_eq!(parse_seconds("1.5"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_seconds(" 2s"), Ok(Duration::from_secs(2)));
+        assert!(parse_seconds("-1").is_err());
+        assert!(parse_seconds("1 min").is_err());
+    }
+
+    #[test]
+    fn expand_leaves_unknown_and_unclosed_references() {
+        let variables = HashMap::from([("A".to_string(), "1".to_string())]);
+        assert_eq!(expand("${A}-${B}-${A}", &variables), "1-${B}-1");
+        assert_eq!(expand("x ${A", &variables), "x ${A");
+    }
+}
@@ -0,0 +1,484 @@
+//! Execute parsed suites against a Renode instance
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use renode_monitor::{path_arg, Monitor, Renode, UartReader};
+
+use crate::robot::{Step, Suite, TestCase};
+use crate::Error;
+
+/// How long to wait for Renode to create the PTY symlink
+const PTY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Quiet period used to collect trailing UART output after a failure
+const DRAIN_QUIET: Duration = Duration::from_millis(300);
+
+/// Output lines of a failed `Run Process` quoted in the failure
+const RUN_OUTPUT_LINES: usize = 10;
+
+/// Runner settings shared by all suites
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Renode executable
+    pub renode: PathBuf,
+    /// Monitor socket port
+    pub port: u16,
+    /// PTY path for `CreateUartPtyTerminal`
+    pub uart_path: PathBuf,
+    /// Multiplier applied to every `Wait For Line On Uart` timeout
+    pub timeout_scale: f64,
+    /// Only run test cases whose name contains one of these (all if empty)
+    pub test_filter: Vec<String>,
+    /// Write Renode's log for each suite to this directory
+    pub log_dir: Option<PathBuf>,
+    /// Record a PC trace of every test case under
+    /// `<dir>/<crate>/<suite>-<test>.trace` (for `trace-coverage`)
+    pub trace_dir: Option<PathBuf>,
+}
+
+/// Outcome of one test case
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Passed,
+    Failed(String),
+}
+
+/// Result of one test case
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    pub name: String,
+    pub outcome: Outcome,
+    pub duration: Duration,
+    /// UART lines received during the test
+    pub transcript: Vec<String>,
+    /// When each `Wait For Line On Uart` matched, in step order
+    pub waits: Vec<WaitTiming>,
+}
+
+/// Time from the start of a test case to a matched UART line
+#[derive(Clone, Debug)]
+pub struct WaitTiming {
+    pub pattern: String,
+    /// Wall-clock time since the test case started
+    pub host: Duration,
+    /// Emulated time on the machine when the line was seen, read with the
+    /// emulation paused, if Renode reported it
+    pub virtual_time: Option<Duration>,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.outcome == Outcome::Passed
+    }
+}
+
+/// Result of one suite
+#[derive(Clone, Debug)]
+pub struct SuiteResult {
+    pub name: String,
+    pub path: PathBuf,
+    pub tests: Vec<TestResult>,
+    pub duration: Duration,
+}
+
+impl SuiteResult {
+    pub fn passed(&self) -> usize {
+        self.tests.iter().filter(|t| t.passed()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.tests.len() - self.passed()
+    }
+}
+
+impl From<&SuiteResult> for test_report::Suite {
+    fn from(result: &SuiteResult) -> Self {
+        let tests = result
+            .tests
+            .iter()
+            .map(|test| test_report::TestCase {
+                name: test.name.clone(),
+                status: match &test.outcome {
+                    Outcome::Passed => test_report::Status::Passed,
+                    Outcome::Failed(reason) => test_report::Status::Failed(reason.clone()),
+                },
+                duration: test.duration,
+                transcript: if test.passed() {
+                    Vec::new()
+                } else {
+                    test.transcript.clone()
+                },
+            })
+            .collect();
+        test_report::Suite {
+            name: test_report::peripheral_name(&result.path),
+            source: "renode-runner".into(),
+            duration: result.duration,
+            tests,
+        }
+    }
+}
+
+/// Run every selected test case of `suite` in a fresh Renode instance
+pub fn run_suite(config: &Config, suite: &Suite) -> Result<SuiteResult, Error> {
+    let start = Instant::now();
+    let log_file = config
+        .log_dir
+        .as_ref()
+        .map(|dir| dir.join(format!("{}.log", suite.name)));
+    let trace_dir = match &config.trace_dir {
+        Some(dir) => {
+            let dir = dir.join(crate_name(&suite.path));
+            fs::create_dir_all(&dir)?;
+            Some(dir)
+        }
+        None => None,
+    };
+    let mut renode = Renode::launch(&config.renode, config.port, log_file.as_deref())?;
+
+    let mut tests = Vec::new();
+    for test in suite.tests.iter().filter(|t| selected(config, t)) {
+        let trace = trace_dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}-{}.trace", suite.name, file_stem(&test.name))));
+        tests.push(run_test(config, &mut renode, test, trace.as_deref()));
+    }
+
+    Ok(SuiteResult {
+        name: suite.name.clone(),
+        path: suite.path.clone(),
+        tests,
+        duration: start.elapsed(),
+    })
+}
+
+/// `stm32f3-dma` for `stm32f3-dma/tests/test-dma.robot`, which is also the
+/// name of its ELF
+fn crate_name(suite: &Path) -> String {
+    suite
+        .parent()
+        .and_then(Path::parent)
+        .and_then(Path::file_name)
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "unknown".into())
+}
+
+/// `Should Report Summary` -> `should-report-summary`
+fn file_stem(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn selected(config: &Config, test: &TestCase) -> bool {
+    config.test_filter.is_empty() || config.test_filter.iter().any(|f| test.name.contains(f))
+}
+
+/// Run one test case; equivalent of Robot's `Reset Emulation` test setup
+fn run_test(
+    config: &Config,
+    renode: &mut Renode,
+    test: &TestCase,
+    trace: Option<&Path>,
+) -> TestResult {
+    let start = Instant::now();
+    let mut uart: Option<UartReader> = None;
+    let mut waits = Vec::new();
+    let mut processes = Vec::new();
+
+    let outcome = match renode.monitor.clear() {
+        Ok(_) => match run_steps(
+            config,
+            renode,
+            test,
+            trace,
+            &mut uart,
+            &mut waits,
+            &mut processes,
+        ) {
+            Ok(()) => Outcome::Passed,
+            Err(reason) => Outcome::Failed(reason),
+        },
+        Err(err) => Outcome::Failed(format!("reset failed: {}", err)),
+    };
+
+    let transcript = match uart {
+        Some(mut reader) => {
+            if outcome != Outcome::Passed {
+                reader.drain(DRAIN_QUIET);
+            }
+            reader.into_transcript()
+        }
+        None => Vec::new(),
+    };
+    terminate(&mut processes);
+    let _ = renode.monitor.clear();
+
+    TestResult {
+        name: test.name.clone(),
+        outcome,
+        duration: start.elapsed(),
+        transcript,
+        waits,
+    }
+}
+
+pub(crate) fn run_steps(
+    config: &Config,
+    renode: &mut Renode,
+    test: &TestCase,
+    trace: Option<&Path>,
+    uart: &mut Option<UartReader>,
+    waits: &mut Vec<WaitTiming>,
+    processes: &mut Vec<Child>,
+) -> Result<(), String> {
+    let start = Instant::now();
+    let mut last_run: Option<(&str, Output)> = None;
+    for step in &test.steps {
+        match step {
+            Step::Execute(command) => {
+                renode
+                    .monitor
+                    .execute(command)
+                    .map_err(|err| err.to_string())?;
+            }
+            Step::CreateTerminalTester(peripheral) => {
+                let monitor = &mut renode.monitor;
+                monitor
+                    .create_uart_pty("term", &config.uart_path)
+                    .and_then(|()| monitor.connect_external(peripheral, "term"))
+                    .and_then(|()| UartReader::open(&config.uart_path, PTY_TIMEOUT))
+                    .map(|reader| *uart = Some(reader))
+                    .map_err(|err| err.to_string())?;
+            }
+            Step::StartEmulation => {
+                if let Some(path) = trace {
+                    // The trace file is closed when the machine is cleared
+                    renode
+                        .monitor
+                        .execute(&format!(
+                            "sysbus.cpu CreateExecutionTracing \"coverage\" {} PC",
+                            path_arg(path)
+                        ))
+                        .map_err(|err| format!("cannot trace: {}", err))?;
+                }
+                renode.monitor.start().map_err(|err| err.to_string())?
+            }
+            Step::WaitForLine { pattern, timeout } => {
+                let reader = uart
+                    .as_mut()
+                    .ok_or("`Wait For Line On Uart` before `Create Terminal Tester`")?;
+                let timeout = timeout.mul_f64(config.timeout_scale);
+                if reader.wait_for_line(pattern, timeout).is_none() {
+                    return Err(format!(
+                        "timed out after {:.1}s waiting for \"{}\"",
+                        timeout.as_secs_f64(),
+                        pattern
+                    ));
+                }
+                let host = start.elapsed();
+                let virtual_time = sample_virtual_time(&mut renode.monitor)
+                    .map_err(|err| format!("cannot sample virtual time: {}", err))?;
+                waits.push(WaitTiming {
+                    pattern: pattern.clone(),
+                    host,
+                    virtual_time,
+                });
+            }
+            Step::WriteLine(text) => {
+                uart.as_mut()
+                    .ok_or("`Write Line To Uart` before `Create Terminal Tester`")?
+                    .write_line(text)
+                    .map_err(|err| format!("cannot write to UART: {}", err))?;
+            }
+            Step::Sleep(duration) => thread::sleep(*duration),
+            Step::StartProcess { program, args } => {
+                let child = Command::new(locate(program))
+                    .args(args)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()
+                    .map_err(|err| format!("cannot start {}: {}", program, err))?;
+                processes.push(child);
+            }
+            Step::TerminateProcesses => terminate(processes),
+            Step::RunProcess { program, args } => {
+                let output = Command::new(locate(program))
+                    .args(args)
+                    .stdin(Stdio::null())
+                    .output()
+                    .map_err(|err| format!("cannot run {}: {}", program, err))?;
+                last_run = Some((program, output));
+            }
+            Step::ExpectExitCode(code) => {
+                let (program, output) = last_run
+                    .as_ref()
+                    .ok_or("`Should Be Equal As Integers` before `Run Process`")?;
+                if output.status.code() != Some(*code) {
+                    return Err(format!(
+                        "{} exited with {}, expected {}:\n{}",
+                        program,
+                        output.status,
+                        code,
+                        tail(&output.stdout, RUN_OUTPUT_LINES)
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The last `lines` lines of a program's output
+fn tail(output: &[u8], lines: usize) -> String {
+    let text = String::from_utf8_lossy(output);
+    let all: Vec<&str> = text.lines().collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+/// Host tools such as `uart-peer` are built next to the runner, so a bare
+/// program name is looked up there before `PATH`
+fn locate(program: &str) -> PathBuf {
+    let sibling = env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join(program)));
+    match sibling {
+        Some(path) if !program.contains('/') && path.is_file() => path,
+        _ => PathBuf::from(program),
+    }
+}
+
+/// Virtual time with the emulation paused, so it does not run on while the
+/// monitor answers; `None` if Renode does not report it
+fn sample_virtual_time(monitor: &mut Monitor) -> Result<Option<Duration>, renode_monitor::Error> {
+    monitor.pause()?;
+    let elapsed = monitor.elapsed_virtual_time().ok();
+    monitor.start()?;
+    Ok(elapsed)
+}
+
+/// Kill and reap the processes a test case started
+pub(crate) fn terminate(processes: &mut Vec<Child>) {
+    for mut child in processes.drain(..) {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(filter: &[&str]) -> Config {
+        Config {
+            renode: PathBuf::from("renode"),
+            port: 1234,
+            uart_path: PathBuf::from("/tmp/uart"),
+            timeout_scale: 1.0,
+            test_filter: filter.iter().map(|f| f.to_string()).collect(),
+            log_dir: None,
+            trace_dir: None,
+        }
+    }
+
+    fn test_case(name: &str) -> TestCase {
+        TestCase {
+            name: name.into(),
+            documentation: String::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    fn result(name: &str, outcome: Outcome) -> TestResult {
+        TestResult {
+            name: name.into(),
+            outcome,
+            duration: Duration::from_millis(20),
+            transcript: vec!["boot".into()],
+            waits: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn names_the_crate_of_a_suite() {
+        assert_eq!(
+            crate_name(Path::new("stm32f3-dma/tests/test-dma.robot")),
+            "stm32f3-dma"
+        );
+        assert_eq!(
+            crate_name(Path::new("/src/fw/stm32f3-adc/tests/test-adc.robot")),
+            "stm32f3-adc"
+        );
+        assert_eq!(crate_name(Path::new("test-dma.robot")), "unknown");
+    }
+
+    #[test]
+    fn turns_test_names_into_file_stems() {
+        assert_eq!(file_stem("Should Report Summary"), "should-report-summary");
+        assert_eq!(
+            file_stem("DMA: half/full  transfer"),
+            "dma-half-full-transfer"
+        );
+        assert_eq!(file_stem("  "), "");
+    }
+
+    #[test]
+    fn filters_test_cases_by_name() {
+        let test = test_case("Should Echo Input");
+        assert!(selected(&config(&[]), &test));
+        assert!(selected(&config(&["Echo"]), &test));
+        assert!(selected(&config(&["Nope", "Input"]), &test));
+        assert!(!selected(&config(&["echo"]), &test));
+    }
+
+    #[test]
+    fn quotes_the_end_of_output() {
+        assert_eq!(tail(b"a\nb\nc\n", 2), "b\nc");
+        assert_eq!(tail(b"a\nb", 5), "a\nb");
+        assert_eq!(tail(b"", 3), "");
+    }
+
+    #[test]
+    fn looks_up_paths_as_given() {
+        assert_eq!(locate("/bin/sh"), PathBuf::from("/bin/sh"));
+        assert_eq!(
+            locate("no-such-tool-here"),
+            PathBuf::from("no-such-tool-here")
+        );
+    }
+
+    #[test]
+    fn converts_to_a_report_suite() {
+        let suite = SuiteResult {
+            name: "test-dma".into(),
+            path: PathBuf::from("stm32f3-dma/tests/test-dma.robot"),
+            tests: vec![
+                result("Passes", Outcome::Passed),
+                result("Fails", Outcome::Failed("timed out".into())),
+            ],
+            duration: Duration::from_secs(1),
+        };
+        assert_eq!((suite.passed(), suite.failed()), (1, 1));
+
+        let report = test_report::Suite::from(&suite);
+        assert_eq!(report.name, "dma");
+        assert_eq!(report.source, "renode-runner");
+        assert_eq!(report.duration, Duration::from_secs(1));
+        assert_eq!(report.tests[0].status, test_report::Status::Passed);
+        // Only failures keep their transcript
+        assert!(report.tests[0].transcript.is_empty());
+        assert_eq!(
+            report.tests[1].status,
+            test_report::Status::Failed("timed out".into())
+        );
+        assert_eq!(report.tests[1].transcript, ["boot"]);
+    }
+}
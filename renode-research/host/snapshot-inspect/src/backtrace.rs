@@ -0,0 +1,226 @@
+//! Backtraces from a stopped core without unwind tables
+//!
+//! The release builds carry no unwind information, so the stack is
+//! scanned instead: from SP up to the top of the stack, every word that is
+//! a return address (odd, inside a function, right after a `BL`/`BLX`)
+//! becomes a frame, and every eight-word exception frame (stacked PC in
+//! code, Thumb bit set in the stacked xPSR) marks where an interrupt or
+//! fault came in. Return addresses left behind by calls that already
+//! returned can show up as extra frames; the innermost ones come from PC
+//! and LR and are exact.
+
+use crate::firmware::Firmware;
+
+/// Core registers of a Cortex-M4
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CoreRegisters {
+    /// R0–R15 (R13 = SP, R14 = LR, R15 = PC)
+    pub r: [u32; 16],
+    pub xpsr: u32,
+}
+
+impl CoreRegisters {
+    pub fn sp(&self) -> u32 {
+        self.r[13]
+    }
+
+    pub fn lr(&self) -> u32 {
+        self.r[14]
+    }
+
+    pub fn pc(&self) -> u32 {
+        self.r[15]
+    }
+
+    /// Active exception number from IPSR, 0 in thread mode
+    pub fn exception(&self) -> u32 {
+        self.xpsr & 0x1FF
+    }
+}
+
+/// Register names in [`CoreRegisters::r`] order
+pub const REGISTER_NAMES: [&str; 16] = [
+    "r0", "r1", "r2", "r3", "r4", "r5", "r6", "r7", "r8", "r9", "r10", "r11", "r12", "sp", "lr",
+    "pc",
+];
+
+/// How a frame was found
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// The current PC
+    Pc,
+    /// The link register
+    Lr,
+    /// PC stacked by exception entry (the interrupted instruction)
+    Exception,
+    /// Return address found on the stack at this address
+    Stack(u64),
+}
+
+/// One frame of a backtrace
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// Code address with the Thumb bit cleared
+    pub address: u64,
+    pub function: Option<String>,
+    pub origin: Origin,
+}
+
+/// `LR` values that mean "return from exception"
+pub fn is_exc_return(value: u32) -> bool {
+    value & 0xFFFF_FF00 == 0xFFFF_FF00
+}
+
+/// Unwind from `registers`, with `stack` holding memory from SP upwards
+pub fn unwind(firmware: &Firmware, registers: &CoreRegisters, stack: &[u8]) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    let mut push = |address: u64, origin: Origin| {
+        let address = address & !1;
+        if frames.last().is_some_and(|f: &Frame| f.address == address) {
+            return;
+        }
+        frames.push(Frame {
+            address,
+            function: firmware.function(address).map(str::to_string),
+            origin,
+        });
+    };
+
+    push(registers.pc().into(), Origin::Pc);
+    let lr = registers.lr();
+    if !is_exc_return(lr) && firmware.is_return_address(lr.into()) {
+        push(lr.into(), Origin::Lr);
+    }
+
+    let sp = u64::from(registers.sp());
+    let words: Vec<u32> = stack
+        .chunks_exact(4)
+        .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+        .collect();
+    let mut i = 0;
+    while i < words.len() {
+        if let Some(frame) = words
+            .get(i..i + 8)
+            .filter(|f| is_exception_frame(firmware, f))
+        {
+            push(frame[6].into(), Origin::Exception);
+            if firmware.is_return_address(frame[5].into()) {
+                push(frame[5].into(), Origin::Lr);
+            }
+            i += 8;
+            continue;
+        }
+        if firmware.is_return_address(words[i].into()) {
+            push(words[i].into(), Origin::Stack(sp + 4 * i as u64));
+        }
+        i += 1;
+    }
+    frames
+}
+
+/// R0–R3, R12, LR, PC, xPSR as pushed on exception entry
+fn is_exception_frame(firmware: &Firmware, frame: &[u32]) -> bool {
+    let (lr, pc, xpsr) = (frame[5], frame[6], frame[7]);
+    xpsr & 0x0100_0000 != 0
+        && xpsr & 0x00F0_0000 == 0
+        && pc & 1 == 0
+        && firmware.is_code(pc.into())
+        && (firmware.is_code(lr.into()) || lr == 0xFFFF_FFFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::firmware::tests::firmware;
+
+    const SP: u32 = 0x2000_7F00;
+
+    fn registers(pc: u32, lr: u32) -> CoreRegisters {
+        let mut registers = CoreRegisters::default();
+        registers.r[13] = SP;
+        registers.r[14] = lr;
+        registers.r[15] = pc;
+        registers
+    }
+
+    fn stack(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    fn frames(frames: &[Frame]) -> Vec<(u64, &str, Origin)> {
+        frames
+            .iter()
+            .map(|f| (f.address, f.function.as_deref().unwrap_or("?"), f.origin))
+            .collect()
+    }
+
+    #[test]
+    fn register_accessors() {
+        let mut registers = registers(0x12, 0x07);
+        registers.xpsr = 0x0100_0013;
+        assert_eq!(
+            (registers.sp(), registers.lr(), registers.pc()),
+            (SP, 0x07, 0x12)
+        );
+        assert_eq!(registers.exception(), 19);
+        assert!(is_exc_return(0xFFFF_FFF9));
+        assert!(is_exc_return(0xFFFF_FFED));
+        assert!(!is_exc_return(0x0800_0101));
+    }
+
+    #[test]
+    fn pc_lr_stack_and_exception_frames() {
+        let stack = stack(&[
+            0x0000_0000,
+            0x0000_0009,
+            // r0-r3, r12, lr, pc, xpsr
+            1,
+            2,
+            3,
+            4,
+            12,
+            0x0000_0007,
+            0x0000_0022,
+            0x0100_0000,
+            0x0000_0007,
+        ]);
+        let unwound = unwind(&firmware(), &registers(0x13, 0x07), &stack);
+        assert_eq!(
+            frames(&unwound),
+            [
+                (0x12, "helper", Origin::Pc),
+                (0x06, "main", Origin::Lr),
+                (0x08, "main", Origin::Stack(u64::from(SP) + 4)),
+                (0x22, "isr", Origin::Exception),
+                (0x06, "main", Origin::Lr),
+            ]
+        );
+    }
+
+    #[test]
+    fn exception_return_lr_is_skipped() {
+        let unwound = unwind(&firmware(), &registers(0x22, 0xFFFF_FFF9), &[]);
+        assert_eq!(frames(&unwound), [(0x22, "isr", Origin::Pc)]);
+        // A PC outside every function still gives a frame
+        let unwound = unwind(&firmware(), &registers(0x100, 0), &[0; 3]);
+        assert_eq!(frames(&unwound), [(0x100, "?", Origin::Pc)]);
+    }
+
+    #[test]
+    fn exception_frames_need_a_thumb_xpsr() {
+        let firmware = firmware();
+        let frame = [0, 0, 0, 0, 0, 0x07, 0x22, 0x0100_0000];
+        assert!(is_exception_frame(&firmware, &frame));
+        let mut bad = frame;
+        bad[7] = 0;
+        assert!(!is_exception_frame(&firmware, &bad));
+        bad = frame;
+        bad[6] = 0x23;
+        assert!(!is_exception_frame(&firmware, &bad));
+        bad = frame;
+        bad[5] = 0x2000_0000;
+        assert!(!is_exception_frame(&firmware, &bad));
+        bad[5] = 0xFFFF_FFFF;
+        assert!(is_exception_frame(&firmware, &bad));
+    }
+}
@@ -0,0 +1,168 @@
+//! What the inspector needs from the firmware ELF
+
+use std::fs;
+use std::path::Path;
+
+use firmware_elf::{Code, Symbol, SymbolTable};
+use object::{Object, ObjectSymbol, SymbolKind};
+
+use crate::Error;
+
+/// Start of SRAM; statics below it live in flash and never change
+const RAM_START: u64 = 0x2000_0000;
+
+/// Top of the stack when the ELF has no `_stack_start` (end of SRAM)
+const DEFAULT_STACK_TOP: u64 = 0x2000_A000;
+
+/// Symbols, code and memory layout of one firmware image
+#[derive(Clone, Debug, Default)]
+pub struct Firmware {
+    pub symbols: SymbolTable,
+    code: Code,
+    /// Initial stack pointer (`_stack_start` from cortex-m-rt's `link.x`)
+    pub stack_top: u64,
+}
+
+impl Firmware {
+    pub fn from_elf(path: impl AsRef<Path>) -> Result<Firmware, Error> {
+        Self::from_elf_data(&fs::read(path)?)
+    }
+
+    pub fn from_elf_data(data: &[u8]) -> Result<Firmware, Error> {
+        let file = object::File::parse(data)?;
+        let code = Code::from_file(&file)?;
+        let stack_top = file
+            .symbols()
+            .find(|s| s.name() == Ok("_stack_start"))
+            .map_or(DEFAULT_STACK_TOP, |s| s.address());
+
+        Ok(Firmware {
+            symbols: SymbolTable::from_elf_data(data)?,
+            code,
+            stack_top,
+        })
+    }
+
+    /// Name of the function containing `address`
+    pub fn function(&self, address: u64) -> Option<&str> {
+        self.symbols
+            .containing(address)
+            .filter(|symbol| symbol.kind == SymbolKind::Text)
+            .map(|symbol| symbol.path.as_str())
+    }
+
+    /// Statics in RAM, by address
+    pub fn statics(&self) -> Vec<&Symbol> {
+        let mut statics: Vec<&Symbol> = self
+            .symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Data && s.address >= RAM_START && s.size > 0)
+            .collect();
+        statics.sort_by_key(|s| s.address);
+        statics
+    }
+
+    /// Whether `value` looks like a return address pushed by a call: a
+    /// Thumb address inside a function, right after a `BL` or `BLX`
+    pub fn is_return_address(&self, value: u64) -> bool {
+        if value & 1 == 0 || self.function(value & !1).is_none() {
+            return false;
+        }
+        let next = value & !1;
+        let bl = next
+            .checked_sub(4)
+            .and_then(|at| Some((self.code.halfword(at)?, self.code.halfword(at + 2)?)))
+            .is_some_and(|(hw1, hw2)| hw1 >> 11 == 0b11110 && hw2 & 0xD000 == 0xD000);
+        let blx = next
+            .checked_sub(2)
+            .and_then(|at| self.code.halfword(at))
+            .is_some_and(|hw| hw & 0xFF87 == 0x4780);
+        bl || blx
+    }
+
+    /// Whether `value` is an address inside a function (bit 0 ignored)
+    pub fn is_code(&self, value: u64) -> bool {
+        self.function(value & !1).is_some()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use firmware_elf::test_support::{thumb, ElfBuilder};
+    use object::SectionKind;
+
+    /// `.text` at address 0 with `main` (0x00), `helper` (0x10) and `isr`
+    /// (0x20); `main` calls `helper` with a `BL` returning to 0x06 and
+    /// something else with a `BLX r3` returning to 0x08
+    ///
+    /// `DST_BUFFER` and `STATE` are statics in RAM, `TABLE` is in flash and
+    /// the stack starts at 0x20008000.
+    pub(crate) fn elf() -> Vec<u8> {
+        let mut code: Vec<u16> = vec![0xBF00; 0x20];
+        code[..4].copy_from_slice(&[0xB580, 0xF000, 0xF805, 0x4798]);
+        code[8] = 0xB500;
+        let mut elf = ElfBuilder::new();
+        let text = elf.section(".text", SectionKind::Text, &thumb(&code));
+        let bss = elf.bss(".bss", 0x40);
+        let symbols = [
+            ("main", 0x01, 0x10, SymbolKind::Text, text),
+            ("helper", 0x11, 0x10, SymbolKind::Text, text),
+            ("isr", 0x21, 0x08, SymbolKind::Text, text),
+            ("STATE", 0x2000_0120, 0x04, SymbolKind::Data, bss),
+            ("DST_BUFFER", 0x2000_0100, 0x20, SymbolKind::Data, bss),
+            ("MARKER", 0x2000_0130, 0, SymbolKind::Data, bss),
+            ("TABLE", 0x30, 0x10, SymbolKind::Data, text),
+        ];
+        for (name, value, size, kind, section) in symbols {
+            elf.symbol(name, value, size, kind, section);
+        }
+        elf.absolute("_stack_start", 0x2000_8000);
+        elf.build()
+    }
+
+    pub(crate) fn firmware() -> Firmware {
+        Firmware::from_elf_data(&elf()).unwrap()
+    }
+
+    #[test]
+    fn functions_and_stack_top() {
+        let firmware = firmware();
+        assert_eq!(firmware.stack_top, 0x2000_8000);
+        assert_eq!(firmware.function(0x00), Some("main"));
+        assert_eq!(firmware.function(0x1F), Some("helper"));
+        assert_eq!(firmware.function(0x30), None);
+        assert!(firmware.is_code(0x23));
+        assert!(!firmware.is_code(0x2000_0100));
+    }
+
+    #[test]
+    fn statics_in_ram_by_address() {
+        let firmware = firmware();
+        let statics: Vec<&str> = firmware.statics().iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(statics, ["DST_BUFFER", "STATE"]);
+    }
+
+    #[test]
+    fn return_addresses_follow_calls() {
+        let firmware = firmware();
+        // After the BL and after the BLX
+        assert!(firmware.is_return_address(0x07));
+        assert!(firmware.is_return_address(0x09));
+        // Even, at a function start, after other code, outside functions
+        assert!(!firmware.is_return_address(0x06));
+        assert!(!firmware.is_return_address(0x01));
+        assert!(!firmware.is_return_address(0x13));
+        assert!(!firmware.is_return_address(0x41));
+    }
+
+    #[test]
+    fn defaults_without_stack_symbol() {
+        let mut elf = ElfBuilder::new();
+        elf.section(".text", SectionKind::Text, &[0; 4]);
+        let firmware = Firmware::from_elf_data(&elf.build()).unwrap();
+        assert_eq!(firmware.stack_top, DEFAULT_STACK_TOP);
+        assert!(firmware.statics().is_empty());
+        assert!(Firmware::from_elf_data(b"not an elf").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Look inside Renode snapshots of failed tests
+//!
+//! Robot's `Create Snapshot On Failure` leaves
+//! `<crate>/snapshots/<suite>.<Test_Name>.fail0.save`. This crate loads one
+//! into a headless Renode through [`renode_monitor`], reads the core
+//! registers, the peripheral registers relevant to the crate
+//! ([`peripherals`]) and the firmware's statics, and unwinds the stack
+//! against the ELF ([`backtrace`]):
+//!
+//! ```no_run
+//! use renode_monitor::Renode;
+//! use snapshot_inspect::{peripherals, Firmware, Snapshot};
+//!
+//! let firmware = Firmware::from_elf("target/thumbv7em-none-eabihf/release/stm32f3-dma")?;
+//! let mut renode = Renode::launch("renode".as_ref(), 1234, None)?;
+//! let snapshot = Snapshot::load(
+//!     &mut renode.monitor,
+//!     "stm32f3-dma/snapshots/test-dma.Should_Complete_Second_Transfer.fail0.save".as_ref(),
+//!     &firmware,
+//!     &peripherals::for_crate("stm32f3-dma"),
+//!     &firmware.statics(),
+//! )?;
+//! print!("{}", snapshot);
+//! # Ok::<(), snapshot_inspect::Error>(())
+//! ```
+//!
+//! [`diff`] compares two snapshots of the same firmware, e.g. one saved
+//! from a passing run with `Save @file` and the failure snapshot.
+
+use std::fmt;
+
+pub mod backtrace;
+pub mod firmware;
+pub mod peripherals;
+pub mod snapshot;
+
+pub use firmware::Firmware;
+pub use snapshot::{diff, Snapshot};
+
+/// Errors loading or reading a snapshot
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read
+    Io(std::io::Error),
+    /// The firmware is not a parseable ELF
+    Elf(object::Error),
+    /// Renode could not be started or a monitor command failed
+    Renode(renode_monitor::Error),
+    /// A crate, peripheral or symbol that could not be resolved
+    Lookup(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "read failed: {}", err),
+            Error::Elf(err) => write!(f, "invalid ELF: {}", err),
+            Error::Renode(err) => write!(f, "renode: {}", err),
+            Error::Lookup(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<object::Error> for Error {
+    fn from(err: object::Error) -> Self {
+        Error::Elf(err)
+    }
+}
+
+impl From<firmware_elf::Error> for Error {
+    fn from(err: firmware_elf::Error) -> Self {
+        match err {
+            firmware_elf::Error::Io(err) => Error::Io(err),
+            firmware_elf::Error::Elf(err) => Error::Elf(err),
+            other => Error::Io(std::io::Error::other(other.to_string())),
+        }
+    }
+}
+
+impl From<renode_monitor::Error> for Error {
+    fn from(err: renode_monitor::Error) -> Self {
+        Error::Renode(err)
+    }
+}
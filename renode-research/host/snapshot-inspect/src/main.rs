@@ -0,0 +1,145 @@
+//! Print or compare Renode failure snapshots
+//!
+//! Usage: `snapshot-inspect [OPTIONS] <SNAPSHOT> [--diff OTHER]`
+//!
+//! The firmware crate is taken from the snapshot's location
+//! (`stm32f3-<x>/snapshots/*.save`) unless `--crate` is given, and its
+//! release ELF is used to name functions and find statics.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use firmware_elf::Symbol;
+use renode_monitor::Renode;
+use snapshot_inspect::{diff, peripherals, Error, Firmware, Snapshot};
+
+/// Firmware target triple
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+#[derive(Parser)]
+#[command(about = "Print registers, backtrace, peripherals and statics from a Renode snapshot")]
+struct Args {
+    /// Snapshot file (`Save @file`, or Robot's `*.fail0.save`)
+    snapshot: PathBuf,
+
+    /// Compare with this snapshot instead of printing
+    #[arg(long, value_name = "OTHER")]
+    diff: Option<PathBuf>,
+
+    /// Firmware crate (default: from the snapshot's directory)
+    #[arg(long = "crate", value_name = "CRATE")]
+    krate: Option<String>,
+
+    /// Firmware ELF (default: the crate's release build)
+    #[arg(long)]
+    elf: Option<PathBuf>,
+
+    /// Also show this peripheral's registers, e.g. `TIM2` (repeatable)
+    #[arg(long = "peripheral", value_name = "NAME")]
+    peripherals: Vec<String>,
+
+    /// Also show this static, e.g. one in flash (repeatable)
+    #[arg(long = "symbol", value_name = "NAME")]
+    symbols: Vec<String>,
+
+    /// Firmware workspace root
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))]
+    root: PathBuf,
+
+    /// Renode executable
+    #[arg(long, env = "RENODE", default_value = "renode")]
+    renode: PathBuf,
+
+    /// Renode monitor port
+    #[arg(long, default_value_t = 1234)]
+    port: u16,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("snapshot-inspect: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Error> {
+    let root = args.root.canonicalize().unwrap_or(args.root.clone());
+    let name = match &args.krate {
+        Some(name) if name.starts_with("stm32f3-") => name.clone(),
+        Some(name) => format!("stm32f3-{}", name),
+        None => crate_of(&args.snapshot).ok_or_else(|| {
+            Error::Lookup(format!(
+                "{}: not under stm32f3-<x>/snapshots, give --crate",
+                args.snapshot.display()
+            ))
+        })?,
+    };
+    let elf = args
+        .elf
+        .clone()
+        .unwrap_or_else(|| root.join("target").join(TARGET).join("release").join(&name));
+    let firmware = Firmware::from_elf(&elf).map_err(|err| match err {
+        Error::Io(err) => Error::Io(std::io::Error::new(
+            err.kind(),
+            format!("{}: {}", elf.display(), err),
+        )),
+        other => other,
+    })?;
+
+    let mut blocks = peripherals::for_crate(&name);
+    for wanted in &args.peripherals {
+        let block = peripherals::by_name(wanted)
+            .ok_or_else(|| Error::Lookup(format!("unknown peripheral {}", wanted)))?;
+        if !blocks.contains(&block) {
+            blocks.push(block);
+        }
+    }
+
+    let mut statics: Vec<&Symbol> = firmware.statics();
+    for wanted in &args.symbols {
+        let symbol = firmware
+            .symbols
+            .lookup(wanted)
+            .ok_or_else(|| Error::Lookup(format!("no symbol `{}` in {}", wanted, elf.display())))?;
+        if !statics.contains(&symbol) {
+            statics.push(symbol);
+        }
+    }
+
+    let mut renode = Renode::launch(&args.renode, args.port, None)?;
+    let first = Snapshot::load(
+        &mut renode.monitor,
+        &args.snapshot,
+        &firmware,
+        &blocks,
+        &statics,
+    )?;
+    match &args.diff {
+        None => print!("{}", first),
+        Some(other) => {
+            let second = Snapshot::load(&mut renode.monitor, other, &firmware, &blocks, &statics)?;
+            match diff(&first, &second) {
+                Some(text) => print!("{}", text),
+                None => println!("no differences"),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `stm32f3-<x>` from `.../stm32f3-<x>/snapshots/<file>`
+fn crate_of(snapshot: &Path) -> Option<String> {
+    let path = snapshot.canonicalize().ok()?;
+    let dir = path.parent()?;
+    if dir.file_name()? != "snapshots" {
+        return None;
+    }
+    let name = dir.parent()?.file_name()?.to_str()?;
+    name.starts_with("stm32f3-").then(|| name.to_string())
+}
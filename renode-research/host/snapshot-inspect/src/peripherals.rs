@@ -0,0 +1,282 @@
+//! Peripheral registers worth looking at after a failure
+//!
+//! Every snapshot shows the fault status ([`SCB`]), clock enables
+//! ([`RCC`]) and the console UART ([`USART1`]); each crate adds the
+//! peripherals its firmware exercises ([`for_crate`]). Offsets are from
+//! RM0316 (STM32F303).
+
+/// A peripheral's base address and the registers read from it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub name: &'static str,
+    pub base: u64,
+    /// `(register, offset from base)`
+    pub registers: &'static [(&'static str, u64)],
+}
+
+pub const SCB: Block = Block {
+    name: "SCB",
+    base: 0xE000_ED00,
+    registers: &[
+        ("ICSR", 0x04),
+        ("VTOR", 0x08),
+        ("CFSR", 0x28),
+        ("HFSR", 0x2C),
+        ("MMFAR", 0x34),
+        ("BFAR", 0x38),
+    ],
+};
+
+pub const NVIC: Block = Block {
+    name: "NVIC",
+    base: 0xE000_E100,
+    registers: &[
+        ("ISER0", 0x000),
+        ("ISER1", 0x004),
+        ("ISPR0", 0x100),
+        ("ISPR1", 0x104),
+    ],
+};
+
+pub const RCC: Block = Block {
+    name: "RCC",
+    base: 0x4002_1000,
+    registers: &[
+        ("CR", 0x00),
+        ("CFGR", 0x04),
+        ("AHBENR", 0x14),
+        ("APB2ENR", 0x18),
+        ("APB1ENR", 0x1C),
+        ("CSR", 0x24),
+    ],
+};
+
+const USART: &[(&str, u64)] = &[
+    ("CR1", 0x00),
+    ("CR2", 0x04),
+    ("CR3", 0x08),
+    ("BRR", 0x0C),
+    ("ISR", 0x1C),
+];
+
+pub const USART1: Block = Block {
+    name: "USART1",
+    base: 0x4001_3800,
+    registers: USART,
+};
+
+pub const USART2: Block = Block {
+    name: "USART2",
+    base: 0x4000_4400,
+    registers: USART,
+};
+
+pub const ADC1: Block = Block {
+    name: "ADC1",
+    base: 0x5000_0000,
+    registers: &[
+        ("ISR", 0x00),
+        ("IER", 0x04),
+        ("CR", 0x08),
+        ("CFGR", 0x0C),
+        ("SMPR1", 0x14),
+        ("SQR1", 0x30),
+        ("DR", 0x40),
+    ],
+};
+
+pub const DMA1: Block = Block {
+    name: "DMA1",
+    base: 0x4002_0000,
+    registers: &[
+        ("ISR", 0x00),
+        ("CCR1", 0x08),
+        ("CNDTR1", 0x0C),
+        ("CPAR1", 0x10),
+        ("CMAR1", 0x14),
+        ("CCR2", 0x1C),
+        ("CNDTR2", 0x20),
+        ("CPAR2", 0x24),
+        ("CMAR2", 0x28),
+    ],
+};
+
+const TIM: &[(&str, u64)] = &[
+    ("CR1", 0x00),
+    ("DIER", 0x0C),
+    ("SR", 0x10),
+    ("CNT", 0x24),
+    ("PSC", 0x28),
+    ("ARR", 0x2C),
+];
+
+pub const TIM2: Block = Block {
+    name: "TIM2",
+    base: 0x4000_0000,
+    registers: TIM,
+};
+
+pub const TIM3: Block = Block {
+    name: "TIM3",
+    base: 0x4000_0400,
+    registers: TIM,
+};
+
+pub const TIM4: Block = Block {
+    name: "TIM4",
+    base: 0x4000_0800,
+    registers: TIM,
+};
+
+const GPIO: &[(&str, u64)] = &[
+    ("MODER", 0x00),
+    ("PUPDR", 0x0C),
+    ("IDR", 0x10),
+    ("ODR", 0x14),
+];
+
+pub const GPIOA: Block = Block {
+    name: "GPIOA",
+    base: 0x4800_0000,
+    registers: GPIO,
+};
+
+pub const GPIOE: Block = Block {
+    name: "GPIOE",
+    base: 0x4800_1000,
+    registers: GPIO,
+};
+
+pub const EXTI: Block = Block {
+    name: "EXTI",
+    base: 0x4001_0400,
+    registers: &[
+        ("IMR1", 0x00),
+        ("RTSR1", 0x08),
+        ("FTSR1", 0x0C),
+        ("PR1", 0x14),
+    ],
+};
+
+pub const RTC: Block = Block {
+    name: "RTC",
+    base: 0x4000_2800,
+    registers: &[
+        ("TR", 0x00),
+        ("DR", 0x04),
+        ("CR", 0x08),
+        ("ISR", 0x0C),
+        ("PRER", 0x10),
+    ],
+};
+
+pub const IWDG: Block = Block {
+    name: "IWDG",
+    base: 0x4000_3000,
+    registers: &[("PR", 0x04), ("RLR", 0x08), ("SR", 0x0C)],
+};
+
+pub const SPI1: Block = Block {
+    name: "SPI1",
+    base: 0x4001_3000,
+    registers: &[("CR1", 0x00), ("CR2", 0x04), ("SR", 0x08)],
+};
+
+pub const I2C1: Block = Block {
+    name: "I2C1",
+    base: 0x4000_5400,
+    registers: &[
+        ("CR1", 0x00),
+        ("CR2", 0x04),
+        ("OAR1", 0x08),
+        ("TIMINGR", 0x10),
+        ("ISR", 0x18),
+    ],
+};
+
+pub const DAC1: Block = Block {
+    name: "DAC1",
+    base: 0x4000_7400,
+    registers: &[("CR", 0x00), ("DHR12R1", 0x08), ("DOR1", 0x2C)],
+};
+
+pub const CRC: Block = Block {
+    name: "CRC",
+    base: 0x4002_3000,
+    registers: &[("DR", 0x00), ("CR", 0x08), ("INIT", 0x10), ("POL", 0x14)],
+};
+
+/// Every block, for [`by_name`]
+pub const ALL: &[Block] = &[
+    SCB, NVIC, RCC, USART1, USART2, ADC1, DMA1, TIM2, TIM3, TIM4, GPIOA, GPIOE, EXTI, RTC, IWDG,
+    SPI1, I2C1, DAC1, CRC,
+];
+
+/// Blocks shown for a firmware crate (`stm32f3-adc` or `adc`)
+pub fn for_crate(name: &str) -> Vec<Block> {
+    let mut blocks = vec![SCB, RCC, USART1];
+    blocks.extend_from_slice(match name.strip_prefix("stm32f3-").unwrap_or(name) {
+        "adc" => &[ADC1][..],
+        "dma" => &[DMA1],
+        "timer" => &[TIM2, TIM3, TIM4, NVIC],
+        "gpio" => &[GPIOA, GPIOE],
+        "exti" => &[EXTI, GPIOA, NVIC],
+        "rtc" => &[RTC],
+        "iwdg" => &[IWDG],
+        "spi" => &[SPI1],
+        "i2c" => &[I2C1],
+        "dac" => &[DAC1],
+        "crc" => &[CRC],
+        "usart2" => &[USART2],
+        // Every suite in one image
+        "selftest" => return ALL.to_vec(),
+        _ => &[],
+    });
+    blocks
+}
+
+/// A block by name, ignoring case (`dma1`, `TIM3`)
+pub fn by_name(name: &str) -> Option<Block> {
+    ALL.iter()
+        .find(|block| block.name.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_crate_shows_the_basics() {
+        for name in ["stm32f3-uart", "dma", "unknown"] {
+            let blocks = for_crate(name);
+            assert_eq!(blocks[..3], [SCB, RCC, USART1], "{}", name);
+        }
+        assert_eq!(for_crate("stm32f3-dma")[3..], [DMA1]);
+        assert_eq!(for_crate("exti")[3..], [EXTI, GPIOA, NVIC]);
+        assert_eq!(for_crate("stm32f3-uart").len(), 3);
+        assert_eq!(for_crate("selftest"), ALL);
+    }
+
+    #[test]
+    fn lookup_ignores_case() {
+        assert_eq!(by_name("dma1"), Some(DMA1));
+        assert_eq!(by_name("Tim3"), Some(TIM3));
+        assert_eq!(by_name("TIM5"), None);
+    }
+
+    #[test]
+    fn registers_are_word_aligned_and_distinct() {
+        for block in ALL {
+            let mut offsets: Vec<u64> = block.registers.iter().map(|&(_, o)| o).collect();
+            assert!(offsets.iter().all(|o| o % 4 == 0), "{}", block.name);
+            offsets.sort();
+            offsets.dedup();
+            assert_eq!(offsets.len(), block.registers.len(), "{}", block.name);
+        }
+        let mut names: Vec<&str> = ALL.iter().map(|b| b.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), ALL.len());
+    }
+}
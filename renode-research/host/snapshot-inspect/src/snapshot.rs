@@ -0,0 +1,397 @@
+//! Reading a snapshot through the monitor, printing and comparing it
+
+use std::fmt::{self, Write};
+use std::path::{Path, PathBuf};
+
+use firmware_elf::Symbol;
+use renode_monitor::Monitor;
+
+use crate::backtrace::{self, CoreRegisters, Frame, Origin, REGISTER_NAMES};
+use crate::firmware::Firmware;
+use crate::peripherals::Block;
+use crate::Error;
+
+/// `GetRegisterUnsafe` index of xPSR on Renode's Cortex-M
+const XPSR: u32 = 25;
+
+/// Most stack read for the backtrace, in bytes
+const MAX_STACK: u64 = 4096;
+
+/// Most bytes shown of one static
+pub const MAX_STATIC: u64 = 256;
+
+/// A peripheral register as found in the snapshot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegisterValue {
+    pub block: &'static str,
+    pub name: &'static str,
+    pub address: u64,
+    pub value: u32,
+}
+
+/// The contents of a static as found in the snapshot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaticValue {
+    pub path: String,
+    pub address: u64,
+    pub size: u64,
+    /// The first [`MAX_STATIC`] bytes
+    pub bytes: Vec<u8>,
+}
+
+/// State of the machine in one snapshot file
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub registers: CoreRegisters,
+    pub backtrace: Vec<Frame>,
+    pub peripherals: Vec<RegisterValue>,
+    pub statics: Vec<StaticValue>,
+}
+
+impl Snapshot {
+    /// Load `path` into Renode (replacing its emulation) and read it
+    pub fn load(
+        monitor: &mut Monitor,
+        path: &Path,
+        firmware: &Firmware,
+        blocks: &[Block],
+        statics: &[&Symbol],
+    ) -> Result<Snapshot, Error> {
+        let path = path.canonicalize()?;
+        monitor.load_snapshot(&path)?;
+        // Robot's `mach create` names the machine `machine-0`; select it by
+        // index so any name works
+        monitor.execute("mach set 0")?;
+
+        let mut registers = CoreRegisters::default();
+        for (index, value) in registers.r.iter_mut().enumerate() {
+            *value = monitor.cpu_register(index as u32)? as u32;
+        }
+        registers.xpsr = monitor.cpu_register(XPSR)? as u32;
+
+        let sp = u64::from(registers.sp());
+        let stack = if sp < firmware.stack_top {
+            monitor.read_bytes(sp, (firmware.stack_top - sp).min(MAX_STACK) as usize)?
+        } else {
+            Vec::new()
+        };
+        let backtrace = backtrace::unwind(firmware, &registers, &stack);
+
+        let mut peripherals = Vec::new();
+        for block in blocks {
+            for &(name, offset) in block.registers {
+                let address = block.base + offset;
+                peripherals.push(RegisterValue {
+                    block: block.name,
+                    name,
+                    address,
+                    value: monitor.read_u32(address)?,
+                });
+            }
+        }
+
+        let statics = statics
+            .iter()
+            .map(|symbol| {
+                Ok(StaticValue {
+                    path: symbol.path.clone(),
+                    address: symbol.address,
+                    size: symbol.size,
+                    bytes: monitor
+                        .read_bytes(symbol.address, symbol.size.min(MAX_STATIC) as usize)?,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Snapshot {
+            path,
+            registers,
+            backtrace,
+            peripherals,
+            statics,
+        })
+    }
+
+    fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "=== {} ===", self.name())?;
+
+        writeln!(f, "\nRegisters ({}):", mode(&self.registers))?;
+        for row in REGISTER_NAMES.chunks(4).zip(self.registers.r.chunks(4)) {
+            let cells: Vec<String> = row
+                .0
+                .iter()
+                .zip(row.1)
+                .map(|(name, value)| format!("{:<4} 0x{:08X}", name, value))
+                .collect();
+            writeln!(f, "  {}", cells.join("  "))?;
+        }
+        writeln!(f, "  xpsr 0x{:08X}", self.registers.xpsr)?;
+
+        writeln!(f, "\nBacktrace:")?;
+        for (depth, frame) in self.backtrace.iter().enumerate() {
+            writeln!(f, "  #{:<2} {}", depth, frame_line(frame))?;
+        }
+
+        writeln!(f, "\nPeripherals:")?;
+        for register in &self.peripherals {
+            writeln!(
+                f,
+                "  {:<7} {:<8} 0x{:08X} = 0x{:08X}",
+                register.block, register.name, register.address, register.value
+            )?;
+        }
+
+        writeln!(f, "\nStatics:")?;
+        for value in &self.statics {
+            writeln!(
+                f,
+                "  {} @ 0x{:08X} ({} bytes)",
+                value.path, value.address, value.size
+            )?;
+            for (row, chunk) in value.bytes.chunks(16).enumerate() {
+                writeln!(
+                    f,
+                    "    {:08X}: {}",
+                    value.address + row as u64 * 16,
+                    hex(chunk)
+                )?;
+            }
+            if value.size > value.bytes.len() as u64 {
+                writeln!(f, "    ...")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What differs between two snapshots of the same firmware, or `None`
+pub fn diff(a: &Snapshot, b: &Snapshot) -> Option<String> {
+    let mut out = String::new();
+
+    let mut registers = String::new();
+    let named = REGISTER_NAMES
+        .iter()
+        .zip(a.registers.r.iter().zip(&b.registers.r))
+        .chain(std::iter::once((
+            &"xpsr",
+            (&a.registers.xpsr, &b.registers.xpsr),
+        )));
+    for (name, (x, y)) in named {
+        if x != y {
+            let _ = writeln!(registers, "  {:<5} 0x{:08X} | 0x{:08X}", name, x, y);
+        }
+    }
+    section(&mut out, "Registers", &registers);
+
+    let functions = |s: &Snapshot| -> Vec<String> {
+        s.backtrace
+            .iter()
+            .map(|frame| frame.function.clone().unwrap_or_else(|| "?".into()))
+            .collect()
+    };
+    if functions(a) != functions(b) {
+        let mut backtraces = String::new();
+        for (sign, snapshot) in [('-', a), ('+', b)] {
+            for frame in &snapshot.backtrace {
+                let _ = writeln!(backtraces, "  {} {}", sign, frame_line(frame));
+            }
+        }
+        section(&mut out, "Backtrace", &backtraces);
+    }
+
+    let mut peripherals = String::new();
+    for (x, y) in a.peripherals.iter().zip(&b.peripherals) {
+        if x.address == y.address && x.value != y.value {
+            let _ = writeln!(
+                peripherals,
+                "  {:<7} {:<8} 0x{:08X} | 0x{:08X}",
+                x.block, x.name, x.value, y.value
+            );
+        }
+    }
+    section(&mut out, "Peripherals", &peripherals);
+
+    let mut statics = String::new();
+    for (x, y) in a.statics.iter().zip(&b.statics) {
+        if x.bytes == y.bytes {
+            continue;
+        }
+        let _ = writeln!(statics, "  {} @ 0x{:08X}", x.path, x.address);
+        for (row, (p, q)) in x.bytes.chunks(16).zip(y.bytes.chunks(16)).enumerate() {
+            if p != q {
+                let address = x.address + row as u64 * 16;
+                let _ = writeln!(statics, "    - {:08X}: {}", address, hex(p));
+                let _ = writeln!(statics, "    + {:08X}: {}", address, hex(q));
+            }
+        }
+    }
+    section(&mut out, "Statics", &statics);
+
+    if out.is_empty() {
+        return None;
+    }
+    Some(format!("--- {}\n+++ {}\n{}", a.name(), b.name(), out))
+}
+
+fn section(out: &mut String, title: &str, body: &str) {
+    if !body.is_empty() {
+        let _ = write!(out, "\n{}:\n{}", title, body);
+    }
+}
+
+fn frame_line(frame: &Frame) -> String {
+    let origin = match frame.origin {
+        Origin::Pc => "pc".to_string(),
+        Origin::Lr => "lr".to_string(),
+        Origin::Exception => "exception entry".to_string(),
+        Origin::Stack(at) => format!("stack 0x{:08X}", at),
+    };
+    format!(
+        "0x{:08X}  {}  ({})",
+        frame.address,
+        frame.function.as_deref().unwrap_or("?"),
+        origin
+    )
+}
+
+/// Thread mode, or the active exception by name
+fn mode(registers: &CoreRegisters) -> String {
+    let name = match registers.exception() {
+        0 => return "thread mode".to_string(),
+        2 => "NMI",
+        3 => "HardFault",
+        4 => "MemManage",
+        5 => "BusFault",
+        6 => "UsageFault",
+        11 => "SVCall",
+        14 => "PendSV",
+        15 => "SysTick",
+        n if n >= 16 => return format!("handler mode, IRQ {}", n - 16),
+        _ => "reserved",
+    };
+    format!("handler mode, {}", name)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    hex.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(name: &str) -> Snapshot {
+        let mut registers = CoreRegisters::default();
+        registers.r[13] = 0x2000_7F00;
+        registers.r[14] = 0x07;
+        registers.r[15] = 0x12;
+        registers.xpsr = 0x0100_0013;
+        Snapshot {
+            path: PathBuf::from("/work/stm32f3-dma/snapshots").join(name),
+            registers,
+            backtrace: vec![
+                Frame {
+                    address: 0x12,
+                    function: Some("helper".into()),
+                    origin: Origin::Pc,
+                },
+                Frame {
+                    address: 0x08,
+                    function: None,
+                    origin: Origin::Stack(0x2000_7F04),
+                },
+            ],
+            peripherals: vec![RegisterValue {
+                block: "DMA1",
+                name: "CNDTR1",
+                address: 0x4002_000C,
+                value: 0x10,
+            }],
+            statics: vec![StaticValue {
+                path: "stm32f3_dma::DST_BUFFER".into(),
+                address: 0x2000_0100,
+                size: 32,
+                bytes: (0..18).collect(),
+            }],
+        }
+    }
+
+    #[test]
+    fn prints_every_section() {
+        let text = snapshot("test-dma.Should_Complete.fail0.save").to_string();
+        let expected = [
+            "=== test-dma.Should_Complete.fail0.save ===\n\nRegisters (handler mode, IRQ 3):\n",
+            "  r0   0x00000000  r1   0x00000000  r2   0x00000000  r3   0x00000000\n",
+            "  r12  0x00000000  sp   0x20007F00  lr   0x00000007  pc   0x00000012\n",
+            "  xpsr 0x01000013\n\nBacktrace:\n",
+            "  #0  0x00000012  helper  (pc)\n",
+            "  #1  0x00000008  ?  (stack 0x20007F04)\n",
+            "\nPeripherals:\n  DMA1    CNDTR1   0x4002000C = 0x00000010\n",
+            "\nStatics:\n  stm32f3_dma::DST_BUFFER @ 0x20000100 (32 bytes)\n",
+            "    20000100: 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F\n",
+            "    20000110: 10 11\n    ...\n",
+        ];
+        for part in expected {
+            assert!(text.contains(part), "{:?} in\n{}", part, text);
+        }
+        assert_eq!(text.lines().filter(|l| l.starts_with("  r")).count(), 4);
+    }
+
+    #[test]
+    fn identical_snapshots_have_no_diff() {
+        assert_eq!(diff(&snapshot("a.save"), &snapshot("b.save")), None);
+    }
+
+    #[test]
+    fn diff_lists_what_changed() {
+        let a = snapshot("pass.save");
+        let mut b = snapshot("fail0.save");
+        b.registers.r[0] = 1;
+        b.backtrace[0].function = Some("isr".into());
+        b.backtrace[0].origin = Origin::Exception;
+        b.peripherals[0].value = 0;
+        b.statics[0].bytes[17] = 0xFF;
+        assert_eq!(
+            diff(&a, &b).unwrap(),
+            "--- pass.save\n+++ fail0.save\n\
+             \nRegisters:\n  r0    0x00000000 | 0x00000001\n\
+             \nBacktrace:\n\
+             \x20 - 0x00000012  helper  (pc)\n\
+             \x20 - 0x00000008  ?  (stack 0x20007F04)\n\
+             \x20 + 0x00000012  isr  (exception entry)\n\
+             \x20 + 0x00000008  ?  (stack 0x20007F04)\n\
+             \nPeripherals:\n  DMA1    CNDTR1   0x00000010 | 0x00000000\n\
+             \nStatics:\n  stm32f3_dma::DST_BUFFER @ 0x20000100\n\
+             \x20   - 20000110: 10 11\n\
+             \x20   + 20000110: 10 FF\n"
+        );
+    }
+
+    #[test]
+    fn modes() {
+        let mode = |ipsr: u32| {
+            mode(&CoreRegisters {
+                xpsr: 0x0100_0000 | ipsr,
+                ..CoreRegisters::default()
+            })
+        };
+        assert_eq!(mode(0), "thread mode");
+        assert_eq!(mode(3), "handler mode, HardFault");
+        assert_eq!(mode(15), "handler mode, SysTick");
+        assert_eq!(mode(16), "handler mode, IRQ 0");
+        assert_eq!(mode(7), "handler mode, reserved");
+        assert_eq!(hex(&[0x00, 0xAB]), "00 AB");
+        assert_eq!(hex(&[]), "");
+    }
+}
@@ -0,0 +1,514 @@
+//! Call graph and worst-case stack depth
+//!
+//! Functions are the sized `STT_FUNC` symbols of the ELF; aliases at the
+//! same address (such as the unused interrupt vectors sharing
+//! `DefaultHandler`) are one node. Each body is decoded between its `$t`
+//! and `$d` mapping symbols so literal pools and jump tables are not read
+//! as code.
+//!
+//! A function's worst case is its own frame plus the deepest of its
+//! callees. Tail calls are counted as calls, which can only overestimate.
+//! Recursion, indirect calls and calls into code without a known frame
+//! make the figure a lower bound; the result records why.
+
+use std::collections::{BTreeSet, HashMap};
+
+use firmware_elf::code::{halfword, is_wide};
+use firmware_elf::Code;
+use object::{Object, ObjectSection, ObjectSymbol, SymbolKind};
+
+use crate::thumb::{self, Flow};
+use crate::{stack_sizes, Error};
+
+/// Where a function's frame size came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameSource {
+    /// The compiler's `.stack_sizes` entry
+    Compiler,
+    /// Decoded from the function's prologue
+    Prologue,
+}
+
+/// cortex-m-rt's default handlers; its linker script points the exception
+/// names at these with zero-sized aliases, which are not function symbols
+const DEFAULT_HANDLERS: &[(&str, &str)] = &[
+    ("DefaultHandler_", "DefaultHandler"),
+    ("HardFault_", "HardFault"),
+];
+
+/// One function in the call graph
+#[derive(Clone, Debug)]
+pub struct Function {
+    /// Start address without the Thumb bit
+    pub address: u64,
+    pub size: u64,
+    /// Demangled names of every symbol at this address
+    pub names: Vec<String>,
+    /// Own stack frame in bytes
+    pub frame: u64,
+    pub source: FrameSource,
+    /// Addresses of called functions (including tail calls), deduplicated
+    pub calls: BTreeSet<u64>,
+    /// Calls or jumps whose target is not a known function start
+    pub unknown_calls: BTreeSet<u64>,
+    /// Uses `BLX <reg>`, `BX <reg>` or loads the PC
+    pub indirect: bool,
+}
+
+impl Function {
+    /// The name to report: the exception name for cortex-m-rt's default
+    /// handlers, otherwise the shortest alias
+    pub fn name(&self) -> &str {
+        for (symbol, exception) in DEFAULT_HANDLERS {
+            if self.names.iter().any(|n| n == symbol) {
+                return exception;
+            }
+        }
+        self.names
+            .iter()
+            .min_by_key(|n| n.len())
+            .map(String::as_str)
+            .unwrap_or("?")
+    }
+}
+
+/// Call graph of a firmware ELF
+#[derive(Clone, Debug)]
+pub struct CallGraph {
+    pub functions: Vec<Function>,
+    /// Whether the ELF had a `.stack_sizes` section
+    pub has_stack_sizes: bool,
+    /// Distinct interrupt and exception handlers from `.vector_table`,
+    /// as `(vector names, handler address)`; `Reset` is left out
+    pub handlers: Vec<(Vec<String>, u64)>,
+}
+
+/// Worst-case stack use from one root
+#[derive(Clone, Debug)]
+pub struct Depth {
+    /// Bytes on the deepest path, root first
+    pub bytes: u64,
+    /// Function addresses on that path
+    pub path: Vec<u64>,
+    /// Cycles found below the root, as function addresses
+    pub recursion: Vec<Vec<u64>>,
+    /// Functions below the root with indirect calls
+    pub indirect: BTreeSet<u64>,
+    /// Unknown call targets below the root
+    pub unknown: BTreeSet<u64>,
+}
+
+impl Depth {
+    /// Whether `bytes` is a true upper bound
+    pub fn is_bounded(&self) -> bool {
+        self.recursion.is_empty() && self.indirect.is_empty() && self.unknown.is_empty()
+    }
+}
+
+impl CallGraph {
+    /// Build the call graph of ELF data
+    pub fn from_elf_data(data: &[u8]) -> Result<CallGraph, Error> {
+        let file = object::File::parse(data)?;
+        let sizes = stack_sizes::read(&file)?;
+        let code = Code::from_file(&file)?;
+
+        // Mapping symbols: (address, is data)
+        let mut mapping: Vec<(u64, bool)> = file
+            .symbols()
+            .filter_map(|s| {
+                let name = s.name().ok()?;
+                let data = name == "$d" || name.starts_with("$d.");
+                let code = name == "$t" || name.starts_with("$t.");
+                (data || code).then(|| (s.address(), data))
+            })
+            .collect();
+        mapping.sort();
+
+        let mut by_address: HashMap<u64, (u64, Vec<String>)> = HashMap::new();
+        for symbol in file.symbols() {
+            if symbol.kind() != SymbolKind::Text || symbol.size() == 0 {
+                continue;
+            }
+            let Ok(name) = symbol.name() else { continue };
+            let entry = by_address
+                .entry(symbol.address() & !1)
+                .or_insert((symbol.size(), Vec::new()));
+            entry
+                .1
+                .push(format!("{:#}", rustc_demangle::demangle(name)));
+        }
+        let mut addresses: Vec<u64> = by_address.keys().copied().collect();
+        addresses.sort();
+
+        let mut functions = Vec::new();
+        for address in &addresses {
+            let (size, mut names) = by_address.remove(address).expect("listed above");
+            names.sort();
+            names.dedup();
+            let body = code
+                .bytes_from(*address)
+                .and_then(|bytes| bytes.get(..size as usize))
+                .unwrap_or_default();
+
+            let (frame, source) = match sizes.as_ref().and_then(|s| s.get(address)) {
+                Some(&frame) => (frame, FrameSource::Compiler),
+                None => (thumb::prologue(body), FrameSource::Prologue),
+            };
+            let mut function = Function {
+                address: *address,
+                size,
+                names,
+                frame,
+                source,
+                calls: BTreeSet::new(),
+                unknown_calls: BTreeSet::new(),
+                indirect: false,
+            };
+            scan(&mut function, body, &mapping, &addresses);
+            functions.push(function);
+        }
+
+        let handlers = vector_handlers(&file, &functions)?;
+        Ok(CallGraph {
+            functions,
+            has_stack_sizes: sizes.is_some(),
+            handlers,
+        })
+    }
+
+    pub fn function(&self, address: u64) -> Option<&Function> {
+        self.functions
+            .binary_search_by_key(&address, |f| f.address)
+            .ok()
+            .map(|index| &self.functions[index])
+    }
+
+    /// The function with this name (any of its aliases)
+    pub fn lookup(&self, name: &str) -> Option<&Function> {
+        self.functions
+            .iter()
+            .find(|f| f.names.iter().any(|n| n == name))
+    }
+
+    /// Worst-case stack use starting at the function at `root`
+    pub fn depth(&self, root: u64) -> Depth {
+        let mut memo = HashMap::new();
+        let mut stack = Vec::new();
+        let mut depth = Depth {
+            bytes: 0,
+            path: Vec::new(),
+            recursion: Vec::new(),
+            indirect: BTreeSet::new(),
+            unknown: BTreeSet::new(),
+        };
+        let (bytes, path) = self.walk(root, &mut memo, &mut stack, &mut depth);
+        depth.bytes = bytes;
+        depth.path = path;
+        depth
+    }
+
+    fn walk(
+        &self,
+        address: u64,
+        memo: &mut HashMap<u64, (u64, Vec<u64>)>,
+        stack: &mut Vec<u64>,
+        depth: &mut Depth,
+    ) -> (u64, Vec<u64>) {
+        if let Some(done) = memo.get(&address) {
+            return done.clone();
+        }
+        let Some(function) = self.function(address) else {
+            depth.unknown.insert(address);
+            return (0, Vec::new());
+        };
+        if let Some(start) = stack.iter().position(|&a| a == address) {
+            let mut cycle = stack[start..].to_vec();
+            cycle.push(address);
+            depth.recursion.push(cycle);
+            return (0, Vec::new());
+        }
+
+        if function.indirect {
+            depth.indirect.insert(address);
+        }
+        depth.unknown.extend(&function.unknown_calls);
+
+        stack.push(address);
+        let mut deepest = (0, Vec::new());
+        for &callee in &function.calls {
+            let below = self.walk(callee, memo, stack, depth);
+            if below.0 > deepest.0 || deepest.1.is_empty() {
+                deepest = below;
+            }
+        }
+        stack.pop();
+
+        let mut path = vec![address];
+        path.extend(deepest.1);
+        let result = (function.frame + deepest.0, path);
+        memo.insert(address, result.clone());
+        result
+    }
+}
+
+/// Record the calls, tail calls and indirect jumps in a function body
+fn scan(function: &mut Function, code: &[u8], mapping: &[(u64, bool)], starts: &[u64]) {
+    let end = function.address + function.size;
+    let mut pos = 0usize;
+    while let Some(hw1) = halfword(code, pos) {
+        let pc = function.address + pos as u64;
+
+        // Skip literal pools and jump tables
+        let region = mapping.partition_point(|&(a, _)| a <= pc);
+        if region > 0 && mapping[region - 1].1 {
+            match mapping[region..]
+                .iter()
+                .find(|&&(a, data)| !data && a < end)
+            {
+                Some(&(next, _)) => {
+                    pos = (next - function.address) as usize;
+                    continue;
+                }
+                None => break,
+            }
+        }
+
+        let wide = is_wide(hw1);
+        let hw2 = if wide {
+            halfword(code, pos + 2).unwrap_or(0)
+        } else {
+            0
+        };
+        match thumb::decode(pc, hw1, hw2) {
+            Some(Flow::Call(to)) | Some(Flow::Branch(to))
+                if !(function.address..end).contains(&to) =>
+            {
+                if starts.binary_search(&to).is_ok() {
+                    function.calls.insert(to);
+                } else {
+                    function.unknown_calls.insert(to);
+                }
+            }
+            Some(Flow::IndirectCall) | Some(Flow::IndirectJump) => function.indirect = true,
+            _ => {}
+        }
+        pos += if wide { 4 } else { 2 };
+    }
+}
+
+/// Handlers in `.vector_table`, grouped by address
+///
+/// Entry 0 is the initial stack pointer and entry 1 is `Reset`; each later
+/// entry is named after the symbols at its address, so a handler defined
+/// with `#[interrupt] fn EXTI0()` is reported as `EXTI0` and every unused
+/// vector as `DefaultHandler`.
+fn vector_handlers(
+    file: &object::File,
+    functions: &[Function],
+) -> Result<Vec<(Vec<String>, u64)>, Error> {
+    let Some(section) = file.section_by_name(".vector_table") else {
+        return Ok(Vec::new());
+    };
+    let data = section.data()?;
+    let mut handlers: Vec<(Vec<String>, u64)> = Vec::new();
+    for word in data.chunks_exact(4).skip(2) {
+        let address = u64::from(u32::from_le_bytes(word.try_into().expect("4 bytes")) & !1);
+        if address == 0 || handlers.iter().any(|(_, a)| *a == address) {
+            continue;
+        }
+        let names = functions
+            .iter()
+            .find(|f| f.address == address)
+            .map(|f| f.names.clone())
+            .unwrap_or_else(|| vec![format!("0x{:08X}", address)]);
+        handlers.push((names, address));
+    }
+    Ok(handlers)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use firmware_elf::test_support::{thumb, ElfBuilder};
+    use object::SectionKind;
+
+    const MAIN: u64 = 0x00;
+    const HELPER: u64 = 0x20;
+    const LEAF: u64 = 0x40;
+    const EXTI0: u64 = 0x50;
+    const PING: u64 = 0x60;
+    const PONG: u64 = 0x70;
+    const STRAY: u64 = 0x80;
+
+    /// A firmware image with `.text` at address 0:
+    ///
+    /// - `main` calls `helper` and `leaf`, with a 12-byte `.stack_sizes` frame
+    /// - `helper` calls `leaf`; its literal pool holds a `BL` lookalike
+    /// - `leaf` calls through a register
+    /// - `EXTI0` tail-calls `leaf`
+    /// - `ping` and `pong` call each other
+    /// - `stray` calls into the middle of nowhere
+    ///
+    /// `.vector_table` lists `EXTI0` twice, `stray` and an address without
+    /// a function.
+    pub(crate) fn firmware() -> Vec<u8> {
+        let mut text: Vec<u16> = vec![0xBF00; 0x44];
+        let mut put = |address: u64, halfwords: &[u16]| {
+            let at = address as usize / 2;
+            text[at..at + halfwords.len()].copy_from_slice(halfwords);
+        };
+        // PUSH {r7, lr}; BL helper; BL leaf; POP {r7, pc}
+        put(MAIN, &[0xB580, 0xF000, 0xF80D, 0xF000, 0xF81B, 0xBD80]);
+        // PUSH {r4, lr}; SUB sp, #16; BL leaf; ADD sp, #16; POP {r4, pc};
+        // literal pool
+        put(
+            HELPER,
+            &[
+                0xB510, 0xB084, 0xF000, 0xF80C, 0xB004, 0xBD10, 0xF000, 0xF800,
+            ],
+        );
+        // SUB sp, #8; BLX r3; ADD sp, #8; BX lr
+        put(LEAF, &[0xB082, 0x4798, 0xB002, 0x4770]);
+        // PUSH {lr}; B.W leaf
+        put(EXTI0, &[0xB500, 0xF7FF, 0xBFF5]);
+        // PUSH {lr}; BL pong / BL ping; POP {pc}
+        put(PING, &[0xB500, 0xF000, 0xF805, 0xBD00]);
+        put(PONG, &[0xB500, 0xF7FF, 0xFFF5, 0xBD00]);
+        // PUSH {lr}; BL 0x100; POP {pc}
+        put(STRAY, &[0xB500, 0xF000, 0xF83D, 0xBD00]);
+
+        let mut elf = ElfBuilder::new();
+        let text_id = elf.section(".text", SectionKind::Text, &thumb(&text));
+
+        let vectors: Vec<u8> = [0x2000_A000u32, 0x01, 0x51, 0x51, 0, 0x81, 0x201]
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        elf.section(".vector_table", SectionKind::ReadOnlyData, &vectors);
+
+        // main: 12 bytes
+        elf.section(stack_sizes::SECTION, SectionKind::Other, &[0, 0, 0, 0, 12]);
+
+        let functions = [
+            ("main", MAIN, 0x10),
+            ("helper", HELPER, 0x10),
+            ("leaf", LEAF, 0x08),
+            ("EXTI0", EXTI0, 0x08),
+            ("__EXTI0_alias", EXTI0, 0x08),
+            ("ping", PING, 0x08),
+            ("pong", PONG, 0x08),
+            ("stray", STRAY, 0x08),
+        ];
+        for (name, address, size) in functions {
+            elf.symbol(name, address | 1, size, SymbolKind::Text, text_id);
+        }
+        for (name, address) in [("$t", 0x00), ("$d", 0x2C), ("$t", 0x30)] {
+            elf.symbol(name, address, 0, SymbolKind::Label, text_id);
+        }
+        elf.absolute("__sheap", 0x2000_1000)
+            .absolute("_stack_start", 0x2000_A000);
+        elf.build()
+    }
+
+    fn graph() -> CallGraph {
+        CallGraph::from_elf_data(&firmware()).unwrap()
+    }
+
+    #[test]
+    fn one_node_per_address() {
+        let graph = graph();
+        let addresses: Vec<u64> = graph.functions.iter().map(|f| f.address).collect();
+        assert_eq!(addresses, [MAIN, HELPER, LEAF, EXTI0, PING, PONG, STRAY]);
+        let exti0 = graph.function(EXTI0).unwrap();
+        assert_eq!(exti0.names, ["EXTI0", "__EXTI0_alias"]);
+        assert_eq!(exti0.name(), "EXTI0");
+        assert_eq!(graph.lookup("__EXTI0_alias").unwrap().address, EXTI0);
+        assert!(graph.function(0x02).is_none());
+        assert!(graph.lookup("missing").is_none());
+    }
+
+    #[test]
+    fn frames_from_the_compiler_or_the_prologue() {
+        let graph = graph();
+        assert!(graph.has_stack_sizes);
+        let frame = |address| {
+            let f = graph.function(address).unwrap();
+            (f.frame, f.source)
+        };
+        assert_eq!(frame(MAIN), (12, FrameSource::Compiler));
+        assert_eq!(frame(HELPER), (24, FrameSource::Prologue));
+        assert_eq!(frame(LEAF), (8, FrameSource::Prologue));
+        assert_eq!(frame(EXTI0), (4, FrameSource::Prologue));
+    }
+
+    #[test]
+    fn calls_tail_calls_and_indirect_flow() {
+        let graph = graph();
+        let function = |address| graph.function(address).unwrap();
+        assert_eq!(function(MAIN).calls, BTreeSet::from([HELPER, LEAF]));
+        // The literal pool's `BL` lookalike is skipped
+        assert_eq!(function(HELPER).calls, BTreeSet::from([LEAF]));
+        assert!(function(HELPER).unknown_calls.is_empty());
+        assert!(function(LEAF).indirect);
+        assert!(!function(MAIN).indirect);
+        assert_eq!(function(EXTI0).calls, BTreeSet::from([LEAF]));
+        assert_eq!(function(STRAY).unknown_calls, BTreeSet::from([0x100]));
+    }
+
+    #[test]
+    fn deepest_path() {
+        let depth = graph().depth(MAIN);
+        assert_eq!(depth.bytes, 12 + 24 + 8);
+        assert_eq!(depth.path, [MAIN, HELPER, LEAF]);
+        assert_eq!(depth.indirect, BTreeSet::from([LEAF]));
+        assert!(depth.recursion.is_empty());
+        assert!(!depth.is_bounded());
+    }
+
+    #[test]
+    fn recursion_and_unknown_code() {
+        let graph = graph();
+        let depth = graph.depth(PING);
+        assert_eq!(depth.bytes, 8);
+        assert_eq!(depth.recursion, [vec![PING, PONG, PING]]);
+
+        let depth = graph.depth(STRAY);
+        assert_eq!(depth.bytes, 4);
+        assert_eq!(depth.unknown, BTreeSet::from([0x100]));
+
+        let depth = graph.depth(0x200);
+        assert_eq!(depth.bytes, 0);
+        assert_eq!(depth.unknown, BTreeSet::from([0x200]));
+    }
+
+    #[test]
+    fn distinct_vector_handlers() {
+        let handlers = graph().handlers;
+        assert_eq!(
+            handlers,
+            [
+                (
+                    vec!["EXTI0".to_string(), "__EXTI0_alias".to_string()],
+                    EXTI0
+                ),
+                (vec!["stray".to_string()], STRAY),
+                (vec!["0x00000200".to_string()], 0x200),
+            ]
+        );
+    }
+
+    #[test]
+    fn default_handlers_report_the_exception() {
+        let function = Function {
+            address: 0,
+            size: 2,
+            names: vec!["DefaultHandler_".into(), "a".into()],
+            frame: 0,
+            source: FrameSource::Prologue,
+            calls: BTreeSet::new(),
+            unknown_calls: BTreeSet::new(),
+            indirect: false,
+        };
+        assert_eq!(function.name(), "DefaultHandler");
+    }
+}
@@ -0,0 +1,216 @@
+//! Static worst-case stack usage of the firmware images
+//!
+//! Reads a release ELF built with `-Z emit-stack-sizes` (see
+//! `cargo xtask stack`), takes each function's frame from the compiler's
+//! `.stack_sizes` section ([`stack_sizes`]) or, for code rustc did not
+//! compile, from its prologue, and decodes the Thumb-2 code ([`thumb`]) into
+//! a call graph ([`graph`]). The roots are `main` and every distinct handler
+//! in `.vector_table`; a handler's figure includes the hardware exception
+//! frame it is entered with.
+//!
+//! The result is only an upper bound when the paths below a root contain no
+//! recursion, no indirect calls and no calls into unknown code; otherwise
+//! [`Root::depth`] says why it is not.
+
+use std::fmt;
+
+use object::{Object, ObjectSymbol};
+
+pub mod graph;
+pub mod stack_sizes;
+pub mod thumb;
+
+pub use graph::{CallGraph, Depth, FrameSource, Function};
+
+/// Bytes the core pushes on exception entry with the FPU enabled: the
+/// extended frame of R0-R3, R12, LR, PC, xPSR, S0-S15, FPSCR and padding
+pub const EXCEPTION_FRAME: u64 = 104;
+
+/// Errors reading an ELF
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be read
+    Io(std::io::Error),
+    /// The file is not a parseable ELF
+    Elf(object::Error),
+    /// The `.stack_sizes` section is malformed
+    StackSizes(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "read failed: {}", err),
+            Error::Elf(err) => write!(f, "invalid ELF: {}", err),
+            Error::StackSizes(msg) => write!(f, "bad {} section: {}", stack_sizes::SECTION, msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<object::Error> for Error {
+    fn from(err: object::Error) -> Self {
+        Error::Elf(err)
+    }
+}
+
+impl From<firmware_elf::Error> for Error {
+    fn from(err: firmware_elf::Error) -> Self {
+        match err {
+            firmware_elf::Error::Io(err) => Error::Io(err),
+            firmware_elf::Error::Elf(err) => Error::Elf(err),
+            other => Error::Io(std::io::Error::other(other.to_string())),
+        }
+    }
+}
+
+/// Worst case from one entry point
+#[derive(Clone, Debug)]
+pub struct Root {
+    pub name: String,
+    pub address: u64,
+    /// Hardware frame pushed before the root runs (0 for `main`)
+    pub entry_frame: u64,
+    pub depth: Depth,
+}
+
+impl Root {
+    /// Stack used from this root, including the exception frame
+    pub fn bytes(&self) -> u64 {
+        self.entry_frame + self.depth.bytes
+    }
+}
+
+/// Per-root worst cases of one firmware image
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    pub graph: CallGraph,
+    /// `main` first (if present), then the vector table handlers
+    pub roots: Vec<Root>,
+    /// Bytes between `__sheap` and `_stack_start`, if both are defined
+    pub available: Option<u64>,
+}
+
+impl Analysis {
+    /// Analyse ELF data
+    pub fn from_elf_data(data: &[u8]) -> Result<Analysis, Error> {
+        let graph = CallGraph::from_elf_data(data)?;
+        let file = object::File::parse(data)?;
+        let symbol = |name: &str| {
+            file.symbols()
+                .find(|s| s.name() == Ok(name))
+                .map(|s| s.address())
+        };
+        let available = match (symbol("__sheap"), symbol("_stack_start")) {
+            (Some(bottom), Some(top)) if top > bottom => Some(top - bottom),
+            _ => None,
+        };
+
+        let mut roots = Vec::new();
+        if let Some(main) = graph.lookup("main") {
+            roots.push(Root {
+                name: "main".into(),
+                address: main.address,
+                entry_frame: 0,
+                depth: graph.depth(main.address),
+            });
+        }
+        for (names, address) in &graph.handlers {
+            let name = match graph.function(*address) {
+                Some(function) => function.name().to_string(),
+                None => names.join("/"),
+            };
+            roots.push(Root {
+                name,
+                address: *address,
+                entry_frame: EXCEPTION_FRAME,
+                depth: graph.depth(*address),
+            });
+        }
+        Ok(Analysis {
+            graph,
+            roots,
+            available,
+        })
+    }
+
+    /// Analyse an ELF file on disk
+    pub fn from_elf(path: impl AsRef<std::path::Path>) -> Result<Analysis, Error> {
+        Self::from_elf_data(&std::fs::read(path)?)
+    }
+
+    /// `main` plus the deepest handler
+    ///
+    /// Every interrupt runs at the reset priority, so handlers cannot
+    /// preempt each other and at most one is active on top of `main`.
+    pub fn worst_case(&self) -> u64 {
+        let main = self
+            .roots
+            .iter()
+            .find(|r| r.entry_frame == 0)
+            .map_or(0, Root::bytes);
+        let handler = self
+            .roots
+            .iter()
+            .filter(|r| r.entry_frame != 0)
+            .map(Root::bytes)
+            .max()
+            .unwrap_or(0);
+        main + handler
+    }
+
+    /// Whether every root's figure is a true upper bound
+    pub fn is_bounded(&self) -> bool {
+        self.roots.iter().all(|r| r.depth.is_bounded())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::tests::firmware;
+
+    #[test]
+    fn roots_are_main_and_the_handlers() {
+        let analysis = Analysis::from_elf_data(&firmware()).unwrap();
+        let roots: Vec<(&str, u64, u64)> = analysis
+            .roots
+            .iter()
+            .map(|r| (r.name.as_str(), r.address, r.bytes()))
+            .collect();
+        assert_eq!(
+            roots,
+            [
+                ("main", 0x00, 44),
+                ("EXTI0", 0x50, EXCEPTION_FRAME + 4 + 8),
+                ("stray", 0x80, EXCEPTION_FRAME + 4),
+                ("0x00000200", 0x200, EXCEPTION_FRAME),
+            ]
+        );
+    }
+
+    #[test]
+    fn worst_case_is_main_plus_one_handler() {
+        let analysis = Analysis::from_elf_data(&firmware()).unwrap();
+        assert_eq!(analysis.worst_case(), 44 + EXCEPTION_FRAME + 12);
+        assert_eq!(analysis.available, Some(0x9000));
+        assert!(!analysis.is_bounded());
+    }
+
+    #[test]
+    fn not_an_elf() {
+        let error = Analysis::from_elf_data(b"firmware").unwrap_err();
+        assert!(error.to_string().starts_with("invalid ELF"), "{}", error);
+        assert_eq!(
+            Error::StackSizes("truncated size").to_string(),
+            "bad .stack_sizes section: truncated size"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Report worst-case stack usage per entry point
+//!
+//! Usage: `stack-usage [OPTIONS] [CRATE]...`
+//!
+//! Analyses `target/stack-sizes/thumbv7em-none-eabihf/release/stm32f3-<x>`,
+//! the release build with `-Z emit-stack-sizes` that `cargo xtask stack`
+//! produces. `--measure` also runs each crate's Robot suites under Renode
+//! on the same ELF and compares the `Stack: used <n> of <m> bytes` line the
+//! firmware prints from its painted stack with the static figure.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use renode_runner::robot::Suite;
+use renode_runner::run::{self, Config};
+use stack_usage::{Analysis, FrameSource, Root};
+
+/// Firmware target triple
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+/// Where `cargo xtask stack` builds, relative to the firmware root
+const STACK_SIZES_DIR: &str = "target/stack-sizes";
+
+/// Prefix of the line `stm32f3_common::stack` prints
+const MEASURED_PREFIX: &str = "Stack: used ";
+
+#[derive(Parser)]
+#[command(about = "Report static worst-case stack usage of the firmware")]
+struct Args {
+    /// Peripheral crates (e.g. `dma exti`); all when omitted
+    crates: Vec<String>,
+
+    /// Firmware workspace root
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))]
+    root: PathBuf,
+
+    /// Directory with the ELFs (default: the `-Z emit-stack-sizes` build)
+    #[arg(long)]
+    elf_dir: Option<PathBuf>,
+
+    /// Print the deepest call path of every root
+    #[arg(long)]
+    paths: bool,
+
+    /// Run the suites under Renode and compare with the painted-stack figure
+    #[arg(long)]
+    measure: bool,
+
+    /// Renode executable (with --measure)
+    #[arg(long, env = "RENODE", default_value = "renode")]
+    renode: PathBuf,
+
+    /// Renode monitor port (with --measure)
+    #[arg(long, default_value_t = 1234)]
+    port: u16,
+
+    /// PTY path for the UART terminal (with --measure)
+    #[arg(long, default_value = "/tmp/uart")]
+    uart: PathBuf,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let root = args.root.canonicalize().unwrap_or(args.root.clone());
+    let elf_dir = args
+        .elf_dir
+        .clone()
+        .unwrap_or_else(|| root.join(STACK_SIZES_DIR).join(TARGET).join("release"));
+
+    let crates = match firmware_crates(&root, &args.crates) {
+        Ok(crates) => crates,
+        Err(err) => {
+            eprintln!("stack-usage: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut status = ExitCode::SUCCESS;
+    for name in &crates {
+        let elf = elf_dir.join(name);
+        let analysis = match Analysis::from_elf(&elf) {
+            Ok(analysis) => analysis,
+            Err(err) => {
+                eprintln!("{}: {}", elf.display(), err);
+                return ExitCode::from(2);
+            }
+        };
+
+        println!("=== {} ===", name);
+        print_analysis(&analysis, args.paths);
+
+        if args.measure {
+            match measure(&args, &root.join(name), &elf) {
+                Ok(Some(measured)) => {
+                    let worst = analysis.worst_case();
+                    if measured <= worst {
+                        println!("  measured: {} bytes (within the static figure)", measured);
+                    } else {
+                        println!(
+                            "  measured: {} bytes EXCEEDS the static figure by {}",
+                            measured,
+                            measured - worst
+                        );
+                        status = ExitCode::FAILURE;
+                    }
+                }
+                Ok(None) => println!(
+                    "  measured: no `{}` line in the transcripts",
+                    MEASURED_PREFIX.trim()
+                ),
+                Err(err) => {
+                    eprintln!("stack-usage: {}", err);
+                    return ExitCode::from(2);
+                }
+            }
+        }
+        println!();
+    }
+    status
+}
+
+fn print_analysis(analysis: &Analysis, paths: bool) {
+    let graph = &analysis.graph;
+    if !graph.has_stack_sizes {
+        println!("  no .stack_sizes section: every frame is a prologue estimate");
+    }
+
+    for root in &analysis.roots {
+        let frame = if root.entry_frame > 0 {
+            format!(" (incl. {}-byte exception frame)", root.entry_frame)
+        } else {
+            String::new()
+        };
+        let bound = if root.depth.is_bounded() {
+            ""
+        } else {
+            " at least"
+        };
+        println!(
+            "  {:<16}{} {} bytes{}",
+            root.name,
+            bound,
+            root.bytes(),
+            frame
+        );
+        if paths {
+            print_path(analysis, root);
+        }
+    }
+
+    let worst = analysis.worst_case();
+    let available = match analysis.available {
+        Some(available) => format!(" of {} available", available),
+        None => String::new(),
+    };
+    println!(
+        "  worst case: {}{} bytes{} (main + deepest handler)",
+        if analysis.is_bounded() {
+            ""
+        } else {
+            "at least "
+        },
+        worst,
+        available
+    );
+    if analysis
+        .available
+        .is_some_and(|available| worst > available)
+    {
+        println!("  STACK OVERFLOW: worst case exceeds the available stack");
+    }
+
+    let name = |address: &u64| {
+        graph
+            .function(*address)
+            .map_or_else(|| format!("0x{:08X}", address), |f| f.name().to_string())
+    };
+    let mut recursion = Vec::new();
+    let mut indirect = BTreeSet::new();
+    let mut unknown = BTreeSet::new();
+    let mut estimated = BTreeSet::new();
+    for root in &analysis.roots {
+        for cycle in &root.depth.recursion {
+            let cycle: Vec<String> = cycle.iter().map(name).collect();
+            let cycle = cycle.join(" -> ");
+            if !recursion.contains(&cycle) {
+                recursion.push(cycle);
+            }
+        }
+        indirect.extend(root.depth.indirect.iter().map(name));
+        unknown.extend(root.depth.unknown.iter().map(|a| format!("0x{:08X}", a)));
+        estimated.extend(
+            root.depth
+                .path
+                .iter()
+                .filter_map(|a| graph.function(*a))
+                .filter(|f| f.source == FrameSource::Prologue && graph.has_stack_sizes)
+                .map(|f| f.name().to_string()),
+        );
+    }
+    for cycle in &recursion {
+        println!("  recursion: {}", cycle);
+    }
+    if !indirect.is_empty() {
+        println!("  indirect calls in: {}", join(&indirect));
+    }
+    if !unknown.is_empty() {
+        println!("  calls to unknown code at: {}", join(&unknown));
+    }
+    if !estimated.is_empty() {
+        println!("  frames estimated from the prologue: {}", join(&estimated));
+    }
+}
+
+fn print_path(analysis: &Analysis, root: &Root) {
+    for address in &root.depth.path {
+        if let Some(function) = analysis.graph.function(*address) {
+            let source = match function.source {
+                FrameSource::Compiler => "",
+                FrameSource::Prologue => " (prologue)",
+            };
+            println!("      {:>5}{} {}", function.frame, source, function.name());
+        }
+    }
+}
+
+fn join(items: &BTreeSet<String>) -> String {
+    items.iter().cloned().collect::<Vec<_>>().join(", ")
+}
+
+/// Run the crate's suites on `elf` and return the largest painted-stack
+/// figure the firmware printed
+fn measure(args: &Args, dir: &Path, elf: &Path) -> Result<Option<u64>, renode_runner::Error> {
+    let variables: HashMap<String, String> =
+        [("ELF".to_string(), elf.to_string_lossy().into_owned())].into();
+    let config = Config {
+        renode: args.renode.clone(),
+        port: args.port,
+        uart_path: args.uart.clone(),
+        timeout_scale: 1.0,
+        test_filter: Vec::new(),
+        log_dir: None,
+        trace_dir: None,
+    };
+
+    let mut measured = None;
+    let Ok(entries) = fs::read_dir(dir.join("tests")) else {
+        return Ok(None);
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "robot"))
+        .collect();
+    files.sort();
+    for file in files {
+        let suite = Suite::load_with(&file, &variables)?;
+        let result = run::run_suite(&config, &suite)?;
+        for line in result.tests.iter().flat_map(|t| &t.transcript) {
+            if let Some(used) = parse_measured(line) {
+                measured = measured.max(Some(used));
+            }
+        }
+    }
+    Ok(measured)
+}
+
+/// `Stack: used <n> of <m> bytes` -> `n`
+fn parse_measured(line: &str) -> Option<u64> {
+    let rest = &line[line.find(MEASURED_PREFIX)? + MEASURED_PREFIX.len()..];
+    rest.split_whitespace().next()?.parse().ok()
+}
+
+/// `stm32f3-*` binary crates, restricted to `wanted` if non-empty
+fn firmware_crates(root: &Path, wanted: &[String]) -> Result<Vec<String>, String> {
+    let entries = fs::read_dir(root).map_err(|err| format!("{}: {}", root.display(), err))?;
+    let mut all: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.join("src/main.rs").is_file())
+        .filter_map(|path| path.file_name()?.to_str().map(str::to_string))
+        .filter(|name| name.starts_with("stm32f3-"))
+        .collect();
+    all.sort();
+
+    if wanted.is_empty() {
+        return Ok(all);
+    }
+    wanted
+        .iter()
+        .map(|name| {
+            let name = if name.starts_with("stm32f3-") {
+                name.clone()
+            } else {
+                format!("stm32f3-{}", name)
+            };
+            if all.contains(&name) {
+                Ok(name)
+            } else {
+                Err(format!("no firmware crate {}", name))
+            }
+        })
+        .collect()
+}
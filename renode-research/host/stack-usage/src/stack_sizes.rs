@@ -0,0 +1,114 @@
+//! The compiler's `.stack_sizes` section
+//!
+//! Built with `-Z emit-stack-sizes`, every function rustc generates gets an
+//! entry: its address as a target-sized little-endian word followed by its
+//! frame size as ULEB128. The addresses are function starts without the
+//! Thumb bit. Code rustc did not compile in this build (precompiled
+//! `compiler_builtins`, assembly trampolines) has no entry.
+
+use std::collections::HashMap;
+
+use object::{Object, ObjectSection};
+
+use crate::Error;
+
+/// Section emitted by `-Z emit-stack-sizes`
+pub const SECTION: &str = ".stack_sizes";
+
+/// Frame size in bytes per function address; `None` if the ELF has no
+/// `.stack_sizes` section
+pub fn read(file: &object::File) -> Result<Option<HashMap<u64, u64>>, Error> {
+    let Some(section) = file.section_by_name(SECTION) else {
+        return Ok(None);
+    };
+    let data = section.data()?;
+    let word = if file.is_64() { 8 } else { 4 };
+
+    let mut sizes = HashMap::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let address = data
+            .get(pos..pos + word)
+            .ok_or(Error::StackSizes("truncated address"))?;
+        let address = address
+            .iter()
+            .rev()
+            .fold(0u64, |acc, &b| acc << 8 | u64::from(b));
+        pos += word;
+
+        let mut size = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = *data.get(pos).ok_or(Error::StackSizes("truncated size"))?;
+            pos += 1;
+            size |= u64::from(byte & 0x7F) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            if shift >= 64 {
+                return Err(Error::StackSizes("size does not fit in 64 bits"));
+            }
+        }
+        sizes.insert(address, size);
+    }
+    Ok(Some(sizes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use firmware_elf::test_support::ElfBuilder;
+    use object::SectionKind;
+
+    fn elf(stack_sizes: Option<&[u8]>) -> Vec<u8> {
+        let mut elf = ElfBuilder::new();
+        elf.section(".text", SectionKind::Text, &[0; 4]);
+        if let Some(data) = stack_sizes {
+            elf.section(SECTION, SectionKind::Other, data);
+        }
+        elf.build()
+    }
+
+    fn sizes(data: &[u8]) -> Result<Option<HashMap<u64, u64>>, Error> {
+        let elf = elf(Some(data));
+        read(&object::File::parse(&*elf).unwrap())
+    }
+
+    #[test]
+    fn addresses_and_uleb128_sizes() {
+        let sizes = sizes(&[
+            0x00, 0x01, 0x00, 0x08, 16, // 0x08000100: 16
+            0x00, 0x02, 0x00, 0x08, 0xAC, 0x02, // 0x08000200: 300
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            sizes,
+            HashMap::from([(0x0800_0100, 16), (0x0800_0200, 300)])
+        );
+    }
+
+    #[test]
+    fn missing_section() {
+        let elf = elf(None);
+        assert!(read(&object::File::parse(&*elf).unwrap())
+            .unwrap()
+            .is_none());
+        assert_eq!(sizes(&[]).unwrap(), Some(HashMap::new()));
+    }
+
+    #[test]
+    fn malformed_entries() {
+        let message = |data: &[u8]| match sizes(data) {
+            Err(Error::StackSizes(msg)) => msg,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(message(&[0x00, 0x01, 0x00]), "truncated address");
+        assert_eq!(message(&[0x00, 0x01, 0x00, 0x08]), "truncated size");
+        assert_eq!(message(&[0x00, 0x01, 0x00, 0x08, 0x80]), "truncated size");
+        let mut long = vec![0x00, 0x01, 0x00, 0x08];
+        long.extend([0x80; 10]);
+        assert_eq!(message(&long), "size does not fit in 64 bits");
+    }
+}
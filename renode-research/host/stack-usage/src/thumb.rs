@@ -0,0 +1,265 @@
+//! Just enough Thumb-2 decoding to build a call graph
+//!
+//! Only control flow that leaves a function matters here: `BL` calls,
+//! `B`/`B.W`/`B<c>` branches whose target lies outside the function (tail
+//! calls), and register-indirect `BLX`/`BX`/`LDR PC`/`MOV PC`. Everything
+//! else is skipped by its length. The prologue decoder estimates a frame
+//! for functions without a `.stack_sizes` entry.
+
+use firmware_elf::code::{halfword, is_wide};
+
+/// A control-flow instruction found in a function body
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    /// `BL <target>`
+    Call(u64),
+    /// `B`, `B<c>` or `B.W` to `target`; a tail call if outside the function
+    Branch(u64),
+    /// `BLX <reg>`
+    IndirectCall,
+    /// `BX <reg>` (other than `LR`), `LDR PC, [...]` or `MOV PC, <reg>`
+    IndirectJump,
+}
+
+/// Decode the instruction at `pc`; `hw2` is ignored for 16-bit ones
+pub fn decode(pc: u64, hw1: u16, hw2: u16) -> Option<Flow> {
+    if is_wide(hw1) {
+        return decode_wide(pc, hw1, hw2);
+    }
+
+    // BLX Rm / BX Rm
+    if hw1 & 0xFF87 == 0x4780 {
+        return Some(Flow::IndirectCall);
+    }
+    if hw1 & 0xFF87 == 0x4700 {
+        let rm = (hw1 >> 3) & 0xF;
+        return (rm != 14).then_some(Flow::IndirectJump);
+    }
+    // MOV PC, Rm
+    if hw1 & 0xFF87 == 0x4687 {
+        return Some(Flow::IndirectJump);
+    }
+    // B<c> (T1); conditions 0b1110 and 0b1111 are UDF and SVC
+    if hw1 & 0xF000 == 0xD000 && (hw1 >> 8) & 0xF < 0xE {
+        let offset = sign_extend(u64::from(hw1 & 0xFF) << 1, 9);
+        return Some(Flow::Branch(target(pc, offset)));
+    }
+    // B (T2)
+    if hw1 & 0xF800 == 0xE000 {
+        let offset = sign_extend(u64::from(hw1 & 0x7FF) << 1, 12);
+        return Some(Flow::Branch(target(pc, offset)));
+    }
+    None
+}
+
+fn decode_wide(pc: u64, hw1: u16, hw2: u16) -> Option<Flow> {
+    if hw1 & 0xF800 == 0xF000 {
+        let s = u64::from((hw1 >> 10) & 1);
+        let j1 = u64::from((hw2 >> 13) & 1);
+        let j2 = u64::from((hw2 >> 11) & 1);
+        let imm11 = u64::from(hw2 & 0x7FF);
+
+        match hw2 & 0xD000 {
+            // BL (T1) and B.W (T4) share the immediate layout
+            0xD000 | 0x9000 => {
+                let i1 = !(j1 ^ s) & 1;
+                let i2 = !(j2 ^ s) & 1;
+                let imm10 = u64::from(hw1 & 0x3FF);
+                let offset =
+                    sign_extend(s << 24 | i1 << 23 | i2 << 22 | imm10 << 12 | imm11 << 1, 25);
+                let to = target(pc, offset);
+                return Some(if hw2 & 0xD000 == 0xD000 {
+                    Flow::Call(to)
+                } else {
+                    Flow::Branch(to)
+                });
+            }
+            // B<c>.W (T3)
+            0x8000 if (hw1 >> 6) & 0xF < 0xE => {
+                let imm6 = u64::from(hw1 & 0x3F);
+                let offset =
+                    sign_extend(s << 20 | j2 << 19 | j1 << 18 | imm6 << 12 | imm11 << 1, 21);
+                return Some(Flow::Branch(target(pc, offset)));
+            }
+            _ => {}
+        }
+    }
+
+    // LDR.W PC, [Rn, #imm12] / LDR PC, [Rn, #-imm8] / LDR PC, [Rn, Rm]
+    if hw1 & 0xFFF0 == 0xF8D0 && hw2 >> 12 == 0xF {
+        return Some(Flow::IndirectJump);
+    }
+    if hw1 & 0xFFF0 == 0xF850 && hw2 >> 12 == 0xF {
+        // `LDR PC, [SP], #4` is a single-register `POP {pc}`: a return
+        let pop = hw1 & 0xF == 13 && hw2 & 0x0FFF == 0x0B04;
+        return (!pop).then_some(Flow::IndirectJump);
+    }
+    None
+}
+
+/// Stack the prologue at the start of a function reserves, in bytes
+///
+/// Recognises `PUSH`, `PUSH.W`, `STR.W Rt, [SP, #-4]!`, `VPUSH`,
+/// `SUB SP, #imm`, `SUB.W SP, SP, #imm` and `SUBW SP, SP, #imm`, and stops
+/// at the first other instruction (`ADD R7, SP` and `MOV R7, SP` frame
+/// pointer set-up is skipped).
+pub fn prologue(code: &[u8]) -> u64 {
+    let mut bytes = 0;
+    let mut pos = 0;
+    while let Some(hw1) = halfword(code, pos) {
+        let wide = is_wide(hw1);
+        let hw2 = if wide {
+            match halfword(code, pos + 2) {
+                Some(hw2) => hw2,
+                None => break,
+            }
+        } else {
+            0
+        };
+
+        let reserved = if wide {
+            match (hw1, hw2) {
+                // PUSH.W {reglist}
+                (0xE92D, _) => Some(u64::from((hw2 & 0x5FFF).count_ones()) * 4),
+                // STR.W Rt, [SP, #-4]!
+                (0xF84D, _) if hw2 & 0x0FFF == 0x0D04 => Some(4),
+                // VPUSH {d/s regs}
+                _ if hw1 & 0xFFBF == 0xED2D && hw2 & 0x0E00 == 0x0A00 => {
+                    Some(u64::from(hw2 & 0xFF) * 4)
+                }
+                // SUB.W SP, SP, #const
+                _ if hw1 & 0xFBEF == 0xF1AD && hw2 & 0x8F00 == 0x0D00 => {
+                    Some(u64::from(expand_imm(imm12(hw1, hw2))))
+                }
+                // SUBW SP, SP, #imm12
+                _ if hw1 & 0xFBFF == 0xF2AD && hw2 & 0x8F00 == 0x0D00 => {
+                    Some(u64::from(imm12(hw1, hw2)))
+                }
+                _ => None,
+            }
+        } else if hw1 & 0xFE00 == 0xB400 {
+            // PUSH {reglist[, lr]}
+            Some(u64::from((hw1 & 0x1FF).count_ones()) * 4)
+        } else if hw1 & 0xFF80 == 0xB080 {
+            // SUB SP, SP, #imm7
+            Some(u64::from(hw1 & 0x7F) * 4)
+        } else if hw1 & 0xFF00 == 0xAF00 || hw1 == 0x466F {
+            // ADD R7, SP, #imm / MOV R7, SP
+            Some(0)
+        } else {
+            None
+        };
+
+        match reserved {
+            Some(reserved) => bytes += reserved,
+            None => break,
+        }
+        pos += if wide { 4 } else { 2 };
+    }
+    bytes
+}
+
+/// `i:imm3:imm8` of a 32-bit data-processing immediate
+fn imm12(hw1: u16, hw2: u16) -> u32 {
+    u32::from((hw1 >> 10) & 1) << 11 | u32::from((hw2 >> 12) & 7) << 8 | u32::from(hw2 & 0xFF)
+}
+
+/// `ThumbExpandImm` for the modified immediate constants
+fn expand_imm(imm12: u32) -> u32 {
+    let imm8 = imm12 & 0xFF;
+    if imm12 >> 10 == 0 {
+        match (imm12 >> 8) & 3 {
+            0 => imm8,
+            1 => imm8 << 16 | imm8,
+            2 => imm8 << 24 | imm8 << 8,
+            _ => imm8 << 24 | imm8 << 16 | imm8 << 8 | imm8,
+        }
+    } else {
+        (0x80 | (imm12 & 0x7F)).rotate_right(imm12 >> 7)
+    }
+}
+
+/// Branch targets are relative to the instruction address plus 4
+fn target(pc: u64, offset: i64) -> u64 {
+    (pc as i64 + 4 + offset) as u64
+}
+
+fn sign_extend(value: u64, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    ((value << shift) as i64) >> shift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use firmware_elf::test_support::thumb as code;
+
+    #[test]
+    fn calls_and_wide_branches() {
+        assert_eq!(decode(0x100, 0xF000, 0xF87E), Some(Flow::Call(0x200)));
+        assert_eq!(decode(0x200, 0xF7FF, 0xFF7E), Some(Flow::Call(0x100)));
+        // B.W
+        assert_eq!(decode(0x100, 0xF000, 0xB87E), Some(Flow::Branch(0x200)));
+        assert_eq!(decode(0x52, 0xF7FF, 0xBFF5), Some(Flow::Branch(0x40)));
+        // BEQ.W
+        assert_eq!(decode(0x100, 0xF000, 0x801E), Some(Flow::Branch(0x140)));
+    }
+
+    #[test]
+    fn narrow_branches() {
+        assert_eq!(decode(0x100, 0xE006, 0), Some(Flow::Branch(0x110)));
+        // `b .`
+        assert_eq!(decode(0x100, 0xE7FE, 0), Some(Flow::Branch(0x100)));
+        assert_eq!(decode(0x100, 0xD004, 0), Some(Flow::Branch(0x10C)));
+        // UDF and SVC share the B<c> encoding
+        assert_eq!(decode(0x100, 0xDE00, 0), None);
+        assert_eq!(decode(0x100, 0xDF00, 0), None);
+    }
+
+    #[test]
+    fn indirect_flow() {
+        // BLX r3, BX r3, MOV pc, r3
+        assert_eq!(decode(0, 0x4798, 0), Some(Flow::IndirectCall));
+        assert_eq!(decode(0, 0x4718, 0), Some(Flow::IndirectJump));
+        assert_eq!(decode(0, 0x469F, 0), Some(Flow::IndirectJump));
+        // BX lr returns
+        assert_eq!(decode(0, 0x4770, 0), None);
+        // LDR.W pc, [r0, #4] and LDR pc, [r1, r2]
+        assert_eq!(decode(0, 0xF8D0, 0xF004), Some(Flow::IndirectJump));
+        assert_eq!(decode(0, 0xF851, 0xF002), Some(Flow::IndirectJump));
+        // LDR pc, [sp], #4 is POP {pc}
+        assert_eq!(decode(0, 0xF85D, 0xFB04), None);
+        // NOP, MOV r4, r0
+        assert_eq!(decode(0, 0xBF00, 0), None);
+        assert_eq!(decode(0, 0x4604, 0), None);
+    }
+
+    #[test]
+    fn prologue_frames() {
+        // PUSH {r4-r7, lr}; ADD r7, sp, #12; PUSH.W {r4-r11, lr}; SUB sp, #8;
+        // MOV r4, r0; SUB sp, #8
+        let frame = prologue(&code(&[
+            0xB5F0, 0xAF03, 0xE92D, 0x4FF0, 0xB082, 0x4604, 0xB082,
+        ]));
+        assert_eq!(frame, 20 + 36 + 8);
+        // STR.W r11, [sp, #-4]!; VPUSH {d8-d9}
+        assert_eq!(prologue(&code(&[0xF84D, 0xBD04, 0xED2D, 0x8B04])), 4 + 16);
+        // SUB.W sp, sp, #0x48; SUB.W sp, sp, #0x100; SUBW sp, sp, #0x404
+        assert_eq!(prologue(&code(&[0xF1AD, 0x0D48])), 0x48);
+        assert_eq!(prologue(&code(&[0xF5AD, 0x7D80])), 0x100);
+        assert_eq!(prologue(&code(&[0xF2AD, 0x4D04])), 0x404);
+        // MOV r7, sp; a truncated PUSH.W
+        assert_eq!(prologue(&code(&[0x466F, 0xE92D])), 0);
+        assert_eq!(prologue(&[]), 0);
+    }
+
+    #[test]
+    fn modified_immediates() {
+        assert_eq!(expand_imm(0x0AB), 0xAB);
+        assert_eq!(expand_imm(0x1AB), 0x00AB_00AB);
+        assert_eq!(expand_imm(0x2AB), 0xAB00_AB00);
+        assert_eq!(expand_imm(0x3AB), 0xABAB_ABAB);
+        assert_eq!(expand_imm(0xF80), 0x100);
+        assert_eq!(imm12(0xF5AD, 0x7D80), 0xF80);
+    }
+}
@@ -0,0 +1,100 @@
+//! Build-script support for the STM32F3 firmware crates
+//!
+//! Called from each firmware crate's `build.rs` to export build metadata
+//! as compile-time environment variables, which `stm32f3_common::image_header!`
+//! bakes into the firmware image header:
+//!
+//! | Variable | Contents |
+//! |----------|----------|
+//! | `BUILD_GIT_COMMIT` | Full commit hash of `HEAD`, or `unknown` |
+//! | `BUILD_GIT_DIRTY` | `1` if tracked files have uncommitted changes, else `0` |
+//! | `BUILD_PROFILE` | Cargo profile (`debug` or `release`) |
+//! | `BUILD_TIMESTAMP` | Unix time of the build (`SOURCE_DATE_EPOCH` if set) |
+//!
+//! `CARGO_PKG_NAME` and `CARGO_PKG_VERSION` are provided by cargo itself.
+//!
+//! The script re-runs when git's `HEAD`, refs or index change, and when the
+//! crate's (or `stm32f3-common`'s and `frame-codec`'s) `src/` or
+//! `Cargo.toml` is edited, so the dirty flag and timestamp follow
+//! uncommitted edits too.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Crates every firmware crate builds in, relative to its manifest
+/// directory; edits there also make the build dirty
+const SHARED_CRATES: &[&str] = &[
+    "../stm32f3-common",
+    "../host/frame-codec",
+    "../host/timer-wheel",
+];
+
+/// Emit the `BUILD_*` variables and the rerun triggers for them
+pub fn emit_build_info() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let dir = Path::new(&manifest_dir);
+
+    let commit = git(dir, &["rev-parse", "HEAD"]).unwrap_or_else(|| "unknown".into());
+    let dirty = git(dir, &["status", "--porcelain", "--untracked-files=no"])
+        .map(|status| !status.is_empty())
+        .unwrap_or(false);
+    let profile = env::var("PROFILE").unwrap_or_else(|_| "unknown".into());
+
+    println!("cargo:rustc-env=BUILD_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=BUILD_GIT_DIRTY={}", u8::from(dirty));
+    println!("cargo:rustc-env=BUILD_PROFILE={}", profile);
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", timestamp());
+
+    // Re-run when HEAD moves, a branch is committed to or the index changes
+    for path in ["HEAD", "index", "packed-refs"] {
+        if let Some(p) = git(dir, &["rev-parse", "--git-path", path]) {
+            println!("cargo:rerun-if-changed={}", dir.join(p).display());
+        }
+    }
+    if let Some(head_ref) = git(dir, &["symbolic-ref", "-q", "HEAD"]) {
+        if let Some(p) = git(dir, &["rev-parse", "--git-path", &head_ref]) {
+            println!("cargo:rerun-if-changed={}", dir.join(p).display());
+        }
+    }
+    // An edit to the sources does not touch the index, but changes what
+    // `BUILD_GIT_DIRTY` and `BUILD_TIMESTAMP` should say
+    for crate_dir in std::iter::once(dir.to_path_buf())
+        .chain(SHARED_CRATES.iter().map(|shared| dir.join(shared)))
+    {
+        for path in ["src", "Cargo.toml"] {
+            let path = crate_dir.join(path);
+            if path.exists() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+    }
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+}
+
+/// Run a git command in `dir`, returning trimmed stdout on success
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Build time in Unix seconds, honouring reproducible-build overrides
+fn timestamp() -> u64 {
+    env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        })
+}
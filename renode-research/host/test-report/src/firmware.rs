@@ -0,0 +1,215 @@
+//! Result records printed by the firmware on USART1
+//!
+//! Every peripheral binary reports its checks in the same shape:
+//!
+//! ```text
+//! Test 1: Memory-to-Memory Transfer
+//! Data verified: PASS
+//!
+//! === Test Summary ===
+//! Passed: 3
+//! Failed: 0
+//! DMA TEST PASSED
+//! ```
+//!
+//! Each `<check>: PASS` or `<check>: FAIL` line becomes a test case, named
+//! after its `Test N:` section (also `--- Test N: ... ---`) when there is
+//! one. A transcript that stops
+//! before `=== Test Summary ===` gets an extra failing case, since the
+//! firmware hung or crashed before reporting everything.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{Error, Status, Suite, TestCase};
+
+/// Line that starts the firmware's summary block
+pub const SUMMARY_MARKER: &str = "=== Test Summary ===";
+
+/// Parse a UART transcript for `peripheral`
+pub fn parse(peripheral: &str, source: &str, lines: &[String]) -> Suite {
+    let mut tests = Vec::new();
+    let mut section: Option<String> = None;
+    // Transcript lines since the last record, attached to failures
+    let mut context_start = 0;
+    let mut summary_seen = false;
+
+    for (index, raw) in lines.iter().enumerate() {
+        let line = raw.trim();
+        if line == SUMMARY_MARKER {
+            summary_seen = true;
+            continue;
+        }
+        if summary_seen {
+            continue;
+        }
+
+        if let Some(title) = section_title(line) {
+            section = Some(title.to_string());
+            context_start = index;
+            continue;
+        }
+
+        let (check, passed) = if let Some(check) = line.strip_suffix(": PASS") {
+            (check, true)
+        } else if let Some(check) = line.strip_suffix(": FAIL") {
+            (check, false)
+        } else {
+            continue;
+        };
+
+        let name = match &section {
+            Some(title) => format!("{} - {}", title, check),
+            None => check.to_string(),
+        };
+        let (status, transcript) = if passed {
+            (Status::Passed, Vec::new())
+        } else {
+            (
+                Status::Failed(line.to_string()),
+                lines[context_start..=index].to_vec(),
+            )
+        };
+        tests.push(TestCase {
+            name,
+            status,
+            duration: Duration::ZERO,
+            transcript,
+        });
+        context_start = index + 1;
+    }
+
+    if !summary_seen {
+        tests.push(TestCase {
+            name: "Test Summary".into(),
+            status: Status::Failed(format!("no \"{}\" line on the UART", SUMMARY_MARKER)),
+            duration: Duration::ZERO,
+            transcript: lines.to_vec(),
+        });
+    }
+
+    Suite {
+        name: peripheral.to_string(),
+        source: source.to_string(),
+        duration: Duration::ZERO,
+        tests,
+    }
+}
+
+/// Parse a saved UART log; the peripheral is taken from the path
+pub fn load(path: &Path) -> Result<Suite, Error> {
+    let text = fs::read_to_string(path)?;
+    let lines: Vec<String> = text
+        .lines()
+        .map(|l| l.trim_end_matches('\r').into())
+        .collect();
+    Ok(parse(
+        &crate::peripheral_name(path),
+        &path.display().to_string(),
+        &lines,
+    ))
+}
+
+/// The `Test <n>: <title>` part of a section heading, which some binaries
+/// print between dashes (`--- Test 1: DAC Channel 1 ---`)
+fn section_title(line: &str) -> Option<&str> {
+    let heading = line
+        .strip_prefix("--- ")
+        .and_then(|rest| rest.strip_suffix(" ---"))
+        .unwrap_or(line);
+    let rest = heading.strip_prefix("Test ")?;
+    let (number, title) = rest.split_once(": ")?;
+    (number.chars().all(|c| c.is_ascii_digit()) && !title.is_empty()).then_some(heading)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn names_checks_after_their_section() {
+        let transcript = lines(
+            "DMA Peripheral Test\n\
+             \n\
+             --- Test 1: Memory-to-Memory Transfer ---\n\
+             Transfer complete\n\
+             Data verified: PASS\n\
+             Test 2: Circular Mode\n\
+             Half transfer: PASS\n\
+             Count mismatch: 3 != 4\n\
+             Full transfer: FAIL\n\
+             \n\
+             === Test Summary ===\n\
+             Late: FAIL\n",
+        );
+        let suite = parse("dma", "uart.log", &transcript);
+        assert_eq!(suite.name, "dma");
+        let names: Vec<&str> = suite.tests.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Test 1: Memory-to-Memory Transfer - Data verified",
+                "Test 2: Circular Mode - Half transfer",
+                "Test 2: Circular Mode - Full transfer",
+            ]
+        );
+        assert_eq!(suite.passed(), 2);
+        let failed = &suite.tests[2];
+        assert_eq!(failed.status, Status::Failed("Full transfer: FAIL".into()));
+        // The lines since the previous record
+        assert_eq!(
+            failed.transcript,
+            ["Count mismatch: 3 != 4", "Full transfer: FAIL"]
+        );
+    }
+
+    #[test]
+    fn a_missing_summary_fails() {
+        let transcript = lines("Test 1: Clock\nClock enabled: PASS\n*** FAULT: HardFault");
+        let suite = parse("rtc", "uart.log", &transcript);
+        assert_eq!(suite.tests.len(), 2);
+        let summary = &suite.tests[1];
+        assert_eq!(summary.name, "Test Summary");
+        assert_eq!(
+            summary.status,
+            Status::Failed("no \"=== Test Summary ===\" line on the UART".into())
+        );
+        assert_eq!(summary.transcript, transcript);
+    }
+
+    #[test]
+    fn reads_a_recorded_transcript() {
+        let transcript = lines(include_str!("../../../stm32f3-dac/tests/uart.golden"));
+        let suite = parse("dac", "uart.golden", &transcript);
+        let names: Vec<&str> = suite.tests.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Test 1: DAC Channel 1 - DAC Channel 1",
+                "Test 2: DAC Channel 2 - DAC Channel 2",
+                "Test 3: DAC Value Range - DAC Value Range",
+            ]
+        );
+        assert_eq!(suite.passed(), 3);
+    }
+
+    #[test]
+    fn section_titles() {
+        assert_eq!(
+            section_title("Test 12: DAC Range"),
+            Some("Test 12: DAC Range")
+        );
+        assert_eq!(
+            section_title("--- Test 1: DAC Channel 1 ---"),
+            Some("Test 1: DAC Channel 1")
+        );
+        assert_eq!(section_title("Test A: DAC"), None);
+        assert_eq!(section_title("Test 1: "), None);
+        assert_eq!(section_title("Tests passed: 03"), None);
+    }
+}
@@ -0,0 +1,187 @@
+//! Static HTML summary
+//!
+//! A single self-contained page: totals, one row per peripheral with
+//! pass/fail counts and duration, then the details and UART transcript of
+//! every failing test.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::{escape, Report, Status};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 4px 10px; text-align: left; }
+td.num { text-align: right; }
+.pass { color: #1a7f37; }
+.fail { color: #cf222e; font-weight: bold; }
+.skip { color: #9a6700; }
+pre { background: #f6f8fa; padding: 8px; overflow-x: auto; }
+";
+
+/// Render `report` as an HTML page
+pub fn write(report: &Report) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>Firmware Test Report</title>\n");
+    let _ = writeln!(out, "<style>\n{}</style>\n</head>\n<body>", STYLE);
+
+    out.push_str("<h1>Firmware Test Report</h1>\n");
+    let (class, result) = if report.failed() == 0 {
+        ("pass", "PASSED")
+    } else {
+        ("fail", "FAILED")
+    };
+    let _ = writeln!(
+        out,
+        "<p><span class=\"{}\">{}</span>: {} tests, {} passed, {} failed, {} skipped in {}</p>",
+        class,
+        result,
+        report.tests(),
+        report.passed(),
+        report.failed(),
+        report.skipped(),
+        seconds(report.duration())
+    );
+
+    out.push_str("<table>\n<tr><th>Peripheral</th><th>Result</th><th>Passed</th>");
+    out.push_str("<th>Failed</th><th>Skipped</th><th>Duration</th><th>Source</th></tr>\n");
+    for suite in &report.suites {
+        let (class, result) = if suite.failed() > 0 {
+            ("fail", "FAIL")
+        } else {
+            ("pass", "PASS")
+        };
+        let _ = writeln!(
+            out,
+            "<tr><td><a href=\"#{name}\">{name}</a></td><td class=\"{}\">{}</td>\
+             <td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td>\
+             <td class=\"num\">{}</td><td>{}</td></tr>",
+            class,
+            result,
+            suite.passed(),
+            suite.failed(),
+            suite.skipped(),
+            seconds(suite.duration),
+            escape(&suite.source),
+            name = escape(&suite.name),
+        );
+    }
+    out.push_str("</table>\n");
+
+    for suite in &report.suites {
+        let _ = writeln!(
+            out,
+            "<h2 id=\"{name}\">{name}</h2>\n<table>\n<tr><th>Test</th><th>Result</th><th>Duration</th></tr>",
+            name = escape(&suite.name)
+        );
+        for test in &suite.tests {
+            let (class, result) = match test.status {
+                Status::Passed => ("pass", "PASS"),
+                Status::Failed(_) => ("fail", "FAIL"),
+                Status::Skipped => ("skip", "SKIP"),
+            };
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td class=\"{}\">{}</td><td class=\"num\">{}</td></tr>",
+                escape(&test.name),
+                class,
+                result,
+                seconds(test.duration)
+            );
+        }
+        out.push_str("</table>\n");
+
+        for test in &suite.tests {
+            if let Status::Failed(message) = &test.status {
+                let _ = writeln!(
+                    out,
+                    "<h3 class=\"fail\">{}</h3>\n<p>{}</p>",
+                    escape(&test.name),
+                    escape(message)
+                );
+                if !test.transcript.is_empty() {
+                    let _ = writeln!(out, "<pre>{}</pre>", escape(&test.transcript.join("\n")));
+                }
+            }
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.2}s", duration.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Suite, TestCase};
+
+    fn case(name: &str, status: Status, transcript: &[&str]) -> TestCase {
+        TestCase {
+            name: name.into(),
+            status,
+            duration: Duration::from_millis(1250),
+            transcript: transcript.iter().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    fn suite(name: &str, tests: Vec<TestCase>) -> Suite {
+        Suite {
+            name: name.into(),
+            source: "renode-runner".into(),
+            duration: Duration::from_millis(2500),
+            tests,
+        }
+    }
+
+    #[test]
+    fn summarises_and_details_failures() {
+        let mut report = Report::default();
+        report.push(suite(
+            "adc",
+            vec![case("Reads <vref>", Status::Passed, &["ignored"])],
+        ));
+        report.push(suite(
+            "dma",
+            vec![
+                case("Circular", Status::Skipped, &[]),
+                case(
+                    "Full transfer",
+                    Status::Failed("expected \"OK\"".into()),
+                    &["Count & size", "FAIL"],
+                ),
+            ],
+        ));
+        let html = write(&report);
+        assert!(html.contains(
+            "<span class=\"fail\">FAILED</span>: 3 tests, 1 passed, 1 failed, 1 skipped in 5.00s"
+        ));
+        assert!(html.contains("<tr><td><a href=\"#dma\">dma</a></td><td class=\"fail\">FAIL</td>"));
+        assert!(html.contains("<tr><td><a href=\"#adc\">adc</a></td><td class=\"pass\">PASS</td>"));
+        assert!(html.contains(
+            "<td>Reads &lt;vref&gt;</td><td class=\"pass\">PASS</td><td class=\"num\">1.25s</td>"
+        ));
+        assert!(html.contains("<td class=\"skip\">SKIP</td>"));
+        assert!(
+            html.contains("<h3 class=\"fail\">Full transfer</h3>\n<p>expected &quot;OK&quot;</p>")
+        );
+        assert!(html.contains("<pre>Count &amp; size\nFAIL</pre>"));
+        // Transcripts of passing tests are not shown
+        assert!(!html.contains("ignored"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn empty_report_passes() {
+        let html = write(&Report::default());
+        assert!(html.contains(
+            "<span class=\"pass\">PASSED</span>: 0 tests, 0 passed, 0 failed, 0 skipped in 0.00s"
+        ));
+        assert!(!html.contains("<h2"));
+    }
+}
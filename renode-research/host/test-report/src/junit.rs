@@ -0,0 +1,151 @@
+//! JUnit XML output
+//!
+//! One `<testsuite>` per peripheral, with the peripheral name as each
+//! test case's `classname`. Failure transcripts go in `<system-out>`, which
+//! CI systems show next to the failure.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::{escape, Report, Status};
+
+/// Render `report` as a JUnit XML document
+pub fn write(report: &Report) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<testsuites name=\"renode-research\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">",
+        report.tests(),
+        report.failed(),
+        report.skipped(),
+        seconds(report.duration())
+    );
+
+    for suite in &report.suites {
+        let _ = writeln!(
+            out,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">",
+            escape(&suite.name),
+            suite.tests.len(),
+            suite.failed(),
+            suite.skipped(),
+            seconds(suite.duration)
+        );
+        let _ = writeln!(
+            out,
+            "    <properties><property name=\"source\" value=\"{}\"/></properties>",
+            escape(&suite.source)
+        );
+
+        for test in &suite.tests {
+            let _ = write!(
+                out,
+                "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+                escape(&test.name),
+                escape(&suite.name),
+                seconds(test.duration)
+            );
+            match &test.status {
+                Status::Passed => out.push_str("/>\n"),
+                Status::Skipped => out.push_str(">\n      <skipped/>\n    </testcase>\n"),
+                Status::Failed(message) => {
+                    out.push_str(">\n");
+                    let _ = writeln!(
+                        out,
+                        "      <failure message=\"{}\"/>",
+                        escape(first_line(message))
+                    );
+                    if !test.transcript.is_empty() {
+                        let _ = writeln!(
+                            out,
+                            "      <system-out>{}</system-out>",
+                            escape(&test.transcript.join("\n"))
+                        );
+                    }
+                    out.push_str("    </testcase>\n");
+                }
+            }
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>\n");
+    out
+}
+
+fn seconds(duration: Duration) -> String {
+    format!("{:.3}", duration.as_secs_f64())
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Suite, TestCase};
+
+    fn case(name: &str, status: Status, transcript: &[&str]) -> TestCase {
+        TestCase {
+            name: name.into(),
+            status,
+            duration: Duration::from_millis(1250),
+            transcript: transcript.iter().map(|line| line.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn writes_well_formed_junit() {
+        let mut report = Report::default();
+        report.push(Suite {
+            name: "dma".into(),
+            source: "stm32f3-dma/tests/uart.log".into(),
+            duration: Duration::from_millis(2500),
+            tests: vec![
+                case("Data verified", Status::Passed, &[]),
+                case("Circular <mode>", Status::Skipped, &[]),
+                case(
+                    "Full transfer",
+                    Status::Failed("Full transfer: FAIL\nmore".into()),
+                    &["Count & size", "\x1b[31mFull transfer: FAIL"],
+                ),
+            ],
+        });
+        let xml = write(&report);
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let root = doc.root_element();
+        assert_eq!(root.attribute("tests"), Some("3"));
+        assert_eq!(root.attribute("failures"), Some("1"));
+        assert_eq!(root.attribute("skipped"), Some("1"));
+        assert_eq!(root.attribute("time"), Some("2.500"));
+
+        let cases: Vec<_> = root
+            .descendants()
+            .filter(|n| n.has_tag_name("testcase"))
+            .collect();
+        assert_eq!(cases.len(), 3);
+        assert_eq!(cases[0].attribute("classname"), Some("dma"));
+        assert_eq!(cases[0].attribute("time"), Some("1.250"));
+        assert_eq!(cases[1].attribute("name"), Some("Circular <mode>"));
+        assert!(cases[1].children().any(|n| n.has_tag_name("skipped")));
+
+        let failure = cases[2]
+            .children()
+            .find(|n| n.has_tag_name("failure"))
+            .unwrap();
+        assert_eq!(failure.attribute("message"), Some("Full transfer: FAIL"));
+        let out = cases[2]
+            .children()
+            .find(|n| n.has_tag_name("system-out"))
+            .unwrap();
+        assert_eq!(out.text(), Some("Count & size\n?[31mFull transfer: FAIL"));
+    }
+
+    #[test]
+    fn empty_report() {
+        let xml = write(&Report::default());
+        assert!(xml.contains("<testsuites name=\"renode-research\" tests=\"0\" failures=\"0\" skipped=\"0\" time=\"0.000\">"));
+        roxmltree::Document::parse(&xml).unwrap();
+    }
+}
@@ -0,0 +1,235 @@
+//! Merge firmware test results into JUnit XML and an HTML summary
+//!
+//! Results come from three places:
+//!
+//! - Robot Framework's `robot_output.xml` ([`robot`])
+//! - the `<check>: PASS` / `<check>: FAIL` records the firmware prints on
+//!   USART1 ([`firmware`])
+//! - `renode-runner`, which builds [`Suite`]s directly
+//!
+//! All of them are collected into one [`Report`], with one [`Suite`] per
+//! peripheral, and written out with [`junit::write`] and [`html::write`].
+
+use std::fmt;
+use std::path::Path;
+use std::time::Duration;
+
+pub mod firmware;
+pub mod html;
+pub mod junit;
+pub mod robot;
+
+/// Errors reading result files
+#[derive(Debug)]
+pub enum Error {
+    /// The file could not be read
+    Io(std::io::Error),
+    /// The file is not well-formed XML
+    Xml(roxmltree::Error),
+    /// The XML is not Robot output
+    Format(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "read failed: {}", err),
+            Error::Xml(err) => write!(f, "invalid XML: {}", err),
+            Error::Format(msg) => write!(f, "unexpected format: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(err: roxmltree::Error) -> Self {
+        Error::Xml(err)
+    }
+}
+
+/// Outcome of one test case
+#[derive(Clone, Debug, PartialEq)]
+pub enum Status {
+    Passed,
+    /// Failed with a message
+    Failed(String),
+    Skipped,
+}
+
+/// One test case
+#[derive(Clone, Debug)]
+pub struct TestCase {
+    pub name: String,
+    pub status: Status,
+    pub duration: Duration,
+    /// UART output (or log messages) relevant to the result
+    pub transcript: Vec<String>,
+}
+
+/// All results for one peripheral
+#[derive(Clone, Debug)]
+pub struct Suite {
+    /// Peripheral name, e.g. `dma`
+    pub name: String,
+    /// Where the results came from (file path or tool name)
+    pub source: String,
+    pub duration: Duration,
+    pub tests: Vec<TestCase>,
+}
+
+impl Suite {
+    pub fn passed(&self) -> usize {
+        self.count(|s| *s == Status::Passed)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|s| matches!(s, Status::Failed(_)))
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.count(|s| *s == Status::Skipped)
+    }
+
+    fn count(&self, f: impl Fn(&Status) -> bool) -> usize {
+        self.tests.iter().filter(|t| f(&t.status)).count()
+    }
+}
+
+/// Merged results across peripherals
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub suites: Vec<Suite>,
+}
+
+impl Report {
+    /// Add a suite, keeping suites ordered by peripheral name
+    pub fn push(&mut self, suite: Suite) {
+        let index = self.suites.partition_point(|s| s.name <= suite.name);
+        self.suites.insert(index, suite);
+    }
+
+    pub fn tests(&self) -> usize {
+        self.suites.iter().map(|s| s.tests.len()).sum()
+    }
+
+    pub fn passed(&self) -> usize {
+        self.suites.iter().map(Suite::passed).sum()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.suites.iter().map(Suite::failed).sum()
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.suites.iter().map(Suite::skipped).sum()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.suites.iter().map(|s| s.duration).sum()
+    }
+}
+
+/// Peripheral name for a result file or suite path
+///
+/// Uses the `stm32f3-<name>` crate directory if the path has one
+/// (`stm32f3-dma/tests/test-dma.robot` -> `dma`), otherwise the file stem
+/// without a `test-` prefix.
+pub fn peripheral_name(path: &Path) -> String {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let parents = path.parent().into_iter().flat_map(|p| p.components().rev());
+    for name in std::iter::once(stem.as_str()).chain(parents.filter_map(|c| c.as_os_str().to_str()))
+    {
+        if let Some(peripheral) = name.strip_prefix("stm32f3-") {
+            return peripheral.to_string();
+        }
+    }
+    stem.strip_prefix("test-").unwrap_or(&stem).to_string()
+}
+
+/// Escape text for XML/HTML content and attribute values
+///
+/// Control characters that XML 1.0 cannot represent (stray bytes from a
+/// UART) are replaced with `?`.
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 => out.push('?'),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suite(name: &str, statuses: &[Status]) -> Suite {
+        Suite {
+            name: name.into(),
+            source: "test".into(),
+            duration: Duration::from_millis(500),
+            tests: statuses
+                .iter()
+                .enumerate()
+                .map(|(index, status)| TestCase {
+                    name: format!("case {}", index),
+                    status: status.clone(),
+                    duration: Duration::ZERO,
+                    transcript: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn peripheral_names() {
+        let name = |path: &str| peripheral_name(Path::new(path));
+        assert_eq!(name("stm32f3-dma/tests/test-dma.robot"), "dma");
+        assert_eq!(name("/ci/stm32f3-i2c/results/output.xml"), "i2c");
+        assert_eq!(name("logs/stm32f3-rtc.log"), "rtc");
+        assert_eq!(name("logs/test-adc.robot"), "adc");
+        assert_eq!(name("uart.log"), "uart");
+    }
+
+    #[test]
+    fn escapes_markup_and_control_bytes() {
+        assert_eq!(
+            escape("<a href=\"x\">&'</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;"
+        );
+        assert_eq!(escape("ok\t\r\n\x00\x1b[0m"), "ok\t\r\n??[0m");
+    }
+
+    #[test]
+    fn report_totals_and_order() {
+        let mut report = Report::default();
+        report.push(suite("uart", &[Status::Passed]));
+        report.push(suite("adc", &[Status::Passed, Status::Failed("x".into())]));
+        report.push(suite("dma", &[Status::Skipped, Status::Passed]));
+        let names: Vec<&str> = report.suites.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["adc", "dma", "uart"]);
+        assert_eq!(report.tests(), 5);
+        assert_eq!(report.passed(), 3);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.skipped(), 1);
+        assert_eq!(report.duration(), Duration::from_millis(1500));
+    }
+}
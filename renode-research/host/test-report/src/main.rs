@@ -0,0 +1,142 @@
+//! Merge firmware test results into one JUnit XML file and HTML page
+//!
+//! Usage: `test-report [--robot <xml>]... [--uart [<peripheral>=]<log>]...`
+//!
+//! With no inputs, every `stm32f3-*/robot_output.xml` under `--root` is
+//! read. Exits non-zero if any test failed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use test_report::{firmware, html, junit, robot, Report};
+
+#[derive(Parser)]
+#[command(about = "Merge firmware test results into JUnit XML and an HTML summary")]
+struct Args {
+    /// Robot Framework output files
+    #[arg(long)]
+    robot: Vec<PathBuf>,
+
+    /// Saved UART transcripts with firmware PASS/FAIL records,
+    /// as `<peripheral>=<file>` or a path containing `stm32f3-<peripheral>`
+    #[arg(long)]
+    uart: Vec<String>,
+
+    /// Firmware workspace root, searched when no inputs are given
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))]
+    root: PathBuf,
+
+    /// JUnit XML output [default: <root>/target/test-report/junit.xml]
+    #[arg(long)]
+    junit: Option<PathBuf>,
+
+    /// HTML output [default: <root>/target/test-report/index.html]
+    #[arg(long)]
+    html: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let mut args = Args::parse();
+    args.root = args.root.canonicalize().unwrap_or(args.root);
+
+    let mut robot_files = args.robot.clone();
+    if robot_files.is_empty() && args.uart.is_empty() {
+        robot_files = discover(&args.root);
+        if robot_files.is_empty() {
+            eprintln!("no robot_output.xml found under {}", args.root.display());
+            return ExitCode::from(2);
+        }
+    }
+
+    let mut report = Report::default();
+    for path in &robot_files {
+        match robot::load(path) {
+            Ok(suites) => suites.into_iter().for_each(|s| report.push(s)),
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                return ExitCode::from(2);
+            }
+        }
+    }
+    for spec in &args.uart {
+        let result = match spec.split_once('=') {
+            Some((name, path)) => firmware::load(Path::new(path)).map(|mut suite| {
+                suite.name = name.to_string();
+                suite
+            }),
+            None => firmware::load(Path::new(spec)),
+        };
+        match result {
+            Ok(suite) => report.push(suite),
+            Err(err) => {
+                eprintln!("{}: {}", spec, err);
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    let out_dir = args.root.join("target").join("test-report");
+    let junit_path = args.junit.unwrap_or_else(|| out_dir.join("junit.xml"));
+    let html_path = args.html.unwrap_or_else(|| out_dir.join("index.html"));
+    for (path, content) in [
+        (&junit_path, junit::write(&report)),
+        (&html_path, html::write(&report)),
+    ] {
+        if let Err(err) = write_file(path, &content) {
+            eprintln!("{}: {}", path.display(), err);
+            return ExitCode::from(2);
+        }
+    }
+
+    for suite in &report.suites {
+        println!(
+            "{:<8} {:>3} passed {:>3} failed",
+            suite.name,
+            suite.passed(),
+            suite.failed()
+        );
+    }
+    println!(
+        "Total: {} passed, {} failed, {} skipped",
+        report.passed(),
+        report.failed(),
+        report.skipped()
+    );
+    println!("JUnit: {}", junit_path.display());
+    println!("HTML:  {}", html_path.display());
+
+    if report.failed() == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// `<root>/stm32f3-*/robot_output.xml`, sorted
+fn discover(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("stm32f3-"))
+        })
+        .map(|dir| dir.join("robot_output.xml"))
+        .filter(|file| file.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+fn write_file(path: &Path, content: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, content)
+}
@@ -0,0 +1,266 @@
+//! Robot Framework `robot_output.xml` reader
+//!
+//! Each `<suite>` with `<test>` children becomes one [`Suite`], named after
+//! the peripheral crate in its `source` path. Durations come from the
+//! `starttime`/`endtime` attributes (Robot 6) or `elapsed` (Robot 7). For
+//! failing tests the keyword log messages are kept as the transcript, which
+//! includes the text `Wait For Line On Uart` was waiting for.
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use roxmltree::{Document, Node};
+
+use crate::{Error, Status, Suite, TestCase};
+
+/// Log levels worth showing for a failed test
+const TRANSCRIPT_LEVELS: [&str; 4] = ["INFO", "WARN", "ERROR", "FAIL"];
+
+/// Read a `robot_output.xml` file
+pub fn load(path: &Path) -> Result<Vec<Suite>, Error> {
+    let text = fs::read_to_string(path)?;
+    parse(&text, &path.display().to_string())
+}
+
+/// Parse `robot_output.xml` content; `source` names the file in the report
+pub fn parse(text: &str, source: &str) -> Result<Vec<Suite>, Error> {
+    let doc = Document::parse(text)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("robot") {
+        return Err(Error::Format(format!(
+            "root element is <{}>, expected <robot>",
+            root.tag_name().name()
+        )));
+    }
+
+    let mut suites = Vec::new();
+    for node in root.descendants().filter(|n| n.has_tag_name("suite")) {
+        let tests: Vec<TestCase> = children(node, "test").map(parse_test).collect();
+        if tests.is_empty() {
+            continue;
+        }
+        let name = match node.attribute("source") {
+            Some(path) => crate::peripheral_name(Path::new(path)),
+            None => node.attribute("name").unwrap_or("suite").to_string(),
+        };
+        suites.push(Suite {
+            name,
+            source: source.to_string(),
+            duration: status(node).map(elapsed).unwrap_or_default(),
+            tests,
+        });
+    }
+    Ok(suites)
+}
+
+fn parse_test(node: Node) -> TestCase {
+    let name = node.attribute("name").unwrap_or_default().to_string();
+    let (result, duration, message) = match status(node) {
+        Some(s) => (
+            s.attribute("status").unwrap_or("FAIL"),
+            elapsed(s),
+            s.text().unwrap_or_default().trim().to_string(),
+        ),
+        None => ("FAIL", Duration::ZERO, "no status".to_string()),
+    };
+
+    let (status, transcript) = match result {
+        "PASS" => (Status::Passed, Vec::new()),
+        "SKIP" | "NOT RUN" => (Status::Skipped, Vec::new()),
+        _ => (Status::Failed(message), messages(node)),
+    };
+    TestCase {
+        name,
+        status,
+        duration,
+        transcript,
+    }
+}
+
+/// Log messages below `node`, in document order
+fn messages(node: Node) -> Vec<String> {
+    node.descendants()
+        .filter(|n| n.has_tag_name("msg"))
+        .filter(|n| {
+            n.attribute("level")
+                .is_some_and(|level| TRANSCRIPT_LEVELS.contains(&level))
+        })
+        .filter_map(|n| n.text())
+        .flat_map(|text| text.lines().map(str::to_string).collect::<Vec<_>>())
+        .collect()
+}
+
+fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    tag: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.has_tag_name(tag))
+}
+
+/// The element's own `<status>` (not one of a nested keyword)
+fn status<'a, 'input>(node: Node<'a, 'input>) -> Option<Node<'a, 'input>> {
+    node.children().rev().find(|n| n.has_tag_name("status"))
+}
+
+fn elapsed(status: Node) -> Duration {
+    if let Some(seconds) = status
+        .attribute("elapsed")
+        .and_then(|s| s.parse::<f64>().ok())
+    {
+        return Duration::from_secs_f64(seconds.max(0.0));
+    }
+    match (
+        status.attribute("starttime").and_then(timestamp_ms),
+        status.attribute("endtime").and_then(timestamp_ms),
+    ) {
+        (Some(start), Some(end)) if end >= start => Duration::from_millis(end - start),
+        _ => Duration::ZERO,
+    }
+}
+
+/// Milliseconds since the epoch for Robot 6 timestamps (`20260127 20:44:16.362`)
+fn timestamp_ms(value: &str) -> Option<u64> {
+    let (date, time) = value.split_once(' ')?;
+    if date.len() != 8 {
+        return None;
+    }
+    let year: i64 = date[0..4].parse().ok()?;
+    let month: i64 = date[4..6].parse().ok()?;
+    let day: i64 = date[6..8].parse().ok()?;
+
+    let mut parts = time.split(':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(((days * 24 + hours) * 60 + minutes) * 60_000 + (seconds * 1000.0).round() as u64)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trimmed Robot 6 output: a passing and a failing test with keywords
+    const ROBOT6: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<robot generator="Robot 6.1.1 (Python 3.11.4 on linux)" generated="20260127 20:44:10.000">
+<suite id="s1" name="Tests">
+<suite id="s1-s1" name="Test-Dma" source="/work/stm32f3-dma/tests/test-dma.robot">
+<test id="s1-s1-t1" name="Should Transfer Memory To Memory">
+<kw name="Wait For Line On Uart">
+<msg timestamp="20260127 20:44:11.000" level="INFO">Data verified: PASS</msg>
+<status status="PASS" starttime="20260127 20:44:10.500" endtime="20260127 20:44:11.000"/>
+</kw>
+<status status="PASS" starttime="20260127 20:44:10.362" endtime="20260127 20:44:11.862"/>
+</test>
+<test id="s1-s1-t2" name="Should Report Summary">
+<kw name="Wait For Line On Uart">
+<msg timestamp="20260127 20:44:12.000" level="DEBUG">polling</msg>
+<msg timestamp="20260127 20:44:13.000" level="FAIL">Terminal tester failed!
+Expected: DMA TEST PASSED</msg>
+<status status="FAIL" starttime="20260127 20:44:12.000" endtime="20260127 20:44:13.000"/>
+</kw>
+<status status="FAIL" starttime="20260127 20:44:11.900" endtime="20260127 20:44:13.900">Terminal tester failed!
+Expected: DMA TEST PASSED</status>
+</test>
+<status status="FAIL" starttime="20260127 20:44:10.000" endtime="20260127 20:44:14.000"/>
+</suite>
+<status status="FAIL" starttime="20260127 20:44:10.000" endtime="20260127 20:44:14.000"/>
+</suite>
+</robot>
+"#;
+
+    /// Robot 7 writes `elapsed` seconds instead of start and end times
+    const ROBOT7: &str = r#"<robot generator="Robot 7.0">
+<suite name="Uart">
+<test name="Should Echo"><status status="PASS" start="2026-01-27T20:44:10.362" elapsed="0.250"/></test>
+<test name="Should Wait"><status status="SKIP" elapsed="0.000">Skipped</status></test>
+<test name="Never Run"><status status="NOT RUN" elapsed="0"/></test>
+<test name="No Status"/>
+<status status="PASS" elapsed="1.5"/>
+</suite>
+</robot>"#;
+
+    #[test]
+    fn reads_robot6_output() {
+        let suites = parse(ROBOT6, "robot_output.xml").unwrap();
+        assert_eq!(suites.len(), 1, "the outer suite has no tests of its own");
+        let suite = &suites[0];
+        assert_eq!(suite.name, "dma");
+        assert_eq!(suite.source, "robot_output.xml");
+        assert_eq!(suite.duration, Duration::from_secs(4));
+
+        let [pass, fail] = &suite.tests[..] else {
+            panic!("{:?}", suite.tests)
+        };
+        assert_eq!(pass.name, "Should Transfer Memory To Memory");
+        assert_eq!(pass.status, Status::Passed);
+        assert_eq!(pass.duration, Duration::from_millis(1500));
+        assert!(pass.transcript.is_empty());
+
+        assert_eq!(
+            fail.status,
+            Status::Failed("Terminal tester failed!\nExpected: DMA TEST PASSED".into())
+        );
+        assert_eq!(fail.duration, Duration::from_secs(2));
+        assert_eq!(
+            fail.transcript,
+            ["Terminal tester failed!", "Expected: DMA TEST PASSED"]
+        );
+    }
+
+    #[test]
+    fn reads_robot7_output() {
+        let suites = parse(ROBOT7, "out.xml").unwrap();
+        let suite = &suites[0];
+        assert_eq!(suite.name, "Uart", "no source, so the suite name");
+        assert_eq!(suite.duration, Duration::from_millis(1500));
+        let statuses: Vec<&Status> = suite.tests.iter().map(|t| &t.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                &Status::Passed,
+                &Status::Skipped,
+                &Status::Skipped,
+                &Status::Failed("no status".into()),
+            ]
+        );
+        assert_eq!(suite.tests[0].duration, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn rejects_other_documents() {
+        assert!(matches!(
+            parse("<testsuites/>", "x"),
+            Err(Error::Format(msg)) if msg == "root element is <testsuites>, expected <robot>"
+        ));
+        assert!(matches!(parse("<robot>", "x"), Err(Error::Xml(_))));
+    }
+
+    #[test]
+    fn robot6_timestamps() {
+        assert_eq!(timestamp_ms("19700101 00:00:00.000"), Some(0));
+        assert_eq!(timestamp_ms("19700102 00:00:01.5"), Some(86_401_500));
+        // 2000 and 2024 are leap years
+        assert_eq!(timestamp_ms("20000301 00:00:00.000"), Some(951_868_800_000));
+        assert_eq!(
+            timestamp_ms("20240229 12:00:00.000"),
+            Some(1_709_208_000_000)
+        );
+        assert_eq!(timestamp_ms("2024-02-29T12:00:00"), None);
+        assert_eq!(timestamp_ms("20240229 12:00"), None);
+        assert_eq!(timestamp_ms("19690101 00:00:00.000"), None);
+    }
+}
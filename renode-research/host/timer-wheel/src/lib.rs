@@ -0,0 +1,391 @@
+//! Hashed timer wheel behind `stm32f3-common`'s `soft_timer`
+//!
+//! The firmware keeps one [`TimerWheel`] in a critical-section mutex and
+//! calls [`TimerWheel::tick`] from the TIM7 interrupt; everything here is
+//! plain data, so the bucket and re-arm logic is tested on the host.
+//! `no_std` and allocation-free: timers live in a fixed array of
+//! [`MAX_TIMERS`] slots linked into [`WHEEL_SIZE`] buckets by deadline.
+
+#![no_std]
+
+/// Maximum number of timers that can be allocated at once
+pub const MAX_TIMERS: usize = 16;
+
+/// Number of wheel buckets (must be a power of two)
+pub const WHEEL_SIZE: usize = 32;
+const WHEEL_MASK: u32 = (WHEEL_SIZE as u32) - 1;
+
+/// Marker for an empty link in the bucket lists
+const NIL: u8 = 0xFF;
+
+/// Handle to an allocated timer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerId(u8);
+
+/// Errors returned by the timer wheel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// All `MAX_TIMERS` slots are allocated
+    NoFreeSlot,
+    /// The handle does not refer to an allocated timer
+    InvalidTimer,
+}
+
+/// Callback collected by [`TimerWheel::tick`] when a timer expires
+pub type Callback = fn();
+
+#[derive(Clone, Copy)]
+struct Slot {
+    allocated: bool,
+    armed: bool,
+    expired: bool,
+    deadline: u32,
+    interval: u32,
+    periodic: bool,
+    callback: Option<Callback>,
+    prev: u8,
+    next: u8,
+}
+
+impl Slot {
+    const EMPTY: Slot = Slot {
+        allocated: false,
+        armed: false,
+        expired: false,
+        deadline: 0,
+        interval: 0,
+        periodic: false,
+        callback: None,
+        prev: NIL,
+        next: NIL,
+    };
+}
+
+/// One-shot and periodic timers counted in ticks
+pub struct TimerWheel {
+    slots: [Slot; MAX_TIMERS],
+    buckets: [u8; WHEEL_SIZE],
+    now: u32,
+}
+
+impl Default for TimerWheel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimerWheel {
+    pub const fn new() -> Self {
+        Self {
+            slots: [Slot::EMPTY; MAX_TIMERS],
+            buckets: [NIL; WHEEL_SIZE],
+            now: 0,
+        }
+    }
+
+    /// Allocate a timer that expires `ticks` from now (at least one), and
+    /// every `ticks` after that if `periodic`
+    ///
+    /// The slot stays allocated after a one-shot expiry so it can be
+    /// restarted or polled; release it with [`cancel`](Self::cancel).
+    pub fn start(
+        &mut self,
+        ticks: u32,
+        periodic: bool,
+        callback: Option<Callback>,
+    ) -> Result<TimerId, Error> {
+        let index = self
+            .slots
+            .iter()
+            .position(|s| !s.allocated)
+            .ok_or(Error::NoFreeSlot)?;
+
+        self.slots[index] = Slot {
+            allocated: true,
+            interval: ticks.max(1),
+            periodic,
+            callback,
+            ..Slot::EMPTY
+        };
+        self.arm(index);
+        Ok(TimerId(index as u8))
+    }
+
+    /// Stop a timer and release its slot
+    pub fn cancel(&mut self, id: TimerId) -> Result<(), Error> {
+        let index = self.slot(id)?;
+        self.disarm(index);
+        self.slots[index] = Slot::EMPTY;
+        Ok(())
+    }
+
+    /// Re-arm a timer one full interval from now
+    ///
+    /// Works for running timers as well as one-shot timers that already
+    /// expired.
+    pub fn restart(&mut self, id: TimerId) -> Result<(), Error> {
+        let index = self.slot(id)?;
+        self.disarm(index);
+        self.slots[index].expired = false;
+        self.arm(index);
+        Ok(())
+    }
+
+    /// Whether a timer is waiting to expire
+    pub fn is_active(&self, id: TimerId) -> Result<bool, Error> {
+        Ok(self.slots[self.slot(id)?].armed)
+    }
+
+    /// Whether a timer expired since the last call, clearing the flag
+    pub fn take_expired(&mut self, id: TimerId) -> Result<bool, Error> {
+        let index = self.slot(id)?;
+        let expired = self.slots[index].expired;
+        self.slots[index].expired = false;
+        Ok(expired)
+    }
+
+    /// Advance one tick, collecting the callbacks of every timer that
+    /// expired into `fired` and returning how many there are
+    pub fn tick(&mut self, fired: &mut [Option<Callback>; MAX_TIMERS]) -> usize {
+        self.now = self.now.wrapping_add(1);
+        let mut count = 0;
+
+        // Timers further away than one wheel revolution share a bucket with
+        // nearer ones, so only fire those whose deadline is exactly now
+        let mut cursor = self.buckets[(self.now & WHEEL_MASK) as usize];
+        while cursor != NIL {
+            let index = cursor as usize;
+            cursor = self.slots[index].next;
+
+            if self.slots[index].deadline != self.now {
+                continue;
+            }
+
+            self.disarm(index);
+            self.slots[index].expired = true;
+            if self.slots[index].periodic {
+                self.arm(index);
+            }
+            if let Some(callback) = self.slots[index].callback {
+                fired[count] = Some(callback);
+                count += 1;
+            }
+        }
+
+        count
+    }
+
+    fn slot(&self, id: TimerId) -> Result<usize, Error> {
+        let index = id.0 as usize;
+        if index < MAX_TIMERS && self.slots[index].allocated {
+            Ok(index)
+        } else {
+            Err(Error::InvalidTimer)
+        }
+    }
+
+    /// Schedule a slot one interval from now and link it into its bucket
+    fn arm(&mut self, index: usize) {
+        let deadline = self.now.wrapping_add(self.slots[index].interval);
+        let bucket = (deadline & WHEEL_MASK) as usize;
+        let head = self.buckets[bucket];
+
+        let slot = &mut self.slots[index];
+        slot.armed = true;
+        slot.deadline = deadline;
+        slot.prev = NIL;
+        slot.next = head;
+
+        if head != NIL {
+            self.slots[head as usize].prev = index as u8;
+        }
+        self.buckets[bucket] = index as u8;
+    }
+
+    /// Remove a slot from its bucket list if it is currently armed
+    fn disarm(&mut self, index: usize) {
+        let slot = self.slots[index];
+        if !slot.armed {
+            return;
+        }
+
+        if slot.prev == NIL {
+            self.buckets[(slot.deadline & WHEEL_MASK) as usize] = slot.next;
+        } else {
+            self.slots[slot.prev as usize].next = slot.next;
+        }
+        if slot.next != NIL {
+            self.slots[slot.next as usize].prev = slot.prev;
+        }
+
+        let slot = &mut self.slots[index];
+        slot.armed = false;
+        slot.prev = NIL;
+        slot.next = NIL;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::vec::Vec;
+
+    /// Tick `ticks` times, returning the ticks (1-based) on which `id` expired
+    fn run(wheel: &mut TimerWheel, id: TimerId, ticks: u32) -> Vec<u32> {
+        let mut fired = [None; MAX_TIMERS];
+        (1..=ticks)
+            .filter(|_| {
+                wheel.tick(&mut fired);
+                wheel.take_expired(id).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn one_shot_expires_once() {
+        let mut wheel = TimerWheel::new();
+        let id = wheel.start(5, false, None).unwrap();
+        assert!(wheel.is_active(id).unwrap());
+        assert_eq!(run(&mut wheel, id, 20), [5]);
+        // Still allocated, but no longer armed
+        assert!(!wheel.is_active(id).unwrap());
+        assert!(!wheel.take_expired(id).unwrap());
+    }
+
+    #[test]
+    fn periodic_rearms() {
+        let mut wheel = TimerWheel::new();
+        let id = wheel.start(3, true, None).unwrap();
+        assert_eq!(run(&mut wheel, id, 10), [3, 6, 9]);
+        assert!(wheel.is_active(id).unwrap());
+    }
+
+    #[test]
+    fn zero_interval_is_one_tick() {
+        let mut wheel = TimerWheel::new();
+        let id = wheel.start(0, true, None).unwrap();
+        assert_eq!(run(&mut wheel, id, 3), [1, 2, 3]);
+    }
+
+    #[test]
+    fn deadlines_past_one_revolution_share_a_bucket() {
+        let mut wheel = TimerWheel::new();
+        let near = wheel.start(8, false, None).unwrap();
+        let far = wheel.start(8 + WHEEL_SIZE as u32, false, None).unwrap();
+        let mut fired = [None; MAX_TIMERS];
+        let mut expiries = Vec::new();
+        for tick in 1..=2 * WHEEL_SIZE as u32 {
+            wheel.tick(&mut fired);
+            for (name, id) in [("near", near), ("far", far)] {
+                if wheel.take_expired(id).unwrap() {
+                    expiries.push((tick, name));
+                }
+            }
+        }
+        assert_eq!(expiries, [(8, "near"), (40, "far")]);
+    }
+
+    #[test]
+    fn counter_wraps_around() {
+        let mut wheel = TimerWheel::new();
+        wheel.now = u32::MAX - 2;
+        let once = wheel.start(5, false, None).unwrap();
+        let periodic = wheel.start(2, true, None).unwrap();
+        assert_eq!(run(&mut wheel, once, 10), [5]);
+        assert_eq!(wheel.now, 7);
+
+        wheel.now = u32::MAX - 2;
+        wheel.restart(periodic).unwrap();
+        assert_eq!(run(&mut wheel, periodic, 7), [2, 4, 6]);
+    }
+
+    #[test]
+    fn cancel_releases_the_slot() {
+        let mut wheel = TimerWheel::new();
+        let id = wheel.start(5, false, None).unwrap();
+        wheel.cancel(id).unwrap();
+        assert_eq!(wheel.is_active(id), Err(Error::InvalidTimer));
+        assert_eq!(wheel.cancel(id), Err(Error::InvalidTimer));
+        let mut fired = [None; MAX_TIMERS];
+        for _ in 0..10 {
+            wheel.tick(&mut fired);
+        }
+        assert_eq!(wheel.take_expired(id), Err(Error::InvalidTimer));
+    }
+
+    #[test]
+    fn cancel_after_expiry() {
+        let mut wheel = TimerWheel::new();
+        let id = wheel.start(2, false, None).unwrap();
+        assert_eq!(run(&mut wheel, id, 3), [2]);
+        wheel.cancel(id).unwrap();
+        assert_eq!(wheel.restart(id), Err(Error::InvalidTimer));
+        // The freed slot is handed out again
+        assert_eq!(wheel.start(1, false, None), Ok(id));
+    }
+
+    #[test]
+    fn cancel_keeps_the_rest_of_a_bucket() {
+        let mut wheel = TimerWheel::new();
+        let ids: Vec<TimerId> = (0..3)
+            .map(|_| wheel.start(4, false, None).unwrap())
+            .collect();
+        // The middle of the bucket list, then its head
+        wheel.cancel(ids[1]).unwrap();
+        wheel.cancel(ids[2]).unwrap();
+        assert_eq!(run(&mut wheel, ids[0], 5), [4]);
+    }
+
+    #[test]
+    fn restart_moves_the_deadline() {
+        let mut wheel = TimerWheel::new();
+        let id = wheel.start(4, false, None).unwrap();
+        assert!(run(&mut wheel, id, 3).is_empty());
+        wheel.restart(id).unwrap();
+        assert_eq!(run(&mut wheel, id, 5), [4]);
+        // And re-arms an expired one-shot
+        wheel.restart(id).unwrap();
+        assert!(wheel.is_active(id).unwrap());
+        assert_eq!(run(&mut wheel, id, 4), [4]);
+    }
+
+    #[test]
+    fn slots_run_out() {
+        let mut wheel = TimerWheel::new();
+        let ids: Vec<TimerId> = (0..MAX_TIMERS)
+            .map(|_| wheel.start(10, false, None).unwrap())
+            .collect();
+        assert_eq!(wheel.start(10, false, None), Err(Error::NoFreeSlot));
+        wheel.cancel(ids[7]).unwrap();
+        assert_eq!(wheel.start(10, true, None), Ok(ids[7]));
+        assert_eq!(wheel.start(10, false, None), Err(Error::NoFreeSlot));
+        assert_eq!(
+            wheel.is_active(TimerId(MAX_TIMERS as u8)),
+            Err(Error::InvalidTimer)
+        );
+    }
+
+    #[test]
+    fn collects_callbacks_of_expired_timers() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        fn callback() {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut wheel = TimerWheel::new();
+        wheel.start(2, false, Some(callback)).unwrap();
+        wheel.start(2, true, Some(callback)).unwrap();
+        wheel.start(2, false, None).unwrap();
+        let mut fired = [None; MAX_TIMERS];
+        assert_eq!(wheel.tick(&mut fired), 0);
+        let count = wheel.tick(&mut fired);
+        assert_eq!(count, 2);
+        for callback in fired.iter().take(count).flatten() {
+            callback();
+        }
+        assert_eq!(CALLS.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,177 @@
+//! Static HTML coverage page
+//!
+//! A single self-contained page: totals, one row per source file, then
+//! every file's source with covered lines in green and instrumentable lines
+//! that never ran in red. Sources that cannot be read are listed without a
+//! listing.
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::{percent, Coverage, FileCoverage};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 4px 10px; text-align: left; }
+td.num { text-align: right; }
+pre { background: #f6f8fa; padding: 8px; overflow-x: auto; line-height: 1.3; }
+.hit { background: #dafbe1; }
+.miss { background: #ffebe9; }
+.count { color: #57606a; display: inline-block; width: 6em; text-align: right; }
+";
+
+/// Render `coverage` as an HTML page; paths are shown relative to `root`
+pub fn write(coverage: &Coverage, root: &Path) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>Firmware Coverage</title>\n");
+    let _ = writeln!(out, "<style>\n{}</style>\n</head>\n<body>", STYLE);
+
+    out.push_str("<h1>Firmware Coverage</h1>\n");
+    let (lines_hit, lines) = coverage.lines();
+    let (functions_hit, functions) = coverage.functions();
+    let _ = writeln!(
+        out,
+        "<p>Lines: {:.1}% ({}/{}), functions: {:.1}% ({}/{})</p>",
+        percent(lines_hit, lines),
+        lines_hit,
+        lines,
+        percent(functions_hit, functions),
+        functions_hit,
+        functions
+    );
+
+    out.push_str("<table>\n<tr><th>File</th><th>Lines</th><th>Hit</th>");
+    out.push_str("<th>Functions</th><th>Hit</th></tr>\n");
+    for (index, (path, file)) in coverage.files.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "<tr><td><a href=\"#f{}\">{}</a></td><td class=\"num\">{:.1}%</td>\
+             <td class=\"num\">{}/{}</td><td class=\"num\">{:.1}%</td>\
+             <td class=\"num\">{}/{}</td></tr>",
+            index,
+            escape(&relative(path, root)),
+            percent(file.lines_hit(), file.lines.len()),
+            file.lines_hit(),
+            file.lines.len(),
+            percent(file.functions_hit(), file.functions.len()),
+            file.functions_hit(),
+            file.functions.len()
+        );
+    }
+    out.push_str("</table>\n");
+
+    for (index, (path, file)) in coverage.files.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "<h2 id=\"f{}\">{}</h2>",
+            index,
+            escape(&relative(path, root))
+        );
+        match fs::read_to_string(path) {
+            Ok(source) => write_listing(&mut out, &source, file),
+            Err(err) => {
+                let _ = writeln!(
+                    out,
+                    "<p>source not available: {}</p>",
+                    escape(&err.to_string())
+                );
+            }
+        }
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn write_listing(out: &mut String, source: &str, file: &FileCoverage) {
+    out.push_str("<pre>");
+    for (index, text) in source.lines().enumerate() {
+        let line = index as u32 + 1;
+        let (class, count) = match file.lines.get(&line) {
+            Some(0) => (" class=\"miss\"", "0".to_string()),
+            Some(count) => (" class=\"hit\"", count.to_string()),
+            None => ("", String::new()),
+        };
+        let _ = writeln!(
+            out,
+            "<span{}><span class=\"count\">{}</span> {:5}  {}</span>",
+            class,
+            count,
+            line,
+            escape(text)
+        );
+    }
+    out.push_str("</pre>\n");
+}
+
+fn relative(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    use crate::FunctionCoverage;
+
+    #[test]
+    fn lists_sources_with_hits_and_misses() {
+        let dir = std::env::temp_dir().join(format!("coverage-html-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        let path = dir.join("src/main.rs");
+        fs::write(&path, "fn main() {\n    if a < b {}\n}\n").unwrap();
+
+        let mut coverage = Coverage::default();
+        coverage.files.insert(
+            path,
+            FileCoverage {
+                lines: BTreeMap::from([(1, 4), (2, 0)]),
+                functions: BTreeMap::from([(
+                    "app::main".to_string(),
+                    FunctionCoverage { line: 1, calls: 4 },
+                )]),
+            },
+        );
+        coverage
+            .files
+            .insert(dir.join("src/gone.rs"), FileCoverage::default());
+        let page = write(&coverage, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(page.contains("<p>Lines: 50.0% (1/2), functions: 100.0% (1/1)</p>"));
+        assert!(page.contains("<a href=\"#f1\">src/main.rs</a>"));
+        assert!(page.contains(
+            "<span class=\"hit\"><span class=\"count\">4</span>     1  fn main() {</span>"
+        ));
+        assert!(page.contains(
+            "<span class=\"miss\"><span class=\"count\">0</span>     2      if a &lt; b {}</span>"
+        ));
+        assert!(page.contains("<span><span class=\"count\"></span>     3  }</span>"));
+        assert!(page.contains("<h2 id=\"f0\">src/gone.rs</h2>\n<p>source not available: "));
+        assert!(page.ends_with("</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn escapes_markup() {
+        assert_eq!(
+            escape("<T as Into<&\"U\">>"),
+            "&lt;T as Into&lt;&amp;&quot;U&quot;&gt;&gt;"
+        );
+        assert_eq!(relative(Path::new("/a/b/c.rs"), Path::new("/a")), "b/c.rs");
+        assert_eq!(relative(Path::new("/x/c.rs"), Path::new("/a")), "/x/c.rs");
+    }
+}
@@ -0,0 +1,79 @@
+//! lcov tracefile output
+//!
+//! The format `genhtml`, editors' coverage gutters and most CI services
+//! read: one `SF:` record per source file with `FN`/`FNDA` function and
+//! `DA` line entries.
+
+use std::fmt::Write;
+
+use crate::Coverage;
+
+/// Render `coverage` as an lcov tracefile; `name` becomes the `TN:` test
+/// name
+pub fn write(coverage: &Coverage, name: &str) -> String {
+    let mut out = String::new();
+    for (path, file) in &coverage.files {
+        let _ = writeln!(out, "TN:{}", name);
+        let _ = writeln!(out, "SF:{}", path.display());
+
+        let mut functions: Vec<_> = file.functions.iter().collect();
+        functions.sort_by_key(|(name, function)| (function.line, name.as_str()));
+        for (name, function) in &functions {
+            let _ = writeln!(out, "FN:{},{}", function.line, name);
+        }
+        for (name, function) in &functions {
+            let _ = writeln!(out, "FNDA:{},{}", function.calls, name);
+        }
+        let _ = writeln!(out, "FNF:{}", file.functions.len());
+        let _ = writeln!(out, "FNH:{}", file.functions_hit());
+
+        for (line, count) in &file.lines {
+            let _ = writeln!(out, "DA:{},{}", line, count);
+        }
+        let _ = writeln!(out, "LF:{}", file.lines.len());
+        let _ = writeln!(out, "LH:{}", file.lines_hit());
+        out.push_str("end_of_record\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_record_per_file() {
+        let coverage = crate::tests::coverage();
+        assert_eq!(
+            write(&coverage, "firmware"),
+            "TN:firmware\n\
+             SF:/work/app/src/main.rs\n\
+             FN:10,app::main\n\
+             FNDA:2,app::main\n\
+             FNF:1\n\
+             FNH:1\n\
+             DA:10,2\n\
+             DA:11,3\n\
+             DA:12,0\n\
+             LF:3\n\
+             LH:2\n\
+             end_of_record\n\
+             TN:firmware\n\
+             SF:/work/app/src/util.rs\n\
+             FN:3,app::util\n\
+             FNDA:0,app::util\n\
+             FNF:1\n\
+             FNH:0\n\
+             DA:3,1\n\
+             DA:4,0\n\
+             LF:2\n\
+             LH:1\n\
+             end_of_record\n"
+        );
+    }
+
+    #[test]
+    fn empty_coverage() {
+        assert_eq!(write(&Coverage::default(), "firmware"), "");
+    }
+}
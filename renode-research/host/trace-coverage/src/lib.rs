@@ -0,0 +1,278 @@
+//! Source coverage of the firmware from Renode execution traces
+//!
+//! Renode can log every instruction the CPU executes
+//! (`sysbus.cpu CreateExecutionTracing "coverage" @file.trace PC`, which
+//! `renode-runner --trace-dir` sets up for each test case) or every
+//! function it enters (`sysbus.cpu LogFunctionNames true`). This crate
+//! maps those addresses back to source lines through the ELF's DWARF line
+//! tables ([`lines`]), merges the hits of every trace of every firmware
+//! image per source file ([`Coverage`]) and writes them as an lcov
+//! tracefile ([`lcov`]) or an HTML page ([`html`]).
+//!
+//! Only code linked into an image counts: a line is instrumentable when
+//! some instruction of the image maps to it, and covered when one of those
+//! instructions was executed in any trace.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub mod html;
+pub mod lcov;
+pub mod lines;
+pub mod trace;
+
+pub use lines::LineTable;
+pub use trace::Hits;
+
+/// Errors reading images or traces
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read
+    Io(std::io::Error),
+    /// The file is not a parseable ELF
+    Elf(object::Error),
+    /// The DWARF debug information is malformed
+    Dwarf(gimli::Error),
+    /// The ELF has no line tables (built without debug info)
+    NoDebugInfo(PathBuf),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "read failed: {}", err),
+            Error::Elf(err) => write!(f, "invalid ELF: {}", err),
+            Error::Dwarf(err) => write!(f, "invalid DWARF: {}", err),
+            Error::NoDebugInfo(path) => {
+                write!(
+                    f,
+                    "{}: no line tables (build with debug = true)",
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<object::Error> for Error {
+    fn from(err: object::Error) -> Self {
+        Error::Elf(err)
+    }
+}
+
+impl From<gimli::Error> for Error {
+    fn from(err: gimli::Error) -> Self {
+        Error::Dwarf(err)
+    }
+}
+
+impl From<firmware_elf::Error> for Error {
+    fn from(err: firmware_elf::Error) -> Self {
+        match err {
+            firmware_elf::Error::Io(err) => Error::Io(err),
+            firmware_elf::Error::Elf(err) => Error::Elf(err),
+            other => Error::Io(std::io::Error::other(other.to_string())),
+        }
+    }
+}
+
+/// A function in a source file
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionCoverage {
+    /// Line of the function's first instruction
+    pub line: u32,
+    /// Times the function was entered
+    pub calls: u64,
+}
+
+/// Coverage of one source file
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileCoverage {
+    /// Execution count per instrumentable line
+    pub lines: BTreeMap<u32, u64>,
+    /// Demangled function name -> coverage
+    pub functions: BTreeMap<String, FunctionCoverage>,
+}
+
+impl FileCoverage {
+    pub fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|&&count| count > 0).count()
+    }
+
+    pub fn functions_hit(&self) -> usize {
+        self.functions.values().filter(|f| f.calls > 0).count()
+    }
+}
+
+/// Coverage merged over any number of images and traces
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    pub files: BTreeMap<PathBuf, FileCoverage>,
+}
+
+impl Coverage {
+    /// Add one image and the hits recorded while it ran
+    ///
+    /// Every line the image has code for is added, with a zero count if
+    /// `hits` never reached it. A line's count is the number of times
+    /// execution arrived at the start of one of its instruction ranges, or
+    /// 1 if it was only reached part-way (a trace that starts mid-line).
+    pub fn add(&mut self, table: &LineTable, hits: &Hits) {
+        let mut counts: HashMap<(usize, u32), u64> = HashMap::new();
+        for row in table.rows() {
+            counts.entry((row.file, row.line)).or_default();
+        }
+        let rows: Vec<(&lines::Row, u64, u64)> = hits
+            .addresses()
+            .iter()
+            .filter_map(|(&address, &count)| Some((table.row(address)?, address, count)))
+            .collect();
+        for &(row, address, count) in &rows {
+            if address == row.address {
+                *counts.entry((row.file, row.line)).or_default() += count;
+            }
+        }
+        // Only after every start is counted, so the order hits are listed
+        // in does not matter
+        for &(row, _, _) in &rows {
+            let entry = counts.entry((row.file, row.line)).or_default();
+            if *entry == 0 {
+                *entry = 1;
+            }
+        }
+        for ((file, line), count) in counts {
+            let file = self
+                .files
+                .entry(table.file(file).to_path_buf())
+                .or_default();
+            *file.lines.entry(line).or_default() += count;
+        }
+
+        for function in table.functions() {
+            let Some(row) = table.row(function.address) else {
+                continue;
+            };
+            let calls = hits.count(function.address);
+            let file = self
+                .files
+                .entry(table.file(row.file).to_path_buf())
+                .or_default();
+            let entry = file
+                .functions
+                .entry(function.name.clone())
+                .or_insert_with(|| FunctionCoverage {
+                    line: row.line,
+                    calls: 0,
+                });
+            entry.calls += calls;
+        }
+    }
+
+    /// Keep only files below `root` (the firmware sources), dropping
+    /// dependencies and the standard library
+    pub fn retain_under(&mut self, root: &Path) {
+        self.files.retain(|path, _| path.starts_with(root));
+    }
+
+    pub fn lines(&self) -> (usize, usize) {
+        self.files.values().fold((0, 0), |(hit, total), file| {
+            (hit + file.lines_hit(), total + file.lines.len())
+        })
+    }
+
+    pub fn functions(&self) -> (usize, usize) {
+        self.files.values().fold((0, 0), |(hit, total), file| {
+            (hit + file.functions_hit(), total + file.functions.len())
+        })
+    }
+}
+
+/// `hit` of `total` as a percentage; 100% when there is nothing to cover
+pub fn percent(hit: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        hit as f64 * 100.0 / total as f64
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::lines::tests::{image, COMP_DIR};
+
+    /// The test image's coverage after `main` ran twice, the second half of
+    /// line 11 once more and `util` was entered part-way
+    pub(crate) fn coverage() -> Coverage {
+        let table = LineTable::from_elf_data(&image()).unwrap();
+        let mut hits = Hits::default();
+        for address in [0x00, 0x00, 0x04, 0x04, 0x06, 0x10, 0x22, 0x1000] {
+            hits.record(address);
+        }
+        let mut coverage = Coverage::default();
+        coverage.add(&table, &hits);
+        coverage
+    }
+
+    fn file(coverage: &Coverage, name: &str) -> FileCoverage {
+        coverage.files[&Path::new(COMP_DIR).join(name)].clone()
+    }
+
+    #[test]
+    fn counts_lines_and_functions() {
+        let coverage = coverage();
+        let main = file(&coverage, "src/main.rs");
+        assert_eq!(main.lines, BTreeMap::from([(10, 2), (11, 3), (12, 0)]));
+        assert_eq!(
+            main.functions["app::main"],
+            FunctionCoverage { line: 10, calls: 2 }
+        );
+        let util = file(&coverage, "src/util.rs");
+        // Reached part-way only
+        assert_eq!(util.lines, BTreeMap::from([(3, 1), (4, 0)]));
+        assert_eq!(util.functions["app::util"].calls, 0);
+
+        assert_eq!(coverage.lines(), (3, 5));
+        assert_eq!(coverage.functions(), (1, 2));
+    }
+
+    #[test]
+    fn merges_images() {
+        let mut coverage = coverage();
+        let table = LineTable::from_elf_data(&image()).unwrap();
+        let mut hits = Hits::default();
+        hits.record(0x28);
+        hits.record(0x20);
+        coverage.add(&table, &hits);
+        let util = file(&coverage, "src/util.rs");
+        assert_eq!(util.lines, BTreeMap::from([(3, 2), (4, 1)]));
+        assert_eq!(util.functions["app::util"].calls, 1);
+        assert_eq!(coverage.lines(), (4, 5));
+    }
+
+    #[test]
+    fn keeps_files_under_a_root() {
+        let mut coverage = coverage();
+        coverage.retain_under(Path::new(COMP_DIR));
+        assert_eq!(coverage.files.len(), 2);
+        coverage.retain_under(Path::new("/work/other"));
+        assert!(coverage.files.is_empty());
+        assert_eq!(coverage.lines(), (0, 0));
+    }
+
+    #[test]
+    fn percentages() {
+        assert_eq!(percent(0, 0), 100.0);
+        assert_eq!(percent(1, 4), 25.0);
+        assert_eq!(percent(3, 3), 100.0);
+    }
+}
@@ -0,0 +1,332 @@
+//! Address to source line mapping from the DWARF line tables
+//!
+//! Every row of a line program starts an address range that ends at the
+//! next row. Rows outside the image's executable sections belong to code
+//! the linker discarded (their sequences start at address 0) and are
+//! dropped, so only code that can actually run is counted.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use firmware_elf::{Code, SymbolTable};
+use gimli::{EndianSlice, RunTimeEndian};
+use object::{Object, ObjectSection, SymbolKind};
+
+use crate::Error;
+
+/// Instructions `[address, end)` generated for one source line
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Row {
+    pub address: u64,
+    pub end: u64,
+    /// Index into [`LineTable::file`]
+    pub file: usize,
+    pub line: u32,
+}
+
+/// A function symbol of the image
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    /// Demangled path without the hash
+    pub name: String,
+    /// Entry address with the Thumb bit cleared
+    pub address: u64,
+}
+
+/// Line table and function symbols of one image
+#[derive(Clone, Debug, Default)]
+pub struct LineTable {
+    /// Sorted by address, non-overlapping
+    rows: Vec<Row>,
+    files: Vec<PathBuf>,
+    functions: Vec<Function>,
+}
+
+type Reader<'a> = EndianSlice<'a, RunTimeEndian>;
+
+impl LineTable {
+    /// Read the line tables and function symbols of an ELF file
+    pub fn from_elf(path: impl AsRef<Path>) -> Result<LineTable, Error> {
+        let path = path.as_ref();
+        let table = Self::from_elf_data(&fs::read(path)?)?;
+        if table.rows.is_empty() {
+            return Err(Error::NoDebugInfo(path.to_path_buf()));
+        }
+        Ok(table)
+    }
+
+    /// Read the line tables and function symbols from ELF data
+    pub fn from_elf_data(data: &[u8]) -> Result<LineTable, Error> {
+        let file = object::File::parse(data)?;
+        let endian = if file.is_little_endian() {
+            RunTimeEndian::Little
+        } else {
+            RunTimeEndian::Big
+        };
+        let code = Code::from_file(&file)?;
+
+        let load = |id: gimli::SectionId| -> Result<Cow<[u8]>, object::Error> {
+            match file.section_by_name(id.name()) {
+                Some(section) => section.uncompressed_data(),
+                None => Ok(Cow::Borrowed(&[])),
+            }
+        };
+        let sections = gimli::DwarfSections::load(load)?;
+        let dwarf = sections.borrow(|section| EndianSlice::new(section, endian));
+
+        let mut table = LineTable::default();
+        let mut file_ids: HashMap<PathBuf, usize> = HashMap::new();
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let Some(program) = unit.line_program.clone() else {
+                continue;
+            };
+            let comp_dir = unit
+                .comp_dir
+                .map(|dir| PathBuf::from(dir.to_string_lossy().into_owned()))
+                .unwrap_or_default();
+
+            // The row whose range is still open: (address, file, line)
+            let mut open: Option<(u64, usize, u32)> = None;
+            let mut rows = program.rows();
+            while let Some((header, row)) = rows.next_row()? {
+                if let Some((address, file, line)) = open.take() {
+                    if row.address() > address && code.contains(address) {
+                        table.rows.push(Row {
+                            address,
+                            end: row.address(),
+                            file,
+                            line,
+                        });
+                    }
+                }
+                if row.end_sequence() {
+                    continue;
+                }
+                let Some(line) = row.line() else {
+                    continue;
+                };
+                let Some(entry) = row.file(header) else {
+                    continue;
+                };
+                let mut path = comp_dir.clone();
+                if let Some(dir) = entry.directory(header) {
+                    // An absolute directory replaces the compilation dir
+                    path.push(attr_string(&dwarf, &unit, dir)?);
+                }
+                path.push(attr_string(&dwarf, &unit, entry.path_name())?);
+                let next = file_ids.len();
+                let file = *file_ids.entry(path.clone()).or_insert_with(|| {
+                    table.files.push(path);
+                    next
+                });
+                open = Some((row.address(), file, line.get() as u32));
+            }
+        }
+        table.rows.sort_by_key(|row| row.address);
+        table.rows.dedup_by_key(|row| row.address);
+
+        table.functions = SymbolTable::from_elf_data(data)?
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Text && symbol.size > 0)
+            .map(|symbol| Function {
+                name: symbol.path.clone(),
+                address: symbol.address & !1,
+            })
+            .filter(|function| code.contains(function.address))
+            .collect();
+        Ok(table)
+    }
+
+    /// Every row, in address order
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// The row whose range contains `address`
+    pub fn row(&self, address: u64) -> Option<&Row> {
+        let index = self.rows.partition_point(|row| row.address <= address);
+        let row = self.rows.get(index.checked_sub(1)?)?;
+        (address < row.end).then_some(row)
+    }
+
+    /// Path of the file with index `file`
+    pub fn file(&self, file: usize) -> &Path {
+        &self.files[file]
+    }
+
+    pub fn functions(&self) -> &[Function] {
+        &self.functions
+    }
+}
+
+fn attr_string(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    unit: &gimli::Unit<Reader<'_>>,
+    value: gimli::AttributeValue<Reader<'_>>,
+) -> Result<String, Error> {
+    Ok(dwarf
+        .attr_string(unit, value)?
+        .to_string_lossy()
+        .into_owned())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use firmware_elf::test_support::ElfBuilder;
+    use gimli::write::{
+        Address, AttributeValue, DwarfUnit, EndianVec, LineProgram, LineString, Sections,
+    };
+    use gimli::{Encoding, Format, LineEncoding};
+    use object::SectionKind;
+
+    pub(crate) const COMP_DIR: &str = "/work/app";
+
+    /// An image with 0x40 bytes of `.text` at address 0 and line tables for
+    /// `src/main.rs` (`main`, 0x00-0x20) and `src/util.rs` (`util`,
+    /// 0x20-0x30), plus a discarded sequence at 0x1000
+    pub(crate) fn image() -> Vec<u8> {
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+        let mut dwarf = DwarfUnit::new(encoding);
+        let mut program = LineProgram::new(
+            encoding,
+            LineEncoding::default(),
+            LineString::String(COMP_DIR.into()),
+            LineString::String(b"src/main.rs".to_vec()),
+            None,
+        );
+        let dir = program.default_directory();
+        let main = program.add_file(LineString::String(b"src/main.rs".to_vec()), dir, None);
+        let util = program.add_file(LineString::String(b"src/util.rs".to_vec()), dir, None);
+
+        let sequences = [
+            (
+                0x00,
+                0x20,
+                main,
+                &[(0x00, 10), (0x04, 11), (0x0C, 12), (0x10, 11)][..],
+            ),
+            (0x20, 0x10, util, &[(0x00, 3), (0x08, 4)][..]),
+            (0x1000, 0x08, main, &[(0x00, 99)][..]),
+        ];
+        for (start, length, file, rows) in sequences {
+            program.begin_sequence(Some(Address::Constant(start)));
+            for &(offset, line) in rows {
+                let row = program.row();
+                row.file = file;
+                row.address_offset = offset;
+                row.line = line;
+                program.generate_row();
+            }
+            program.end_sequence(length);
+        }
+        dwarf.unit.line_program = program;
+        let root = dwarf.unit.root();
+        dwarf.unit.get_mut(root).set(
+            gimli::DW_AT_comp_dir,
+            AttributeValue::String(COMP_DIR.into()),
+        );
+        let mut sections = Sections::new(EndianVec::new(gimli::RunTimeEndian::Little));
+        dwarf.write(&mut sections).unwrap();
+
+        let mut elf = ElfBuilder::new();
+        let text = elf.section(".text", SectionKind::Text, &[0; 0x40]);
+        sections
+            .for_each(|id, data| {
+                if !data.slice().is_empty() {
+                    elf.section(id.name(), SectionKind::Debug, data.slice());
+                }
+                Ok::<(), gimli::write::Error>(())
+            })
+            .unwrap();
+
+        let symbols = [
+            ("_ZN3app4main17h0123456789abcdefE", 0x01, 0x20),
+            ("_ZN3app4util17h0123456789abcdefE", 0x21, 0x10),
+            ("marker", 0x31, 0),
+        ];
+        for (name, value, size) in symbols {
+            elf.symbol(name, value, size, SymbolKind::Text, text);
+        }
+        elf.build()
+    }
+
+    #[test]
+    fn rows_cover_the_code() {
+        let table = LineTable::from_elf_data(&image()).unwrap();
+        let rows: Vec<(u64, u64, u32)> = table
+            .rows()
+            .iter()
+            .map(|row| (row.address, row.end, row.line))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (0x00, 0x04, 10),
+                (0x04, 0x0C, 11),
+                (0x0C, 0x10, 12),
+                (0x10, 0x20, 11),
+                (0x20, 0x28, 3),
+                (0x28, 0x30, 4),
+            ]
+        );
+        let main = table.file(table.rows()[0].file);
+        assert_eq!(main, Path::new(COMP_DIR).join("src/main.rs"));
+        assert_eq!(
+            table.file(table.rows()[4].file),
+            Path::new(COMP_DIR).join("src/util.rs")
+        );
+    }
+
+    #[test]
+    fn row_lookup() {
+        let table = LineTable::from_elf_data(&image()).unwrap();
+        let line = |address| table.row(address).map(|row| row.line);
+        assert_eq!(line(0x00), Some(10));
+        assert_eq!(line(0x0B), Some(11));
+        assert_eq!(line(0x2F), Some(4));
+        assert_eq!(line(0x30), None);
+        assert_eq!(line(0x1000), None);
+    }
+
+    #[test]
+    fn sized_functions_in_code() {
+        let table = LineTable::from_elf_data(&image()).unwrap();
+        assert_eq!(
+            table.functions(),
+            [
+                Function {
+                    name: "app::main".into(),
+                    address: 0x00,
+                },
+                Function {
+                    name: "app::util".into(),
+                    address: 0x20,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn no_debug_info() {
+        let mut elf = ElfBuilder::new();
+        elf.section(".text", SectionKind::Text, &[0; 4]);
+        let data = elf.build();
+        assert!(LineTable::from_elf_data(&data).unwrap().rows().is_empty());
+
+        let path = std::env::temp_dir().join(format!("trace-coverage-{}.elf", std::process::id()));
+        fs::write(&path, &data).unwrap();
+        let error = LineTable::from_elf(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(error, Error::NoDebugInfo(_)), "{}", error);
+        assert!(LineTable::from_elf_data(b"not an elf").is_err());
+    }
+}
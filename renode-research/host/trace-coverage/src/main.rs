@@ -0,0 +1,253 @@
+//! Turn Renode execution traces into lcov and HTML coverage
+//!
+//! Usage: `trace-coverage [OPTIONS] [CRATE]...`
+//!
+//! Reads `<traces>/stm32f3-<x>/*.trace` (and `*.log` with `LogFunctionNames`
+//! output), as written by `renode-runner --trace-dir <traces>`, maps the
+//! addresses through the DWARF line tables of
+//! `target/thumbv7em-none-eabihf/release/stm32f3-<x>` and writes one report
+//! over every crate. `cargo xtask coverage` runs the suites and this tool.
+//!
+//! ```text
+//! stm32f3-i2c/src/main.rs          lines  78.4% (149/190)  functions  85.7% (6/7)
+//! stm32f3-common/src/lib.rs        lines 100.0% (12/12)    functions 100.0% (3/3)
+//! total                            lines  81.2% (...)
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use trace_coverage::{html, lcov, percent, Coverage, Error, Hits, LineTable};
+
+/// Firmware target triple
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+/// Where `cargo xtask coverage` keeps traces and reports, relative to the
+/// firmware root
+const COVERAGE_DIR: &str = "target/coverage";
+
+#[derive(Parser)]
+#[command(about = "Line and function coverage from Renode execution traces")]
+struct Args {
+    /// Peripheral crates (e.g. `dma i2c`); every crate with traces when
+    /// omitted
+    crates: Vec<String>,
+
+    /// Firmware workspace root
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))]
+    root: PathBuf,
+
+    /// Directory with a `stm32f3-<x>/` directory of traces per crate
+    /// (default: target/coverage/traces)
+    #[arg(long)]
+    traces: Option<PathBuf>,
+
+    /// Directory with the ELFs the traces were recorded from
+    /// (default: the release build)
+    #[arg(long)]
+    elf_dir: Option<PathBuf>,
+
+    /// Write an lcov tracefile here (default: target/coverage/lcov.info)
+    #[arg(long)]
+    lcov: Option<PathBuf>,
+
+    /// Write an HTML page here (default: target/coverage/index.html)
+    #[arg(long)]
+    html: Option<PathBuf>,
+
+    /// Also report dependencies and the standard library, not only files
+    /// under the firmware root
+    #[arg(long)]
+    all_sources: bool,
+
+    /// Exit 1 if total line coverage is below this percentage
+    #[arg(long, value_name = "PERCENT")]
+    fail_under: Option<f64>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let root = args.root.canonicalize().unwrap_or(args.root.clone());
+    match run(&args, &root) {
+        Ok(coverage) => {
+            let (hit, total) = coverage.lines();
+            match args.fail_under {
+                Some(minimum) if percent(hit, total) < minimum => {
+                    println!(
+                        "line coverage {:.1}% is below --fail-under {:.1}%",
+                        percent(hit, total),
+                        minimum
+                    );
+                    ExitCode::FAILURE
+                }
+                _ => ExitCode::SUCCESS,
+            }
+        }
+        Err(err) => {
+            eprintln!("trace-coverage: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &Args, root: &Path) -> Result<Coverage, Error> {
+    let output = root.join(COVERAGE_DIR);
+    let traces = args.traces.clone().unwrap_or_else(|| output.join("traces"));
+    let elf_dir = args
+        .elf_dir
+        .clone()
+        .unwrap_or_else(|| root.join("target").join(TARGET).join("release"));
+
+    let mut coverage = Coverage::default();
+    for name in traced_crates(&traces, &args.crates)? {
+        let elf = elf_dir.join(&name);
+        let table = LineTable::from_elf(&elf).map_err(|err| match err {
+            Error::Io(err) => Error::Io(std::io::Error::new(
+                err.kind(),
+                format!("{}: {}", elf.display(), err),
+            )),
+            other => other,
+        })?;
+
+        let mut hits = Hits::default();
+        let files = trace_files(&traces.join(&name))?;
+        for file in &files {
+            hits.merge(&Hits::load(file)?);
+        }
+        println!(
+            "{}: {} trace(s), {} distinct addresses",
+            name,
+            files.len(),
+            hits.addresses().len()
+        );
+        coverage.add(&table, &hits);
+    }
+    if !args.all_sources {
+        coverage.retain_under(root);
+    }
+
+    println!();
+    print_summary(&coverage, root);
+
+    let lcov_path = args
+        .lcov
+        .clone()
+        .unwrap_or_else(|| output.join("lcov.info"));
+    let html_path = args
+        .html
+        .clone()
+        .unwrap_or_else(|| output.join("index.html"));
+    for (path, content) in [
+        (&lcov_path, lcov::write(&coverage, "firmware")),
+        (&html_path, html::write(&coverage, root)),
+    ] {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, content)?;
+        println!("wrote {}", path.display());
+    }
+    Ok(coverage)
+}
+
+/// Crate directories under `traces`, restricted to `wanted` if non-empty
+fn traced_crates(traces: &Path, wanted: &[String]) -> Result<Vec<String>, Error> {
+    let missing = |msg: String| Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, msg));
+    let entries =
+        fs::read_dir(traces).map_err(|err| missing(format!("{}: {}", traces.display(), err)))?;
+    let mut all: Vec<String> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_dir())
+        .filter_map(|path| path.file_name()?.to_str().map(str::to_string))
+        .filter(|name| name.starts_with("stm32f3-"))
+        .collect();
+    all.sort();
+
+    if wanted.is_empty() {
+        if all.is_empty() {
+            return Err(missing(format!("no traces in {}", traces.display())));
+        }
+        return Ok(all);
+    }
+    wanted
+        .iter()
+        .map(|name| {
+            let name = if name.starts_with("stm32f3-") {
+                name.clone()
+            } else {
+                format!("stm32f3-{}", name)
+            };
+            if all.contains(&name) {
+                Ok(name)
+            } else {
+                Err(missing(format!(
+                    "no traces for {} in {}",
+                    name,
+                    traces.display()
+                )))
+            }
+        })
+        .collect()
+}
+
+/// `*.trace` and `*.log` files in `dir`, sorted
+fn trace_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "trace" || ext == "log")
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn print_summary(coverage: &Coverage, root: &Path) {
+    let width = coverage
+        .files
+        .keys()
+        .map(|path| display(path, root).len())
+        .max()
+        .unwrap_or(0)
+        .max("total".len());
+    for (path, file) in &coverage.files {
+        println!(
+            "{:width$}  {}",
+            display(path, root),
+            figures(
+                (file.lines_hit(), file.lines.len()),
+                (file.functions_hit(), file.functions.len())
+            ),
+            width = width
+        );
+    }
+    println!(
+        "{:width$}  {}",
+        "total",
+        figures(coverage.lines(), coverage.functions()),
+        width = width
+    );
+}
+
+fn figures(lines: (usize, usize), functions: (usize, usize)) -> String {
+    format!(
+        "lines {:5.1}% ({}/{})  functions {:5.1}% ({}/{})",
+        percent(lines.0, lines.1),
+        lines.0,
+        lines.1,
+        percent(functions.0, functions.1),
+        functions.0,
+        functions.1
+    )
+}
+
+fn display(path: &Path, root: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
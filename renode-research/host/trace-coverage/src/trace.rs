@@ -0,0 +1,197 @@
+//! Executed addresses from Renode traces and logs
+//!
+//! Two inputs are understood, line by line, and may be mixed:
+//!
+//! ```text
+//! 0x8000404                                   # CreateExecutionTracing ... PC
+//! 0x8000404: 0xB580 push {r7, lr}             # ... Disassembly / PCAndOpcode
+//! cpu: Entering function main (entry) at 0x8000C3C   # LogFunctionNames true
+//! ```
+//!
+//! Anything else (other log messages, blank lines) is skipped, so a whole
+//! Renode log can be passed in. Bit 0 (the Thumb bit, set when Renode
+//! prints a symbol address) is cleared. Function-name logs only record entry
+//! points; they give function coverage and the first line of each function
+//! entered.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::Error;
+
+/// Marker of a `LogFunctionNames` line
+const ENTERING: &str = "Entering function ";
+
+/// Execution count per address
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Hits {
+    counts: HashMap<u64, u64>,
+}
+
+impl Hits {
+    /// Read a trace or log file
+    pub fn load(path: impl AsRef<Path>) -> Result<Hits, Error> {
+        let mut hits = Hits::default();
+        hits.read(BufReader::new(File::open(path)?))?;
+        Ok(hits)
+    }
+
+    /// Add every address in `reader`
+    pub fn read(&mut self, reader: impl BufRead) -> Result<(), Error> {
+        for line in reader.lines() {
+            if let Some(address) = parse_line(&line?) {
+                self.record(address);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn record(&mut self, address: u64) {
+        *self.counts.entry(address).or_default() += 1;
+    }
+
+    /// Times `address` was executed
+    pub fn count(&self, address: u64) -> u64 {
+        self.counts.get(&address).copied().unwrap_or(0)
+    }
+
+    pub fn addresses(&self) -> &HashMap<u64, u64> {
+        &self.counts
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Add the hits of another trace of the same image
+    pub fn merge(&mut self, other: &Hits) {
+        for (&address, &count) in &other.counts {
+            *self.counts.entry(address).or_default() += count;
+        }
+    }
+}
+
+/// What a trace or log line recorded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Record {
+    /// One executed instruction (`CreateExecutionTracing`)
+    Instruction(u64),
+    /// Execution entering a function at this address (`LogFunctionNames`);
+    /// Renode logs it per translation block, so it stands for the
+    /// straight-line code that starts there
+    Block(u64),
+}
+
+impl Record {
+    pub fn address(self) -> u64 {
+        match self {
+            Record::Instruction(address) | Record::Block(address) => address,
+        }
+    }
+}
+
+/// The executed address a trace or log line records, if any
+///
+/// ```
+/// use trace_coverage::trace::parse_line;
+///
+/// assert_eq!(parse_line("0x8000404"), Some(0x800_0404));
+/// assert_eq!(parse_line("0x8000404: 0xB580 push {r7, lr}"), Some(0x800_0404));
+/// assert_eq!(
+///     parse_line("12:00:01.0000 [INFO] cpu: Entering function main (entry) at 0x8000C3D"),
+///     Some(0x800_0C3C)
+/// );
+/// assert_eq!(parse_line("[INFO] sysbus: Loaded ELF"), None);
+/// ```
+pub fn parse_line(line: &str) -> Option<u64> {
+    parse_record(line).map(Record::address)
+}
+
+/// [`parse_line`], keeping what kind of line it was
+pub fn parse_record(line: &str) -> Option<Record> {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix("0x") {
+        let digits = rest.split(|c: char| !c.is_ascii_hexdigit()).next()?;
+        let pc = u64::from_str_radix(digits, 16).ok()?;
+        return Some(Record::Instruction(pc & !1));
+    }
+    let (_, function) = line.split_once(ENTERING)?;
+    let (_, address) = function.rsplit_once(" at 0x")?;
+    let pc = u64::from_str_radix(address.trim(), 16).ok()?;
+    Some(Record::Block(pc & !1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records() {
+        assert_eq!(
+            parse_record("0x8000405"),
+            Some(Record::Instruction(0x800_0404))
+        );
+        assert_eq!(
+            parse_record("  0x08000404: 0xB580 push {r7, lr}"),
+            Some(Record::Instruction(0x800_0404))
+        );
+        assert_eq!(
+            parse_record("cpu: Entering function main (entry) at 0x8000C3D"),
+            Some(Record::Block(0x800_0C3C))
+        );
+        // Function names may contain ` at `
+        assert_eq!(
+            parse_record("cpu: Entering function <T as core::fmt::Display>::fmt at 0x8001001"),
+            Some(Record::Block(0x800_1000))
+        );
+        assert_eq!(Record::Block(4).address(), 4);
+    }
+
+    #[test]
+    fn skips_other_lines() {
+        for line in [
+            "",
+            "0x",
+            "0xZZ",
+            "[INFO] sysbus: Loaded ELF at 0x8000000",
+            "cpu: Entering function main (entry) at 0xnothex",
+            "cpu: Entering function main",
+        ] {
+            assert_eq!(parse_record(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn counts_and_merges() {
+        let mut hits = Hits::default();
+        assert!(hits.is_empty());
+        hits.read(
+            "0x100\n0x101: 0xBF00 nop\n[INFO] start\ncpu: Entering function f at 0x201\n"
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(hits.count(0x100), 2);
+        assert_eq!(hits.count(0x200), 1);
+        assert_eq!(hits.count(0x300), 0);
+
+        let mut other = Hits::default();
+        other.record(0x100);
+        other.record(0x300);
+        hits.merge(&other);
+        assert_eq!(hits.count(0x100), 3);
+        assert_eq!(hits.count(0x300), 1);
+        assert_eq!(hits.addresses().len(), 3);
+    }
+
+    #[test]
+    fn load_from_a_file() {
+        let path = std::env::temp_dir().join(format!("trace-{}.trace", std::process::id()));
+        std::fs::write(&path, "0x8000404\n0x8000406\n").unwrap();
+        let hits = Hits::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(hits.addresses().len(), 2);
+        assert!(matches!(Hits::load(&path), Err(Error::Io(_))));
+    }
+}
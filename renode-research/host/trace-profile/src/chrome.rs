@@ -0,0 +1,128 @@
+//! Chrome trace event JSON, as read by `chrome://tracing` and Perfetto
+//!
+//! Each trace becomes a process named after its file with one thread,
+//! and each function activation a complete (`"ph": "X"`) event. Timestamps
+//! are microseconds at the given core clock, assuming one instruction per
+//! cycle; the instruction count is kept in the event's `args`.
+
+use std::fmt::Write;
+
+use crate::image::Image;
+use crate::profile::Profile;
+
+/// Render `traces` (name, profile) as a Chrome trace JSON document
+pub fn write(image: &Image, traces: &[(String, Profile)], mhz: f64) -> String {
+    let mut out = String::from("{\"traceEvents\":[\n");
+    let mut first = true;
+    let mut event = |out: &mut String, json: String| {
+        if !first {
+            out.push_str(",\n");
+        }
+        first = false;
+        out.push_str(&json);
+    };
+
+    for (pid, (name, profile)) in traces.iter().enumerate() {
+        let pid = pid + 1;
+        event(
+            &mut out,
+            format!(
+                "{{\"name\":\"process_name\",\"ph\":\"M\",\"pid\":{},\"args\":{{\"name\":{}}}}}",
+                pid,
+                string(name)
+            ),
+        );
+        // Parents return after their children; emit them first so viewers
+        // that expect begin-time order nest them correctly
+        let mut spans = profile.spans.clone();
+        spans.sort_by_key(|span| (span.start, span.depth));
+        for span in &spans {
+            let mut json = String::new();
+            let _ = write!(
+                json,
+                "{{\"name\":{},\"ph\":\"X\",\"pid\":{},\"tid\":1,\"ts\":{:.3},\"dur\":{:.3},\
+                 \"args\":{{\"instructions\":{}}}}}",
+                string(image.name(span.function)),
+                pid,
+                span.start as f64 / mhz,
+                (span.end - span.start) as f64 / mhz,
+                span.end - span.start
+            );
+            event(&mut out, json);
+        }
+    }
+    out.push_str("\n],\"displayTimeUnit\":\"ns\"}\n");
+    out
+}
+
+/// A JSON string literal
+fn string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tests::{image, HELPER, MAIN};
+    use crate::profile::Span;
+
+    #[test]
+    fn one_process_per_trace() {
+        let image = image();
+        let profile = Profile {
+            spans: vec![
+                Span {
+                    function: HELPER,
+                    start: 36,
+                    end: 72,
+                    depth: 1,
+                },
+                Span {
+                    function: MAIN,
+                    start: 0,
+                    end: 144,
+                    depth: 0,
+                },
+            ],
+            ..Profile::default()
+        };
+        let traces = [
+            ("uart.trace".to_string(), profile),
+            ("empty".to_string(), Profile::default()),
+        ];
+        assert_eq!(
+            write(&image, &traces, 72.0),
+            "{\"traceEvents\":[\n\
+             {\"name\":\"process_name\",\"ph\":\"M\",\"pid\":1,\"args\":{\"name\":\"uart.trace\"}},\n\
+             {\"name\":\"main\",\"ph\":\"X\",\"pid\":1,\"tid\":1,\"ts\":0.000,\"dur\":2.000,\
+             \"args\":{\"instructions\":144}},\n\
+             {\"name\":\"helper\",\"ph\":\"X\",\"pid\":1,\"tid\":1,\"ts\":0.500,\"dur\":0.500,\
+             \"args\":{\"instructions\":36}},\n\
+             {\"name\":\"process_name\",\"ph\":\"M\",\"pid\":2,\"args\":{\"name\":\"empty\"}}\n\
+             ],\"displayTimeUnit\":\"ns\"}\n"
+        );
+        assert_eq!(
+            write(&image, &[], 72.0),
+            "{\"traceEvents\":[\n\n],\"displayTimeUnit\":\"ns\"}\n"
+        );
+    }
+
+    #[test]
+    fn json_strings() {
+        assert_eq!(string("<T as Foo>::bar"), "\"<T as Foo>::bar\"");
+        assert_eq!(string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+    }
+}
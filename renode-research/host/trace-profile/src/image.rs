@@ -0,0 +1,283 @@
+//! Functions and code of a firmware image
+//!
+//! Maps addresses to the function containing them and, for
+//! `LogFunctionNames` logs, estimates how many instructions run from a
+//! block start: Renode translates straight-line code up to the next
+//! instruction that can change the PC, so the block is decoded until such
+//! an instruction.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use firmware_elf::code::{halfword, is_wide};
+use firmware_elf::{Code, SymbolTable};
+use object::SymbolKind;
+
+use crate::Error;
+
+/// Name reported for addresses outside every function symbol
+pub const UNKNOWN: &str = "<unknown>";
+
+/// Longest block assumed, in instructions
+const MAX_BLOCK: u64 = 256;
+
+/// A function symbol
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Function {
+    /// Demangled path without the hash
+    pub name: String,
+    /// Entry address with the Thumb bit cleared
+    pub address: u64,
+    pub size: u64,
+}
+
+/// Function symbols and executable sections of one ELF
+#[derive(Clone, Debug, Default)]
+pub struct Image {
+    /// Sorted by address; index `functions.len()` is [`UNKNOWN`]
+    functions: Vec<Function>,
+    code: Code,
+    blocks: HashMap<u64, u64>,
+}
+
+impl Image {
+    pub fn from_elf(path: impl AsRef<Path>) -> Result<Image, Error> {
+        Self::from_elf_data(&fs::read(path)?)
+    }
+
+    pub fn from_elf_data(data: &[u8]) -> Result<Image, Error> {
+        let code = Code::from_elf_data(data)?;
+        let mut functions: Vec<Function> = SymbolTable::from_elf_data(data)?
+            .iter()
+            .filter(|symbol| symbol.kind == SymbolKind::Text && symbol.size > 0)
+            .map(|symbol| Function {
+                name: symbol.path.clone(),
+                address: symbol.address & !1,
+                size: symbol.size,
+            })
+            .collect();
+        functions.sort_by_key(|function| function.address);
+        functions.dedup_by_key(|function| function.address);
+
+        Ok(Image {
+            functions,
+            code,
+            blocks: HashMap::new(),
+        })
+    }
+
+    /// Number of function ids, including the one for [`UNKNOWN`]
+    pub fn len(&self) -> usize {
+        self.functions.len() + 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// Id of the function containing `address`
+    pub fn function_at(&self, address: u64) -> usize {
+        let index = self.functions.partition_point(|f| f.address <= address);
+        match index.checked_sub(1).map(|i| (i, &self.functions[i])) {
+            Some((i, f)) if address < f.address + f.size => i,
+            _ => self.functions.len(),
+        }
+    }
+
+    /// Whether `address` is the entry of function `id`
+    pub fn is_entry(&self, id: usize, address: u64) -> bool {
+        self.functions.get(id).is_some_and(|f| f.address == address)
+    }
+
+    pub fn name(&self, id: usize) -> &str {
+        self.functions.get(id).map_or(UNKNOWN, |f| f.name.as_str())
+    }
+
+    /// Instructions from `address` up to and including the next one that
+    /// can change the PC (an estimate of a Renode translation block)
+    pub fn block_length(&mut self, address: u64) -> u64 {
+        if let Some(&length) = self.blocks.get(&address) {
+            return length;
+        }
+        let length = self.decode_block(address);
+        self.blocks.insert(address, length);
+        length
+    }
+
+    fn decode_block(&self, address: u64) -> u64 {
+        let Some(bytes) = self.code.bytes_from(address) else {
+            return 1;
+        };
+
+        let mut pos = 0;
+        let mut count = 0;
+        while count < MAX_BLOCK {
+            let Some(hw1) = halfword(bytes, pos) else {
+                break;
+            };
+            count += 1;
+            if is_wide(hw1) {
+                let Some(hw2) = halfword(bytes, pos + 2) else {
+                    break;
+                };
+                if ends_block_wide(hw1, hw2) {
+                    break;
+                }
+                pos += 4;
+            } else {
+                if ends_block(hw1) {
+                    break;
+                }
+                pos += 2;
+            }
+        }
+        count.max(1)
+    }
+}
+
+/// 16-bit instructions that can change the PC
+fn ends_block(hw: u16) -> bool {
+    hw & 0xF000 == 0xD000           // B<c>, UDF, SVC
+        || hw & 0xF800 == 0xE000    // B
+        || hw & 0xFF00 == 0x4700    // BX, BLX <reg>
+        || hw & 0xFF00 == 0xBD00    // POP {..., pc}
+        || hw & 0xF500 == 0xB100    // CBZ, CBNZ
+        || hw & 0xFF87 == 0x4687    // MOV pc, <reg>
+        || hw & 0xFF87 == 0x4487    // ADD pc, <reg>
+        || hw & 0xFF00 == 0xBE00 // BKPT
+}
+
+/// 32-bit instructions that can change the PC
+fn ends_block_wide(hw1: u16, hw2: u16) -> bool {
+    let pc_load = hw2 & 0xF000 == 0xF000;
+    let pc_listed = hw2 & 0x8000 != 0;
+    match hw1 {
+        // B.W, BL, B<c>.W
+        _ if hw1 >> 11 == 0b11110 => pc_listed,
+        // LDMIA/POP.W and LDMDB {..., pc}
+        _ if hw1 & 0xFFD0 == 0xE890 || hw1 & 0xFFD0 == 0xE910 => pc_listed,
+        // LDR.W pc, [Rn, #imm12] and LDR pc, [Rn, ...]
+        _ if hw1 & 0xFFF0 == 0xF8D0 || hw1 & 0xFFF0 == 0xF850 => pc_load,
+        // TBB, TBH
+        _ if hw1 & 0xFFF0 == 0xE8D0 => hw2 & 0xFFE0 == 0xF000,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use firmware_elf::test_support::{thumb, ElfBuilder};
+    use object::SectionKind;
+
+    pub(crate) const MAIN: usize = 0;
+    pub(crate) const HELPER: usize = 1;
+    pub(crate) const ISR: usize = 2;
+
+    /// `.text` at address 0 with `main` (0x00), `helper` (0x10, with an
+    /// alias) and `isr` (0x20), then NOPs outside any function up to 0x400
+    ///
+    /// `main` pushes, moves, calls `helper` and pops the PC; `helper` runs
+    /// two NOPs and returns; `isr` starts with a CBZ.
+    pub(crate) fn elf() -> Vec<u8> {
+        let mut code: Vec<u16> = vec![0xBF00; 0x200];
+        let mut put = |address: usize, halfwords: &[u16]| {
+            code[address / 2..address / 2 + halfwords.len()].copy_from_slice(halfwords);
+        };
+        put(0x00, &[0xB580, 0x4604, 0xF000, 0xF804, 0xBD80]);
+        put(0x10, &[0xBF00, 0xBF00, 0x4770]);
+        put(0x20, &[0xB100, 0xF8D0, 0x1004, 0xF8D0, 0xF004]);
+        let mut elf = ElfBuilder::new();
+        let text = elf.section(".text", SectionKind::Text, &thumb(&code));
+        let symbols = [
+            ("main", 0x01, 0x10),
+            ("helper", 0x11, 0x10),
+            ("helper_alias", 0x11, 0x10),
+            ("isr", 0x21, 0x0A),
+            ("label", 0x31, 0),
+        ];
+        for (name, value, size) in symbols {
+            elf.symbol(name, value, size, SymbolKind::Text, text);
+        }
+        elf.build()
+    }
+
+    pub(crate) fn image() -> Image {
+        Image::from_elf_data(&elf()).unwrap()
+    }
+
+    #[test]
+    fn functions_by_address() {
+        let image = image();
+        assert_eq!(image.len(), 4);
+        assert!(!image.is_empty());
+        assert_eq!(image.function_at(0x00), MAIN);
+        assert_eq!(image.function_at(0x0F), MAIN);
+        assert_eq!(image.function_at(0x10), HELPER);
+        assert_eq!(image.function_at(0x29), ISR);
+        assert_eq!(image.function_at(0x2A), 3);
+        assert_eq!(image.function_at(0x1000), 3);
+        assert_eq!(image.name(MAIN), "main");
+        assert_eq!(image.name(ISR), "isr");
+        assert_eq!(image.name(3), UNKNOWN);
+        assert!(image.is_entry(HELPER, 0x10));
+        assert!(!image.is_entry(HELPER, 0x12));
+        assert!(!image.is_entry(3, 0x2A));
+        assert!(Image::default().is_empty());
+    }
+
+    #[test]
+    fn blocks_end_at_control_flow() {
+        let mut image = image();
+        // PUSH, MOV, BL
+        assert_eq!(image.block_length(0x00), 3);
+        // POP {r7, pc}
+        assert_eq!(image.block_length(0x08), 1);
+        // NOP, NOP, BX lr
+        assert_eq!(image.block_length(0x10), 3);
+        assert_eq!(image.block_length(0x14), 1);
+        // CBZ
+        assert_eq!(image.block_length(0x20), 1);
+        // LDR.W r1, [r0, #4]; LDR.W pc, [r0, #4]
+        assert_eq!(image.block_length(0x22), 2);
+        // Long runs are capped, addresses outside the code count once
+        assert_eq!(image.block_length(0x30), MAX_BLOCK);
+        assert_eq!(image.block_length(0x3FE), 1);
+        assert_eq!(image.block_length(0x8000), 1);
+    }
+
+    #[test]
+    fn narrow_block_ends() {
+        for hw in [
+            0xD004, 0xDE00, 0xDF00, 0xE7FE, 0x4770, 0x4798, 0xBD80, 0xB100, 0xB910, 0x469F, 0x4487,
+            0xBE00,
+        ] {
+            assert!(ends_block(hw), "{:04X}", hw);
+        }
+        for hw in [0xBF00, 0xB580, 0x4604, 0x4408, 0xB082] {
+            assert!(!ends_block(hw), "{:04X}", hw);
+        }
+    }
+
+    #[test]
+    fn wide_block_ends() {
+        // BL, B.W, B<c>.W
+        assert!(ends_block_wide(0xF000, 0xF804));
+        assert!(ends_block_wide(0xF000, 0xB87E));
+        assert!(ends_block_wide(0xF000, 0x801E));
+        // POP.W {r4, pc} and LDMDB sp, {r4, pc}
+        assert!(ends_block_wide(0xE8BD, 0x8010));
+        assert!(ends_block_wide(0xE91D, 0x8010));
+        // TBB [r0, r1]; TBH [r0, r1, lsl #1]
+        assert!(ends_block_wide(0xE8D0, 0xF001));
+        assert!(ends_block_wide(0xE8D0, 0xF011));
+        assert!(ends_block_wide(0xF851, 0xF002));
+
+        // MOV.W r0, #0; POP.W {r4, r5}; LDR.W r1, [r0, #4]; STRD
+        assert!(!ends_block_wide(0xF04F, 0x0000));
+        assert!(!ends_block_wide(0xE8BD, 0x0030));
+        assert!(!ends_block_wide(0xF8D0, 0x1004));
+        assert!(!ends_block_wide(0xE9C0, 0x2300));
+    }
+}
@@ -0,0 +1,59 @@
+//! Function-level profiles of the firmware from Renode traces
+//!
+//! Reads the same traces as `trace-coverage`: PC traces from
+//! `CreateExecutionTracing` (one line per instruction, so counts are exact)
+//! or `LogFunctionNames` logs (one line per block, counts estimated from
+//! the code, see [`image`]). [`profile`] rebuilds the call stack and counts
+//! inclusive and exclusive instructions per function; [`chrome`] exports
+//! the activations as a timeline for `chrome://tracing` or Perfetto.
+
+use std::fmt;
+
+pub mod chrome;
+pub mod image;
+pub mod profile;
+
+pub use image::Image;
+pub use profile::{FunctionStats, Profile, Profiler, Span};
+
+/// Errors reading images or traces
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be read
+    Io(std::io::Error),
+    /// The file is not a parseable ELF
+    Elf(object::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "read failed: {}", err),
+            Error::Elf(err) => write!(f, "invalid ELF: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<object::Error> for Error {
+    fn from(err: object::Error) -> Self {
+        Error::Elf(err)
+    }
+}
+
+impl From<firmware_elf::Error> for Error {
+    fn from(err: firmware_elf::Error) -> Self {
+        match err {
+            firmware_elf::Error::Io(err) => Error::Io(err),
+            firmware_elf::Error::Elf(err) => Error::Elf(err),
+            other => Error::Io(std::io::Error::other(other.to_string())),
+        }
+    }
+}
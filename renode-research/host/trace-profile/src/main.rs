@@ -0,0 +1,196 @@
+//! Per-function instruction counts and a call timeline from Renode traces
+//!
+//! Usage: `trace-profile [OPTIONS] <CRATE> [TRACE]...`
+//!
+//! With no `TRACE` given, profiles every `*.trace` and `*.log` in
+//! `<--traces>/stm32f3-<x>/` (written by `renode-runner --trace-dir`,
+//! `target/coverage/traces` by default). A Renode log recorded with
+//!
+//! ```text
+//! sysbus.cpu LogFunctionNames True
+//! logFile @/tmp/function-trace.log
+//! ```
+//!
+//! can be passed directly. `--chrome FILE` writes the activations as a
+//! Chrome trace for `chrome://tracing` or <https://ui.perfetto.dev>.
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use trace_coverage::trace::parse_record;
+use trace_profile::{chrome, Error, Image, Profile, Profiler};
+
+/// Firmware target triple
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+#[derive(Parser)]
+#[command(about = "Profile firmware functions from Renode execution traces")]
+struct Args {
+    /// Peripheral crate the traces were recorded from (e.g. `adc`)
+    #[arg(name = "CRATE")]
+    krate: String,
+
+    /// Trace or log files (default: the crate's traces under --traces)
+    traces: Vec<PathBuf>,
+
+    /// Directory `renode-runner --trace-dir` wrote to
+    #[arg(long = "traces", value_name = "DIR")]
+    trace_dir: Option<PathBuf>,
+
+    /// Firmware workspace root
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))]
+    root: PathBuf,
+
+    /// ELF the traces were recorded from (default: the release build)
+    #[arg(long)]
+    elf: Option<PathBuf>,
+
+    /// Functions listed, by exclusive instructions
+    #[arg(long, default_value_t = 20)]
+    top: usize,
+
+    /// Write a Chrome trace / Perfetto JSON timeline here
+    #[arg(long, value_name = "FILE")]
+    chrome: Option<PathBuf>,
+
+    /// Core clock for the timeline, at one instruction per cycle
+    #[arg(long, default_value_t = 72.0)]
+    mhz: f64,
+
+    /// Leave activations shorter than this many instructions out of the
+    /// timeline
+    #[arg(long, default_value_t = 20)]
+    min_span: u64,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("trace-profile: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Error> {
+    let root = args.root.canonicalize().unwrap_or(args.root.clone());
+    let name = if args.krate.starts_with("stm32f3-") {
+        args.krate.clone()
+    } else {
+        format!("stm32f3-{}", args.krate)
+    };
+    let elf = args
+        .elf
+        .clone()
+        .unwrap_or_else(|| root.join("target").join(TARGET).join("release").join(&name));
+    let mut image = Image::from_elf(&elf).map_err(|err| match err {
+        Error::Io(err) => Error::Io(std::io::Error::new(
+            err.kind(),
+            format!("{}: {}", elf.display(), err),
+        )),
+        other => other,
+    })?;
+
+    let files = if args.traces.is_empty() {
+        let dir = args
+            .trace_dir
+            .clone()
+            .unwrap_or_else(|| root.join("target/coverage/traces"));
+        trace_files(&dir.join(&name))?
+    } else {
+        args.traces.clone()
+    };
+
+    let mut traces = Vec::new();
+    let mut total = Profile::default();
+    for file in &files {
+        let mut profiler = Profiler::new(&mut image, args.min_span);
+        for line in BufReader::new(File::open(file)?).lines() {
+            if let Some(record) = parse_record(&line?) {
+                profiler.record(record);
+            }
+        }
+        let profile = profiler.finish();
+        total.merge(&profile);
+        let stem = file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        traces.push((stem, profile));
+    }
+
+    println!(
+        "=== {}: {} trace(s), {} instructions ===",
+        name,
+        files.len(),
+        total.instructions
+    );
+    print_table(&image, &total, args.top);
+
+    if let Some(path) = &args.chrome {
+        fs::write(path, chrome::write(&image, &traces, args.mhz))?;
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
+
+/// `*.trace` and `*.log` files in `dir`, sorted
+fn trace_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let entries = fs::read_dir(dir).map_err(|err| {
+        Error::Io(std::io::Error::new(
+            err.kind(),
+            format!("{}: {}", dir.display(), err),
+        ))
+    })?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "trace" || ext == "log")
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+fn print_table(image: &Image, profile: &Profile, top: usize) {
+    let mut rows: Vec<(usize, &trace_profile::FunctionStats)> = profile
+        .stats
+        .iter()
+        .enumerate()
+        .filter(|(_, stats)| stats.exclusive > 0 || stats.calls > 0)
+        .collect();
+    rows.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(&b.0)));
+
+    let share = |count: u64| {
+        if profile.instructions == 0 {
+            0.0
+        } else {
+            count as f64 * 100.0 / profile.instructions as f64
+        }
+    };
+    println!(
+        "  {:>8} {:>12} {:>7} {:>12} {:>7}  Function",
+        "Calls", "Inclusive", "%", "Exclusive", "%"
+    );
+    for (id, stats) in rows.iter().take(top) {
+        println!(
+            "  {:>8} {:>12} {:>6.1}% {:>12} {:>6.1}%  {}",
+            stats.calls,
+            stats.inclusive,
+            share(stats.inclusive),
+            stats.exclusive,
+            share(stats.exclusive),
+            image.name(*id)
+        );
+    }
+    if rows.len() > top {
+        println!("  ... {} more (--top)", rows.len() - top);
+    }
+}
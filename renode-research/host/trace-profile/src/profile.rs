@@ -0,0 +1,261 @@
+//! Call stacks and per-function instruction counts
+//!
+//! A trace only says where execution is, not how it got there, so calls
+//! and returns are inferred from the function each address falls in:
+//!
+//! - arriving at the **entry** of a function is a call (recursion and tail
+//!   calls included), as is arriving in a function that is not on the
+//!   stack (an interrupt handler, or the first address of the trace);
+//! - arriving in the middle of a function that is on the stack is a
+//!   return to it, popping every frame above;
+//! - anything else stays in the current frame.
+//!
+//! Time is counted in instructions. Exclusive counts go to the function on
+//! top of the stack; inclusive counts cover the outermost activation of a
+//! function, so recursion is not counted twice.
+
+use trace_coverage::trace::Record;
+
+use crate::image::Image;
+
+/// Totals for one function
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FunctionStats {
+    pub calls: u64,
+    /// Instructions executed in the function and everything it called
+    pub inclusive: u64,
+    /// Instructions executed in the function itself
+    pub exclusive: u64,
+}
+
+/// One activation of a function on the timeline
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub function: usize,
+    /// Instruction count at entry and at return
+    pub start: u64,
+    pub end: u64,
+    /// Stack depth, 0 for the outermost frame
+    pub depth: usize,
+}
+
+/// Result of profiling one trace
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    /// Indexed by function id ([`Image::function_at`])
+    pub stats: Vec<FunctionStats>,
+    /// Activations that lasted at least the profiler's minimum, in the
+    /// order they returned
+    pub spans: Vec<Span>,
+    /// Instructions in the trace
+    pub instructions: u64,
+}
+
+impl Profile {
+    /// Add another profile of the same image
+    pub fn merge(&mut self, other: &Profile) {
+        if self.stats.len() < other.stats.len() {
+            self.stats
+                .resize(other.stats.len(), FunctionStats::default());
+        }
+        for (total, stats) in self.stats.iter_mut().zip(&other.stats) {
+            total.calls += stats.calls;
+            total.inclusive += stats.inclusive;
+            total.exclusive += stats.exclusive;
+        }
+        self.instructions += other.instructions;
+    }
+}
+
+struct Frame {
+    function: usize,
+    start: u64,
+}
+
+/// Builds a [`Profile`] from the records of one trace, in order
+pub struct Profiler<'a> {
+    image: &'a mut Image,
+    stack: Vec<Frame>,
+    /// Activations of each function currently on the stack
+    active: Vec<u32>,
+    profile: Profile,
+    /// Shortest activation kept in [`Profile::spans`]
+    min_span: u64,
+}
+
+impl<'a> Profiler<'a> {
+    /// `min_span` drops activations shorter than that many instructions
+    /// from the timeline (they still count in the totals)
+    pub fn new(image: &'a mut Image, min_span: u64) -> Profiler<'a> {
+        let functions = image.len();
+        Profiler {
+            image,
+            stack: Vec::new(),
+            active: vec![0; functions],
+            profile: Profile {
+                stats: vec![FunctionStats::default(); functions],
+                ..Profile::default()
+            },
+            min_span,
+        }
+    }
+
+    /// Account for one trace record
+    pub fn record(&mut self, record: Record) {
+        let (address, weight) = match record {
+            Record::Instruction(address) => (address, 1),
+            Record::Block(address) => (address, self.image.block_length(address)),
+        };
+        let function = self.image.function_at(address);
+
+        let top = self.stack.last().map(|frame| frame.function);
+        if self.image.is_entry(function, address) {
+            self.push(function);
+        } else if top != Some(function) {
+            match self.stack.iter().rposition(|f| f.function == function) {
+                Some(depth) => self.pop_to(depth + 1),
+                None => self.push(function),
+            }
+        }
+
+        self.profile.stats[function].exclusive += weight;
+        self.profile.instructions += weight;
+    }
+
+    /// Close the frames still open at the end of the trace
+    pub fn finish(mut self) -> Profile {
+        self.pop_to(0);
+        self.profile
+    }
+
+    fn push(&mut self, function: usize) {
+        self.profile.stats[function].calls += 1;
+        self.active[function] += 1;
+        self.stack.push(Frame {
+            function,
+            start: self.profile.instructions,
+        });
+    }
+
+    /// Pop frames until `depth` remain
+    fn pop_to(&mut self, depth: usize) {
+        let now = self.profile.instructions;
+        while self.stack.len() > depth {
+            let frame = self.stack.pop().expect("stack deeper than depth");
+            self.active[frame.function] -= 1;
+            if self.active[frame.function] == 0 {
+                self.profile.stats[frame.function].inclusive += now - frame.start;
+            }
+            if now - frame.start >= self.min_span {
+                self.profile.spans.push(Span {
+                    function: frame.function,
+                    start: frame.start,
+                    end: now,
+                    depth: self.stack.len(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::tests::{image, HELPER, ISR, MAIN};
+
+    fn profile(min_span: u64, records: &[Record]) -> Profile {
+        let mut image = image();
+        let mut profiler = Profiler::new(&mut image, min_span);
+        for &record in records {
+            profiler.record(record);
+        }
+        profiler.finish()
+    }
+
+    fn trace(addresses: &[u64]) -> Vec<Record> {
+        addresses.iter().map(|&a| Record::Instruction(a)).collect()
+    }
+
+    fn stats(calls: u64, inclusive: u64, exclusive: u64) -> FunctionStats {
+        FunctionStats {
+            calls,
+            inclusive,
+            exclusive,
+        }
+    }
+
+    #[test]
+    fn calls_and_returns() {
+        let profile = profile(0, &trace(&[0x00, 0x02, 0x04, 0x10, 0x12, 0x14, 0x08, 0x0A]));
+        assert_eq!(profile.instructions, 8);
+        assert_eq!(profile.stats[MAIN], stats(1, 8, 5));
+        assert_eq!(profile.stats[HELPER], stats(1, 3, 3));
+        assert_eq!(
+            profile.spans,
+            [
+                Span {
+                    function: HELPER,
+                    start: 3,
+                    end: 6,
+                    depth: 1,
+                },
+                Span {
+                    function: MAIN,
+                    start: 0,
+                    end: 8,
+                    depth: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn interrupts_and_unknown_code_are_calls() {
+        // The handler is entered mid-function, then returns into `main`
+        let profile = profile(0, &trace(&[0x00, 0x02, 0x24, 0x26, 0x04, 0x100]));
+        assert_eq!(profile.stats[ISR], stats(1, 2, 2));
+        assert_eq!(profile.stats[MAIN].calls, 1);
+        assert_eq!(profile.stats[3], stats(1, 1, 1));
+        let depths: Vec<(usize, usize)> = profile
+            .spans
+            .iter()
+            .map(|s| (s.function, s.depth))
+            .collect();
+        assert_eq!(depths, [(ISR, 1), (3, 1), (MAIN, 0)]);
+    }
+
+    #[test]
+    fn recursion_counts_once() {
+        let profile = profile(0, &trace(&[0x00, 0x02, 0x00, 0x02, 0x08]));
+        assert_eq!(profile.stats[MAIN], stats(2, 5, 5));
+        assert_eq!(profile.spans.len(), 2);
+    }
+
+    #[test]
+    fn short_spans_only_count_in_totals() {
+        let profile = profile(4, &trace(&[0x00, 0x02, 0x04, 0x10, 0x12, 0x14, 0x08, 0x0A]));
+        assert_eq!(profile.stats[HELPER], stats(1, 3, 3));
+        assert_eq!(profile.spans.len(), 1);
+        assert_eq!(profile.spans[0].function, MAIN);
+    }
+
+    #[test]
+    fn blocks_weigh_their_estimated_length() {
+        let profile = profile(0, &[Record::Block(0x00), Record::Block(0x10)]);
+        assert_eq!(profile.instructions, 6);
+        assert_eq!(profile.stats[MAIN], stats(1, 6, 3));
+        assert_eq!(profile.stats[HELPER], stats(1, 3, 3));
+    }
+
+    #[test]
+    fn merges_totals() {
+        let mut total = Profile::default();
+        let one = profile(0, &trace(&[0x00, 0x10]));
+        total.merge(&one);
+        total.merge(&one);
+        assert_eq!(total.instructions, 4);
+        assert_eq!(total.stats[MAIN], stats(2, 4, 2));
+        assert_eq!(total.stats[HELPER], stats(2, 2, 2));
+        assert!(total.spans.is_empty());
+    }
+}
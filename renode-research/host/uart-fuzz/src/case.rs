@@ -0,0 +1,253 @@
+//! Fuzz cases and their text form
+//!
+//! A case is what one iteration sends: byte strings with pauses between
+//! them. Saved cases have one step per line, so a shrunk case can be read,
+//! edited and replayed with `--replay`:
+//!
+//! ```text
+//! # uart-fuzz --seed 42: case 17, hang
+//! send "peek 0x4\x1B[A\r"
+//! pause 150
+//! send "\x00\x03\x02\x01\x00"
+//! ```
+//!
+//! Strings take `\r`, `\n`, `\t`, `\"`, `\\` and `\xNN`; `#` starts a comment.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::Error;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Bytes written to the UART in one go
+    Send(Vec<u8>),
+    /// Milliseconds to wait before the next step
+    Pause(u64),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Case {
+    pub steps: Vec<Step>,
+}
+
+/// Why a case's text form could not be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line number
+    pub line: usize,
+    pub msg: String,
+}
+
+impl Case {
+    /// Every byte the case sends, pauses left out
+    pub fn bytes(&self) -> Vec<u8> {
+        self.steps
+            .iter()
+            .flat_map(|step| match step {
+                Step::Send(bytes) => bytes.as_slice(),
+                Step::Pause(_) => &[],
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Bytes sent plus steps taken, which shrinking makes smaller
+    pub fn size(&self) -> usize {
+        self.bytes().len() + self.steps.len()
+    }
+
+    pub fn parse(text: &str) -> Result<Case, ParseError> {
+        let mut steps = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let error = |msg: String| ParseError {
+                line: index + 1,
+                msg,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+            let rest = rest.trim();
+            match keyword {
+                "send" => {
+                    let quoted = rest
+                        .strip_prefix('"')
+                        .and_then(|s| s.strip_suffix('"'))
+                        .ok_or_else(|| {
+                            error(format!("expected a quoted string, found `{}`", rest))
+                        })?;
+                    steps.push(Step::Send(unescape(quoted).map_err(error)?));
+                }
+                "pause" => {
+                    let ms = rest
+                        .parse()
+                        .map_err(|_| error(format!("expected milliseconds, found `{}`", rest)))?;
+                    steps.push(Step::Pause(ms));
+                }
+                _ => return Err(error(format!("unknown step `{}`", keyword))),
+            }
+        }
+        Ok(Case { steps })
+    }
+
+    /// Read a saved case
+    pub fn load(path: &Path) -> Result<Case, Error> {
+        let text = std::fs::read_to_string(path)?;
+        Case::parse(&text).map_err(|err| Error::Case {
+            path: path.to_path_buf(),
+            line: err.line,
+            msg: err.msg,
+        })
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            match step {
+                Step::Send(bytes) => writeln!(f, "send \"{}\"", escape(bytes))?,
+                Step::Pause(ms) => writeln!(f, "pause {}", ms)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bytes as the inside of a `send` string
+pub fn escape(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for &byte in bytes {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\r' => out.push_str("\\r"),
+            b'\n' => out.push_str("\\n"),
+            b'\t' => out.push_str("\\t"),
+            b' '..=b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("\\x{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut chars = text.bytes();
+    while let Some(byte) = chars.next() {
+        if byte != b'\\' {
+            out.push(byte);
+            continue;
+        }
+        match chars.next() {
+            Some(b'r') => out.push(b'\r'),
+            Some(b'n') => out.push(b'\n'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'"') => out.push(b'"'),
+            Some(b'\\') => out.push(b'\\'),
+            Some(b'x') => {
+                let digits = [chars.next(), chars.next()];
+                let hex: String = digits.iter().flatten().map(|&b| b as char).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(value) if hex.len() == 2 => out.push(value),
+                    _ => return Err(format!("bad escape `\\x{}`", hex)),
+                }
+            }
+            Some(other) => return Err(format!("bad escape `\\{}`", other as char)),
+            None => return Err("string ends with `\\`".into()),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(line: usize, msg: &str) -> Result<Case, ParseError> {
+        Err(ParseError {
+            line,
+            msg: msg.into(),
+        })
+    }
+
+    #[test]
+    fn every_byte_round_trips() {
+        let case = Case {
+            steps: vec![
+                Step::Send((0..=255).collect()),
+                Step::Pause(0),
+                Step::Send(Vec::new()),
+                Step::Pause(200),
+            ],
+        };
+        let text = case.to_string();
+        assert_eq!(text.lines().count(), 4);
+        assert_eq!(Case::parse(&text), Ok(case));
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            escape(b"a \"b\"\\\r\n\t\x1b\x7f\x00"),
+            "a \\\"b\\\"\\\\\\r\\n\\t\\x1B\\x7F\\x00"
+        );
+        let case = Case::parse("send \"\\x1b[A\\x0D\"").unwrap();
+        assert_eq!(case.steps, [Step::Send(b"\x1b[A\r".to_vec())]);
+    }
+
+    #[test]
+    fn reads_a_saved_case() {
+        let text = "# uart-fuzz --seed 42: case 17, hang\n\n  send \"peek 0x4\\x1B[A\\r\"  \npause 150\n# edited\nsend \"\\x00\\x03\"\n";
+        let case = Case::parse(text).unwrap();
+        assert_eq!(
+            case.steps,
+            [
+                Step::Send(b"peek 0x4\x1b[A\r".to_vec()),
+                Step::Pause(150),
+                Step::Send(vec![0, 3]),
+            ]
+        );
+        assert_eq!(case.bytes(), b"peek 0x4\x1b[A\r\x00\x03");
+        assert_eq!(case.size(), 14 + 3);
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(
+            Case::parse("pause 1\nwait 5"),
+            error(2, "unknown step `wait`")
+        );
+        assert_eq!(
+            Case::parse("pause soon"),
+            error(1, "expected milliseconds, found `soon`")
+        );
+        assert_eq!(
+            Case::parse("pause -1"),
+            error(1, "expected milliseconds, found `-1`")
+        );
+        assert_eq!(
+            Case::parse("# x\nsend led"),
+            error(2, "expected a quoted string, found `led`")
+        );
+        assert_eq!(
+            Case::parse("send \"led"),
+            error(1, "expected a quoted string, found `\"led`")
+        );
+    }
+
+    #[test]
+    fn bad_escapes() {
+        assert_eq!(Case::parse("send \"\\q\""), error(1, "bad escape `\\q`"));
+        assert_eq!(
+            Case::parse("send \"\\xG1\""),
+            error(1, "bad escape `\\xG1`")
+        );
+        assert_eq!(Case::parse("send \"\\x1\""), error(1, "bad escape `\\x1`"));
+        assert_eq!(
+            Case::parse("send \"a\\\""),
+            error(1, "string ends with `\\`")
+        );
+    }
+}
@@ -0,0 +1,264 @@
+//! Judging what the firmware sent back for a case
+
+use std::fmt;
+
+use frame_decode::{Decoder, Item};
+
+use crate::model::Model;
+
+/// Start of the firmware's HardFault report (`stm32f3_common::fault`)
+pub const FAULT_MARKER: &str = "*** FAULT:";
+
+/// What went wrong with a case
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// Nothing answered the probe sent after the case
+    Hang,
+    /// The firmware reported a HardFault; the report line
+    Fault(String),
+    /// The firmware answered, but not as the shell should
+    Violation(String),
+}
+
+impl Failure {
+    /// Failures of the same kind count as the same failure when shrinking
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Failure::Hang => "hang",
+            Failure::Fault(_) => "fault",
+            Failure::Violation(_) => "protocol violation",
+        }
+    }
+
+    /// Whether the firmware needs a reset before it can take more input
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, Failure::Hang | Failure::Fault(_))
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Hang => write!(f, "hang: no reply to the probe"),
+            Failure::Fault(report) => write!(f, "fault: {}", report),
+            Failure::Violation(msg) => write!(f, "protocol violation: {}", msg),
+        }
+    }
+}
+
+/// The output with echoed backspaces (`\x08 \x08`) applied, as a terminal
+/// would show it
+pub fn normalize(output: &[u8]) -> Vec<u8> {
+    let mut screen = Vec::with_capacity(output.len());
+    for &byte in output {
+        if byte == 0x08 {
+            if screen.last().is_some_and(|&b| b != b'\n') {
+                screen.pop();
+            }
+        } else {
+            screen.push(byte);
+        }
+    }
+    screen
+}
+
+/// The fault report line, if the output has one
+pub fn fault(output: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(output);
+    text.lines()
+        .find(|line| line.contains(FAULT_MARKER))
+        .map(|line| line.trim().to_string())
+}
+
+/// Compare the shell's answer with what `model` predicts
+///
+/// `output` starts with the prompt that preceded the case and ends with the
+/// prompt after the probe, so every prompt begins a line of its own.
+pub fn check(output: &[u8], model: &Model) -> Result<(), Failure> {
+    if let Some(report) = fault(output) {
+        return Err(Failure::Fault(report));
+    }
+
+    let mut decoder = Decoder::new();
+    let mut items = decoder.push(&normalize(output));
+    items.extend(decoder.finish());
+
+    let mut prompts = 0;
+    let mut replies = Vec::new();
+    for item in items {
+        match item {
+            Item::Text(line) => {
+                if line.starts_with("> ") {
+                    prompts += 1;
+                }
+                if line.starts_with("unknown command: ") {
+                    replies.push(line);
+                }
+            }
+            Item::Corrupt { reason, bytes } => {
+                return Err(Failure::Violation(format!(
+                    "{} bytes that are neither text nor a frame ({})",
+                    bytes.len(),
+                    reason
+                )))
+            }
+            Item::Frame(_) | Item::Lost { .. } => {}
+        }
+    }
+
+    // The prompt before the case is not the model's
+    let expected = model.prompts + 1;
+    if prompts != expected {
+        return Err(Failure::Violation(format!(
+            "{} prompt(s) for {} line(s) and Ctrl-C, expected {}",
+            prompts, model.prompts, expected
+        )));
+    }
+    if let Some((got, want)) = replies
+        .iter()
+        .zip(&model.replies)
+        .find(|(got, want)| got != want)
+    {
+        return Err(Failure::Violation(format!(
+            "got `{}`, expected `{}`",
+            got, want
+        )));
+    }
+    if replies.len() != model.replies.len() {
+        return Err(Failure::Violation(format!(
+            "{} `unknown command` replies, expected {}",
+            replies.len(),
+            model.replies.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Recorded from `stm32f3-uart`; `led`, `peek`, `blink` and `led` again
+    /// follow the `help` listing
+    const TRANSCRIPT: &str = include_str!("../../../stm32f3-uart/tests/uart.golden");
+
+    fn commands() -> Vec<String> {
+        ["led", "peek", "poke", "reset", "help"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect()
+    }
+
+    /// The recorded answer to the lines after `help`, with the prompt that
+    /// follows the last one
+    fn answer() -> String {
+        let start = TRANSCRIPT.find("> led 4 on").unwrap();
+        TRANSCRIPT[start..].replace('\n', "\r\n") + "> "
+    }
+
+    fn model<'a>(commands: &'a [String], input: &[u8]) -> Model<'a> {
+        let mut model = Model::new(commands, "help");
+        model.feed(input);
+        model
+    }
+
+    const INPUT: &[u8] = b"led 4 on\rpeek 0x48001014\rblink\rled 3 off\r";
+
+    #[test]
+    fn accepts_the_recorded_answer() {
+        let commands = commands();
+        let model = model(&commands, INPUT);
+        assert_eq!(check(answer().as_bytes(), &model), Ok(()));
+    }
+
+    #[test]
+    fn missing_prompt() {
+        let commands = commands();
+        let model = model(&commands, INPUT);
+        let answer = answer();
+        let cut = answer.strip_suffix("> ").unwrap();
+        assert_eq!(
+            check(cut.as_bytes(), &model),
+            Err(Failure::Violation(
+                "4 prompt(s) for 4 line(s) and Ctrl-C, expected 5".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn wrong_reply() {
+        // Without `peek`, the model expects a second reply
+        let mut commands = commands();
+        commands.retain(|name| name != "peek");
+        let model = model(&commands, INPUT);
+        assert_eq!(
+            check(answer().as_bytes(), &model),
+            Err(Failure::Violation(
+                "got `unknown command: blink (try `help`)`, expected \
+                 `unknown command: peek (try `help`)`"
+                    .into()
+            ))
+        );
+    }
+
+    #[test]
+    fn extra_reply() {
+        let commands = commands();
+        let model = model(&commands, INPUT);
+        let answer = answer().replace("LD3: off", "unknown command: led (try `help`)");
+        assert_eq!(
+            check(answer.as_bytes(), &model),
+            Err(Failure::Violation(
+                "2 `unknown command` replies, expected 1".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn garbage() {
+        let commands = commands();
+        let model = model(&commands, INPUT);
+        let mut answer = answer().into_bytes();
+        answer.splice(10..10, [0x00, 0xFF, 0x13, 0x00]);
+        let failure = check(&answer, &model).unwrap_err();
+        assert!(
+            matches!(&failure, Failure::Violation(msg) if msg.contains("neither text nor a frame")),
+            "{}",
+            failure
+        );
+    }
+
+    #[test]
+    fn fault_report() {
+        let commands = commands();
+        let model = model(&commands, b"peek 0x60000000\r");
+        let output = b"> peek 0x60000000\r\n*** FAULT: HardFault at 0x08000ABC\r\n";
+        assert_eq!(
+            check(output, &model),
+            Err(Failure::Fault("*** FAULT: HardFault at 0x08000ABC".into()))
+        );
+        assert!(check(output, &model).unwrap_err().is_fatal());
+        assert_eq!(fault(b"> help\r\n"), None);
+    }
+
+    #[test]
+    fn normalize_applies_backspaces() {
+        assert_eq!(normalize(b"> ledd\x08 \x08 3"), b"> led 3");
+        // Never past the start of a line
+        assert_eq!(normalize(b"ok\n\x08\x08x"), b"ok\nx");
+        assert_eq!(normalize(b"\x08"), b"");
+    }
+
+    #[test]
+    fn kinds() {
+        assert_eq!(Failure::Hang.kind(), "hang");
+        assert_eq!(Failure::Fault(String::new()).kind(), "fault");
+        assert_eq!(
+            Failure::Violation(String::new()).kind(),
+            "protocol violation"
+        );
+        assert!(Failure::Hang.is_fatal());
+        assert!(!Failure::Violation(String::new()).is_fatal());
+        assert_eq!(Failure::Hang.to_string(), "hang: no reply to the probe");
+    }
+}
@@ -0,0 +1,442 @@
+//! Random and mutated input for the shell, as `proptest` strategies
+//!
+//! Each case is a few pieces, each drawn from one of these:
+//!
+//! | Piece | What it sends |
+//! |-------|---------------|
+//! | command | A known command with numbers, usage words or junk as arguments |
+//! | mutant | A command line with bytes flipped, inserted, dropped or repeated |
+//! | long line | More printable characters than the shell's line holds |
+//! | editing | Backspace, Ctrl-C, Ctrl-U, `ESC [ A` and stray escape sequences |
+//! | noise | Arbitrary bytes |
+//!
+//! Pieces are sometimes split, so a line arrives in parts, and separated by
+//! pauses of up to [`MAX_PAUSE`] ms. Every choice is drawn through proptest,
+//! so a failing case shrinks along the same lines ([`crate::shrink`]):
+//! fewer pieces, arguments and mutations, shorter tokens and pauses.
+
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use proptest::sample::{select, Index};
+use proptest::strategy::{BoxedStrategy, ValueTree};
+use proptest::test_runner::{Config, RngAlgorithm, TestRng, TestRunner};
+
+use crate::case::{Case, Step};
+use crate::model::LINE_MAX;
+use crate::target::Command;
+
+/// Longest pause between pieces, in milliseconds
+pub const MAX_PAUSE: u64 = 200;
+
+/// Most pieces in a case
+const MAX_PIECES: usize = 4;
+
+/// Most arguments given to a command, a few past the shell's `MAX_ARGS`
+const MAX_ARGS: usize = 10;
+
+/// Numbers that tend to sit on an edge somewhere
+const NUMBERS: &[&str] = &[
+    "0",
+    "1",
+    "-1",
+    "4",
+    "16",
+    "17",
+    "255",
+    "256",
+    "65536",
+    "4294967295",
+    "4294967296",
+    "99999999999999999999",
+    "0x0",
+    "0x3",
+    "0xFFFFFFFF",
+    "0x100000000",
+    "0x08000000",
+    "0x10000000",
+    "0x20000000",
+    "0x40013800",
+    "0xE000ED00",
+    "0x",
+    "0xG",
+    "-0",
+    "+1",
+];
+
+/// The line endings the shell takes
+const ENDINGS: &[&[u8]] = &[b"\r", b"\n", b"\r\n"];
+
+/// Control sequences the line editor reacts to, and some it should ignore
+const EDITING: &[&[u8]] = &[
+    b"\x08",
+    b"\x7f",
+    b"\x03",
+    b"\x15",
+    b"\x1b",
+    b"\x1b[A",
+    b"\x1b[B",
+    b"\x1b[1;5A",
+    b"\x1b[999999A",
+    b"\x1bO",
+    b"\x1b\x1b[",
+    b"\r",
+    b"\n",
+    b"\r\n",
+    b"\n\r",
+    b"\t",
+    b"\0",
+];
+
+/// A case still holding the choices it was drawn from, for shrinking
+pub type CaseTree = Box<dyn ValueTree<Value = Case>>;
+
+/// Draws cases from [`case`], seeded so `--seed` reproduces a run
+pub struct Generator {
+    runner: TestRunner,
+    strategy: BoxedStrategy<Case>,
+}
+
+impl Generator {
+    /// `commands` are the ones cases may run; leave out any that must not
+    /// run at random, such as `reset`
+    pub fn new(seed: u64, commands: Vec<Command>, max_bytes: usize) -> Generator {
+        let mut bytes = [0; 32];
+        bytes[..8].copy_from_slice(&seed.to_le_bytes());
+        let rng = TestRng::from_seed(RngAlgorithm::ChaCha, &bytes);
+        Generator {
+            runner: TestRunner::new_with_rng(Config::default(), rng),
+            strategy: case(&commands, max_bytes),
+        }
+    }
+
+    pub fn case(&mut self) -> CaseTree {
+        self.strategy
+            .new_tree(&mut self.runner)
+            .expect("case strategies never reject")
+    }
+}
+
+/// How a piece is laid out: a pause before it, and where it is split
+type Layout = (Option<u64>, Option<(Index, u64)>);
+
+/// Cases of up to [`MAX_PIECES`] pieces, sending at most `max_bytes`
+pub fn case(commands: &[Command], max_bytes: usize) -> BoxedStrategy<Case> {
+    let max_bytes = max_bytes.max(1);
+    let layout = (
+        option::weighted(0.3, pause()),
+        option::weighted(0.15, (any::<Index>(), pause())),
+    );
+    vec((piece(commands), layout), 1..=MAX_PIECES)
+        .prop_map(move |pieces| assemble(pieces, max_bytes))
+        .boxed()
+}
+
+fn assemble(pieces: Vec<(Vec<u8>, Layout)>, max_bytes: usize) -> Case {
+    let mut steps = Vec::new();
+    let mut budget = max_bytes;
+    for (mut piece, (pause, split)) in pieces {
+        if budget == 0 {
+            break;
+        }
+        piece.truncate(budget);
+        budget -= piece.len();
+
+        if let (Some(ms), false) = (pause, steps.is_empty()) {
+            steps.push(Step::Pause(ms));
+        }
+        match split {
+            Some((at, ms)) if piece.len() > 1 => {
+                let rest = piece.split_off(1 + at.index(piece.len() - 1));
+                steps.push(Step::Send(piece));
+                steps.push(Step::Pause(ms));
+                steps.push(Step::Send(rest));
+            }
+            _ => steps.push(Step::Send(piece)),
+        }
+    }
+    Case { steps }
+}
+
+fn pause() -> impl Strategy<Value = u64> {
+    0..=MAX_PAUSE
+}
+
+fn piece(commands: &[Command]) -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        35 => command_line(commands),
+        25 => (command_line(commands), vec(mutation(), 1..=4))
+            .prop_map(|(line, mutations)| mutate(line, mutations)),
+        8 => long_line(commands),
+        12 => editing(),
+        10 => vec(any::<u8>(), 1..=32),
+    ]
+}
+
+/// An argument before it is matched to the command it is given to
+#[derive(Clone, Debug)]
+enum Arg {
+    /// One of the command's usage words, or a number if it has none
+    Word(Index),
+    Number(&'static str),
+    Hex(u32),
+    Decimal(u32),
+    Token(String),
+}
+
+impl Arg {
+    fn text(self, words: &[String]) -> String {
+        match self {
+            Arg::Word(index) if !words.is_empty() => index.get(words).clone(),
+            Arg::Word(index) => index.get(NUMBERS).to_string(),
+            Arg::Number(number) => number.to_string(),
+            Arg::Hex(value) => format!("0x{:X}", value),
+            Arg::Decimal(value) => value.to_string(),
+            Arg::Token(token) => token,
+        }
+    }
+}
+
+fn argument() -> impl Strategy<Value = Arg> {
+    prop_oneof![
+        4 => any::<Index>().prop_map(Arg::Word),
+        2 => select(NUMBERS).prop_map(Arg::Number),
+        1 => any::<u32>().prop_map(Arg::Hex),
+        1 => any::<u32>().prop_map(Arg::Decimal),
+        2 => token().prop_map(Arg::Token),
+    ]
+}
+
+fn command_line(commands: &[Command]) -> impl Strategy<Value = Vec<u8>> {
+    // A known command, or None for a made-up name
+    let known = if commands.is_empty() {
+        Just(None).boxed()
+    } else {
+        prop_oneof![
+            9 => select(commands.to_vec()).prop_map(Some),
+            1 => Just(None),
+        ]
+        .boxed()
+    };
+    let args = vec((1..=2usize, argument()), 0..=3);
+    let many = vec((1..=2usize, argument()), 0..=MAX_ARGS);
+    (
+        known,
+        token(),
+        prop_oneof![7 => args, 3 => many],
+        proptest::bool::weighted(0.1),
+        select(ENDINGS),
+    )
+        .prop_map(|(known, made_up, args, trailing, ending)| {
+            let (name, words) = match known {
+                Some(command) => (command.name, command.words),
+                None => (made_up, Vec::new()),
+            };
+            let mut line = name.into_bytes();
+            for (spaces, arg) in args {
+                line.extend(std::iter::repeat_n(b' ', spaces));
+                line.extend_from_slice(arg.text(&words).as_bytes());
+            }
+            if trailing {
+                line.push(b' ');
+            }
+            line.extend_from_slice(ending);
+            line
+        })
+}
+
+/// A word of printable, non-space characters
+fn token() -> impl Strategy<Value = String> {
+    vec(b'!'..=b'~', 1..=12).prop_map(|bytes| bytes.into_iter().map(char::from).collect())
+}
+
+/// One change to a command line; positions are taken relative to the line
+/// as it is when the change is made
+#[derive(Clone, Debug)]
+enum Mutation {
+    /// Flip one bit of a byte
+    Flip(Index, u8),
+    Insert(Index, u8),
+    Remove(Index),
+    /// Insert a control sequence
+    Splice(Index, &'static [u8]),
+    /// Repeat the bytes between two positions
+    Repeat(Index, Index),
+    Truncate(Index),
+}
+
+fn mutation() -> impl Strategy<Value = Mutation> {
+    prop_oneof![
+        (any::<Index>(), 0..8u8).prop_map(|(at, bit)| Mutation::Flip(at, bit)),
+        (any::<Index>(), any::<u8>()).prop_map(|(at, byte)| Mutation::Insert(at, byte)),
+        any::<Index>().prop_map(Mutation::Remove),
+        (any::<Index>(), select(EDITING)).prop_map(|(at, sequence)| Mutation::Splice(at, sequence)),
+        (any::<Index>(), any::<Index>()).prop_map(|(at, end)| Mutation::Repeat(at, end)),
+        any::<Index>().prop_map(Mutation::Truncate),
+    ]
+}
+
+fn mutate(mut bytes: Vec<u8>, mutations: Vec<Mutation>) -> Vec<u8> {
+    for mutation in mutations {
+        let len = bytes.len();
+        match mutation {
+            Mutation::Insert(at, byte) => bytes.insert(at.index(len + 1), byte),
+            _ if len == 0 => {}
+            Mutation::Flip(at, bit) => bytes[at.index(len)] ^= 1 << bit,
+            Mutation::Remove(at) => {
+                bytes.remove(at.index(len));
+            }
+            Mutation::Splice(at, sequence) => {
+                let at = at.index(len);
+                bytes.splice(at..at, sequence.iter().copied());
+            }
+            Mutation::Repeat(at, end) => {
+                let at = at.index(len);
+                let end = at + 1 + end.index(len - at);
+                let repeated = bytes[at..end].to_vec();
+                bytes.splice(at..at, repeated);
+            }
+            Mutation::Truncate(at) => bytes.truncate(at.index(len)),
+        }
+    }
+    bytes
+}
+
+fn long_line(commands: &[Command]) -> impl Strategy<Value = Vec<u8>> {
+    let names: Vec<String> = commands.iter().map(|c| c.name.clone()).collect();
+    let prefix = if names.is_empty() {
+        Just(None).boxed()
+    } else {
+        option::of(select(names)).boxed()
+    };
+    (prefix, vec(b' '..=b'~', LINE_MAX - 2..=LINE_MAX + 40)).prop_map(|(name, mut line)| {
+        if let Some(name) = name {
+            line.splice(0..0, format!("{} ", name).into_bytes());
+        }
+        line.push(b'\r');
+        line
+    })
+}
+
+fn editing() -> impl Strategy<Value = Vec<u8>> {
+    let part = prop_oneof![
+        select(EDITING).prop_map(<[u8]>::to_vec),
+        token().prop_map(String::into_bytes),
+    ];
+    vec(part, 1..=8).prop_map(|parts| parts.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands() -> Vec<Command> {
+        vec![Command {
+            name: "led".into(),
+            words: vec!["on".into(), "off".into()],
+        }]
+    }
+
+    /// The smallest `Index`, which picks the first item whatever the length
+    fn first() -> Index {
+        let mut tree = any::<Index>()
+            .new_tree(&mut TestRunner::deterministic())
+            .unwrap();
+        while tree.simplify() {}
+        tree.current()
+    }
+
+    fn cases(seed: u64, max_bytes: usize, count: usize) -> Vec<Case> {
+        let mut generator = Generator::new(seed, commands(), max_bytes);
+        (0..count).map(|_| generator.case().current()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_cases() {
+        assert_eq!(cases(1729, 96, 50), cases(1729, 96, 50));
+        assert_ne!(cases(1729, 96, 50), cases(1730, 96, 50));
+    }
+
+    #[test]
+    fn cases_stay_in_bounds() {
+        for case in cases(1, 40, 500) {
+            assert!(case.bytes().len() <= 40, "{}", case);
+            assert!(!case.steps.is_empty());
+            assert!(
+                matches!(case.steps.first(), Some(Step::Send(_))),
+                "{}",
+                case
+            );
+            for step in &case.steps {
+                if let Step::Pause(ms) = step {
+                    assert!(*ms <= MAX_PAUSE);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn runs_the_known_commands() {
+        let cases = cases(2, 96, 500);
+        let lines = cases
+            .iter()
+            .filter(|case| case.bytes().starts_with(b"led "))
+            .count();
+        assert!(lines > 50, "{} of 500 cases start with `led `", lines);
+        assert!(cases.iter().any(|case| case
+            .bytes()
+            .windows(6)
+            .any(|w| w == b"led on" || w == b"led of")));
+    }
+
+    #[test]
+    fn mutations() {
+        assert_eq!(
+            mutate(b"led".to_vec(), vec![Mutation::Remove(first())]),
+            b"ed"
+        );
+        assert_eq!(
+            mutate(b"led".to_vec(), vec![Mutation::Truncate(first())]),
+            b""
+        );
+        assert_eq!(
+            mutate(b"led".to_vec(), vec![Mutation::Flip(first(), 5)]),
+            b"Led"
+        );
+        assert_eq!(
+            mutate(b"led".to_vec(), vec![Mutation::Splice(first(), b"\x1b[A")]),
+            b"\x1b[Aled"
+        );
+        assert_eq!(
+            mutate(b"led".to_vec(), vec![Mutation::Repeat(first(), first())]),
+            b"lled"
+        );
+        // Only an insert changes an empty line
+        assert_eq!(
+            mutate(
+                Vec::new(),
+                vec![Mutation::Remove(first()), Mutation::Insert(first(), b'x')]
+            ),
+            b"x"
+        );
+    }
+
+    #[test]
+    fn assemble_splits_and_truncates() {
+        let pieces = vec![
+            (b"led on\r".to_vec(), (Some(50), Some((first(), 20)))),
+            (b"peek\r".to_vec(), (Some(10), None)),
+        ];
+        let case = assemble(pieces, 9);
+        assert_eq!(
+            case.steps,
+            [
+                Step::Send(b"l".to_vec()),
+                Step::Pause(20),
+                Step::Send(b"ed on\r".to_vec()),
+                Step::Pause(10),
+                Step::Send(b"pe".to_vec()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Randomized fuzzing of the firmware shell over a Renode UART
+//!
+//! The shell in `stm32f3_common::shell` takes whatever arrives on USART1.
+//! This crate sends it random and mutated command lines, control
+//! sequences, raw bytes and pauses drawn from proptest strategies
+//! ([`gen::Generator`]), and checks every case for three kinds of failure
+//! ([`check::Failure`]):
+//!
+//! - a hang: the probe line sent after the case is never answered
+//! - a fault: the firmware's HardFault report (`stm32f3_common::fault`)
+//! - a protocol violation: garbage on the UART, or prompts and
+//!   `unknown command` replies that differ from what [`model::Model`], a
+//!   host copy of the shell's line editor, predicts for the bytes sent
+//!
+//! The checks need no knowledge of what each command does, only the
+//! command names, which [`target::Target::discover`] reads from `help`.
+//! A failing case is shrunk ([`shrink::shrink`]) by rerunning the smaller
+//! versions proptest derives from it, and saved in a text form
+//! ([`case::Case`]) that can be edited and replayed:
+//!
+//! ```
+//! use uart_fuzz::{Case, Step};
+//!
+//! let case = Case {
+//!     steps: vec![Step::Send(b"peek 0x0\x1b[A\r".to_vec()), Step::Pause(150)],
+//! };
+//! let text = case.to_string();
+//! assert_eq!(text, "send \"peek 0x0\\x1B[A\\r\"\npause 150\n");
+//! assert_eq!(Case::parse(&text), Ok(case));
+//! ```
+
+use std::fmt;
+use std::path::PathBuf;
+
+pub mod case;
+pub mod check;
+pub mod gen;
+pub mod model;
+pub mod shrink;
+pub mod target;
+
+pub use case::{Case, Step};
+pub use check::Failure;
+pub use target::{Outcome, Target};
+
+/// Errors from the fuzzer itself, as opposed to failures it finds
+#[derive(Debug)]
+pub enum Error {
+    /// Socket, PTY or file I/O failed
+    Io(std::io::Error),
+    /// Could not connect to the Renode terminal
+    Connect { target: String, msg: String },
+    /// Renode closed the terminal
+    Closed,
+    /// The Renode monitor failed
+    Monitor(renode_monitor::Error),
+    /// The firmware never answered, so there is nothing to fuzz
+    NoShell(String),
+    /// A saved case is malformed
+    Case {
+        path: PathBuf,
+        line: usize,
+        msg: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Connect { target, msg } => write!(f, "cannot connect to {}: {}", target, msg),
+            Error::Closed => write!(f, "Renode closed the terminal"),
+            Error::Monitor(err) => write!(f, "monitor: {}", err),
+            Error::NoShell(msg) => write!(f, "no shell on the UART: {}", msg),
+            Error::Case { path, line, msg } => {
+                write!(f, "{}:{}: {}", path.display(), line, msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<renode_monitor::Error> for Error {
+    fn from(err: renode_monitor::Error) -> Self {
+        Error::Monitor(err)
+    }
+}
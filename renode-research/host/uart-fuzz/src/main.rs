@@ -0,0 +1,239 @@
+//! Fuzz the firmware shell over a Renode UART terminal
+//!
+//! Usage: `uart-fuzz (--connect HOST:PORT | --pty PATH) [--monitor PORT] [--seed N] [--cases N]`
+//!
+//! ```text
+//! # renode: emulation CreateServerSocketTerminal 4567 "fuzz" false
+//! #         connector Connect sysbus.usart1 fuzz
+//! uart-fuzz --connect 127.0.0.1:4567 --monitor 1234 --cases 500
+//! uart-fuzz --pty /tmp/uart --seed 42 -v
+//! uart-fuzz --pty /tmp/uart --replay ../target/uart-fuzz/seed-42-case-17.txt
+//! ```
+//!
+//! Stops at the first failing case, shrinks it and saves it for
+//! `--replay`. Exits with 1 when a case failed.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use clap::Parser;
+use proptest::strategy::ValueTree;
+
+use renode_monitor::Monitor;
+use uart_fuzz::gen::{CaseTree, Generator};
+use uart_fuzz::shrink::shrink;
+use uart_fuzz::{check, Case, Error, Failure, Outcome, Target};
+
+/// Transcript lines shown for a failing case
+const SHOW_LINES: usize = 20;
+
+#[derive(Parser)]
+#[command(about = "Fuzz the firmware shell over a Renode UART terminal")]
+struct Args {
+    /// Renode server socket terminal (`CreateServerSocketTerminal`)
+    #[arg(long, value_name = "HOST:PORT", conflicts_with = "pty")]
+    connect: Option<String>,
+
+    /// Renode PTY terminal (`CreateUartPtyTerminal`)
+    #[arg(long, value_name = "PATH")]
+    pty: Option<PathBuf>,
+
+    /// Renode monitor port (`renode --port`), to reset the machine after a
+    /// hang or fault and keep shrinking
+    #[arg(long, value_name = "PORT")]
+    monitor: Option<u16>,
+
+    /// Machine to reset through the monitor
+    #[arg(long, default_value = "STM32F3_Discovery")]
+    machine: String,
+
+    /// How long to keep retrying while the terminal does not exist yet
+    #[arg(long, default_value_t = 10.0, value_name = "SECONDS")]
+    wait: f64,
+
+    /// How long the firmware may take to boot into its shell
+    #[arg(long, default_value_t = 5.0, value_name = "SECONDS")]
+    boot: f64,
+
+    /// How long a case may take to be answered before it counts as a hang
+    #[arg(long, default_value_t = 2.0, value_name = "SECONDS")]
+    timeout: f64,
+
+    /// Seed for the cases (default: from the clock, and printed)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Cases to run
+    #[arg(long, default_value_t = 200)]
+    cases: u32,
+
+    /// Most bytes a case sends
+    #[arg(long, default_value_t = 96)]
+    max_bytes: usize,
+
+    /// Commands never run on purpose
+    #[arg(long, value_delimiter = ',', default_value = "reset,poke")]
+    skip: Vec<String>,
+
+    /// Most runs spent shrinking a failing case
+    #[arg(long, default_value_t = 300)]
+    shrink_budget: usize,
+
+    /// Where failing cases are saved
+    #[arg(long, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../../target/uart-fuzz"))]
+    out: PathBuf,
+
+    /// Run one saved case instead of random ones
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["seed", "cases"])]
+    replay: Option<PathBuf>,
+
+    /// Print every case
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("uart-fuzz: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Ok(false) when a case failed
+fn run(args: &Args) -> Result<bool, Error> {
+    let wait = Duration::from_secs_f64(args.wait.max(0.0));
+    let boot = Duration::from_secs_f64(args.boot.max(0.0));
+    let mut target = match (&args.connect, &args.pty) {
+        (Some(address), _) => Target::connect(address, wait)?,
+        (None, Some(path)) => Target::open_pty(path, wait)?,
+        (None, None) => {
+            return Err(Error::Connect {
+                target: "nothing".into(),
+                msg: "pass --connect HOST:PORT or --pty PATH".into(),
+            })
+        }
+    };
+    target.timeout = Duration::from_secs_f64(args.timeout.max(0.1));
+    if let Some(port) = args.monitor {
+        let mut monitor = Monitor::connect(port, wait)?;
+        monitor.select_machine(&args.machine)?;
+        target.set_monitor(monitor);
+    }
+
+    target.sync(boot)?;
+    let commands = target.discover()?;
+    let names: Vec<&str> = commands.iter().map(|c| c.name.as_str()).collect();
+    println!("commands: {}", names.join(" "));
+
+    if let Some(path) = &args.replay {
+        let case = Case::load(path)?;
+        let outcome = target.run(&case, boot)?;
+        show(&case, &outcome);
+        return Ok(outcome.failure.is_none());
+    }
+
+    let seed = args.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|t| t.as_nanos() as u64)
+            .unwrap_or_default()
+    });
+    let allowed = commands
+        .into_iter()
+        .filter(|c| !args.skip.contains(&c.name))
+        .collect();
+    let mut generator = Generator::new(seed, allowed, args.max_bytes);
+    println!(
+        "seed {}, {} case(s), skipping {}",
+        seed,
+        args.cases,
+        args.skip.join(" ")
+    );
+
+    for number in 1..=args.cases {
+        let mut tree = generator.case();
+        let case = tree.current();
+        if args.verbose {
+            print!("--- case {}\n{}", number, case);
+        }
+        let outcome = target.run(&case, boot)?;
+        let Some(failure) = outcome.failure.clone() else {
+            continue;
+        };
+
+        println!("case {} failed: {}", number, failure);
+        show(&case, &outcome);
+        let (case, outcome) = if failure.is_fatal() && !target.can_reset() {
+            println!("not shrinking: pass --monitor PORT so the machine can be reset");
+            (case, outcome)
+        } else {
+            minimize(&mut target, &mut tree, outcome, args.shrink_budget, boot)?
+        };
+
+        std::fs::create_dir_all(&args.out)?;
+        let path = args.out.join(format!("seed-{}-case-{}.txt", seed, number));
+        let failure = outcome.failure.as_ref().unwrap_or(&failure);
+        let header = format!(
+            "# uart-fuzz --seed {}: case {}, {}\n",
+            seed, number, failure
+        );
+        std::fs::write(&path, header + &case.to_string())?;
+        println!("saved {}", path.display());
+        return Ok(false);
+    }
+    println!("=== {} case(s), no failures ===", args.cases);
+    Ok(true)
+}
+
+/// Shrink a failing case and show what is left of it
+fn minimize(
+    target: &mut Target,
+    tree: &mut CaseTree,
+    outcome: Outcome,
+    budget: usize,
+    boot: Duration,
+) -> Result<(Case, Outcome), Error> {
+    let kind = outcome.failure.as_ref().map(Failure::kind);
+    let size = tree.current().bytes().len();
+    let mut last = outcome;
+    let (small, runs) = shrink(tree, budget, |candidate| {
+        let outcome = target.run(candidate, boot)?;
+        let same = outcome.failure.as_ref().map(Failure::kind) == kind;
+        if same {
+            last = outcome;
+        }
+        Ok(same)
+    })?;
+    println!(
+        "shrunk from {} to {} byte(s) in {} run(s):",
+        size,
+        small.bytes().len(),
+        runs
+    );
+    show(&small, &last);
+    Ok((small, last))
+}
+
+fn show(case: &Case, outcome: &Outcome) {
+    for line in case.to_string().lines() {
+        println!("  {}", line);
+    }
+    let text = String::from_utf8_lossy(&check::normalize(&outcome.output)).replace('\r', "");
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.len() > SHOW_LINES {
+        println!("       | ...");
+    }
+    for line in &lines[lines.len().saturating_sub(SHOW_LINES)..] {
+        println!("       | {}", line);
+    }
+    match &outcome.failure {
+        Some(failure) => println!("=> {}", failure),
+        None => println!("=> ok"),
+    }
+}
@@ -0,0 +1,264 @@
+//! Host copy of the firmware shell's line editor
+//!
+//! Mirrors `Shell::input` in `stm32f3_common::shell` byte for byte, so
+//! feeding it what a case sends predicts what the shell must answer
+//! without knowing what any command does:
+//!
+//! - one prompt for every line that ends and every Ctrl-C
+//! - `unknown command: <name> (try `help`)` for a line whose first word
+//!   is not a command, and `unknown command: <name>` for `help <name>`
+//!
+//! ```
+//! use uart_fuzz::model::Model;
+//!
+//! let commands = vec!["led".to_string(), "peek".to_string()];
+//! let mut model = Model::new(&commands, "led 3 on");
+//! // Typed, erased with Ctrl-U, then the up arrow recalls `led 3 on`
+//! model.feed(b"blink\x15\x1b[A\rblonk\n\x03");
+//! assert_eq!(model.lines, ["led 3 on", "blonk"]);
+//! assert_eq!(model.prompts, 3);
+//! assert_eq!(model.replies, ["unknown command: blonk (try `help`)"]);
+//! ```
+//!
+//! Any change to the shell's editing keys or error messages has to be
+//! made here too.
+
+/// Longest command line (`shell::LINE_MAX`); further characters are dropped
+pub const LINE_MAX: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Input {
+    Normal,
+    /// Just after CR: an LF completes CRLF rather than ending another line
+    AfterCr,
+    /// After ESC
+    Escape,
+    /// Inside an `ESC [` control sequence
+    Csi,
+}
+
+#[derive(Clone, Debug)]
+pub struct Model<'a> {
+    commands: &'a [String],
+    input: Input,
+    line: Vec<u8>,
+    previous: Vec<u8>,
+    /// Prompts the shell prints
+    pub prompts: usize,
+    /// Lines the shell runs, including empty ones
+    pub lines: Vec<String>,
+    /// `unknown command` replies, in order
+    pub replies: Vec<String>,
+}
+
+impl<'a> Model<'a> {
+    /// The shell right after it ran `previous`, ended with a CR
+    pub fn new(commands: &'a [String], previous: &str) -> Model<'a> {
+        Model {
+            commands,
+            input: Input::AfterCr,
+            line: Vec::new(),
+            previous: previous.as_bytes().to_vec(),
+            prompts: 0,
+            lines: Vec::new(),
+            replies: Vec::new(),
+        }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.input(byte);
+        }
+    }
+
+    fn input(&mut self, byte: u8) {
+        let state = std::mem::replace(&mut self.input, Input::Normal);
+        match (state, byte) {
+            (Input::AfterCr, b'\n') => {}
+            (Input::Escape, b'[') => self.input = Input::Csi,
+            (Input::Escape, _) => {}
+            (Input::Csi, b'A') => self.line = self.previous.clone(),
+            (Input::Csi, 0x20..=0x3F) => self.input = Input::Csi,
+            (Input::Csi, _) => {}
+            (_, b'\r' | b'\n') => {
+                if byte == b'\r' {
+                    self.input = Input::AfterCr;
+                }
+                self.execute();
+                self.prompts += 1;
+            }
+            (_, 0x1B) => self.input = Input::Escape,
+            (_, 0x08 | 0x7F) if !self.line.is_empty() => {
+                self.line.pop();
+            }
+            // Ctrl-C
+            (_, 0x03) => {
+                self.line.clear();
+                self.prompts += 1;
+            }
+            // Ctrl-U
+            (_, 0x15) => self.line.clear(),
+            (_, 0x20..=0x7E) if self.line.len() < LINE_MAX => self.line.push(byte),
+            _ => {}
+        }
+    }
+
+    fn execute(&mut self) {
+        let line = String::from_utf8_lossy(&std::mem::take(&mut self.line)).into_owned();
+        if !line.is_empty() {
+            self.previous = line.as_bytes().to_vec();
+        }
+        let mut words = line.split_ascii_whitespace();
+        match words.next() {
+            // Built in unless a firmware registers its own, which none does
+            Some("help") => {
+                if let Some(name) = words.next().filter(|name| !self.known(name)) {
+                    self.replies.push(format!("unknown command: {}", name));
+                }
+            }
+            Some(name) if !self.known(name) => {
+                self.replies
+                    .push(format!("unknown command: {} (try `help`)", name));
+            }
+            _ => {}
+        }
+        self.lines.push(line);
+    }
+
+    fn known(&self, name: &str) -> bool {
+        self.commands.iter().any(|command| command == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A session recorded from `stm32f3-uart`: `help`, then four lines
+    const TRANSCRIPT: &str = include_str!("../../../stm32f3-uart/tests/uart.golden");
+
+    /// Command names from the transcript's `help` listing
+    fn listed(transcript: &str) -> Vec<String> {
+        transcript
+            .lines()
+            .skip_while(|line| *line != "> help")
+            .skip(1)
+            .take_while(|line| line.starts_with("  "))
+            .filter_map(|line| line.split_whitespace().next())
+            .map(String::from)
+            .collect()
+    }
+
+    fn commands(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn predicts_the_recorded_session() {
+        let commands = listed(TRANSCRIPT);
+        assert_eq!(commands, ["led", "peek", "poke", "reset", "help"]);
+
+        // Each line was typed after a prompt, which the shell echoes with it
+        let typed: Vec<&str> = TRANSCRIPT
+            .lines()
+            .filter_map(|line| line.strip_prefix("> "))
+            .collect();
+        let mut model = Model::new(&commands, "");
+        for line in &typed {
+            model.feed(line.as_bytes());
+            model.feed(b"\r");
+        }
+        assert_eq!(model.lines, typed);
+        assert_eq!(model.prompts, typed.len());
+        let replies: Vec<&str> = TRANSCRIPT
+            .lines()
+            .filter(|line| line.starts_with("unknown command"))
+            .collect();
+        assert_eq!(model.replies, replies);
+        assert_eq!(replies, ["unknown command: blink (try `help`)"]);
+    }
+
+    #[test]
+    fn line_endings() {
+        let commands = commands(&["led"]);
+        let mut model = Model::new(&commands, "");
+        // CRLF ends one line, LF alone and LFCR end two
+        model.feed(b"led 3 on\r\nled 4 on\nled 5 on\n\r");
+        assert_eq!(model.lines, ["led 3 on", "led 4 on", "led 5 on", ""]);
+        assert_eq!(model.prompts, 4);
+        assert!(model.replies.is_empty());
+    }
+
+    #[test]
+    fn starts_just_after_a_cr() {
+        let commands = commands(&["led"]);
+        let mut model = Model::new(&commands, "led 3 on");
+        model.feed(b"\n");
+        assert_eq!(model.prompts, 0);
+        model.feed(b"\n");
+        assert_eq!(model.lines, [""]);
+    }
+
+    #[test]
+    fn editing_keys() {
+        let commands = commands(&["led"]);
+        let mut model = Model::new(&commands, "");
+        // Backspace and DEL, also on an empty line
+        model.feed(b"\x08\x7fledd\x08 3\x7f4 on\r");
+        // Ctrl-C drops the line and prompts again without running it
+        model.feed(b"blink\x03");
+        // Ctrl-U erases the line but keeps editing
+        model.feed(b"blink\x15led 5 on\r");
+        assert_eq!(model.lines, ["led 4 on", "led 5 on"]);
+        assert_eq!(model.prompts, 3);
+        assert!(model.replies.is_empty());
+    }
+
+    #[test]
+    fn up_arrow_recalls_the_last_non_empty_line() {
+        let commands = commands(&["led"]);
+        let mut model = Model::new(&commands, "led 3 on");
+        model.feed(b"\r\x1b[A\r");
+        assert_eq!(model.lines, ["", "led 3 on"]);
+        // Recall replaces what was typed, and the line can be edited on
+        model.feed(b"xyz\x1b[A\x08\x08off\r");
+        assert_eq!(model.lines[2], "led 3 off");
+    }
+
+    #[test]
+    fn other_escape_sequences_are_ignored() {
+        let commands = commands(&["led"]);
+        let mut model = Model::new(&commands, "led 3 on");
+        // Down arrow, a parameterized one, ESC O and ESC x are swallowed
+        model.feed(b"\x1b[B\x1b[1;5B\x1bOled\x1bx\r");
+        assert_eq!(model.lines, ["led"]);
+        // A parameterized `A` is still an up arrow
+        model.feed(b"\x1b[999999A\r");
+        assert_eq!(model.lines[1], "led");
+    }
+
+    #[test]
+    fn drops_characters_past_line_max() {
+        let commands = commands(&["led"]);
+        let mut model = Model::new(&commands, "");
+        let long = "x".repeat(LINE_MAX + 10);
+        model.feed(long.as_bytes());
+        model.feed(b"\t\0\xff\r");
+        assert_eq!(model.lines, ["x".repeat(LINE_MAX)]);
+    }
+
+    #[test]
+    fn unknown_commands() {
+        let commands = commands(&["led"]);
+        let mut model = Model::new(&commands, "");
+        model.feed(b"  blink  fast \rhelp\rhelp led\rhelp blink\r");
+        assert_eq!(
+            model.replies,
+            [
+                "unknown command: blink (try `help`)",
+                "unknown command: blink",
+            ]
+        );
+        assert_eq!(model.prompts, 4);
+    }
+}
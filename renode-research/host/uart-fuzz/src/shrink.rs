@@ -0,0 +1,148 @@
+//! Shrinking a failing case to a small one that fails the same way
+//!
+//! Cases come from [`crate::gen`] as proptest value trees, which know the
+//! choices a case was drawn from and how to make each one simpler. This
+//! walks the tree the way proptest's own runner does, only against the
+//! firmware: a candidate that still fails is kept and simplified further,
+//! one that passes is complicated back a step, until the tree has nothing
+//! left to try or the run budget is spent:
+//!
+//! ```
+//! use proptest::strategy::ValueTree;
+//! use uart_fuzz::gen::Generator;
+//! use uart_fuzz::shrink::shrink;
+//! use uart_fuzz::Case;
+//!
+//! // Pretend an escape byte is what breaks the firmware
+//! let fails = |case: &Case| Ok(case.bytes().contains(&0x1b));
+//! let mut generator = Generator::new(42, Vec::new(), 96);
+//! let mut tree = generator.case();
+//! while !fails(&tree.current())? {
+//!     tree = generator.case();
+//! }
+//! let (small, runs) = shrink(&mut tree, 300, fails)?;
+//! assert!(small.bytes().contains(&0x1b));
+//! assert!(runs <= 300);
+//! # Ok::<(), uart_fuzz::Error>(())
+//! ```
+
+use proptest::strategy::ValueTree;
+
+use crate::case::Case;
+use crate::Error;
+
+/// Shrink the failing case `tree` holds, calling `fails` at most `budget`
+/// times; returns the smallest failing case found and how many runs it took
+pub fn shrink<T>(
+    tree: &mut T,
+    budget: usize,
+    mut fails: impl FnMut(&Case) -> Result<bool, Error>,
+) -> Result<(Case, usize), Error>
+where
+    T: ValueTree<Value = Case> + ?Sized,
+{
+    let mut best = tree.current();
+    let mut runs = 0;
+    if !tree.simplify() {
+        return Ok((best, runs));
+    }
+    while runs < budget {
+        let candidate = tree.current();
+        runs += 1;
+        let more = if fails(&candidate)? {
+            best = candidate;
+            tree.simplify()
+        } else {
+            tree.complicate()
+        };
+        if !more {
+            break;
+        }
+    }
+    Ok((best, runs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::{CaseTree, Generator};
+    use crate::target::Command;
+
+    fn commands() -> Vec<Command> {
+        vec![
+            Command {
+                name: "led".into(),
+                words: vec!["3".into(), "10".into(), "on".into(), "off".into()],
+            },
+            Command {
+                name: "peek".into(),
+                words: Vec::new(),
+            },
+        ]
+    }
+
+    /// The first case `fails` holds for, from a fixed seed
+    fn failing(fails: impl Fn(&Case) -> bool) -> CaseTree {
+        let mut generator = Generator::new(7, commands(), 96);
+        for _ in 0..1000 {
+            let tree = generator.case();
+            if fails(&tree.current()) {
+                return tree;
+            }
+        }
+        panic!("no failing case in 1000");
+    }
+
+    fn has_escape(case: &Case) -> bool {
+        case.bytes().contains(&0x1b)
+    }
+
+    #[test]
+    fn keeps_what_fails() {
+        let mut tree = failing(has_escape);
+        let case = tree.current();
+        let (small, runs) = shrink(&mut tree, 500, |case| Ok(has_escape(case))).unwrap();
+        assert!(has_escape(&small));
+        assert!(small.size() < case.size(), "{} from {}", small, case);
+        // Nothing but the escape byte, with maybe a byte drawn alongside it
+        assert!(small.bytes().len() <= 2, "{}", small);
+        assert!(runs > 0 && runs <= 500);
+    }
+
+    #[test]
+    fn shrinks_the_failing_line() {
+        let led = |case: &Case| {
+            let bytes = case.bytes();
+            bytes.windows(6).any(|w| w == b"led 10")
+        };
+        let mut tree = failing(led);
+        let (small, _) = shrink(&mut tree, 1000, |case| Ok(led(case))).unwrap();
+        assert!(led(&small));
+        assert!(small.bytes().len() <= 8, "{}", small);
+    }
+
+    #[test]
+    fn stops_at_the_budget() {
+        let mut tree = failing(|_| true);
+        let (_, runs) = shrink(&mut tree, 3, |_| Ok(true)).unwrap();
+        assert_eq!(runs, 3);
+        let (_, runs) = shrink(&mut tree, 0, |_| Ok(true)).unwrap();
+        assert_eq!(runs, 0);
+    }
+
+    #[test]
+    fn returns_the_case_when_nothing_smaller_fails() {
+        let mut tree = failing(|case| case.bytes().len() > 20);
+        let case = tree.current();
+        let (small, runs) = shrink(&mut tree, 1000, |_| Ok(false)).unwrap();
+        assert_eq!(small, case);
+        assert!(runs > 0);
+    }
+
+    #[test]
+    fn passes_errors_on() {
+        let mut tree = failing(|_| true);
+        let result = shrink(&mut tree, 10, |_| Err(Error::Closed));
+        assert!(matches!(result, Err(Error::Closed)));
+    }
+}
@@ -0,0 +1,468 @@
+//! The firmware shell on a Renode UART terminal
+//!
+//! Every exchange ends with a probe: Ctrl-C twice, which leaves any escape
+//! sequence and clears the line whatever state the shell is in, then a
+//! made-up command `fuzz-probe-N`. The shell's `unknown command` reply to
+//! it marks the end of the case's output, and its absence a hang. `N`
+//! counts up, so a late reply to an earlier probe never matches.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::sys::termios::{self, SetArg};
+use renode_monitor::Monitor;
+
+use crate::case::{Case, Step};
+use crate::check::{self, Failure, FAULT_MARKER};
+use crate::model::Model;
+use crate::Error;
+
+/// A shell command found by [`Target::discover`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Command {
+    pub name: String,
+    /// Words from its usage line (`on|off|toggle` gives `on`, `off` and
+    /// `toggle`; `<3-10>` gives `3` and `10`)
+    pub words: Vec<String>,
+}
+
+/// What came back for a case
+#[derive(Clone, Debug)]
+pub struct Outcome {
+    /// Everything received, up to the reply to the probe
+    pub output: Vec<u8>,
+    pub failure: Option<Failure>,
+}
+
+pub struct Target {
+    chunks: Receiver<Vec<u8>>,
+    writer: Box<dyn Write + Send>,
+    received: Vec<u8>,
+    probes: u32,
+    /// The line the shell ran last, for its up-arrow recall
+    previous: String,
+    /// How long a case may take to be answered
+    pub timeout: Duration,
+    /// Command names, without the built-in `help`
+    pub commands: Vec<String>,
+    /// Resets the machine after a hang or fault
+    monitor: Option<Monitor>,
+}
+
+impl Target {
+    /// Connect to a Renode server socket terminal, retrying until `wait`
+    /// since Renode may not have created it yet
+    pub fn connect(address: &str, wait: Duration) -> Result<Target, Error> {
+        let start = Instant::now();
+        let stream = loop {
+            match TcpStream::connect(address) {
+                Ok(stream) => break stream,
+                Err(err) if start.elapsed() > wait => {
+                    return Err(Error::Connect {
+                        target: address.to_string(),
+                        msg: err.to_string(),
+                    })
+                }
+                Err(_) => thread::sleep(Duration::from_millis(100)),
+            }
+        };
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+        Ok(Target::new(reader, stream))
+    }
+
+    /// Open a Renode PTY terminal, waiting up to `wait` for it to appear
+    pub fn open_pty(path: &Path, wait: Duration) -> Result<Target, Error> {
+        let start = Instant::now();
+        let file = loop {
+            match OpenOptions::new().read(true).write(true).open(path) {
+                Ok(file) => break file,
+                Err(err) if start.elapsed() > wait => {
+                    return Err(Error::Connect {
+                        target: path.display().to_string(),
+                        msg: err.to_string(),
+                    })
+                }
+                Err(_) => thread::sleep(Duration::from_millis(100)),
+            }
+        };
+
+        // Raw mode: control characters must reach the UART untouched
+        if let Ok(mut attrs) = termios::tcgetattr(&file) {
+            termios::cfmakeraw(&mut attrs);
+            let _ = termios::tcsetattr(&file, SetArg::TCSANOW, &attrs);
+        }
+        let reader: File = file.try_clone()?;
+        Ok(Target::new(reader, file))
+    }
+
+    fn new(mut reader: impl Read + Send + 'static, writer: impl Write + Send + 'static) -> Target {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0u8; 1024];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        if tx.send(buf[..n].to_vec()).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+        Target {
+            chunks: rx,
+            writer: Box::new(writer),
+            received: Vec::new(),
+            probes: 0,
+            previous: String::new(),
+            timeout: Duration::from_secs(2),
+            commands: Vec::new(),
+            monitor: None,
+        }
+    }
+
+    /// Reset the machine through the Renode monitor after a hang or fault
+    pub fn set_monitor(&mut self, monitor: Monitor) {
+        self.monitor = Some(monitor);
+    }
+
+    pub fn can_reset(&self) -> bool {
+        self.monitor.is_some()
+    }
+
+    /// Wait up to `wait` for the shell to answer a probe, probing again
+    /// every [`timeout`](Self::timeout) while the firmware boots
+    pub fn sync(&mut self, wait: Duration) -> Result<(), Error> {
+        let start = Instant::now();
+        loop {
+            self.received.clear();
+            if self.probe(self.timeout.min(wait))? {
+                return Ok(());
+            }
+            if start.elapsed() >= wait {
+                return Err(Error::NoShell(format!(
+                    "no reply to `{}` within {:.1} s",
+                    self.previous,
+                    wait.as_secs_f64()
+                )));
+            }
+        }
+    }
+
+    /// Reset the machine and wait up to `wait` for the shell
+    pub fn reset(&mut self, wait: Duration) -> Result<(), Error> {
+        let Some(monitor) = self.monitor.as_mut() else {
+            return Err(Error::NoShell(
+                "the firmware is stuck and there is no monitor to reset it".into(),
+            ));
+        };
+        monitor.reset_machine()?;
+        self.sync(wait)
+    }
+
+    /// Learn the command names from `help`, and their arguments from
+    /// `help <name>`
+    pub fn discover(&mut self) -> Result<Vec<Command>, Error> {
+        let listing = self.exchange(b"help\r")?;
+        let mut commands: Vec<Command> = text(&listing)
+            .lines()
+            .filter(|line| line.starts_with("  "))
+            .filter_map(|line| line.split_whitespace().next())
+            .filter(|&name| name != "help")
+            .map(|name| Command {
+                name: name.to_string(),
+                words: Vec::new(),
+            })
+            .collect();
+        if commands.is_empty() {
+            return Err(Error::NoShell("`help` lists no commands".into()));
+        }
+
+        for command in &mut commands {
+            let request = format!("help {}\r", command.name);
+            let answer = self.exchange(request.as_bytes())?;
+            let prefix = format!("{} ", command.name);
+            if let Some(usage) = text(&answer)
+                .lines()
+                .find_map(|line| line.strip_prefix(&prefix))
+            {
+                command.words = usage_words(usage);
+            }
+        }
+        self.commands = commands.iter().map(|c| c.name.clone()).collect();
+        Ok(commands)
+    }
+
+    /// Send `case` and check the answer
+    ///
+    /// After a failure that leaves the firmware stuck, the next call resets
+    /// the machine first (which needs the monitor).
+    pub fn run(&mut self, case: &Case, boot: Duration) -> Result<Outcome, Error> {
+        if self.previous.is_empty() {
+            self.reset(boot)?;
+        }
+        let previous = self.previous.clone();
+        self.received.clear();
+        for step in &case.steps {
+            match step {
+                Step::Send(bytes) => self.send(bytes)?,
+                Step::Pause(ms) => thread::sleep(Duration::from_millis(*ms)),
+            }
+        }
+        let answered = self.probe(self.timeout)?;
+        let output = std::mem::take(&mut self.received);
+
+        let failure = if let Some(report) = check::fault(&output) {
+            Some(Failure::Fault(report))
+        } else if !answered {
+            // A live shell that missed the probe lost input; a dead one hangs
+            if self.probe(self.timeout)? {
+                Some(Failure::Violation(
+                    "no reply to the probe after the case, but to the next one".into(),
+                ))
+            } else {
+                Some(Failure::Hang)
+            }
+        } else {
+            let mut model = Model::new(&self.commands, &previous);
+            model.feed(&case.bytes());
+            model.feed(&probe_bytes(&self.previous));
+            let mut shown = b"> ".to_vec();
+            shown.extend_from_slice(&output);
+            check::check(&shown, &model).err()
+        };
+        if failure.as_ref().is_some_and(Failure::is_fatal) {
+            self.previous.clear();
+        }
+        Ok(Outcome { output, failure })
+    }
+
+    /// Send `bytes` and a probe, and return what came back
+    fn exchange(&mut self, bytes: &[u8]) -> Result<Vec<u8>, Error> {
+        self.received.clear();
+        self.send(bytes)?;
+        if !self.probe(self.timeout)? {
+            return Err(Error::NoShell(format!(
+                "no reply to `{}`",
+                String::from_utf8_lossy(bytes).trim_end()
+            )));
+        }
+        Ok(std::mem::take(&mut self.received))
+    }
+
+    /// Send the next probe and wait up to `timeout` for its reply, leaving
+    /// everything received up to the reply in `received`
+    fn probe(&mut self, timeout: Duration) -> Result<bool, Error> {
+        self.probes += 1;
+        self.previous = format!("fuzz-probe-{}", self.probes);
+        let reply = format!("unknown command: {} (try `help`)\r\n> ", self.previous);
+        self.send(&probe_bytes(&self.previous))?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(end) = find(&self.received, reply.as_bytes()) {
+                self.received.truncate(end + reply.len());
+                return Ok(true);
+            }
+            // A complete fault report ends the wait
+            if let Some(at) = find(&self.received, FAULT_MARKER.as_bytes()) {
+                if self.received[at..].contains(&b'\n') {
+                    return Ok(false);
+                }
+            }
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.chunks.recv_timeout(left) {
+                Ok(chunk) => self.received.extend_from_slice(&chunk),
+                Err(RecvTimeoutError::Timeout) => return Ok(false),
+                Err(RecvTimeoutError::Disconnected) => return Err(Error::Closed),
+            }
+        }
+    }
+
+    fn send(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.writer.write_all(bytes)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Ctrl-C twice, then the probe line
+fn probe_bytes(line: &str) -> Vec<u8> {
+    let mut bytes = b"\x03\x03".to_vec();
+    bytes.extend_from_slice(line.as_bytes());
+    bytes.push(b'\r');
+    bytes
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(&check::normalize(bytes)).replace('\r', "")
+}
+
+/// Literal words and range ends in a usage line such as
+/// `<3-10> on|off|toggle` or `<suite>...|all|default`
+fn usage_words(usage: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for token in usage.split(|c: char| c.is_whitespace() || c == '|') {
+        let token = token.trim_end_matches("...");
+        if let Some(inner) = token.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+            // A placeholder: only number ranges say anything
+            if let Some((low, high)) = inner.split_once('-') {
+                if low.parse::<u32>().is_ok() && high.parse::<u32>().is_ok() {
+                    words.push(low.to_string());
+                    words.push(high.to_string());
+                }
+            }
+        } else if !token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric()) {
+            // `[count]` and the like are optional placeholders and skipped
+            words.push(token.to_string());
+        }
+    }
+    words.dedup();
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::mpsc::Sender;
+
+    use super::*;
+
+    /// A shell with one command, `led`, that goes silent after `crash`
+    struct Shell {
+        line: Vec<u8>,
+        replies: Sender<Vec<u8>>,
+        dead: bool,
+    }
+
+    impl Write for Shell {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            for &byte in bytes {
+                match byte {
+                    0x03 => self.line.clear(),
+                    b'\r' => {
+                        let line = String::from_utf8_lossy(&self.line).into_owned();
+                        self.line.clear();
+                        let reply = match line.as_str() {
+                            _ if self.dead => continue,
+                            "help" => {
+                                "Commands:\r\n  led    Set the LED\r\n  help   List commands\r\n"
+                                    .into()
+                            }
+                            "help led" => "led <1-4> on|off|toggle\r\n".into(),
+                            "crash" => {
+                                self.dead = true;
+                                format!("{} HardFault at 0x08000100\r\n", FAULT_MARKER)
+                            }
+                            _ => format!("unknown command: {} (try `help`)\r\n> ", line),
+                        };
+                        let _ = self
+                            .replies
+                            .send(format!("{}\r\n{}", line, reply).into_bytes());
+                    }
+                    _ => self.line.push(byte),
+                }
+            }
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct Replies(Receiver<Vec<u8>>);
+
+    impl Read for Replies {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.recv() {
+                Ok(chunk) => {
+                    buf[..chunk.len()].copy_from_slice(&chunk);
+                    Ok(chunk.len())
+                }
+                Err(_) => Ok(0),
+            }
+        }
+    }
+
+    fn shell() -> Target {
+        let (tx, rx) = mpsc::channel();
+        let shell = Shell {
+            line: Vec::new(),
+            replies: tx,
+            dead: false,
+        };
+        Target::new(Replies(rx), shell)
+    }
+
+    #[test]
+    fn reads_usage_words() {
+        assert_eq!(
+            usage_words("<3-10> on|off|toggle"),
+            ["3", "10", "on", "off", "toggle"]
+        );
+        assert_eq!(usage_words("<suite>...|all|default"), ["all", "default"]);
+        assert_eq!(usage_words("<name> [count] <a-b>"), Vec::<String>::new());
+        assert_eq!(usage_words("on on|off"), ["on", "off"]);
+    }
+
+    #[test]
+    fn probes_after_clearing_the_line() {
+        assert_eq!(probe_bytes("fuzz-probe-1"), b"\x03\x03fuzz-probe-1\r");
+        assert_eq!(find(b"abcabc", b"ca"), Some(2));
+        assert_eq!(find(b"ab", b"abc"), None);
+        assert_eq!(text(b"ab\x08c\r\nd"), "ac\nd");
+    }
+
+    #[test]
+    fn discovers_commands() {
+        let mut target = shell();
+        target.sync(Duration::from_secs(5)).unwrap();
+        let commands = target.discover().unwrap();
+        assert_eq!(
+            commands,
+            [Command {
+                name: "led".into(),
+                words: ["1", "4", "on", "off", "toggle"].map(String::from).to_vec(),
+            }]
+        );
+        assert_eq!(target.commands, ["led"]);
+    }
+
+    #[test]
+    fn a_fault_report_ends_the_wait() {
+        let mut target = shell();
+        target.timeout = Duration::from_secs(30);
+        let start = Instant::now();
+        let err = target.exchange(b"crash\r").unwrap_err();
+        assert!(matches!(err, Error::NoShell(_)));
+        assert!(start.elapsed() < target.timeout);
+        assert!(!target.can_reset());
+        assert!(matches!(
+            target.reset(Duration::from_secs(1)),
+            Err(Error::NoShell(_))
+        ));
+    }
+
+    #[test]
+    fn a_closed_terminal_is_an_error() {
+        let mut target = Target::new(io::empty(), io::sink());
+        assert!(matches!(
+            target.sync(Duration::from_secs(1)),
+            Err(Error::Closed)
+        ));
+    }
+}
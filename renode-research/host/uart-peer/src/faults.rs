@@ -0,0 +1,99 @@
+//! Fault injection on the lines a role sends
+//!
+//! Faults are counted in sent lines rather than drawn at random, so a test
+//! sees the same faults on every run: `--drop-every 3` always swallows the
+//! third, sixth, ... line.
+
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default)]
+pub struct Faults {
+    /// Pause before answering a received line
+    pub delay: Duration,
+    /// Drop every Nth line sent
+    pub drop_every: Option<u32>,
+    /// Flip a bit in every Nth line sent
+    pub corrupt_every: Option<u32>,
+    /// Lines offered so far
+    sent: u32,
+}
+
+impl Faults {
+    pub fn new(delay: Duration, drop_every: Option<u32>, corrupt_every: Option<u32>) -> Faults {
+        Faults {
+            delay,
+            drop_every,
+            corrupt_every,
+            sent: 0,
+        }
+    }
+
+    /// The line to send in place of `line`, or `None` to drop it
+    ///
+    /// A corrupted line has bit 0 of its middle character flipped, which
+    /// keeps it printable ASCII but breaks an NMEA checksum or turns `OK`
+    /// into `OJ`.
+    pub fn apply(&mut self, line: &str) -> Option<String> {
+        self.sent += 1;
+        let due = |every: Option<u32>| every.is_some_and(|n| n > 0 && self.sent.is_multiple_of(n));
+
+        if due(self.drop_every) {
+            return None;
+        }
+        if due(self.corrupt_every) && !line.is_empty() {
+            let mut bytes = line.as_bytes().to_vec();
+            let middle = bytes.len() / 2;
+            bytes[middle] ^= 0x01;
+            return Some(String::from_utf8_lossy(&bytes).into_owned());
+        }
+        Some(line.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sent(faults: &mut Faults, lines: &[&str]) -> Vec<Option<String>> {
+        lines.iter().map(|line| faults.apply(line)).collect()
+    }
+
+    #[test]
+    fn no_faults_by_default() {
+        let mut faults = Faults::default();
+        assert_eq!(
+            sent(&mut faults, &["OK", "RDY"]),
+            [Some("OK".into()), Some("RDY".into())]
+        );
+    }
+
+    #[test]
+    fn drops_every_nth_line() {
+        let mut faults = Faults::new(Duration::ZERO, Some(3), None);
+        let lines = sent(&mut faults, &["1", "2", "3", "4", "5", "6", "7"]);
+        let dropped: Vec<usize> = (0..lines.len()).filter(|&i| lines[i].is_none()).collect();
+        assert_eq!(dropped, [2, 5]);
+    }
+
+    #[test]
+    fn corrupts_the_middle_character() {
+        let mut faults = Faults::new(Duration::ZERO, None, Some(2));
+        assert_eq!(
+            sent(&mut faults, &["OK", "OK", "", "$GPGGA*47"]),
+            [
+                Some("OK".into()),
+                Some("OJ".into()),
+                Some("".into()),
+                Some("$GPGFA*47".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn dropping_wins_and_zero_is_off() {
+        let mut faults = Faults::new(Duration::ZERO, Some(2), Some(2));
+        assert_eq!(sent(&mut faults, &["a", "b"]), [Some("a".into()), None]);
+        let mut faults = Faults::new(Duration::ZERO, Some(0), Some(0));
+        assert_eq!(sent(&mut faults, &["a"]), [Some("a".into())]);
+    }
+}
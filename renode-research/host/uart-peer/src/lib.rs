@@ -0,0 +1,205 @@
+//! Scripted devices on the other end of an emulated UART
+//!
+//! Renode's built-in models only cover a few external devices. This crate
+//! connects to a Renode UART terminal, either a server socket
+//! (`emulation CreateServerSocketTerminal 4567 "peer" false`) or a PTY
+//! (`emulation CreateUartPtyTerminal`), and plays a device role on it:
+//!
+//! | Role | Device |
+//! |------|--------|
+//! | [`nmea::Gps`] | GPS receiver streaming `GGA`/`RMC` sentences |
+//! | [`modem::Modem`] | AT-command modem with a GNSS stream (`AT+CGNSTST=1`) |
+//! | [`script::Script`] | Command/response rules read from a file |
+//!
+//! [`Faults`] sits between the role and the link to delay, drop or corrupt
+//! the lines it sends, so the firmware's error handling can be tested
+//! end to end.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Instant;
+
+pub mod faults;
+pub mod link;
+pub mod modem;
+pub mod nmea;
+pub mod script;
+
+pub use faults::Faults;
+pub use link::Link;
+
+/// Errors from the peer
+#[derive(Debug)]
+pub enum Error {
+    /// Socket, PTY or file I/O failed
+    Io(std::io::Error),
+    /// Could not connect to the Renode terminal
+    Connect { target: String, msg: String },
+    /// A script file is malformed
+    Script {
+        path: PathBuf,
+        line: usize,
+        msg: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Connect { target, msg } => write!(f, "cannot connect to {}: {}", target, msg),
+            Error::Script { path, line, msg } => {
+                write!(f, "{}:{}: {}", path.display(), line, msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// A device played on the link
+///
+/// Roles work on whole lines without terminators; [`serve`] adds `\r\n`.
+pub trait Role {
+    /// Lines sent as soon as the link is up (a modem's `RDY` banner)
+    fn greeting(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Lines answering one received line
+    fn respond(&mut self, line: &str) -> Vec<String>;
+
+    /// Unsolicited lines due at `now`
+    fn poll(&mut self, _now: Instant) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// When [`poll`](Self::poll) next has something to send
+    fn next_due(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Whether the role has nothing more to do (a finite GPS stream)
+    fn finished(&self) -> bool {
+        false
+    }
+}
+
+/// Run `role` on `link` until the other end closes it or the role finishes
+///
+/// With `verbose`, every line is echoed to stderr as `< received` and
+/// `> sent`.
+pub fn serve(
+    link: &mut Link,
+    role: &mut dyn Role,
+    faults: &mut Faults,
+    verbose: bool,
+) -> Result<(), Error> {
+    let greeting = role.greeting();
+    send(link, faults, &greeting, verbose)?;
+
+    while !role.finished() {
+        let timeout = role
+            .next_due()
+            .map(|due| due.saturating_duration_since(Instant::now()));
+        match link.recv_line(timeout) {
+            Ok(Some(line)) => {
+                if verbose {
+                    eprintln!("< {}", line);
+                }
+                let reply = role.respond(&line);
+                if !reply.is_empty() && !faults.delay.is_zero() {
+                    std::thread::sleep(faults.delay);
+                }
+                send(link, faults, &reply, verbose)?;
+            }
+            Ok(None) => {}
+            Err(link::Closed) => return Ok(()),
+        }
+        let due = role.poll(Instant::now());
+        send(link, faults, &due, verbose)?;
+    }
+    Ok(())
+}
+
+fn send(
+    link: &mut Link,
+    faults: &mut Faults,
+    lines: &[String],
+    verbose: bool,
+) -> Result<(), Error> {
+    for line in lines {
+        let Some(line) = faults.apply(line) else {
+            if verbose {
+                eprintln!("> (dropped) {}", line);
+            }
+            continue;
+        };
+        if verbose {
+            eprintln!("> {}", line);
+        }
+        link.send_line(&line)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::Duration;
+
+    use crate::link::tests::{link, open};
+    use crate::modem::Modem;
+    use crate::script::Script;
+
+    #[test]
+    fn serves_until_the_link_closes() {
+        let (mut link, output) = link(b"ATE0\rATI\rAT+FOO\r");
+        let mut modem = Modem::new(Duration::from_secs(1));
+        serve(&mut link, &mut modem, &mut Faults::default(), false).unwrap();
+        assert_eq!(
+            output.text(),
+            "RDY\r\nATE0\r\nOK\r\nuart-peer modem\r\nOK\r\nERROR\r\n"
+        );
+    }
+
+    #[test]
+    fn faults_apply_to_every_sent_line() {
+        let script = Script::parse(Path::new("x"), "@connect => HELLO\nPING => PONG\n");
+        let (mut link, output) = link(b"PING\nPING\nPING\n");
+        let mut faults = Faults::new(Duration::ZERO, Some(2), Some(3));
+        serve(&mut link, &mut script.unwrap(), &mut faults, false).unwrap();
+        // HELLO, PONG (dropped), PONG (corrupted), PONG (dropped)
+        assert_eq!(output.text(), "HELLO\r\nPOOG\r\n");
+    }
+
+    #[test]
+    fn stops_when_the_role_finishes() {
+        let (mut link, _tx, output) = open();
+        let mut gps = nmea::Gps::new(Duration::ZERO, Some(1));
+        serve(&mut link, &mut gps, &mut Faults::default(), false).unwrap();
+        let text = output.text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("$GPGGA,123519,"));
+    }
+
+    #[test]
+    fn error_messages() {
+        let error = Error::Connect {
+            target: "localhost:4567".into(),
+            msg: "connection refused".into(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "cannot connect to localhost:4567: connection refused"
+        );
+    }
+}
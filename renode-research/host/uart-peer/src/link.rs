@@ -0,0 +1,217 @@
+//! Line-based connection to a Renode UART terminal
+//!
+//! A background thread reads the socket or PTY and splits the stream into
+//! lines on `\r` or `\n` (firmware usually terminates AT commands with a
+//! bare `\r`), so the peer can wait for input and its own timers at once.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::sys::termios::{self, SetArg};
+
+use crate::Error;
+
+/// The other end closed the connection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Closed;
+
+pub struct Link {
+    lines: Receiver<String>,
+    writer: Box<dyn Write + Send>,
+}
+
+impl Link {
+    /// Connect to a Renode server socket terminal, retrying until `timeout`
+    /// since Renode may not have created it yet
+    pub fn connect(address: &str, timeout: Duration) -> Result<Link, Error> {
+        let start = Instant::now();
+        let stream = loop {
+            match TcpStream::connect(address) {
+                Ok(stream) => break stream,
+                Err(err) if start.elapsed() > timeout => {
+                    return Err(Error::Connect {
+                        target: address.to_string(),
+                        msg: err.to_string(),
+                    })
+                }
+                Err(_) => thread::sleep(Duration::from_millis(100)),
+            }
+        };
+        stream.set_nodelay(true)?;
+        let reader = stream.try_clone()?;
+        Ok(Link::new(reader, stream))
+    }
+
+    /// Open a Renode PTY terminal, waiting up to `timeout` for it to appear
+    pub fn open_pty(path: &Path, timeout: Duration) -> Result<Link, Error> {
+        let start = Instant::now();
+        let file = loop {
+            match OpenOptions::new().read(true).write(true).open(path) {
+                Ok(file) => break file,
+                Err(err) if start.elapsed() > timeout => {
+                    return Err(Error::Connect {
+                        target: path.display().to_string(),
+                        msg: err.to_string(),
+                    })
+                }
+                Err(_) => thread::sleep(Duration::from_millis(100)),
+            }
+        };
+
+        // Raw mode: no echo back into the emulated UART, no CR/LF rewriting
+        if let Ok(mut attrs) = termios::tcgetattr(&file) {
+            termios::cfmakeraw(&mut attrs);
+            let _ = termios::tcsetattr(&file, SetArg::TCSANOW, &attrs);
+        }
+        let reader: File = file.try_clone()?;
+        Ok(Link::new(reader, file))
+    }
+
+    fn new(reader: impl Read + Send + 'static, writer: impl Write + Send + 'static) -> Link {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || read_lines(reader, tx));
+        Link {
+            lines: rx,
+            writer: Box::new(writer),
+        }
+    }
+
+    /// Next non-empty line; `Ok(None)` if `timeout` passes first, waiting
+    /// indefinitely without one
+    pub fn recv_line(&mut self, timeout: Option<Duration>) -> Result<Option<String>, Closed> {
+        match timeout {
+            Some(timeout) => match self.lines.recv_timeout(timeout) {
+                Ok(line) => Ok(Some(line)),
+                Err(RecvTimeoutError::Timeout) => Ok(None),
+                Err(RecvTimeoutError::Disconnected) => Err(Closed),
+            },
+            None => self.lines.recv().map(Some).map_err(|_| Closed),
+        }
+    }
+
+    /// Send `line` followed by `\r\n`
+    pub fn send_line(&mut self, line: &str) -> std::io::Result<()> {
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.write_all(b"\r\n")?;
+        self.writer.flush()
+    }
+}
+
+/// Reader thread: split on `\r` and `\n`, dropping empty lines
+fn read_lines(mut reader: impl Read, tx: mpsc::Sender<String>) {
+    let mut buf = [0u8; 256];
+    let mut line = Vec::new();
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        for &byte in &buf[..n] {
+            match byte {
+                b'\r' | b'\n' => {
+                    if line.is_empty() {
+                        continue;
+                    }
+                    let text = String::from_utf8_lossy(&line).into_owned();
+                    line.clear();
+                    if tx.send(text).is_err() {
+                        return;
+                    }
+                }
+                b => line.push(b),
+            }
+        }
+    }
+    if !line.is_empty() {
+        let _ = tx.send(String::from_utf8_lossy(&line).into_owned());
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    /// A writer whose bytes stay readable after the link owns it
+    #[derive(Clone, Default)]
+    pub(crate) struct Shared(pub Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        pub(crate) fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    /// A link reading `input` and writing to the returned buffer
+    pub(crate) fn link(input: &[u8]) -> (Link, Shared) {
+        let output = Shared::default();
+        let link = Link::new(Cursor::new(input.to_vec()), output.clone());
+        (link, output)
+    }
+
+    /// A link that stays open while the sender lives
+    pub(crate) fn open() -> (Link, mpsc::Sender<String>, Shared) {
+        let output = Shared::default();
+        let (tx, rx) = mpsc::channel();
+        let link = Link {
+            lines: rx,
+            writer: Box::new(output.clone()),
+        };
+        (link, tx, output)
+    }
+
+    #[test]
+    fn splits_on_cr_and_lf() {
+        let (mut link, _) = link(b"AT\rATI\r\n\r\nAT+CSQ\nlast");
+        let mut lines = Vec::new();
+        while let Ok(line) = link.recv_line(None) {
+            lines.push(line.unwrap());
+        }
+        assert_eq!(lines, ["AT", "ATI", "AT+CSQ", "last"]);
+    }
+
+    #[test]
+    fn times_out_then_closes() {
+        let (mut link, tx, _) = open();
+        assert_eq!(link.recv_line(Some(Duration::from_millis(1))), Ok(None));
+        tx.send("PING".into()).unwrap();
+        assert_eq!(
+            link.recv_line(Some(Duration::from_millis(1))),
+            Ok(Some("PING".into()))
+        );
+        drop(tx);
+        assert_eq!(link.recv_line(Some(Duration::from_millis(1))), Err(Closed));
+        assert_eq!(link.recv_line(None), Err(Closed));
+    }
+
+    #[test]
+    fn sends_crlf_lines() {
+        let (mut link, output) = link(b"");
+        link.send_line("OK").unwrap();
+        link.send_line("").unwrap();
+        assert_eq!(output.text(), "OK\r\n\r\n");
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced() {
+        let (mut link, _) = link(b"caf\xE9\n");
+        assert_eq!(link.recv_line(None), Ok(Some("caf\u{FFFD}".into())));
+    }
+}
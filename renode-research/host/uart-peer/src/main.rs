@@ -0,0 +1,123 @@
+//! Play a scripted device on a Renode UART terminal
+//!
+//! Usage: `uart-peer (--connect HOST:PORT | --pty PATH) [FAULTS] <ROLE>`
+//!
+//! ```text
+//! # renode: emulation CreateServerSocketTerminal 4567 "peer" false
+//! #         connector Connect sysbus.usart2 peer
+//! uart-peer --connect 127.0.0.1:4567 modem
+//! uart-peer --connect 127.0.0.1:4567 --drop-every 3 -v modem
+//! uart-peer --pty /tmp/usart2 gps --interval 100 --count 20
+//! uart-peer --connect 127.0.0.1:4567 script rules.txt
+//! ```
+//!
+//! Exits when Renode closes the terminal, or when a `gps --count` stream
+//! is done.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{Parser, Subcommand};
+
+use uart_peer::modem::Modem;
+use uart_peer::nmea::Gps;
+use uart_peer::script::Script;
+use uart_peer::{Error, Faults, Link, Role};
+
+#[derive(Parser)]
+#[command(about = "Play a scripted device on a Renode UART terminal")]
+struct Args {
+    /// Renode server socket terminal (`CreateServerSocketTerminal`)
+    #[arg(long, value_name = "HOST:PORT", conflicts_with = "pty")]
+    connect: Option<String>,
+
+    /// Renode PTY terminal (`CreateUartPtyTerminal`)
+    #[arg(long, value_name = "PATH")]
+    pty: Option<PathBuf>,
+
+    /// How long to keep retrying while the terminal does not exist yet
+    #[arg(long, default_value_t = 10.0, value_name = "SECONDS")]
+    wait: f64,
+
+    /// Pause before every reply, in milliseconds
+    #[arg(long, default_value_t = 0, value_name = "MS")]
+    delay: u64,
+
+    /// Drop every Nth line sent
+    #[arg(long, value_name = "N")]
+    drop_every: Option<u32>,
+
+    /// Flip a bit in every Nth line sent
+    #[arg(long, value_name = "N")]
+    corrupt_every: Option<u32>,
+
+    /// Log every line to stderr
+    #[arg(short, long)]
+    verbose: bool,
+
+    #[command(subcommand)]
+    role: RoleArgs,
+}
+
+#[derive(Subcommand)]
+enum RoleArgs {
+    /// GPS receiver streaming NMEA GGA/RMC sentences
+    Gps {
+        /// Time between fixes, in milliseconds
+        #[arg(long, default_value_t = 200)]
+        interval: u64,
+
+        /// Stop after this many fixes
+        #[arg(long)]
+        count: Option<u32>,
+    },
+    /// AT-command modem; `AT+CGNSTST=1` starts a GPS stream
+    Modem {
+        /// Time between GNSS fixes, in milliseconds
+        #[arg(long, default_value_t = 200)]
+        interval: u64,
+    },
+    /// Command/response rules from a file (`REQUEST => RESPONSE` per line)
+    Script { rules: PathBuf },
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("uart-peer: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Error> {
+    let mut role: Box<dyn Role> = match &args.role {
+        RoleArgs::Gps { interval, count } => {
+            Box::new(Gps::new(Duration::from_millis(*interval), *count))
+        }
+        RoleArgs::Modem { interval } => Box::new(Modem::new(Duration::from_millis(*interval))),
+        RoleArgs::Script { rules } => Box::new(Script::load(rules)?),
+    };
+
+    let wait = Duration::from_secs_f64(args.wait.max(0.0));
+    let mut link = match (&args.connect, &args.pty) {
+        (Some(address), _) => Link::connect(address, wait)?,
+        (None, Some(path)) => Link::open_pty(path, wait)?,
+        (None, None) => {
+            return Err(Error::Connect {
+                target: "nothing".into(),
+                msg: "pass --connect HOST:PORT or --pty PATH".into(),
+            })
+        }
+    };
+
+    let mut faults = Faults::new(
+        Duration::from_millis(args.delay),
+        args.drop_every,
+        args.corrupt_every,
+    );
+    uart_peer::serve(&mut link, role.as_mut(), &mut faults, args.verbose)
+}
@@ -0,0 +1,198 @@
+//! AT-command modem with a GNSS receiver
+//!
+//! Modelled loosely on the SIMCom GSM/GNSS modules: the modem announces
+//! itself with `RDY`, echoes commands until `ATE0`, and answers each one
+//! with its result lines followed by `OK` or `ERROR`.
+//!
+//! | Command | Reply |
+//! |---------|-------|
+//! | `AT` | `OK` |
+//! | `ATE0` / `ATE1` | `OK`; echo off / on |
+//! | `ATI` | `uart-peer modem`, `OK` |
+//! | `AT+CSQ` | `+CSQ: 21,0`, `OK` |
+//! | `AT+CGNSPWR=<0/1>` | `OK`; GNSS power |
+//! | `AT+CGNSTST=<0/1>` | `OK`; stop / start streaming NMEA sentences |
+//! | anything else | `ERROR` |
+//!
+//! `AT+CGNSTST=1` powers GNSS on implicitly, so firmware can skip
+//! `AT+CGNSPWR`.
+
+use std::time::{Duration, Instant};
+
+use crate::nmea::Stream;
+use crate::Role;
+
+/// Banner sent when the link comes up
+pub const READY: &str = "RDY";
+
+/// `ATI` identification line
+pub const IDENTIFICATION: &str = "uart-peer modem";
+
+/// Signal quality reported by `AT+CSQ` (0-31, 99 = unknown)
+pub const SIGNAL_QUALITY: u8 = 21;
+
+#[derive(Clone, Debug)]
+pub struct Modem {
+    echo: bool,
+    interval: Duration,
+    gnss_power: bool,
+    /// NMEA stream while `AT+CGNSTST=1` is in effect
+    stream: Option<Stream>,
+}
+
+impl Modem {
+    /// A modem streaming one fix per `interval` once GNSS output is on
+    pub fn new(interval: Duration) -> Modem {
+        Modem {
+            echo: true,
+            interval,
+            gnss_power: false,
+            stream: None,
+        }
+    }
+
+    /// Result lines of one command; `None` for `ERROR`
+    fn execute(&mut self, command: &str) -> Option<Vec<String>> {
+        let upper = command.to_ascii_uppercase();
+        let result = match upper.as_str() {
+            "AT" => Vec::new(),
+            "ATE0" => {
+                self.echo = false;
+                Vec::new()
+            }
+            "ATE1" => {
+                self.echo = true;
+                Vec::new()
+            }
+            "ATI" => vec![IDENTIFICATION.to_string()],
+            "AT+CSQ" => vec![format!("+CSQ: {},0", SIGNAL_QUALITY)],
+            "AT+CGNSPWR=0" => {
+                self.gnss_power = false;
+                self.stream = None;
+                Vec::new()
+            }
+            "AT+CGNSPWR=1" => {
+                self.gnss_power = true;
+                Vec::new()
+            }
+            "AT+CGNSPWR?" => vec![format!("+CGNSPWR: {}", u8::from(self.gnss_power))],
+            "AT+CGNSTST=0" => {
+                self.stream = None;
+                Vec::new()
+            }
+            "AT+CGNSTST=1" => {
+                self.gnss_power = true;
+                // Keep a running stream going if the command is repeated;
+                // a new one sends its first fix right after the OK
+                if self.stream.is_none() {
+                    self.stream = Some(Stream::new(self.interval));
+                }
+                Vec::new()
+            }
+            _ => return None,
+        };
+        Some(result)
+    }
+}
+
+impl Role for Modem {
+    fn greeting(&mut self) -> Vec<String> {
+        vec![READY.to_string()]
+    }
+
+    fn respond(&mut self, line: &str) -> Vec<String> {
+        let command = line.trim();
+        let mut reply = Vec::new();
+        if self.echo {
+            reply.push(command.to_string());
+        }
+        match self.execute(command) {
+            Some(lines) => {
+                reply.extend(lines);
+                reply.push("OK".into());
+            }
+            None => reply.push("ERROR".into()),
+        }
+        reply
+    }
+
+    fn poll(&mut self, now: Instant) -> Vec<String> {
+        match &mut self.stream {
+            Some(stream) => stream.poll(now),
+            None => Vec::new(),
+        }
+    }
+
+    fn next_due(&self) -> Option<Instant> {
+        self.stream.as_ref().map(Stream::next_due)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modem() -> Modem {
+        Modem::new(Duration::from_secs(1))
+    }
+
+    #[test]
+    fn greets_and_echoes() {
+        let mut modem = modem();
+        assert_eq!(modem.greeting(), [READY]);
+        assert_eq!(modem.respond("AT\r"), ["AT", "OK"]);
+        assert_eq!(modem.respond("ATE0"), ["ATE0", "OK"]);
+        assert_eq!(modem.respond("AT"), ["OK"]);
+        // Echo is decided before the command runs
+        assert_eq!(modem.respond("ATE1"), ["OK"]);
+        assert_eq!(modem.respond("AT"), ["AT", "OK"]);
+    }
+
+    #[test]
+    fn answers_queries() {
+        let mut modem = modem();
+        modem.respond("ATE0");
+        assert_eq!(modem.respond("ATI"), [IDENTIFICATION, "OK"]);
+        assert_eq!(modem.respond("at+csq"), ["+CSQ: 21,0", "OK"]);
+        assert_eq!(modem.respond("AT+CGNSPWR?"), ["+CGNSPWR: 0", "OK"]);
+        assert_eq!(modem.respond("AT+CGNSPWR=1"), ["OK"]);
+        assert_eq!(modem.respond("AT+CGNSPWR?"), ["+CGNSPWR: 1", "OK"]);
+        assert_eq!(modem.respond("AT+CGNSPWR=2"), ["ERROR"]);
+        assert_eq!(modem.respond("hello"), ["ERROR"]);
+    }
+
+    #[test]
+    fn streams_gnss_while_enabled() {
+        let mut modem = modem();
+        modem.respond("ATE0");
+        assert!(modem.next_due().is_none());
+        assert!(modem.poll(Instant::now()).is_empty());
+
+        assert_eq!(modem.respond("AT+CGNSTST=1"), ["OK"]);
+        assert_eq!(modem.respond("AT+CGNSPWR?"), ["+CGNSPWR: 1", "OK"]);
+        let due = modem.next_due().unwrap();
+        let first = modem.poll(due);
+        assert_eq!(first.len(), 2);
+        assert!(first[0].starts_with("$GPGGA,123519,"));
+
+        // Repeating the command keeps the running stream
+        modem.respond("AT+CGNSTST=1");
+        let next = modem.poll(due + Duration::from_secs(1));
+        assert!(next[0].starts_with("$GPGGA,123520,"));
+
+        assert_eq!(modem.respond("AT+CGNSTST=0"), ["OK"]);
+        assert!(modem.next_due().is_none());
+    }
+
+    #[test]
+    fn power_off_stops_the_stream() {
+        let mut modem = modem();
+        modem.respond("AT+CGNSTST=1");
+        modem.respond("AT+CGNSPWR=0");
+        assert!(modem.next_due().is_none());
+        assert_eq!(
+            modem.respond("AT+CGNSPWR?"),
+            ["AT+CGNSPWR?", "+CGNSPWR: 0", "OK"]
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! GPS receiver streaming NMEA 0183 sentences
+//!
+//! Every fix is a `$GPGGA` followed by a `$GPRMC` sentence. The receiver
+//! starts at 48°07.038'N 011°31.000'E, 12:35:19 UTC on 23 March 1994 (the
+//! position of the NMEA specification's example sentences) and moves a
+//! little north-east each fix, so consecutive sentences differ:
+//!
+//! ```text
+//! $GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47
+//! $GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::Role;
+
+/// Starting latitude and longitude in thousandths of an arc minute
+const START_LAT: u32 = 48 * 60_000 + 7_038;
+const START_LON: u32 = 11 * 60_000 + 31_000;
+
+/// Movement per fix, in thousandths of an arc minute
+const STEP: u32 = 10;
+
+/// 12:35:19 in seconds since midnight
+const START_TIME: u32 = 12 * 3600 + 35 * 60 + 19;
+
+/// Satellites reported in `GGA`
+pub const SATELLITES: u8 = 8;
+
+/// Position and time of one fix
+///
+/// ```
+/// use uart_peer::nmea::Fix;
+///
+/// let first = Fix { index: 0 };
+/// assert_eq!(
+///     first.gga(),
+///     "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47"
+/// );
+/// assert_eq!(
+///     first.rmc(),
+///     "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A"
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fix {
+    pub index: u32,
+}
+
+impl Fix {
+    fn lat(&self) -> String {
+        let lat = START_LAT + self.index * STEP;
+        format!(
+            "{:02}{:02}.{:03}",
+            lat / 60_000,
+            lat % 60_000 / 1000,
+            lat % 1000
+        )
+    }
+
+    fn lon(&self) -> String {
+        let lon = START_LON + self.index * STEP;
+        format!(
+            "{:03}{:02}.{:03}",
+            lon / 60_000,
+            lon % 60_000 / 1000,
+            lon % 1000
+        )
+    }
+
+    fn time(&self) -> String {
+        let t = (START_TIME + self.index) % 86_400;
+        format!("{:02}{:02}{:02}", t / 3600, t % 3600 / 60, t % 60)
+    }
+
+    /// `$GPGGA` (fix data)
+    pub fn gga(&self) -> String {
+        sentence(&format!(
+            "GPGGA,{},{},N,{},E,1,{:02},0.9,545.4,M,46.9,M,,",
+            self.time(),
+            self.lat(),
+            self.lon(),
+            SATELLITES
+        ))
+    }
+
+    /// `$GPRMC` (recommended minimum data)
+    pub fn rmc(&self) -> String {
+        sentence(&format!(
+            "GPRMC,{},A,{},N,{},E,022.4,084.4,230394,003.1,W",
+            self.time(),
+            self.lat(),
+            self.lon()
+        ))
+    }
+}
+
+/// `$<body>*<checksum>`, the checksum being the XOR of the body bytes
+pub fn sentence(body: &str) -> String {
+    let checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+    format!("${}*{:02X}", body, checksum)
+}
+
+/// Sentence generator shared by [`Gps`] and the modem's GNSS stream
+#[derive(Clone, Debug)]
+pub struct Stream {
+    interval: Duration,
+    next: Fix,
+    due: Instant,
+}
+
+impl Stream {
+    /// A stream whose first fix is due immediately
+    pub fn new(interval: Duration) -> Stream {
+        Stream {
+            interval,
+            next: Fix { index: 0 },
+            due: Instant::now(),
+        }
+    }
+
+    /// Fixes sent so far
+    pub fn fixes(&self) -> u32 {
+        self.next.index
+    }
+
+    pub fn next_due(&self) -> Instant {
+        self.due
+    }
+
+    /// The sentences of the next fix if it is due
+    pub fn poll(&mut self, now: Instant) -> Vec<String> {
+        if now < self.due {
+            return Vec::new();
+        }
+        let fix = self.next;
+        self.next.index += 1;
+        self.due = now + self.interval;
+        vec![fix.gga(), fix.rmc()]
+    }
+}
+
+/// A GPS receiver that streams fixes and ignores its input
+#[derive(Clone, Debug)]
+pub struct Gps {
+    stream: Stream,
+    /// Stop after this many fixes
+    count: Option<u32>,
+}
+
+impl Gps {
+    pub fn new(interval: Duration, count: Option<u32>) -> Gps {
+        Gps {
+            stream: Stream::new(interval),
+            count,
+        }
+    }
+}
+
+impl Role for Gps {
+    fn respond(&mut self, _line: &str) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn poll(&mut self, now: Instant) -> Vec<String> {
+        if self.finished() {
+            return Vec::new();
+        }
+        self.stream.poll(now)
+    }
+
+    fn next_due(&self) -> Option<Instant> {
+        Some(self.stream.next_due())
+    }
+
+    fn finished(&self) -> bool {
+        self.count.is_some_and(|count| self.stream.fixes() >= count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether the checksum after `*` matches the body
+    fn valid(sentence: &str) -> bool {
+        let (body, checksum) = sentence[1..].split_once('*').unwrap();
+        let xor = body.bytes().fold(0u8, |acc, b| acc ^ b);
+        u8::from_str_radix(checksum, 16) == Ok(xor)
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(sentence("AB"), "$AB*03");
+        assert_eq!(sentence(""), "$*00");
+        for index in [0, 1, 59, 3600, 100_000] {
+            let fix = Fix { index };
+            assert!(valid(&fix.gga()), "{}", fix.gga());
+            assert!(valid(&fix.rmc()), "{}", fix.rmc());
+        }
+    }
+
+    #[test]
+    fn moves_north_east_each_fix() {
+        let fix = Fix { index: 1 };
+        assert!(fix
+            .gga()
+            .starts_with("$GPGGA,123520,4807.048,N,01131.010,E,1,08,"));
+        assert!(fix
+            .rmc()
+            .starts_with("$GPRMC,123520,A,4807.048,N,01131.010,E,"));
+    }
+
+    #[test]
+    fn minutes_and_degrees_carry() {
+        // 7.038' + 300 * 0.010' = 10.038'
+        assert_eq!(Fix { index: 300 }.lat(), "4810.038");
+        // 31.000' + 2900 * 0.010' = 60.000' = one degree
+        assert_eq!(Fix { index: 2900 }.lon(), "01200.000");
+    }
+
+    #[test]
+    fn time_wraps_at_midnight() {
+        let midnight = 86_400 - START_TIME;
+        assert_eq!(
+            Fix {
+                index: midnight - 1
+            }
+            .time(),
+            "235959"
+        );
+        assert_eq!(Fix { index: midnight }.time(), "000000");
+    }
+
+    #[test]
+    fn stream_sends_one_fix_per_interval() {
+        let interval = Duration::from_secs(1);
+        let mut stream = Stream::new(interval);
+        let start = stream.next_due();
+        assert_eq!(
+            stream.poll(start),
+            [Fix { index: 0 }.gga(), Fix { index: 0 }.rmc()]
+        );
+        assert_eq!(stream.fixes(), 1);
+        assert_eq!(stream.next_due(), start + interval);
+        assert!(stream.poll(start + interval / 2).is_empty());
+        assert_eq!(stream.poll(start + interval)[0], Fix { index: 1 }.gga());
+        assert_eq!(stream.fixes(), 2);
+    }
+
+    #[test]
+    fn gps_stops_after_count() {
+        let mut gps = Gps::new(Duration::ZERO, Some(2));
+        assert!(gps.respond("$PMTK220,1000*1F").is_empty());
+        let now = gps.next_due().unwrap();
+        assert_eq!(gps.poll(now).len(), 2);
+        assert!(!gps.finished());
+        assert_eq!(gps.poll(now).len(), 2);
+        assert!(gps.finished());
+        assert!(gps.poll(now).is_empty());
+        assert!(!Gps::new(Duration::ZERO, None).finished());
+    }
+}
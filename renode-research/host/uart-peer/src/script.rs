@@ -0,0 +1,178 @@
+//! Command/response peer driven by a rules file
+//!
+//! Each non-empty line that does not start with `#` is a rule
+//! `REQUEST => RESPONSE`:
+//!
+//! ```text
+//! # Sent when the link comes up
+//! @connect => HELLO 1.0
+//! PING => PONG
+//! # Several rules for one request send several lines, in order
+//! GET TEMP => TEMP 21.5
+//! GET TEMP => OK
+//! # A trailing `*` matches any line with that prefix
+//! SET * => OK
+//! # Lines no other rule matches
+//! * => ERR unknown command
+//! ```
+//!
+//! Exact requests take precedence over prefix rules, and the longest
+//! prefix wins; a line nothing matches is ignored. `=> ` with nothing after
+//! it matches without answering.
+
+use std::fs;
+use std::path::Path;
+
+use crate::{Error, Role};
+
+/// Request that matches the link coming up
+const CONNECT: &str = "@connect";
+
+#[derive(Clone, Debug, PartialEq)]
+struct Rule {
+    request: String,
+    response: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Script {
+    rules: Vec<Rule>,
+}
+
+impl Script {
+    pub fn load(path: &Path) -> Result<Script, Error> {
+        let text = fs::read_to_string(path)?;
+        Script::parse(path, &text)
+    }
+
+    /// Parse rules; `path` is only used in error messages
+    pub fn parse(path: &Path, text: &str) -> Result<Script, Error> {
+        let mut rules = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (request, response) = line.split_once("=>").ok_or_else(|| Error::Script {
+                path: path.to_path_buf(),
+                line: index + 1,
+                msg: format!("expected `REQUEST => RESPONSE`, got `{}`", line),
+            })?;
+            rules.push(Rule {
+                request: request.trim().to_string(),
+                response: response.trim().to_string(),
+            });
+        }
+        Ok(Script { rules })
+    }
+
+    /// Responses of the best-matching request
+    fn matching(&self, line: &str) -> Vec<String> {
+        let exact: Vec<&Rule> = self.rules.iter().filter(|r| r.request == line).collect();
+        let chosen = if !exact.is_empty() {
+            exact
+        } else {
+            let prefix = |rule: &Rule| {
+                rule.request
+                    .strip_suffix('*')
+                    .filter(|prefix| line.starts_with(prefix.trim_end()))
+                    .map(str::len)
+            };
+            let Some(best) = self.rules.iter().filter_map(prefix).max() else {
+                return Vec::new();
+            };
+            self.rules
+                .iter()
+                .filter(|r| prefix(r) == Some(best))
+                .collect()
+        };
+        chosen
+            .into_iter()
+            .filter(|r| !r.response.is_empty())
+            .map(|r| r.response.clone())
+            .collect()
+    }
+}
+
+impl Role for Script {
+    fn greeting(&mut self) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter(|r| r.request == CONNECT && !r.response.is_empty())
+            .map(|r| r.response.clone())
+            .collect()
+    }
+
+    fn respond(&mut self, line: &str) -> Vec<String> {
+        if line.trim() == CONNECT {
+            return Vec::new();
+        }
+        self.matching(line.trim())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &str = "\
+# Sent when the link comes up
+@connect => HELLO 1.0
+PING => PONG
+# Several rules for one request send several lines, in order
+GET TEMP => TEMP 21.5
+GET TEMP => OK
+# A trailing `*` matches any line with that prefix
+SET * => OK
+SET MODE * => MODE SET
+QUIET =>
+* => ERR unknown command
+";
+
+    fn script() -> Script {
+        Script::parse(Path::new("peer.rules"), RULES).unwrap()
+    }
+
+    #[test]
+    fn greeting_from_connect_rules() {
+        let mut script = script();
+        assert_eq!(script.greeting(), ["HELLO 1.0"]);
+        assert!(script.respond("@connect").is_empty());
+        assert!(Script::default().greeting().is_empty());
+    }
+
+    #[test]
+    fn exact_requests_first() {
+        let mut script = script();
+        assert_eq!(script.respond("PING\r"), ["PONG"]);
+        assert_eq!(script.respond("GET TEMP"), ["TEMP 21.5", "OK"]);
+    }
+
+    #[test]
+    fn longest_prefix_wins() {
+        let mut script = script();
+        assert_eq!(script.respond("SET LED 1"), ["OK"]);
+        assert_eq!(script.respond("SET MODE fast"), ["MODE SET"]);
+        assert_eq!(script.respond("REBOOT"), ["ERR unknown command"]);
+    }
+
+    #[test]
+    fn empty_responses_match_silently() {
+        let mut script = script();
+        assert!(script.respond("QUIET").is_empty());
+        let mut script = Script::parse(Path::new("x"), "PING => PONG").unwrap();
+        assert!(script.respond("PONG").is_empty());
+    }
+
+    #[test]
+    fn malformed_rules_name_the_line() {
+        let error =
+            Script::parse(Path::new("peer.rules"), "# rules\n\nPING -> PONG\n").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "peer.rules:3: expected `REQUEST => RESPONSE`, got `PING -> PONG`"
+        );
+        let missing = Script::load(Path::new("/nonexistent/peer.rules")).unwrap_err();
+        assert!(matches!(missing, Error::Io(_)));
+    }
+}
@@ -0,0 +1,838 @@
+//! Workspace task runner
+//!
+//! Usage, from `renode-research/`:
+//!
+//! ```text
+//! cargo xtask build [CRATE]...          # release firmware builds
+//! cargo xtask test [CRATE]... [--golden] [-j N] # build, run under Renode, report
+//! cargo xtask run CRATE                 # build and open it in Renode
+//! cargo xtask gen [--check]             # regenerate .resc/.robot files
+//! cargo xtask size [CRATE]... [--update] # compare with size-baseline.txt
+//! cargo xtask stack [CRATE]... [--measure] # worst-case stack usage
+//! cargo xtask coverage [CRATE]...        # line coverage from Renode traces
+//! cargo xtask profile CRATE [--test NAME] # per-function profile and timeline
+//! cargo xtask log-levels                 # check max-level-* compile log lines out
+//! cargo xtask svd [--force]              # download the STM32F303 SVD for log-triage
+//! ```
+//!
+//! `CRATE` is a peripheral name such as `dma` or `stm32f3-dma`; with none
+//! given every firmware crate is used. `test` drives `renode-runner` and
+//! writes `target/test-report/{junit.xml,index.html}`; its exit code is the
+//! runner's, so it can gate CI directly.
+
+use std::env;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode};
+
+use clap::{Args, Parser, Subcommand};
+
+/// Firmware target triple
+const TARGET: &str = "thumbv7em-none-eabihf";
+
+/// Where `test` writes its reports, relative to the firmware root
+const REPORT_DIR: &str = "target/test-report";
+
+/// Where `stack` builds with `-Z emit-stack-sizes`, relative to the
+/// firmware root; kept apart so the normal build is not invalidated
+const STACK_SIZES_DIR: &str = "target/stack-sizes";
+
+/// Where `coverage` keeps traces and reports, relative to the firmware root
+const COVERAGE_DIR: &str = "target/coverage";
+
+/// Where `profile` keeps traces and timelines, relative to the firmware root
+const PROFILE_DIR: &str = "target/profile";
+
+/// Where `log-levels` builds each variant, relative to the firmware root
+const LOG_LEVELS_DIR: &str = "target/log-levels";
+
+/// `log-levels` builds this crate: it logs at INFO and DEBUG
+const LOG_LEVELS_CRATE: &str = "stm32f3-dma";
+
+/// Feature of `stm32f3-common` for each variant (none is the default, INFO),
+/// and whether the INFO and DEBUG messages must be in the image
+const LOG_LEVELS: &[(Option<&str>, bool, bool)] = &[
+    (Some("max-level-off"), false, false),
+    (None, true, false),
+    (Some("max-level-debug"), true, true),
+];
+
+/// Text of the INFO and DEBUG messages `stm32f3-dma` logs
+const LOG_MESSAGES: [&str; 2] = ["Transferring ", "SRC: 0x"];
+
+/// Where `svd` saves the STM32F303 SVD, relative to the firmware root;
+/// `log-triage` reads it from here when no `--svd` is given
+const SVD_PATH: &str = "target/svd/STM32F303.svd";
+
+/// The SVD the `stm32f3` PAC is generated from, with stm32-rs's patches
+const SVD_URL: &str = "https://stm32-rs.github.io/stm32-rs/STM32F303.svd.patched";
+
+/// Host tools that report their own failures; their exit code is passed on
+const REPORTING_TOOLS: &[&str] = &[
+    "renode-runner",
+    "renode-gen",
+    "size-report",
+    "stack-usage",
+    "trace-coverage",
+    "trace-profile",
+];
+
+#[derive(Parser)]
+#[command(about = "Build, run and test the STM32F3 firmware under Renode")]
+struct Cli {
+    /// Firmware workspace root
+    #[arg(long, global = true, default_value = concat!(env!("CARGO_MANIFEST_DIR"), "/../.."))]
+    root: PathBuf,
+
+    #[command(subcommand)]
+    task: Task,
+}
+
+#[derive(Subcommand)]
+enum Task {
+    /// Build firmware crates
+    Build(Build),
+    /// Build firmware crates and run their suites under Renode
+    Test(Test),
+    /// Build one firmware crate and start it in an interactive Renode
+    Run(Run),
+    /// Regenerate Renode scripts and Robot suites from Cargo.toml metadata
+    Gen(Gen),
+    /// Build release firmware and compare its size with the baseline
+    Size(Size),
+    /// Build firmware with stack-size metadata and report worst-case usage
+    Stack(Stack),
+    /// Run the suites with execution tracing and report source coverage
+    Coverage(Coverage),
+    /// Trace one crate's suite and profile it per function
+    Profile(Profile),
+    /// Check that the `max-level-*` features compile log calls out
+    LogLevels,
+    /// Download the STM32F303 SVD that `log-triage` names registers from
+    Svd(Svd),
+}
+
+#[derive(Args)]
+struct Build {
+    /// Peripheral crates (e.g. `dma i2c`); all when omitted
+    crates: Vec<String>,
+
+    /// Build the debug profile instead of release
+    #[arg(long)]
+    debug: bool,
+}
+
+#[derive(Args)]
+struct Test {
+    /// Peripheral crates (e.g. `dma i2c`); all when omitted
+    crates: Vec<String>,
+
+    /// Renode executable
+    #[arg(long, env = "RENODE", default_value = "renode")]
+    renode: PathBuf,
+
+    /// Only run test cases whose name contains this text (repeatable)
+    #[arg(long = "test")]
+    tests: Vec<String>,
+
+    /// Compare full UART transcripts with `tests/uart.golden`
+    #[arg(long)]
+    golden: bool,
+
+    /// Re-record `tests/uart.golden` (implies --golden)
+    #[arg(long)]
+    update: bool,
+
+    /// Multiply every UART wait timeout (for slow hosts)
+    #[arg(long)]
+    timeout_scale: Option<f64>,
+
+    /// Run every suite this many times and flag flaky test cases
+    #[arg(long)]
+    repeat: Option<u32>,
+
+    /// Run up to this many suites at once, each in its own Renode
+    #[arg(short, long)]
+    jobs: Option<u16>,
+
+    /// Skip the firmware build
+    #[arg(long)]
+    no_build: bool,
+}
+
+#[derive(Args)]
+struct Run {
+    /// Peripheral crate to start
+    #[arg(name = "CRATE")]
+    krate: String,
+
+    /// Renode executable
+    #[arg(long, env = "RENODE", default_value = "renode")]
+    renode: PathBuf,
+
+    /// Start the emulation right away
+    #[arg(long)]
+    start: bool,
+}
+
+#[derive(Args)]
+struct Gen {
+    /// Peripheral crates (e.g. `dma i2c`); all when omitted
+    crates: Vec<String>,
+
+    /// Fail if any generated file is out of date instead of writing it
+    #[arg(long)]
+    check: bool,
+}
+
+#[derive(Args)]
+struct Size {
+    /// Peripheral crates (e.g. `dma i2c`); all when omitted
+    crates: Vec<String>,
+
+    /// Rewrite `size-baseline.txt` with the current sizes
+    #[arg(long)]
+    update: bool,
+
+    /// Allowed growth of flash or RAM, in percent
+    #[arg(long)]
+    threshold: Option<f64>,
+}
+
+#[derive(Args)]
+struct Stack {
+    /// Peripheral crates (e.g. `dma i2c`); all when omitted
+    crates: Vec<String>,
+
+    /// Print the deepest call path of every entry point
+    #[arg(long)]
+    paths: bool,
+
+    /// Also run the suites under Renode and compare with the painted stack
+    #[arg(long)]
+    measure: bool,
+
+    /// Renode executable (with --measure)
+    #[arg(long, env = "RENODE", default_value = "renode")]
+    renode: PathBuf,
+}
+
+#[derive(Args)]
+struct Coverage {
+    /// Peripheral crates (e.g. `dma i2c`); all when omitted
+    crates: Vec<String>,
+
+    /// Renode executable
+    #[arg(long, env = "RENODE", default_value = "renode")]
+    renode: PathBuf,
+
+    /// Run up to this many suites at once, each in its own Renode
+    #[arg(short, long)]
+    jobs: Option<u16>,
+
+    /// Fail if total line coverage is below this percentage
+    #[arg(long, value_name = "PERCENT")]
+    fail_under: Option<f64>,
+
+    /// Skip the firmware build
+    #[arg(long)]
+    no_build: bool,
+}
+
+#[derive(Args)]
+struct Profile {
+    /// Peripheral crate (e.g. `adc`)
+    #[arg(name = "CRATE")]
+    krate: String,
+
+    /// Only trace test cases whose name contains this text (repeatable)
+    #[arg(long = "test")]
+    tests: Vec<String>,
+
+    /// Functions listed, by exclusive instructions
+    #[arg(long)]
+    top: Option<usize>,
+
+    /// Renode executable
+    #[arg(long, env = "RENODE", default_value = "renode")]
+    renode: PathBuf,
+
+    /// Skip the firmware build
+    #[arg(long)]
+    no_build: bool,
+}
+
+#[derive(Args)]
+struct Svd {
+    /// Where to download it from
+    #[arg(long, default_value = SVD_URL)]
+    url: String,
+
+    /// Download it again even if it is there
+    #[arg(long)]
+    force: bool,
+}
+
+/// A task that could not be carried out
+#[derive(Debug)]
+enum Error {
+    Io(std::io::Error),
+    /// No firmware crate by that name
+    UnknownCrate(String),
+    /// A child process could not be started
+    Spawn {
+        program: String,
+        err: std::io::Error,
+    },
+    /// A child process exited unsuccessfully
+    Failed {
+        program: String,
+        code: Option<i32>,
+    },
+    /// A log message is in an image built without its level, or missing
+    /// from one built with it
+    LogLevel {
+        level: String,
+        message: &'static str,
+        present: bool,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::UnknownCrate(name) => write!(f, "no firmware crate {}", name),
+            Error::Spawn { program, err } => write!(f, "could not run {}: {}", program, err),
+            Error::Failed {
+                program,
+                code: Some(code),
+            } => write!(f, "{} exited with status {}", program, code),
+            Error::Failed {
+                program,
+                code: None,
+            } => write!(f, "{} was killed", program),
+            Error::LogLevel {
+                level,
+                message,
+                present: true,
+            } => write!(f, "`{}` is compiled in at {}", message.trim(), level),
+            Error::LogLevel {
+                level,
+                message,
+                present: false,
+            } => write!(f, "`{}` is missing at {}", message.trim(), level),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let root = cli.root.canonicalize().unwrap_or(cli.root);
+
+    let result = match cli.task {
+        Task::Build(args) => build(&root, &args),
+        Task::Test(args) => test(&root, &args),
+        Task::Run(args) => run(&root, &args),
+        Task::Gen(args) => gen(&root, &args),
+        Task::Size(args) => size(&root, &args),
+        Task::Stack(args) => stack(&root, &args),
+        Task::Coverage(args) => coverage(&root, &args),
+        Task::Profile(args) => profile(&root, &args),
+        Task::LogLevels => log_levels(&root),
+        Task::Svd(args) => svd(&root, &args),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(Error::Failed {
+            program,
+            code: Some(code),
+        }) if REPORTING_TOOLS.contains(&program.as_str()) => {
+            ExitCode::from(code.clamp(1, 255) as u8)
+        }
+        Err(err) => {
+            eprintln!("xtask: {}", err);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn build(root: &Path, args: &Build) -> Result<(), Error> {
+    let crates = firmware_crates(root, &args.crates)?;
+    cargo_build(root, &crates, !args.debug)
+}
+
+fn test(root: &Path, args: &Test) -> Result<(), Error> {
+    let crates = firmware_crates(root, &args.crates)?;
+    if !args.no_build {
+        cargo_build(root, &crates, true)?;
+    }
+    build_tools(root)?;
+
+    let reports = root.join(REPORT_DIR);
+    let mut command = host_cargo(root);
+    command.args(["run", "--quiet", "--release", "-p", "renode-runner", "--"]);
+    command.arg("--root").arg(root);
+    command.arg("--renode").arg(&args.renode);
+    command.arg("--junit").arg(reports.join("junit.xml"));
+    command.arg("--html").arg(reports.join("index.html"));
+    for name in &args.tests {
+        command.arg("--test").arg(name);
+    }
+    if args.golden {
+        command.arg("--golden");
+    }
+    if args.update {
+        command.arg("--update");
+    }
+    if let Some(scale) = args.timeout_scale {
+        command.arg("--timeout-scale").arg(scale.to_string());
+    }
+    if let Some(jobs) = args.jobs {
+        command.arg("--jobs").arg(jobs.to_string());
+    }
+    if let Some(repeat) = args.repeat {
+        command.arg("--repeat").arg(repeat.to_string());
+        command.arg("--stats").arg(reports.join("flaky.tsv"));
+    }
+    // With no crates given the runner discovers every suite itself
+    if !args.crates.is_empty() {
+        command.args(&crates);
+    }
+
+    status(command, "renode-runner")
+}
+
+fn run(root: &Path, args: &Run) -> Result<(), Error> {
+    let krate = firmware_crates(root, std::slice::from_ref(&args.krate))?.remove(0);
+    cargo_build(root, std::slice::from_ref(&krate), true)?;
+
+    // `renode-config.resc` loads `$bin?=@target/...` relative to the crate,
+    // so set `$bin` to the workspace build and include it from a wrapper.
+    let dir = root.join(&krate);
+    let elf = root
+        .join("target")
+        .join(TARGET)
+        .join("release")
+        .join(&krate);
+    let mut script = format!(
+        "$bin=@\"{}\"\npath add @\"{}\"\ninclude @\"{}\"\n",
+        elf.display(),
+        dir.display(),
+        dir.join("renode-config.resc").display()
+    );
+    if args.start {
+        script.push_str("start\n");
+    }
+
+    let wrapper_dir = root.join("target/xtask");
+    fs::create_dir_all(&wrapper_dir)?;
+    let wrapper = wrapper_dir.join(format!("{}.resc", krate));
+    fs::write(&wrapper, script)?;
+
+    let mut command = Command::new(&args.renode);
+    command.arg(&wrapper).current_dir(&dir);
+    status(command, &args.renode.display().to_string())
+}
+
+fn gen(root: &Path, args: &Gen) -> Result<(), Error> {
+    let mut command = host_cargo(root);
+    command.args(["run", "--quiet", "-p", "renode-gen", "--"]);
+    command.arg("--root").arg(root);
+    if args.check {
+        command.arg("--check");
+    }
+    command.args(&args.crates);
+    status(command, "renode-gen")
+}
+
+fn size(root: &Path, args: &Size) -> Result<(), Error> {
+    let crates = firmware_crates(root, &args.crates)?;
+    cargo_build(root, &crates, true)?;
+
+    let mut command = host_cargo(root);
+    command.args([
+        "run",
+        "--quiet",
+        "-p",
+        "firmware-elf",
+        "--bin",
+        "size-report",
+        "--",
+    ]);
+    command.arg("--root").arg(root);
+    if args.update {
+        command.arg("--update");
+    }
+    if let Some(threshold) = args.threshold {
+        command.arg("--threshold").arg(threshold.to_string());
+    }
+    if !args.crates.is_empty() {
+        command.args(&crates);
+    }
+    status(command, "size-report")
+}
+
+fn stack(root: &Path, args: &Stack) -> Result<(), Error> {
+    let crates = firmware_crates(root, &args.crates)?;
+
+    // `-Z emit-stack-sizes` needs nightly, which only the rustup proxy can
+    // select. RUSTFLAGS replaces the target rustflags from .cargo/config.toml,
+    // so the linker script is passed again.
+    let mut command = Command::new("cargo");
+    command.args(["+nightly", "build", "--release", "--target-dir"]);
+    command.arg(STACK_SIZES_DIR).current_dir(root);
+    command.env("RUSTFLAGS", "-C link-arg=-Tlink.x -Z emit-stack-sizes");
+    command.env_remove("RUSTUP_TOOLCHAIN");
+    for krate in &crates {
+        command.arg("-p").arg(krate);
+    }
+    status(command, "cargo +nightly build")?;
+
+    let mut command = host_cargo(root);
+    command.args(["run", "--quiet", "-p", "stack-usage", "--"]);
+    command.arg("--root").arg(root);
+    command
+        .arg("--elf-dir")
+        .arg(root.join(STACK_SIZES_DIR).join(TARGET).join("release"));
+    if args.paths {
+        command.arg("--paths");
+    }
+    if args.measure {
+        command.arg("--measure");
+        command.arg("--renode").arg(&args.renode);
+    }
+    command.args(&crates);
+    status(command, "stack-usage")
+}
+
+fn coverage(root: &Path, args: &Coverage) -> Result<(), Error> {
+    let crates = firmware_crates(root, &args.crates)?;
+    if !args.no_build {
+        cargo_build(root, &crates, true)?;
+    }
+    build_tools(root)?;
+
+    // Traces of an older build would map to the wrong lines
+    let traces = root.join(COVERAGE_DIR).join("traces");
+    if traces.exists() {
+        fs::remove_dir_all(&traces)?;
+    }
+
+    let mut command = host_cargo(root);
+    command.args(["run", "--quiet", "--release", "-p", "renode-runner", "--"]);
+    command.arg("--root").arg(root);
+    command.arg("--renode").arg(&args.renode);
+    command.arg("--trace-dir").arg(&traces);
+    if let Some(jobs) = args.jobs {
+        command.arg("--jobs").arg(jobs.to_string());
+    }
+    if !args.crates.is_empty() {
+        command.args(&crates);
+    }
+    // Failing tests still leave traces worth reporting
+    let tests = status(command, "renode-runner");
+
+    let mut command = host_cargo(root);
+    command.args(["run", "--quiet", "--release", "-p", "trace-coverage", "--"]);
+    command.arg("--root").arg(root);
+    command.arg("--traces").arg(&traces);
+    if let Some(minimum) = args.fail_under {
+        command.arg("--fail-under").arg(minimum.to_string());
+    }
+    if !args.crates.is_empty() {
+        command.args(&crates);
+    }
+    status(command, "trace-coverage")?;
+    tests
+}
+
+fn profile(root: &Path, args: &Profile) -> Result<(), Error> {
+    let crates = firmware_crates(root, std::slice::from_ref(&args.krate))?;
+    if !args.no_build {
+        cargo_build(root, &crates, true)?;
+    }
+    build_tools(root)?;
+
+    let dir = root.join(PROFILE_DIR);
+    let traces = dir.join("traces");
+    if traces.exists() {
+        fs::remove_dir_all(&traces)?;
+    }
+
+    let mut command = host_cargo(root);
+    command.args(["run", "--quiet", "--release", "-p", "renode-runner", "--"]);
+    command.arg("--root").arg(root);
+    command.arg("--renode").arg(&args.renode);
+    command.arg("--trace-dir").arg(&traces);
+    for name in &args.tests {
+        command.arg("--test").arg(name);
+    }
+    command.args(&crates);
+    let tests = status(command, "renode-runner");
+
+    let mut command = host_cargo(root);
+    command.args(["run", "--quiet", "--release", "-p", "trace-profile", "--"]);
+    command.arg("--root").arg(root);
+    command.arg("--traces").arg(&traces);
+    command
+        .arg("--chrome")
+        .arg(dir.join(format!("{}.json", crates[0])));
+    if let Some(top) = args.top {
+        command.arg("--top").arg(top.to_string());
+    }
+    command.args(&crates);
+    status(command, "trace-profile")?;
+    tests
+}
+
+/// Build `stm32f3-dma` at several log levels and look for its log
+/// messages in the ELF: a message above the level must not be there at all
+fn log_levels(root: &Path) -> Result<(), Error> {
+    for &(feature, info, debug) in LOG_LEVELS {
+        let level = feature.unwrap_or("the default level");
+        let target_dir = root
+            .join(LOG_LEVELS_DIR)
+            .join(feature.unwrap_or("default"));
+
+        let mut command = Command::new(cargo());
+        command
+            .args(["build", "--quiet", "--release", "-p", LOG_LEVELS_CRATE])
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .current_dir(root);
+        if let Some(feature) = feature {
+            command
+                .arg("--features")
+                .arg(format!("stm32f3-common/{}", feature));
+        }
+        status(command, "cargo build")?;
+
+        let elf = fs::read(
+            target_dir
+                .join(TARGET)
+                .join("release")
+                .join(LOG_LEVELS_CRATE),
+        )?;
+        for (message, wanted) in LOG_MESSAGES.into_iter().zip([info, debug]) {
+            let present = elf
+                .windows(message.len())
+                .any(|window| window == message.as_bytes());
+            if present != wanted {
+                return Err(Error::LogLevel {
+                    level: level.to_string(),
+                    message,
+                    present,
+                });
+            }
+        }
+        let kept: Vec<&str> = ["INFO", "DEBUG"]
+            .into_iter()
+            .zip([info, debug])
+            .filter(|&(_, kept)| kept)
+            .map(|(name, _)| name)
+            .collect();
+        println!(
+            "{:<18} keeps {}",
+            level,
+            if kept.is_empty() {
+                "no log lines".to_string()
+            } else {
+                kept.join(" and ")
+            }
+        );
+    }
+    Ok(())
+}
+
+fn svd(root: &Path, args: &Svd) -> Result<(), Error> {
+    let path = root.join(SVD_PATH);
+    if path.is_file() && !args.force {
+        println!("{} is there (--force to download it again)", path.display());
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    // Download next to it first, so a failed download leaves no stub
+    let partial = path.with_extension("svd.part");
+    let mut command = Command::new("curl");
+    command
+        .args(["--fail", "--location", "--silent", "--show-error", "--output"])
+        .arg(&partial)
+        .arg(&args.url);
+    if let Err(err) = status(command, "curl") {
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+    fs::rename(&partial, &path)?;
+    println!("{} <- {}", path.display(), args.url);
+    Ok(())
+}
+
+/// Host programs the suites start (`uart-peer`) or run (`frame-decode`,
+/// `gdb-dump`), which the runner finds next to its own binary
+fn build_tools(root: &Path) -> Result<(), Error> {
+    let mut command = host_cargo(root);
+    command.args(["build", "--quiet", "--release"]);
+    for package in ["uart-peer", "frame-decode", "gdb-remote"] {
+        command.args(["-p", package]);
+    }
+    status(command, "cargo build")
+}
+
+/// `cargo build` the given crates for the firmware target
+fn cargo_build(root: &Path, crates: &[String], release: bool) -> Result<(), Error> {
+    let mut command = Command::new(cargo());
+    command.arg("build").current_dir(root);
+    if release {
+        command.arg("--release");
+    }
+    for krate in crates {
+        command.arg("-p").arg(krate);
+    }
+    status(command, "cargo build")
+}
+
+/// `cargo` running in the host workspace, whose config targets the host
+fn host_cargo(root: &Path) -> Command {
+    let mut command = Command::new(cargo());
+    command.current_dir(root.join("host"));
+    command
+}
+
+/// The cargo that launched us, so toolchain overrides carry through
+fn cargo() -> OsString {
+    env::var_os("CARGO").unwrap_or_else(|| "cargo".into())
+}
+
+fn status(mut command: Command, program: &str) -> Result<(), Error> {
+    let status = command.status().map_err(|err| Error::Spawn {
+        program: program.to_string(),
+        err,
+    })?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Failed {
+            program: program.to_string(),
+            code: status.code(),
+        })
+    }
+}
+
+/// Firmware crate names for the given peripherals, or every firmware crate
+///
+/// Firmware crates are the `stm32f3-*` directories with a
+/// `renode-config.resc`; `stm32f3-common` is a library and is skipped.
+fn firmware_crates(root: &Path, wanted: &[String]) -> Result<Vec<String>, Error> {
+    let mut all: Vec<String> = fs::read_dir(root)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.join("renode-config.resc").is_file())
+        .filter_map(|path| path.file_name()?.to_str().map(str::to_string))
+        .filter(|name| name.starts_with("stm32f3-"))
+        .collect();
+    all.sort();
+
+    if wanted.is_empty() {
+        return Ok(all);
+    }
+    wanted
+        .iter()
+        .map(|name| {
+            let name = if name.starts_with("stm32f3-") {
+                name.clone()
+            } else {
+                format!("stm32f3-{}", name)
+            };
+            if all.contains(&name) {
+                Ok(name)
+            } else {
+                Err(Error::UnknownCrate(name))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    fn root() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+    }
+
+    #[test]
+    fn cli_is_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_test_options() {
+        let cli = Cli::try_parse_from(["xtask", "test", "dma", "i2c", "-j", "4", "--test", "Echo"])
+            .unwrap();
+        let Task::Test(test) = cli.task else {
+            panic!("not a test task");
+        };
+        assert_eq!(test.crates, ["dma", "i2c"]);
+        assert_eq!(test.jobs, Some(4));
+        assert_eq!(test.tests, ["Echo"]);
+        assert!(!test.golden && !test.no_build);
+
+        assert!(Cli::try_parse_from(["xtask", "run"]).is_err());
+        assert!(Cli::try_parse_from(["xtask", "log-levels", "extra"]).is_err());
+    }
+
+    #[test]
+    fn finds_firmware_crates() {
+        let all = firmware_crates(&root(), &[]).unwrap();
+        assert!(all.contains(&"stm32f3-dma".to_string()));
+        assert!(!all.contains(&"stm32f3-common".to_string()));
+        assert!(all.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let wanted = ["dma".to_string(), "stm32f3-dma".to_string()];
+        assert_eq!(
+            firmware_crates(&root(), &wanted).unwrap(),
+            ["stm32f3-dma", "stm32f3-dma"]
+        );
+        let unknown = firmware_crates(&root(), &["nope".to_string()]).unwrap_err();
+        assert_eq!(unknown.to_string(), "no firmware crate stm32f3-nope");
+    }
+
+    #[test]
+    fn describes_errors() {
+        let failed = Error::Failed {
+            program: "curl".into(),
+            code: Some(22),
+        };
+        assert_eq!(failed.to_string(), "curl exited with status 22");
+        let killed = Error::Failed {
+            program: "renode".into(),
+            code: None,
+        };
+        assert_eq!(killed.to_string(), "renode was killed");
+        let level = Error::LogLevel {
+            level: "max-level-off".into(),
+            message: "Transferring ",
+            present: true,
+        };
+        assert_eq!(
+            level.to_string(),
+            "`Transferring` is compiled in at max-level-off"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! STM32F3 ADC (Analog-to-Digital Converter) Test
+//!
+//! This tests the ADC functionality:
+//! - ADC initialization and enable
+//! - Single conversion mode
+//! - Read conversion result
+//! - Reports results via USART1
+
+#![no_std]
+
+use core::fmt::Write;
+use stm32f3_common::stack;
+use stm32f3_common::{constants, delay, uart_write_hex, uart_write_hex16, uart_write_str};
+use stm32f3xx_hal::{
+    gpio::{Output, PushPull, PE9},
+    pac,
+    prelude::*,
+};
+
+/// Run the ADC tests, reporting on `serial`; `true` if all passed
+///
+/// LD3 (`led`, PE9) is left on when they pass.
+pub fn run<W: Write>(serial: &mut W, led: &mut PE9<Output<PushPull>>) -> bool {
+    // Get ADC peripherals via PAC
+    let adc1 = unsafe { &*pac::ADC1::ptr() };
+    let adc1_2 = unsafe { &*pac::ADC1_2::ptr() };
+    let rcc_ptr = unsafe { &*pac::RCC::ptr() };
+
+    // Initialize ADC
+    // Enable ADC clock (ADC12 is bit 28 of AHBENR)
+    rcc_ptr.ahbenr.modify(|_, w| w.adc12en().enabled());
+
+    delay(constants::MEDIUM_DELAY);
+
+    // Configure ADC clock in common control register
+    // CKMODE = 01 (synchronous clock mode, ADC clock = AHB clock / 1)
+    adc1_2.ccr.modify(|_, w| w.ckmode().bits(0b01));
+
+    // Make sure ADC is disabled first
+    adc1.cr.write(|w| w.aden().clear_bit());
+    delay(constants::STABILIZATION_DELAY);
+
+    // Configure ADC:
+    // - Single conversion mode (CONT = 0)
+    // - Right alignment (ALIGN = 0)
+    // - 12-bit resolution (RES = 00)
+    adc1.cfgr
+        .write(|w| w.cont().single().align().right().res().bits12());
+
+    // Set sequence length to 1 (L = 0 means 1 conversion)
+    // and select channel 0 for first conversion
+    adc1.sqr1.write(|w| unsafe { w.l().bits(0).sq1().bits(0) });
+
+    // Enable ADC (ADEN = 1)
+    adc1.cr.modify(|_, w| w.aden().enabled());
+
+    // Wait for ADC ready (ADRDY flag in ISR)
+    let mut timeout = constants::INIT_TIMEOUT;
+    while adc1.isr.read().adrdy().is_not_ready() && timeout > 0 {
+        timeout -= 1;
+        delay(10);
+    }
+
+    uart_write_str(serial, "ADC1 initialized\n");
+    led.set_high().ok();
+
+    // Perform conversions
+    let mut test_passed = true;
+    let num_conversions = 3;
+
+    for i in 0..num_conversions {
+        // Start conversion (ADSTART = 1)
+        adc1.cr.modify(|_, w| w.adstart().set_bit());
+
+        // Wait for end of conversion (EOC flag)
+        let mut timeout = constants::INIT_TIMEOUT;
+        while adc1.isr.read().eoc().is_not_complete() && timeout > 0 {
+            timeout -= 1;
+            delay(10);
+        }
+
+        // Read conversion result
+        let result = adc1.dr.read().rdata().bits();
+
+        uart_write_str(serial, "Channel 0 conversion ");
+        uart_write_hex(serial, i as u8);
+        uart_write_str(serial, ": 0x");
+        uart_write_hex16(serial, result);
+
+        // In simulation, we expect a valid 12-bit value (0-4095)
+        if result <= 0x0FFF {
+            uart_write_str(serial, " OK\n");
+        } else {
+            uart_write_str(serial, " FAIL\n");
+            test_passed = false;
+        }
+
+        // Clear EOC flag by reading DR (already done above)
+        delay(constants::LONG_DELAY);
+    }
+
+    // Summary
+    uart_write_str(serial, "\n=== Test Summary ===\n");
+    uart_write_str(serial, "Conversions: ");
+    uart_write_hex(serial, num_conversions as u8);
+    uart_write_str(serial, "\n");
+
+    stack::uart_write_stack_usage(serial);
+    if test_passed {
+        uart_write_str(serial, "ADC TEST PASSED\n");
+        led.set_high().ok();
+    } else {
+        uart_write_str(serial, "ADC TEST FAILED\n");
+        led.set_low().ok();
+    }
+
+    test_passed
+}
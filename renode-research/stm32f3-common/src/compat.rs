@@ -0,0 +1,271 @@
+//! embedded-hal 1.0 wrappers for the stm32f3xx-hal drivers
+//!
+//! stm32f3xx-hal 0.10 implements the embedded-hal 0.2 traits. These
+//! wrappers expose its I2C and SPI drivers through the 1.0 `I2c` and
+//! `SpiBus` traits, and provide a `DelayNs` implementation, so drivers
+//! written in this workspace can be generic over embedded-hal 1.0.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{self, I2c, NoAcknowledgeSource, Operation, SevenBitAddress};
+use embedded_hal::spi::{self, SpiBus};
+use embedded_hal_02::blocking::i2c as i2c_02;
+use embedded_hal_02::spi::FullDuplex;
+use stm32f3xx_hal::{i2c::Error as HalI2cError, spi::Error as HalSpiError};
+
+/// Error reported by [`I2cCompat`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct I2cError(i2c::ErrorKind);
+
+impl From<HalI2cError> for I2cError {
+    fn from(err: HalI2cError) -> Self {
+        I2cError(match err {
+            HalI2cError::Arbitration => i2c::ErrorKind::ArbitrationLoss,
+            HalI2cError::Bus => i2c::ErrorKind::Bus,
+            HalI2cError::Nack => i2c::ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            _ => i2c::ErrorKind::Other,
+        })
+    }
+}
+
+impl i2c::Error for I2cError {
+    fn kind(&self) -> i2c::ErrorKind {
+        self.0
+    }
+}
+
+/// Most bytes in a run of adjacent operations of one kind that
+/// [`I2cCompat`] copies into a single transfer
+pub const I2C_MERGE_MAX: usize = 64;
+
+/// embedded-hal 1.0 `I2c` adapter for `stm32f3xx_hal::i2c::I2c`
+///
+/// The 0.2 traits only offer write, read and write-then-read as single bus
+/// transactions, so those are the shapes a 1.0 transaction can take here.
+/// Adjacent operations of one kind are merged into one transfer, as the 1.0
+/// contract requires (no START between them), copying through a buffer of
+/// [`I2C_MERGE_MAX`] bytes when there is more than one. Writes followed by
+/// reads become one write-then-read with a repeated START and no STOP in
+/// between. Anything else, such as a write after a read, would need a STOP
+/// the contract forbids and fails with [`i2c::ErrorKind::Other`] before
+/// touching the bus.
+pub struct I2cCompat<T> {
+    i2c: T,
+}
+
+impl<T> I2cCompat<T> {
+    /// Wrap a HAL I2C driver
+    pub fn new(i2c: T) -> Self {
+        Self { i2c }
+    }
+
+    /// Unwrap the HAL I2C driver
+    pub fn free(self) -> T {
+        self.i2c
+    }
+}
+
+impl<T> i2c::ErrorType for I2cCompat<T> {
+    type Error = I2cError;
+}
+
+impl<T, E> I2c<SevenBitAddress> for I2cCompat<T>
+where
+    T: i2c_02::Write<Error = E> + i2c_02::Read<Error = E> + i2c_02::WriteRead<Error = E>,
+    E: Into<I2cError>,
+{
+    fn transaction(
+        &mut self,
+        address: SevenBitAddress,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if operations.is_empty() {
+            return Ok(());
+        }
+        // Writes first, then reads: the only shapes the 0.2 traits can do
+        // without a STOP in between
+        let split = operations
+            .iter()
+            .position(|op| matches!(op, Operation::Read(_)))
+            .unwrap_or(operations.len());
+        let (writes, reads) = operations.split_at_mut(split);
+        if reads.iter().any(|op| matches!(op, Operation::Write(_))) {
+            return Err(UNSUPPORTED);
+        }
+
+        let mut write_buf = [0u8; I2C_MERGE_MAX];
+        let bytes: &[u8] = match writes {
+            [Operation::Write(bytes)] => bytes,
+            _ => {
+                let mut len = 0;
+                for op in writes.iter() {
+                    if let Operation::Write(bytes) = op {
+                        let end = len + bytes.len();
+                        write_buf
+                            .get_mut(len..end)
+                            .ok_or(UNSUPPORTED)?
+                            .copy_from_slice(bytes);
+                        len = end;
+                    }
+                }
+                &write_buf[..len]
+            }
+        };
+
+        let read_len: usize = reads.iter().map(op_len).sum();
+        let mut read_buf = [0u8; I2C_MERGE_MAX];
+        let merged = reads.len() > 1;
+        let buffer: &mut [u8] = match reads {
+            [Operation::Read(buffer)] => buffer,
+            _ => read_buf.get_mut(..read_len).ok_or(UNSUPPORTED)?,
+        };
+
+        // The HAL takes no empty reads; an empty write is an address probe
+        match (bytes.is_empty(), buffer.is_empty()) {
+            (_, true) => self.i2c.write(address, bytes),
+            (true, false) => self.i2c.read(address, buffer),
+            (false, false) => self.i2c.write_read(address, bytes, buffer),
+        }
+        .map_err(Into::into)?;
+
+        if merged {
+            let mut at = 0;
+            for op in reads.iter_mut() {
+                if let Operation::Read(buffer) = op {
+                    buffer.copy_from_slice(&read_buf[at..at + buffer.len()]);
+                    at += buffer.len();
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A transaction [`I2cCompat`] cannot run as the 1.0 contract requires
+const UNSUPPORTED: I2cError = I2cError(i2c::ErrorKind::Other);
+
+fn op_len(op: &Operation<'_>) -> usize {
+    match op {
+        Operation::Write(bytes) => bytes.len(),
+        Operation::Read(buffer) => buffer.len(),
+    }
+}
+
+/// Error reported by [`SpiCompat`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpiError(spi::ErrorKind);
+
+impl From<HalSpiError> for SpiError {
+    fn from(err: HalSpiError) -> Self {
+        SpiError(match err {
+            HalSpiError::Overrun => spi::ErrorKind::Overrun,
+            HalSpiError::ModeFault => spi::ErrorKind::ModeFault,
+            _ => spi::ErrorKind::Other,
+        })
+    }
+}
+
+impl spi::Error for SpiError {
+    fn kind(&self) -> spi::ErrorKind {
+        self.0
+    }
+}
+
+/// embedded-hal 1.0 `SpiBus` adapter for `stm32f3xx_hal::spi::Spi`
+///
+/// Built on the 0.2 `FullDuplex` trait: every word is sent and the word
+/// clocked in at the same time is read back.
+pub struct SpiCompat<T> {
+    spi: T,
+}
+
+impl<T> SpiCompat<T> {
+    /// Wrap a HAL SPI driver
+    pub fn new(spi: T) -> Self {
+        Self { spi }
+    }
+
+    /// Unwrap the HAL SPI driver
+    pub fn free(self) -> T {
+        self.spi
+    }
+}
+
+impl<T, E> SpiCompat<T>
+where
+    T: FullDuplex<u8, Error = E>,
+    E: Into<SpiError>,
+{
+    fn exchange(&mut self, word: u8) -> Result<u8, SpiError> {
+        nb::block!(self.spi.send(word)).map_err(Into::into)?;
+        nb::block!(self.spi.read()).map_err(Into::into)
+    }
+}
+
+impl<T> spi::ErrorType for SpiCompat<T> {
+    type Error = SpiError;
+}
+
+impl<T, E> SpiBus<u8> for SpiCompat<T>
+where
+    T: FullDuplex<u8, Error = E>,
+    E: Into<SpiError>,
+{
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.exchange(0x00)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        for &word in words {
+            self.exchange(word)?;
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        // Clock out the longer of the two; pad writes with zeros and
+        // discard reads past the end of the read buffer
+        for i in 0..read.len().max(write.len()) {
+            let received = self.exchange(write.get(i).copied().unwrap_or(0x00))?;
+            if let Some(slot) = read.get_mut(i) {
+                *slot = received;
+            }
+        }
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        for word in words.iter_mut() {
+            *word = self.exchange(*word)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        // Every exchange waits for its received word, so nothing is in flight
+        Ok(())
+    }
+}
+
+/// embedded-hal 1.0 `DelayNs` implementation using CPU cycle counting
+///
+/// Delays are at least as long as requested; interrupts can extend them.
+pub struct CycleDelay {
+    sysclk_hz: u32,
+}
+
+impl CycleDelay {
+    /// Create a delay for a core running at `sysclk_hz`
+    pub fn new(sysclk_hz: u32) -> Self {
+        Self { sysclk_hz }
+    }
+}
+
+impl DelayNs for CycleDelay {
+    fn delay_ns(&mut self, ns: u32) {
+        let cycles = (u64::from(ns) * u64::from(self.sysclk_hz)).div_ceil(1_000_000_000);
+        cortex_m::asm::delay(cycles.min(u64::from(u32::MAX)) as u32);
+    }
+}
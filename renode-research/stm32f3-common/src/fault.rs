@@ -0,0 +1,83 @@
+//! HardFault report on USART1
+//!
+//! `panic-halt` and the default `cortex-m-rt` handler stop silently, so a
+//! fault looks like a hang from outside. Firmware that takes input forwards
+//! its HardFault handler here to print the stacked PC and LR and the fault
+//! status registers before halting:
+//!
+//! ```ignore
+//! #[exception]
+//! unsafe fn HardFault(frame: &ExceptionFrame) -> ! {
+//!     stm32f3_common::fault::report(frame)
+//! }
+//! ```
+//!
+//! ```text
+//! *** FAULT: HardFault PC=0x08000F12 LR=0x08000E9B CFSR=0x00008200 HFSR=0x40000000 BFAR=0x20010000
+//! ```
+//!
+//! The line always starts with [`FAULT_MARKER`], which the host `uart-fuzz`
+//! tool watches for. USART1 is written through its registers, since the
+//! `Serial` belongs to whatever code was interrupted.
+
+use core::fmt::{self, Write};
+
+use cortex_m_rt::ExceptionFrame;
+use stm32f3xx_hal::pac;
+
+use crate::uart::{uart_write_hex32, uart_write_str};
+
+/// Start of the report line
+pub const FAULT_MARKER: &str = "*** FAULT:";
+
+/// Configurable Fault Status Register
+const CFSR: *const u32 = 0xE000_ED28 as *const u32;
+/// HardFault Status Register
+const HFSR: *const u32 = 0xE000_ED2C as *const u32;
+/// BusFault Address Register
+const BFAR: *const u32 = 0xE000_ED38 as *const u32;
+
+/// USART1 transmitter used without the HAL
+struct RawUsart1;
+
+impl Write for RawUsart1 {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let usart1 = unsafe { &*pac::USART1::ptr() };
+        for byte in s.bytes() {
+            while usart1.isr.read().txe().bit_is_clear() {}
+            usart1.tdr.write(|w| w.tdr().bits(byte as u16));
+        }
+        Ok(())
+    }
+}
+
+/// Print the fault report on USART1 and halt
+pub fn report(frame: &ExceptionFrame) -> ! {
+    let out = &mut RawUsart1;
+    // SAFETY: the System Control Block fault registers are always readable
+    let (cfsr, hfsr, bfar) = unsafe {
+        (
+            core::ptr::read_volatile(CFSR),
+            core::ptr::read_volatile(HFSR),
+            core::ptr::read_volatile(BFAR),
+        )
+    };
+
+    uart_write_str(out, "\n");
+    uart_write_str(out, FAULT_MARKER);
+    for (name, value) in [
+        (" HardFault PC=0x", frame.pc()),
+        (" LR=0x", frame.lr()),
+        (" CFSR=0x", cfsr),
+        (" HFSR=0x", hfsr),
+        (" BFAR=0x", bfar),
+    ] {
+        uart_write_str(out, name);
+        uart_write_hex32(out, value);
+    }
+    uart_write_str(out, "\n");
+
+    loop {
+        cortex_m::asm::nop();
+    }
+}
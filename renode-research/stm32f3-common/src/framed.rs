@@ -0,0 +1,194 @@
+//! Binary results channel: postcard records in COBS frames
+//!
+//! Text lines are easy to read but fragile: a stray byte on the line or two
+//! contexts printing at once can corrupt a `PASS` line without the host
+//! noticing. With the `framed` feature the firmware can send [`Record`]s
+//! instead, each in its own frame:
+//!
+//! ```text
+//! 0x00 | COBS( postcard((seq, record)) | crc16 ) | 0x00
+//! ```
+//!
+//! - `seq` is a `u16` counting frames from 0, so the host sees lost ones
+//! - `crc16` is CRC-16/CCITT-FALSE of the postcard bytes, little endian
+//! - COBS removes every zero byte, so `0x00` only ever delimits frames; the
+//!   leading delimiter separates the frame from text printed before it
+//!
+//! COBS and the CRC come from the host workspace's `frame-codec` crate,
+//! which `frame-decode` checks frames with, so the two cannot drift apart.
+//!
+//! Frames and ordinary text can share USART1; [`FrameWriter`] passes text
+//! written through `core::fmt::Write` straight on. The host `frame-decode` tool
+//! splits the stream on `0x00`, checks each frame, passes text lines
+//! through and reports frames that fail to decode. Its `Record` mirrors the
+//! one here: the order of variants and fields is the wire format.
+//!
+//! ```ignore
+//! let mut frames = FrameWriter::new(SerialPort::new(serial));
+//! frames.send(&Record::Start { name: "stm32f3-crc" })?;
+//! frames.result("Single word CRC", crc_result != 0xFFFF_FFFF, crc_result)?;
+//! frames.log(Level::Info, module_path!(), format_args!("CRC: 0x{:08X}", crc_result))?;
+//! ```
+
+use core::fmt;
+
+use embedded_io::Write;
+use frame_codec::{cobs_encode, crc16, max_encoded_len};
+use serde::Serialize;
+
+use crate::log::Level;
+
+/// Largest serialized record (sequence number included)
+pub const MAX_PAYLOAD: usize = 128;
+
+/// Longest log message kept; longer ones are truncated
+pub const MAX_MESSAGE: usize = 96;
+
+/// Payload, CRC, COBS overhead and both delimiters
+const FRAME_CAPACITY: usize = max_encoded_len(MAX_PAYLOAD + 2) + 2;
+
+/// One message on the results channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum Record<'a> {
+    /// A run started; sent before its results
+    Start { name: &'a str },
+    /// Outcome of one test, with the value it was judged on
+    Result {
+        test: &'a str,
+        passed: bool,
+        value: u32,
+    },
+    /// A log message
+    Log {
+        level: Level,
+        module: &'a str,
+        message: &'a str,
+    },
+    /// The run finished
+    Summary { passed: u16, failed: u16 },
+}
+
+/// Error sending a frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameError<E> {
+    /// The record does not fit in [`MAX_PAYLOAD`] bytes
+    TooLarge,
+    /// The writer failed
+    Write(E),
+}
+
+/// Sends [`Record`]s as frames over an `embedded_io` writer
+///
+/// Each frame goes out in a single `write_all`. A frame interleaved with
+/// other output fails its CRC on the host and is reported, never misread.
+pub struct FrameWriter<W> {
+    writer: W,
+    seq: u16,
+}
+
+impl<W: Write> FrameWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, seq: 0 }
+    }
+
+    /// Get a reference to the wrapped writer, e.g. to print text
+    pub fn inner(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Unwrap the writer
+    pub fn free(self) -> W {
+        self.writer
+    }
+
+    /// Send one record
+    pub fn send(&mut self, record: &Record) -> Result<(), FrameError<W::Error>> {
+        let mut payload = [0u8; MAX_PAYLOAD + 2];
+        let used = postcard::to_slice(&(self.seq, record), &mut payload[..MAX_PAYLOAD])
+            .map_err(|_| FrameError::TooLarge)?
+            .len();
+        let crc = crc16(&payload[..used]);
+        payload[used..used + 2].copy_from_slice(&crc.to_le_bytes());
+
+        let mut frame = [0u8; FRAME_CAPACITY];
+        let len = cobs_encode(&payload[..used + 2], &mut frame[1..FRAME_CAPACITY - 1])
+            .ok_or(FrameError::TooLarge)?;
+        // frame[0] and frame[len + 1] stay 0: the delimiters
+        self.writer
+            .write_all(&frame[..len + 2])
+            .map_err(FrameError::Write)?;
+        self.seq = self.seq.wrapping_add(1);
+        Ok(())
+    }
+
+    /// Send a [`Record::Result`]
+    pub fn result(
+        &mut self,
+        test: &str,
+        passed: bool,
+        value: u32,
+    ) -> Result<(), FrameError<W::Error>> {
+        self.send(&Record::Result {
+            test,
+            passed,
+            value,
+        })
+    }
+
+    /// Format and send a [`Record::Log`], truncated to [`MAX_MESSAGE`] bytes
+    pub fn log(
+        &mut self,
+        level: Level,
+        module: &str,
+        args: fmt::Arguments,
+    ) -> Result<(), FrameError<W::Error>> {
+        let mut message = Truncating {
+            buf: [0; MAX_MESSAGE],
+            len: 0,
+        };
+        let _ = fmt::write(&mut message, args);
+        self.send(&Record::Log {
+            level,
+            module,
+            message: message.as_str(),
+        })
+    }
+
+    /// Send a [`Record::Summary`]
+    pub fn summary(&mut self, passed: u16, failed: u16) -> Result<(), FrameError<W::Error>> {
+        self.send(&Record::Summary { passed, failed })
+    }
+}
+
+/// Text goes out as it is, between frames
+impl<W: Write> fmt::Write for FrameWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+/// Formats into a fixed buffer, dropping what does not fit
+struct Truncating {
+    buf: [u8; MAX_MESSAGE],
+    len: usize,
+}
+
+impl Truncating {
+    fn as_str(&self) -> &str {
+        // Only whole characters are ever copied in
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+impl fmt::Write for Truncating {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = MAX_MESSAGE - self.len;
+        let mut take = s.len().min(room);
+        while !s.is_char_boundary(take) {
+            take -= 1;
+        }
+        self.buf[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+//! Firmware image header with build information
+//!
+//! Each firmware crate places an [`ImageHeader`] at [`IMAGE_HEADER_ADDR`],
+//! a fixed offset just after the vector table, by invoking
+//! [`image_header!`](crate::image_header) once. The build metadata comes
+//! from the `stm32f3-build` build-script helper.
+//!
+//! The header lets Robot logs, transcripts and host tools identify the exact
+//! build that produced them: the firmware prints it at boot, Renode tests
+//! can read it from flash, and host tools read the same bytes from the ELF.
+//!
+//! Layout (little endian, 128 bytes):
+//!
+//! | Offset | Size | Field |
+//! |--------|------|-------|
+//! | 0x00 | 4 | magic `IMAGE_HEADER_MAGIC` |
+//! | 0x04 | 2 | header version |
+//! | 0x06 | 2 | header size |
+//! | 0x08 | 8 | build timestamp (Unix seconds) |
+//! | 0x10 | 4 | flags (bit 0: git tree dirty) |
+//! | 0x14 | 4 | reserved |
+//! | 0x18 | 32 | crate name (NUL padded) |
+//! | 0x38 | 16 | crate version (NUL padded) |
+//! | 0x48 | 40 | git commit hash (NUL padded) |
+//! | 0x70 | 16 | cargo profile (NUL padded) |
+
+use core::fmt::Write;
+
+use crate::uart::{uart_write_dec64, uart_write_str};
+
+/// Flash address of the image header (must match `memory.x`)
+pub const IMAGE_HEADER_ADDR: u32 = 0x0800_0200;
+
+/// Magic value identifying a valid header ("IMGH" in flash byte order)
+pub const IMAGE_HEADER_MAGIC: u32 = u32::from_le_bytes(*b"IMGH");
+
+/// Current header layout version
+pub const IMAGE_HEADER_VERSION: u16 = 1;
+
+/// Flag bit set when the git working tree had uncommitted changes
+pub const FLAG_GIT_DIRTY: u32 = 1 << 0;
+
+/// Build information stored at a fixed flash offset
+#[repr(C)]
+pub struct ImageHeader {
+    pub magic: u32,
+    pub header_version: u16,
+    pub header_size: u16,
+    pub build_timestamp: u64,
+    pub flags: u32,
+    pub reserved: u32,
+    pub crate_name: [u8; 32],
+    pub crate_version: [u8; 16],
+    pub git_commit: [u8; 40],
+    pub profile: [u8; 16],
+}
+
+impl ImageHeader {
+    /// Build a header from the `BUILD_*` values; used by `image_header!`
+    pub const fn new(
+        crate_name: &str,
+        crate_version: &str,
+        git_commit: &str,
+        git_dirty: &str,
+        profile: &str,
+        build_timestamp: &str,
+    ) -> Self {
+        Self {
+            magic: IMAGE_HEADER_MAGIC,
+            header_version: IMAGE_HEADER_VERSION,
+            header_size: core::mem::size_of::<ImageHeader>() as u16,
+            build_timestamp: parse_u64(build_timestamp),
+            flags: if parse_u64(git_dirty) != 0 {
+                FLAG_GIT_DIRTY
+            } else {
+                0
+            },
+            reserved: 0,
+            crate_name: padded(crate_name),
+            crate_version: padded(crate_version),
+            git_commit: padded(git_commit),
+            profile: padded(profile),
+        }
+    }
+
+    /// The header of the running image, if one was linked in
+    pub fn current() -> Option<&'static ImageHeader> {
+        // SAFETY: the address is in flash, which is always readable and
+        // never written at runtime; the magic check rejects images that
+        // did not place a header there
+        let header = unsafe { &*(IMAGE_HEADER_ADDR as *const ImageHeader) };
+        if header.magic == IMAGE_HEADER_MAGIC {
+            Some(header)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the git working tree had uncommitted changes
+    pub fn is_dirty(&self) -> bool {
+        self.flags & FLAG_GIT_DIRTY != 0
+    }
+
+    /// Crate name as a string
+    pub fn crate_name(&self) -> &str {
+        field_str(&self.crate_name)
+    }
+
+    /// Crate version as a string
+    pub fn crate_version(&self) -> &str {
+        field_str(&self.crate_version)
+    }
+
+    /// Git commit hash as a string
+    pub fn git_commit(&self) -> &str {
+        field_str(&self.git_commit)
+    }
+
+    /// Cargo profile as a string
+    pub fn profile(&self) -> &str {
+        field_str(&self.profile)
+    }
+}
+
+/// Copy a string into a NUL-padded fixed-size field, truncating if needed
+const fn padded<const N: usize>(s: &str) -> [u8; N] {
+    let bytes = s.as_bytes();
+    let mut out = [0u8; N];
+    let mut i = 0;
+    while i < bytes.len() && i < N {
+        out[i] = bytes[i];
+        i += 1;
+    }
+    out
+}
+
+/// Parse a decimal string at compile time (non-digits are ignored)
+const fn parse_u64(s: &str) -> u64 {
+    let bytes = s.as_bytes();
+    let mut value = 0u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i].is_ascii_digit() {
+            value = value * 10 + (bytes[i] - b'0') as u64;
+        }
+        i += 1;
+    }
+    value
+}
+
+/// View a NUL-padded field as a string
+fn field_str(field: &[u8]) -> &str {
+    let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    core::str::from_utf8(&field[..len]).unwrap_or("?")
+}
+
+/// Write the running image's build line to UART
+///
+/// Prints `Build: <name> <version> git:<commit>[-dirty] profile:<profile>
+/// time:<timestamp>` so logs record which firmware produced them.
+///
+/// # Arguments
+/// * `uart` - Any type implementing `core::fmt::Write`
+pub fn uart_write_build_info<W: Write>(uart: &mut W) {
+    match ImageHeader::current() {
+        Some(header) => {
+            uart_write_str(uart, "Build: ");
+            uart_write_str(uart, header.crate_name());
+            uart_write_str(uart, " ");
+            uart_write_str(uart, header.crate_version());
+            uart_write_str(uart, " git:");
+            uart_write_str(uart, header.git_commit());
+            if header.is_dirty() {
+                uart_write_str(uart, "-dirty");
+            }
+            uart_write_str(uart, " profile:");
+            uart_write_str(uart, header.profile());
+            uart_write_str(uart, " time:");
+            uart_write_dec64(uart, header.build_timestamp);
+            uart_write_str(uart, "\n");
+        }
+        None => uart_write_str(uart, "Build: unknown (no image header)\n"),
+    }
+}
+
+/// Place this crate's [`ImageHeader`] at [`IMAGE_HEADER_ADDR`]
+///
+/// Invoke once at the top level of a firmware crate whose `build.rs`
+/// calls `stm32f3_build::emit_build_info()` and whose `memory.x` reserves
+/// the `.image_header` section.
+#[macro_export]
+macro_rules! image_header {
+    () => {
+        #[used]
+        #[no_mangle]
+        #[link_section = ".image_header"]
+        pub static IMAGE_HEADER: $crate::image::ImageHeader = $crate::image::ImageHeader::new(
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            env!("BUILD_GIT_COMMIT"),
+            env!("BUILD_GIT_DIRTY"),
+            env!("BUILD_PROFILE"),
+            env!("BUILD_TIMESTAMP"),
+        );
+    };
+}
@@ -0,0 +1,199 @@
+//! Byte-oriented output over `embedded_io::Write`
+//!
+//! Unlike the `uart_write_*` helpers, these work on raw bytes and return
+//! the writer's error instead of discarding it, so callers can detect a
+//! failed transmission.
+
+use core::convert::Infallible;
+use embedded_hal_02::serial;
+use embedded_io::{ErrorKind, ErrorType, Read, Write};
+use stm32f3xx_hal::serial::Error as HalSerialError;
+
+const HEX_CHARS: &[u8] = b"0123456789ABCDEF";
+
+/// Write bytes, converting \n to \r\n
+///
+/// # Arguments
+/// * `w` - Any type implementing `embedded_io::Write`
+/// * `bytes` - The bytes to write (need not be UTF-8)
+pub fn write_bytes<W: Write>(w: &mut W, bytes: &[u8]) -> Result<(), W::Error> {
+    for line in bytes.split_inclusive(|&b| b == b'\n') {
+        match line.split_last() {
+            Some((b'\n', body)) => {
+                w.write_all(body)?;
+                w.write_all(b"\r\n")?;
+            }
+            _ => w.write_all(line)?,
+        }
+    }
+    Ok(())
+}
+
+/// Write a string, converting \n to \r\n
+///
+/// # Arguments
+/// * `w` - Any type implementing `embedded_io::Write`
+/// * `s` - The string to write
+pub fn write_str<W: Write>(w: &mut W, s: &str) -> Result<(), W::Error> {
+    write_bytes(w, s.as_bytes())
+}
+
+/// Write a hex byte (2 hex digits)
+///
+/// # Arguments
+/// * `w` - Any type implementing `embedded_io::Write`
+/// * `byte` - The byte to write as hex
+pub fn write_hex<W: Write>(w: &mut W, byte: u8) -> Result<(), W::Error> {
+    w.write_all(&[
+        HEX_CHARS[(byte >> 4) as usize],
+        HEX_CHARS[(byte & 0x0F) as usize],
+    ])
+}
+
+/// Write a 16-bit hex value (4 hex digits)
+///
+/// # Arguments
+/// * `w` - Any type implementing `embedded_io::Write`
+/// * `value` - The 16-bit value to write as hex
+pub fn write_hex16<W: Write>(w: &mut W, value: u16) -> Result<(), W::Error> {
+    value
+        .to_be_bytes()
+        .iter()
+        .try_for_each(|&b| write_hex(w, b))
+}
+
+/// Write a 32-bit hex value (8 hex digits)
+///
+/// # Arguments
+/// * `w` - Any type implementing `embedded_io::Write`
+/// * `value` - The 32-bit value to write as hex
+pub fn write_hex32<W: Write>(w: &mut W, value: u32) -> Result<(), W::Error> {
+    value
+        .to_be_bytes()
+        .iter()
+        .try_for_each(|&b| write_hex(w, b))
+}
+
+/// Error reported by [`SerialPort`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerialError {
+    /// De-synchronization, excessive noise or a break character
+    Framing,
+    /// Noise detected on a received frame
+    Noise,
+    /// A byte was received before the previous one was read
+    Overrun,
+    /// Parity check failed
+    Parity,
+    /// Any other error reported by the HAL
+    Other,
+}
+
+impl From<HalSerialError> for SerialError {
+    fn from(err: HalSerialError) -> Self {
+        match err {
+            HalSerialError::Framing => SerialError::Framing,
+            HalSerialError::Noise => SerialError::Noise,
+            HalSerialError::Overrun => SerialError::Overrun,
+            HalSerialError::Parity => SerialError::Parity,
+            _ => SerialError::Other,
+        }
+    }
+}
+
+impl From<Infallible> for SerialError {
+    fn from(err: Infallible) -> Self {
+        match err {}
+    }
+}
+
+impl embedded_io::Error for SerialError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            SerialError::Framing | SerialError::Noise | SerialError::Parity => {
+                ErrorKind::InvalidData
+            }
+            SerialError::Overrun | SerialError::Other => ErrorKind::Other,
+        }
+    }
+}
+
+/// `embedded_io` adapter for the stm32f3xx-hal `Serial` types
+///
+/// Wraps anything implementing the embedded-hal 0.2 `serial::Write<u8>`
+/// (and optionally `serial::Read<u8>`) traits, which includes
+/// `stm32f3xx_hal::serial::Serial` and its split `Tx`/`Rx` halves.
+///
+/// ```ignore
+/// let mut port = SerialPort::new(serial);
+/// io::write_str(&mut port, "DMA Peripheral Test\n")?;
+/// ```
+pub struct SerialPort<S> {
+    serial: S,
+}
+
+impl<S> SerialPort<S> {
+    /// Wrap a HAL serial type
+    pub fn new(serial: S) -> Self {
+        Self { serial }
+    }
+
+    /// Get a reference to the wrapped serial type
+    pub fn inner(&mut self) -> &mut S {
+        &mut self.serial
+    }
+
+    /// Unwrap the HAL serial type
+    pub fn free(self) -> S {
+        self.serial
+    }
+}
+
+impl<S> ErrorType for SerialPort<S> {
+    type Error = SerialError;
+}
+
+impl<S> Write for SerialPort<S>
+where
+    S: serial::Write<u8>,
+    S::Error: Into<SerialError>,
+{
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        for &byte in buf {
+            nb::block!(self.serial.write(byte)).map_err(Into::into)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        nb::block!(self.serial.flush()).map_err(Into::into)
+    }
+}
+
+impl<S> Read for SerialPort<S>
+where
+    S: serial::Read<u8>,
+    S::Error: Into<SerialError>,
+{
+    /// Block until at least one byte arrives, then return whatever else is
+    /// already waiting without blocking again
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        buf[0] = nb::block!(self.serial.read()).map_err(Into::into)?;
+        let mut count = 1;
+        while count < buf.len() {
+            match self.serial.read() {
+                Ok(byte) => {
+                    buf[count] = byte;
+                    count += 1;
+                }
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(err)) => return Err(err.into()),
+            }
+        }
+        Ok(count)
+    }
+}
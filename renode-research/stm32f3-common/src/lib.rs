@@ -2,13 +2,32 @@
 //!
 //! This crate provides common helper functions and constants used across
 //! all STM32F3 peripheral test examples.
+//!
+//! Each peripheral crate keeps its tests in `lib.rs` behind a `run`
+//! function taking the serial port and the status LED (plus any bus the
+//! tests need) and returning whether they passed. Its `main.rs` only sets
+//! up the clocks, USART1 and the LED before calling it. `stm32f3-selftest`
+//! links the same libraries and runs them one after another in one boot.
 
 #![no_std]
 
+pub mod compat;
 pub mod constants;
 pub mod delay;
+pub mod fault;
+#[cfg(feature = "framed")]
+pub mod framed;
+pub mod image;
+pub mod io;
+pub mod log;
+pub mod shell;
+pub mod soft_timer;
+pub mod stack;
 pub mod uart;
 
 pub use constants::*;
 pub use delay::delay;
-pub use uart::{uart_write_hex, uart_write_hex16, uart_write_hex32, uart_write_str};
+pub use uart::{
+    uart_write_dec, uart_write_dec64, uart_write_hex, uart_write_hex16, uart_write_hex32,
+    uart_write_str,
+};
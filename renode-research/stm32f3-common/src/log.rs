@@ -0,0 +1,203 @@
+//! Leveled logging macros with a compile-time maximum level
+//!
+//! The `error!`, `warn!`, `info!`, `debug!` and `trace!` macros write one
+//! line per call in the form
+//!
+//! ```text
+//! [DEBUG stm32f3_dma] SRC: 0x20000000
+//! ```
+//!
+//! so the host side can split the level and module tag from the message.
+//!
+//! The maximum level is chosen with the `max-level-*` cargo features of this
+//! crate; with none enabled it is INFO. When several are enabled the most
+//! verbose one wins. None of them is a default feature, since a default
+//! `max-level-info` would outvote `max-level-warn` and below in every crate
+//! that depends on this one. Calls above the maximum level are removed at
+//! compile time and their arguments are never evaluated; `cargo xtask
+//! log-levels` checks that in the release ELFs.
+//!
+//! Individual modules can be quietened further at runtime with
+//! [`set_module_filters`].
+//!
+//! The peripheral crates log register readbacks at DEBUG and unexpected
+//! values at WARN. Test headers, results and the lines the suites wait for
+//! stay plain `uart_write_str` output, so they print at every level.
+
+use core::cell::Cell;
+use core::fmt;
+use cortex_m::interrupt::{self, Mutex};
+
+use crate::uart::uart_write_str;
+
+/// Severity of a log record (lower is more severe)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "framed", derive(serde::Serialize))]
+#[repr(u8)]
+pub enum Level {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl Level {
+    /// Tag written at the start of each line
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+/// Maximum enabled level, or `Off` to disable a module entirely
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum LevelFilter {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl LevelFilter {
+    /// Check whether records at `level` pass this filter
+    pub const fn allows(self, level: Level) -> bool {
+        level as u8 <= self as u8
+    }
+}
+
+/// Maximum level compiled into the firmware, selected by cargo feature
+/// (INFO without one)
+pub const STATIC_MAX_LEVEL: LevelFilter = if cfg!(feature = "max-level-trace") {
+    LevelFilter::Trace
+} else if cfg!(feature = "max-level-debug") {
+    LevelFilter::Debug
+} else if cfg!(feature = "max-level-info") {
+    LevelFilter::Info
+} else if cfg!(feature = "max-level-warn") {
+    LevelFilter::Warn
+} else if cfg!(feature = "max-level-error") {
+    LevelFilter::Error
+} else if cfg!(feature = "max-level-off") {
+    LevelFilter::Off
+} else {
+    LevelFilter::Info
+};
+
+/// Per-module overrides, matched by module path prefix
+static MODULE_FILTERS: Mutex<Cell<&'static [(&'static str, LevelFilter)]>> =
+    Mutex::new(Cell::new(&[]));
+
+/// Install per-module level overrides
+///
+/// Each entry is a module path prefix (e.g. `"stm32f3_dma"`) and the most
+/// verbose level allowed for it. The longest matching prefix applies;
+/// modules without a match use [`STATIC_MAX_LEVEL`]. Overrides can only
+/// lower the level, never raise it above the compiled-in maximum.
+pub fn set_module_filters(filters: &'static [(&'static str, LevelFilter)]) {
+    interrupt::free(|cs| MODULE_FILTERS.borrow(cs).set(filters));
+}
+
+/// Check whether a record from `module` at `level` should be written
+pub fn enabled(level: Level, module: &str) -> bool {
+    let filters = interrupt::free(|cs| MODULE_FILTERS.borrow(cs).get());
+
+    let mut best: Option<(usize, LevelFilter)> = None;
+    for &(prefix, filter) in filters {
+        let matches = module == prefix
+            || (module.starts_with(prefix) && module[prefix.len()..].starts_with("::"));
+        if matches && best.is_none_or(|(len, _)| prefix.len() > len) {
+            best = Some((prefix.len(), filter));
+        }
+    }
+
+    best.is_none_or(|(_, filter)| filter.allows(level))
+}
+
+/// Adapter that routes `core::fmt` output through `uart_write_str`
+struct LineWriter<'a, W: fmt::Write>(&'a mut W);
+
+impl<W: fmt::Write> fmt::Write for LineWriter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        uart_write_str(self.0, s);
+        Ok(())
+    }
+}
+
+/// Write one `[LEVEL module] message` line
+///
+/// Used by the logging macros; call those instead.
+#[doc(hidden)]
+pub fn write_record<W: fmt::Write>(uart: &mut W, level: Level, module: &str, args: fmt::Arguments) {
+    let mut line = LineWriter(uart);
+    let _ = fmt::write(
+        &mut line,
+        format_args!("[{} {}] {}\n", level.as_str(), module, args),
+    );
+}
+
+/// Log a message at an explicit level
+///
+/// # Example
+/// ```ignore
+/// log!(&mut serial, Level::Info, "Feed {}: OK", i);
+/// ```
+#[macro_export]
+macro_rules! log {
+    ($uart:expr, $level:expr, $($arg:tt)+) => {{
+        let level: $crate::log::Level = $level;
+        if $crate::log::STATIC_MAX_LEVEL.allows(level)
+            && $crate::log::enabled(level, module_path!())
+        {
+            $crate::log::write_record($uart, level, module_path!(), format_args!($($arg)+));
+        }
+    }};
+}
+
+/// Log a message at error level
+#[macro_export]
+macro_rules! error {
+    ($uart:expr, $($arg:tt)+) => {
+        $crate::log!($uart, $crate::log::Level::Error, $($arg)+)
+    };
+}
+
+/// Log a message at warn level
+#[macro_export]
+macro_rules! warn {
+    ($uart:expr, $($arg:tt)+) => {
+        $crate::log!($uart, $crate::log::Level::Warn, $($arg)+)
+    };
+}
+
+/// Log a message at info level
+#[macro_export]
+macro_rules! info {
+    ($uart:expr, $($arg:tt)+) => {
+        $crate::log!($uart, $crate::log::Level::Info, $($arg)+)
+    };
+}
+
+/// Log a message at debug level
+#[macro_export]
+macro_rules! debug {
+    ($uart:expr, $($arg:tt)+) => {
+        $crate::log!($uart, $crate::log::Level::Debug, $($arg)+)
+    };
+}
+
+/// Log a message at trace level
+#[macro_export]
+macro_rules! trace {
+    ($uart:expr, $($arg:tt)+) => {
+        $crate::log!($uart, $crate::log::Level::Trace, $($arg)+)
+    };
+}
@@ -0,0 +1,516 @@
+//! Line-editing command shell on USART1
+//!
+//! Received bytes are collected by the USART1 interrupt into a ring buffer
+//! and turned into command lines by [`Shell::poll`] in the main loop. The
+//! firmware owns the interrupt vector and forwards it here:
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn USART1_EXTI25() {
+//!     stm32f3_common::shell::on_usart1_rx();
+//! }
+//!
+//! const COMMANDS: &[Command<Leds>] = &[Command {
+//!     name: "led",
+//!     usage: "<3-10> on|off|toggle",
+//!     help: "Switch a Discovery LED",
+//!     min_args: 2,
+//!     max_args: 2,
+//!     handler: led,
+//! }];
+//!
+//! shell::init_rx();
+//! let mut shell = Shell::new(COMMANDS);
+//! shell.start(&mut serial);
+//! loop {
+//!     shell.poll(&mut leds, &mut serial);
+//! }
+//! ```
+//!
+//! Each command gets a context owned by the firmware (`Leds` above), its
+//! arguments and the UART. `help`, `peek`, `poke` and `reset` are built in;
+//! a registered command with the same name replaces the built-in one.
+//!
+//! Editing: backspace/delete, Ctrl-U clears the line, Ctrl-C abandons it,
+//! the up arrow recalls the previous line. Lines end with CR, LF or CRLF,
+//! so both terminals and `Write Line To Uart` work.
+
+use core::cell::UnsafeCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use cortex_m::peripheral::SCB;
+use stm32f3xx_hal::pac::{self, NVIC};
+
+use crate::uart::uart_write_str;
+
+/// Bytes buffered between the interrupt and [`Shell::poll`]
+pub const RX_CAPACITY: usize = 128;
+
+/// Longest command line; further characters are dropped
+pub const LINE_MAX: usize = 64;
+
+/// Most arguments a command line can have, after the command name
+pub const MAX_ARGS: usize = 8;
+
+/// Most words `peek` reads at once
+const PEEK_MAX: u32 = 16;
+
+const PROMPT: &str = "> ";
+
+/// Single-producer (interrupt), single-consumer (main loop) byte queue
+struct RxRing {
+    buf: UnsafeCell<[u8; RX_CAPACITY]>,
+    /// Next slot the interrupt writes; only the interrupt stores it
+    head: AtomicUsize,
+    /// Next slot the main loop reads; only the main loop stores it
+    tail: AtomicUsize,
+}
+
+// SAFETY: a slot is written only while it is outside head..tail and read
+// only while inside, and the indices are published with release ordering
+unsafe impl Sync for RxRing {}
+
+impl RxRing {
+    const fn new() -> Self {
+        Self {
+            buf: UnsafeCell::new([0; RX_CAPACITY]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    fn push(&self, byte: u8) -> bool {
+        let head = self.head.load(Ordering::Relaxed);
+        if head.wrapping_sub(self.tail.load(Ordering::Acquire)) == RX_CAPACITY {
+            return false;
+        }
+        // SAFETY: the slot at head is free (see `Sync` above)
+        unsafe { (*self.buf.get())[head % RX_CAPACITY] = byte };
+        self.head.store(head.wrapping_add(1), Ordering::Release);
+        true
+    }
+
+    fn pop(&self) -> Option<u8> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail == self.head.load(Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: the slot at tail holds a published byte
+        let byte = unsafe { (*self.buf.get())[tail % RX_CAPACITY] };
+        self.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Some(byte)
+    }
+}
+
+static RX: RxRing = RxRing::new();
+
+/// Bytes lost to a full ring buffer or a USART overrun
+static RX_DROPPED: AtomicU32 = AtomicU32::new(0);
+
+/// Enable the USART1 receive interrupt
+///
+/// Call after the HAL `Serial` has configured USART1.
+pub fn init_rx() {
+    let usart1 = unsafe { &*pac::USART1::ptr() };
+    usart1.cr1.modify(|_, w| w.rxneie().set_bit());
+    unsafe {
+        NVIC::unmask(pac::Interrupt::USART1_EXTI25);
+    }
+}
+
+/// Move a received byte into the ring buffer
+///
+/// Must be called from the `USART1_EXTI25` interrupt handler. Clears an
+/// overrun, which would otherwise keep the interrupt pending.
+pub fn on_usart1_rx() {
+    let usart1 = unsafe { &*pac::USART1::ptr() };
+    let isr = usart1.isr.read();
+    if isr.ore().bit_is_set() {
+        usart1.icr.write(|w| w.orecf().set_bit());
+        RX_DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+    if isr.rxne().bit_is_set() {
+        let byte = usart1.rdr.read().rdr().bits() as u8;
+        if !RX.push(byte) {
+            RX_DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Take the next received byte, if any
+pub fn read_byte() -> Option<u8> {
+    RX.pop()
+}
+
+/// Received bytes lost so far
+pub fn rx_dropped() -> u32 {
+    RX_DROPPED.load(Ordering::Relaxed)
+}
+
+/// Why a command did not run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommandError {
+    /// Wrong number of arguments; the usage line is printed
+    Usage,
+    /// The argument at this index is not acceptable
+    Invalid(usize),
+    /// The command ran and failed
+    Failed(&'static str),
+}
+
+/// Command handler: context, arguments (without the command name), output
+///
+/// Output written with `\n` reaches the UART as `\r\n`.
+pub type Handler<C> = fn(&mut C, &Args, &mut dyn Write) -> Result<(), CommandError>;
+
+/// A registered command
+pub struct Command<C> {
+    pub name: &'static str,
+    /// Argument synopsis shown by `help`, e.g. `<3-10> on|off`
+    pub usage: &'static str,
+    /// One-line description shown by `help`
+    pub help: &'static str,
+    pub min_args: usize,
+    pub max_args: usize,
+    pub handler: Handler<C>,
+}
+
+/// Arguments of one command line
+pub struct Args<'a> {
+    words: [&'a str; MAX_ARGS],
+    len: usize,
+}
+
+impl<'a> Args<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&'a str> {
+        self.words[..self.len].get(index).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.words[..self.len].iter().copied()
+    }
+
+    /// Parse a decimal or `0x` hexadecimal argument
+    pub fn number(&self, index: usize) -> Result<u32, CommandError> {
+        let word = self.get(index).ok_or(CommandError::Usage)?;
+        let parsed = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => word.parse(),
+        };
+        parsed.map_err(|_| CommandError::Invalid(index))
+    }
+
+    /// Index of the argument in `options`
+    pub fn choice(&self, index: usize, options: &[&str]) -> Result<usize, CommandError> {
+        let word = self.get(index).ok_or(CommandError::Usage)?;
+        options
+            .iter()
+            .position(|option| *option == word)
+            .ok_or(CommandError::Invalid(index))
+    }
+}
+
+/// Escape sequence state of the line editor
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Input {
+    Normal,
+    /// After CR, so a following LF is not a second, empty line
+    AfterCr,
+    /// After ESC
+    Escape,
+    /// Inside `ESC [`, until the final byte
+    Csi,
+}
+
+/// The line editor and command dispatcher
+pub struct Shell<'a, C> {
+    commands: &'a [Command<C>],
+    line: [u8; LINE_MAX],
+    len: usize,
+    previous: [u8; LINE_MAX],
+    previous_len: usize,
+    input: Input,
+    echo: bool,
+}
+
+impl<'a, C> Shell<'a, C> {
+    pub fn new(commands: &'a [Command<C>]) -> Self {
+        Self {
+            commands,
+            line: [0; LINE_MAX],
+            len: 0,
+            previous: [0; LINE_MAX],
+            previous_len: 0,
+            input: Input::Normal,
+            echo: true,
+        }
+    }
+
+    /// Echo typed characters back (on by default)
+    pub fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    /// Print the first prompt
+    pub fn start<W: Write>(&mut self, out: &mut W) {
+        uart_write_str(out, PROMPT);
+    }
+
+    /// Handle every byte received since the last call
+    pub fn poll<W: Write>(&mut self, context: &mut C, out: &mut W) {
+        while let Some(byte) = read_byte() {
+            self.input(byte, context, out);
+        }
+    }
+
+    /// Handle one input byte, running the command when a line ends
+    pub fn input<W: Write>(&mut self, byte: u8, context: &mut C, out: &mut W) {
+        let state = core::mem::replace(&mut self.input, Input::Normal);
+        match (state, byte) {
+            (Input::AfterCr, b'\n') => {}
+            (Input::Escape, b'[') => self.input = Input::Csi,
+            (Input::Escape, _) => {}
+            (Input::Csi, b'A') => self.recall(out),
+            // Parameter and intermediate bytes; anything else ends the sequence
+            (Input::Csi, 0x20..=0x3F) => self.input = Input::Csi,
+            (Input::Csi, _) => {}
+            (_, b'\r' | b'\n') => {
+                if byte == b'\r' {
+                    self.input = Input::AfterCr;
+                }
+                uart_write_str(out, "\n");
+                self.execute(context, out);
+                uart_write_str(out, PROMPT);
+            }
+            (_, 0x1B) => self.input = Input::Escape,
+            (_, 0x08 | 0x7F) if self.len > 0 => {
+                self.len -= 1;
+                self.echo_str(out, "\x08 \x08");
+            }
+            // Ctrl-C
+            (_, 0x03) => {
+                self.len = 0;
+                uart_write_str(out, "^C\n");
+                uart_write_str(out, PROMPT);
+            }
+            // Ctrl-U
+            (_, 0x15) => self.erase(out),
+            (_, 0x20..=0x7E) if self.len < LINE_MAX => {
+                self.line[self.len] = byte;
+                self.len += 1;
+                if self.echo {
+                    let _ = out.write_char(byte as char);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn echo_str<W: Write>(&self, out: &mut W, s: &str) {
+        if self.echo {
+            uart_write_str(out, s);
+        }
+    }
+
+    /// Clear the line being edited, on screen too
+    fn erase<W: Write>(&mut self, out: &mut W) {
+        while self.len > 0 {
+            self.len -= 1;
+            self.echo_str(out, "\x08 \x08");
+        }
+    }
+
+    /// Replace the line being edited with the previous one
+    fn recall<W: Write>(&mut self, out: &mut W) {
+        self.erase(out);
+        self.line = self.previous;
+        self.len = self.previous_len;
+        if let Ok(text) = core::str::from_utf8(&self.line[..self.len]) {
+            self.echo_str(out, text);
+        }
+    }
+
+    fn execute<W: Write>(&mut self, context: &mut C, out: &mut W) {
+        let len = core::mem::take(&mut self.len);
+        if len > 0 {
+            self.previous = self.line;
+            self.previous_len = len;
+        }
+        // Only printable ASCII is ever stored
+        let line = core::str::from_utf8(&self.line[..len]).unwrap_or("");
+        let mut words = line.split_ascii_whitespace();
+        let Some(name) = words.next() else {
+            return;
+        };
+
+        let mut args = Args {
+            words: [""; MAX_ARGS],
+            len: 0,
+        };
+        let mut too_many = false;
+        for word in words {
+            if args.len == MAX_ARGS {
+                too_many = true;
+                break;
+            }
+            args.words[args.len] = word;
+            args.len += 1;
+        }
+
+        let mut out = Crlf(out);
+        if name == "help" && !self.commands.iter().any(|c| c.name == "help") {
+            self.help(&args, &mut out);
+            return;
+        }
+        let builtins = builtins::<C>();
+        let Some(command) = self
+            .commands
+            .iter()
+            .chain(builtins.iter())
+            .find(|c| c.name == name)
+        else {
+            let _ = writeln!(out, "unknown command: {} (try `help`)", name);
+            return;
+        };
+
+        let result = if too_many || args.len < command.min_args || args.len > command.max_args {
+            Err(CommandError::Usage)
+        } else {
+            (command.handler)(context, &args, &mut out)
+        };
+        match result {
+            Ok(()) => {}
+            Err(CommandError::Usage) => {
+                let _ = writeln!(out, "usage: {} {}", command.name, command.usage);
+            }
+            Err(CommandError::Invalid(index)) => {
+                let _ = writeln!(out, "invalid argument: {}", args.get(index).unwrap_or(""));
+                let _ = writeln!(out, "usage: {} {}", command.name, command.usage);
+            }
+            Err(CommandError::Failed(msg)) => {
+                let _ = writeln!(out, "error: {}", msg);
+            }
+        }
+    }
+
+    /// `help [command]`
+    fn help(&self, args: &Args, out: &mut dyn Write) {
+        let builtins = builtins::<C>();
+        let mut all = self.commands.iter().chain(
+            builtins
+                .iter()
+                .filter(|b| !self.commands.iter().any(|c| c.name == b.name)),
+        );
+        match args.get(0) {
+            Some(name) => match all.find(|c| c.name == name) {
+                Some(command) => {
+                    let _ = writeln!(out, "{} {}", command.name, command.usage);
+                    let _ = writeln!(out, "  {}", command.help);
+                }
+                None => {
+                    let _ = writeln!(out, "unknown command: {}", name);
+                }
+            },
+            None => {
+                for command in all {
+                    let _ = writeln!(out, "  {:<8} {}", command.name, command.help);
+                }
+                let _ = writeln!(out, "  help     List commands, or show one's usage");
+            }
+        }
+    }
+}
+
+/// Built-in commands, usable with any context
+fn builtins<C>() -> [Command<C>; 3] {
+    [
+        Command {
+            name: "peek",
+            usage: "<address> [count]",
+            help: "Read 32-bit words (no checks: unmapped addresses fault)",
+            min_args: 1,
+            max_args: 2,
+            handler: peek::<C>,
+        },
+        Command {
+            name: "poke",
+            usage: "<address> <value>",
+            help: "Write a 32-bit word",
+            min_args: 2,
+            max_args: 2,
+            handler: poke::<C>,
+        },
+        Command {
+            name: "reset",
+            usage: "",
+            help: "Reset the MCU",
+            min_args: 0,
+            max_args: 0,
+            handler: reset::<C>,
+        },
+    ]
+}
+
+/// `peek <address> [count]`
+fn peek<C>(_: &mut C, args: &Args, out: &mut dyn Write) -> Result<(), CommandError> {
+    let address = word_address(args, 0)?;
+    let count = if args.len() > 1 { args.number(1)? } else { 1 };
+    if count == 0 || count > PEEK_MAX {
+        return Err(CommandError::Invalid(1));
+    }
+    for index in 0..count {
+        let at = address.wrapping_add(index * 4);
+        // SAFETY: the user asked for this address; see the help text
+        let value = unsafe { core::ptr::read_volatile(at as *const u32) };
+        let _ = writeln!(out, "0x{:08X}: 0x{:08X}", at, value);
+    }
+    Ok(())
+}
+
+/// `poke <address> <value>`
+fn poke<C>(_: &mut C, args: &Args, out: &mut dyn Write) -> Result<(), CommandError> {
+    let address = word_address(args, 0)?;
+    let value = args.number(1)?;
+    // SAFETY: the user asked for this address
+    unsafe { core::ptr::write_volatile(address as *mut u32, value) };
+    let _ = writeln!(out, "0x{:08X} <- 0x{:08X}", address, value);
+    Ok(())
+}
+
+/// `reset`
+///
+/// Waits for the transmitter to finish so "resetting" reaches the host
+/// before the USART is reset with the rest of the chip.
+fn reset<C>(_: &mut C, _: &Args, out: &mut dyn Write) -> Result<(), CommandError> {
+    let _ = writeln!(out, "resetting");
+    let usart1 = unsafe { &*pac::USART1::ptr() };
+    while usart1.isr.read().tc().bit_is_clear() {}
+    SCB::sys_reset()
+}
+
+/// A word-aligned address argument
+fn word_address(args: &Args, index: usize) -> Result<u32, CommandError> {
+    let address = args.number(index)?;
+    if address % 4 != 0 {
+        return Err(CommandError::Invalid(index));
+    }
+    Ok(address)
+}
+
+/// Adapter that converts `\n` to `\r\n` on the way to the UART
+struct Crlf<'a, W: Write>(&'a mut W);
+
+impl<W: Write> Write for Crlf<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        uart_write_str(self.0, s);
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+//! Software timer service driven by the TIM7 update interrupt
+//!
+//! TIM7 is a basic timer that none of the peripheral tests use, so it is
+//! dedicated to generating a 1 kHz tick. Each tick advances a hashed timer
+//! wheel (the host-tested `timer-wheel` crate) which fires any one-shot or
+//! periodic timers due at that tick.
+//!
+//! The firmware owns the interrupt vector and forwards it here:
+//!
+//! ```ignore
+//! #[interrupt]
+//! fn TIM7() {
+//!     stm32f3_common::soft_timer::on_tick();
+//! }
+//! ```
+//!
+//! Callbacks run in interrupt context, so they should be short (toggle a
+//! flag, feed the watchdog). Code running in the main loop can instead poll
+//! [`take_expired`] or use a [`Deadline`] for timeouts.
+
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicU32, Ordering};
+use cortex_m::interrupt::{self, Mutex};
+use stm32f3xx_hal::pac::{self, NVIC};
+use timer_wheel::TimerWheel;
+
+pub use timer_wheel::{Callback, Error, TimerId, MAX_TIMERS};
+
+/// Tick rate of the timer service (1 tick = 1 ms)
+pub const TICK_HZ: u32 = 1_000;
+
+/// Counter prescaler output frequency (10 kHz, 0.1ms per count)
+const COUNTER_HZ: u32 = 10_000;
+
+/// Milliseconds elapsed since [`init`], incremented by [`on_tick`]
+static TICKS: AtomicU32 = AtomicU32::new(0);
+
+static WHEEL: Mutex<RefCell<TimerWheel>> = Mutex::new(RefCell::new(TimerWheel::new()));
+
+/// Start TIM7 as the 1 kHz tick source and unmask its interrupt
+///
+/// # Arguments
+/// * `tim7` - The TIM7 peripheral, dedicated to the timer service
+/// * `timer_clock_hz` - Input clock of TIM7 (72 MHz in the Renode platform)
+pub fn init(tim7: pac::TIM7, timer_clock_hz: u32) {
+    // Enable TIM7 clock directly, matching the other timer tests
+    unsafe {
+        let rcc_ptr = &*pac::RCC::ptr();
+        rcc_ptr.apb1enr.modify(|_, w| w.tim7en().enabled());
+    }
+
+    // Prescaler: timer clock / 10kHz, ARR: 10 counts = 1ms
+    tim7.psc
+        .write(|w| w.psc().bits((timer_clock_hz / COUNTER_HZ - 1) as u16));
+    tim7.arr
+        .write(|w| w.arr().bits((COUNTER_HZ / TICK_HZ - 1) as u16));
+    tim7.cnt.write(|w| unsafe { w.bits(0) });
+
+    // Generate update event to load prescaler, then clear the flag
+    tim7.egr.write(|w| w.ug().update());
+    tim7.sr.write(|w| w.uif().clear());
+
+    tim7.dier.write(|w| w.uie().enabled());
+    tim7.cr1.write(|w| w.cen().enabled());
+
+    unsafe {
+        NVIC::unmask(pac::Interrupt::TIM7);
+    }
+}
+
+/// Advance the timer wheel by one tick
+///
+/// Must be called from the `TIM7` interrupt handler. Clears the update flag
+/// and runs the callbacks of every timer that expired on this tick.
+pub fn on_tick() {
+    let tim7 = unsafe { &*pac::TIM7::ptr() };
+    tim7.sr.write(|w| w.uif().clear());
+
+    TICKS.fetch_add(1, Ordering::SeqCst);
+
+    let mut fired = [None; MAX_TIMERS];
+    let count = interrupt::free(|cs| WHEEL.borrow(cs).borrow_mut().tick(&mut fired));
+
+    // Run callbacks outside the critical section so they may reschedule
+    for callback in fired.iter().take(count).flatten() {
+        callback();
+    }
+}
+
+/// Milliseconds elapsed since the timer service was started
+pub fn now_ms() -> u32 {
+    TICKS.load(Ordering::SeqCst)
+}
+
+/// Schedule a one-shot timer that expires after `ms` milliseconds
+///
+/// The slot stays allocated after expiry so it can be restarted or polled;
+/// release it with [`cancel`].
+pub fn start_once(ms: u32, callback: Option<Callback>) -> Result<TimerId, Error> {
+    interrupt::free(|cs| WHEEL.borrow(cs).borrow_mut().start(ms, false, callback))
+}
+
+/// Schedule a periodic timer that expires every `ms` milliseconds
+pub fn start_periodic(ms: u32, callback: Option<Callback>) -> Result<TimerId, Error> {
+    interrupt::free(|cs| WHEEL.borrow(cs).borrow_mut().start(ms, true, callback))
+}
+
+/// Stop a timer and release its slot
+pub fn cancel(id: TimerId) -> Result<(), Error> {
+    interrupt::free(|cs| WHEEL.borrow(cs).borrow_mut().cancel(id))
+}
+
+/// Re-arm a timer one full interval from now
+///
+/// Works for running timers as well as one-shot timers that already expired.
+pub fn restart(id: TimerId) -> Result<(), Error> {
+    interrupt::free(|cs| WHEEL.borrow(cs).borrow_mut().restart(id))
+}
+
+/// Check whether a timer is waiting to expire
+pub fn is_active(id: TimerId) -> Result<bool, Error> {
+    interrupt::free(|cs| WHEEL.borrow(cs).borrow().is_active(id))
+}
+
+/// Return whether a timer expired since the last call, clearing the flag
+///
+/// Lets the main loop react to timers (e.g. stepping an LED pattern)
+/// without doing the work in interrupt context.
+pub fn take_expired(id: TimerId) -> Result<bool, Error> {
+    interrupt::free(|cs| WHEEL.borrow(cs).borrow_mut().take_expired(id))
+}
+
+/// A point in time measured against the timer service tick
+///
+/// Replaces cycle-counted timeouts with wall-clock ones:
+///
+/// ```ignore
+/// let deadline = Deadline::after_ms(100);
+/// while !done() && !deadline.expired() {}
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Deadline {
+    at: u32,
+}
+
+impl Deadline {
+    /// A deadline `ms` milliseconds from now
+    pub fn after_ms(ms: u32) -> Self {
+        Self {
+            at: now_ms().wrapping_add(ms),
+        }
+    }
+
+    /// Check whether the deadline has passed (wrap-around safe)
+    pub fn expired(&self) -> bool {
+        now_ms().wrapping_sub(self.at) as i32 >= 0
+    }
+}
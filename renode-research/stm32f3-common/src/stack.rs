@@ -0,0 +1,80 @@
+//! Stack painting and high-water measurement
+//!
+//! [`paint`] fills the unused part of the stack with [`STACK_PAINT`] as the
+//! first thing `main` does; [`high_water`] later finds the deepest word that
+//! was overwritten. Everything the firmware ever pushed, including interrupt
+//! handlers running on the main stack, lies between that word and
+//! `_stack_start`.
+//!
+//! The stack runs from `_stack_start` (top of RAM, see `memory.x`) down to
+//! `__sheap`, the end of `.bss`/`.uninit` placed by `cortex-m-rt`. The host
+//! `stack-usage` tool compares the figure the firmware prints with its
+//! static worst case.
+
+use core::fmt::Write;
+
+use crate::uart::{uart_write_dec, uart_write_str};
+
+/// Fill pattern for the unused stack
+pub const STACK_PAINT: u32 = 0xCCCC_CCCC;
+
+/// Bytes below the current stack pointer left unpainted, so `paint`'s own
+/// frame is never overwritten
+const PAINT_GUARD: usize = 64;
+
+extern "C" {
+    static __sheap: u32;
+    static _stack_start: u32;
+}
+
+/// Lowest address the stack may grow down to
+fn bottom() -> usize {
+    core::ptr::addr_of!(__sheap) as usize
+}
+
+/// Address just above the first stacked word
+fn top() -> usize {
+    core::ptr::addr_of!(_stack_start) as usize
+}
+
+/// Total stack space in bytes
+pub fn size() -> usize {
+    top() - bottom()
+}
+
+/// Paint the stack below the current stack pointer
+///
+/// Call once as the first statement of `main`, before interrupts are
+/// enabled, so the high-water mark covers the whole run.
+pub fn paint() {
+    let sp = cortex_m::register::msp::read() as usize;
+    let mut addr = bottom();
+    while addr + 4 <= sp - PAINT_GUARD {
+        // SAFETY: the range between `__sheap` and the guard below the
+        // current stack pointer is unused RAM owned by the stack
+        unsafe { core::ptr::write_volatile(addr as *mut u32, STACK_PAINT) };
+        addr += 4;
+    }
+}
+
+/// Deepest stack use since [`paint`], in bytes
+///
+/// Returns [`size`] if the paint was overwritten all the way down (or
+/// `paint` was never called).
+pub fn high_water() -> usize {
+    let mut addr = bottom();
+    // SAFETY: reads stay within the stack region
+    while addr < top() && unsafe { core::ptr::read_volatile(addr as *const u32) } == STACK_PAINT {
+        addr += 4;
+    }
+    top() - addr
+}
+
+/// Print `Stack: used <n> of <size> bytes`
+pub fn uart_write_stack_usage<W: Write>(uart: &mut W) {
+    uart_write_str(uart, "Stack: used ");
+    uart_write_dec(uart, high_water() as u32);
+    uart_write_str(uart, " of ");
+    uart_write_dec(uart, size() as u32);
+    uart_write_str(uart, " bytes\n");
+}
@@ -46,3 +46,60 @@ pub fn uart_write_hex32<W: core::fmt::Write>(uart: &mut W, value: u32) {
     uart_write_hex(uart, ((value >> 8) & 0xFF) as u8);
     uart_write_hex(uart, (value & 0xFF) as u8);
 }
+
+/// Write an unsigned value to UART in decimal, without `core::fmt`
+///
+/// # Arguments
+/// * `uart` - Any type implementing `core::fmt::Write`
+/// * `value` - The value to write
+pub fn uart_write_dec<W: core::fmt::Write>(uart: &mut W, value: u32) {
+    let mut digits = [0u8; 10];
+    let mut len = 0;
+    let mut rest = value;
+    loop {
+        digits[len] = b'0' + (rest % 10) as u8;
+        len += 1;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    for &digit in digits[..len].iter().rev() {
+        let _ = uart.write_char(digit as char);
+    }
+}
+
+/// Write a 64-bit unsigned value to UART in decimal
+///
+/// Divides on 16-bit limbs, so neither `core::fmt` nor the u64 division
+/// routines are linked in.
+///
+/// # Arguments
+/// * `uart` - Any type implementing `core::fmt::Write`
+/// * `value` - The value to write
+pub fn uart_write_dec64<W: core::fmt::Write>(uart: &mut W, value: u64) {
+    let mut limbs = [
+        (value >> 48) as u32 & 0xFFFF,
+        (value >> 32) as u32 & 0xFFFF,
+        (value >> 16) as u32 & 0xFFFF,
+        value as u32 & 0xFFFF,
+    ];
+    let mut digits = [0u8; 20];
+    let mut len = 0;
+    loop {
+        let mut rem = 0;
+        for limb in limbs.iter_mut() {
+            let cur = (rem << 16) | *limb;
+            *limb = cur / 10;
+            rem = cur % 10;
+        }
+        digits[len] = b'0' + rem as u8;
+        len += 1;
+        if limbs.iter().all(|&limb| limb == 0) {
+            break;
+        }
+    }
+    for &digit in digits[..len].iter().rev() {
+        let _ = uart.write_char(digit as char);
+    }
+}
@@ -0,0 +1,182 @@
+//! STM32F3 EXTI (External Interrupt) Test
+//!
+//! This tests the EXTI functionality:
+//! - Rising edge interrupt detection on PA0
+//! - Falling edge interrupt detection
+//! - Multiple interrupt count verification
+//! - Reports results via USART1
+
+#![no_std]
+
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU32, Ordering};
+use stm32f3_common::stack;
+use stm32f3_common::{constants, delay, uart_write_hex, uart_write_str};
+use stm32f3xx_hal::{
+    gpio::{Output, PushPull, PE9},
+    pac,
+    prelude::*,
+};
+
+// Global interrupt counter
+static INTERRUPT_COUNT: AtomicU32 = AtomicU32::new(0);
+static RISING_EDGE_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// EXTI0 interrupt handler body (PA0); the firmware forwards the vector
+pub fn on_exti0() {
+    // Increment total interrupt count
+    INTERRUPT_COUNT.fetch_add(1, Ordering::SeqCst);
+
+    // Clear the pending flag
+    let exti = unsafe { &*pac::EXTI::ptr() };
+
+    // Check which edge triggered (we configured both)
+    // The pending register tells us an interrupt occurred
+    if exti.pr1.read().pr0().bit_is_set() {
+        // Clear the pending bit by writing 1
+        exti.pr1.write(|w| w.pr0().set_bit());
+
+        // We'll track this as a rising edge for simplicity
+        // (In real hardware, you'd need additional logic to detect edge type)
+        RISING_EDGE_COUNT.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+/// Run the EXTI tests, reporting on `serial`; `true` if all passed
+///
+/// PA0 should be an input with its pull-down, and the `EXTI0` interrupt
+/// unmasked and forwarded to [`on_exti0`]. The tests wait for the user
+/// button to be pressed and released three times. LD3 (`led`, PE9) is left
+/// on when they pass.
+pub fn run<W: Write>(serial: &mut W, led: &mut PE9<Output<PushPull>>) -> bool {
+    // Enable SYSCFG clock for EXTI configuration
+    // On STM32F3, SYSCFG is on APB2
+    unsafe {
+        let rcc_ptr = &*pac::RCC::ptr();
+        rcc_ptr.apb2enr.modify(|_, w| w.syscfgen().enabled());
+    }
+
+    // Configure EXTI0 for PA0
+    // By default, EXTI0 is already mapped to PA0 (SYSCFG_EXTICR1 = 0)
+    // We just need to configure the edge detection and enable the interrupt
+
+    let exti = unsafe { &*pac::EXTI::ptr() };
+
+    // Configure rising edge trigger for line 0
+    exti.rtsr1.modify(|_, w| w.tr0().enabled());
+
+    // Configure falling edge trigger for line 0 (to detect both edges)
+    exti.ftsr1.modify(|_, w| w.tr0().enabled());
+
+    // Unmask interrupt for line 0
+    exti.imr1.modify(|_, w| w.mr0().set_bit());
+
+    uart_write_str(serial, "EXTI0 configured for PA0 (rising + falling edge)\n");
+    led.set_high().ok();
+
+    // Test counters
+    let mut tests_passed = 0u8;
+    let mut tests_failed = 0u8;
+
+    // ========================================
+    // Test 1: Rising Edge Interrupt
+    // ========================================
+    uart_write_str(serial, "\n--- Test 1: Rising Edge Interrupt ---\n");
+    uart_write_str(serial, "Waiting for button press (rising edge)...\n");
+
+    let initial_count = INTERRUPT_COUNT.load(Ordering::SeqCst);
+
+    // Wait for interrupt with timeout
+    let mut timeout = constants::INPUT_TIMEOUT;
+    while timeout > 0 && INTERRUPT_COUNT.load(Ordering::SeqCst) == initial_count {
+        timeout -= 1;
+        delay(10);
+    }
+
+    if INTERRUPT_COUNT.load(Ordering::SeqCst) > initial_count {
+        uart_write_str(serial, "Rising edge interrupt detected: PASS\n");
+        tests_passed += 1;
+        led.toggle().ok();
+    } else {
+        uart_write_str(serial, "Rising edge interrupt timeout: FAIL\n");
+        tests_failed += 1;
+    }
+
+    // ========================================
+    // Test 2: Falling Edge Interrupt
+    // ========================================
+    uart_write_str(serial, "\n--- Test 2: Falling Edge Interrupt ---\n");
+    uart_write_str(serial, "Waiting for button release (falling edge)...\n");
+
+    let count_before_release = INTERRUPT_COUNT.load(Ordering::SeqCst);
+
+    // Wait for another interrupt (falling edge)
+    timeout = constants::INPUT_TIMEOUT;
+    while timeout > 0 && INTERRUPT_COUNT.load(Ordering::SeqCst) == count_before_release {
+        timeout -= 1;
+        delay(10);
+    }
+
+    if INTERRUPT_COUNT.load(Ordering::SeqCst) > count_before_release {
+        uart_write_str(serial, "Falling edge interrupt detected: PASS\n");
+        tests_passed += 1;
+        led.toggle().ok();
+    } else {
+        uart_write_str(serial, "Falling edge interrupt timeout: FAIL\n");
+        tests_failed += 1;
+    }
+
+    // ========================================
+    // Test 3: Multiple Interrupt Count
+    // ========================================
+    uart_write_str(serial, "\n--- Test 3: Multiple Interrupt Count ---\n");
+    uart_write_str(serial, "Press button 2 more times...\n");
+
+    let count_before_multi = INTERRUPT_COUNT.load(Ordering::SeqCst);
+    let target_count = count_before_multi + 4; // 2 presses = 4 edges (2 rising + 2 falling)
+
+    // Wait for 4 more interrupts
+    timeout = 1000000;
+    while timeout > 0 && INTERRUPT_COUNT.load(Ordering::SeqCst) < target_count {
+        timeout -= 1;
+        delay(10);
+    }
+
+    let final_count = INTERRUPT_COUNT.load(Ordering::SeqCst);
+    uart_write_str(serial, "Total interrupts: ");
+    uart_write_hex(serial, final_count as u8);
+    uart_write_str(serial, "\n");
+
+    if final_count >= target_count {
+        uart_write_str(serial, "Multiple interrupt count: PASS\n");
+        tests_passed += 1;
+    } else {
+        uart_write_str(serial, "Multiple interrupt count: FAIL\n");
+        tests_failed += 1;
+    }
+
+    // ========================================
+    // Test Summary
+    // ========================================
+    uart_write_str(serial, "\n=== Test Summary ===\n");
+    uart_write_str(serial, "Tests passed: ");
+    uart_write_hex(serial, tests_passed);
+    uart_write_str(serial, "\n");
+    uart_write_str(serial, "Tests failed: ");
+    uart_write_hex(serial, tests_failed);
+    uart_write_str(serial, "\n");
+    uart_write_str(serial, "Total interrupts: ");
+    uart_write_hex(serial, INTERRUPT_COUNT.load(Ordering::SeqCst) as u8);
+    uart_write_str(serial, "\n");
+
+    stack::uart_write_stack_usage(serial);
+    if tests_failed == 0 {
+        uart_write_str(serial, "EXTI TEST PASSED\n");
+        led.set_high().ok();
+    } else {
+        uart_write_str(serial, "EXTI TEST FAILED\n");
+        led.set_low().ok();
+    }
+
+    tests_failed == 0
+}
@@ -0,0 +1,173 @@
+//! STM32F3 GPIO (General Purpose Input/Output) Test
+//!
+//! This tests the GPIO functionality:
+//! - Output toggle test (PE8, PE9 LEDs)
+//! - Input read test (PA0 button)
+//! - Pull-up/pull-down configuration
+//! - Reports results via USART1
+
+#![no_std]
+
+use core::fmt::Write;
+use stm32f3_common::stack;
+use stm32f3_common::{constants, debug, delay, uart_write_hex, uart_write_str};
+use stm32f3xx_hal::{
+    gpio::{gpioa, Input, Output, PushPull, Resistor, PA0, PA1, PE8, PE9},
+    prelude::*,
+};
+
+/// Run the GPIO tests, reporting on `serial`; `true` if all passed
+///
+/// `pe8` and `led` (LD3, PE9) are toggled as outputs, `button` (PA0, with
+/// its pull-down) must see a press and release, and `pa1` has its pull
+/// resistor changed through `pupdr`. LD3 is left on when the tests pass.
+pub fn run<W: Write>(
+    serial: &mut W,
+    led: &mut PE9<Output<PushPull>>,
+    pe8: &mut PE8<Output<PushPull>>,
+    button: &PA0<Input>,
+    pa1: &mut PA1<Input>,
+    pupdr: &mut gpioa::PUPDR,
+) -> bool {
+    // Test counters
+    let mut tests_passed = 0u8;
+    let mut tests_failed = 0u8;
+
+    // ========================================
+    // Test 1: Output Toggle Test (LEDs on PE8, PE9)
+    // ========================================
+    uart_write_str(serial, "\n--- Test 1: Output Toggle ---\n");
+
+    // Test LED toggle sequence
+    debug!(serial, "Setting PE8 HIGH");
+    pe8.set_high().ok();
+    delay(constants::LONG_DELAY);
+
+    debug!(serial, "Setting PE9 HIGH");
+    led.set_high().ok();
+    delay(constants::LONG_DELAY);
+
+    debug!(serial, "Setting PE8 LOW");
+    pe8.set_low().ok();
+    delay(constants::LONG_DELAY);
+
+    debug!(serial, "Setting PE9 LOW");
+    led.set_low().ok();
+    delay(constants::LONG_DELAY);
+
+    // Toggle test
+    debug!(serial, "Toggling PE8");
+    pe8.toggle().ok();
+    delay(constants::LONG_DELAY);
+    pe8.toggle().ok();
+
+    uart_write_str(serial, "Output toggle test: PASS\n");
+    tests_passed += 1;
+
+    // ========================================
+    // Test 2: Input Read Test (Button on PA0)
+    // ========================================
+    uart_write_str(serial, "\n--- Test 2: Input Read ---\n");
+
+    // Read initial state (should be low with pull-down when not pressed)
+    let initial_state = button.is_high().unwrap_or(false);
+    debug!(
+        serial,
+        "Initial PA0 state: {}",
+        if initial_state { "HIGH" } else { "LOW" }
+    );
+
+    // In Renode, the button press will be simulated externally
+    // For this test, we verify we can read the input
+    uart_write_str(serial, "Waiting for button press on PA0...\n");
+
+    // Wait for button press (high state) with timeout
+    let mut button_pressed = false;
+    let mut timeout = constants::INPUT_TIMEOUT;
+    while timeout > 0 {
+        if button.is_high().unwrap_or(false) {
+            button_pressed = true;
+            break;
+        }
+        timeout -= 1;
+        delay(10);
+    }
+
+    if button_pressed {
+        uart_write_str(serial, "Button press detected: PASS\n");
+        tests_passed += 1;
+
+        // Wait for button release
+        uart_write_str(serial, "Waiting for button release...\n");
+        timeout = constants::INPUT_TIMEOUT;
+        while timeout > 0 && button.is_high().unwrap_or(false) {
+            timeout -= 1;
+            delay(10);
+        }
+        if !button.is_high().unwrap_or(true) {
+            uart_write_str(serial, "Button release detected: PASS\n");
+            tests_passed += 1;
+        } else {
+            uart_write_str(serial, "Button release timeout: FAIL\n");
+            tests_failed += 1;
+        }
+    } else {
+        uart_write_str(serial, "Button press timeout: FAIL\n");
+        tests_failed += 1;
+        tests_failed += 1; // Also count release test as failed
+    }
+
+    // ========================================
+    // Test 3: Pull-up/Pull-down Configuration
+    // ========================================
+    uart_write_str(serial, "\n--- Test 3: Pull Configuration ---\n");
+
+    // NOTE: Renode's GPIO model doesn't simulate internal pull resistors
+    // on floating pins, so this test verifies the register configuration
+    // is accepted without errors, rather than the actual electrical behavior.
+
+    // Configure PA1 with pull-up
+    pa1.set_internal_resistor(pupdr, Resistor::PullUp);
+    delay(constants::MEDIUM_DELAY);
+    let pullup_state = pa1.is_high().unwrap_or(false);
+    debug!(
+        serial,
+        "PA1 with pull-up: {}",
+        if pullup_state {
+            "HIGH"
+        } else {
+            "LOW (Renode limitation)"
+        }
+    );
+
+    // Reconfigure PA1 with pull-down
+    pa1.set_internal_resistor(pupdr, Resistor::PullDown);
+    delay(constants::MEDIUM_DELAY);
+
+    // Pull configuration registers were set without errors
+    uart_write_str(serial, "Pull register configuration: OK\n");
+    uart_write_str(serial, "Pull configuration test: PASS\n");
+    tests_passed += 1;
+
+    // ========================================
+    // Test Summary
+    // ========================================
+    uart_write_str(serial, "\n=== Test Summary ===\n");
+    uart_write_str(serial, "Tests passed: ");
+    uart_write_hex(serial, tests_passed);
+    uart_write_str(serial, "\n");
+    uart_write_str(serial, "Tests failed: ");
+    uart_write_hex(serial, tests_failed);
+    uart_write_str(serial, "\n");
+
+    stack::uart_write_stack_usage(serial);
+    if tests_failed == 0 {
+        uart_write_str(serial, "GPIO TEST PASSED\n");
+        led.set_high().ok();
+    } else {
+        uart_write_str(serial, "GPIO TEST FAILED\n");
+        led.set_low().ok();
+    }
+
+    tests_failed == 0
+}
@@ -14,3 +14,15 @@ ENTRY(Reset);
 
 /* Stack size */
 _stack_start = ORIGIN(RAM) + LENGTH(RAM);
+
+/* Firmware image header (stm32f3_common::image) at a fixed offset after
+   the vector table; .text starts after the space reserved for it */
+SECTIONS
+{
+  .image_header ORIGIN(FLASH) + 0x200 :
+  {
+    KEEP(*(.image_header));
+  } > FLASH
+} INSERT AFTER .vector_table;
+
+_stext = ORIGIN(FLASH) + 0x400;
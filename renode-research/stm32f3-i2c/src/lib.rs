@@ -0,0 +1,157 @@
+//! STM32F3 I2C Sensor Test in Rust
+//!
+//! This tests I2C1 functionality on the STM32F303:
+//! - I2C1 configured to communicate with a BME280 sensor
+//! - Reads chip ID register to verify communication
+//! - Accesses the bus through the embedded-hal 1.0 `I2c` trait
+//! - Reports results via USART1
+
+#![no_std]
+
+use core::fmt::Write;
+use embedded_hal::i2c::I2c;
+use stm32f3_common::stack;
+use stm32f3_common::{debug, uart_write_hex, uart_write_str};
+use stm32f3xx_hal::{
+    gpio::{Output, PushPull, PE9},
+    prelude::*,
+};
+
+// BME280 I2C address (0x76 with SDO to GND, 0x77 with SDO to VDD)
+const BME280_ADDR: u8 = 0x76;
+
+// BME280 Register addresses
+const BME280_REG_ID: u8 = 0xD0;
+const BME280_REG_CTRL_HUM: u8 = 0xF2;
+const BME280_REG_CTRL_MEAS: u8 = 0xF4;
+const BME280_REG_TEMP_MSB: u8 = 0xFA;
+
+// Expected chip ID for BME280
+const BME280_CHIP_ID: u8 = 0x60;
+
+/// Run the sensor tests against the BME280 on `i2c`, reporting on
+/// `serial`; `true` if all passed
+///
+/// LD3 (`led`, PE9) is left on when they pass.
+pub fn run<W, I>(serial: &mut W, led: &mut PE9<Output<PushPull>>, i2c: &mut I) -> bool
+where
+    W: Write,
+    I: I2c,
+{
+    let mut pass_count = 0u8;
+    let mut fail_count = 0u8;
+
+    uart_write_str(serial, "Starting I2C test...\n");
+
+    // Test 1: Read chip ID register
+    uart_write_str(serial, "\nTest 1: Read Chip ID\n");
+    let mut id_buf = [0u8; 1];
+    match i2c.write_read(BME280_ADDR, &[BME280_REG_ID], &mut id_buf) {
+        Ok(_) => {
+            uart_write_str(serial, "Chip ID: 0x");
+            uart_write_hex(serial, id_buf[0]);
+            uart_write_str(serial, " Expected: 0x");
+            uart_write_hex(serial, BME280_CHIP_ID);
+            if id_buf[0] == BME280_CHIP_ID {
+                uart_write_str(serial, " PASS\n");
+                pass_count += 1;
+                led.set_high().ok();
+            } else {
+                uart_write_str(serial, " FAIL\n");
+                fail_count += 1;
+            }
+        }
+        Err(_) => {
+            uart_write_str(serial, "I2C read error FAIL\n");
+            fail_count += 1;
+        }
+    }
+
+    // Test 2: Write and read back humidity control register
+    uart_write_str(serial, "\nTest 2: Write/Read CTRL_HUM\n");
+    let ctrl_hum_val: u8 = 0x01; // oversampling x1
+    match i2c.write(BME280_ADDR, &[BME280_REG_CTRL_HUM, ctrl_hum_val]) {
+        Ok(_) => {
+            debug!(serial, "Write CTRL_HUM: 0x{:02X} OK", ctrl_hum_val);
+
+            // Read back
+            let mut read_buf = [0u8; 1];
+            match i2c.write_read(BME280_ADDR, &[BME280_REG_CTRL_HUM], &mut read_buf) {
+                Ok(_) => {
+                    uart_write_str(serial, "Read CTRL_HUM: 0x");
+                    uart_write_hex(serial, read_buf[0]);
+                    if read_buf[0] == ctrl_hum_val {
+                        uart_write_str(serial, " PASS\n");
+                        pass_count += 1;
+                    } else {
+                        uart_write_str(serial, " FAIL\n");
+                        fail_count += 1;
+                    }
+                }
+                Err(_) => {
+                    uart_write_str(serial, "I2C read error FAIL\n");
+                    fail_count += 1;
+                }
+            }
+        }
+        Err(_) => {
+            uart_write_str(serial, "I2C write error FAIL\n");
+            fail_count += 1;
+        }
+    }
+
+    // Test 3: Configure and trigger measurement
+    uart_write_str(serial, "\nTest 3: Trigger Measurement\n");
+    // Set temp oversampling x1, pressure oversampling x1, forced mode
+    let ctrl_meas_val: u8 = 0x25; // osrs_t=001, osrs_p=001, mode=01
+    match i2c.write(BME280_ADDR, &[BME280_REG_CTRL_MEAS, ctrl_meas_val]) {
+        Ok(_) => {
+            debug!(serial, "Write CTRL_MEAS: 0x{:02X} OK", ctrl_meas_val);
+
+            // Small delay for measurement (in real hardware)
+            for _ in 0..10000 {
+                cortex_m::asm::nop();
+            }
+
+            // Read temperature registers (3 bytes: MSB, LSB, XLSB)
+            let mut temp_buf = [0u8; 3];
+            match i2c.write_read(BME280_ADDR, &[BME280_REG_TEMP_MSB], &mut temp_buf) {
+                Ok(_) => {
+                    uart_write_str(serial, "Temp raw: 0x");
+                    uart_write_hex(serial, temp_buf[0]);
+                    uart_write_hex(serial, temp_buf[1]);
+                    uart_write_hex(serial, temp_buf[2]);
+                    uart_write_str(serial, " PASS\n");
+                    pass_count += 1;
+                }
+                Err(_) => {
+                    uart_write_str(serial, "I2C read error FAIL\n");
+                    fail_count += 1;
+                }
+            }
+        }
+        Err(_) => {
+            uart_write_str(serial, "I2C write error FAIL\n");
+            fail_count += 1;
+        }
+    }
+
+    // Summary
+    uart_write_str(serial, "\n=== Test Summary ===\n");
+    uart_write_str(serial, "Passed: ");
+    uart_write_hex(serial, pass_count);
+    uart_write_str(serial, "\nFailed: ");
+    uart_write_hex(serial, fail_count);
+    uart_write_str(serial, "\n");
+
+    stack::uart_write_stack_usage(serial);
+    if fail_count == 0 {
+        uart_write_str(serial, "I2C TEST PASSED\n");
+        led.set_high().ok();
+    } else {
+        uart_write_str(serial, "I2C TEST FAILED\n");
+        led.set_low().ok();
+    }
+
+    fail_count == 0
+}
@@ -0,0 +1,93 @@
+//! STM32F3 IWDG (Independent Watchdog) Test
+//!
+//! This tests the Independent Watchdog Timer functionality:
+//! - IWDG initialization with prescaler and reload value
+//! - Watchdog feeding (reload) to prevent reset
+//! - Reports results via USART1
+
+#![no_std]
+
+use core::fmt::Write;
+use stm32f3_common::stack;
+use stm32f3_common::{constants, delay, uart_write_hex, uart_write_str};
+use stm32f3xx_hal::{
+    gpio::{Output, PushPull, PE9},
+    pac,
+    prelude::*,
+};
+
+/// Run the IWDG tests, reporting on `serial`; `true` if all passed
+///
+/// The watchdog cannot be stopped once started, so from here on the
+/// caller must [`feed`] it at least every ~400 ms. LD3 (`led`, PE9) is
+/// left on when the tests pass.
+pub fn run<W: Write>(serial: &mut W, led: &mut PE9<Output<PushPull>>) -> bool {
+    // Get IWDG peripheral via PAC
+    let iwdg = unsafe { &*pac::IWDG::ptr() };
+
+    // Initialize IWDG
+    // LSI clock is ~40kHz
+    // Prescaler = 4 means divide by 4, so 40kHz/4 = 10kHz
+    // Reload = 0xFFF (4095) means timeout = 4095/10kHz = ~410ms
+
+    // Enable write access to PR and RLR (key = 0x5555)
+    iwdg.kr.write(|w| unsafe { w.key().bits(0x5555) });
+
+    // Set prescaler to 4 (PR = 0)
+    iwdg.pr.write(|w| w.pr().divide_by4());
+
+    // Set reload value to 0xFFF
+    iwdg.rlr.write(|w| w.rl().bits(0xFFF));
+
+    // Wait for registers to update (check status register)
+    let mut timeout = constants::MEDIUM_DELAY;
+    while (iwdg.sr.read().pvu().bit_is_set() || iwdg.sr.read().rvu().bit_is_set()) && timeout > 0 {
+        timeout -= 1;
+        delay(constants::STABILIZATION_DELAY);
+    }
+
+    // Start the watchdog (key = 0xCCCC)
+    iwdg.kr.write(|w| unsafe { w.key().bits(0xCCCC) });
+
+    uart_write_str(serial, "IWDG initialized (prescaler=4, reload=0xFFF)\n");
+    led.set_high().ok();
+
+    uart_write_str(serial, "Feeding watchdog...\n");
+
+    // Feed the watchdog multiple times with delays
+    for i in 1..=3 {
+        // Delay a bit (but less than timeout)
+        delay(constants::VERY_LONG_DELAY);
+
+        // Reload the watchdog counter (key = 0xAAAA)
+        iwdg.kr.write(|w| unsafe { w.key().bits(0xAAAA) });
+
+        uart_write_str(serial, "Feed ");
+        uart_write_hex(serial, i);
+        uart_write_str(serial, ": OK\n");
+
+        // Toggle LED
+        if i % 2 == 0 {
+            led.set_low().ok();
+        } else {
+            led.set_high().ok();
+        }
+    }
+
+    // Final status
+    uart_write_str(serial, "\n=== Test Summary ===\n");
+    uart_write_str(serial, "Watchdog feeds: 3\n");
+    uart_write_str(serial, "System resets: 0\n");
+    stack::uart_write_stack_usage(serial);
+    uart_write_str(serial, "IWDG TEST PASSED\n");
+
+    led.set_high().ok();
+
+    true
+}
+
+/// Reload the watchdog counter
+pub fn feed() {
+    let iwdg = unsafe { &*pac::IWDG::ptr() };
+    iwdg.kr.write(|w| unsafe { w.key().bits(0xAAAA) });
+}
@@ -0,0 +1,156 @@
+//! STM32F3 RTC (Real-Time Clock) Test
+//!
+//! This tests the Real-Time Clock functionality:
+//! - RTC initialization
+//! - Set time and date
+//! - Read time back and verify
+//! - Reports results via USART1
+
+#![no_std]
+
+use core::fmt::Write;
+use stm32f3_common::stack;
+use stm32f3_common::{constants, delay, uart_write_hex, uart_write_str};
+use stm32f3xx_hal::{
+    gpio::{Output, PushPull, PE9},
+    pac,
+    prelude::*,
+};
+
+/// Convert BCD to binary
+fn bcd_to_bin(bcd: u8) -> u8 {
+    ((bcd >> 4) * 10) + (bcd & 0x0F)
+}
+
+/// Convert binary to BCD
+fn bin_to_bcd(bin: u8) -> u8 {
+    ((bin / 10) << 4) | (bin % 10)
+}
+
+/// Run the RTC tests, reporting on `serial`; `true` if all passed
+///
+/// LD3 (`led`, PE9) is left on when they pass.
+pub fn run<W: Write>(serial: &mut W, led: &mut PE9<Output<PushPull>>) -> bool {
+    // Get peripheral pointers via PAC
+    let rtc = unsafe { &*pac::RTC::ptr() };
+    let pwr = unsafe { &*pac::PWR::ptr() };
+    let rcc_ptr = unsafe { &*pac::RCC::ptr() };
+
+    // Initialize RTC
+    // Enable PWR clock
+    rcc_ptr.apb1enr.modify(|_, w| w.pwren().enabled());
+
+    // Enable access to backup domain
+    pwr.cr.modify(|_, w| w.dbp().set_bit());
+
+    // Enable LSI and select as RTC clock source
+    // Enable RTC clock, select LSI (bits 9:8 = 10)
+    rcc_ptr
+        .bdcr
+        .modify(|_, w| w.rtcen().enabled().rtcsel().lsi());
+
+    delay(constants::MEDIUM_DELAY);
+
+    // Disable RTC write protection
+    rtc.wpr.write(|w| w.key().bits(0xCA));
+    rtc.wpr.write(|w| w.key().bits(0x53));
+
+    // Enter initialization mode
+    rtc.isr.modify(|_, w| w.init().init_mode());
+
+    // Wait for INITF flag
+    let mut timeout = constants::INIT_TIMEOUT;
+    while rtc.isr.read().initf().is_not_allowed() && timeout > 0 {
+        timeout -= 1;
+        delay(10);
+    }
+
+    uart_write_str(serial, "RTC initialized\n");
+    led.set_high().ok();
+
+    // Set time to 12:30:00
+    let hours: u8 = 12;
+    let minutes: u8 = 30;
+    let seconds: u8 = 0;
+
+    // Set time register (BCD format)
+    rtc.tr.write(|w| {
+        w.ht()
+            .bits(bin_to_bcd(hours) >> 4)
+            .hu()
+            .bits(bin_to_bcd(hours) & 0x0F)
+            .mnt()
+            .bits(bin_to_bcd(minutes) >> 4)
+            .mnu()
+            .bits(bin_to_bcd(minutes) & 0x0F)
+            .st()
+            .bits(bin_to_bcd(seconds) >> 4)
+            .su()
+            .bits(bin_to_bcd(seconds) & 0x0F)
+    });
+
+    // Exit initialization mode
+    rtc.isr.modify(|_, w| w.init().free_running_mode());
+
+    // Re-enable write protection
+    rtc.wpr.write(|w| w.key().bits(0xFF));
+
+    uart_write_str(serial, "Time set: ");
+    uart_write_hex(serial, hours);
+    uart_write_str(serial, ":");
+    uart_write_hex(serial, minutes);
+    uart_write_str(serial, ":");
+    uart_write_hex(serial, seconds);
+    uart_write_str(serial, "\n");
+
+    // Small delay to let time advance
+    delay(constants::VERY_LONG_DELAY);
+
+    // Read time back
+    let tr_read = rtc.tr.read();
+
+    let hours_read = bcd_to_bin((tr_read.ht().bits() << 4) | tr_read.hu().bits());
+    let minutes_read = bcd_to_bin((tr_read.mnt().bits() << 4) | tr_read.mnu().bits());
+    let seconds_read = bcd_to_bin((tr_read.st().bits() << 4) | tr_read.su().bits());
+
+    uart_write_str(serial, "Time read: ");
+    uart_write_hex(serial, hours_read);
+    uart_write_str(serial, ":");
+    uart_write_hex(serial, minutes_read);
+    uart_write_str(serial, ":");
+    uart_write_hex(serial, seconds_read);
+    uart_write_str(serial, "\n");
+
+    // Verify time (allow for some seconds to have passed)
+    let mut test_passed = true;
+
+    // Hours and minutes should match exactly
+    if hours_read != hours {
+        uart_write_str(serial, "Hours mismatch!\n");
+        test_passed = false;
+    }
+    if minutes_read != minutes {
+        uart_write_str(serial, "Minutes mismatch!\n");
+        test_passed = false;
+    }
+    // Seconds can be 0 or slightly more
+    if seconds_read > 10 {
+        uart_write_str(serial, "Seconds out of range!\n");
+        test_passed = false;
+    }
+
+    // Summary
+    uart_write_str(serial, "\n=== Test Summary ===\n");
+    stack::uart_write_stack_usage(serial);
+    if test_passed {
+        uart_write_str(serial, "Time verification: PASS\n");
+        uart_write_str(serial, "RTC TEST PASSED\n");
+        led.set_high().ok();
+    } else {
+        uart_write_str(serial, "Time verification: FAIL\n");
+        uart_write_str(serial, "RTC TEST FAILED\n");
+        led.set_low().ok();
+    }
+
+    test_passed
+}
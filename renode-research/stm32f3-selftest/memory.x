@@ -0,0 +1,25 @@
+/* STM32F303xC Memory Layout */
+MEMORY
+{
+  FLASH : ORIGIN = 0x08000000, LENGTH = 256K
+  CCM   : ORIGIN = 0x10000000, LENGTH = 8K
+  RAM   : ORIGIN = 0x20000000, LENGTH = 40K
+}
+
+/* Entry point */
+ENTRY(Reset);
+
+/* Stack pointer initial value */
+_stack_start = ORIGIN(RAM) + LENGTH(RAM);
+
+/* Firmware image header (stm32f3_common::image) at a fixed offset after
+   the vector table; .text starts after the space reserved for it */
+SECTIONS
+{
+  .image_header ORIGIN(FLASH) + 0x200 :
+  {
+    KEEP(*(.image_header));
+  } > FLASH
+} INSERT AFTER .vector_table;
+
+_stext = ORIGIN(FLASH) + 0x400;
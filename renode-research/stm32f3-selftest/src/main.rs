@@ -0,0 +1,687 @@
+//! STM32F3 All-in-One Self-Test
+//!
+//! Runs the test suites of the peripheral crates, linked in as libraries,
+//! in one boot:
+//! - Each suite is a cargo feature; all but `usart2`, which needs the
+//!   `uart-peer` modem, are built by default
+//! - After a one-second pause the built-in suites run, or the ones `boot`
+//!   stored in the RTC backup register BKP0R; a key press skips the run
+//! - A shell on USART1 lists the suites (`suites`), runs any of them
+//!   (`run`) and picks the ones later boots run (`boot`)
+//! - Reports each suite's result and duration via USART1, then a summary
+//! - With the `framed` feature (on by default), `frames on` makes every
+//!   run also send its results as `stm32f3_common::framed` records, which
+//!   `host/frame-decode` checks
+//!
+//! The suites share one board: PA5 is SPI1's clock, so the DAC's second
+//! output is only checked through its registers, and the watchdog, which
+//! cannot be stopped, is started last and fed by a software timer from
+//! then on.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use core::fmt::Write;
+use cortex_m_rt::{entry, exception, ExceptionFrame};
+use stm32f3_common::image::uart_write_build_info;
+use stm32f3_common::shell::{self, Args, Command, CommandError, Shell};
+use stm32f3_common::soft_timer::{self, Deadline};
+use stm32f3_common::stack;
+use stm32f3_common::{uart_write_dec, uart_write_str};
+use stm32f3xx_hal::{
+    gpio::{Output, PushPull, AF7, PA10, PA9, PE9},
+    pac::{self, interrupt},
+    prelude::*,
+    serial::{config::Config as UartConfig, Serial},
+};
+
+#[cfg(feature = "framed")]
+use stm32f3_common::{framed::FrameWriter, framed::Record, io::SerialPort};
+
+#[cfg(feature = "i2c")]
+use stm32f3_common::compat::I2cCompat;
+#[cfg(feature = "spi")]
+use stm32f3_common::compat::SpiCompat;
+#[cfg(feature = "gpio")]
+use stm32f3xx_hal::gpio::{gpioa, Input, PA0, PA1, PE8};
+#[cfg(feature = "usart2")]
+use stm32f3xx_hal::gpio::{PA2, PA3};
+#[cfg(feature = "i2c")]
+use stm32f3xx_hal::{
+    gpio::{OpenDrain, AF4, PB6, PB7},
+    i2c::I2c,
+};
+#[cfg(feature = "spi")]
+use stm32f3xx_hal::{
+    gpio::{AF5, PA5, PA6, PA7},
+    spi::{config::Config as SpiConfig, Spi},
+};
+
+stm32f3_common::image_header!();
+
+/// A test suite and whether this image has it
+struct Suite {
+    name: &'static str,
+    built: bool,
+}
+
+const fn suite(name: &'static str, built: bool) -> Suite {
+    Suite { name, built }
+}
+
+/// Every suite in run order; a suite's index is its bit in a selection
+///
+/// `iwdg` is last: once the watchdog runs it cannot be stopped.
+const SUITES: [Suite; 12] = [
+    suite("crc", cfg!(feature = "crc")),
+    suite("dac", cfg!(feature = "dac")),
+    suite("adc", cfg!(feature = "adc")),
+    suite("dma", cfg!(feature = "dma")),
+    suite("rtc", cfg!(feature = "rtc")),
+    suite("timer", cfg!(feature = "timer")),
+    suite("i2c", cfg!(feature = "i2c")),
+    suite("spi", cfg!(feature = "spi")),
+    suite("gpio", cfg!(feature = "gpio")),
+    suite("exti", cfg!(feature = "exti")),
+    suite("usart2", cfg!(feature = "usart2")),
+    suite("iwdg", cfg!(feature = "iwdg")),
+];
+
+/// The suites built into this image, as a selection
+const BUILT: u32 = {
+    let mut selection = 0;
+    let mut index = 0;
+    while index < SUITES.len() {
+        if SUITES[index].built {
+            selection |= 1 << index;
+        }
+        index += 1;
+    }
+    selection
+};
+
+/// Upper half of BKP0R when its lower half is a selection stored by `boot`
+const BOOT_MAGIC: u32 = 0x5E1F_0000;
+
+/// Pause before the suites run, in which a key press skips them
+const AUTORUN_DELAY_MS: u32 = 1_000;
+
+/// How often the watchdog is fed once the IWDG suite has started it
+#[cfg(feature = "iwdg")]
+const FEED_INTERVAL_MS: u32 = 100;
+
+/// Shell commands on top of the built-in `help`, `peek`, `poke` and `reset`
+const COMMANDS: &[Command<Selftest>] = &[
+    Command {
+        name: "suites",
+        usage: "",
+        help: "List the suites and their last results",
+        min_args: 0,
+        max_args: 0,
+        handler: suites,
+    },
+    Command {
+        name: "run",
+        usage: "<suite>...|all",
+        help: "Run suites now",
+        min_args: 1,
+        max_args: shell::MAX_ARGS,
+        handler: run,
+    },
+    Command {
+        name: "boot",
+        usage: "<suite>...|all|default",
+        help: "Pick the suites later boots run (kept in BKP0R)",
+        min_args: 1,
+        max_args: shell::MAX_ARGS,
+        handler: boot,
+    },
+    #[cfg(feature = "framed")]
+    Command {
+        name: "frames",
+        usage: "on|off",
+        help: "Also send run results as frames",
+        min_args: 1,
+        max_args: 1,
+        handler: frames,
+    },
+];
+
+/// Outcome of a suite's last run
+#[derive(Clone, Copy)]
+struct Outcome {
+    passed: bool,
+    ms: u32,
+}
+
+/// Shell context: the results so far and a run asked for by `run`
+struct Selftest {
+    results: [Option<Outcome>; SUITES.len()],
+    /// Selection the main loop runs next, with the plain UART
+    pending: Option<u32>,
+    /// Runs also send their results as frames
+    #[cfg(feature = "framed")]
+    frames: bool,
+}
+
+/// USART1 on PA9 (TX) and PA10 (RX), for the reports and the shell
+type Usart1 = Serial<pac::USART1, (PA9<AF7<PushPull>>, PA10<AF7<PushPull>>)>;
+
+/// USART1 as the suites and the shell write to it; text passes through
+/// the frame writer unchanged
+#[cfg(feature = "framed")]
+type Console = FrameWriter<SerialPort<Usart1>>;
+#[cfg(not(feature = "framed"))]
+type Console = Usart1;
+
+/// I2C1 on PB6 (SCL) and PB7 (SDA), for the BME280
+#[cfg(feature = "i2c")]
+type I2c1 = I2cCompat<I2c<pac::I2C1, (PB6<AF4<OpenDrain>>, PB7<AF4<OpenDrain>>)>>;
+
+/// SPI1 on PA5 (SCK), PA6 (MISO) and PA7 (MOSI), for the loopback
+#[cfg(feature = "spi")]
+type Spi1 = SpiCompat<Spi<pac::SPI1, (PA5<AF5<PushPull>>, PA6<AF5<PushPull>>, PA7<AF5<PushPull>>)>>;
+
+/// USART2 on PA2 (TX) and PA3 (RX), for the modem peer
+#[cfg(feature = "usart2")]
+type Usart2 = Serial<pac::USART2, (PA2<AF7<PushPull>>, PA3<AF7<PushPull>>)>;
+
+/// Everything the suites drive, set up once at boot
+struct Board {
+    /// LD3, which every suite leaves on when it passes
+    led: PE9<Output<PushPull>>,
+    #[cfg(feature = "gpio")]
+    pe8: PE8<Output<PushPull>>,
+    #[cfg(feature = "gpio")]
+    button: PA0<Input>,
+    #[cfg(feature = "gpio")]
+    pa1: PA1<Input>,
+    #[cfg(feature = "gpio")]
+    pupdr: gpioa::PUPDR,
+    #[cfg(feature = "i2c")]
+    i2c: I2c1,
+    #[cfg(feature = "spi")]
+    spi: Spi1,
+    #[cfg(feature = "usart2")]
+    modem: stm32f3_usart2::Modem<Usart2>,
+    /// The watchdog runs and a software timer feeds it
+    #[cfg(feature = "iwdg")]
+    feeding: bool,
+}
+
+impl Board {
+    /// Run suite `index` of [`SUITES`]; `true` if it passed
+    fn run<W: Write>(&mut self, index: usize, serial: &mut W) -> bool {
+        let led = &mut self.led;
+        match SUITES[index].name {
+            #[cfg(feature = "crc")]
+            "crc" => stm32f3_crc::run(serial, led),
+            #[cfg(feature = "dac")]
+            "dac" => stm32f3_dac::run(serial, led),
+            #[cfg(feature = "adc")]
+            "adc" => stm32f3_adc::run(serial, led),
+            #[cfg(feature = "dma")]
+            "dma" => stm32f3_dma::run(serial, led),
+            #[cfg(feature = "rtc")]
+            "rtc" => stm32f3_rtc::run(serial, led),
+            #[cfg(feature = "timer")]
+            "timer" => stm32f3_timer::run(serial, led),
+            #[cfg(feature = "i2c")]
+            "i2c" => stm32f3_i2c::run(serial, led, &mut self.i2c),
+            #[cfg(feature = "spi")]
+            "spi" => stm32f3_spi::run(serial, led, &mut self.spi),
+            #[cfg(feature = "gpio")]
+            "gpio" => stm32f3_gpio::run(
+                serial,
+                led,
+                &mut self.pe8,
+                &self.button,
+                &mut self.pa1,
+                &mut self.pupdr,
+            ),
+            #[cfg(feature = "exti")]
+            "exti" => stm32f3_exti::run(serial, led),
+            #[cfg(feature = "usart2")]
+            "usart2" => stm32f3_usart2::run(serial, led, &mut self.modem),
+            #[cfg(feature = "iwdg")]
+            "iwdg" => {
+                let passed = stm32f3_iwdg::run(serial, led);
+                if !self.feeding {
+                    soft_timer::start_periodic(FEED_INTERVAL_MS, Some(stm32f3_iwdg::feed)).unwrap();
+                    self.feeding = true;
+                }
+                passed
+            }
+            // Not built; selections only ever hold built suites
+            _ => false,
+        }
+    }
+}
+
+/// TIM7 interrupt handler - drives the software timers (timeouts, suite
+/// durations, the timer suite and the watchdog feed)
+#[interrupt]
+fn TIM7() {
+    soft_timer::on_tick();
+}
+
+/// EXTI0 interrupt handler (PA0), for the EXTI suite
+#[cfg(feature = "exti")]
+#[interrupt]
+fn EXTI0() {
+    stm32f3_exti::on_exti0();
+}
+
+/// USART1 interrupt handler - collects received bytes for the shell
+#[interrupt]
+fn USART1_EXTI25() {
+    shell::on_usart1_rx();
+}
+
+/// HardFault handler - reports the fault on USART1 instead of halting silently
+#[exception]
+unsafe fn HardFault(frame: &ExceptionFrame) -> ! {
+    stm32f3_common::fault::report(frame)
+}
+
+/// `suites`
+fn suites(selftest: &mut Selftest, _: &Args, out: &mut dyn Write) -> Result<(), CommandError> {
+    for (suite, result) in SUITES.iter().zip(selftest.results) {
+        let _ = match (suite.built, result) {
+            (false, _) => writeln!(out, "{}: not built", suite.name),
+            (true, None) => writeln!(out, "{}: not run", suite.name),
+            (true, Some(outcome)) => writeln!(
+                out,
+                "{}: {} ({} ms)",
+                suite.name,
+                verdict(outcome.passed),
+                outcome.ms
+            ),
+        };
+    }
+    Ok(())
+}
+
+/// `run <suite>...|all`
+fn run(selftest: &mut Selftest, args: &Args, _: &mut dyn Write) -> Result<(), CommandError> {
+    selftest.pending = Some(selection(args)?);
+    Ok(())
+}
+
+/// `boot <suite>...|all|default`
+fn boot(_: &mut Selftest, args: &Args, out: &mut dyn Write) -> Result<(), CommandError> {
+    if args.len() == 1 && args.get(0) == Some("default") {
+        write_bkp0r(0);
+        let _ = writeln!(out, "Next boot runs the built-in suites");
+    } else {
+        let selection = selection(args)?;
+        write_bkp0r(BOOT_MAGIC | selection);
+        let _ = write!(out, "Next boot runs:");
+        write_names(out, selection);
+        let _ = writeln!(out);
+    }
+    Ok(())
+}
+
+/// `frames on|off`
+#[cfg(feature = "framed")]
+fn frames(selftest: &mut Selftest, args: &Args, out: &mut dyn Write) -> Result<(), CommandError> {
+    selftest.frames = args.choice(0, &["off", "on"])? == 1;
+    let _ = writeln!(
+        out,
+        "Frames {}",
+        if selftest.frames { "on" } else { "off" }
+    );
+    Ok(())
+}
+
+/// Parse built suite names, or `all` for every built suite
+fn selection(args: &Args) -> Result<u32, CommandError> {
+    let mut selection = 0;
+    for (index, word) in args.iter().enumerate() {
+        selection |= match word {
+            "all" => BUILT,
+            name => {
+                let suite = SUITES
+                    .iter()
+                    .position(|suite| suite.built && suite.name == name)
+                    .ok_or(CommandError::Invalid(index))?;
+                1 << suite
+            }
+        };
+    }
+    Ok(selection)
+}
+
+/// The names in `selection`, each after a space
+fn write_names(out: &mut dyn Write, selection: u32) {
+    for (index, suite) in SUITES.iter().enumerate() {
+        if selection & 1 << index != 0 {
+            let _ = write!(out, " {}", suite.name);
+        }
+    }
+}
+
+fn verdict(passed: bool) -> &'static str {
+    if passed {
+        "PASS"
+    } else {
+        "FAIL"
+    }
+}
+
+/// Selection stored in BKP0R by `boot`, if any
+fn boot_selection() -> Option<u32> {
+    let rtc = unsafe { &*pac::RTC::ptr() };
+    let value = rtc.bkpr[0].read().bits();
+    (value & 0xFFFF_0000 == BOOT_MAGIC).then_some(value & BUILT)
+}
+
+/// Write BKP0R, which keeps its value across resets
+fn write_bkp0r(value: u32) {
+    let rcc = unsafe { &*pac::RCC::ptr() };
+    let pwr = unsafe { &*pac::PWR::ptr() };
+    let rtc = unsafe { &*pac::RTC::ptr() };
+
+    // The backup domain is write-protected until DBP is set
+    rcc.apb1enr.modify(|_, w| w.pwren().enabled());
+    pwr.cr.modify(|_, w| w.dbp().set_bit());
+    rtc.bkpr[0].write(|w| w.bkp().bits(value));
+}
+
+/// Run the suites in `selection` in order, then print a summary
+fn run_suites(board: &mut Board, selftest: &mut Selftest, serial: &mut Console, selection: u32) {
+    let selected = || {
+        SUITES
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| selection & 1 << index != 0)
+    };
+    #[cfg(feature = "framed")]
+    if selftest.frames {
+        let _ = serial.send(&Record::Start {
+            name: env!("CARGO_PKG_NAME"),
+        });
+    }
+
+    for (index, suite) in selected() {
+        uart_write_str(serial, "\n=== Suite ");
+        uart_write_str(serial, suite.name);
+        uart_write_str(serial, " ===\n");
+
+        let start = soft_timer::now_ms();
+        let passed = board.run(index, serial);
+        let ms = soft_timer::now_ms().wrapping_sub(start);
+        selftest.results[index] = Some(Outcome { passed, ms });
+
+        uart_write_str(serial, "Suite ");
+        uart_write_str(serial, suite.name);
+        uart_write_str(serial, ": ");
+        uart_write_str(serial, verdict(passed));
+        uart_write_str(serial, "\n");
+        #[cfg(feature = "framed")]
+        if selftest.frames {
+            let _ = serial.result(suite.name, passed, ms);
+        }
+    }
+
+    // Summary
+    let (mut passed, mut failed) = (0, 0);
+    uart_write_str(serial, "\n=== Self-Test Summary ===\n");
+    for (index, suite) in selected() {
+        let Some(outcome) = selftest.results[index] else {
+            continue;
+        };
+        if outcome.passed {
+            passed += 1;
+        } else {
+            failed += 1;
+        }
+        uart_write_str(serial, suite.name);
+        uart_write_str(serial, ": ");
+        uart_write_str(serial, verdict(outcome.passed));
+        uart_write_str(serial, " (");
+        uart_write_dec(serial, outcome.ms);
+        uart_write_str(serial, " ms)\n");
+    }
+    uart_write_str(serial, "Suites passed: ");
+    uart_write_dec(serial, passed);
+    uart_write_str(serial, "\nSuites failed: ");
+    uart_write_dec(serial, failed);
+    uart_write_str(serial, "\n");
+
+    stack::uart_write_stack_usage(serial);
+    if failed == 0 {
+        uart_write_str(serial, "SELFTEST PASSED\n");
+        board.led.set_high().ok();
+    } else {
+        uart_write_str(serial, "SELFTEST FAILED\n");
+        board.led.set_low().ok();
+    }
+    #[cfg(feature = "framed")]
+    if selftest.frames {
+        let _ = serial.summary(passed as u16, failed as u16);
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    stack::paint();
+
+    // Take ownership of the device peripherals
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Set up the system clocks using HSI (8 MHz internal oscillator)
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    // GPIO ports; splitting resets a port, so each is split only once
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb);
+    #[cfg(feature = "i2c")]
+    let mut gpiob = dp.GPIOB.split(&mut rcc.ahb);
+    let mut gpioe = dp.GPIOE.split(&mut rcc.ahb);
+
+    // Configure LED on PE9 as output (for status indication)
+    let led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // User button on PA0 with pull-down, for the GPIO and EXTI suites
+    #[cfg_attr(not(feature = "gpio"), allow(unused_variables))]
+    let button = gpioa
+        .pa0
+        .into_pull_down_input(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    // PE8 output and PA1 input for the GPIO suite
+    #[cfg(feature = "gpio")]
+    let pe8 = gpioe
+        .pe8
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+    #[cfg(feature = "gpio")]
+    let pa1 = gpioa.pa1.into_input(&mut gpioa.moder);
+
+    // DAC1_OUT1 on PA4 in analog mode; PA5 (DAC1_OUT2) is SPI1's clock
+    #[cfg(feature = "dac")]
+    let _pa4 = gpioa.pa4.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    // Configure USART1 pins for debug output and the shell
+    // PA9 = TX, PA10 = RX (Alternate Function 7)
+    let tx_pin =
+        gpioa
+            .pa9
+            .into_af_push_pull::<7>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let rx_pin =
+        gpioa
+            .pa10
+            .into_af_push_pull::<7>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+
+    // Set up USART1 at 115200 baud
+    let serial: Usart1 = Serial::new(
+        dp.USART1,
+        (tx_pin, rx_pin),
+        UartConfig::default().baudrate(115200.Bd()),
+        clocks,
+        &mut rcc.apb2,
+    );
+    #[cfg(feature = "framed")]
+    let mut serial: Console = FrameWriter::new(SerialPort::new(serial));
+    #[cfg(not(feature = "framed"))]
+    let mut serial: Console = serial;
+
+    uart_write_str(&mut serial, "STM32F3 Self-Test\n");
+    uart_write_build_info(&mut serial);
+
+    // 1 ms tick for the timeouts, durations and the timer suite (TIM7 runs
+    // at 72 MHz in Renode)
+    soft_timer::init(dp.TIM7, 72_000_000);
+
+    // Enable the EXTI suite's interrupt in NVIC
+    #[cfg(feature = "exti")]
+    {
+        let mut cp = cortex_m::Peripherals::take().unwrap();
+        unsafe {
+            cp.NVIC.set_priority(pac::Interrupt::EXTI0, 1);
+            pac::NVIC::unmask(pac::Interrupt::EXTI0);
+        }
+    }
+
+    // I2C1 at 100kHz for the BME280 (PB6 = SCL, PB7 = SDA, AF4)
+    #[cfg(feature = "i2c")]
+    let i2c = {
+        let scl =
+            gpiob
+                .pb6
+                .into_af_open_drain::<4>(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+        let sda =
+            gpiob
+                .pb7
+                .into_af_open_drain::<4>(&mut gpiob.moder, &mut gpiob.otyper, &mut gpiob.afrl);
+        I2cCompat::new(I2c::new(
+            dp.I2C1,
+            (scl, sda),
+            100_000.Hz(),
+            clocks,
+            &mut rcc.apb1,
+        ))
+    };
+
+    // SPI1 at 1MHz for the loopback (PA5 = SCK, PA6 = MISO, PA7 = MOSI, AF5)
+    #[cfg(feature = "spi")]
+    let spi = {
+        let sck =
+            gpioa
+                .pa5
+                .into_af_push_pull::<5>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        let miso =
+            gpioa
+                .pa6
+                .into_af_push_pull::<5>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        let mosi =
+            gpioa
+                .pa7
+                .into_af_push_pull::<5>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        SpiCompat::new(Spi::new(
+            dp.SPI1,
+            (sck, miso, mosi),
+            SpiConfig::default().frequency(1.MHz()),
+            clocks,
+            &mut rcc.apb2,
+        ))
+    };
+
+    // USART2 at 115200 baud for the modem peer (PA2 = TX, PA3 = RX, AF7)
+    #[cfg(feature = "usart2")]
+    let modem = {
+        let peer_tx =
+            gpioa
+                .pa2
+                .into_af_push_pull::<7>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        let peer_rx =
+            gpioa
+                .pa3
+                .into_af_push_pull::<7>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        stm32f3_usart2::Modem::new(Serial::new(
+            dp.USART2,
+            (peer_tx, peer_rx),
+            UartConfig::default().baudrate(115200.Bd()),
+            clocks,
+            &mut rcc.apb1,
+        ))
+    };
+
+    let mut board = Board {
+        led,
+        #[cfg(feature = "gpio")]
+        pe8,
+        #[cfg(feature = "gpio")]
+        button,
+        #[cfg(feature = "gpio")]
+        pa1,
+        #[cfg(feature = "gpio")]
+        pupdr: gpioa.pupdr,
+        #[cfg(feature = "i2c")]
+        i2c,
+        #[cfg(feature = "spi")]
+        spi,
+        #[cfg(feature = "usart2")]
+        modem,
+        #[cfg(feature = "iwdg")]
+        feeding: false,
+    };
+    let mut selftest = Selftest {
+        results: [None; SUITES.len()],
+        pending: None,
+        #[cfg(feature = "framed")]
+        frames: false,
+    };
+
+    // Start taking commands; bytes typed from now on reach the shell
+    shell::init_rx();
+    let mut shell = Shell::new(COMMANDS);
+
+    let (selection, source) = match boot_selection() {
+        Some(selection) => (selection, "BKP0R"),
+        None => (BUILT, "built in"),
+    };
+    uart_write_str(&mut serial, "Suites:");
+    write_names(&mut serial, selection);
+    uart_write_str(&mut serial, " (");
+    uart_write_str(&mut serial, source);
+    uart_write_str(&mut serial, ")\n");
+
+    // Give a key press the chance to skip the run
+    let mut key = None;
+    if selection != 0 {
+        uart_write_str(&mut serial, "Running in 1 s, press any key for the shell\n");
+        let deadline = Deadline::after_ms(AUTORUN_DELAY_MS);
+        while key.is_none() && !deadline.expired() {
+            key = shell::read_byte();
+        }
+    }
+    match key {
+        Some(byte) => {
+            uart_write_str(&mut serial, "Autorun skipped\n");
+            shell.start(&mut serial);
+            // The key starts the first command line
+            shell.input(byte, &mut selftest, &mut serial);
+        }
+        None => {
+            if selection != 0 {
+                run_suites(&mut board, &mut selftest, &mut serial, selection);
+            }
+            shell.start(&mut serial);
+        }
+    }
+
+    // Main loop - run commands, and the suites `run` asks for
+    loop {
+        shell.poll(&mut selftest, &mut serial);
+        if let Some(selection) = selftest.pending.take() {
+            run_suites(&mut board, &mut selftest, &mut serial, selection);
+            shell.start(&mut serial);
+        }
+    }
+}
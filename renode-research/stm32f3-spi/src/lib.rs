@@ -0,0 +1,80 @@
+//! STM32F3 SPI Loopback Test in Rust
+//!
+//! This tests SPI1 functionality on the STM32F303:
+//! - SPI1 configured with MOSI connected to MISO (loopback)
+//! - Sends test bytes and verifies they are received correctly
+//! - Accesses the bus through the embedded-hal 1.0 `SpiBus` trait
+//! - Reports results via USART1
+
+#![no_std]
+
+use core::fmt::Write;
+use embedded_hal::spi::SpiBus;
+use stm32f3_common::stack;
+use stm32f3_common::{uart_write_hex, uart_write_str};
+use stm32f3xx_hal::{
+    gpio::{Output, PushPull, PE9},
+    prelude::*,
+};
+
+/// Run the loopback tests on `spi`, reporting on `serial`; `true` if all
+/// passed
+///
+/// LD3 (`led`, PE9) is left on when they pass.
+pub fn run<W, S>(serial: &mut W, led: &mut PE9<Output<PushPull>>, spi: &mut S) -> bool
+where
+    W: Write,
+    S: SpiBus,
+{
+    // Test data to send
+    let test_data: [u8; 5] = [0xAA, 0x55, 0x12, 0x34, 0xFF];
+    let mut pass_count = 0u8;
+    let mut fail_count = 0u8;
+
+    uart_write_str(serial, "Starting loopback test...\n");
+
+    // Perform loopback test for each byte
+    for &tx_byte in test_data.iter() {
+        // Transfer byte (send and receive simultaneously)
+        let mut buf = [tx_byte];
+        let rx_byte = match spi.transfer_in_place(&mut buf) {
+            Ok(()) => buf[0],
+            Err(_) => 0x00,
+        };
+
+        // Report result
+        uart_write_str(serial, "TX: 0x");
+        uart_write_hex(serial, tx_byte);
+        uart_write_str(serial, " RX: 0x");
+        uart_write_hex(serial, rx_byte);
+
+        if tx_byte == rx_byte {
+            uart_write_str(serial, " PASS\n");
+            pass_count += 1;
+            led.set_high().ok();
+        } else {
+            uart_write_str(serial, " FAIL\n");
+            fail_count += 1;
+            led.set_low().ok();
+        }
+    }
+
+    // Summary
+    uart_write_str(serial, "\n=== Test Summary ===\n");
+    uart_write_str(serial, "Passed: ");
+    uart_write_hex(serial, pass_count);
+    uart_write_str(serial, "\nFailed: ");
+    uart_write_hex(serial, fail_count);
+    uart_write_str(serial, "\n");
+
+    stack::uart_write_stack_usage(serial);
+    if fail_count == 0 {
+        uart_write_str(serial, "SPI TEST PASSED\n");
+        led.set_high().ok();
+    } else {
+        uart_write_str(serial, "SPI TEST FAILED\n");
+        led.set_low().ok();
+    }
+
+    fail_count == 0
+}
@@ -1,9 +1,4 @@
 //! STM32F3 SPI Loopback Test in Rust
-//!
-//! This tests SPI1 functionality on the STM32F303:
-//! - SPI1 configured with MOSI connected to MISO (loopback)
-//! - Sends test bytes and verifies they are received correctly
-//! - Reports results via USART1
 
 #![no_std]
 #![no_main]
@@ -11,7 +6,9 @@
 use panic_halt as _;
 
 use cortex_m_rt::entry;
-use stm32f3_common::{uart_write_hex, uart_write_str};
+use stm32f3_common::image::uart_write_build_info;
+use stm32f3_common::stack;
+use stm32f3_common::{compat::SpiCompat, uart_write_str};
 use stm32f3xx_hal::{
     pac,
     prelude::*,
@@ -19,8 +16,12 @@ use stm32f3xx_hal::{
     spi::{config::Config as SpiConfig, Spi},
 };
 
+stm32f3_common::image_header!();
+
 #[entry]
 fn main() -> ! {
+    stack::paint();
+
     // Take ownership of the device peripherals
     let dp = pac::Peripherals::take().unwrap();
 
@@ -59,6 +60,7 @@ fn main() -> ! {
     );
 
     uart_write_str(&mut serial, "SPI1 Loopback Test\n");
+    uart_write_build_info(&mut serial);
 
     // Configure SPI1 pins (Alternate Function 5)
     // PA5 = SCK, PA6 = MISO, PA7 = MOSI
@@ -78,63 +80,18 @@ fn main() -> ! {
     // Configure SPI1 with default config (Mode 0, 1MHz)
     let spi_config = SpiConfig::default().frequency(1.MHz());
 
-    let mut spi = Spi::new(
+    // Wrap for the embedded-hal 1.0 traits
+    let mut spi = SpiCompat::new(Spi::new(
         dp.SPI1,
         (sck, miso, mosi),
         spi_config,
         clocks,
         &mut rcc.apb2,
-    );
+    ));
 
     uart_write_str(&mut serial, "SPI1 initialized\n");
 
-    // Test data to send
-    let test_data: [u8; 5] = [0xAA, 0x55, 0x12, 0x34, 0xFF];
-    let mut pass_count = 0u8;
-    let mut fail_count = 0u8;
-
-    uart_write_str(&mut serial, "Starting loopback test...\n");
-
-    // Perform loopback test for each byte
-    for &tx_byte in test_data.iter() {
-        // Transfer byte (send and receive simultaneously)
-        let rx_byte = match spi.transfer(&mut [tx_byte]) {
-            Ok(received) => received[0],
-            Err(_) => 0x00,
-        };
-
-        // Report result
-        uart_write_str(&mut serial, "TX: 0x");
-        uart_write_hex(&mut serial, tx_byte);
-        uart_write_str(&mut serial, " RX: 0x");
-        uart_write_hex(&mut serial, rx_byte);
-
-        if tx_byte == rx_byte {
-            uart_write_str(&mut serial, " PASS\n");
-            pass_count += 1;
-            led.set_high().ok();
-        } else {
-            uart_write_str(&mut serial, " FAIL\n");
-            fail_count += 1;
-            led.set_low().ok();
-        }
-    }
-
-    // Summary
-    uart_write_str(&mut serial, "\n=== Test Summary ===\n");
-    uart_write_str(&mut serial, "Passed: ");
-    uart_write_hex(&mut serial, pass_count);
-    uart_write_str(&mut serial, "\nFailed: ");
-    uart_write_hex(&mut serial, fail_count);
-    uart_write_str(&mut serial, "\n");
-
-    if fail_count == 0 {
-        uart_write_str(&mut serial, "SPI TEST PASSED\n");
-        led.set_high().ok();
-    } else {
-        uart_write_str(&mut serial, "SPI TEST FAILED\n");
-        led.set_low().ok();
-    }
+    stm32f3_spi::run(&mut serial, &mut led, &mut spi);
 
     // Halt
     loop {
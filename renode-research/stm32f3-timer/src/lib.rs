@@ -0,0 +1,309 @@
+//! STM32F3 Timer Peripheral Test in Rust
+//!
+//! This tests Timer functionality on the STM32F303:
+//! - Timer2 configured as a basic counter
+//! - Timer3 configured with periodic updates
+//! - Verifies counter increments and timing
+//! - Software timers driven by the TIM7 update interrupt
+//! - Reports results via USART1
+
+#![no_std]
+
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU32, Ordering};
+use stm32f3_common::soft_timer::{self, Deadline};
+use stm32f3_common::stack;
+use stm32f3_common::{constants, debug, uart_write_hex, uart_write_str};
+use stm32f3xx_hal::{
+    gpio::{Output, PushPull, PE9},
+    pac,
+    prelude::*,
+};
+
+// Number of times the periodic software timer callback has run
+static PERIODIC_FIRES: AtomicU32 = AtomicU32::new(0);
+
+/// Periodic software timer callback (runs in interrupt context)
+fn on_periodic() {
+    PERIODIC_FIRES.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Run the timer tests, reporting on `serial`; `true` if all passed
+///
+/// The software timer service must already be running on TIM7
+/// ([`soft_timer::init`]). LD3 (`led`, PE9) is left on when the tests pass.
+pub fn run<W: Write>(serial: &mut W, led: &mut PE9<Output<PushPull>>) -> bool {
+    let mut pass_count = 0u8;
+    let mut fail_count = 0u8;
+
+    // =========================================
+    // Test 1: Timer2 as countdown timer (delay)
+    // =========================================
+    uart_write_str(serial, "\nTest 1: Timer2 Countdown\n");
+
+    // Enable TIM2 clock and configure directly for better Renode compatibility
+    // The HAL's wait() polls UIF flag which Renode may not set properly
+    unsafe {
+        let rcc_ptr = &*pac::RCC::ptr();
+        rcc_ptr.apb1enr.modify(|_, w| w.tim2en().enabled());
+    }
+
+    let tim2 = unsafe { &*pac::TIM2::ptr() };
+
+    // Configure for 100ms timeout at 72MHz
+    // Prescaler: 7199 -> 72MHz / 7200 = 10kHz (0.1ms per tick)
+    // ARR: 999 -> 1000 ticks = 100ms
+    tim2.psc.write(|w| w.psc().bits(7199));
+    tim2.arr.write(|w| w.bits(999));
+    tim2.cnt.write(|w| w.bits(0));
+
+    // Generate update event to load prescaler, then clear the flag
+    tim2.egr.write(|w| w.ug().update());
+    tim2.sr.write(|w| w.uif().clear_bit());
+
+    // Enable counter
+    tim2.cr1.write(|w| w.cen().enabled());
+    uart_write_str(serial, "Timer2 started (100ms)\n");
+
+    // Wait for timer to reach ARR value using wrap-around detection
+    // The counter resets to 0 when it reaches ARR, so detect the wrap
+    let arr_val = tim2.arr.read().bits();
+    let mut last_cnt: u32 = 0;
+    let mut timeout_count = 0u32;
+    let mut expired = false;
+
+    loop {
+        let cnt = tim2.cnt.read().bits();
+
+        // Detect wrap-around: counter was high and is now low
+        if cnt < last_cnt && last_cnt > (arr_val / 2) {
+            expired = true;
+            break;
+        }
+
+        // Also check UIF flag as backup
+        if tim2.sr.read().uif().bit_is_set() {
+            expired = true;
+            break;
+        }
+
+        last_cnt = cnt;
+        timeout_count += 1;
+        if timeout_count > constants::TIMER_TIMEOUT {
+            break; // Safety timeout
+        }
+    }
+
+    // Stop timer
+    tim2.cr1.write(|w| w.cen().disabled());
+
+    if expired {
+        uart_write_str(serial, "Timer2 expired: PASS\n");
+        pass_count += 1;
+        led.set_high().ok();
+    } else {
+        uart_write_str(serial, "Timer2 timeout: FAIL\n");
+        fail_count += 1;
+    }
+
+    // =========================================
+    // Test 2: Timer3 periodic mode
+    // =========================================
+    uart_write_str(serial, "\nTest 2: Timer3 Periodic\n");
+
+    // Enable TIM3 clock and configure directly for better Renode compatibility
+    unsafe {
+        let rcc_ptr = &*pac::RCC::ptr();
+        rcc_ptr.apb1enr.modify(|_, w| w.tim3en().enabled());
+    }
+
+    let tim3 = unsafe { &*pac::TIM3::ptr() };
+
+    // Configure for 50ms period at 72MHz
+    // Prescaler: 7199 -> 72MHz / 7200 = 10kHz (0.1ms per tick)
+    // ARR: 499 -> 500 ticks = 50ms
+    tim3.psc.write(|w| w.psc().bits(7199));
+    tim3.arr.write(|w| unsafe { w.bits(499) });
+    tim3.cnt.write(|w| unsafe { w.bits(0) });
+
+    // Generate update event to load prescaler, then clear the flag
+    tim3.egr.write(|w| w.ug().update());
+    tim3.sr.write(|w| w.uif().clear_bit());
+
+    // Enable counter in auto-reload mode
+    tim3.cr1.write(|w| w.cen().enabled());
+    uart_write_str(serial, "Timer3 started (50ms periodic)\n");
+
+    // Count multiple periods by detecting counter wrap
+    let mut period_count = 0u8;
+    let arr_val = tim3.arr.read().bits() as u16;
+
+    for _ in 0..3 {
+        // Wait for counter to reach near max
+        let mut last_cnt: u16 = 0;
+        let mut timeout = 0u32;
+        loop {
+            let cnt = tim3.cnt.read().bits() as u16;
+            // Detect wrap-around (counter reset to 0 after reaching ARR)
+            if cnt < last_cnt && last_cnt > (arr_val / 2) {
+                break;
+            }
+            // Or counter reached ARR
+            if cnt >= arr_val {
+                // Wait for it to wrap
+                while tim3.cnt.read().bits() as u16 >= arr_val / 2 {
+                    timeout += 1;
+                    if timeout > 10_000_000 {
+                        break;
+                    }
+                }
+                break;
+            }
+            last_cnt = cnt;
+            timeout += 1;
+            if timeout > 10_000_000 {
+                break;
+            }
+        }
+        period_count += 1;
+        uart_write_str(serial, "Period ");
+        uart_write_hex(serial, period_count);
+        uart_write_str(serial, " complete\n");
+    }
+
+    // Stop timer
+    tim3.cr1.write(|w| w.cen().disabled());
+
+    if period_count == 3 {
+        uart_write_str(serial, "Timer3 periodic: PASS\n");
+        pass_count += 1;
+    } else {
+        uart_write_str(serial, "Timer3 periodic: FAIL\n");
+        fail_count += 1;
+    }
+
+    // =========================================
+    // Test 3: Timer4 direct register access
+    // =========================================
+    uart_write_str(serial, "\nTest 3: Timer4 Counter\n");
+
+    // Enable TIM4 clock manually
+    unsafe {
+        let rcc_ptr = &*pac::RCC::ptr();
+        rcc_ptr.apb1enr.modify(|_, w| w.tim4en().enabled());
+    }
+
+    // Configure TIM4 directly
+    let tim4 = unsafe { &*pac::TIM4::ptr() };
+
+    // Set prescaler and auto-reload
+    tim4.psc.write(|w| w.psc().bits(7999)); // 72MHz / 8000 = 9kHz
+    tim4.arr.write(|w| unsafe { w.bits(0xFFFF) }); // Max count
+    tim4.cnt.write(|w| unsafe { w.bits(0) }); // Clear counter
+
+    // Enable counter
+    tim4.cr1.write(|w| w.cen().enabled());
+
+    // Read counter a few times
+    let cnt1 = tim4.cnt.read().bits();
+    for _ in 0..10000 {
+        cortex_m::asm::nop();
+    }
+    let cnt2 = tim4.cnt.read().bits();
+
+    debug!(serial, "CNT1: 0x{:08X}", cnt1);
+    debug!(serial, "CNT2: 0x{:08X}", cnt2);
+
+    if cnt2 > cnt1 {
+        uart_write_str(serial, "Counter incrementing: PASS\n");
+        pass_count += 1;
+    } else {
+        uart_write_str(serial, "Counter not incrementing: FAIL\n");
+        fail_count += 1;
+    }
+
+    // Stop timer
+    tim4.cr1.write(|w| w.cen().disabled());
+
+    // =========================================
+    // Test 4: Software timers on TIM7
+    // =========================================
+    uart_write_str(serial, "\nTest 4: Software Timers\n");
+
+    // Count from zero in case the suite runs again
+    PERIODIC_FIRES.store(0, Ordering::SeqCst);
+
+    // 250ms one-shot polled from the main loop, 50ms periodic with callback
+    let once = soft_timer::start_once(250, None).unwrap();
+    let periodic = soft_timer::start_periodic(50, Some(on_periodic)).unwrap();
+    uart_write_str(serial, "One-shot 250ms, periodic 50ms started\n");
+
+    let deadline = Deadline::after_ms(1000);
+    let mut once_fired = false;
+    while !deadline.expired() {
+        if soft_timer::take_expired(once).unwrap() {
+            once_fired = true;
+            break;
+        }
+        // Blink the LED on each periodic expiry
+        if soft_timer::take_expired(periodic).unwrap() {
+            led.toggle().ok();
+        }
+    }
+
+    let fires = PERIODIC_FIRES.load(Ordering::SeqCst);
+    uart_write_str(serial, "Periodic fires: ");
+    uart_write_hex(serial, fires as u8);
+    uart_write_str(serial, "\n");
+
+    if once_fired && fires >= 4 {
+        uart_write_str(serial, "One-shot and periodic: PASS\n");
+        pass_count += 1;
+    } else {
+        uart_write_str(serial, "One-shot and periodic: FAIL\n");
+        fail_count += 1;
+    }
+
+    // Cancelled timers must stop firing, restarted ones must fire again
+    soft_timer::cancel(periodic).unwrap();
+    let fires_at_cancel = PERIODIC_FIRES.load(Ordering::SeqCst);
+    soft_timer::restart(once).unwrap();
+
+    let deadline = Deadline::after_ms(500);
+    let mut restart_fired = false;
+    while !deadline.expired() {
+        if soft_timer::take_expired(once).unwrap() {
+            restart_fired = true;
+        }
+    }
+    soft_timer::cancel(once).unwrap();
+
+    if restart_fired && PERIODIC_FIRES.load(Ordering::SeqCst) == fires_at_cancel {
+        uart_write_str(serial, "Cancel and restart: PASS\n");
+        pass_count += 1;
+    } else {
+        uart_write_str(serial, "Cancel and restart: FAIL\n");
+        fail_count += 1;
+    }
+
+    // =========================================
+    // Summary
+    // =========================================
+    uart_write_str(serial, "\n=== Test Summary ===\n");
+    uart_write_str(serial, "Passed: ");
+    uart_write_hex(serial, pass_count);
+    uart_write_str(serial, "\nFailed: ");
+    uart_write_hex(serial, fail_count);
+    uart_write_str(serial, "\n");
+
+    stack::uart_write_stack_usage(serial);
+    if fail_count == 0 {
+        uart_write_str(serial, "TIMER TEST PASSED\n");
+        led.set_high().ok();
+    } else {
+        uart_write_str(serial, "TIMER TEST FAILED\n");
+        led.set_low().ok();
+    }
+
+    fail_count == 0
+}
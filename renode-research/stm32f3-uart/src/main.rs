@@ -5,20 +5,78 @@
 //! - USART1 output at 115200 baud (PA9 TX, PA10 RX)
 //! - GPIO button input on PA0 (User Button)
 //! - LED on PE9
+//! - A command shell on USART1 RX (`help`, `led 3 on`, `peek`, `poke`, `reset`)
+//! - A HardFault report on USART1 instead of a silent halt
 
 #![no_std]
 #![no_main]
 
 use panic_halt as _;
 
-use cortex_m_rt::entry;
+use core::fmt::Write;
+
+use cortex_m_rt::{entry, exception, ExceptionFrame};
+use stm32f3_common::image::uart_write_build_info;
+use stm32f3_common::shell::{self, Args, Command, CommandError, Shell};
 use stm32f3_common::uart_write_str;
 use stm32f3xx_hal::{
-    pac,
+    gpio::{Gpioe, Output, Pin, PushPull, Ux},
+    pac::{self, interrupt},
     prelude::*,
     serial::{config::Config, Serial},
 };
 
+stm32f3_common::image_header!();
+
+/// A Discovery user LED on port E
+type Led = Pin<Gpioe, Ux, Output<PushPull>>;
+
+/// The eight user LEDs, indexed by board label: `leds[0]` is LD3 (PE9)
+struct Leds([Led; 8]);
+
+/// Shell commands on top of the built-in `help`, `peek`, `poke` and `reset`
+const COMMANDS: &[Command<Leds>] = &[Command {
+    name: "led",
+    usage: "<3-10> on|off|toggle",
+    help: "Switch a Discovery user LED (LD3 is PE9)",
+    min_args: 2,
+    max_args: 2,
+    handler: led,
+}];
+
+/// USART1 interrupt handler - collects received bytes for the shell
+#[interrupt]
+fn USART1_EXTI25() {
+    shell::on_usart1_rx();
+}
+
+/// HardFault handler - reports the fault on USART1 instead of halting silently
+#[exception]
+unsafe fn HardFault(frame: &ExceptionFrame) -> ! {
+    stm32f3_common::fault::report(frame)
+}
+
+/// `led <3-10> on|off|toggle`
+fn led(leds: &mut Leds, args: &Args, out: &mut dyn Write) -> Result<(), CommandError> {
+    let number = args.number(0)?;
+    if !(3..=10).contains(&number) {
+        return Err(CommandError::Invalid(0));
+    }
+    let led = &mut leds.0[number as usize - 3];
+    match args.choice(1, &["on", "off", "toggle"])? {
+        0 => led.set_high().ok(),
+        1 => led.set_low().ok(),
+        _ => led.toggle().ok(),
+    };
+    let state = if led.is_set_high().unwrap_or(false) {
+        "on"
+    } else {
+        "off"
+    };
+    let _ = writeln!(out, "LD{}: {}", number, state);
+    Ok(())
+}
+
 #[entry]
 fn main() -> ! {
     // Take ownership of the device peripherals
@@ -33,10 +91,41 @@ fn main() -> ! {
     let mut gpioa = dp.GPIOA.split(&mut rcc.ahb);
     let mut gpioe = dp.GPIOE.split(&mut rcc.ahb);
 
-    // Configure LED on PE9 as output
-    let mut _led = gpioe
-        .pe9
-        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+    // Configure the user LEDs LD3-LD10 (PE8-PE15) as outputs
+    let mut leds = Leds([
+        gpioe
+            .pe9
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe8
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe10
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe15
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe11
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe14
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe12
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+        gpioe
+            .pe13
+            .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper)
+            .downgrade(),
+    ]);
 
     // Configure User Button on PA0 as input (active high on STM32F3 Discovery)
     let button = gpioa
@@ -65,15 +154,23 @@ fn main() -> ! {
 
     // Print hello world
     uart_write_str(&mut serial, "hello world!\n");
+    uart_write_build_info(&mut serial);
 
     // Turn on LED
-    _led.set_high().ok();
+    leds.0[0].set_high().ok();
+
+    // Start taking commands
+    shell::init_rx();
+    let mut shell = Shell::new(COMMANDS);
+    shell.start(&mut serial);
 
     // Button state tracking
     let mut button_is_pressed = false;
 
-    // Main loop - detect button press/release
+    // Main loop - run commands and detect button press/release
     loop {
+        shell.poll(&mut leds, &mut serial);
+
         let button_state = button.is_high().unwrap_or(false);
 
         if !button_is_pressed && button_state {
@@ -84,7 +181,7 @@ fn main() -> ! {
             uart_write_str(&mut serial, "button pressed\n");
             button_is_pressed = false;
             // Toggle LED
-            _led.toggle().ok();
+            leds.0[0].toggle().ok();
         }
     }
 }
@@ -0,0 +1,429 @@
+//! STM32F3 USART2 Peer Test
+//!
+//! This tests USART2 against a device simulated on the host:
+//! - USART2 (PA2 TX, PA3 RX) is connected to a Renode socket terminal that
+//!   `host/uart-peer modem` plays an AT-command modem on
+//! - Waits for the modem's `RDY` banner, then sends AT commands and checks
+//!   the replies, resending a command whose reply does not arrive in time
+//! - Starts the modem's GNSS stream and parses NMEA GGA/RMC sentences,
+//!   skipping ones with a bad checksum
+//! - Reports results via USART1
+
+#![no_std]
+
+use core::fmt::Write;
+use stm32f3_common::soft_timer::Deadline;
+use stm32f3_common::stack;
+use stm32f3_common::{uart_write_dec, uart_write_hex, uart_write_str};
+use stm32f3xx_hal::{
+    gpio::{Output, PushPull, PE9},
+    hal::serial,
+    prelude::*,
+};
+
+/// How long to wait for the modem's `RDY` banner
+const READY_TIMEOUT_MS: u32 = 10_000;
+
+/// How long to wait for `OK`/`ERROR` before resending a command
+const REPLY_TIMEOUT_MS: u32 = 2_000;
+
+/// Sends per command, including the first
+const ATTEMPTS: u32 = 3;
+
+/// Valid NMEA sentences to collect from the GNSS stream
+const NMEA_SENTENCES: u32 = 6;
+
+/// How long the GNSS stream may take to deliver them
+const NMEA_TIMEOUT_MS: u32 = 20_000;
+
+/// Longest line kept; NMEA sentences are at most 82 characters
+const LINE_LEN: usize = 96;
+
+/// Final response to an AT command
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Reply {
+    Ok,
+    Error,
+    Timeout,
+}
+
+impl Reply {
+    fn name(self) -> &'static str {
+        match self {
+            Reply::Ok => "OK",
+            Reply::Error => "ERROR",
+            Reply::Timeout => "TIMEOUT",
+        }
+    }
+}
+
+/// Line-oriented AT-command client on a serial port
+pub struct Modem<S> {
+    serial: S,
+    line: [u8; LINE_LEN],
+    len: usize,
+    /// Information line of the last command (e.g. `+CSQ: 21,0`)
+    info: [u8; LINE_LEN],
+    info_len: usize,
+    /// Commands resent after a timeout
+    retries: u32,
+    /// The `RDY` banner has been seen; it is only sent once
+    ready: bool,
+}
+
+impl<S> Modem<S>
+where
+    S: serial::Read<u8> + serial::Write<u8>,
+{
+    pub fn new(serial: S) -> Self {
+        Self {
+            serial,
+            line: [0; LINE_LEN],
+            len: 0,
+            info: [0; LINE_LEN],
+            info_len: 0,
+            retries: 0,
+            ready: false,
+        }
+    }
+
+    /// The last line read, without its terminator
+    fn line(&self) -> &[u8] {
+        &self.line[..self.len]
+    }
+
+    fn info(&self) -> &[u8] {
+        &self.info[..self.info_len]
+    }
+
+    /// Read the next non-empty line; `false` if the deadline passes first
+    ///
+    /// Lines end at `\r` or `\n`; characters beyond [`LINE_LEN`] are
+    /// dropped.
+    fn read_line(&mut self, deadline: &Deadline) -> bool {
+        self.len = 0;
+        loop {
+            match self.serial.read() {
+                Ok(b'\r') | Ok(b'\n') => {
+                    if self.len > 0 {
+                        return true;
+                    }
+                }
+                Ok(byte) => {
+                    if self.len < LINE_LEN {
+                        self.line[self.len] = byte;
+                        self.len += 1;
+                    }
+                }
+                // Nothing received yet, or a byte lost to an overrun or
+                // framing error; the line's own checks catch the latter
+                Err(_) => {
+                    if deadline.expired() {
+                        return false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wait for a line equal to `expected`
+    fn wait_for(&mut self, expected: &[u8], timeout_ms: u32) -> bool {
+        let deadline = Deadline::after_ms(timeout_ms);
+        while self.read_line(&deadline) {
+            if self.line() == expected {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Send `command` terminated by `\r`
+    fn send(&mut self, command: &str) {
+        for &byte in command.as_bytes().iter().chain(b"\r") {
+            while self.serial.write(byte).is_err() {}
+        }
+    }
+
+    /// Send `command` until it gets `OK` or `ERROR`, at most [`ATTEMPTS`]
+    /// times
+    ///
+    /// The echo of the command and NMEA sentences in between are skipped;
+    /// any other line is kept as the command's information line.
+    fn command(&mut self, command: &str) -> Reply {
+        self.info_len = 0;
+        for attempt in 0..ATTEMPTS {
+            if attempt > 0 {
+                self.retries += 1;
+            }
+            self.send(command);
+            let deadline = Deadline::after_ms(REPLY_TIMEOUT_MS);
+            while self.read_line(&deadline) {
+                match self.line() {
+                    b"OK" => return Reply::Ok,
+                    b"ERROR" => return Reply::Error,
+                    line if line == command.as_bytes() || line.starts_with(b"$") => {}
+                    _ => {
+                        self.info[..self.len].copy_from_slice(&self.line[..self.len]);
+                        self.info_len = self.len;
+                    }
+                }
+            }
+        }
+        Reply::Timeout
+    }
+}
+
+/// Pass/fail counts for the summary
+#[derive(Default)]
+struct Tally {
+    passed: u8,
+    failed: u8,
+}
+
+impl Tally {
+    /// Finish a result line with ` PASS` or ` FAIL`
+    fn record<W: Write>(&mut self, log: &mut W, passed: bool) {
+        if passed {
+            uart_write_str(log, " PASS\n");
+            self.passed += 1;
+        } else {
+            uart_write_str(log, " FAIL\n");
+            self.failed += 1;
+        }
+    }
+}
+
+/// Run an AT command, logging each resend
+fn at<S, W>(modem: &mut Modem<S>, log: &mut W, command: &str) -> Reply
+where
+    S: serial::Read<u8> + serial::Write<u8>,
+    W: Write,
+{
+    let before = modem.retries;
+    let reply = modem.command(command);
+    for _ in before..modem.retries {
+        uart_write_str(log, "Retried ");
+        uart_write_str(log, command);
+        uart_write_str(log, " after timeout\n");
+    }
+    reply
+}
+
+/// Run an AT command and check its final response
+fn check_reply<S, W>(
+    modem: &mut Modem<S>,
+    log: &mut W,
+    tally: &mut Tally,
+    label: &str,
+    command: &str,
+    expected: Reply,
+) -> Reply
+where
+    S: serial::Read<u8> + serial::Write<u8>,
+    W: Write,
+{
+    let reply = at(modem, log, command);
+    uart_write_str(log, label);
+    uart_write_str(log, ": ");
+    uart_write_str(log, reply.name());
+    tally.record(log, reply == expected);
+    reply
+}
+
+/// Body of a `$<body>*<checksum>` sentence if the checksum matches
+fn nmea_body(line: &[u8]) -> Option<&[u8]> {
+    let star = line.iter().rposition(|&b| b == b'*')?;
+    let body = line.get(1..star)?;
+    let checksum = line.get(star + 1..)?;
+    if checksum.len() != 2 {
+        return None;
+    }
+    let expected = hex_digit(checksum[0])? << 4 | hex_digit(checksum[1])?;
+    let actual = body.iter().fold(0u8, |acc, &b| acc ^ b);
+    (actual == expected).then_some(body)
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        _ => None,
+    }
+}
+
+/// Comma-separated field `index` of a sentence body (0 is the type)
+fn field(body: &[u8], index: usize) -> &[u8] {
+    body.split(|&b| b == b',').nth(index).unwrap_or(b"")
+}
+
+/// Parse an unsigned decimal field
+fn parse_dec(digits: &[u8]) -> Option<u32> {
+    if digits.is_empty() {
+        return None;
+    }
+    digits.iter().try_fold(0u32, |acc, &b| {
+        b.is_ascii_digit()
+            .then(|| acc.wrapping_mul(10) + u32::from(b - b'0'))
+    })
+}
+
+fn write_bytes<W: Write>(log: &mut W, bytes: &[u8]) {
+    uart_write_str(log, core::str::from_utf8(bytes).unwrap_or("?"));
+}
+
+/// Log a GGA fix as `Fix <time>: <lat><N/S> <lon><E/W> sats <n>`; `false`
+/// if the sentence reports no fix
+fn log_fix<W: Write>(log: &mut W, body: &[u8]) -> bool {
+    let quality = parse_dec(field(body, 6)).unwrap_or(0);
+    let Some(satellites) = parse_dec(field(body, 7)) else {
+        return false;
+    };
+    if quality == 0 {
+        return false;
+    }
+    uart_write_str(log, "Fix ");
+    write_bytes(log, field(body, 1));
+    uart_write_str(log, ": ");
+    write_bytes(log, field(body, 2));
+    write_bytes(log, field(body, 3));
+    uart_write_str(log, " ");
+    write_bytes(log, field(body, 4));
+    write_bytes(log, field(body, 5));
+    uart_write_str(log, " sats ");
+    uart_write_dec(log, satellites);
+    uart_write_str(log, "\n");
+    true
+}
+
+/// Run the USART2 peer tests against `modem`, reporting on `serial`;
+/// `true` if all passed
+///
+/// `soft_timer` must be running for the reply timeouts. LD3 (`led`, PE9)
+/// is left on when the tests pass.
+pub fn run<W, S>(serial: &mut W, led: &mut PE9<Output<PushPull>>, modem: &mut Modem<S>) -> bool
+where
+    W: Write,
+    S: serial::Read<u8> + serial::Write<u8>,
+{
+    let mut tally = Tally::default();
+    modem.retries = 0;
+
+    // Test 1: the modem announces itself
+    uart_write_str(serial, "Waiting for modem...\n");
+    let ready = modem.ready || modem.wait_for(b"RDY", READY_TIMEOUT_MS);
+    modem.ready = ready;
+    uart_write_str(
+        serial,
+        if ready {
+            "Modem ready"
+        } else {
+            "Modem not ready"
+        },
+    );
+    tally.record(serial, ready);
+
+    if ready {
+        // Test 2-3: echo off and a plain AT
+        check_reply(modem, serial, &mut tally, "ATE0", "ATE0", Reply::Ok);
+        check_reply(modem, serial, &mut tally, "AT", "AT", Reply::Ok);
+
+        // Test 4: signal quality, `+CSQ: <rssi>,<ber>` with rssi 0-31
+        let reply = at(modem, serial, "AT+CSQ");
+        let rssi = modem
+            .info()
+            .strip_prefix(b"+CSQ: ")
+            .and_then(|rest| parse_dec(rest.split(|&b| b == b',').next()?))
+            .filter(|&rssi| rssi <= 31);
+        uart_write_str(serial, "Signal: ");
+        match rssi {
+            Some(rssi) => uart_write_dec(serial, rssi),
+            None => uart_write_str(serial, "?"),
+        }
+        tally.record(serial, reply == Reply::Ok && rssi.is_some());
+
+        // Test 5: identification; the text itself is informational
+        let reply = at(modem, serial, "ATI");
+        uart_write_str(serial, "Modem: ");
+        write_bytes(serial, modem.info());
+        tally.record(serial, reply == Reply::Ok);
+
+        // Test 6: an unsupported command is rejected
+        check_reply(
+            modem,
+            serial,
+            &mut tally,
+            "Unknown command",
+            "AT+NOPE",
+            Reply::Error,
+        );
+
+        // Test 7-8: GNSS stream with checksummed NMEA sentences
+        let stream = check_reply(
+            modem,
+            serial,
+            &mut tally,
+            "GNSS stream",
+            "AT+CGNSTST=1",
+            Reply::Ok,
+        );
+        let (mut valid, mut bad, mut fixes, mut rmc) = (0u32, 0u32, 0u32, 0u32);
+        if stream == Reply::Ok {
+            let deadline = Deadline::after_ms(NMEA_TIMEOUT_MS);
+            while valid < NMEA_SENTENCES && modem.read_line(&deadline) {
+                if !modem.line().starts_with(b"$") {
+                    continue;
+                }
+                let Some(body) = nmea_body(modem.line()) else {
+                    uart_write_str(serial, "Checksum mismatch\n");
+                    bad += 1;
+                    continue;
+                };
+                valid += 1;
+                match field(body, 0) {
+                    b"GPGGA" if log_fix(serial, body) => fixes += 1,
+                    b"GPRMC" if field(body, 2) == b"A" => rmc += 1,
+                    _ => {}
+                }
+            }
+        }
+        uart_write_str(serial, "Sentences: ");
+        uart_write_dec(serial, valid);
+        uart_write_str(serial, " valid, ");
+        uart_write_dec(serial, bad);
+        uart_write_str(serial, " bad\n");
+        uart_write_str(serial, "NMEA:");
+        tally.record(serial, valid >= NMEA_SENTENCES && fixes > 0 && rmc > 0);
+
+        // Test 9: stop the stream; sentences still in flight are skipped
+        check_reply(
+            modem,
+            serial,
+            &mut tally,
+            "GNSS stop",
+            "AT+CGNSTST=0",
+            Reply::Ok,
+        );
+    }
+
+    // Summary
+    uart_write_str(serial, "\n=== Test Summary ===\n");
+    uart_write_str(serial, "Passed: ");
+    uart_write_hex(serial, tally.passed);
+    uart_write_str(serial, "\nFailed: ");
+    uart_write_hex(serial, tally.failed);
+    uart_write_str(serial, "\nRetries: ");
+    uart_write_dec(serial, modem.retries);
+    uart_write_str(serial, "\n");
+
+    stack::uart_write_stack_usage(serial);
+    if tally.failed == 0 {
+        uart_write_str(serial, "USART2 TEST PASSED\n");
+        led.set_high().ok();
+    } else {
+        uart_write_str(serial, "USART2 TEST FAILED\n");
+        led.set_low().ok();
+    }
+
+    tally.failed == 0
+}
@@ -0,0 +1,106 @@
+//! STM32F3 USART2 Peer Test in Rust
+//!
+//! Sets up the clocks, USART1, USART2 for the peer and the status LED, then
+//! runs the tests in `lib.rs`.
+
+#![no_std]
+#![no_main]
+
+use panic_halt as _;
+
+use cortex_m_rt::entry;
+use stm32f3_common::image::uart_write_build_info;
+use stm32f3_common::soft_timer;
+use stm32f3_common::stack;
+use stm32f3_common::uart_write_str;
+use stm32f3_usart2::Modem;
+use stm32f3xx_hal::{
+    pac::{self, interrupt},
+    prelude::*,
+    serial::{config::Config as UartConfig, Serial},
+};
+
+stm32f3_common::image_header!();
+
+/// TIM7 interrupt handler - drives the software timers used for timeouts
+#[interrupt]
+fn TIM7() {
+    soft_timer::on_tick();
+}
+#[entry]
+fn main() -> ! {
+    stack::paint();
+
+    // Take ownership of the device peripherals
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Set up the system clocks using HSI (8 MHz internal oscillator)
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let clocks = rcc.cfgr.freeze(&mut flash.acr);
+
+    // GPIO ports
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb);
+    let mut gpioe = dp.GPIOE.split(&mut rcc.ahb);
+
+    // Configure LED on PE9 as output (for status indication)
+    let mut led = gpioe
+        .pe9
+        .into_push_pull_output(&mut gpioe.moder, &mut gpioe.otyper);
+
+    // Configure USART1 pins for debug output
+    // PA9 = TX, PA10 = RX (Alternate Function 7)
+    let tx_pin =
+        gpioa
+            .pa9
+            .into_af_push_pull::<7>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let rx_pin =
+        gpioa
+            .pa10
+            .into_af_push_pull::<7>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+
+    // Set up USART1 at 115200 baud
+    let mut serial = Serial::new(
+        dp.USART1,
+        (tx_pin, rx_pin),
+        UartConfig::default().baudrate(115200.Bd()),
+        clocks,
+        &mut rcc.apb2,
+    );
+
+    uart_write_str(&mut serial, "USART2 Peer Test\n");
+    uart_write_build_info(&mut serial);
+
+    // 1 ms tick for the reply timeouts (TIM7 runs at 72 MHz in Renode)
+    soft_timer::init(dp.TIM7, 72_000_000);
+
+    // Configure USART2 pins for the peer
+    // PA2 = TX, PA3 = RX (Alternate Function 7)
+    let peer_tx =
+        gpioa
+            .pa2
+            .into_af_push_pull::<7>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let peer_rx =
+        gpioa
+            .pa3
+            .into_af_push_pull::<7>(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+    // Set up USART2 at 115200 baud
+    let usart2 = Serial::new(
+        dp.USART2,
+        (peer_tx, peer_rx),
+        UartConfig::default().baudrate(115200.Bd()),
+        clocks,
+        &mut rcc.apb1,
+    );
+    let mut modem = Modem::new(usart2);
+
+    uart_write_str(&mut serial, "USART2 initialized\n");
+
+    stm32f3_usart2::run(&mut serial, &mut led, &mut modem);
+
+    // Halt
+    loop {
+        cortex_m::asm::wfi();
+    }
+}